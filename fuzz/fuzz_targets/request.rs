@@ -0,0 +1,36 @@
+//! Feeds arbitrary bytes to the parser of the request roxy reads from stdin.
+
+#![no_main]
+
+use libfuzzer_sys::fuzz_target;
+use roxy::common::{decode_arg, NicOutput, Node, NodeRequest, RoxyRequest, Subsystem};
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(req) = serde_json::from_slice::<RoxyRequest>(data) else {
+        return;
+    };
+    match req {
+        RoxyRequest::Batch(reqs) | RoxyRequest::Transaction { transaction: reqs } => {
+            reqs.iter().for_each(decode);
+        }
+        RoxyRequest::Single(req) => decode(&req),
+    }
+});
+
+// Decodes the arguments as the types the tasks of the kind accept.
+fn decode(req: &NodeRequest) {
+    match req.kind {
+        Node::History(_) => {
+            let _ = decode_arg::<Option<Subsystem>>(&req.arg);
+            let _ = decode_arg::<(Subsystem, i64)>(&req.arg);
+        }
+        Node::Interface(_) => {
+            let _ = decode_arg::<Option<String>>(&req.arg);
+            let _ = decode_arg::<(String, NicOutput)>(&req.arg);
+        }
+        _ => {
+            let _ = decode_arg::<String>(&req.arg);
+            let _ = decode_arg::<Vec<String>>(&req.arg);
+        }
+    }
+}
@@ -0,0 +1,47 @@
+//! Feeds arbitrary bytes to the decoder of task arguments, as every type
+//! accepted by a task. The types are those of the `parse` calls in
+//! `src/root/task.rs`, and must be added here with a new one.
+
+#![no_main]
+
+use std::collections::BTreeMap;
+
+use libfuzzer_sys::fuzz_target;
+use roxy::common::{
+    decode_arg, CaptureSettings, Endpoint, GroupSettings, JobRun, MaintenanceWindow, NetnsCapture,
+    NicOutput, NicOutputV1, PageRequest, ServiceAction, Subsystem, SystemState, SystemStateV1,
+    TunnelConfig, VfConfig, VrrpConfig, WireguardConfig,
+};
+
+fuzz_target!(|data: &[u8]| {
+    let _ = decode_arg::<u64>(data);
+    let _ = decode_arg::<String>(data);
+    let _ = decode_arg::<Option<String>>(data);
+    let _ = decode_arg::<Vec<String>>(data);
+    let _ = decode_arg::<Vec<u8>>(data);
+    let _ = decode_arg::<Option<Subsystem>>(data);
+    let _ = decode_arg::<(Subsystem, i64)>(data);
+    let _ = decode_arg::<(String, u32)>(data);
+    let _ = decode_arg::<(String, Vec<u32>)>(data);
+    let _ = decode_arg::<(String, Vec<String>)>(data);
+    let _ = decode_arg::<(String, BTreeMap<String, String>)>(data);
+    let _ = decode_arg::<(String, CaptureSettings)>(data);
+    let _ = decode_arg::<(String, GroupSettings)>(data);
+    let _ = decode_arg::<(String, MaintenanceWindow, Option<String>)>(data);
+    let _ = decode_arg::<(String, NicOutput)>(data);
+    let _ = decode_arg::<(String, NicOutput, bool)>(data);
+    let _ = decode_arg::<(String, NicOutputV1)>(data);
+    let _ = decode_arg::<(String, NicOutputV1, bool)>(data);
+    let _ = decode_arg::<(String, ServiceAction)>(data);
+    let _ = decode_arg::<(String, TunnelConfig)>(data);
+    let _ = decode_arg::<(String, u32, PageRequest)>(data);
+    let _ = decode_arg::<(String, u32, VfConfig)>(data);
+    let _ = decode_arg::<Option<PageRequest>>(data);
+    let _ = decode_arg::<JobRun>(data);
+    let _ = decode_arg::<NetnsCapture>(data);
+    let _ = decode_arg::<SystemState>(data);
+    let _ = decode_arg::<SystemStateV1>(data);
+    let _ = decode_arg::<Vec<Endpoint>>(data);
+    let _ = decode_arg::<VrrpConfig>(data);
+    let _ = decode_arg::<WireguardConfig>(data);
+});
@@ -0,0 +1,214 @@
+use std::{
+    ffi::OsString,
+    io::Write,
+    path::PathBuf,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Result};
+
+use crate::{
+    common::{NodeRequest, Role, RoxyRequest},
+    TaskResult,
+};
+
+const DEFAULT_ROXY_PROGRAM: &str = "roxy";
+const DEFAULT_ROXY_PATH_ENV: &str = "/usr/local/aice/bin";
+
+/// Client that spawns the roxy executable and delivers requests to it.
+///
+/// By default, `roxy` is searched in `/usr/local/aice/bin`.
+///
+/// # Example
+///
+/// ```ignore
+/// let client = RoxyClient::new()
+///     .program("/opt/aice/bin/roxy")
+///     .env("RUST_LOG", "debug")
+///     .current_dir("/tmp")
+///     .role(Role::Operator);
+/// let hostname = client.run::<String>(req)?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct RoxyClient {
+    program: PathBuf,
+    envs: Vec<(OsString, OsString)>,
+    current_dir: Option<PathBuf>,
+    role: Option<Role>,
+}
+
+impl Default for RoxyClient {
+    fn default() -> Self {
+        RoxyClient {
+            program: PathBuf::from(DEFAULT_ROXY_PROGRAM),
+            envs: vec![(
+                OsString::from("PATH"),
+                OsString::from(DEFAULT_ROXY_PATH_ENV),
+            )],
+            current_dir: None,
+            role: None,
+        }
+    }
+}
+
+impl RoxyClient {
+    #[must_use]
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Sets the path of the roxy executable. A bare name is searched in the
+    /// `PATH` environment variable given to roxy.
+    #[must_use]
+    pub fn program<P: Into<PathBuf>>(mut self, program: P) -> Self {
+        self.program = program.into();
+        self
+    }
+
+    /// Adds or overwrites an environment variable given to roxy.
+    #[must_use]
+    pub fn env<K, V>(mut self, key: K, val: V) -> Self
+    where
+        K: Into<OsString>,
+        V: Into<OsString>,
+    {
+        let key = key.into();
+        let val = val.into();
+        if let Some(item) = self.envs.iter_mut().find(|(k, _)| *k == key) {
+            item.1 = val;
+        } else {
+            self.envs.push((key, val));
+        }
+        self
+    }
+
+    /// Sets the working directory of roxy.
+    #[must_use]
+    pub fn current_dir<P: Into<PathBuf>>(mut self, dir: P) -> Self {
+        self.current_dir = Some(dir.into());
+        self
+    }
+
+    /// Sets the role of the requests without a role, e.g. the role of the
+    /// Manager account on whose behalf the requests are sent. Signed requests
+    /// are left as they are.
+    #[must_use]
+    pub fn role(mut self, role: Role) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    /// Sends a request to roxy and returns the decoded response.
+    ///
+    /// # Errors
+    ///
+    /// * Failure to convert request message to JSON
+    /// * Failure to spawn roxy
+    /// * Failure to write command to roxy
+    /// * Invalid json syntax in response message
+    /// * base64 decode error for reponse message
+    /// * Received execution error from roxy
+    pub fn run<T>(&self, req: NodeRequest) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        self.exchange::<TaskResult>(&RoxyRequest::Single(self.scoped(req)))?
+            .decode()
+    }
+
+    /// Sends requests to roxy at once. Roxy executes them in order in a single
+    /// invocation, except that consecutive read-only requests (`Get`, `List`,
+    /// `Status`, `Ping`, and `Preflight`) are executed concurrently. A request
+    /// which changes the system sees the results of the requests before it.
+    /// Returns the result of each request in the same order. Use
+    /// [`TaskResult::decode`] to get the response of each request.
+    ///
+    /// # Errors
+    ///
+    /// * Failure to convert request message to JSON
+    /// * Failure to spawn roxy
+    /// * Failure to write command to roxy
+    /// * Invalid json syntax in response message
+    pub fn run_batch(&self, reqs: Vec<NodeRequest>) -> Result<Vec<TaskResult>> {
+        self.run_many(&RoxyRequest::Batch(self.scoped_all(reqs)))
+    }
+
+    /// Sends requests to roxy as a transaction. If any request fails, roxy
+    /// stops executing the remaining requests and restores the system
+    /// configuration saved before the transaction. In that case, the results
+    /// of the requests executed successfully are replaced with an error.
+    /// Only requests which read the system or change the configuration of a
+    /// [`Subsystem`](crate::common::Subsystem) can be restored, so roxy
+    /// refuses a transaction with any other request, e.g. a reboot or a
+    /// WireGuard configuration, without executing any of them.
+    ///
+    /// # Errors
+    ///
+    /// * Failure to convert request message to JSON
+    /// * Failure to spawn roxy
+    /// * Failure to write command to roxy
+    /// * Invalid json syntax in response message
+    pub fn run_transaction(&self, reqs: Vec<NodeRequest>) -> Result<Vec<TaskResult>> {
+        self.run_many(&RoxyRequest::Transaction {
+            transaction: self.scoped_all(reqs),
+        })
+    }
+
+    // The role of a signed request is covered by its signature.
+    fn scoped(&self, mut req: NodeRequest) -> NodeRequest {
+        if req.role.is_none() && req.signature.is_none() {
+            req.role = self.role;
+        }
+        req
+    }
+
+    fn scoped_all(&self, reqs: Vec<NodeRequest>) -> Vec<NodeRequest> {
+        reqs.into_iter().map(|req| self.scoped(req)).collect()
+    }
+
+    fn run_many(&self, msg: &RoxyRequest) -> Result<Vec<TaskResult>> {
+        let len = match msg {
+            RoxyRequest::Batch(reqs) | RoxyRequest::Transaction { transaction: reqs } => reqs.len(),
+            RoxyRequest::Single(_) => 1,
+        };
+        let results = self.exchange::<Vec<TaskResult>>(msg)?;
+        if results.len() == len {
+            Ok(results)
+        } else {
+            Err(anyhow!(
+                "fail to parse response. {} results for {} requests",
+                results.len(),
+                len
+            ))
+        }
+    }
+
+    fn exchange<T>(&self, msg: &RoxyRequest) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        let mut cmd = Command::new(&self.program);
+        cmd.envs(self.envs.iter().map(|(k, v)| (k, v)))
+            .stdin(Stdio::piped())
+            .stdout(Stdio::piped());
+        if let Some(dir) = &self.current_dir {
+            cmd.current_dir(dir);
+        }
+        let buf = serde_json::to_vec(msg)?;
+        let mut child = cmd.spawn()?;
+
+        if let Some(mut child_stdin) = child.stdin.take() {
+            // Roxy may exit without reading the whole request, and then the
+            // write fails. The response tells what happened in that case.
+            std::thread::spawn(move || {
+                let _ = child_stdin.write_all(&buf);
+            });
+        } else {
+            return Err(anyhow!("failed to execute roxy"));
+        }
+
+        let output = child.wait_with_output()?;
+        serde_json::from_reader::<&[u8], T>(&output.stdout)
+            .map_err(|e| anyhow!("fail to parse response. {}", e))
+    }
+}
@@ -1,34 +1,359 @@
+mod audit;
+mod bond;
+mod capture;
+mod change;
+mod cleanup;
+mod compliance;
+mod connectivity;
+mod cpu_governor;
+mod crash;
+mod dns;
+mod filesystem;
+mod fstrim;
+mod hardware;
+mod history;
+mod hostname;
+mod info;
 mod interface;
+mod interface_group;
+mod irq_affinity;
+mod kdump;
+mod license;
+mod maintenance;
+mod netns;
+mod page;
+mod ping;
+mod preflight;
+mod raid;
+mod schedule;
 mod services;
+mod smart;
+mod sriov;
+mod state;
+mod stats;
+mod tuning;
+mod tunnel;
+mod vrrp;
+mod wireguard;
+
+use std::{
+    collections::BTreeSet,
+    fmt, fs, io,
+    path::Path,
+    process::{Command, Output},
+    sync::Mutex,
+    time::Instant,
+};
 
 use anyhow::{anyhow, Result};
-pub use interface::{Nic, NicOutput};
+pub use audit::{AuditEvent, AuditRecord, AuditStatus, SessionAuditStatus};
+use bincode::Options;
+pub use bond::{Aggregator, BondSlave, BondStatus};
+pub use capture::{CaptureSettings, CaptureStatus};
+pub use change::ChangeEvent;
+pub use cleanup::CleanupReport;
+pub use compliance::{Baseline, ComplianceReport};
+pub use connectivity::{Endpoint, EndpointCheck, Protocol, ReadinessReport};
+pub use cpu_governor::{CpuGovernor, GovernorStatus};
+pub use crash::CrashReport;
+pub use dns::{DnsLookup, DnsResolver, UpstreamDns};
+pub use filesystem::FilesystemHealth;
+pub use fstrim::{TrimRun, TrimStatus, TrimmedFilesystem};
+pub use hardware::HardwareInfo;
+pub use history::HistoryEntry;
+pub use hostname::HostnameInfo;
+pub use info::NodeInfo;
+pub use interface::{
+    nameserver_order, ActivationMode, Nic, NicOutput, NicOutputV1, Route, RouteOutput, RoutingRule,
+    RoutingRuleOutput,
+};
+pub use interface_group::{GroupSettings, InterfaceResult};
+pub use irq_affinity::{IrqAffinity, IrqLayout};
+pub use kdump::KdumpStatus;
+pub use license::{License, LicenseStatus};
+pub use maintenance::{is_deferrable, DeferredOperation, MaintenanceWindow, OperationStatus};
+pub use netns::{NetnsCapture, NetnsInterface};
+pub use page::{Page, PageRequest, MAX_PAGE_LIMIT};
+pub use ping::PingInfo;
+pub use preflight::PreflightCheck;
+pub use raid::{MdArray, RaidStatus, RaidVolume};
+pub use schedule::JobRun;
 use serde::{Deserialize, Serialize};
-pub use services::waitfor_up;
+pub use services::{waitfor_up, ContainerHealth, ServiceAction};
+pub use smart::{DiskHealth, SmartAttribute};
+pub use sriov::{SriovStatus, VfConfig, VirtualFunction};
+pub use state::{DriftItem, FirewallState, SshdState, StateChange, SystemState, SystemStateV1};
+pub use stats::{TaskStats, LATENCY_BUCKETS_MS};
+pub use tuning::{TuningSetting, TuningStatus};
+pub use tunnel::{TunnelConfig, TunnelMode};
+pub use vrrp::{VrrpConfig, VrrpState, VrrpStatus};
+pub use wireguard::{WireguardConfig, WireguardPeer, WireguardStatus};
+
+/// Directory where roxy keeps its state, such as configuration history.
+pub const ROXY_STATE_DIR: &str = "/usr/local/aice/var/roxy";
+
+/// Directory under [`ROXY_STATE_DIR`] where roxy records when it modified
+/// each subsystem. Roxy writes `<subsystem>.begin` with the start time
+/// before modifying a subsystem, and `<subsystem>.end` with the start and
+/// end times after it, in seconds since the Unix epoch.
+pub const WRITE_MARKER_DIR: &str = "writes";
+
+/// Extension of the marker written before roxy modifies a subsystem.
+pub const WRITE_BEGIN: &str = "begin";
+
+/// Extension of the marker written after roxy modifies a subsystem.
+pub const WRITE_END: &str = "end";
+
+/// Directory of the netplan files. All the YAML files in it belong to
+/// [`Subsystem::Interface`].
+pub const NETPLAN_DIR: &str = "/etc/netplan";
 
 pub const DEFAULT_PATH_ENV: &str = "/usr/sbin:/usr/bin:/sbin:/bin:/usr/local/aice/bin";
 
+/// Environment variables given to every command spawned by roxy. The C
+/// locale keeps the output of the commands in English for parsing.
+pub const SANITIZED_ENV: [(&str, &str); 3] =
+    [("LANG", "C"), ("LC_ALL", "C"), ("PATH", DEFAULT_PATH_ENV)];
+
+/// Returns a `Command` for the program with the environment cleared and set
+/// to [`SANITIZED_ENV`].
+#[must_use]
+pub fn command(program: &str) -> Command {
+    let mut cmd = Command::new(program);
+    cmd.env_clear().envs(SANITIZED_ENV);
+    cmd
+}
+
+/// Maximum length of stderr logged by [`run_command`].
+const MAX_LOGGED_STDERR: usize = 512;
+
+// Names of the programs run by `run_command` in this process.
+static SPAWNED: Mutex<BTreeSet<String>> = Mutex::new(BTreeSet::new());
+
+/// Returns the names of the programs run by [`run_command`] in this process,
+/// e.g. to find the audit records of the commands which failed.
+#[must_use]
+pub fn spawned_programs() -> Vec<String> {
+    SPAWNED
+        .lock()
+        .map(|spawned| spawned.iter().cloned().collect())
+        .unwrap_or_default()
+}
+
+/// Runs the command to completion and collects its output. The command line,
+/// duration, exit status and the beginning of stderr are logged at debug
+/// level.
+///
+/// # Errors
+///
+/// * fail to execute the command
+pub fn run_command(cmd: &mut Command) -> io::Result<Output> {
+    if let Some(name) = Path::new(cmd.get_program()).file_name() {
+        if let Ok(mut spawned) = SPAWNED.lock() {
+            spawned.insert(name.to_string_lossy().into_owned());
+        }
+    }
+    let start = Instant::now();
+    let ret = cmd.output();
+    if log::log_enabled!(log::Level::Debug) {
+        let line = command_line(cmd);
+        let elapsed = start.elapsed();
+        match &ret {
+            Ok(output) => {
+                let stderr = String::from_utf8_lossy(&output.stderr);
+                log::debug!(
+                    "`{line}` finished in {elapsed:?} with {}. stderr: {:?}",
+                    output.status,
+                    truncate(stderr.trim(), MAX_LOGGED_STDERR)
+                );
+            }
+            Err(e) => log::debug!("`{line}` failed in {elapsed:?}. {e}"),
+        }
+    }
+    ret
+}
+
+fn command_line(cmd: &Command) -> String {
+    let mut line = cmd.get_program().to_string_lossy().into_owned();
+    for arg in cmd.get_args() {
+        line.push(' ');
+        line.push_str(&arg.to_string_lossy());
+    }
+    line
+}
+
+fn truncate(s: &str, max: usize) -> &str {
+    if s.len() <= max {
+        return s;
+    }
+    let mut end = max;
+    while !s.is_char_boundary(end) {
+        end -= 1;
+    }
+    &s[..end]
+}
+
 /// Types of command to node.
 #[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum Node {
+    Audit(SubCommand),
+    Banner(SubCommand),
+    Bond(SubCommand),
+    Capture(SubCommand),
+    Changes(SubCommand),
+    Cleanup(SubCommand),
+    Compliance(SubCommand),
+    Compose(SubCommand),
+    Connectivity(SubCommand),
+    CpuGovernor(SubCommand),
+    Crash(SubCommand),
+    Dns(SubCommand),
+    Filesystem(SubCommand),
+    Fstrim(SubCommand),
+    Hardware(SubCommand),
+    History(SubCommand),
     Hostname(SubCommand),
+    Info(SubCommand),
     Interface(SubCommand),
+    InterfaceGroup(SubCommand),
+    /// `Interface` exchanging [`NicOutput`], which has the settings added
+    /// after [`NicOutputV1`]. `Interface` keeps exchanging [`NicOutputV1`], so
+    /// that a library and roxy of different versions understand each other.
+    InterfaceV2(SubCommand),
+    IrqAffinity(SubCommand),
+    Kdump(SubCommand),
+    License(SubCommand),
+    Maintenance(SubCommand),
+    Netns(SubCommand),
     Ntp(SubCommand),
+    Ping,
     PowerOff,
+    Preflight,
+    Raid(SubCommand),
     Reboot,
+    Replica(SubCommand),
+    Schedule(SubCommand),
     Service(SubCommand),
+    ServiceEnvironment(SubCommand),
+    ServiceGroup(SubCommand),
+    SessionAudit(SubCommand),
+    Smart(SubCommand),
+    Sriov(SubCommand),
     Sshd(SubCommand),
+    State(SubCommand),
+    /// `State` exchanging [`SystemState`], whose interfaces are
+    /// [`NicOutput`]. `State` keeps exchanging [`SystemStateV1`].
+    StateV2(SubCommand),
+    Stats(SubCommand),
     Syslog(SubCommand),
+    Tuning(SubCommand),
+    Tunnel(SubCommand),
     Ufw(SubCommand),
     Version(SubCommand),
+    Vrrp(SubCommand),
+    Wireguard(SubCommand),
 }
 
-#[derive(Debug, Deserialize, Serialize, Clone)]
+/// Version of the layout of the arguments and responses of requests, from
+/// which the capability hash of [`PingInfo`] is computed with [`NODE_KINDS`].
+/// It is bumped whenever the layout of an existing kind changes, so that
+/// roxy and the library built with different layouts are not compatible.
+pub const PROTOCOL_VERSION: u32 = 1;
+
+/// Names of the variants of [`Node`], from which the capability hash of
+/// [`PingInfo`] is computed.
+pub const NODE_KINDS: [&str; 51] = [
+    "Audit",
+    "Banner",
+    "Bond",
+    "Capture",
+    "Changes",
+    "Cleanup",
+    "Compliance",
+    "Compose",
+    "Connectivity",
+    "CpuGovernor",
+    "Crash",
+    "Dns",
+    "Filesystem",
+    "Fstrim",
+    "Hardware",
+    "History",
+    "Hostname",
+    "Info",
+    "Interface",
+    "InterfaceGroup",
+    "InterfaceV2",
+    "IrqAffinity",
+    "Kdump",
+    "License",
+    "Maintenance",
+    "Netns",
+    "Ntp",
+    "Ping",
+    "PowerOff",
+    "Preflight",
+    "Raid",
+    "Reboot",
+    "Replica",
+    "Schedule",
+    "Service",
+    "ServiceEnvironment",
+    "ServiceGroup",
+    "SessionAudit",
+    "Smart",
+    "Sriov",
+    "Sshd",
+    "State",
+    "StateV2",
+    "Stats",
+    "Syslog",
+    "Tuning",
+    "Tunnel",
+    "Ufw",
+    "Version",
+    "Vrrp",
+    "Wireguard",
+];
+
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct NodeRequest {
     /// command
     pub kind: Node,
     /// command arguments
     pub arg: Vec<u8>,
+    /// role of the requester, which limits the tasks roxy executes. If None,
+    /// the request has the highest role allowed to the user running roxy.
+    /// It is omitted if None, so that the request is encoded as before
+    /// roles.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<Role>,
+    /// Signature of the Manager over the request, which roxy verifies if
+    /// `/etc/roxy/request-signing.pem` exists, so that a request relayed by
+    /// another process cannot be forged by that process.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub signature: Option<RequestSignature>,
+}
+
+/// A detached signature over a [`NodeRequest`], made with the private key of
+/// the Manager.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RequestSignature {
+    /// When the request was signed, in seconds since the Unix epoch. Roxy
+    /// refuses a signature more than five minutes away from its clock.
+    pub signed_at: i64,
+    /// Random bytes which make the signature unique. Roxy refuses a nonce it
+    /// has seen within the age limit of signatures, so that a signed request
+    /// cannot be replayed.
+    pub nonce: Vec<u8>,
+    /// ID of the node the request is signed for, as returned by [`node_id`].
+    /// Roxy refuses a request signed for another node.
+    pub node: String,
+    /// SHA-256 signature in DER over [`NodeRequest::signed_payload`].
+    pub signature: Vec<u8>,
 }
 
 impl NodeRequest {
@@ -44,13 +369,132 @@ impl NodeRequest {
         T: Serialize,
     {
         match bincode::serialize(&cmd) {
-            Ok(arg) => Ok(NodeRequest { kind, arg }),
+            Ok(arg) => Ok(NodeRequest {
+                kind,
+                arg,
+                role: None,
+                signature: None,
+            }),
             Err(e) => Err(anyhow!("Error: {}", e)),
         }
     }
+
+    /// Sets the role of the requester. Roxy refuses the request if the role
+    /// does not allow the task, or is higher than the role allowed to the
+    /// user running roxy.
+    #[must_use]
+    pub fn role(mut self, role: Role) -> Self {
+        self.role = Some(role);
+        self
+    }
+
+    /// Returns the bytes to sign for the request: its kind, arguments, and
+    /// role, and the time, nonce, and node of the signature.
+    ///
+    /// # Errors
+    ///
+    /// * If serialization of the request fails, then an error is returned.
+    pub fn signed_payload(&self, signed_at: i64, nonce: &[u8], node: &str) -> Result<Vec<u8>> {
+        bincode::serialize(&(&self.kind, &self.arg, &self.role, signed_at, nonce, node))
+            .map_err(|e| anyhow!("Error: {}", e))
+    }
+
+    /// Attaches the signature of the Manager. The request should not be
+    /// changed afterward, including its role.
+    #[must_use]
+    pub fn signature(mut self, signature: RequestSignature) -> Self {
+        self.signature = Some(signature);
+        self
+    }
+}
+
+/// File with the ID of the node, which a [`RequestSignature`] is signed for.
+pub const MACHINE_ID: &str = "/etc/machine-id";
+
+/// Returns the ID of the node, which a [`RequestSignature`] for the node
+/// should have.
+///
+/// # Errors
+///
+/// * If `/etc/machine-id` cannot be read or is empty, then an error is
+///   returned.
+pub fn node_id() -> Result<String> {
+    let id = fs::read_to_string(MACHINE_ID)?.trim().to_string();
+    if id.is_empty() {
+        Err(anyhow!("{} is empty", MACHINE_ID))
+    } else {
+        Ok(id)
+    }
+}
+
+/// Error message of roxy for a task kind it does not know, followed by the
+/// kind, e.g. `unsupported task: Interface::Set`.
+pub const UNSUPPORTED_TASK: &str = "unsupported task";
+
+/// Returns the error message of roxy for a task kind it does not know.
+#[must_use]
+pub fn unsupported_task(kind: &str) -> String {
+    format!("{UNSUPPORTED_TASK}: {kind}")
+}
+
+/// Error message of roxy for an address which conflicts with another
+/// interface or host, followed by the conflict, e.g. `address conflict:
+/// 10.0.0.1 is assigned to eth1`.
+pub const ADDRESS_CONFLICT: &str = "address conflict";
+
+/// Error message of roxy for a diagnostic killed at the resource limits in
+/// `/etc/roxy/limits.yaml`, followed by the diagnostic, e.g. `resource limit
+/// exceeded: smartctl`.
+pub const LIMIT_EXCEEDED: &str = "resource limit exceeded";
+
+/// Maximum size of a request read by roxy, and of the arguments decoded by
+/// [`decode_arg`].
+pub const MAX_REQUEST_SIZE: u64 = 8 * 1024 * 1024;
+
+/// Maximum size of a response of roxy before base64 encoding. A larger list
+/// should be requested a [`Page`] at a time.
+pub const MAX_RESPONSE_SIZE: u64 = 16 * 1024 * 1024;
+
+/// Error message of roxy for a response larger than [`MAX_RESPONSE_SIZE`].
+pub const RESPONSE_TOO_LARGE: &str = "response too large";
+
+/// Decodes command arguments serialized by [`NodeRequest::new`]. The size of
+/// the decoded arguments is limited to [`MAX_REQUEST_SIZE`], so a length
+/// prefix in untrusted input cannot make it allocate without bound.
+///
+/// # Errors
+///
+/// * If the arguments are not a valid encoding of `T`, then an error is
+///   returned.
+pub fn decode_arg<T>(arg: &[u8]) -> Result<T>
+where
+    T: serde::de::DeserializeOwned,
+{
+    bincode::options()
+        .with_fixint_encoding()
+        .allow_trailing_bytes()
+        .with_limit(MAX_REQUEST_SIZE)
+        .deserialize::<T>(arg)
+        .map_err(|e| anyhow!("fail to parse argument. {}", e))
+}
+
+/// Message delivered to roxy: a single request or a batch of requests
+/// executed sequentially in one roxy invocation.
+#[derive(Debug, Deserialize, Serialize, Clone, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(untagged)]
+pub enum RoxyRequest {
+    Batch(Vec<NodeRequest>),
+    Single(NodeRequest),
+    /// A batch of requests which is rolled back as a whole if any of them
+    /// fails.
+    Transaction {
+        transaction: Vec<NodeRequest>,
+    },
 }
 
 #[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub enum SubCommand {
     Add,
     Delete,
@@ -59,9 +503,512 @@ pub enum SubCommand {
     Get,
     Init,
     List,
+    Revert,
     Set,
     SetOsVersion,
     SetProductVersion,
     Status,
     Update,
 }
+
+/// Role of a requester, from the lowest to the highest.
+///
+/// * `ReadOnly` may only issue `Get`, `List`, and `Status` subcommands, and
+///   `Ping` and `Preflight`.
+/// * `Operator` may issue any task except those denied to operators in
+///   `/etc/roxy/roles.yaml`.
+/// * `Admin` may issue any task.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, PartialOrd, Ord)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(rename_all = "kebab-case")]
+pub enum Role {
+    ReadOnly,
+    Operator,
+    Admin,
+}
+
+/// Configuration managed by roxy.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub enum Subsystem {
+    Firewall,
+    Hostname,
+    Interface,
+    Ntp,
+    Sshd,
+    Syslog,
+    Version,
+}
+
+impl Subsystem {
+    /// Every subsystem.
+    pub const ALL: [Subsystem; 7] = [
+        Subsystem::Firewall,
+        Subsystem::Hostname,
+        Subsystem::Interface,
+        Subsystem::Ntp,
+        Subsystem::Sshd,
+        Subsystem::Syslog,
+        Subsystem::Version,
+    ];
+
+    /// The configuration files of the subsystem, which roxy saves before
+    /// changing it, and [`ConfigWatcher`](crate::ConfigWatcher) watches. The
+    /// main file is the first. The netplan files in [`NETPLAN_DIR`] also
+    /// belong to `Interface`.
+    #[must_use]
+    pub fn files(self) -> &'static [&'static str] {
+        match self {
+            Subsystem::Firewall => &[
+                "/etc/ufw/ufw.conf",
+                "/etc/ufw/user.rules",
+                "/etc/ufw/user6.rules",
+            ],
+            Subsystem::Hostname => &["/etc/hosts", "/etc/sysctl.d/90-roxy-domainname.conf"],
+            Subsystem::Interface => &[
+                "/etc/default/roxy-dns-priority",
+                "/etc/default/roxy-interface-labels",
+            ],
+            Subsystem::Ntp => &["/etc/ntp.conf"],
+            Subsystem::Sshd => &[
+                "/etc/ssh/sshd_config",
+                "/etc/issue",
+                "/etc/issue.net",
+                "/etc/motd",
+            ],
+            Subsystem::Syslog => &["/etc/rsyslog.d/50-default.conf"],
+            Subsystem::Version => &["/etc/version"],
+        }
+    }
+}
+
+impl fmt::Display for Subsystem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Subsystem::Firewall => "firewall",
+            Subsystem::Hostname => "hostname",
+            Subsystem::Interface => "interface",
+            Subsystem::Ntp => "ntp",
+            Subsystem::Sshd => "sshd",
+            Subsystem::Syslog => "syslog",
+            Subsystem::Version => "version",
+        };
+        write!(f, "{s}")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::ffi::OsStr;
+
+    use proptest::{collection::vec, option, prelude::*};
+
+    use super::*;
+
+    #[test]
+    fn command_env_is_sanitized() {
+        let cmd = command("ufw");
+        let envs = cmd.get_envs().collect::<Vec<_>>();
+        assert_eq!(envs.len(), SANITIZED_ENV.len());
+        for (key, val) in SANITIZED_ENV {
+            assert!(envs.contains(&(OsStr::new(key), Some(OsStr::new(val)))));
+        }
+    }
+
+    #[test]
+    fn signed_payload_covers_role_time_nonce_and_node() {
+        let req = NodeRequest::new(Node::Ntp(SubCommand::Set), vec!["10.0.0.1"])
+            .expect("argument should serialize");
+        let payload = req.signed_payload(1, &[1], "node").expect("payload");
+        assert_ne!(
+            payload,
+            req.signed_payload(2, &[1], "node").expect("payload")
+        );
+        assert_ne!(
+            payload,
+            req.signed_payload(1, &[2], "node").expect("payload")
+        );
+        assert_ne!(
+            payload,
+            req.signed_payload(1, &[1], "other").expect("payload")
+        );
+        let admin = req.clone().role(Role::Admin);
+        assert_ne!(
+            payload,
+            admin.signed_payload(1, &[1], "node").expect("payload")
+        );
+        let signed = req.signature(RequestSignature {
+            signed_at: 1,
+            nonce: vec![1],
+            node: "node".to_string(),
+            signature: vec![1, 2, 3],
+        });
+        assert_eq!(
+            payload,
+            signed.signed_payload(1, &[1], "node").expect("payload")
+        );
+    }
+
+    const SUBCOMMANDS: [SubCommand; 13] = [
+        SubCommand::Add,
+        SubCommand::Delete,
+        SubCommand::Disable,
+        SubCommand::Enable,
+        SubCommand::Get,
+        SubCommand::Init,
+        SubCommand::List,
+        SubCommand::Revert,
+        SubCommand::Set,
+        SubCommand::SetOsVersion,
+        SubCommand::SetProductVersion,
+        SubCommand::Status,
+        SubCommand::Update,
+    ];
+
+    // Every kind of node with every subcommand. The matches fail to compile
+    // when a variant is added, as a reminder to add it here.
+    fn all_nodes() -> Vec<Node> {
+        let kinds: [fn(SubCommand) -> Node; 47] = [
+            Node::Audit,
+            Node::Banner,
+            Node::Bond,
+            Node::Capture,
+            Node::Changes,
+            Node::Cleanup,
+            Node::Compliance,
+            Node::Compose,
+            Node::Connectivity,
+            Node::CpuGovernor,
+            Node::Crash,
+            Node::Dns,
+            Node::Filesystem,
+            Node::Fstrim,
+            Node::Hardware,
+            Node::History,
+            Node::Hostname,
+            Node::Info,
+            Node::Interface,
+            Node::InterfaceGroup,
+            Node::InterfaceV2,
+            Node::IrqAffinity,
+            Node::Kdump,
+            Node::License,
+            Node::Maintenance,
+            Node::Netns,
+            Node::Ntp,
+            Node::Raid,
+            Node::Replica,
+            Node::Schedule,
+            Node::Service,
+            Node::ServiceEnvironment,
+            Node::ServiceGroup,
+            Node::SessionAudit,
+            Node::Smart,
+            Node::Sriov,
+            Node::Sshd,
+            Node::State,
+            Node::StateV2,
+            Node::Stats,
+            Node::Syslog,
+            Node::Tuning,
+            Node::Tunnel,
+            Node::Ufw,
+            Node::Version,
+            Node::Vrrp,
+            Node::Wireguard,
+        ];
+        let mut nodes = vec![Node::Ping, Node::PowerOff, Node::Preflight, Node::Reboot];
+        for kind in kinds {
+            nodes.extend(SUBCOMMANDS.map(kind));
+        }
+        nodes.iter().for_each(listed);
+        for cmd in SUBCOMMANDS {
+            match cmd {
+                SubCommand::Add
+                | SubCommand::Delete
+                | SubCommand::Disable
+                | SubCommand::Enable
+                | SubCommand::Get
+                | SubCommand::Init
+                | SubCommand::List
+                | SubCommand::Revert
+                | SubCommand::Set
+                | SubCommand::SetOsVersion
+                | SubCommand::SetProductVersion
+                | SubCommand::Status
+                | SubCommand::Update => {}
+            }
+        }
+        nodes
+    }
+
+    // Fails to compile when a variant is added to `Node`, as a reminder to
+    // add it to `all_nodes`.
+    fn listed(node: &Node) {
+        match node {
+            Node::Audit(_)
+            | Node::Banner(_)
+            | Node::Bond(_)
+            | Node::Capture(_)
+            | Node::Changes(_)
+            | Node::Cleanup(_)
+            | Node::Compliance(_)
+            | Node::Compose(_)
+            | Node::Connectivity(_)
+            | Node::CpuGovernor(_)
+            | Node::Crash(_)
+            | Node::Dns(_)
+            | Node::Filesystem(_)
+            | Node::Fstrim(_)
+            | Node::Hardware(_)
+            | Node::History(_)
+            | Node::Hostname(_)
+            | Node::Info(_)
+            | Node::Interface(_)
+            | Node::InterfaceGroup(_)
+            | Node::InterfaceV2(_)
+            | Node::IrqAffinity(_)
+            | Node::Kdump(_)
+            | Node::License(_)
+            | Node::Maintenance(_)
+            | Node::Netns(_)
+            | Node::Ntp(_)
+            | Node::Ping
+            | Node::PowerOff
+            | Node::Preflight
+            | Node::Raid(_)
+            | Node::Reboot
+            | Node::Replica(_)
+            | Node::Schedule(_)
+            | Node::Service(_)
+            | Node::ServiceEnvironment(_)
+            | Node::ServiceGroup(_)
+            | Node::SessionAudit(_)
+            | Node::Smart(_)
+            | Node::Sriov(_)
+            | Node::Sshd(_)
+            | Node::State(_)
+            | Node::StateV2(_)
+            | Node::Stats(_)
+            | Node::Syslog(_)
+            | Node::Tuning(_)
+            | Node::Tunnel(_)
+            | Node::Ufw(_)
+            | Node::Version(_)
+            | Node::Vrrp(_)
+            | Node::Wireguard(_) => {}
+        }
+    }
+
+    fn nic_output() -> impl Strategy<Value = NicOutput> {
+        let strings = || option::of(vec(any::<String>(), 0..4));
+        let number = || option::of(any::<u32>());
+        let route = (
+            any::<String>(),
+            option::of(any::<String>()),
+            number(),
+            number(),
+        )
+            .prop_map(|(to, via, metric, table)| RouteOutput {
+                to,
+                via,
+                metric,
+                table,
+            });
+        let rule = (
+            option::of(any::<String>()),
+            option::of(any::<String>()),
+            any::<u32>(),
+            number(),
+            number(),
+        )
+            .prop_map(|(from, to, table, priority, mark)| RoutingRuleOutput {
+                from,
+                to,
+                table,
+                priority,
+                mark,
+            });
+        let activation_mode = option::of(prop_oneof![
+            Just(ActivationMode::Manual),
+            Just(ActivationMode::Off)
+        ]);
+        (
+            (
+                strings(),
+                option::of(any::<bool>()),
+                option::of(any::<String>()),
+                strings(),
+            ),
+            (number(), strings(), number(), option::of(any::<bool>())),
+            (
+                activation_mode,
+                option::of(vec(route, 0..3)),
+                option::of(vec(rule, 0..3)),
+                option::of(any::<String>()),
+            ),
+        )
+            .prop_map(
+                |(
+                    (addresses, dhcp4, gateway4, nameservers),
+                    (gateway_metric, search_domains, dns_priority, optional),
+                    (activation_mode, routes, routing_policy, label),
+                )| {
+                    let mut nic = NicOutput::new(addresses, dhcp4, gateway4, nameservers);
+                    nic.gateway_metric = gateway_metric;
+                    nic.search_domains = search_domains;
+                    nic.dns_priority = dns_priority;
+                    nic.optional = optional;
+                    nic.activation_mode = activation_mode;
+                    nic.routes = routes;
+                    nic.routing_policy = routing_policy;
+                    nic.label = label;
+                    nic
+                },
+            )
+    }
+
+    proptest! {
+        #![proptest_config(ProptestConfig::with_cases(32))]
+
+        #[test]
+        fn node_request_round_trip(arg in (any::<String>(), nic_output())) {
+            for kind in all_nodes() {
+                let req = NodeRequest::new(kind.clone(), &arg).expect("argument should serialize");
+                prop_assert_eq!(
+                    decode_arg::<(String, NicOutput)>(&req.arg).expect("valid argument"),
+                    arg.clone()
+                );
+
+                let json = serde_json::to_string(&req).expect("request should serialize");
+                let decoded = serde_json::from_str::<NodeRequest>(&json).expect("valid request");
+                prop_assert_eq!(&decoded, &req);
+                prop_assert_eq!(decoded.kind, kind);
+            }
+        }
+
+        #[test]
+        fn roxy_request_round_trip(
+            args in vec(option::of(vec(any::<String>(), 0..4)), all_nodes().len())
+        ) {
+            let reqs = all_nodes()
+                .into_iter()
+                .zip(args)
+                .map(|(kind, arg)| NodeRequest::new(kind, arg).expect("argument should serialize"))
+                .collect::<Vec<_>>();
+
+            let single = RoxyRequest::Single(reqs[0].clone());
+            let batch = RoxyRequest::Batch(reqs.clone());
+            let transaction = RoxyRequest::Transaction { transaction: reqs };
+            for msg in [single, batch, transaction] {
+                let json = serde_json::to_vec(&msg).expect("request should serialize");
+                prop_assert_eq!(
+                    serde_json::from_slice::<RoxyRequest>(&json).expect("valid request"),
+                    msg
+                );
+            }
+        }
+
+        #[test]
+        fn nic_output_round_trip(nic in nic_output()) {
+            let bin = bincode::serialize(&nic).expect("nic should serialize");
+            prop_assert_eq!(decode_arg::<NicOutput>(&bin).expect("valid nic"), nic.clone());
+            let json = serde_json::to_string(&nic).expect("nic should serialize");
+            prop_assert_eq!(
+                serde_json::from_str::<NicOutput>(&json).expect("valid nic"),
+                nic
+            );
+        }
+
+        #[test]
+        fn truncated_arg(nic in nic_output()) {
+            let bin = bincode::serialize(&nic).expect("nic should serialize");
+            for len in 0..bin.len() {
+                // Must not panic.
+                let _ = decode_arg::<NicOutput>(&bin[..len]);
+            }
+        }
+    }
+
+    #[test]
+    fn node_kinds() {
+        let mut kinds = all_nodes()
+            .iter()
+            .map(|node| {
+                let name = format!("{node:?}");
+                name.split('(').next().unwrap_or_default().to_string()
+            })
+            .collect::<Vec<_>>();
+        kinds.sort();
+        kinds.dedup();
+        assert_eq!(kinds, NODE_KINDS);
+    }
+
+    #[test]
+    fn default_dns_route() {
+        let strings = |items: &[&str]| items.iter().map(ToString::to_string).collect::<Vec<_>>();
+        let nic = NicOutput::new(None, None, None, Some(strings(&["10.0.0.53"])))
+            .search_domains(strings(&["example.com"]));
+        let mut yaml = nic.to();
+        yaml.set_default_dns_route(true);
+        assert_eq!(
+            yaml.nameservers.as_ref().and_then(|nm| nm.get("search")),
+            Some(&strings(&["example.com", "~."]))
+        );
+        assert_eq!(NicOutput::from(&yaml), nic);
+        yaml.set_default_dns_route(false);
+        assert_eq!(yaml.nameservers, nic.to().nameservers);
+    }
+
+    #[test]
+    fn nic_activation_mode() {
+        let nic = Nic::new(None, Some(false), None, None, Some(true));
+        assert_eq!(nic.activation_mode, None);
+        let nic = nic.activation_mode(ActivationMode::Off);
+        let output = NicOutput::from(&nic);
+        assert_eq!(output.optional, Some(true));
+        assert_eq!(output.activation_mode, Some(ActivationMode::Off));
+    }
+
+    #[test]
+    fn dns_order() {
+        let nic = |priority: Option<u32>| {
+            let nic = NicOutput::new(None, None, None, Some(vec!["10.0.0.53".to_string()]));
+            match priority {
+                Some(priority) => nic.dns_priority(priority),
+                None => nic,
+            }
+        };
+        let interfaces = vec![
+            ("eth0".to_string(), nic(None)),
+            ("eth1".to_string(), nic(Some(20))),
+            (
+                "eth2".to_string(),
+                NicOutput::new(None, Some(true), None, None),
+            ),
+            ("eth3".to_string(), nic(Some(10))),
+        ];
+        assert_eq!(nameserver_order(&interfaces), ["eth3", "eth1", "eth0"]);
+    }
+
+    #[test]
+    fn arg_size_limit() {
+        let req = NodeRequest::new(Node::Ntp(SubCommand::Set), vec!["10.0.0.1"])
+            .expect("argument should serialize");
+        assert_eq!(
+            decode_arg::<Vec<String>>(&req.arg).expect("valid argument"),
+            ["10.0.0.1"]
+        );
+        assert!(decode_arg::<Vec<String>>(&u64::MAX.to_le_bytes()).is_err());
+        assert!(decode_arg::<String>(&(MAX_REQUEST_SIZE + 1).to_le_bytes()).is_err());
+    }
+
+    #[test]
+    fn logged_command_line() {
+        let mut cmd = command("ufw");
+        cmd.args(["allow", "22/tcp"]);
+        assert_eq!(command_line(&cmd), "ufw allow 22/tcp");
+        assert_eq!(truncate("netplan", 3), "net");
+        assert_eq!(truncate("네트워크", 4), "네");
+        assert_eq!(truncate("ufw", 512), "ufw");
+    }
+}
@@ -0,0 +1,42 @@
+use std::collections::BTreeMap;
+
+use serde::{Deserialize, Serialize};
+
+/// Status of the session recording of interactive logins.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct SessionAuditStatus {
+    /// True if `pam_tty_audit` is enabled for SSH and console logins.
+    pub enabled: bool,
+    /// True if auditd is running, which is required to record sessions.
+    pub auditd_active: bool,
+}
+
+/// Status of auditd and the ruleset installed by roxy.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct AuditStatus {
+    /// True if auditd is running.
+    pub active: bool,
+    /// True if the ruleset is installed in `/etc/audit/rules.d`.
+    pub installed: bool,
+    /// The number of rules loaded in the kernel.
+    pub loaded_rules: usize,
+}
+
+/// An audit event, which consists of the records with the same serial
+/// number.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct AuditEvent {
+    pub serial: u64,
+    /// The time of the event, in RFC 3339 format.
+    pub time: String,
+    /// The key of the rule which generated the event, e.g. "roxy-config".
+    pub key: Option<String>,
+    pub records: Vec<AuditRecord>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct AuditRecord {
+    /// The record type, e.g. "SYSCALL" or "PATH".
+    pub kind: String,
+    pub fields: BTreeMap<String, String>,
+}
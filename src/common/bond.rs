@@ -0,0 +1,56 @@
+use serde::{Deserialize, Serialize};
+
+/// A bonding interface and its slaves, as in `/proc/net/bonding`.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BondStatus {
+    /// e.g. "bond0"
+    pub name: String,
+    /// e.g. "IEEE 802.3ad Dynamic link aggregation"
+    pub mode: String,
+    /// "up" or "down"
+    pub mii_status: String,
+    /// The slave carrying the traffic in the active-backup mode.
+    pub active_slave: Option<String>,
+    /// The aggregator selected by 802.3ad.
+    pub aggregator: Option<Aggregator>,
+    pub slaves: Vec<BondSlave>,
+    /// True if the bond or any slave is down, or any slave is left out of
+    /// the aggregator selected by 802.3ad.
+    pub degraded: bool,
+}
+
+/// The 802.3ad aggregator carrying the traffic of a bond.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Aggregator {
+    pub id: u32,
+    /// The number of slaves in the aggregator.
+    pub ports: u32,
+    /// The MAC address of the LACP partner, which is all zeros if no partner
+    /// answers.
+    pub partner_mac: String,
+    pub partner_key: u32,
+}
+
+/// A slave of a bonding interface.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct BondSlave {
+    pub name: String,
+    /// "up" or "down"
+    pub mii_status: String,
+    /// e.g. "10000 Mbps"
+    pub speed: Option<String>,
+    /// "full" or "half"
+    pub duplex: Option<String>,
+    /// The number of times the link went down, each of which made the bond
+    /// fail over to the other slaves.
+    pub link_failure_count: u32,
+    pub permanent_mac: Option<String>,
+    /// The 802.3ad aggregator the slave belongs to.
+    pub aggregator_id: Option<u32>,
+    /// The system MAC address of the LACP partner seen by the slave.
+    pub partner_mac: Option<String>,
+    /// e.g. "none", "monitoring", or "churned", which means LACP failed to
+    /// settle with the partner.
+    pub actor_churn_state: Option<String>,
+    pub partner_churn_state: Option<String>,
+}
@@ -0,0 +1,40 @@
+use serde::{Deserialize, Serialize};
+
+/// Settings of an interface for packet capture. The offloads merge or split
+/// packets in the NIC or the kernel, so a capture service sees packets
+/// different from those on the wire unless they are disabled.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[allow(clippy::struct_excessive_bools)] // independent switches of a NIC
+pub struct CaptureSettings {
+    /// Whether the interface receives the packets addressed to other hosts.
+    pub promiscuous: bool,
+    /// Generic receive offload.
+    pub gro: bool,
+    /// Large receive offload.
+    pub lro: bool,
+    /// TCP segmentation offload.
+    pub tso: bool,
+}
+
+impl CaptureSettings {
+    /// Returns the settings required by capture services: promiscuous mode
+    /// with all the offloads disabled.
+    #[must_use]
+    pub fn capture() -> Self {
+        CaptureSettings {
+            promiscuous: true,
+            gro: false,
+            lro: false,
+            tso: false,
+        }
+    }
+}
+
+/// Capture settings of an interface.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CaptureStatus {
+    /// The settings of the running interface.
+    pub current: CaptureSettings,
+    /// The settings applied at boot, or None if roxy does not manage them.
+    pub persistent: Option<CaptureSettings>,
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+use super::Subsystem;
+
+/// A change of the system made by roxy.
+///
+/// A task changing the configuration of a [`Subsystem`] is recorded with the
+/// lines it changed, an event for each subsystem. A task changing the system
+/// otherwise, e.g. `Wireguard::Set`, `Tuning::Set`, or `Service::Set`, is
+/// recorded without a subsystem or lines. Tasks changing only the records of
+/// roxy itself, e.g. `Stats::Delete` or `Maintenance::Add`, are not recorded.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ChangeEvent {
+    /// Sequence number, which increases by one with each event. It is the
+    /// cursor from which the following events are fetched.
+    pub seq: u64,
+    /// The time of the change, in RFC 3339 format.
+    pub time: String,
+    /// The subsystem whose configuration changed, or None if the task
+    /// changed the system outside the subsystems.
+    pub subsystem: Option<Subsystem>,
+    /// The task which made the change, e.g. `Ntp::Set`.
+    pub task: String,
+    /// The user who ran roxy.
+    pub actor: String,
+    /// Removed configuration, e.g. "/etc/ntp.conf: server a.example.com iburst".
+    /// Empty if `subsystem` is None.
+    pub before: Vec<String>,
+    /// Added configuration, in the same form as `before`.
+    pub after: Vec<String>,
+    /// Notes on the task, by the policy hooks of roxy or by roxy rolling back
+    /// an interrupted change.
+    #[serde(default)]
+    pub notes: Vec<String>,
+}
@@ -0,0 +1,26 @@
+use serde::{Deserialize, Serialize};
+
+/// Files removed, or to be removed in a dry run, from a directory to keep
+/// it within its quota.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CleanupReport {
+    /// e.g. "/data/pcap"
+    pub path: String,
+    /// The quota in bytes.
+    pub max_size: u64,
+    /// The size in bytes of the files in the directory before the cleanup.
+    pub size: u64,
+    /// The files removed, oldest first.
+    pub removed: Vec<String>,
+    /// The size in bytes of the removed files.
+    pub freed: u64,
+}
+
+impl CleanupReport {
+    /// True if the directory is still over its quota after the cleanup,
+    /// e.g. because its files were modified too recently to be removed.
+    #[must_use]
+    pub fn over_quota(&self) -> bool {
+        self.size.saturating_sub(self.freed) > self.max_size
+    }
+}
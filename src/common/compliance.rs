@@ -0,0 +1,46 @@
+use serde::{Deserialize, Serialize};
+
+use super::{DriftItem, StateChange, Subsystem, SystemState};
+
+/// A configuration policy pushed by the Manager, e.g. the firewall must be
+/// active and sshd must listen on a port. It is installed as a JSON document
+/// with the base64 of the JSON `Baseline` and its signature made by the
+/// private key of the Manager:
+///
+/// ```json
+/// {"payload": "eyJpZCI6...", "signature": "MEUCIQ..."}
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Baseline {
+    /// e.g. "pci-2026-10"
+    pub id: String,
+    /// The configuration the node must have. The subsystems set to `None`
+    /// are not checked.
+    pub state: SystemState,
+    /// The subsystems restored to `state` when they violate it. The
+    /// violations of the other subsystems are only reported.
+    #[serde(default)]
+    pub remediate: Vec<Subsystem>,
+}
+
+/// The result of checking the system against the installed [`Baseline`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ComplianceReport {
+    /// The ID of the baseline.
+    pub baseline: String,
+    /// The time of the check in RFC 3339 format.
+    pub checked_at: String,
+    /// The differences from the baseline left after remediation.
+    pub violations: Vec<DriftItem>,
+    /// The changes made to restore the subsystems to remediate.
+    pub remediated: Vec<StateChange>,
+}
+
+impl ComplianceReport {
+    /// Returns true if the system complies with the baseline, possibly after
+    /// remediation.
+    #[must_use]
+    pub fn is_compliant(&self) -> bool {
+        self.violations.is_empty()
+    }
+}
@@ -0,0 +1,67 @@
+use std::net::SocketAddr;
+
+use serde::{Deserialize, Serialize};
+
+/// How an [`Endpoint`] is checked.
+#[derive(Clone, Copy, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum Protocol {
+    /// Passes if a TCP connection is established.
+    #[default]
+    Tcp,
+    /// Passes unless the host rejects a UDP datagram with an ICMP error, since
+    /// a UDP service does not have to answer. No latency is measured.
+    Udp,
+    /// Passes if the server answers an SNTP request.
+    Ntp,
+}
+
+/// A remote service the node has to reach, e.g. the Manager.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Endpoint {
+    /// What the endpoint is, e.g. "manager", "ntp".
+    pub name: String,
+    /// Hostname or address.
+    pub host: String,
+    pub port: u16,
+    #[serde(default)]
+    pub protocol: Protocol,
+}
+
+/// Result of checking an [`Endpoint`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct EndpointCheck {
+    pub endpoint: Endpoint,
+    /// The address the host resolved to, or None if it is not resolved.
+    pub address: Option<SocketAddr>,
+    /// Round-trip time in milliseconds, if measured.
+    pub latency_ms: Option<u64>,
+    /// Why the check failed, or None if it passed.
+    pub error: Option<String>,
+}
+
+impl EndpointCheck {
+    #[must_use]
+    pub fn passed(&self) -> bool {
+        self.error.is_none()
+    }
+}
+
+/// Reachability of the endpoints required by the node.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ReadinessReport {
+    pub checks: Vec<EndpointCheck>,
+}
+
+impl ReadinessReport {
+    /// Returns true if every endpoint is reachable.
+    #[must_use]
+    pub fn is_ready(&self) -> bool {
+        self.checks.iter().all(EndpointCheck::passed)
+    }
+
+    /// Returns the checks which failed.
+    pub fn failures(&self) -> impl Iterator<Item = &EndpointCheck> {
+        self.checks.iter().filter(|check| !check.passed())
+    }
+}
@@ -0,0 +1,27 @@
+use serde::{Deserialize, Serialize};
+
+/// The frequency scaling governor of a CPU.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CpuGovernor {
+    /// e.g. 0
+    pub cpu: u32,
+    /// e.g. "performance"
+    pub governor: String,
+}
+
+/// The frequency scaling governors of the CPUs. Latency-sensitive services,
+/// such as packet capture, run best with "performance", which keeps the CPUs
+/// at their highest frequency.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct GovernorStatus {
+    /// The scaling driver, e.g. `intel_pstate`, or None if the CPUs do not
+    /// support frequency scaling, as in most virtual machines.
+    pub driver: Option<String>,
+    /// The governors supported by all the CPUs, e.g. "performance" and
+    /// "powersave".
+    pub available: Vec<String>,
+    /// The governor of each online CPU supporting frequency scaling.
+    pub cpus: Vec<CpuGovernor>,
+    /// The governor set at boot, or None if roxy does not set it.
+    pub persistent: Option<String>,
+}
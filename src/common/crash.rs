@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// Report of a panic in roxy, which is kept until it is cleared.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct CrashReport {
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+    /// Version of roxy which panicked.
+    pub version: String,
+    /// Kind of the task being executed, e.g. `Interface::Set`, or None if
+    /// roxy panicked before executing a task.
+    pub task: Option<String>,
+    pub message: String,
+    /// Source location of the panic, e.g. `src/root/ifconfig.rs:42:9`.
+    pub location: Option<String>,
+    pub backtrace: String,
+}
@@ -0,0 +1,40 @@
+use std::net::IpAddr;
+
+use serde::{Deserialize, Serialize};
+
+/// Result of resolving a name with the system resolver.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DnsLookup {
+    pub name: String,
+    /// The canonical name if `name` is an alias: the name at the end of its
+    /// CNAME chain, or the official name of an alias in `/etc/hosts`. The
+    /// system resolver does not report the names in between, so a chain of
+    /// several CNAMEs shows only its last name.
+    pub canonical_name: Option<String>,
+    /// Addresses of the name, both v4 and v6. Empty if the name is not found.
+    pub addresses: Vec<IpAddr>,
+    pub resolver: DnsResolver,
+}
+
+/// Resolver configuration which answered a [`DnsLookup`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct DnsResolver {
+    /// Nameservers in `/etc/resolv.conf`.
+    pub nameservers: Vec<IpAddr>,
+    /// Search domains in `/etc/resolv.conf`.
+    pub search: Vec<String>,
+    /// Whether the queries go through the stub resolver of systemd-resolved.
+    pub stub: bool,
+    /// Servers to which systemd-resolved forwards the queries. Empty if
+    /// systemd-resolved is not running.
+    pub upstream: Vec<UpstreamDns>,
+}
+
+/// DNS servers of systemd-resolved for a link, or for all links.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct UpstreamDns {
+    /// "Global", or the name of the interface.
+    pub link: String,
+    /// e.g. "10.0.0.1", "1.1.1.1#cloudflare-dns.com".
+    pub servers: Vec<String>,
+}
@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// Errors of a mounted filesystem, recorded by the filesystem itself and
+/// logged by the kernel.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct FilesystemHealth {
+    /// e.g. "/dev/sda1"
+    pub device: String,
+    pub mount_point: String,
+    /// e.g. "ext4"
+    pub fs_type: String,
+    /// The state in the superblock, e.g. "clean with errors". None if it is
+    /// not ext2/3/4.
+    pub state: Option<String>,
+    /// Number of errors recorded in the superblock.
+    pub error_count: u64,
+    /// Seconds since the Unix epoch of the first error recorded.
+    pub first_error: Option<i64>,
+    /// Seconds since the Unix epoch of the last error recorded.
+    pub last_error: Option<i64>,
+    /// Number of filesystem errors of the device in the kernel log.
+    pub kernel_errors: u64,
+}
+
+impl FilesystemHealth {
+    /// True if the filesystem recorded an error, or the kernel logged one.
+    #[must_use]
+    pub fn has_errors(&self) -> bool {
+        self.error_count > 0
+            || self.kernel_errors > 0
+            || self
+                .state
+                .as_ref()
+                .is_some_and(|state| state.contains("error"))
+    }
+}
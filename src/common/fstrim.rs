@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/// The space discarded from a mounted filesystem by fstrim.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TrimmedFilesystem {
+    /// e.g. "/data"
+    pub mount_point: String,
+    /// e.g. "/dev/sda1". None if fstrim does not report it.
+    pub device: Option<String>,
+    /// Bytes discarded.
+    pub trimmed: u64,
+}
+
+/// A run of fstrim on all the mounted filesystems supporting it.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TrimRun {
+    /// Seconds since the Unix epoch when the run started.
+    pub time: i64,
+    /// False if fstrim failed on any filesystem.
+    pub success: bool,
+    pub filesystems: Vec<TrimmedFilesystem>,
+    /// The errors reported by fstrim, e.g. "fstrim: /boot: the discard
+    /// operation is not supported".
+    pub errors: Vec<String>,
+}
+
+/// The periodic fstrim timer and the last run of fstrim on demand.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TrimStatus {
+    /// True if `fstrim.timer` is enabled at boot.
+    pub timer_enabled: bool,
+    /// True if `fstrim.timer` is active, waiting for its next run.
+    pub timer_active: bool,
+    /// The last run requested through roxy. Runs of the timer are not
+    /// included.
+    pub last_run: Option<TrimRun>,
+}
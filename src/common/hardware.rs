@@ -0,0 +1,15 @@
+use serde::{Deserialize, Serialize};
+
+/// Hardware inventory read from the DMI table, and the BMC if available.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct HardwareInfo {
+    pub manufacturer: String,
+    pub product_name: String,
+    pub serial_number: String,
+    pub bios_vendor: String,
+    pub bios_version: String,
+    /// The BIOS release date as reported by the firmware, e.g. "03/14/2023".
+    pub bios_date: String,
+    /// BMC firmware revision reported by ipmitool.
+    pub bmc_firmware: Option<String>,
+}
@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+use super::Subsystem;
+
+/// A saved configuration of a subsystem. Only the subsystems in [`Subsystem`]
+/// have a history.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct HistoryEntry {
+    pub subsystem: Subsystem,
+    /// Identifier used to revert to this configuration.
+    pub id: i64,
+    /// The time when the configuration was saved, in RFC 3339 format.
+    pub time: String,
+    /// Line-based diff from the previous configuration of the subsystem.
+    pub diff: String,
+}
@@ -0,0 +1,13 @@
+use serde::{Deserialize, Serialize};
+
+/// Short and fully-qualified names of the host.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct HostnameInfo {
+    /// The short name, e.g. "sensor1".
+    pub hostname: String,
+    /// The domain name, e.g. "example.com".
+    pub domain: Option<String>,
+    /// The fully-qualified name, e.g. "sensor1.example.com". It is the same as
+    /// the short name if the domain is not set.
+    pub fqdn: String,
+}
@@ -0,0 +1,19 @@
+use serde::{Deserialize, Serialize};
+
+use super::HostnameInfo;
+
+/// Identity and versions of a node, collected in a single request.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct NodeInfo {
+    pub hostname: HostnameInfo,
+    pub os_version: String,
+    pub product_version: String,
+    /// How long the system has been running, in seconds.
+    pub uptime: u64,
+    /// Release of the running kernel, e.g. "6.8.0-45-generic".
+    pub kernel: String,
+    /// e.g. `x86_64`, `aarch64`.
+    pub arch: String,
+    /// Version of the roxy executable that answered the request.
+    pub roxy_version: String,
+}
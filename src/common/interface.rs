@@ -2,7 +2,14 @@ use std::{collections::HashMap, fmt};
 
 use serde::{Deserialize, Serialize};
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+/// The routing domain which makes systemd-resolved send every query to the
+/// nameservers of an interface.
+const DEFAULT_DNS_ROUTE: &str = "~.";
+
+/// Destinations of a default route in netplan.
+const DEFAULT_ROUTES: [&str; 2] = ["default", "0.0.0.0/0"];
+
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
 pub struct Nic {
     #[serde(skip_serializing_if = "Option::is_none")]
     pub addresses: Option<Vec<String>>,
@@ -14,6 +21,55 @@ pub struct Nic {
     pub nameservers: Option<HashMap<String, Vec<String>>>,
     #[serde(skip_serializing_if = "Option::is_none")]
     pub optional: Option<bool>,
+    #[serde(
+        default,
+        rename = "activation-mode",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub activation_mode: Option<ActivationMode>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub routes: Option<Vec<Route>>,
+    #[serde(
+        default,
+        rename = "routing-policy",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub routing_policy: Option<Vec<RoutingRule>>,
+}
+
+/// A static route in netplan.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Route {
+    pub to: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub via: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub metric: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub table: Option<u32>,
+}
+
+/// A routing policy rule in netplan, which selects the routing table for
+/// packets matching it.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+pub struct RoutingRule {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub from: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub to: Option<String>,
+    pub table: u32,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub priority: Option<u32>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub mark: Option<u32>,
+}
+
+impl Route {
+    /// Returns whether the route is a default route.
+    #[must_use]
+    pub fn is_default(&self) -> bool {
+        DEFAULT_ROUTES.contains(&self.to.as_str())
+    }
 }
 
 impl fmt::Display for Nic {
@@ -27,6 +83,70 @@ impl fmt::Display for Nic {
 }
 
 impl Nic {
+    /// Makes the nameservers of the interface the default route of DNS
+    /// queries, or removes it. The nameservers of the other interfaces are
+    /// used only for their search domains while an interface is the default.
+    pub fn set_default_dns_route(&mut self, default: bool) {
+        if let Some(search) = self
+            .nameservers
+            .as_mut()
+            .and_then(|nm| nm.get_mut("search"))
+        {
+            search.retain(|domain| domain != DEFAULT_DNS_ROUTE);
+        }
+        if default {
+            self.nameservers
+                .get_or_insert_with(HashMap::new)
+                .entry("search".to_string())
+                .or_default()
+                .push(DEFAULT_DNS_ROUTE.to_string());
+        }
+    }
+
+    /// Returns the default gateway of the interface, set either by
+    /// `gateway4` or by a default route, and the metric of the route.
+    #[must_use]
+    pub fn default_gateway(&self) -> Option<(&str, Option<u32>)> {
+        if let Some(gateway) = &self.gateway4 {
+            return Some((gateway, None));
+        }
+        self.routes.as_ref()?.iter().find_map(|route| {
+            if route.is_default() && route.table.is_none() {
+                route.via.as_deref().map(|via| (via, route.metric))
+            } else {
+                None
+            }
+        })
+    }
+
+    /// Returns the gateways of all default routes of the interface in the
+    /// main table, set by `gateway4` or by routes, and their metrics.
+    #[must_use]
+    pub fn default_gateways(&self) -> Vec<(&str, Option<u32>)> {
+        let mut gateways = self
+            .gateway4
+            .iter()
+            .map(|gateway| (gateway.as_str(), None))
+            .collect::<Vec<_>>();
+        gateways.extend(self.routes.iter().flatten().filter_map(|route| {
+            if route.is_default() && route.table.is_none() {
+                route.via.as_deref().map(|via| (via, route.metric))
+            } else {
+                None
+            }
+        }));
+        gateways
+    }
+
+    /// Returns whether the interface has nameservers.
+    #[must_use]
+    pub fn has_nameservers(&self) -> bool {
+        self.nameservers
+            .as_ref()
+            .and_then(|nm| nm.get("addresses"))
+            .is_some_and(|addrs| !addrs.is_empty())
+    }
+
     #[must_use]
     pub fn new(
         addresses: Option<Vec<String>>,
@@ -41,16 +161,172 @@ impl Nic {
             gateway4,
             nameservers,
             optional,
+            activation_mode: None,
+            routes: None,
+            routing_policy: None,
+        }
+    }
+
+    /// Sets how networkd brings up the interface.
+    #[must_use]
+    pub fn activation_mode(mut self, mode: ActivationMode) -> Self {
+        self.activation_mode = Some(mode);
+        self
+    }
+}
+
+/// How networkd brings up an interface, which is `activation-mode` in netplan.
+#[derive(Debug, Clone, Copy, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+#[serde(rename_all = "lowercase")]
+pub enum ActivationMode {
+    /// The interface is not brought up by networkd, but its configuration is
+    /// applied when it is brought up manually.
+    Manual,
+    /// The interface is kept down.
+    Off,
+}
+
+impl fmt::Display for ActivationMode {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ActivationMode::Manual => write!(f, "manual"),
+            ActivationMode::Off => write!(f, "off"),
         }
     }
 }
 
-#[derive(Debug, Clone, Deserialize, Serialize)]
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
 pub struct NicOutput {
     pub addresses: Option<Vec<String>>,
     pub dhcp4: Option<bool>,
     pub gateway4: Option<String>,
+    /// Metric of the default route through `gateway4`. Several interfaces
+    /// can have a gateway if each has a different metric, and the lowest is
+    /// preferred.
+    pub gateway_metric: Option<u32>,
     pub nameservers: Option<Vec<String>>,
+    /// Domains appended to a name which is not fully qualified in DNS
+    /// queries.
+    pub search_domains: Option<Vec<String>>,
+    /// Priority of the nameservers over those of the other interfaces. The
+    /// lowest is used first.
+    pub dns_priority: Option<u32>,
+    /// Whether booting does not wait for the interface to be online, e.g. for
+    /// a capture interface which may be unplugged.
+    pub optional: Option<bool>,
+    pub activation_mode: Option<ActivationMode>,
+    /// Static routes other than the default route through `gateway4`, e.g.
+    /// routes in another routing table.
+    pub routes: Option<Vec<RouteOutput>>,
+    /// Rules selecting the routing table, e.g. to send the traffic from an
+    /// address through another uplink.
+    pub routing_policy: Option<Vec<RoutingRuleOutput>>,
+    /// Human label of the interface, e.g. "WAN" or "Capture-A", which can be
+    /// used in place of its name in interface requests. It is kept when the
+    /// interface is set without a label.
+    pub label: Option<String>,
+}
+
+/// The settings of an interface exchanged by `Interface` requests: those of
+/// [`NicOutput`] before the others were added. Bincode encodes a struct
+/// without field names, so the layout is kept for a library or roxy which
+/// knows only these settings. `InterfaceV2` requests exchange [`NicOutput`].
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub struct NicOutputV1 {
+    pub addresses: Option<Vec<String>>,
+    pub dhcp4: Option<bool>,
+    pub gateway4: Option<String>,
+    pub nameservers: Option<Vec<String>>,
+}
+
+impl From<NicOutputV1> for NicOutput {
+    fn from(nic: NicOutputV1) -> Self {
+        NicOutput::new(nic.addresses, nic.dhcp4, nic.gateway4, nic.nameservers)
+    }
+}
+
+impl From<NicOutput> for NicOutputV1 {
+    fn from(nic: NicOutput) -> Self {
+        NicOutputV1 {
+            addresses: nic.addresses,
+            dhcp4: nic.dhcp4,
+            gateway4: nic.gateway4,
+            nameservers: nic.nameservers,
+        }
+    }
+}
+
+/// A static route of an interface.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RouteOutput {
+    /// Destination network, or "default".
+    pub to: String,
+    pub via: Option<String>,
+    pub metric: Option<u32>,
+    /// Routing table. The main table if None.
+    pub table: Option<u32>,
+}
+
+impl RouteOutput {
+    #[must_use]
+    pub fn to(&self) -> Route {
+        Route {
+            to: self.to.clone(),
+            via: self.via.clone(),
+            metric: self.metric,
+            table: self.table,
+        }
+    }
+
+    #[must_use]
+    pub fn from(route: &Route) -> Self {
+        RouteOutput {
+            to: route.to.clone(),
+            via: route.via.clone(),
+            metric: route.metric,
+            table: route.table,
+        }
+    }
+}
+
+/// A rule selecting the routing table for packets matching the source
+/// network, the destination network, and the firewall mark given.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq, Eq)]
+#[cfg_attr(feature = "arbitrary", derive(arbitrary::Arbitrary))]
+pub struct RoutingRuleOutput {
+    pub from: Option<String>,
+    pub to: Option<String>,
+    pub table: u32,
+    /// Order of the rule. The lowest is evaluated first.
+    pub priority: Option<u32>,
+    pub mark: Option<u32>,
+}
+
+impl RoutingRuleOutput {
+    #[must_use]
+    pub fn to(&self) -> RoutingRule {
+        RoutingRule {
+            from: self.from.clone(),
+            to: self.to.clone(),
+            table: self.table,
+            priority: self.priority,
+            mark: self.mark,
+        }
+    }
+
+    #[must_use]
+    pub fn from(rule: &RoutingRule) -> Self {
+        RoutingRuleOutput {
+            from: rule.from.clone(),
+            to: rule.to.clone(),
+            table: rule.table,
+            priority: rule.priority,
+            mark: rule.mark,
+        }
+    }
 }
 
 impl fmt::Display for NicOutput {
@@ -70,10 +346,50 @@ impl fmt::Display for NicOutput {
         } else {
             writeln!(f, "\tgateway4: -")?;
         }
+        if let Some(v) = self.gateway_metric {
+            writeln!(f, "\tgateway metric: {v}")?;
+        } else {
+            writeln!(f, "\tgateway metric: -")?;
+        }
         if let Some(v) = &self.nameservers {
-            write!(f, "\tnameservers: {v:?}")
+            writeln!(f, "\tnameservers: {v:?}")?;
+        } else {
+            writeln!(f, "\tnameservers: -")?;
+        }
+        if let Some(v) = &self.search_domains {
+            writeln!(f, "\tsearch domains: {v:?}")?;
         } else {
-            write!(f, "\tnameservers: -")
+            writeln!(f, "\tsearch domains: -")?;
+        }
+        if let Some(v) = self.dns_priority {
+            writeln!(f, "\tdns priority: {v}")?;
+        } else {
+            writeln!(f, "\tdns priority: -")?;
+        }
+        if let Some(v) = self.optional {
+            writeln!(f, "\toptional: {v}")?;
+        } else {
+            writeln!(f, "\toptional: -")?;
+        }
+        if let Some(v) = self.activation_mode {
+            writeln!(f, "\tactivation-mode: {v}")?;
+        } else {
+            writeln!(f, "\tactivation-mode: -")?;
+        }
+        if let Some(v) = &self.routes {
+            writeln!(f, "\troutes: {v:?}")?;
+        } else {
+            writeln!(f, "\troutes: -")?;
+        }
+        if let Some(v) = &self.routing_policy {
+            writeln!(f, "\trouting-policy: {v:?}")?;
+        } else {
+            writeln!(f, "\trouting-policy: -")?;
+        }
+        if let Some(v) = &self.label {
+            write!(f, "\tlabel: {v}")
+        } else {
+            write!(f, "\tlabel: -")
         }
     }
 }
@@ -90,26 +406,125 @@ impl NicOutput {
             addresses,
             dhcp4,
             gateway4,
+            gateway_metric: None,
             nameservers,
+            search_domains: None,
+            dns_priority: None,
+            optional: None,
+            activation_mode: None,
+            routes: None,
+            routing_policy: None,
+            label: None,
         }
     }
 
+    /// Sets the metric of the default route through `gateway4`.
+    #[must_use]
+    pub fn gateway_metric(mut self, metric: u32) -> Self {
+        self.gateway_metric = Some(metric);
+        self
+    }
+
+    /// Sets the DNS search domains.
+    #[must_use]
+    pub fn search_domains(mut self, domains: Vec<String>) -> Self {
+        self.search_domains = Some(domains);
+        self
+    }
+
+    /// Sets the priority of the nameservers over those of the other
+    /// interfaces. The lowest is used first.
+    #[must_use]
+    pub fn dns_priority(mut self, priority: u32) -> Self {
+        self.dns_priority = Some(priority);
+        self
+    }
+
+    /// Sets the static routes other than the default route through
+    /// `gateway4`.
+    #[must_use]
+    pub fn routes(mut self, routes: Vec<RouteOutput>) -> Self {
+        self.routes = Some(routes);
+        self
+    }
+
+    /// Sets the rules selecting the routing table.
+    #[must_use]
+    pub fn routing_policy(mut self, rules: Vec<RoutingRuleOutput>) -> Self {
+        self.routing_policy = Some(rules);
+        self
+    }
+
+    /// Sets the human label of the interface, which consists of up to 32
+    /// letters, digits, '-', '_', and '.'.
+    #[must_use]
+    pub fn label(mut self, label: &str) -> Self {
+        self.label = Some(label.to_string());
+        self
+    }
+
+    /// Sets whether booting does not wait for the interface to be online.
+    #[must_use]
+    pub fn optional(mut self, optional: bool) -> Self {
+        self.optional = Some(optional);
+        self
+    }
+
+    /// Sets how networkd brings up the interface.
+    #[must_use]
+    pub fn activation_mode(mut self, mode: ActivationMode) -> Self {
+        self.activation_mode = Some(mode);
+        self
+    }
+
     #[must_use]
     pub fn to(&self) -> Nic {
-        let nameservers = if let Some(nm) = &self.nameservers {
+        let nameservers = if self.nameservers.is_some() || self.search_domains.is_some() {
             let mut m = HashMap::new();
-            m.insert("addresses".to_string(), nm.clone());
-            m.insert("search".to_string(), Vec::new());
+            if let Some(nm) = &self.nameservers {
+                m.insert("addresses".to_string(), nm.clone());
+            }
+            m.insert(
+                "search".to_string(),
+                self.search_domains.clone().unwrap_or_default(),
+            );
             Some(m)
         } else {
             None
         };
+        // A gateway with a metric is written as a default route, since
+        // gateway4 has no metric.
+        let (gateway4, mut routes) = match (&self.gateway4, self.gateway_metric) {
+            (Some(gateway), Some(metric)) => (
+                None,
+                vec![Route {
+                    to: DEFAULT_ROUTES[0].to_string(),
+                    via: Some(gateway.clone()),
+                    metric: Some(metric),
+                    table: None,
+                }],
+            ),
+            (gateway, _) => (gateway.clone(), Vec::new()),
+        };
+        if let Some(others) = &self.routes {
+            routes.extend(others.iter().map(RouteOutput::to));
+        }
         Nic {
             addresses: self.addresses.clone(),
             dhcp4: self.dhcp4,
-            gateway4: self.gateway4.clone(),
+            gateway4,
             nameservers,
-            optional: None,
+            optional: self.optional,
+            activation_mode: self.activation_mode,
+            routes: if routes.is_empty() {
+                None
+            } else {
+                Some(routes)
+            },
+            routing_policy: self
+                .routing_policy
+                .as_ref()
+                .map(|rules| rules.iter().map(RoutingRuleOutput::to).collect()),
         }
     }
 
@@ -122,11 +537,66 @@ impl NicOutput {
                 None
             }
         };
+        // An empty list is written when only addresses are set.
+        let search_domains = nic
+            .nameservers
+            .as_ref()
+            .and_then(|nm| nm.get("search"))
+            .map(|search| {
+                search
+                    .iter()
+                    .filter(|domain| *domain != DEFAULT_DNS_ROUTE)
+                    .cloned()
+                    .collect::<Vec<_>>()
+            })
+            .filter(|search| !search.is_empty());
+        let (gateway4, gateway_metric) = match nic.default_gateway() {
+            Some((gateway, metric)) => (Some(gateway.to_string()), metric),
+            None => (None, None),
+        };
+        // The default route giving the gateway is not one of the others.
+        let mut routes = nic.routes.clone().unwrap_or_default();
+        if nic.gateway4.is_none() {
+            if let Some(i) = routes.iter().position(|route| {
+                route.is_default() && route.table.is_none() && route.via.is_some()
+            }) {
+                routes.remove(i);
+            }
+        }
+        let routes = if routes.is_empty() {
+            None
+        } else {
+            Some(routes.iter().map(RouteOutput::from).collect())
+        };
         NicOutput {
             addresses: nic.addresses.clone(),
             dhcp4: nic.dhcp4,
-            gateway4: nic.gateway4.clone(),
+            gateway4,
+            gateway_metric,
             nameservers,
+            search_domains,
+            dns_priority: None,
+            optional: nic.optional,
+            activation_mode: nic.activation_mode,
+            routes,
+            routing_policy: nic
+                .routing_policy
+                .as_ref()
+                .map(|rules| rules.iter().map(RoutingRuleOutput::from).collect()),
+            label: None,
         }
     }
 }
+
+/// Returns the interfaces with nameservers in the order their nameservers are
+/// used, which is the order of [`NicOutput::dns_priority`]. Interfaces without
+/// a priority come last in the given order.
+#[must_use]
+pub fn nameserver_order(interfaces: &[(String, NicOutput)]) -> Vec<&str> {
+    let mut order = interfaces
+        .iter()
+        .filter(|(_, nic)| nic.nameservers.as_ref().is_some_and(|nm| !nm.is_empty()))
+        .collect::<Vec<_>>();
+    order.sort_by_key(|(_, nic)| nic.dns_priority.unwrap_or(u32::MAX));
+    order.into_iter().map(|(name, _)| name.as_str()).collect()
+}
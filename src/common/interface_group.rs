@@ -0,0 +1,29 @@
+use serde::{Deserialize, Serialize};
+
+use super::{CaptureSettings, NicOutput};
+
+/// Settings applied to each interface of a group, e.g. the capture ports of
+/// an appliance.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct GroupSettings {
+    /// The configuration set to each interface. It cannot have a label, which
+    /// is unique to an interface.
+    pub nic: Option<NicOutput>,
+    /// The capture settings, such as the offloads, of each interface.
+    pub capture: Option<CaptureSettings>,
+}
+
+/// Result of applying [`GroupSettings`] to an interface.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct InterfaceResult {
+    pub ifname: String,
+    /// Why the settings are not applied, or None if they are.
+    pub error: Option<String>,
+}
+
+impl InterfaceResult {
+    #[must_use]
+    pub fn succeeded(&self) -> bool {
+        self.error.is_none()
+    }
+}
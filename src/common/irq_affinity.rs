@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// The CPUs to which an IRQ of a NIC is delivered.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IrqAffinity {
+    /// e.g. 42
+    pub irq: u32,
+    /// The name of the IRQ in `/proc/interrupts`, e.g. "eth0-TxRx-0".
+    pub name: Option<String>,
+    /// The CPUs the IRQ may be delivered to, e.g. "0-3,8".
+    pub cpus: String,
+    /// The CPUs the IRQ is actually delivered to, if the kernel reports them.
+    pub effective_cpus: Option<String>,
+}
+
+/// The IRQs of a NIC and the CPUs handling them. With receive side scaling,
+/// each receive queue has its own IRQ, so the CPU handling a queue is the
+/// one its IRQ is delivered to.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct IrqLayout {
+    /// e.g. "eth0"
+    pub interface: String,
+    /// The NUMA node of the NIC, or None if the system has a single node.
+    pub numa_node: Option<u32>,
+    /// The CPUs of the NUMA node of the NIC, e.g. "0-7", which handle its
+    /// IRQs without crossing nodes.
+    pub local_cpus: Option<String>,
+    /// The number of receive queues.
+    pub rx_queues: usize,
+    /// The MSI-X IRQs of the NIC, in the order of their numbers.
+    pub irqs: Vec<IrqAffinity>,
+    /// The CPUs to which the IRQs are pinned at boot, or None if roxy does
+    /// not pin them.
+    pub pinned: Option<Vec<u32>>,
+}
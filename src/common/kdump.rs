@@ -0,0 +1,17 @@
+use serde::{Deserialize, Serialize};
+
+/// Kernel crash dump configuration and status.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct KdumpStatus {
+    /// True if kdump is enabled in `/etc/default/kdump-tools`.
+    pub enabled: bool,
+    /// True if a crash kernel is loaded, so a kernel panic produces a dump.
+    pub armed: bool,
+    /// The crash kernel reservation of the running kernel, e.g.
+    /// "512M-:192M".
+    pub crashkernel: Option<String>,
+    /// The crash kernel reservation applied after the next reboot.
+    pub configured_crashkernel: Option<String>,
+    /// Paths of the vmcore files under `/var/crash`.
+    pub vmcores: Vec<String>,
+}
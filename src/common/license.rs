@@ -0,0 +1,36 @@
+use serde::{Deserialize, Serialize};
+
+/// Entitlements granted by a product license.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct License {
+    pub id: String,
+    pub customer: String,
+    /// Date of issue, e.g. "2026-01-01".
+    pub issued: String,
+    /// The last day the license is valid, or None if it does not expire.
+    #[serde(default)]
+    pub expires: Option<String>,
+    /// Licensed features, e.g. "review", "crusher".
+    #[serde(default)]
+    pub features: Vec<String>,
+    /// Maximum number of nodes, or None if unlimited.
+    #[serde(default)]
+    pub max_nodes: Option<u32>,
+}
+
+/// The installed license and whether it is valid.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LicenseStatus {
+    pub license: License,
+    /// Whether the license is signed by the key of the product.
+    pub signature_valid: bool,
+    pub expired: bool,
+}
+
+impl LicenseStatus {
+    /// Returns true if the license is signed and not expired.
+    #[must_use]
+    pub fn is_valid(&self) -> bool {
+        self.signature_valid && !self.expired
+    }
+}
@@ -0,0 +1,136 @@
+use anyhow::{anyhow, Result};
+use chrono::NaiveTime;
+use serde::{Deserialize, Serialize};
+
+use super::{Node, NodeRequest, SubCommand};
+
+const TIME_FORMAT: &str = "%H:%M";
+
+/// Daily hours in local time when a deferred operation may run, e.g. from
+/// "02:00" to "04:00". A window ending earlier than it starts crosses
+/// midnight.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MaintenanceWindow {
+    pub start: String,
+    pub end: String,
+}
+
+impl MaintenanceWindow {
+    /// Returns true if the time is in the window, including its start and
+    /// excluding its end.
+    ///
+    /// # Errors
+    ///
+    /// * If `start` or `end` is not in the form of "HH:MM", or they are the
+    ///   same, then an error is returned.
+    pub fn contains(&self, time: NaiveTime) -> Result<bool> {
+        let start = NaiveTime::parse_from_str(&self.start, TIME_FORMAT)
+            .map_err(|_| anyhow!("invalid start of window: {}", self.start))?;
+        let end = NaiveTime::parse_from_str(&self.end, TIME_FORMAT)
+            .map_err(|_| anyhow!("invalid end of window: {}", self.end))?;
+        if start == end {
+            return Err(anyhow!("empty window: {}-{}", self.start, self.end));
+        }
+        Ok(if start < end {
+            start <= time && time < end
+        } else {
+            start <= time || time < end
+        })
+    }
+}
+
+/// Progress of a deferred operation.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum OperationStatus {
+    /// Waiting for its window.
+    Queued,
+    /// Executed successfully.
+    Executed,
+    /// Executed, but failed with the error.
+    Failed(String),
+    /// Its window did not come before it expired.
+    Expired,
+    /// Cancelled before it was executed.
+    Cancelled,
+}
+
+/// A request deferred to a maintenance window.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DeferredOperation {
+    pub id: u64,
+    #[serde(with = "as_json")]
+    pub request: NodeRequest,
+    pub window: MaintenanceWindow,
+    /// The time when it was queued, in RFC 3339 format.
+    pub queued: String,
+    /// The time after which it is not executed, in RFC 3339 format, or None
+    /// if it waits for a window forever.
+    pub expires: Option<String>,
+    pub status: OperationStatus,
+    /// The time when its status last changed from `Queued`, in RFC 3339
+    /// format.
+    pub updated: Option<String>,
+}
+
+/// Returns true if the request may be deferred to a maintenance window:
+/// reboot, power off, restarting services, and updating compose stacks.
+#[must_use]
+pub fn is_deferrable(request: &NodeRequest) -> bool {
+    matches!(
+        request.kind,
+        Node::PowerOff
+            | Node::Reboot
+            | Node::Service(SubCommand::Set | SubCommand::Enable | SubCommand::Update)
+            | Node::ServiceGroup(SubCommand::Update)
+            | Node::Compose(SubCommand::Update)
+    )
+}
+
+// Serializes a request in JSON, where a task kind is its name, since bincode
+// encodes a task kind by its index in `Node`, which differs between versions.
+mod as_json {
+    use serde::{de, ser, Deserialize, Deserializer, Serializer};
+
+    use super::NodeRequest;
+
+    pub(super) fn serialize<S: Serializer>(
+        request: &NodeRequest,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        let json = serde_json::to_string(request).map_err(ser::Error::custom)?;
+        serializer.serialize_str(&json)
+    }
+
+    pub(super) fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<NodeRequest, D::Error> {
+        let json = String::deserialize(deserializer)?;
+        serde_json::from_str(&json).map_err(de::Error::custom)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn windows() {
+        let at = |s| NaiveTime::parse_from_str(s, TIME_FORMAT).unwrap();
+        let window = |start: &str, end: &str| MaintenanceWindow {
+            start: start.to_string(),
+            end: end.to_string(),
+        };
+        let night = window("02:00", "04:00");
+        assert!(night.contains(at("02:00")).unwrap());
+        assert!(night.contains(at("03:59")).unwrap());
+        assert!(!night.contains(at("04:00")).unwrap());
+
+        let midnight = window("23:00", "01:00");
+        assert!(midnight.contains(at("23:30")).unwrap());
+        assert!(midnight.contains(at("00:30")).unwrap());
+        assert!(!midnight.contains(at("12:00")).unwrap());
+
+        assert!(window("02:00", "02:00").contains(at("02:00")).is_err());
+        assert!(window("2am", "04:00").contains(at("02:00")).is_err());
+    }
+}
@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// An interface in a named network namespace, e.g. a capture NIC moved into a
+/// namespace of its own, which is not listed with the interfaces of roxy.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct NetnsInterface {
+    /// The name of the namespace, as in `ip netns list`.
+    pub namespace: String,
+    pub name: String,
+    /// Whether the interface is administratively up.
+    pub up: bool,
+    /// The operational state, e.g. "UP" or "DOWN".
+    pub operstate: String,
+    pub mac: Option<String>,
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub rx_dropped: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+}
+
+/// A short packet capture with tcpdump on an interface in a named network
+/// namespace, to check that traffic reaches the interface.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct NetnsCapture {
+    pub namespace: String,
+    pub interface: String,
+    /// 1 to 1000. The capture stops after this many packets.
+    pub count: u32,
+    /// 1 to 60. The capture stops after this many seconds, even if fewer
+    /// packets arrived.
+    pub timeout_secs: u64,
+    /// A pcap filter expression, e.g. "tcp port 443".
+    pub filter: Option<String>,
+}
@@ -0,0 +1,98 @@
+use serde::{Deserialize, Serialize};
+
+/// Maximum number of items roxy returns in a page.
+pub const MAX_PAGE_LIMIT: u64 = 10_000;
+
+/// A range of a list to get from roxy.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PageRequest {
+    /// Number of items to skip.
+    pub offset: u64,
+    /// Maximum number of items, at most [`MAX_PAGE_LIMIT`].
+    pub limit: u64,
+}
+
+impl PageRequest {
+    #[must_use]
+    pub fn new(offset: u64, limit: u64) -> Self {
+        PageRequest { offset, limit }
+    }
+
+    /// Returns the request for the page following this one.
+    #[must_use]
+    pub fn next(self) -> Self {
+        PageRequest {
+            offset: self.offset.saturating_add(self.limit),
+            ..self
+        }
+    }
+
+    fn limit(self) -> u64 {
+        self.limit.min(MAX_PAGE_LIMIT)
+    }
+}
+
+/// A part of a list returned by roxy.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct Page<T> {
+    pub items: Vec<T>,
+    /// Offset of the first item in the list.
+    pub offset: u64,
+    /// Number of items in the list, or None if it is not known in advance,
+    /// e.g. for log lines.
+    pub total: Option<u64>,
+}
+
+impl<T> Page<T> {
+    /// Returns the requested part of the list.
+    #[must_use]
+    pub fn of(items: Vec<T>, req: PageRequest) -> Self {
+        let total = items.len() as u64;
+        let items = items
+            .into_iter()
+            .skip(usize::try_from(req.offset).unwrap_or(usize::MAX))
+            .take(usize::try_from(req.limit()).unwrap_or(usize::MAX))
+            .collect();
+        Page {
+            items,
+            offset: req.offset,
+            total: Some(total),
+        }
+    }
+
+    /// Returns true if there may be items after this page.
+    #[must_use]
+    pub fn has_more(&self) -> bool {
+        match self.total {
+            Some(total) => self.offset + (self.items.len() as u64) < total,
+            None => !self.items.is_empty(),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pages() {
+        let items = (0..25).collect::<Vec<_>>();
+        let req = PageRequest::new(0, 10);
+        let first = Page::of(items.clone(), req);
+        assert_eq!(first.items, (0..10).collect::<Vec<_>>());
+        assert_eq!(first.total, Some(25));
+        assert!(first.has_more());
+
+        let last = Page::of(items.clone(), req.next().next());
+        assert_eq!(last.items, (20..25).collect::<Vec<_>>());
+        assert_eq!(last.offset, 20);
+        assert!(!last.has_more());
+
+        let beyond = Page::of(items.clone(), PageRequest::new(u64::MAX, 10));
+        assert!(beyond.items.is_empty());
+        assert!(!beyond.has_more());
+
+        let capped = Page::of(vec![0; 20_000], PageRequest::new(0, u64::MAX));
+        assert_eq!(capped.items.len() as u64, MAX_PAGE_LIMIT);
+    }
+}
@@ -0,0 +1,58 @@
+use serde::{Deserialize, Serialize};
+
+use super::{NODE_KINDS, PROTOCOL_VERSION};
+
+/// Response of roxy to a ping: its version and the kinds of request it
+/// handles.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct PingInfo {
+    pub version: String,
+    /// Hash of the protocol version and the names of the request kinds,
+    /// which is the same between builds handling the same kinds with the
+    /// same layouts.
+    pub capability_hash: u64,
+}
+
+impl PingInfo {
+    /// Returns the ping response of this build.
+    #[must_use]
+    pub fn local() -> Self {
+        PingInfo {
+            version: env!("CARGO_PKG_VERSION").to_string(),
+            capability_hash: capability_hash(PROTOCOL_VERSION, &NODE_KINDS),
+        }
+    }
+
+    /// Returns true if roxy handles the same kinds of request, with the same
+    /// layouts, as this build.
+    #[must_use]
+    pub fn is_compatible(&self) -> bool {
+        self.capability_hash == capability_hash(PROTOCOL_VERSION, &NODE_KINDS)
+    }
+}
+
+// FNV-1a, which is stable across builds and platforms unlike `DefaultHasher`.
+fn capability_hash(version: u32, kinds: &[&str]) -> u64 {
+    const OFFSET_BASIS: u64 = 0xcbf2_9ce4_8422_2325;
+    const PRIME: u64 = 0x0000_0100_0000_01b3;
+    version
+        .to_le_bytes()
+        .into_iter()
+        .chain(kinds.iter().flat_map(|kind| kind.bytes().chain([b'\n'])))
+        .fold(OFFSET_BASIS, |hash, byte| {
+            (hash ^ u64::from(byte)).wrapping_mul(PRIME)
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn hash() {
+        assert_eq!(capability_hash(1, &["a"]), capability_hash(1, &["a"]));
+        assert_ne!(capability_hash(1, &["ab"]), capability_hash(1, &["a", "b"]));
+        assert_ne!(capability_hash(1, &["a"]), capability_hash(2, &["a"]));
+        assert!(PingInfo::local().is_compatible());
+    }
+}
@@ -0,0 +1,20 @@
+use std::fmt;
+
+use serde::{Deserialize, Serialize};
+
+/// Result of a check of the environment roxy runs in.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct PreflightCheck {
+    /// What is checked, e.g. "binary netplan".
+    pub name: String,
+    pub passed: bool,
+    /// The reason of the failure, or what is found if passed.
+    pub detail: String,
+}
+
+impl fmt::Display for PreflightCheck {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let result = if self.passed { "PASS" } else { "FAIL" };
+        write!(f, "[{result}] {}: {}", self.name, self.detail)
+    }
+}
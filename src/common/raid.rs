@@ -0,0 +1,50 @@
+use serde::{Deserialize, Serialize};
+
+/// Status of software and hardware RAID arrays.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct RaidStatus {
+    /// Software RAID arrays in `/proc/mdstat`.
+    pub arrays: Vec<MdArray>,
+    /// Virtual drives of hardware RAID controllers managed by storcli.
+    pub volumes: Vec<RaidVolume>,
+}
+
+impl RaidStatus {
+    /// True if any array or virtual drive is degraded.
+    #[must_use]
+    pub fn is_degraded(&self) -> bool {
+        self.arrays.iter().any(|a| a.degraded) || self.volumes.iter().any(|v| v.degraded)
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct MdArray {
+    /// e.g. "md0"
+    pub name: String,
+    /// "active" or "inactive"
+    pub state: String,
+    /// e.g. "raid1"
+    pub level: String,
+    pub devices: Vec<String>,
+    pub failed_devices: Vec<String>,
+    /// The number of devices the array should have.
+    pub total: u32,
+    /// The number of devices working.
+    pub working: u32,
+    pub degraded: bool,
+    /// Progress of resync or recovery, e.g. "recovery = 8.5%".
+    pub sync: Option<String>,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct RaidVolume {
+    pub controller: u32,
+    /// Disk group and virtual drive, e.g. "0/0".
+    pub id: String,
+    pub name: String,
+    /// e.g. "RAID1"
+    pub level: String,
+    /// e.g. `Optl`, `Dgrd`, `Pdgd`, `OfLn`
+    pub state: String,
+    pub degraded: bool,
+}
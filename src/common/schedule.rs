@@ -0,0 +1,16 @@
+use serde::{Deserialize, Serialize};
+
+/// The last run of a scheduled job.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct JobRun {
+    /// The name of the job, e.g. `disk_health`.
+    pub name: String,
+    /// The cron expression of the job, e.g. "0 */6 * * *".
+    pub schedule: String,
+    /// The time when the job started, in RFC 3339 format.
+    pub started: String,
+    pub duration_ms: u64,
+    pub succeeded: bool,
+    /// The output of the job, or the error if it failed.
+    pub message: String,
+}
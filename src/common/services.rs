@@ -5,6 +5,7 @@ use std::{
 };
 
 use anyhow::Result;
+use serde::{Deserialize, Serialize};
 
 /// Check the port is open (service is available).
 /// * Be careful! The opened ports does not mean that service is available. Sometimes it takes more time.
@@ -29,3 +30,32 @@ pub fn waitfor_up(addr: &str, port: &str, timeout: u64) -> Result<bool> {
         }
     }
 }
+
+/// An action on a service managed by systemd or a container runtime.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum ServiceAction {
+    Start,
+    Stop,
+    /// Restarts a running service, or starts a stopped one.
+    Restart,
+    /// Checks whether the service is running.
+    Status,
+    /// Starts the service at boot.
+    EnableAtBoot,
+    /// Stops starting the service at boot.
+    DisableAtBoot,
+}
+
+/// State of a container of a compose stack.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ContainerHealth {
+    /// Container name, e.g. "aice-review-1".
+    pub name: String,
+    /// Service name in the compose file.
+    pub service: String,
+    /// e.g. "running", "exited".
+    pub state: String,
+    /// "healthy", "unhealthy", or "starting", or None if the container has no
+    /// health check.
+    pub health: Option<String>,
+}
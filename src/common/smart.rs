@@ -0,0 +1,32 @@
+use serde::{Deserialize, Serialize};
+
+/// SMART health of a disk reported by smartctl.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct DiskHealth {
+    /// e.g. "/dev/sda"
+    pub device: String,
+    pub model: String,
+    /// The overall health self-assessment.
+    pub passed: bool,
+    /// Attributes which failed, or reached their thresholds.
+    pub failing_attributes: Vec<SmartAttribute>,
+}
+
+impl DiskHealth {
+    /// True if the disk failed the self-assessment or has failing attributes.
+    #[must_use]
+    pub fn is_failing(&self) -> bool {
+        !self.passed || !self.failing_attributes.is_empty()
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct SmartAttribute {
+    pub id: u32,
+    /// e.g. `Reallocated_Sector_Ct`
+    pub name: String,
+    /// The normalized value.
+    pub value: u32,
+    pub threshold: u32,
+    pub raw: String,
+}
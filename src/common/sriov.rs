@@ -0,0 +1,35 @@
+use serde::{Deserialize, Serialize};
+
+/// Settings of an SR-IOV virtual function given to a VM or a container.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VfConfig {
+    /// MAC address, e.g. "02:00:00:00:00:01". None keeps the current one.
+    pub mac: Option<String>,
+    /// VLAN tagged to the frames of the VF, or 0 for no VLAN. None keeps the
+    /// current one.
+    pub vlan: Option<u16>,
+}
+
+/// A virtual function of a physical NIC.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VirtualFunction {
+    pub index: u32,
+    /// Interface name of the VF, or None if it is bound to a driver such as
+    /// vfio-pci, which has no network interface.
+    pub ifname: Option<String>,
+    pub mac: String,
+    /// 0 if the VF has no VLAN.
+    pub vlan: u16,
+}
+
+/// SR-IOV status of a physical NIC.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct SriovStatus {
+    /// Maximum number of VFs supported by the NIC.
+    pub total_vfs: u32,
+    /// Number of enabled VFs.
+    pub num_vfs: u32,
+    /// Number of VFs enabled at boot, or None if roxy does not manage it.
+    pub persistent_vfs: Option<u32>,
+    pub vfs: Vec<VirtualFunction>,
+}
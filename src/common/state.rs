@@ -0,0 +1,191 @@
+use std::{collections::BTreeMap, fmt};
+
+use serde::{Deserialize, Serialize};
+
+use super::{NicOutput, NicOutputV1, Subsystem};
+
+/// Desired or current state of an appliance. Its `Display` implementation
+/// writes the state as a YAML document which can be applied as it is.
+///
+/// A subsystem set to `None` is left untouched when the state is applied.
+/// DNS servers are configured per interface with `nameservers`.
+///
+/// # Example
+///
+/// ```yaml
+/// hostname: sensor1
+/// interfaces:
+///   eno1:
+///     addresses: [192.168.0.10/24]
+///     dhcp4: false
+///     gateway4: 192.168.0.1
+///     nameservers: [8.8.8.8]
+/// ntp: [time.bora.net]
+/// syslog: ["@@192.168.0.205:514"]
+/// firewall:
+///   enabled: true
+///   rules: [allow 22/tcp]
+/// sshd:
+///   port: 22
+/// ```
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SystemState {
+    /// The short or fully-qualified name. A short name clears the domain.
+    pub hostname: Option<String>,
+    pub interfaces: Option<BTreeMap<String, NicOutput>>,
+    /// NTP server addresses.
+    pub ntp: Option<Vec<String>>,
+    /// Remote syslog server addresses. `@@` for tcp, `@` for udp.
+    pub syslog: Option<Vec<String>>,
+    pub firewall: Option<FirewallState>,
+    pub sshd: Option<SshdState>,
+}
+
+impl SystemState {
+    /// Returns the state without the fields identifying the node, its
+    /// hostname and interfaces, so that the other node of an HA pair can
+    /// apply it.
+    #[must_use]
+    pub fn without_identity(self) -> Self {
+        Self {
+            hostname: None,
+            interfaces: None,
+            ..self
+        }
+    }
+
+    /// Returns true if the state has a field identifying the node.
+    #[must_use]
+    pub fn has_identity(&self) -> bool {
+        self.hostname.is_some() || self.interfaces.is_some()
+    }
+}
+
+/// [`SystemState`] exchanged by `State` requests, whose interfaces have only
+/// the settings of [`NicOutputV1`]. `StateV2` requests exchange
+/// [`SystemState`].
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct SystemStateV1 {
+    pub hostname: Option<String>,
+    pub interfaces: Option<BTreeMap<String, NicOutputV1>>,
+    pub ntp: Option<Vec<String>>,
+    pub syslog: Option<Vec<String>>,
+    pub firewall: Option<FirewallState>,
+    pub sshd: Option<SshdState>,
+}
+
+impl From<SystemStateV1> for SystemState {
+    fn from(state: SystemStateV1) -> Self {
+        SystemState {
+            hostname: state.hostname,
+            interfaces: state.interfaces.map(|interfaces| {
+                interfaces
+                    .into_iter()
+                    .map(|(name, nic)| (name, nic.into()))
+                    .collect()
+            }),
+            ntp: state.ntp,
+            syslog: state.syslog,
+            firewall: state.firewall,
+            sshd: state.sshd,
+        }
+    }
+}
+
+impl From<SystemState> for SystemStateV1 {
+    fn from(state: SystemState) -> Self {
+        SystemStateV1 {
+            hostname: state.hostname,
+            interfaces: state.interfaces.map(|interfaces| {
+                interfaces
+                    .into_iter()
+                    .map(|(name, nic)| (name, nic.into()))
+                    .collect()
+            }),
+            ntp: state.ntp,
+            syslog: state.syslog,
+            firewall: state.firewall,
+            sshd: state.sshd,
+        }
+    }
+}
+
+impl fmt::Display for SystemState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Ok(s) = serde_yaml::to_string(self) {
+            write!(f, "{s}")
+        } else {
+            Ok(())
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct FirewallState {
+    pub enabled: bool,
+    /// ufw rules, e.g. "allow 22/tcp".
+    pub rules: Vec<String>,
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+pub struct SshdState {
+    pub port: u16,
+}
+
+/// A change made to the system while applying a [`SystemState`].
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct StateChange {
+    pub subsystem: Subsystem,
+    /// The name of the changed item in the subsystem, e.g. interface name.
+    pub target: Option<String>,
+    pub before: String,
+    pub after: String,
+}
+
+impl fmt::Display for StateChange {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(target) = &self.target {
+            write!(
+                f,
+                "{}({target}): {} -> {}",
+                self.subsystem, self.before, self.after
+            )
+        } else {
+            write!(f, "{}: {} -> {}", self.subsystem, self.before, self.after)
+        }
+    }
+}
+
+/// A difference between the last [`SystemState`] applied by roxy and the
+/// system, e.g. made by editing a configuration file by hand.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct DriftItem {
+    pub subsystem: Subsystem,
+    /// The name of the item in the subsystem, e.g. interface name.
+    pub target: Option<String>,
+    /// The configuration applied by roxy.
+    pub desired: String,
+    /// The configuration of the system.
+    pub current: String,
+}
+
+impl fmt::Display for DriftItem {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        if let Some(target) = &self.target {
+            write!(
+                f,
+                "{}({target}): {} != {}",
+                self.subsystem, self.current, self.desired
+            )
+        } else {
+            write!(
+                f,
+                "{}: {} != {}",
+                self.subsystem, self.current, self.desired
+            )
+        }
+    }
+}
@@ -0,0 +1,61 @@
+use serde::{Deserialize, Serialize};
+
+/// Upper bounds of the latency buckets in milliseconds. The last bucket of
+/// [`TaskStats::buckets`] counts the tasks slower than all of them.
+pub const LATENCY_BUCKETS_MS: [u64; 5] = [10, 100, 1_000, 10_000, 60_000];
+
+/// Statistics of the tasks of a kind handled by roxy.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TaskStats {
+    /// Task kind, e.g. `Interface::Set`.
+    pub kind: String,
+    pub count: u64,
+    pub failures: u64,
+    pub total_ms: u64,
+    pub max_ms: u64,
+    /// Number of tasks in each latency bucket of [`LATENCY_BUCKETS_MS`],
+    /// followed by the number of tasks slower than the last bound.
+    pub buckets: Vec<u64>,
+}
+
+impl TaskStats {
+    /// Returns the average latency in milliseconds.
+    #[must_use]
+    pub fn mean_ms(&self) -> u64 {
+        self.total_ms.checked_div(self.count).unwrap_or_default()
+    }
+
+    /// Adds a task which took `elapsed_ms` milliseconds.
+    pub fn add(&mut self, elapsed_ms: u64, failed: bool) {
+        self.count += 1;
+        if failed {
+            self.failures += 1;
+        }
+        self.total_ms = self.total_ms.saturating_add(elapsed_ms);
+        self.max_ms = self.max_ms.max(elapsed_ms);
+        self.buckets.resize(LATENCY_BUCKETS_MS.len() + 1, 0);
+        let bucket = LATENCY_BUCKETS_MS
+            .iter()
+            .position(|bound| elapsed_ms <= *bound)
+            .unwrap_or(LATENCY_BUCKETS_MS.len());
+        self.buckets[bucket] += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn add_to_buckets() {
+        let mut stats = TaskStats::default();
+        stats.add(5, false);
+        stats.add(250, true);
+        stats.add(90_000, false);
+        assert_eq!(stats.count, 3);
+        assert_eq!(stats.failures, 1);
+        assert_eq!(stats.max_ms, 90_000);
+        assert_eq!(stats.mean_ms(), 30_085);
+        assert_eq!(stats.buckets, [1, 0, 1, 0, 0, 1]);
+    }
+}
@@ -0,0 +1,37 @@
+use serde::{Deserialize, Serialize};
+
+/// A kernel setting of a tuning profile.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TuningSetting {
+    /// The sysctl key, e.g. `vm.swappiness`, or the path under `/sys` with
+    /// dots, e.g. `kernel.mm.transparent_hugepage.enabled`.
+    pub name: String,
+    /// The value of the profile.
+    pub expected: String,
+    /// The running value, or None if the kernel does not have the setting.
+    pub current: Option<String>,
+}
+
+/// The tuning profile of the kernel, and whether its settings are in effect.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TuningStatus {
+    /// The profile applied at boot, e.g. "capture". "default" if roxy has
+    /// not applied any other.
+    pub profile: String,
+    /// The settings of the profile.
+    pub settings: Vec<TuningSetting>,
+    /// The names of the profiles which can be applied.
+    pub profiles: Vec<String>,
+}
+
+impl TuningStatus {
+    /// Returns the settings whose running values differ from the profile,
+    /// e.g. because they were changed by hand.
+    #[must_use]
+    pub fn drifted(&self) -> Vec<&TuningSetting> {
+        self.settings
+            .iter()
+            .filter(|setting| setting.current.as_deref() != Some(setting.expected.as_str()))
+            .collect()
+    }
+}
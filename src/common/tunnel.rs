@@ -0,0 +1,66 @@
+use serde::{Deserialize, Serialize};
+
+/// The encapsulation of a tunnel interface, named as the `mode` of a netplan
+/// tunnel.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(rename_all = "lowercase")]
+pub enum TunnelMode {
+    /// GRE over IPv4, carrying IP packets.
+    Gre,
+    /// GRE over IPv6, carrying IP packets.
+    Ip6Gre,
+    /// GRE over IPv4, carrying Ethernet frames, e.g. mirrored by a switch.
+    Gretap,
+    /// GRE over IPv6, carrying Ethernet frames.
+    Ip6Gretap,
+    /// VXLAN over IPv4 or IPv6, carrying Ethernet frames.
+    Vxlan,
+}
+
+impl TunnelMode {
+    /// Bytes added to each packet by the encapsulation, including the outer
+    /// IP header, which the MTU of the tunnel must leave room for within the
+    /// MTU of the interface carrying it.
+    #[must_use]
+    pub fn overhead(self, with_key: bool) -> u32 {
+        const IPV4: u32 = 20;
+        const IPV6: u32 = 40;
+        const GRE: u32 = 4;
+        const GRE_KEY: u32 = 4;
+        const ETHERNET: u32 = 14;
+        const UDP: u32 = 8;
+        const VXLAN: u32 = 8;
+        let key = if with_key { GRE_KEY } else { 0 };
+        match self {
+            TunnelMode::Gre => IPV4 + GRE + key,
+            TunnelMode::Ip6Gre => IPV6 + GRE + key,
+            TunnelMode::Gretap => IPV4 + GRE + key + ETHERNET,
+            TunnelMode::Ip6Gretap => IPV6 + GRE + key + ETHERNET,
+            // Over IPv4. VXLAN over IPv6 adds 20 bytes more.
+            TunnelMode::Vxlan => IPV4 + UDP + VXLAN + ETHERNET,
+        }
+    }
+}
+
+/// A GRE or VXLAN tunnel interface in netplan, e.g. to receive traffic
+/// mirrored by a remote switch.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct TunnelConfig {
+    pub mode: TunnelMode,
+    /// The address of this node the tunnel is bound to. It should be
+    /// assigned to an interface.
+    pub local: String,
+    /// The address of the other end of the tunnel. Required except for VXLAN,
+    /// which learns its peers if None.
+    pub remote: Option<String>,
+    /// The VXLAN network identifier, 1 to 16777215. Required for VXLAN.
+    pub vni: Option<u32>,
+    /// The UDP port of VXLAN, or None for 4789.
+    pub port: Option<u16>,
+    /// The key of GRE, which the other end should send as well.
+    pub key: Option<u32>,
+    pub addresses: Option<Vec<String>>,
+    /// The MTU of the tunnel, or None for the largest one the interface
+    /// carrying the tunnel allows.
+    pub mtu: Option<u32>,
+}
@@ -0,0 +1,48 @@
+use serde::{Deserialize, Serialize};
+
+/// A VRRP instance run by keepalived, which moves virtual IP addresses to the
+/// other node of an HA pair when the node holding them fails. Both nodes are
+/// configured with the same `virtual_router_id`, addresses, and password, and
+/// different priorities.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VrrpConfig {
+    /// The interface sending the advertisements and holding the addresses.
+    pub interface: String,
+    /// 1 to 255, unique among the VRRP routers of the network.
+    pub virtual_router_id: u8,
+    /// 1 to 254. The node with the higher priority holds the addresses.
+    pub priority: u8,
+    /// Addresses with their prefix length, e.g. "192.168.0.100/24".
+    pub virtual_addresses: Vec<String>,
+    /// Password of up to 8 characters authenticating the advertisements, or
+    /// None to send them without authentication.
+    pub auth_pass: Option<String>,
+}
+
+/// The state of a VRRP instance, as reported by keepalived.
+#[derive(Clone, Copy, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum VrrpState {
+    /// Holds the virtual addresses.
+    Master,
+    /// Ready to take over the virtual addresses.
+    Backup,
+    /// The interface is down, or the instance cannot run.
+    Fault,
+    /// keepalived has stopped.
+    Stop,
+}
+
+/// The VRRP configuration of a node, and its state.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct VrrpStatus {
+    /// None if VRRP is not configured by roxy. The password is not returned,
+    /// so `auth_pass` is always None.
+    pub config: Option<VrrpConfig>,
+    /// Whether keepalived is running.
+    pub active: bool,
+    /// The last state reported by keepalived since boot, or None if it has
+    /// not reported one.
+    pub state: Option<VrrpState>,
+    /// Whether the interface has the first virtual address.
+    pub holds_addresses: bool,
+}
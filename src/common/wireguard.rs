@@ -0,0 +1,47 @@
+use serde::{Deserialize, Serialize};
+
+/// A WireGuard interface brought up by wg-quick, e.g. a management tunnel to
+/// the Manager which works even if the primary network is misconfigured.
+/// The private key is generated on the node and never leaves it.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WireguardConfig {
+    /// The name of the interface, e.g. "wg-mgmt".
+    pub interface: String,
+    /// Addresses of the interface with their prefix length, e.g.
+    /// "10.99.0.2/24".
+    pub addresses: Vec<String>,
+    /// The UDP port to listen on, or None for a random port, which is enough
+    /// if only this node initiates the tunnel.
+    pub listen_port: Option<u16>,
+    pub peer: WireguardPeer,
+}
+
+/// The other end of a WireGuard tunnel.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WireguardPeer {
+    /// The public key of the peer in base64.
+    pub public_key: String,
+    /// The address and the port of the peer, e.g. "manager.example.com:51820",
+    /// or None if the peer initiates the tunnel.
+    pub endpoint: Option<String>,
+    /// Networks routed to the peer, e.g. "10.99.0.0/24".
+    pub allowed_ips: Vec<String>,
+    /// Seconds between keepalive packets, which keep the tunnel open through
+    /// NAT, or None to send none.
+    pub persistent_keepalive: Option<u16>,
+}
+
+/// A WireGuard interface configured by roxy, and the state of its tunnel.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct WireguardStatus {
+    pub config: WireguardConfig,
+    /// The public key of this node in base64, to be configured on the peer.
+    pub public_key: String,
+    /// Whether the interface is up.
+    pub active: bool,
+    /// The time of the last handshake with the peer in seconds since the
+    /// Unix epoch, or None if there has been none.
+    pub latest_handshake: Option<i64>,
+    pub rx_bytes: u64,
+    pub tx_bytes: u64,
+}
@@ -1,16 +1,45 @@
+mod client;
 pub mod common;
 mod user;
 
-use std::process::{Command, Stdio};
+use std::collections::BTreeMap;
 
 use anyhow::{anyhow, Result};
+pub use client::RoxyClient;
 pub use common::waitfor_up;
-use common::{NicOutput, Node, NodeRequest, SubCommand};
+use common::{
+    AuditEvent, AuditStatus, BondStatus, CaptureSettings, CaptureStatus, ChangeEvent,
+    CleanupReport, ComplianceReport, ContainerHealth, CrashReport, DeferredOperation, DiskHealth,
+    DnsLookup, DriftItem, Endpoint, FilesystemHealth, GovernorStatus, GroupSettings, HardwareInfo,
+    HistoryEntry, HostnameInfo, InterfaceResult, IrqLayout, JobRun, KdumpStatus, LicenseStatus,
+    MaintenanceWindow, NetnsCapture, NetnsInterface, NicOutput, NicOutputV1, Node, NodeInfo,
+    NodeRequest, Page, PageRequest, PingInfo, PreflightCheck, RaidStatus, ReadinessReport,
+    ServiceAction, SessionAuditStatus, SriovStatus, StateChange, SubCommand, Subsystem,
+    SystemState, TaskStats, TrimRun, TrimStatus, TuningStatus, TunnelConfig, VfConfig, VrrpConfig,
+    VrrpStatus, WireguardConfig, WireguardStatus,
+};
 use data_encoding::BASE64;
 use serde::Deserialize;
-pub use user::hwinfo::{uptime, version};
-pub use user::process::{process_list, Process};
-pub use user::usg::{resource_usage, ResourceUsage};
+use thiserror::Error;
+pub use user::cache::ResponseCache;
+pub use user::clock::{clock_skew, ClockSkew};
+pub use user::ethtool::{nic_info, nic_infos, NicInfo, DROP_STATISTICS};
+pub use user::hwinfo::{boot_info, format_uptime, uptime, version, BootInfo};
+pub use user::ifstat::{
+    interface_counters, InterfaceCounters, InterfaceDelta, InterfaceSample, InterfaceSampler,
+};
+pub use user::link::{link_states, LinkMonitor, LinkState, LinkTransition};
+pub use user::monitor::{Alert, HealthMonitor, Metric, Thresholds};
+pub use user::notify::{Event, EventKind, Notifier};
+pub use user::process::{process_list, process_page, Process, ProcessSampler};
+pub use user::reboot::{reboot_required, RebootReason};
+pub use user::scheduler::{Schedule, Scheduler};
+pub use user::usg::{
+    disk_usage_mounts, pressure, resource_usage, MountUsage, Pressure, PressureAvg, PressureStat,
+    ResourceUsage, UsageSample, UsageSampler,
+};
+pub use user::watch::{ConfigWatcher, ExternalChange};
+pub use user::watchdog::{RestartPolicy, Watchdog, WatchdogEvent};
 const FAIL_REQUEST: &str = "Failed to create a request";
 
 /// Control services: start, stop, restart, status
@@ -20,6 +49,7 @@ const FAIL_REQUEST: &str = "Failed to create a request";
 /// * Return error if invalid subcommand is specified
 /// * Return error if target service is not registered as a systemctl service
 /// * Return error if it failed to execute the command
+#[deprecated(note = "use `service_action`")]
 pub fn service_control(subcmd: SubCommand, service: String) -> Result<bool> {
     if let Ok(req) = NodeRequest::new::<String>(Node::Service(subcmd), service) {
         run_roxy::<bool>(req)
@@ -28,6 +58,299 @@ pub fn service_control(subcmd: SubCommand, service: String) -> Result<bool> {
     }
 }
 
+/// Runs an action on a service, and returns whether the service is running
+/// for [`ServiceAction::Status`], or whether the action succeeded for the
+/// others.
+///
+/// A service listed in `/etc/roxy/services.yaml` with its container runtime,
+/// `docker` or `podman`, and container name is controlled as a container.
+/// Other services are controlled by systemd.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn service_action(service: String, action: ServiceAction) -> Result<bool> {
+    if let Ok(req) = NodeRequest::new::<(String, ServiceAction)>(
+        Node::Service(SubCommand::Set),
+        (service, action),
+    ) {
+        run_roxy::<bool>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the environment variables set for a service in its systemd
+/// drop-in, `/etc/systemd/system/<service>.service.d/override.conf`.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn service_environment(service: String) -> Result<BTreeMap<String, String>> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::ServiceEnvironment(SubCommand::Get), service)
+    {
+        run_roxy::<BTreeMap<String, String>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets environment variables for a service in its systemd drop-in, keeping
+/// the other variables and directives there, and reloads systemd. The
+/// service sees them after it is restarted, e.g. by [`service_action`]. Only
+/// the services listed under `services` or `startup` in
+/// `/etc/roxy/services.yaml` are managed.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error, e.g. for a service
+///   which is not listed, a name which is not a shell variable name or is read
+///   by the dynamic loader or systemd, such as `LD_PRELOAD` or `SYSTEMD_*`, or
+///   a value with a control character
+pub fn set_service_environment(
+    service: String,
+    variables: BTreeMap<String, String>,
+) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<(String, BTreeMap<String, String>)>(
+        Node::ServiceEnvironment(SubCommand::Set),
+        (service, variables),
+    ) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Unsets environment variables of a service listed in
+/// `/etc/roxy/services.yaml` in its systemd drop-in, and reloads systemd. The
+/// drop-in is removed when nothing is left in it.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn unset_service_environment(service: String, names: Vec<String>) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<(String, Vec<String>)>(
+        Node::ServiceEnvironment(SubCommand::Delete),
+        (service, names),
+    ) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Starts the services listed under `startup` in `/etc/roxy/services.yaml`.
+/// A service starts after the services in its `after` list are started and
+/// their `ready` address accepts a connection.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn start_all() -> Result<String> {
+    service_group(SubCommand::Enable)
+}
+
+/// Stops the services listed under `startup` in `/etc/roxy/services.yaml`,
+/// each before the services it depends on.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn stop_all() -> Result<String> {
+    service_group(SubCommand::Disable)
+}
+
+/// Stops the services listed under `startup` in `/etc/roxy/services.yaml`
+/// and starts them again in the order of their dependencies.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn restart_all() -> Result<String> {
+    service_group(SubCommand::Update)
+}
+
+fn service_group(cmd: SubCommand) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::ServiceGroup(cmd), None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the last lines of the log of a service, from the journal or from
+/// the container runtime of the service.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+/// * [`LimitExceeded`] if journalctl or the container runtime exceeds its
+///   memory limit
+pub fn service_logs(service: String, lines: u32) -> Result<String> {
+    if let Ok(req) =
+        NodeRequest::new::<(String, u32)>(Node::Service(SubCommand::Get), (service, lines))
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns a page of the log of a service. The offset of the page counts
+/// back from the newest line, and the lines in a page are from the oldest.
+/// The pages go back up to 100,000 lines.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+/// * [`LimitExceeded`] if journalctl or the container runtime exceeds its
+///   memory limit
+pub fn service_log_page(service: String, page: PageRequest) -> Result<Page<String>> {
+    if let Ok(req) = NodeRequest::new::<(String, u32, PageRequest)>(
+        Node::Service(SubCommand::Get),
+        (service, 0, page),
+    ) {
+        run_roxy::<Page<String>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Creates and starts the containers of a compose stack listed under `stacks`
+/// in `/etc/roxy/services.yaml`. Nothing is started if any image of the stack
+/// is not from the `registries` allowed for it.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn compose_up(stack: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Compose(SubCommand::Enable), stack) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Stops and removes the containers of a compose stack.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn compose_down(stack: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Compose(SubCommand::Disable), stack) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Pulls the images of a compose stack and recreates the containers whose
+/// image is updated. It fails without pulling anything if an image is not
+/// from one of the `registries` of the stack.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn update_compose(stack: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Compose(SubCommand::Update), stack) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the state and health of the containers of a compose stack.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn compose_status(stack: String) -> Result<Vec<ContainerHealth>> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Compose(SubCommand::Status), stack) {
+        run_roxy::<Vec<ContainerHealth>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Checks whether the node reaches the endpoints it requires: those in
+/// `/etc/roxy/endpoints.yaml`, its NTP and syslog servers, and `extra`. The
+/// report has the result and latency of each endpoint; a syslog server with
+/// an invalid address is reported as a failed endpoint.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn network_readiness(extra: Vec<Endpoint>) -> Result<ReadinessReport> {
+    if let Ok(req) = NodeRequest::new::<Vec<Endpoint>>(Node::Connectivity(SubCommand::Get), extra) {
+        run_roxy::<ReadinessReport>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Resolves a name with the system resolver of the node, and returns its
+/// addresses with the resolver configuration used. The addresses are empty if
+/// the name is not found. Like other programs on the node, it resolves through
+/// `getaddrinfo`, which reports only the last name of a CNAME chain as the
+/// canonical name; query the DNS servers directly to see the whole chain.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn dns_lookup(name: String) -> Result<DnsLookup> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Dns(SubCommand::Get), name) {
+        run_roxy::<DnsLookup>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Flushes the DNS cache of systemd-resolved.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn flush_dns_cache() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Dns(SubCommand::Delete), None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Checks that roxy is installed and executable without changing anything,
+/// and returns its version and capability hash.
+/// [`PingInfo::is_compatible`] tells whether it handles the same kinds of
+/// request as this library.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn ping() -> Result<PingInfo> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Ping, None) {
+        run_roxy::<PingInfo>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
 /// Returns a hostname.
 #[must_use]
 pub fn hostname() -> String {
@@ -56,91 +379,1629 @@ pub fn set_os_version(ver: String) -> Result<String> {
     }
 }
 
-/// Sets a version for product.
+/// Sets a version for product.
+///
+/// # Errors
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If reading or writing of a product version file fails, then an error
+///   is returned.
+pub fn set_product_version(ver: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Version(SubCommand::SetProductVersion), ver) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets a hostname. If a fully-qualified name such as "sensor1.example.com"
+/// is given, the first label is set as the hostname, and the rest as the
+/// domain in `kernel.domainname` and `/etc/hosts`. A short name clears the
+/// domain.
+///
+/// # Errors
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If the hostname is invalid, then an error is returned.
+/// * If `hostname::set` fails, then an error is returned.
+/// * If writing of `/etc/hosts` or the domain name fails, then an error is
+///   returned.
+pub fn set_hostname(host: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Hostname(SubCommand::Set), host) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Installs the auditd ruleset which watches the configuration files managed
+/// by roxy and logs execution of privileged binaries, or removes it.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_audit_rules(enable: bool) -> Result<String> {
+    let cmd = if enable {
+        SubCommand::Enable
+    } else {
+        SubCommand::Disable
+    };
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Audit(cmd), None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the status of auditd and the ruleset installed by roxy.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn audit_status() -> Result<AuditStatus> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Audit(SubCommand::Status), None) {
+        run_roxy::<AuditStatus>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the audit events generated by the ruleset since `start`, which is
+/// one of "recent" (10 minutes ago), "today", "yesterday", "this-week", and
+/// "this-month". "today" is used if `None`. At most the last 1000 events are
+/// returned.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+/// * [`LimitExceeded`] if ausearch exceeds its memory limit
+pub fn audit_events(start: Option<String>) -> Result<Vec<AuditEvent>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Audit(SubCommand::Get), start) {
+        run_roxy::<Vec<AuditEvent>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the login banner.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn banner() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Banner(SubCommand::Get), None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets the login banner to `/etc/issue`, `/etc/issue.net`, and `/etc/motd`,
+/// and configures sshd to show it before authentication. An empty text
+/// removes the banner.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_banner(text: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Banner(SubCommand::Set), text) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the short and fully-qualified names of the host. If roxy is too
+/// old to manage the domain, then an error is returned.
+///
+/// # Errors
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If reading of `/etc/hosts` fails, then an error is returned.
+pub fn hostname_info() -> Result<HostnameInfo> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Hostname(SubCommand::Status), None) {
+        run_roxy::<HostnameInfo>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the hostname, FQDN, OS and product versions, uptime, kernel
+/// release, architecture, and roxy version of the node in a single request.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn node_info() -> Result<NodeInfo> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Info(SubCommand::Get), None) {
+        run_roxy::<NodeInfo>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns tuples of (facilitiy, proto, addr) of syslog servers.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If it fails to open `/etc/rsyslog.d/50-default.conf`, then an error
+///   is returned.
+pub fn syslog_servers() -> Result<Option<Vec<(String, String, String)>>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Syslog(SubCommand::Get), None) {
+        run_roxy::<Option<Vec<(String, String, String)>>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets syslog servers.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If it fails to open or write `/etc/rsyslog.d/50-default.conf`, then
+///   an error is returned.
+/// * If it fails to restart rsyslogd service, then an error is returned.
+pub fn set_syslog_servers(servers: Vec<String>) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Vec<String>>(Node::Syslog(SubCommand::Set), servers) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Initiates syslog servers.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If it fails to open or write `/etc/rsyslog.d/50-default.conf`, then
+///   an error is returned.
+/// * If it fails to restart rsyslogd service, then an error is returned.
+pub fn init_syslog_servers() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Syslog(SubCommand::Init), None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// (Re)start syslog services.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If it fails to restart rsyslogd service, then an error is returned.
+pub fn start_syslog_servers() -> Result<bool> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Syslog(SubCommand::Enable), None) {
+        run_roxy::<bool>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the list of interface names.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+pub fn list_of_interfaces(prefix: Option<String>) -> Result<Vec<String>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Interface(SubCommand::List), prefix) {
+        run_roxy::<Vec<String>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the settings of interface. All interfafces if None for device name
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If roxy is too old to know `InterfaceV2`, then [`Unsupported`] is
+///   returned.
+pub fn interfaces(dev: Option<String>) -> Result<Option<Vec<(String, NicOutput)>>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::InterfaceV2(SubCommand::Get), dev) {
+        run_roxy::<Option<Vec<(String, NicOutput)>>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets an interface setting.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If it fails to read or write a netplan yaml conf file, then an error
+///   is returned.
+/// * If dhcp4 and static ip address or nameserver address is set in the same
+///   interface, then an error is returned.
+/// * If a user tries to set a new gateway address when another interface has
+///   the same, then an error is returned.
+pub fn set_interface(
+    dev: String,
+    addresses: Option<Vec<String>>,
+    dhcp4: Option<bool>,
+    gateway4: Option<String>,
+    nameservers: Option<Vec<String>>,
+) -> Result<String> {
+    let nic = NicOutputV1 {
+        addresses,
+        dhcp4,
+        gateway4,
+        nameservers,
+    };
+    if let Ok(req) =
+        NodeRequest::new::<(String, NicOutputV1)>(Node::Interface(SubCommand::Set), (dev, nic))
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets an interface setting, including the settings which [`set_interface`]
+/// leaves unset, such as `optional` and `activation-mode`. The existing
+/// setting of the interface is overwritten.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If it fails to read or write a netplan yaml conf file, then an error
+///   is returned.
+/// * If dhcp4 and static ip address or nameserver address is set in the same
+///   interface, then an error is returned.
+/// * If a user tries to set a new gateway address when another interface has
+///   the same, then an error is returned.
+/// * If roxy is too old to know `InterfaceV2`, then [`Unsupported`] is
+///   returned.
+pub fn configure_interface(dev: String, nic: NicOutput) -> Result<String> {
+    if let Ok(req) =
+        NodeRequest::new::<(String, NicOutput)>(Node::InterfaceV2(SubCommand::Set), (dev, nic))
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the names of the interfaces in a group of
+/// `/etc/roxy/interface-groups.yaml`, or matching a glob pattern such as
+/// `cap*`.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn interface_group(target: &str) -> Result<Vec<String>> {
+    if let Ok(req) = NodeRequest::new::<&str>(Node::InterfaceGroup(SubCommand::Get), target) {
+        run_roxy::<Vec<String>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Applies the same settings to each interface in a group, or matching a
+/// glob pattern, as [`interface_group`] returns. An interface which fails
+/// does not stop the rest.
+///
+/// # Example
+///
+/// ```ignore
+/// let settings = GroupSettings {
+///     nic: Some(NicOutput::new(None, Some(false), None, None).optional(true)),
+///     capture: Some(CaptureSettings::capture()),
+/// };
+/// for ret in roxy::configure_interface_group("cap*", settings)? {
+///     if let Some(e) = ret.error {
+///         println!("{}: {e}", ret.ifname);
+///     }
+/// }
+/// ```
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn configure_interface_group(
+    target: &str,
+    settings: GroupSettings,
+) -> Result<Vec<InterfaceResult>> {
+    if let Ok(req) = NodeRequest::new::<(&str, GroupSettings)>(
+        Node::InterfaceGroup(SubCommand::Set),
+        (target, settings),
+    ) {
+        run_roxy::<Vec<InterfaceResult>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets an interface setting like [`configure_interface`], and then checks
+/// whether the gateway answers ARP on the interface to catch a mistyped
+/// gateway.
+///
+/// # Return
+///
+/// * Whether the gateway is reachable. None if the gateway is not set or
+///   cannot be checked.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If it fails to read or write a netplan yaml conf file, then an error
+///   is returned.
+/// * If dhcp4 and static ip address or nameserver address is set in the same
+///   interface, then an error is returned.
+/// * If a user tries to set a new gateway address when another interface has
+///   the same, then an error is returned.
+/// * If roxy is too old to know `InterfaceV2`, then [`Unsupported`] is
+///   returned.
+pub fn configure_interface_checked(dev: String, nic: NicOutput) -> Result<Option<bool>> {
+    if let Ok(req) = NodeRequest::new::<(String, NicOutput, bool)>(
+        Node::InterfaceV2(SubCommand::Set),
+        (dev, nic, true),
+    ) {
+        run_roxy::<Option<bool>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Init the settings of an interface.
+///
+/// # Errors
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If the specified interface name is not found, then an error is returned.
+/// * If it failed to load /etc/netplan yaml files, then an error is returned.
+/// * If if failed to execute netplan apply command, then an error is returned.
+/// * If it failed to execute ifconfig command, then an error is returned.
+pub fn init_interface(dev: String) -> Result<String> {
+    if let Ok(req) =
+        NodeRequest::new::<Option<String>>(Node::Interface(SubCommand::Init), Some(dev))
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Removes interface/gateway/nameserver address or dhcp4 option of interface.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If it fails to read or write a netplan yaml conf file, then an error
+///   is returned.
+pub fn remove_interface(
+    dev: String,
+    addresses: Option<Vec<String>>,
+    dhcp4: Option<bool>,
+    gateway4: Option<String>,
+    nameservers: Option<Vec<String>>,
+) -> Result<String> {
+    let nic = NicOutputV1 {
+        addresses,
+        dhcp4,
+        gateway4,
+        nameservers,
+    };
+    if let Ok(req) =
+        NodeRequest::new::<(String, NicOutputV1)>(Node::Interface(SubCommand::Delete), (dev, nic))
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Reboots the system.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If `nix::sys::reboot::reboot` fails, then an error is returned.
+pub fn reboot() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Reboot, None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Turns the system off.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If `nix::sys::reboot::reboot` fails, then an error is returned.
+pub fn power_off() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::PowerOff, None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Checks the environment roxy runs in without changing the system: required
+/// external binaries, write access to the managed configuration files,
+/// privilege of roxy, and the directories in `PATH`.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+pub fn preflight() -> Result<Vec<PreflightCheck>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Preflight, None) {
+        run_roxy::<Vec<PreflightCheck>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the status of software RAID arrays in `/proc/mdstat`, and virtual
+/// drives of hardware RAID controllers if storcli is installed.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If it fails to read `/proc/mdstat`, then an error is returned.
+pub fn raid_status() -> Result<RaidStatus> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Raid(SubCommand::Status), None) {
+        run_roxy::<RaidStatus>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the status of the bonding interfaces, or the one with the name,
+/// with the link state and the failure count of each slave, and the LACP
+/// partner of 802.3ad bonds. A bond is degraded if it or any slave is down,
+/// or any slave is left out of the aggregator LACP selected.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn bond_status(name: Option<String>) -> Result<Vec<BondStatus>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Bond(SubCommand::Status), name) {
+        run_roxy::<Vec<BondStatus>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns hardware inventory including the BIOS version and release date,
+/// and the BMC firmware revision if a BMC is present.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If dmidecode fails to execute, then an error is returned.
+pub fn hardware_info() -> Result<HardwareInfo> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Hardware(SubCommand::Get), None) {
+        run_roxy::<HardwareInfo>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Enables or disables recording of the keystrokes of interactive SSH and
+/// console sessions to the audit log. The change applies to new sessions.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_session_audit(enable: bool) -> Result<String> {
+    let cmd = if enable {
+        SubCommand::Enable
+    } else {
+        SubCommand::Disable
+    };
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::SessionAudit(cmd), None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns whether session recording is enabled, and auditd is running.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn session_audit_status() -> Result<SessionAuditStatus> {
+    if let Ok(req) =
+        NodeRequest::new::<Option<String>>(Node::SessionAudit(SubCommand::Status), None)
+    {
+        run_roxy::<SessionAuditStatus>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Reports the files to be removed from the directories over their quotas
+/// in `/etc/roxy/cleanup.yaml`, without removing them.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn cleanup_report() -> Result<Vec<CleanupReport>> {
+    cleanup(SubCommand::Status)
+}
+
+/// Removes the oldest files from the directories over their quotas in
+/// `/etc/roxy/cleanup.yaml`, until they are within the quotas. Files
+/// modified in the last minute are not removed.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn clean_up() -> Result<Vec<CleanupReport>> {
+    cleanup(SubCommand::Update)
+}
+
+fn cleanup(cmd: SubCommand) -> Result<Vec<CleanupReport>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Cleanup(cmd), None) {
+        run_roxy::<Vec<CleanupReport>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns SMART health of the disks found by smartctl.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If smartctl fails to execute, then an error is returned.
+/// * If smartctl exceeds its memory limit, then [`LimitExceeded`] is
+///   returned.
+pub fn disk_health() -> Result<Vec<DiskHealth>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Smart(SubCommand::Status), None) {
+        run_roxy::<Vec<DiskHealth>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the errors of the filesystems mounted from block devices: the
+/// errors recorded in the superblock of ext2/3/4 filesystems, and the
+/// filesystem errors in the kernel log.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn filesystem_health() -> Result<Vec<FilesystemHealth>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Filesystem(SubCommand::Status), None)
+    {
+        run_roxy::<Vec<FilesystemHealth>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Runs fstrim on all the mounted filesystems supporting it. A run in which
+/// fstrim fails on some filesystems is returned with `success` false and
+/// the errors of fstrim.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn trim_filesystems() -> Result<TrimRun> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Fstrim(SubCommand::Update), None) {
+        run_roxy::<TrimRun>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the state of the periodic fstrim timer, and the last run of
+/// [`trim_filesystems`].
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn trim_status() -> Result<TrimStatus> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Fstrim(SubCommand::Status), None) {
+        run_roxy::<TrimStatus>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Enables and starts the periodic fstrim timer, which trims the
+/// filesystems weekly.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn enable_trim_timer() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Fstrim(SubCommand::Enable), None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Stops and disables the periodic fstrim timer.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn disable_trim_timer() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Fstrim(SubCommand::Disable), None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Return configured sshd port number.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+#[deprecated(note = "use `sshd_port`")]
+pub fn get_sshd() -> Result<u16> {
+    sshd_port()
+}
+
+/// Returns the port sshd listens on.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn sshd_port() -> Result<u16> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Sshd(SubCommand::Get), None) {
+        run_roxy::<u16>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets the port sshd listens on, and restarts sshd.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_sshd_port(port: u16) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Sshd(SubCommand::Set), port.to_string()) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Restart sshd service.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn start_sshd() -> Result<bool> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Sshd(SubCommand::Enable), None) {
+        run_roxy::<bool>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Return configured NTP server FQDNs
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+#[deprecated(note = "use `ntp_servers`")]
+pub fn get_ntp() -> Result<Option<Vec<String>>> {
+    ntp_servers()
+}
+
+/// Set ntp servers
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+#[deprecated(note = "use `set_ntp_servers`")]
+pub fn set_ntp(servers: Vec<String>) -> Result<bool> {
+    set_ntp_servers(servers).map(|_| true)
+}
+
+/// Returns the NTP servers, or None if no server is configured.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn ntp_servers() -> Result<Option<Vec<String>>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Ntp(SubCommand::Get), None) {
+        run_roxy::<Option<Vec<String>>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Replaces the NTP servers, and restarts ntp.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_ntp_servers(servers: Vec<String>) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Vec<String>>(Node::Ntp(SubCommand::Set), servers) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// (Re)Start ntp service
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn start_ntp() -> Result<bool> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Ntp(SubCommand::Enable), None) {
+        run_roxy::<bool>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Stop ntp service
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn stop_ntp() -> Result<bool> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Ntp(SubCommand::Disable), None) {
+        run_roxy::<bool>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the user rules of ufw, e.g. "allow 22/tcp", or None if there is no
+/// rule.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn firewall_rules() -> Result<Option<Vec<String>>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Ufw(SubCommand::Get), None) {
+        run_roxy::<Option<Vec<String>>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns a page of the user rules of ufw.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn firewall_rules_page(page: PageRequest) -> Result<Page<String>> {
+    if let Ok(req) = NodeRequest::new::<Option<PageRequest>>(Node::Ufw(SubCommand::Get), Some(page))
+    {
+        run_roxy::<Page<String>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Adds ufw rules, e.g. "allow 22/tcp".
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn add_firewall_rules(rules: Vec<String>) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Vec<String>>(Node::Ufw(SubCommand::Add), rules) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Deletes ufw rules, e.g. "allow 22/tcp".
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn remove_firewall_rules(rules: Vec<String>) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Vec<String>>(Node::Ufw(SubCommand::Delete), rules) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the capture settings of an interface: promiscuous mode and
+/// offloads of the running interface, and those applied at boot.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn capture_settings(dev: String) -> Result<CaptureStatus> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Capture(SubCommand::Get), dev) {
+        run_roxy::<CaptureStatus>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets promiscuous mode and offloads of an interface, and applies them again
+/// at boot. [`CaptureSettings::capture`] returns the settings required by
+/// capture services.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_capture_settings(dev: String, settings: CaptureSettings) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<(String, CaptureSettings)>(
+        Node::Capture(SubCommand::Set),
+        (dev, settings),
+    ) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Stops applying the capture settings of an interface at boot. The running
+/// interface keeps its settings until reboot.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn remove_capture_settings(dev: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Capture(SubCommand::Delete), dev) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the interfaces in a named network namespace, or in every named
+/// namespace if None, with their state and counters. Capture NICs moved into
+/// a namespace of their own are not in [`list_of_interfaces`].
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn netns_interfaces(namespace: Option<String>) -> Result<Vec<NetnsInterface>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Netns(SubCommand::List), namespace) {
+        run_roxy::<Vec<NetnsInterface>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Captures packets with tcpdump on an interface in a named network
+/// namespace, and returns a line for each packet. It returns when `count`
+/// packets arrive or `timeout_secs` passes. Since the packets may carry
+/// anything on the network, the capture is not allowed to the read-only
+/// role.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn netns_capture(capture: NetnsCapture) -> Result<Vec<String>> {
+    if let Ok(req) = NodeRequest::new::<NetnsCapture>(Node::Netns(SubCommand::Update), capture) {
+        run_roxy::<Vec<String>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the frequency scaling governor of each CPU, the governors
+/// supported by all of them, and the governor set at boot.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn cpu_governors() -> Result<GovernorStatus> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::CpuGovernor(SubCommand::Get), None) {
+        run_roxy::<GovernorStatus>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets the frequency scaling governor of all the CPUs, such as
+/// "performance", and sets it again at boot instead of the default governor
+/// of Ubuntu.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_cpu_governor(governor: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::CpuGovernor(SubCommand::Set), governor) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Stops setting the governor of the CPUs at boot, so that Ubuntu sets its
+/// default governor again. The running CPUs keep their governor until
+/// reboot.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn remove_cpu_governor() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::CpuGovernor(SubCommand::Delete), None)
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the kernel tuning profile applied at boot, the running values of
+/// its settings, and the profiles which can be applied.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn tuning_profile() -> Result<TuningStatus> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Tuning(SubCommand::Get), None) {
+        run_roxy::<TuningStatus>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Applies a kernel tuning profile, "capture", "database", or "default", and
+/// applies it again at boot. The profile sets transparent huge pages,
+/// swappiness, writeback, and receive buffers together: if one of them
+/// fails, the others are restored.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_tuning_profile(profile: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Tuning(SubCommand::Set), profile) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the VRRP configuration of keepalived without the password, whether
+/// keepalived is running, and whether this node holds the virtual addresses.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn vrrp_status() -> Result<VrrpStatus> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Vrrp(SubCommand::Get), None) {
+        run_roxy::<VrrpStatus>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Writes the keepalived configuration for a VRRP instance, readable only by
+/// root, and starts keepalived now and at boot. Both nodes of an HA pair are
+/// configured with the same virtual router id, addresses, and password, and
+/// different priorities. A keepalived configuration not written by roxy is
+/// not replaced.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error, e.g. for an unknown
+///   interface, an invalid address, or an address conflicting with another
+///   interface, with the conflict in the message
+pub fn set_vrrp(config: VrrpConfig) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<VrrpConfig>(Node::Vrrp(SubCommand::Set), config) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Stops keepalived, releasing the virtual addresses to the peer, and removes
+/// its configuration.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn remove_vrrp() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Vrrp(SubCommand::Delete), None) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the configuration of a WireGuard interface, the public key of this
+/// node to be configured on the peer, and the state of the tunnel, or None if
+/// the interface is not configured.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn wireguard_status(interface: String) -> Result<Option<WireguardStatus>> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Wireguard(SubCommand::Get), interface) {
+        run_roxy::<Option<WireguardStatus>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Configures a WireGuard interface with wg-quick, and brings it up now and
+/// at boot, e.g. as a management tunnel independent of the netplan
+/// configuration. The keys of this node are generated the first time and
+/// kept when the configuration changes.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_wireguard(config: WireguardConfig) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<WireguardConfig>(Node::Wireguard(SubCommand::Set), config) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Brings a WireGuard interface down, and removes its configuration and the
+/// keys of this node.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn remove_wireguard(interface: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Wireguard(SubCommand::Delete), interface) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the GRE and VXLAN tunnels in netplan with their names, or the one
+/// with the name.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn tunnels(name: Option<String>) -> Result<Vec<(String, TunnelConfig)>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Tunnel(SubCommand::Get), name) {
+        run_roxy::<Vec<(String, TunnelConfig)>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Adds or replaces a GRE or VXLAN tunnel in netplan, and applies it, e.g. to
+/// receive traffic mirrored by a remote switch. The local address should be
+/// assigned to an interface, and the MTU should leave room for the
+/// encapsulation within the MTU of that interface. If the MTU is None, the
+/// largest one is set.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error, which is
+///   [`AddressConflict`] if an address of the tunnel conflicts with another
+///   interface or host
+pub fn set_tunnel(name: String, config: TunnelConfig) -> Result<String> {
+    if let Ok(req) =
+        NodeRequest::new::<(String, TunnelConfig)>(Node::Tunnel(SubCommand::Set), (name, config))
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Removes a tunnel from netplan and deletes its interface.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn remove_tunnel(name: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Tunnel(SubCommand::Delete), name) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the IRQs of an interface, the CPUs handling them, and its NUMA
+/// node.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn irq_layout(dev: String) -> Result<IrqLayout> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::IrqAffinity(SubCommand::Get), dev) {
+        run_roxy::<IrqLayout>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Pins the MSI-X IRQs of an interface, one for each receive queue with
+/// RSS, to the CPUs in turn, and pins them again at boot. irqbalance no
+/// longer moves them. The CPUs should be on the NUMA node of the interface,
+/// [`IrqLayout::local_cpus`].
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn pin_irqs(dev: String, cpus: Vec<u32>) -> Result<String> {
+    if let Ok(req) =
+        NodeRequest::new::<(String, Vec<u32>)>(Node::IrqAffinity(SubCommand::Set), (dev, cpus))
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Stops pinning the IRQs of an interface at boot, and lets irqbalance
+/// balance them again.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn unpin_irqs(dev: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::IrqAffinity(SubCommand::Delete), dev) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the SR-IOV status of a physical NIC: the number of VFs and their
+/// MAC addresses and VLANs.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn sriov_status(dev: String) -> Result<SriovStatus> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Sriov(SubCommand::Get), dev) {
+        run_roxy::<SriovStatus>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Enables the given number of SR-IOV virtual functions of a physical NIC,
+/// and enables them again at boot. 0 disables all the VFs.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_sriov_vfs(dev: String, num_vfs: u32) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<(String, u32)>(Node::Sriov(SubCommand::Set), (dev, num_vfs))
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Sets the MAC address or VLAN of a virtual function, which are set again at
+/// boot.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn configure_sriov_vf(dev: String, index: u32, config: VfConfig) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<(String, u32, VfConfig)>(
+        Node::Sriov(SubCommand::Update),
+        (dev, index, config),
+    ) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Stops enabling the virtual functions of a physical NIC at boot. The
+/// running VFs are kept until reboot.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn remove_sriov_settings(dev: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Sriov(SubCommand::Delete), dev) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the installed product license and whether its signature and
+/// expiry are valid, or None if no license is installed.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn license() -> Result<Option<LicenseStatus>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::License(SubCommand::Get), None) {
+        run_roxy::<Option<LicenseStatus>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Installs a product license. The license is a JSON document with the
+/// base64 `payload` of the JSON [`License`](common::License) and its base64
+/// `signature` by the product key. It is rejected if the signature is invalid
+/// or the license is expired.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn install_license(blob: Vec<u8>) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Vec<u8>>(Node::License(SubCommand::Set), blob) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Installs a compliance baseline pushed by the Manager, replacing the
+/// installed one. The baseline is a JSON document with the base64 `payload`
+/// of the JSON [`Baseline`](common::Baseline) and its base64 `signature` by
+/// the request signing key of the Manager. It is rejected if the public key
+/// of the Manager is not installed or the signature is invalid.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn install_baseline(blob: Vec<u8>) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Vec<u8>>(Node::Compliance(SubCommand::Set), blob) {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Removes the compliance baseline and its last report.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn remove_baseline() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Compliance(SubCommand::Delete), None)
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the report of the last [`enforce_compliance`], or None if it has
+/// not run since the baseline was installed.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn compliance_report() -> Result<Option<ComplianceReport>> {
+    compliance(SubCommand::Get)
+}
+
+/// Checks the system against the compliance baseline without changing it,
+/// or returns None if no baseline is installed.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn check_compliance() -> Result<Option<ComplianceReport>> {
+    compliance(SubCommand::Status)
+}
+
+/// Checks the system against the compliance baseline, restores the
+/// subsystems the baseline marks to remediate, and saves the report for
+/// [`compliance_report`]. It needs no signature of the Manager, since it
+/// only applies the signed baseline, so it can run as a scheduled job:
+///
+/// ```ignore
+/// let scheduler = Scheduler::from_conf()?.register("compliance", || {
+///     Ok(format!("{:?}", roxy::enforce_compliance()?))
+/// });
+/// ```
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn enforce_compliance() -> Result<Option<ComplianceReport>> {
+    compliance(SubCommand::Update)
+}
+
+fn compliance(cmd: SubCommand) -> Result<Option<ComplianceReport>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Compliance(cmd), None) {
+        run_roxy::<Option<ComplianceReport>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns kdump status, the crash kernel reservation, and vmcore files.
+///
+/// # Errors
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn kdump_status() -> Result<KdumpStatus> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Kdump(SubCommand::Status), None) {
+        run_roxy::<KdumpStatus>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Enables kdump and loads the crash kernel.
 ///
 /// # Errors
 ///
-/// * If serialization of command arguments does not succeed, then an error
-///   is returned.
-/// * If spawning the roxy executable fails, then an error is returned.
-/// * If delivering a command to roxy fails, then an error is returned.
-/// * If a response message from roxy is invalid regarding JSON syntax or
-///   is not successfully base64-decoded, then an error is returned.
-/// * If reading or writing of a product version file fails, then an error
-///   is returned.
-pub fn set_product_version(ver: String) -> Result<String> {
-    if let Ok(req) = NodeRequest::new::<String>(Node::Version(SubCommand::SetProductVersion), ver) {
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn enable_kdump() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Kdump(SubCommand::Enable), None) {
         run_roxy::<String>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Sets a hostname.
+/// Disables kdump and unloads the crash kernel.
 ///
 /// # Errors
 ///
-/// * If serialization of command arguments does not succeed, then an error
-///   is returned.
-/// * If spawning the roxy executable fails, then an error is returned.
-/// * If delivering a command to roxy fails, then an error is returned.
-/// * If a response message from roxy is invalid regarding JSON syntax or
-///   is not successfully base64-decoded, then an error is returned.
-/// * If `hostname::set` fails, then an error is returned.
-pub fn set_hostname(host: String) -> Result<String> {
-    if let Ok(req) = NodeRequest::new::<String>(Node::Hostname(SubCommand::Set), host) {
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn disable_kdump() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Kdump(SubCommand::Disable), None) {
         run_roxy::<String>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Returns tuples of (facilitiy, proto, addr) of syslog servers.
+/// Sets the memory reserved for the crash kernel, such as "512M-:192M". It
+/// takes effect after reboot.
 ///
 /// # Errors
 ///
-/// The following errors are possible:
-///
-/// * If serialization of command arguments does not succeed, then an error
-///   is returned.
-/// * If spawning the roxy executable fails, then an error is returned.
-/// * If delivering a command to roxy fails, then an error is returned.
-/// * If a response message from roxy is invalid regarding JSON syntax or
-///   is not successfully base64-decoded, then an error is returned.
-/// * If it fails to open `/etc/rsyslog.d/50-default.conf`, then an error
-///   is returned.
-pub fn syslog_servers() -> Result<Option<Vec<(String, String, String)>>> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Syslog(SubCommand::Get), None) {
-        run_roxy::<Option<Vec<(String, String, String)>>>(req)
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn set_crashkernel(reservation: String) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<String>(Node::Kdump(SubCommand::Set), reservation) {
+        run_roxy::<String>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Sets syslog servers.
+/// Converges the system to the desired state described in a JSON or YAML
+/// document. Subsystems which are not specified in the document are left
+/// untouched. See [`SystemState`] for the format of the document.
 ///
 /// # Errors
 ///
 /// The following errors are possible:
 ///
+/// * If the document is not a valid state document, then an error is
+///   returned.
 /// * If serialization of command arguments does not succeed, then an error
 ///   is returned.
 /// * If spawning the roxy executable fails, then an error is returned.
 /// * If delivering a command to roxy fails, then an error is returned.
 /// * If a response message from roxy is invalid regarding JSON syntax or
 ///   is not successfully base64-decoded, then an error is returned.
-/// * If it fails to open or write `/etc/rsyslog.d/50-default.conf`, then
-///   an error is returned.
-/// * If it fails to restart rsyslogd service, then an error is returned.
-pub fn set_syslog_servers(servers: Vec<String>) -> Result<String> {
-    if let Ok(req) = NodeRequest::new::<Vec<String>>(Node::Syslog(SubCommand::Set), servers) {
-        run_roxy::<String>(req)
+/// * If it fails to apply any subsystem, then all changes are rolled back
+///   and an error is returned.
+/// * If roxy is too old to know `StateV2`, then [`Unsupported`] is
+///   returned.
+pub fn apply_state(doc: &str) -> Result<Vec<StateChange>> {
+    let state = serde_yaml::from_str::<SystemState>(doc)
+        .map_err(|e| anyhow!("invalid state document. {}", e))?;
+    if let Ok(req) = NodeRequest::new::<SystemState>(Node::StateV2(SubCommand::Set), state) {
+        run_roxy::<Vec<StateChange>>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Initiates syslog servers.
+/// Returns the current state of the system in the same format used by
+/// [`apply_state`]. Use `to_string()` to get the state as a YAML document.
 ///
 /// # Errors
 ///
@@ -152,18 +2013,26 @@ pub fn set_syslog_servers(servers: Vec<String>) -> Result<String> {
 /// * If delivering a command to roxy fails, then an error is returned.
 /// * If a response message from roxy is invalid regarding JSON syntax or
 ///   is not successfully base64-decoded, then an error is returned.
-/// * If it fails to open or write `/etc/rsyslog.d/50-default.conf`, then
-///   an error is returned.
-/// * If it fails to restart rsyslogd service, then an error is returned.
-pub fn init_syslog_servers() -> Result<String> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Syslog(SubCommand::Init), None) {
-        run_roxy::<String>(req)
+/// * If it fails to read the configuration of any subsystem, then an error is
+///   returned.
+/// * If roxy is too old to know `StateV2`, then [`Unsupported`] is
+///   returned.
+pub fn export_state() -> Result<SystemState> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::StateV2(SubCommand::Get), None) {
+        run_roxy::<SystemState>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// (Re)start syslog services.
+/// Returns the state shared by the nodes of an HA pair: the state of
+/// [`export_state`] without the hostname and interfaces, which identify each
+/// node. The peer applies it with [`apply_replica`].
+///
+/// When a change to the shared state succeeds, roxy also runs
+/// `/etc/roxy/replication-hook`, if it exists, with the kind of the task as
+/// its argument and `{"kind": ..., "state": ...}` in JSON as its input, so
+/// that the hook can send the state to the peer.
 ///
 /// # Errors
 ///
@@ -175,36 +2044,51 @@ pub fn init_syslog_servers() -> Result<String> {
 /// * If delivering a command to roxy fails, then an error is returned.
 /// * If a response message from roxy is invalid regarding JSON syntax or
 ///   is not successfully base64-decoded, then an error is returned.
-/// * If it fails to restart rsyslogd service, then an error is returned.
-pub fn start_syslog_servers() -> Result<bool> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Syslog(SubCommand::Enable), None) {
-        run_roxy::<bool>(req)
+/// * If it fails to read the configuration of any subsystem, then an error is
+///   returned.
+pub fn export_replica() -> Result<SystemState> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Replica(SubCommand::Get), None) {
+        run_roxy::<SystemState>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Returns the list of interface names.
+/// Applies the state exported by the peer with [`export_replica`] as
+/// [`apply_state`] does. Applying a replica does not run the replication
+/// hook, so that it is not sent back to the peer.
 ///
 /// # Errors
 ///
 /// The following errors are possible:
 ///
+/// * If the state has a hostname or interfaces, then an error is returned.
 /// * If serialization of command arguments does not succeed, then an error
 ///   is returned.
 /// * If spawning the roxy executable fails, then an error is returned.
 /// * If delivering a command to roxy fails, then an error is returned.
 /// * If a response message from roxy is invalid regarding JSON syntax or
 ///   is not successfully base64-decoded, then an error is returned.
-pub fn list_of_interfaces(prefix: Option<String>) -> Result<Vec<String>> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Interface(SubCommand::List), prefix) {
-        run_roxy::<Vec<String>>(req)
+/// * If it fails to apply any subsystem, then all changes are rolled back
+///   and an error is returned.
+pub fn apply_replica(state: SystemState) -> Result<Vec<StateChange>> {
+    if state.has_identity() {
+        return Err(anyhow!(
+            "a replica should not have a hostname or interfaces"
+        ));
+    }
+    if let Ok(req) = NodeRequest::new::<SystemState>(Node::Replica(SubCommand::Set), state) {
+        run_roxy::<Vec<StateChange>>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Returns the settings of interface. All interfafces if None for device name
+/// Returns the differences between the system and the last state applied
+/// with [`apply_state`], e.g. made by editing a configuration file by hand or
+/// by an image update. Only the subsystems set in an applied state are
+/// compared, and the changes made by roxy afterward are not drift. Empty if
+/// no state has been applied.
 ///
 /// # Errors
 ///
@@ -216,15 +2100,23 @@ pub fn list_of_interfaces(prefix: Option<String>) -> Result<Vec<String>> {
 /// * If delivering a command to roxy fails, then an error is returned.
 /// * If a response message from roxy is invalid regarding JSON syntax or
 ///   is not successfully base64-decoded, then an error is returned.
-pub fn interfaces(dev: Option<String>) -> Result<Option<Vec<(String, NicOutput)>>> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Interface(SubCommand::Get), dev) {
-        run_roxy::<Option<Vec<(String, NicOutput)>>>(req)
+/// * If it fails to read the configuration of any subsystem, then an error is
+///   returned.
+pub fn config_drift() -> Result<Vec<DriftItem>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::State(SubCommand::Status), None) {
+        run_roxy::<Vec<DriftItem>>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Sets an interface setting.
+/// Returns the saved configurations of a subsystem, oldest first. All
+/// subsystems if None.
+///
+/// Roxy saves the configuration of a subsystem before and after changing it,
+/// and keeps the last 10 configurations per subsystem. Only the subsystems in
+/// [`Subsystem`] are saved; other configuration, e.g. of WireGuard, keepalived,
+/// or systemd units, has no history.
 ///
 /// # Errors
 ///
@@ -236,209 +2128,265 @@ pub fn interfaces(dev: Option<String>) -> Result<Option<Vec<(String, NicOutput)>
 /// * If delivering a command to roxy fails, then an error is returned.
 /// * If a response message from roxy is invalid regarding JSON syntax or
 ///   is not successfully base64-decoded, then an error is returned.
-/// * If it fails to read or write a netplan yaml conf file, then an error
-///   is returned.
-/// * If dhcp4 and static ip address or nameserver address is set in the same
-///   interface, then an error is returned.
-/// * If a user tries to set a new gateway address when another interface has
-///   the same, then an error is returned.
-pub fn set_interface(
-    dev: String,
-    addresses: Option<Vec<String>>,
-    dhcp4: Option<bool>,
-    gateway4: Option<String>,
-    nameservers: Option<Vec<String>>,
-) -> Result<String> {
-    let nic = NicOutput::new(addresses, dhcp4, gateway4, nameservers);
+/// * If it fails to read the saved configurations, then an error is returned.
+pub fn config_history(subsystem: Option<Subsystem>) -> Result<Vec<HistoryEntry>> {
     if let Ok(req) =
-        NodeRequest::new::<(String, NicOutput)>(Node::Interface(SubCommand::Set), (dev, nic))
+        NodeRequest::new::<Option<Subsystem>>(Node::History(SubCommand::Get), subsystem)
     {
-        run_roxy::<String>(req)
+        run_roxy::<Vec<HistoryEntry>>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Init the settings of an interface.
+/// Defers a request to a maintenance window. Only reboot, power off,
+/// restarting services, and updating compose stacks can be deferred; see
+/// [`common::is_deferrable`]. The request is executed by
+/// [`run_due_operations`] in the window, unless it expires first.
+///
+/// # Example
+///
+/// ```ignore
+/// let window = MaintenanceWindow { start: "02:00".into(), end: "04:00".into() };
+/// let reboot = NodeRequest::new::<Option<String>>(Node::Reboot, None)?;
+/// let id = roxy::defer_to_window(&reboot, window, None)?;
+/// ```
 ///
 /// # Errors
-/// * If serialization of command arguments does not succeed, then an error
-///   is returned.
-/// * If spawning the roxy executable fails, then an error is returned.
-/// * If delivering a command to roxy fails, then an error is returned.
-/// * If a response message from roxy is invalid regarding JSON syntax or
-///   is not successfully base64-decoded, then an error is returned.
-/// * If the specified interface name is not found, then an error is returned.
-/// * If it failed to load /etc/netplan yaml files, then an error is returned.
-/// * If if failed to execute netplan apply command, then an error is returned.
-/// * If it failed to execute ifconfig command, then an error is returned.
-pub fn init_interface(dev: String) -> Result<String> {
-    if let Ok(req) =
-        NodeRequest::new::<Option<String>>(Node::Interface(SubCommand::Init), Some(dev))
-    {
-        run_roxy::<String>(req)
+///
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn defer_to_window(
+    request: &NodeRequest,
+    window: MaintenanceWindow,
+    expires: Option<String>,
+) -> Result<u64> {
+    // The deferred request is sent in JSON, where its task kind is a name
+    // rather than an index, which differs between versions.
+    let request = serde_json::to_string(request)?;
+    if let Ok(req) = NodeRequest::new::<(String, MaintenanceWindow, Option<String>)>(
+        Node::Maintenance(SubCommand::Add),
+        (request, window, expires),
+    ) {
+        run_roxy::<u64>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Removes interface/gateway/nameserver address or dhcp4 option of interface.
+/// Returns the deferred operations which are queued or finished recently,
+/// with their status.
 ///
 /// # Errors
 ///
-/// The following errors are possible:
-///
-/// * If serialization of command arguments does not succeed, then an error
-///   is returned.
-/// * If spawning the roxy executable fails, then an error is returned.
-/// * If delivering a command to roxy fails, then an error is returned.
-/// * If a response message from roxy is invalid regarding JSON syntax or
-///   is not successfully base64-decoded, then an error is returned.
-/// * If it fails to read or write a netplan yaml conf file, then an error
-///   is returned.
-pub fn remove_interface(
-    dev: String,
-    addresses: Option<Vec<String>>,
-    dhcp4: Option<bool>,
-    gateway4: Option<String>,
-    nameservers: Option<Vec<String>>,
-) -> Result<String> {
-    let nic = NicOutput::new(addresses, dhcp4, gateway4, nameservers);
-    if let Ok(req) =
-        NodeRequest::new::<(String, NicOutput)>(Node::Interface(SubCommand::Delete), (dev, nic))
-    {
-        run_roxy::<String>(req)
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn deferred_operations() -> Result<Vec<DeferredOperation>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Maintenance(SubCommand::Get), None) {
+        run_roxy::<Vec<DeferredOperation>>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Reboots the system.
+/// Cancels a queued operation.
 ///
 /// # Errors
 ///
-/// The following errors are possible:
-///
-/// * If serialization of command arguments does not succeed, then an error
-///   is returned.
-/// * If spawning the roxy executable fails, then an error is returned.
-/// * If delivering a command to roxy fails, then an error is returned.
-/// * If a response message from roxy is invalid regarding JSON syntax or
-///   is not successfully base64-decoded, then an error is returned.
-/// * If `nix::sys::reboot::reboot` fails, then an error is returned.
-pub fn reboot() -> Result<String> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Reboot, None) {
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn cancel_deferred(id: u64) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<u64>(Node::Maintenance(SubCommand::Delete), id) {
         run_roxy::<String>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Turns the system off.
+/// Executes the deferred operations whose window has come, and returns the
+/// operations executed or expired. Call it periodically, e.g. as a job of a
+/// [`Scheduler`] every minute. If a reboot is due, it does not return.
 ///
 /// # Errors
 ///
-/// The following errors are possible:
-///
-/// * If serialization of command arguments does not succeed, then an error
-///   is returned.
-/// * If spawning the roxy executable fails, then an error is returned.
-/// * If delivering a command to roxy fails, then an error is returned.
-/// * If a response message from roxy is invalid regarding JSON syntax or
-///   is not successfully base64-decoded, then an error is returned.
-/// * If `nix::sys::reboot::reboot` fails, then an error is returned.
-pub fn power_off() -> Result<String> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::PowerOff, None) {
-        run_roxy::<String>(req)
+/// * Return error if it fails to build request message
+/// * Return error if `run_roxy` function returns error
+pub fn run_due_operations() -> Result<Vec<DeferredOperation>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Maintenance(SubCommand::Update), None)
+    {
+        run_roxy::<Vec<DeferredOperation>>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Return configured sshd port number.
+/// Returns the last run of each job run by a [`Scheduler`], sorted by the
+/// job name.
 ///
 /// # Errors
 ///
 /// * Return error if it fails to build request message
 /// * Return error if `run_roxy` function returns error
-pub fn get_sshd() -> Result<u16> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Sshd(SubCommand::Get), None) {
-        run_roxy::<u16>(req)
+pub fn job_runs() -> Result<Vec<JobRun>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Schedule(SubCommand::Get), None) {
+        run_roxy::<Vec<JobRun>>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Restart sshd service.
+/// Records the run of a scheduled job, replacing the previous run of the
+/// job. [`Scheduler`] records its runs with this.
 ///
 /// # Errors
 ///
 /// * Return error if it fails to build request message
 /// * Return error if `run_roxy` function returns error
-pub fn start_sshd() -> Result<bool> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Sshd(SubCommand::Enable), None) {
-        run_roxy::<bool>(req)
+pub fn record_job_run(run: JobRun) -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<JobRun>(Node::Schedule(SubCommand::Set), run) {
+        run_roxy::<String>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Return configured NTP server FQDNs
+/// Returns the changes of the system made by roxy after the event `since`,
+/// oldest first. See [`ChangeEvent`] for the tasks recorded. At most 1,000 events are returned at a time; the
+/// `seq` of the last one is the cursor to fetch the following events. Use 0
+/// to fetch from the oldest event kept.
 ///
 /// # Errors
 ///
 /// * Return error if it fails to build request message
 /// * Return error if `run_roxy` function returns error
-pub fn get_ntp() -> Result<Option<Vec<String>>> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Ntp(SubCommand::Get), None) {
-        run_roxy::<Option<Vec<String>>>(req)
+pub fn change_events(since: u64) -> Result<Vec<ChangeEvent>> {
+    if let Ok(req) = NodeRequest::new::<u64>(Node::Changes(SubCommand::Get), since) {
+        run_roxy::<Vec<ChangeEvent>>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Set ntp servers
+/// Reverts the configuration of a subsystem to a saved one. Configuration
+/// outside the subsystems in [`Subsystem`] is not reverted.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If the saved configuration is not found, then an error is returned.
+/// * If it fails to restore or apply the configuration, then an error is
+///   returned.
+pub fn revert_config(subsystem: Subsystem, id: i64) -> Result<String> {
+    if let Ok(req) =
+        NodeRequest::new::<(Subsystem, i64)>(Node::History(SubCommand::Revert), (subsystem, id))
+    {
+        run_roxy::<String>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Returns the number of tasks, failures, and latency histogram of each task
+/// kind handled by roxy since the statistics were last reset.
+///
+/// # Errors
+///
+/// The following errors are possible:
+///
+/// * If serialization of command arguments does not succeed, then an error
+///   is returned.
+/// * If spawning the roxy executable fails, then an error is returned.
+/// * If delivering a command to roxy fails, then an error is returned.
+/// * If a response message from roxy is invalid regarding JSON syntax or
+///   is not successfully base64-decoded, then an error is returned.
+/// * If it fails to read the statistics, then an error is returned.
+pub fn task_stats() -> Result<Vec<TaskStats>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Stats(SubCommand::Get), None) {
+        run_roxy::<Vec<TaskStats>>(req)
+    } else {
+        Err(anyhow!(FAIL_REQUEST))
+    }
+}
+
+/// Clears the statistics of the tasks handled by roxy.
 ///
 /// # Errors
 ///
 /// * Return error if it fails to build request message
 /// * Return error if `run_roxy` function returns error
-pub fn set_ntp(servers: Vec<String>) -> Result<bool> {
-    if let Ok(req) = NodeRequest::new::<Vec<String>>(Node::Ntp(SubCommand::Get), servers) {
-        run_roxy::<bool>(req)
+pub fn reset_task_stats() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Stats(SubCommand::Delete), None) {
+        run_roxy::<String>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// (Re)Start ntp service
+/// Returns the reports of the panics of roxy, oldest first, which are kept
+/// until they are cleared. Each report has the version of roxy, the task it
+/// was executing, and the backtrace.
 ///
 /// # Errors
 ///
 /// * Return error if it fails to build request message
 /// * Return error if `run_roxy` function returns error
-pub fn start_ntp() -> Result<bool> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Ntp(SubCommand::Enable), None) {
-        run_roxy::<bool>(req)
+pub fn crash_reports() -> Result<Vec<CrashReport>> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Crash(SubCommand::Get), None) {
+        run_roxy::<Vec<CrashReport>>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
-/// Stop ntp service
+/// Clears the reports of the panics of roxy.
 ///
 /// # Errors
 ///
 /// * Return error if it fails to build request message
 /// * Return error if `run_roxy` function returns error
-pub fn stop_ntp() -> Result<bool> {
-    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Ntp(SubCommand::Disable), None) {
-        run_roxy::<bool>(req)
+pub fn clear_crash_reports() -> Result<String> {
+    if let Ok(req) = NodeRequest::new::<Option<String>>(Node::Crash(SubCommand::Delete), None) {
+        run_roxy::<String>(req)
     } else {
         Err(anyhow!(FAIL_REQUEST))
     }
 }
 
+/// The error for a task which roxy does not know, e.g. because roxy is older
+/// than this library. It holds the kind of the task, e.g. `Interface::Set`.
+///
+/// [`TaskResult::decode`] and the functions sending a request return it, which
+/// can be checked with [`anyhow::Error::downcast_ref`].
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("unsupported task: {0}")]
+pub struct Unsupported(pub String);
+
+/// The error for a response larger than [`common::MAX_RESPONSE_SIZE`]. A list
+/// that large should be requested a [`common::Page`] at a time.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("response too large")]
+pub struct ResponseTooLarge;
+
+/// The error for an interface address which conflicts with another
+/// interface or host. It holds the conflict, e.g. `10.0.0.0/24 overlaps
+/// 10.0.0.0/16 of eth1`, which names the other interface.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("address conflict: {0}")]
+pub struct AddressConflict(pub String);
+
+/// The error for a diagnostic, such as smartctl, killed by the kernel at the
+/// memory limit set in `/etc/roxy/limits.yaml`. It holds the diagnostic.
+#[derive(Debug, Error, PartialEq, Eq)]
+#[error("resource limit exceeded: {0}")]
+pub struct LimitExceeded(pub String);
+
 /// Response message from Roxy to caller
 #[derive(Deserialize, Debug)]
 pub enum TaskResult {
@@ -446,46 +2394,87 @@ pub enum TaskResult {
     Err(String),
 }
 
-// TODO: fix the exact path to "roxy"
-//
+impl TaskResult {
+    /// Decodes the response message.
+    ///
+    /// # Errors
+    ///
+    /// * base64 decode error for reponse message
+    /// * Received execution error from roxy
+    /// * [`Unsupported`] if roxy does not know the task
+    /// * [`ResponseTooLarge`] if the response exceeds the size limit
+    /// * [`AddressConflict`] if an address conflicts with another interface
+    /// * [`LimitExceeded`] if a diagnostic exceeds its memory limit
+    pub fn decode<T>(self) -> Result<T>
+    where
+        T: serde::de::DeserializeOwned,
+    {
+        match self {
+            TaskResult::Ok(x) => {
+                let decoded = BASE64
+                    .decode(x.as_bytes())
+                    .map_err(|_| anyhow!("fail to decode response."))?;
+                Ok(bincode::deserialize::<T>(&decoded)?)
+            }
+            TaskResult::Err(x) if x == common::RESPONSE_TOO_LARGE => Err(ResponseTooLarge.into()),
+            TaskResult::Err(x) => {
+                let detail = |prefix: &str| x.strip_prefix(prefix)?.strip_prefix(": ");
+                if let Some(kind) = detail(common::UNSUPPORTED_TASK) {
+                    Err(Unsupported(kind.to_string()).into())
+                } else if let Some(conflict) = detail(common::ADDRESS_CONFLICT) {
+                    Err(AddressConflict(conflict.to_string()).into())
+                } else if let Some(program) = detail(common::LIMIT_EXCEEDED) {
+                    Err(LimitExceeded(program.to_string()).into())
+                } else {
+                    Err(anyhow!("{}", x))
+                }
+            }
+        }
+    }
+}
+
+/// Sends a request to roxy using the default [`RoxyClient`].
+///
 /// # Errors
 ///
+/// * Failure to convert request message to JSON
 /// * Failure to spawn roxy
 /// * Failure to write command to roxy
 /// * Invalid json syntax in response message
 /// * base64 decode error for reponse message
 /// * Received execution error from roxy
-///
-/// # Panics
-///
-/// * panic if it failed to convert request message to json
 pub fn run_roxy<T>(req: NodeRequest) -> Result<T>
 where
     T: serde::de::DeserializeOwned,
 {
-    let mut child = Command::new("roxy")
-        .env("PATH", "/usr/local/aice/bin")
-        .stdin(Stdio::piped())
-        .stdout(Stdio::piped())
-        .spawn()?;
-
-    if let Some(child_stdin) = child.stdin.take() {
-        std::thread::spawn(move || {
-            serde_json::to_writer(child_stdin, &req).expect("`Task` should serialize to JSON");
-        });
-    } else {
-        return Err(anyhow!("failed to execute roxy"));
-    }
-
-    let output = child.wait_with_output()?;
-    match serde_json::from_reader::<&[u8], TaskResult>(&output.stdout) {
-        Ok(TaskResult::Ok(x)) => {
-            let decoded = BASE64
-                .decode(x.as_bytes())
-                .map_err(|_| anyhow!("fail to decode response."))?;
-            Ok(bincode::deserialize::<T>(&decoded)?)
-        }
-        Ok(TaskResult::Err(x)) => Err(anyhow!("{}", x)),
-        Err(e) => Err(anyhow!("fail to parse response. {}", e)),
-    }
+    RoxyClient::default().run(req)
+}
+
+/// Sends requests to roxy at once using the default [`RoxyClient`]. The
+/// requests are executed in a single roxy invocation, as described in
+/// [`RoxyClient::run_batch`].
+///
+/// # Errors
+///
+/// * Failure to convert request message to JSON
+/// * Failure to spawn roxy
+/// * Failure to write command to roxy
+/// * Invalid json syntax in response message
+pub fn run_roxy_batch(reqs: Vec<NodeRequest>) -> Result<Vec<TaskResult>> {
+    RoxyClient::default().run_batch(reqs)
+}
+
+/// Sends requests to roxy as a transaction using the default [`RoxyClient`].
+/// If any request fails, the system configuration is rolled back to the state
+/// before the transaction. A transaction with a request which cannot be
+/// rolled back is refused, as described in [`RoxyClient::run_transaction`].
+///
+/// # Errors
+///
+/// * Failure to convert request message to JSON
+/// * Failure to spawn roxy
+/// * Failure to write command to roxy
+/// * Invalid json syntax in response message
+pub fn run_roxy_transaction(reqs: Vec<NodeRequest>) -> Result<Vec<TaskResult>> {
+    RoxyClient::default().run_transaction(reqs)
 }
@@ -1,21 +1,63 @@
 mod root;
 
 use std::{
-    io::{stdin, stdout},
+    env,
+    io::{stdin, stdout, Read},
     process,
 };
 
 use data_encoding::BASE64;
-use root::task::{ExecResult, Task, ERR_INVALID_COMMAND};
-use roxy::common::{self, Node, NodeRequest};
+use log::{LevelFilter, Log, Metadata, Record};
+use root::task::{log_debug, ExecResult, Task, ERR_INVALID_COMMAND, ERR_PERMISSION_DENIED};
+use roxy::common::{self, Node, NodeRequest, RoxyRequest};
+
+// Forwards log records to the roxy log file or syslog.
+struct Logger;
+
+impl Log for Logger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            root::logging::write(Some(record.level()), &record.args().to_string());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+static LOGGER: Logger = Logger;
 
 fn main() {
-    let nr: NodeRequest = match serde_json::from_reader(stdin()) {
-        Ok(nr) => nr,
+    init_logger();
+    sanitize_env();
+    root::crash::install_hook();
+    if let Err(e) = root::journal::recover() {
+        log_debug(&format!("fail to recover interrupted changes. {e:?}"));
+    }
+
+    let mut buf = Vec::new();
+    if let Err(err) = stdin().take(common::MAX_REQUEST_SIZE).read_to_end(&mut buf) {
+        log::error!("Stdin Error: {}", err);
+        process::exit(1);
+    }
+    let req: RoxyRequest = match serde_json::from_slice(&buf) {
+        Ok(req) => req,
         Err(err) => {
+            // A newer library may send task kinds unknown to this version.
+            if let Some(ret) = root::unsupported::respond(&buf, execute) {
+                if let Err(err) = serde_json::to_writer_pretty(stdout(), &ret) {
+                    log::error!("Stdout Error: {}", err);
+                    process::exit(1);
+                }
+                return;
+            }
+
             log::error!("Command Error: {}", err);
             if let Err(err) =
-                serde_json::to_writer_pretty(stdout(), &ExecResult::Err(ERR_INVALID_COMMAND))
+                serde_json::to_writer_pretty(stdout(), &ExecResult::Err(ERR_INVALID_COMMAND.into()))
             {
                 log::error!("Serialize Error: {}", err);
             }
@@ -23,23 +65,160 @@ fn main() {
         }
     };
 
+    let ret = match req {
+        RoxyRequest::Batch(nrs) => {
+            serde_json::to_writer_pretty(stdout(), &root::batch::execute(&nrs, execute))
+        }
+        RoxyRequest::Single(nr) => serde_json::to_writer_pretty(stdout(), &execute(&nr)),
+        RoxyRequest::Transaction { transaction } => {
+            // No task is executed if any of them is not allowed.
+            let denied: Vec<bool> = transaction.iter().map(|nr| !authorized(nr)).collect();
+            let ret = if denied.contains(&true) {
+                denied
+                    .iter()
+                    .map(|&denied| {
+                        Err(if denied {
+                            ERR_PERMISSION_DENIED
+                        } else {
+                            root::transaction::ERR_NOT_EXECUTED
+                        }
+                        .into())
+                    })
+                    .collect()
+            } else {
+                let tasks: Vec<Task> = transaction.iter().map(task).collect();
+                root::transaction::execute(&tasks)
+            };
+            serde_json::to_writer_pretty(stdout(), &ret)
+        }
+    };
+    if let Err(err) = ret {
+        log::error!("Stdout Error: {}", err);
+        process::exit(1);
+    }
+}
+
+// Enables logging at the level given by `RUST_LOG`, e.g. "debug" to log the
+// commands spawned by roxy. Logging is disabled if it is not set.
+//
+// `ROXY_LOG_TARGET` sets where the log goes: "file" for the roxy log file,
+// which is the default, "syslog" for the local syslog daemon, or "both".
+// The log file is rotated when it reaches `ROXY_LOG_MAX_SIZE` bytes, keeping
+// `ROXY_LOG_KEEP` rotated files.
+fn init_logger() {
+    let mut rotation = root::logging::Rotation::default();
+    if let Some(max_size) = env::var("ROXY_LOG_MAX_SIZE")
+        .ok()
+        .and_then(|size| size.parse().ok())
+    {
+        rotation.max_size = max_size;
+    }
+    if let Some(keep) = env::var("ROXY_LOG_KEEP")
+        .ok()
+        .and_then(|keep| keep.parse().ok())
+    {
+        rotation.keep = keep;
+    }
+    root::logging::set_rotation(rotation);
+    if let Ok(target) = env::var("ROXY_LOG_TARGET") {
+        match target.parse() {
+            Ok(target) => root::logging::set_target(target),
+            Err(e) => log_debug(&e.to_string()),
+        }
+    }
+    let level = env::var("RUST_LOG")
+        .ok()
+        .and_then(|level| level.parse::<LevelFilter>().ok())
+        .unwrap_or(LevelFilter::Off);
+    if log::set_logger(&LOGGER).is_ok() {
+        log::set_max_level(level);
+    }
+}
+
+// Replaces the environment inherited from the caller, which is also used by
+// the commands spawned by the libraries such as systemctl.
+fn sanitize_env() {
+    for (key, _) in env::vars_os() {
+        env::remove_var(key);
+    }
+    for (key, val) in common::SANITIZED_ENV {
+        env::set_var(key, val);
+    }
+}
+
+// Executes the request if its role allows it, and its signature is valid
+// if it needs one.
+fn execute(nr: &NodeRequest) -> ExecResult {
+    if authorized(nr) {
+        task(nr).execute()
+    } else {
+        Err(ERR_PERMISSION_DENIED.into())
+    }
+}
+
+fn authorized(nr: &NodeRequest) -> bool {
+    match root::role::authorize(nr).and_then(|()| root::signing::verify_request(nr)) {
+        Ok(()) => true,
+        Err(e) => {
+            log_debug(&format!("request denied. {e:?}"));
+            false
+        }
+    }
+}
+
+fn task(nr: &NodeRequest) -> Task {
     let arg = BASE64.encode(&nr.arg);
-    let task = match nr.kind {
+    match nr.kind {
+        Node::Audit(cmd) => Task::Audit { cmd, arg },
+        Node::Banner(cmd) => Task::Banner { cmd, arg },
+        Node::Bond(cmd) => Task::Bond { cmd, arg },
+        Node::Capture(cmd) => Task::Capture { cmd, arg },
+        Node::Changes(cmd) => Task::Changes { cmd, arg },
+        Node::Cleanup(cmd) => Task::Cleanup { cmd, arg },
+        Node::Compliance(cmd) => Task::Compliance { cmd, arg },
+        Node::Compose(cmd) => Task::Compose { cmd, arg },
+        Node::Connectivity(cmd) => Task::Connectivity { cmd, arg },
+        Node::CpuGovernor(cmd) => Task::CpuGovernor { cmd, arg },
+        Node::Crash(cmd) => Task::Crash { cmd, arg },
+        Node::Dns(cmd) => Task::Dns { cmd, arg },
+        Node::Filesystem(cmd) => Task::Filesystem { cmd, arg },
+        Node::Fstrim(cmd) => Task::Fstrim { cmd, arg },
+        Node::Hardware(cmd) => Task::Hardware { cmd, arg },
+        Node::History(cmd) => Task::History { cmd, arg },
         Node::Hostname(cmd) => Task::Hostname { cmd, arg },
+        Node::Info(cmd) => Task::Info { cmd, arg },
         Node::Interface(cmd) => Task::Interface { cmd, arg },
+        Node::InterfaceGroup(cmd) => Task::InterfaceGroup { cmd, arg },
+        Node::InterfaceV2(cmd) => Task::InterfaceV2 { cmd, arg },
+        Node::IrqAffinity(cmd) => Task::IrqAffinity { cmd, arg },
+        Node::Kdump(cmd) => Task::Kdump { cmd, arg },
+        Node::License(cmd) => Task::License { cmd, arg },
+        Node::Maintenance(cmd) => Task::Maintenance { cmd, arg },
+        Node::Netns(cmd) => Task::Netns { cmd, arg },
         Node::Ntp(cmd) => Task::Ntp { cmd, arg },
+        Node::Ping => Task::Ping(arg),
         Node::PowerOff => Task::PowerOff(arg),
+        Node::Preflight => Task::Preflight(arg),
+        Node::Raid(cmd) => Task::Raid { cmd, arg },
         Node::Reboot => Task::Reboot(arg),
+        Node::Replica(cmd) => Task::Replica { cmd, arg },
+        Node::Schedule(cmd) => Task::Schedule { cmd, arg },
         Node::Service(cmd) => Task::Service { cmd, arg },
+        Node::ServiceEnvironment(cmd) => Task::ServiceEnvironment { cmd, arg },
+        Node::ServiceGroup(cmd) => Task::ServiceGroup { cmd, arg },
+        Node::SessionAudit(cmd) => Task::SessionAudit { cmd, arg },
+        Node::Smart(cmd) => Task::Smart { cmd, arg },
+        Node::Sriov(cmd) => Task::Sriov { cmd, arg },
         Node::Sshd(cmd) => Task::Sshd { cmd, arg },
+        Node::State(cmd) => Task::State { cmd, arg },
+        Node::StateV2(cmd) => Task::StateV2 { cmd, arg },
+        Node::Stats(cmd) => Task::Stats { cmd, arg },
         Node::Syslog(cmd) => Task::Syslog { cmd, arg },
+        Node::Tuning(cmd) => Task::Tuning { cmd, arg },
+        Node::Tunnel(cmd) => Task::Tunnel { cmd, arg },
         Node::Ufw(cmd) => Task::Ufw { cmd, arg },
         Node::Version(cmd) => Task::Version { cmd, arg },
-    };
-
-    let ret = task.execute();
-    if let Err(err) = serde_json::to_writer_pretty(stdout(), &ret) {
-        log::error!("Stdout Error: {}", err);
-        process::exit(1);
+        Node::Vrrp(cmd) => Task::Vrrp { cmd, arg },
+        Node::Wireguard(cmd) => Task::Wireguard { cmd, arg },
     }
 }
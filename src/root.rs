@@ -1,9 +1,60 @@
+mod audit;
+mod banner;
+pub(crate) mod batch;
+mod bond;
+mod capture;
+mod changes;
+mod cleanup;
+mod compliance;
+mod compose;
+mod connectivity;
+mod cpu_governor;
+pub(crate) mod crash;
+mod dns;
+mod filesystem;
+mod fqdn;
+mod fstrim;
+mod hardware;
+mod history;
+mod hook;
 mod hwinfo;
 mod ifconfig;
+mod info;
+mod interface_group;
+mod irq_affinity;
+pub(crate) mod journal;
+mod kdump;
+mod license;
+mod limits;
+pub(crate) mod logging;
+mod mac;
+mod maintenance;
+mod netns;
 mod ntp;
+mod persist;
+mod preflight;
+mod raid;
+mod replica;
+pub(crate) mod role;
+mod schedule;
+mod service_environment;
 mod services;
+pub(crate) mod signing;
+mod smart;
+mod sriov;
 mod sshd;
+mod state;
+mod stats;
 mod syslog;
 pub(crate) mod task;
+pub(crate) mod transaction;
+mod tuning;
+mod tunnel;
+mod ufw;
+pub(crate) mod unsupported;
+mod vrrp;
+mod wireguard;
 
-use super::common::{Nic, NicOutput, SubCommand};
+use super::common::{
+    Nic, NicOutput, NicOutputV1, SubCommand, Subsystem, SystemState, SystemStateV1, ROXY_STATE_DIR,
+};
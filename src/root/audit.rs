@@ -0,0 +1,308 @@
+use std::{collections::BTreeMap, fmt::Write as FmtWrite, fs};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use roxy::common::{
+    command, run_command, AuditEvent, AuditRecord, AuditStatus, SessionAuditStatus,
+};
+
+use crate::root::{banner, fqdn, hwinfo, ifconfig, kdump, limits, ntp, persist, sshd, syslog, ufw};
+
+pub(crate) const PAM_SSHD: &str = "/etc/pam.d/sshd";
+pub(crate) const PAM_LOGIN: &str = "/etc/pam.d/login";
+const PAM_TTY_AUDIT: &str = "pam_tty_audit.so";
+const PAM_TTY_AUDIT_LINE: &str = "session required pam_tty_audit.so enable=*";
+const AUDITD_SERVICE_UNIT: &str = "auditd";
+pub(crate) const AUDIT_RULES: &str = "/etc/audit/rules.d/90-roxy.rules";
+const CONFIG_KEY: &str = "roxy-config";
+const EXEC_KEY: &str = "roxy-exec";
+const PRIVILEGED_BINARIES: [&str; 4] = [
+    "/usr/bin/su",
+    "/usr/bin/sudo",
+    "/usr/local/aice/bin/roxy",
+    "/usr/sbin/usermod",
+];
+const SEARCH_STARTS: [&str; 5] = ["recent", "today", "yesterday", "this-week", "this-month"];
+const DEFAULT_SEARCH_START: &str = "today";
+const MAX_AUDIT_EVENTS: usize = 1000;
+
+// Installs the ruleset which watches the configuration files managed by roxy,
+// and logs execution of privileged binaries, then loads it.
+//
+// # Errors
+//
+// * fail to write /etc/audit/rules.d/90-roxy.rules
+// * fail to execute augenrules
+pub(crate) fn enable() -> Result<bool> {
+    persist::write(AUDIT_RULES, rules())?;
+    load_rules()
+}
+
+// Removes the ruleset, and reloads the rules.
+//
+// # Errors
+//
+// * fail to remove /etc/audit/rules.d/90-roxy.rules
+// * fail to execute augenrules
+pub(crate) fn disable() -> Result<bool> {
+    persist::remove(AUDIT_RULES)?;
+    load_rules()
+}
+
+// Gets the status of auditd and the ruleset.
+//
+// # Errors
+//
+// * fail to execute auditctl
+pub(crate) fn status() -> Result<AuditStatus> {
+    let active = systemctl::SystemCtl::default()
+        .is_active(AUDITD_SERVICE_UNIT)
+        .unwrap_or_default();
+    let installed = fs::read_to_string(AUDIT_RULES).is_ok_and(|contents| contents == rules());
+    let output = run_command(command("auditctl").arg("-l"))?;
+    if !output.status.success() {
+        return Err(anyhow!("fail to list audit rules"));
+    }
+    let loaded_rules = String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .filter(|line| line.starts_with('-'))
+        .count();
+    Ok(AuditStatus {
+        active,
+        installed,
+        loaded_rules,
+    })
+}
+
+// Gets the audit events generated by the ruleset since `start`, which is one
+// of "recent", "today", "yesterday", "this-week", and "this-month". At most
+// the last 1000 events are returned.
+//
+// # Errors
+//
+// * invalid start
+// * fail to execute ausearch
+// * ausearch exceeds its memory limit
+pub(crate) fn events(start: Option<&str>) -> Result<Vec<AuditEvent>> {
+    let start = start.unwrap_or(DEFAULT_SEARCH_START);
+    if !SEARCH_STARTS.contains(&start) {
+        return Err(anyhow!("invalid start: {}", start));
+    }
+    let output = limits::run(
+        "ausearch",
+        &["--raw", "--start", start, "-k", CONFIG_KEY, "-k", EXEC_KEY],
+    )?;
+    // ausearch exits with 1 if no events are found.
+    if !output.status.success() && !output.stdout.is_empty() {
+        return Err(anyhow!("fail to search audit events"));
+    }
+    let mut events = parse_raw_events(&String::from_utf8_lossy(&output.stdout));
+    if events.len() > MAX_AUDIT_EVENTS {
+        events.drain(..events.len() - MAX_AUDIT_EVENTS);
+    }
+    Ok(events)
+}
+
+fn rules() -> String {
+    let mut paths = vec![
+        banner::ISSUE,
+        banner::ISSUE_NET,
+        banner::MOTD,
+        fqdn::DOMAIN_SYSCTL_CONF,
+        fqdn::HOSTS,
+        hwinfo::DEFAULT_VERSION_PATH,
+        ifconfig::NETPLAN_PATH,
+        kdump::KDUMP_CONF,
+        kdump::KDUMP_GRUB_CONF,
+        ntp::NTP_CONF,
+        PAM_LOGIN,
+        PAM_SSHD,
+        sshd::SSHD_CONFIG,
+        syslog::RSYSLOG_CONF,
+        ufw::UFW_CONF,
+        ufw::UFW_USER_RULES,
+        ufw::UFW_USER6_RULES,
+    ];
+    paths.sort_unstable();
+
+    let mut rules = String::from("## Managed by roxy. Do not edit.\n");
+    for path in paths {
+        writeln!(rules, "-w {path} -p wa -k {CONFIG_KEY}")
+            .expect("writing to string should not fail");
+    }
+    for path in PRIVILEGED_BINARIES {
+        writeln!(
+            rules,
+            "-a always,exit -F path={path} -F perm=x -F auid>=1000 -F auid!=unset -k {EXEC_KEY}"
+        )
+        .expect("writing to string should not fail");
+    }
+    rules
+}
+
+fn load_rules() -> Result<bool> {
+    let output = run_command(command("augenrules").arg("--load"))?;
+    Ok(output.status.success())
+}
+
+// Parses raw audit records such as:
+//
+// type=SYSCALL msg=audit(1700000000.123:456): arch=c000003e syscall=257 success=yes key="roxy-config"
+fn parse_raw_events(output: &str) -> Vec<AuditEvent> {
+    let mut events: Vec<AuditEvent> = Vec::new();
+    for line in output.lines() {
+        let Some((kind, rest)) = line
+            .strip_prefix("type=")
+            .and_then(|line| line.split_once(" msg=audit("))
+        else {
+            continue;
+        };
+        let Some((stamp, rest)) = rest.split_once("):") else {
+            continue;
+        };
+        let Some((time, serial)) = stamp.split_once(':') else {
+            continue;
+        };
+        let Ok(serial) = serial.parse::<u64>() else {
+            continue;
+        };
+
+        let fields = rest
+            .split_whitespace()
+            .filter_map(|field| field.split_once('='))
+            .map(|(k, v)| (k.to_string(), v.trim_matches('"').to_string()))
+            .collect::<BTreeMap<_, _>>();
+        let key = fields.get("key").filter(|key| *key != "(null)").cloned();
+        let record = AuditRecord {
+            kind: kind.to_string(),
+            fields,
+        };
+
+        if let Some(event) = events.iter_mut().rev().find(|e| e.serial == serial) {
+            if event.key.is_none() {
+                event.key = key;
+            }
+            event.records.push(record);
+        } else {
+            events.push(AuditEvent {
+                serial,
+                time: audit_time(time),
+                key,
+                records: vec![record],
+            });
+        }
+    }
+    events
+}
+
+fn audit_time(time: &str) -> String {
+    let (secs, millis) = time.split_once('.').unwrap_or((time, "0"));
+    let secs = secs.parse::<i64>().unwrap_or_default();
+    let nanos = millis.parse::<u32>().unwrap_or_default() * 1_000_000;
+    DateTime::from_timestamp(secs, nanos)
+        .map(|t| t.with_timezone(&Local).to_rfc3339())
+        .unwrap_or_default()
+}
+
+// Enables or disables recording of the keystrokes of interactive SSH and
+// console sessions to the audit log using pam_tty_audit. The change applies
+// to new sessions.
+//
+// # Errors
+//
+// * fail to read or write /etc/pam.d/sshd or /etc/pam.d/login
+pub(crate) fn set_session_audit(enable: bool) -> Result<()> {
+    for path in [PAM_SSHD, PAM_LOGIN] {
+        let contents = fs::read_to_string(path)?;
+        let new_contents = update_pam(&contents, enable);
+        if new_contents != contents {
+            persist::write(path, new_contents)?;
+        }
+    }
+    Ok(())
+}
+
+// Gets whether session recording is enabled, and auditd is running.
+//
+// # Errors
+//
+// * fail to read /etc/pam.d/sshd or /etc/pam.d/login
+pub(crate) fn session_audit_status() -> Result<SessionAuditStatus> {
+    let mut enabled = true;
+    for path in [PAM_SSHD, PAM_LOGIN] {
+        enabled &= fs::read_to_string(path)?
+            .lines()
+            .any(|line| is_tty_audit(line) && !line.trim_start().starts_with('#'));
+    }
+    let auditd_active = systemctl::SystemCtl::default()
+        .is_active(AUDITD_SERVICE_UNIT)
+        .unwrap_or_default();
+    Ok(SessionAuditStatus {
+        enabled,
+        auditd_active,
+    })
+}
+
+fn is_tty_audit(line: &str) -> bool {
+    line.split_whitespace().any(|field| field == PAM_TTY_AUDIT)
+}
+
+// Removes pam_tty_audit lines, and appends one if enabled.
+fn update_pam(contents: &str, enable: bool) -> String {
+    let mut new_contents = String::new();
+    for line in contents.lines() {
+        if !is_tty_audit(line) {
+            new_contents.push_str(line);
+            new_contents.push('\n');
+        }
+    }
+    if enable {
+        new_contents.push_str(PAM_TTY_AUDIT_LINE);
+        new_contents.push('\n');
+    }
+    new_contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PAM: &str = "# PAM configuration for the Secure Shell service
+@include common-auth
+session    required     pam_loginuid.so
+";
+
+    const RAW_EVENTS: &str = "type=SYSCALL msg=audit(1700000000.123:456): arch=c000003e syscall=257 success=yes exit=3 auid=1000 uid=0 comm=\"vi\" key=\"roxy-config\"
+type=CWD msg=audit(1700000000.123:456): cwd=\"/root\"
+type=PATH msg=audit(1700000000.123:456): item=0 name=\"/etc/ntp.conf\" inode=1234
+type=SYSCALL msg=audit(1700000100.000:460): arch=c000003e syscall=59 success=yes auid=1000 comm=\"sudo\" key=\"roxy-exec\"
+";
+
+    #[test]
+    fn raw_events() {
+        let events = parse_raw_events(RAW_EVENTS);
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0].serial, 456);
+        assert_eq!(events[0].key.as_deref(), Some("roxy-config"));
+        assert_eq!(events[0].records.len(), 3);
+        assert_eq!(events[0].records[2].kind, "PATH");
+        assert_eq!(events[0].records[2].fields["name"], "/etc/ntp.conf");
+        assert_eq!(events[1].key.as_deref(), Some("roxy-exec"));
+        assert!(!events[1].time.is_empty());
+    }
+
+    #[test]
+    fn ruleset() {
+        let rules = rules();
+        assert!(rules.contains("-w /etc/ssh/sshd_config -p wa -k roxy-config\n"));
+        assert!(rules.contains("-F path=/usr/bin/sudo -F perm=x"));
+    }
+
+    #[test]
+    fn tty_audit() {
+        let enabled = update_pam(PAM, true);
+        assert_eq!(enabled, format!("{PAM}{PAM_TTY_AUDIT_LINE}\n"));
+        assert_eq!(update_pam(&enabled, true), enabled);
+        assert_eq!(update_pam(&enabled, false), PAM);
+    }
+}
@@ -0,0 +1,55 @@
+use std::fs;
+
+use anyhow::{anyhow, Result};
+
+use crate::root::{persist, sshd};
+
+pub(crate) const ISSUE: &str = "/etc/issue";
+pub(crate) const ISSUE_NET: &str = "/etc/issue.net";
+pub(crate) const MOTD: &str = "/etc/motd";
+const MAX_BANNER_LEN: usize = 4096;
+
+// Sets the login banner shown on consoles, before SSH authentication, and
+// after login.
+//
+// # Example
+//
+// let ret = banner::set("Authorized access only.")?;
+//
+// # Errors
+//
+// * banner is too long
+// * fail to write /etc/issue, /etc/issue.net, or /etc/motd
+// * fail to modify /etc/ssh/sshd_config or restart sshd service
+pub(crate) fn set(text: &str) -> Result<bool> {
+    if text.len() > MAX_BANNER_LEN {
+        return Err(anyhow!("banner is too long"));
+    }
+    let mut text = text.trim_end().to_string();
+    if !text.is_empty() {
+        text.push('\n');
+    }
+
+    // getty interprets backslash escapes in /etc/issue.
+    persist::write(ISSUE, text.replace('\\', "\\\\"))?;
+    persist::write(ISSUE_NET, &text)?;
+    persist::write(MOTD, &text)?;
+    sshd::set_banner(if text.is_empty() {
+        None
+    } else {
+        Some(ISSUE_NET)
+    })
+}
+
+// Gets the login banner.
+//
+// # Errors
+//
+// * fail to read /etc/issue.net
+pub(crate) fn get() -> Result<String> {
+    match fs::read_to_string(ISSUE_NET) {
+        Ok(text) => Ok(text.trim_end().to_string()),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e.into()),
+    }
+}
@@ -0,0 +1,127 @@
+use std::{
+    panic,
+    sync::atomic::{AtomicUsize, Ordering},
+    thread,
+};
+
+use roxy::common::NodeRequest;
+
+use super::{role, task::ExecResult, unsupported::kind_name};
+
+// The most read-only tasks of a batch executed at the same time.
+const MAX_WORKERS: usize = 4;
+
+// Executes the requests of a batch in order, except that consecutive
+// read-only requests are executed concurrently. A request which changes the
+// system is executed alone, after the requests before it have completed, so
+// that the requests after it see the change.
+//
+// # Return
+//
+// * the results in the order of the requests
+pub(crate) fn execute<F>(nrs: &[NodeRequest], execute: F) -> Vec<ExecResult>
+where
+    F: Fn(&NodeRequest) -> ExecResult + Sync,
+{
+    let mut results = Vec::with_capacity(nrs.len());
+    let mut rest = nrs;
+    while let Some(first) = rest.first() {
+        let reads = rest.iter().take_while(|nr| is_read_only(nr)).count();
+        if reads > 1 {
+            results.extend(concurrently(&rest[..reads], &execute));
+            rest = &rest[reads..];
+        } else {
+            results.push(execute(first));
+            rest = &rest[1..];
+        }
+    }
+    results
+}
+
+// Executes the requests on at most `MAX_WORKERS` threads, each taking the
+// next request not taken yet.
+fn concurrently<F>(nrs: &[NodeRequest], execute: &F) -> Vec<ExecResult>
+where
+    F: Fn(&NodeRequest) -> ExecResult + Sync,
+{
+    let next = AtomicUsize::new(0);
+    let mut results = thread::scope(|s| {
+        let workers = (0..nrs.len().min(MAX_WORKERS))
+            .map(|_| {
+                s.spawn(|| {
+                    let mut results = Vec::new();
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        let Some(nr) = nrs.get(index) else {
+                            break;
+                        };
+                        results.push((index, execute(nr)));
+                    }
+                    results
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut results = Vec::with_capacity(nrs.len());
+        for worker in workers {
+            match worker.join() {
+                Ok(done) => results.extend(done),
+                // A panic is reported once, by the thread which panicked.
+                Err(payload) => panic::resume_unwind(payload),
+            }
+        }
+        results
+    });
+    results.sort_unstable_by_key(|(index, _)| *index);
+    results.into_iter().map(|(_, ret)| ret).collect()
+}
+
+fn is_read_only(nr: &NodeRequest) -> bool {
+    serde_json::to_value(&nr.kind)
+        .ok()
+        .as_ref()
+        .and_then(kind_name)
+        .is_some_and(|kind| role::is_read_only(&kind))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::sync::Mutex;
+
+    use roxy::common::{Node, SubCommand};
+
+    use super::*;
+
+    #[test]
+    fn reads_around_change() {
+        let kinds = [
+            Node::Ntp(SubCommand::Get),
+            Node::Sshd(SubCommand::Get),
+            Node::Ping,
+            Node::Ntp(SubCommand::Set),
+            Node::Ntp(SubCommand::Get),
+            Node::Syslog(SubCommand::Status),
+        ];
+        let nrs = kinds
+            .into_iter()
+            .map(|kind| NodeRequest::new(kind, None::<String>).expect("valid request"))
+            .collect::<Vec<_>>();
+        let executed = Mutex::new(Vec::new());
+        let results = execute(&nrs, |nr| {
+            let kind = format!("{:?}", nr.kind);
+            executed.lock().expect("not poisoned").push(kind.clone());
+            Ok(kind)
+        });
+
+        let kinds = nrs
+            .iter()
+            .map(|nr| Ok(format!("{:?}", nr.kind)))
+            .collect::<Vec<ExecResult>>();
+        assert_eq!(results, kinds);
+        let executed = executed.into_inner().expect("not poisoned");
+        let position = |kind: &str| executed.iter().position(|k| k == kind);
+        let change = position("Ntp(Set)").expect("executed");
+        assert_eq!(change, 3);
+        assert!(position("Ping").is_some_and(|p| p < change));
+        assert!(position("Syslog(Status)").is_some_and(|p| p > change));
+    }
+}
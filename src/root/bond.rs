@@ -0,0 +1,329 @@
+use std::{fs, io};
+
+use anyhow::Result;
+use roxy::common::{Aggregator, BondSlave, BondStatus};
+
+const BONDING_DIR: &str = "/proc/net/bonding";
+const LACP_MODE: &str = "IEEE 802.3ad Dynamic link aggregation";
+const NO_PARTNER: &str = "00:00:00:00:00:00";
+
+// Gets the status of the bonding interfaces, or the one with the name.
+//
+// # Errors
+//
+// * fail to read /proc/net/bonding other than it does not exist
+pub(crate) fn get(name: Option<&str>) -> Result<Vec<BondStatus>> {
+    let entries = match fs::read_dir(BONDING_DIR) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut bonds = Vec::new();
+    for entry in entries.flatten() {
+        let bond = entry.file_name().to_string_lossy().into_owned();
+        if name.is_some_and(|name| name != bond) {
+            continue;
+        }
+        match fs::read_to_string(entry.path()) {
+            Ok(contents) => bonds.push(parse(&bond, &contents)),
+            // The bond was deleted after listed.
+            Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+            Err(e) => return Err(e.into()),
+        }
+    }
+    bonds.sort_by(|a, b| a.name.cmp(&b.name));
+    Ok(bonds)
+}
+
+#[derive(PartialEq)]
+enum Section {
+    Bond,
+    Aggregator,
+    Slave,
+    ActorPdu,
+    PartnerPdu,
+}
+
+// Parses a file in /proc/net/bonding such as:
+//
+// Bonding Mode: IEEE 802.3ad Dynamic link aggregation
+// MII Status: up
+// Active Aggregator Info:
+//         Aggregator ID: 1
+//         Partner Mac Address: 00:1c:73:00:00:99
+//
+// Slave Interface: enp1s0f0
+// MII Status: up
+// Link Failure Count: 1
+// Aggregator ID: 1
+// details partner lacp pdu:
+//     system mac address: 00:1c:73:00:00:99
+fn parse(name: &str, contents: &str) -> BondStatus {
+    let mut bond = BondStatus {
+        name: name.to_string(),
+        ..BondStatus::default()
+    };
+    let mut section = Section::Bond;
+    for line in contents.lines() {
+        let line = line.trim();
+        match line {
+            "Active Aggregator Info:" => {
+                section = Section::Aggregator;
+                bond.aggregator = Some(Aggregator::default());
+                continue;
+            }
+            "details actor lacp pdu:" => {
+                section = Section::ActorPdu;
+                continue;
+            }
+            "details partner lacp pdu:" => {
+                section = Section::PartnerPdu;
+                continue;
+            }
+            "" if section == Section::Aggregator => {
+                section = Section::Bond;
+                continue;
+            }
+            _ => {}
+        }
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if key == "Slave Interface" {
+            section = Section::Slave;
+            bond.slaves.push(BondSlave {
+                name: value.to_string(),
+                ..BondSlave::default()
+            });
+            continue;
+        }
+        match section {
+            Section::Bond => match key {
+                "Bonding Mode" => bond.mode = value.to_string(),
+                "MII Status" => bond.mii_status = value.to_string(),
+                "Currently Active Slave" if value != "None" => {
+                    bond.active_slave = Some(value.to_string());
+                }
+                _ => {}
+            },
+            Section::Aggregator => {
+                let Some(aggregator) = bond.aggregator.as_mut() else {
+                    continue;
+                };
+                match key {
+                    "Aggregator ID" => aggregator.id = value.parse().unwrap_or_default(),
+                    "Number of ports" => aggregator.ports = value.parse().unwrap_or_default(),
+                    "Partner Key" => aggregator.partner_key = value.parse().unwrap_or_default(),
+                    "Partner Mac Address" => aggregator.partner_mac = value.to_string(),
+                    _ => {}
+                }
+            }
+            Section::Slave | Section::ActorPdu | Section::PartnerPdu => {
+                let Some(slave) = bond.slaves.last_mut() else {
+                    continue;
+                };
+                match (&section, key) {
+                    (Section::Slave, "MII Status") => slave.mii_status = value.to_string(),
+                    (Section::Slave, "Speed") if value != "Unknown" => {
+                        slave.speed = Some(value.to_string());
+                    }
+                    (Section::Slave, "Duplex") if value != "Unknown" => {
+                        slave.duplex = Some(value.to_string());
+                    }
+                    (Section::Slave, "Link Failure Count") => {
+                        slave.link_failure_count = value.parse().unwrap_or_default();
+                    }
+                    (Section::Slave, "Permanent HW addr") => {
+                        slave.permanent_mac = Some(value.to_string());
+                    }
+                    (Section::Slave, "Aggregator ID") => slave.aggregator_id = value.parse().ok(),
+                    (Section::Slave, "Actor Churn State") => {
+                        slave.actor_churn_state = Some(value.to_string());
+                    }
+                    (Section::Slave, "Partner Churn State") => {
+                        slave.partner_churn_state = Some(value.to_string());
+                    }
+                    (Section::PartnerPdu, "system mac address") => {
+                        slave.partner_mac = Some(value.to_string());
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    bond.degraded = is_degraded(&bond);
+    bond
+}
+
+fn is_degraded(bond: &BondStatus) -> bool {
+    if bond.mii_status != "up" || bond.slaves.iter().any(|s| s.mii_status != "up") {
+        return true;
+    }
+    if bond.mode != LACP_MODE {
+        return false;
+    }
+    // Slaves out of the selected aggregator carry no traffic, e.g. because
+    // they are connected to a switch port not in the LACP group.
+    bond.aggregator.as_ref().is_none_or(|aggregator| {
+        aggregator.partner_mac == NO_PARTNER
+            || bond
+                .slaves
+                .iter()
+                .any(|s| s.aggregator_id != Some(aggregator.id))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const LACP: &str = "Ethernet Channel Bonding Driver: v5.15.0-91-generic
+
+Bonding Mode: IEEE 802.3ad Dynamic link aggregation
+Transmit Hash Policy: layer3+4 (1)
+MII Status: up
+MII Polling Interval (ms): 100
+
+802.3ad info
+LACP active: on
+LACP rate: fast
+System MAC address: 3c:fd:fe:00:00:01
+Active Aggregator Info:
+\tAggregator ID: 1
+\tNumber of ports: 2
+\tActor Key: 15
+\tPartner Key: 32773
+\tPartner Mac Address: 00:1c:73:00:00:99
+
+Slave Interface: enp1s0f0
+MII Status: up
+Speed: 10000 Mbps
+Duplex: full
+Link Failure Count: 1
+Permanent HW addr: 3c:fd:fe:00:00:01
+Slave queue ID: 0
+Aggregator ID: 1
+Actor Churn State: none
+Partner Churn State: none
+details actor lacp pdu:
+    system priority: 65535
+    system mac address: 3c:fd:fe:00:00:01
+    port key: 15
+details partner lacp pdu:
+    system priority: 32768
+    system mac address: 00:1c:73:00:00:99
+    oper key: 32773
+
+Slave Interface: enp1s0f1
+MII Status: up
+Speed: 10000 Mbps
+Duplex: full
+Link Failure Count: 0
+Permanent HW addr: 3c:fd:fe:00:00:02
+Slave queue ID: 0
+Aggregator ID: 1
+Actor Churn State: none
+Partner Churn State: none
+details actor lacp pdu:
+    system mac address: 3c:fd:fe:00:00:01
+details partner lacp pdu:
+    system mac address: 00:1c:73:00:00:99
+";
+
+    const ACTIVE_BACKUP: &str = "Ethernet Channel Bonding Driver: v5.15.0-91-generic
+
+Bonding Mode: fault-tolerance (active-backup)
+Primary Slave: None
+Currently Active Slave: eth1
+MII Status: up
+
+Slave Interface: eth0
+MII Status: down
+Speed: Unknown
+Duplex: Unknown
+Link Failure Count: 3
+Permanent HW addr: 52:54:00:00:00:01
+Slave queue ID: 0
+
+Slave Interface: eth1
+MII Status: up
+Speed: 1000 Mbps
+Duplex: full
+Link Failure Count: 0
+Permanent HW addr: 52:54:00:00:00:02
+Slave queue ID: 0
+";
+
+    #[test]
+    fn lacp() {
+        let bond = parse("bond0", LACP);
+        assert_eq!(bond.mode, LACP_MODE);
+        assert_eq!(bond.mii_status, "up");
+        assert_eq!(bond.active_slave, None);
+        assert_eq!(
+            bond.aggregator,
+            Some(Aggregator {
+                id: 1,
+                ports: 2,
+                partner_mac: "00:1c:73:00:00:99".to_string(),
+                partner_key: 32773,
+            })
+        );
+        assert_eq!(bond.slaves.len(), 2);
+        assert_eq!(
+            bond.slaves[0],
+            BondSlave {
+                name: "enp1s0f0".to_string(),
+                mii_status: "up".to_string(),
+                speed: Some("10000 Mbps".to_string()),
+                duplex: Some("full".to_string()),
+                link_failure_count: 1,
+                permanent_mac: Some("3c:fd:fe:00:00:01".to_string()),
+                aggregator_id: Some(1),
+                partner_mac: Some("00:1c:73:00:00:99".to_string()),
+                actor_churn_state: Some("none".to_string()),
+                partner_churn_state: Some("none".to_string()),
+            }
+        );
+        assert!(!bond.degraded);
+
+        // The second slave is connected to a port out of the LACP group.
+        let (first, second) = LACP.split_at(LACP.find("enp1s0f1").expect("second slave"));
+        let bond = parse(
+            "bond0",
+            &format!(
+                "{first}{}",
+                second.replace("Aggregator ID: 1", "Aggregator ID: 2")
+            ),
+        );
+        assert_eq!(bond.slaves[0].aggregator_id, Some(1));
+        assert_eq!(bond.slaves[1].aggregator_id, Some(2));
+        assert!(bond.degraded);
+        let bond = parse(
+            "bond0",
+            &LACP.replace(
+                "Partner Mac Address: 00:1c:73:00:00:99",
+                "Partner Mac Address: 00:00:00:00:00:00",
+            ),
+        );
+        assert!(bond.degraded);
+    }
+
+    #[test]
+    fn active_backup() {
+        let bond = parse("bond1", ACTIVE_BACKUP);
+        assert_eq!(bond.active_slave.as_deref(), Some("eth1"));
+        assert_eq!(bond.aggregator, None);
+        assert_eq!(bond.slaves[0].mii_status, "down");
+        assert_eq!(bond.slaves[0].speed, None);
+        assert_eq!(bond.slaves[0].link_failure_count, 3);
+        assert_eq!(bond.slaves[1].aggregator_id, None);
+        assert!(bond.degraded);
+        let bond = parse(
+            "bond1",
+            &ACTIVE_BACKUP.replacen("MII Status: down", "MII Status: up", 1),
+        );
+        assert!(!bond.degraded);
+    }
+}
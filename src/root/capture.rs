@@ -0,0 +1,325 @@
+use std::{fmt::Write, fs, path::Path};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command, CaptureSettings, CaptureStatus};
+
+use crate::root::{netns, persist};
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const UNIT_DIR: &str = "/etc/systemd/system";
+const IFF_PROMISC: u32 = 0x100;
+const MAX_IFNAME_LEN: usize = 15;
+// Names of the offloads in the output of `ethtool -k`.
+const GRO: &str = "generic-receive-offload";
+const LRO: &str = "large-receive-offload";
+const TSO: &str = "tcp-segmentation-offload";
+
+// Applies the capture settings to the interface, and installs a unit which
+// applies them again at boot.
+//
+// # Errors
+//
+// * invalid or unknown interface
+// * fail to run ip or ethtool, e.g. because the NIC cannot change an offload
+// * fail to write or enable the unit
+pub(crate) fn set(ifname: &str, settings: CaptureSettings) -> Result<()> {
+    validate(ifname)?;
+    for args in commands(ifname, settings) {
+        let output = run_command(command(args[0]).args(&args[1..]))?;
+        if !output.status.success() {
+            return Err(anyhow!("fail to run {}", args.join(" ")));
+        }
+    }
+
+    persist::write(&unit_path(ifname), unit(ifname, settings))?;
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", &unit_name(ifname)])
+}
+
+// Gets the capture settings of the running interface, and those applied at
+// boot.
+//
+// # Errors
+//
+// * invalid or unknown interface
+// * fail to read the flags or offloads of the interface
+pub(crate) fn get(ifname: &str) -> Result<CaptureStatus> {
+    validate(ifname)?;
+    let flags = fs::read_to_string(format!("{SYS_CLASS_NET}/{ifname}/flags"))?;
+    let flags = u32::from_str_radix(flags.trim().trim_start_matches("0x"), 16)?;
+    let output = run_command(command("ethtool").args(["-k", ifname]))?;
+    if !output.status.success() {
+        return Err(anyhow!("fail to get offloads of {}", ifname));
+    }
+    let features = String::from_utf8_lossy(&output.stdout);
+    let offload = |name: &str| {
+        feature(&features, name).ok_or_else(|| anyhow!("{} of {} not found", name, ifname))
+    };
+
+    Ok(CaptureStatus {
+        current: CaptureSettings {
+            promiscuous: flags & IFF_PROMISC != 0,
+            gro: offload(GRO)?,
+            lro: offload(LRO)?,
+            tso: offload(TSO)?,
+        },
+        persistent: fs::read_to_string(unit_path(ifname))
+            .ok()
+            .and_then(|contents| parse_unit(&contents)),
+    })
+}
+
+// Removes the unit applying the capture settings at boot. The running
+// interface keeps its settings until reboot.
+//
+// # Errors
+//
+// * invalid interface name
+// * fail to disable or remove the unit
+pub(crate) fn delete(ifname: &str) -> Result<()> {
+    validate_name(ifname)?;
+    if !Path::new(&unit_path(ifname)).exists() {
+        return Ok(());
+    }
+    systemctl(&["disable", &unit_name(ifname)])?;
+    persist::remove(&unit_path(ifname))?;
+    systemctl(&["daemon-reload"])
+}
+
+// Checks the name, which goes into a unit file, and the existence of the
+// interface.
+pub(crate) fn validate(ifname: &str) -> Result<()> {
+    validate_name(ifname)?;
+    if Path::new(SYS_CLASS_NET).join(ifname).exists() {
+        Ok(())
+    } else if let Some(namespace) = netns::find(ifname) {
+        Err(anyhow!(
+            "interface \"{}\" is in network namespace \"{}\".",
+            ifname,
+            namespace
+        ))
+    } else {
+        Err(anyhow!("interface \"{}\" not found.", ifname))
+    }
+}
+
+pub(crate) fn validate_name(ifname: &str) -> Result<()> {
+    if !ifname.is_empty()
+        && ifname.len() <= MAX_IFNAME_LEN
+        && ifname != "."
+        && ifname != ".."
+        && ifname
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c))
+    {
+        Ok(())
+    } else {
+        Err(anyhow!("invalid interface name: {}", ifname))
+    }
+}
+
+// Returns the name of the device unit of the interface, which is its sysfs
+// path escaped as systemd does, e.g. `sys-subsystem-net-devices-cap\x2d0.device`
+// for "cap-0".
+pub(crate) fn device_unit(ifname: &str) -> String {
+    let mut escaped = String::new();
+    for (i, c) in ifname.chars().enumerate() {
+        if c.is_ascii_alphanumeric() || c == '_' || (c == '.' && i > 0) {
+            escaped.push(c);
+        } else {
+            write!(escaped, "\\x{:02x}", u32::from(c)).expect("writing to string should not fail");
+        }
+    }
+    format!("sys-subsystem-net-devices-{escaped}.device")
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let output = run_command(command("systemctl").args(args))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("fail to run systemctl {}", args.join(" ")))
+    }
+}
+
+fn unit_name(ifname: &str) -> String {
+    format!("roxy-capture-{ifname}.service")
+}
+
+fn unit_path(ifname: &str) -> String {
+    format!("{UNIT_DIR}/{}", unit_name(ifname))
+}
+
+fn on_off(on: bool) -> &'static str {
+    if on {
+        "on"
+    } else {
+        "off"
+    }
+}
+
+// Commands applying the settings, e.g. ["ip", "link", "set", ...].
+fn commands(ifname: &str, settings: CaptureSettings) -> [Vec<&str>; 2] {
+    [
+        vec![
+            "ip",
+            "link",
+            "set",
+            "dev",
+            ifname,
+            "promisc",
+            on_off(settings.promiscuous),
+        ],
+        vec![
+            "ethtool",
+            "-K",
+            ifname,
+            "gro",
+            on_off(settings.gro),
+            "lro",
+            on_off(settings.lro),
+            "tso",
+            on_off(settings.tso),
+        ],
+    ]
+}
+
+fn unit(ifname: &str, settings: CaptureSettings) -> String {
+    let device = device_unit(ifname);
+    let exec_start = commands(ifname, settings)
+        .iter()
+        .map(|args| format!("ExecStart={}\n", args.join(" ")))
+        .collect::<Vec<_>>()
+        .concat();
+    format!(
+        "# Written by roxy.\n\
+         [Unit]\n\
+         Description=Capture settings of {ifname}\n\
+         BindsTo={device}\n\
+         After={device} systemd-networkd.service\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         RemainAfterExit=yes\n\
+         {exec_start}\
+         \n\
+         [Install]\n\
+         WantedBy={device}\n"
+    )
+}
+
+// Reads the settings from a unit written by `unit`.
+fn parse_unit(contents: &str) -> Option<CaptureSettings> {
+    let mut promiscuous = None;
+    let (mut gro, mut lro, mut tso) = (None, None, None);
+    for line in contents.lines() {
+        let Some(args) = line.strip_prefix("ExecStart=") else {
+            continue;
+        };
+        let args = args.split_whitespace().collect::<Vec<_>>();
+        for pair in args.windows(2) {
+            let value = match pair[1] {
+                "on" => true,
+                "off" => false,
+                _ => continue,
+            };
+            match pair[0] {
+                "promisc" => promiscuous = Some(value),
+                "gro" => gro = Some(value),
+                "lro" => lro = Some(value),
+                "tso" => tso = Some(value),
+                _ => {}
+            }
+        }
+    }
+    Some(CaptureSettings {
+        promiscuous: promiscuous?,
+        gro: gro?,
+        lro: lro?,
+        tso: tso?,
+    })
+}
+
+// Gets whether a feature is on from the output of `ethtool -k`, e.g.
+// "large-receive-offload: off [fixed]".
+fn feature(features: &str, name: &str) -> Option<bool> {
+    features.lines().find_map(|line| {
+        let (key, value) = line.trim().split_once(':')?;
+        if key != name {
+            return None;
+        }
+        match value.split_whitespace().next()? {
+            "on" => Some(true),
+            "off" => Some(false),
+            _ => None,
+        }
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FEATURES: &str = "Features for eth0:
+rx-checksumming: on
+tcp-segmentation-offload: on
+\ttx-tcp-segmentation: on
+generic-segmentation-offload: on
+generic-receive-offload: off
+large-receive-offload: off [fixed]
+";
+
+    #[test]
+    fn offloads() {
+        assert_eq!(feature(FEATURES, GRO), Some(false));
+        assert_eq!(feature(FEATURES, LRO), Some(false));
+        assert_eq!(feature(FEATURES, TSO), Some(true));
+        assert_eq!(feature(FEATURES, "tx-tcp-segmentation"), Some(true));
+        assert_eq!(feature(FEATURES, "rx-vlan-filter"), None);
+    }
+
+    #[test]
+    fn unit_round_trip() {
+        let settings = CaptureSettings {
+            tso: true,
+            ..CaptureSettings::capture()
+        };
+        let contents = unit("eth0", settings);
+        assert!(contents.contains("ExecStart=ip link set dev eth0 promisc on\n"));
+        assert!(contents.contains("ExecStart=ethtool -K eth0 gro off lro off tso on\n"));
+        assert!(contents.contains("WantedBy=sys-subsystem-net-devices-eth0.device\n"));
+        assert_eq!(parse_unit(&contents), Some(settings));
+        assert_eq!(parse_unit("[Unit]\n"), None);
+    }
+
+    #[test]
+    fn interface_name() {
+        assert!(validate_name("eth0").is_ok());
+        assert!(validate_name("enp3s0f1.100").is_ok());
+        assert!(validate_name("").is_err());
+        assert!(validate_name("..").is_err());
+        assert!(validate_name("eth0/../x").is_err());
+        assert!(validate_name("eth0\nExecStart=sh").is_err());
+        assert!(validate_name("verylonginterface0").is_err());
+    }
+
+    #[test]
+    fn device_unit_name() {
+        assert_eq!(device_unit("eth0"), "sys-subsystem-net-devices-eth0.device");
+        assert_eq!(
+            device_unit("cap-0"),
+            "sys-subsystem-net-devices-cap\\x2d0.device"
+        );
+        assert_eq!(
+            device_unit("enp3s0f1.100"),
+            "sys-subsystem-net-devices-enp3s0f1.100.device"
+        );
+        assert_eq!(
+            device_unit(".x_1"),
+            "sys-subsystem-net-devices-\\x2ex_1.device"
+        );
+        let contents = unit("cap-0", CaptureSettings::capture());
+        assert!(contents.contains("BindsTo=sys-subsystem-net-devices-cap\\x2d0.device\n"));
+        assert_eq!(parse_unit(&contents), Some(CaptureSettings::capture()));
+    }
+}
@@ -0,0 +1,266 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{self, Read, Seek, SeekFrom, Write},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use nix::{
+    fcntl::{Flock, FlockArg},
+    unistd::{getuid, User},
+};
+use roxy::common::{ChangeEvent, Subsystem};
+
+use super::{history, transaction::Checkpoint, ROXY_STATE_DIR};
+
+const CHANGES_FILE: &str = "changes.jsonl";
+const CHANGES_LOCK: &str = "changes.lock";
+// Events older than the last MAX_CHANGE_EVENTS are dropped.
+const MAX_CHANGE_EVENTS: usize = 10_000;
+// The number of events returned at a time.
+pub(crate) const MAX_EVENTS_PER_REQUEST: usize = 1_000;
+
+// The subsystem of a change, or None for a change outside the subsystems,
+// with the removed and added lines.
+type Change = (Option<Subsystem>, (Vec<String>, Vec<String>));
+
+// Saves the configuration of each subsystem before a change. A subsystem
+// whose configuration cannot be read is not recorded.
+pub(crate) fn snapshot(subsystems: &[Subsystem]) -> Vec<(Subsystem, Checkpoint)> {
+    subsystems
+        .iter()
+        .filter_map(|subsystem| Some((*subsystem, Checkpoint::of(&[*subsystem]).ok()?)))
+        .collect()
+}
+
+// Appends an event for each subsystem whose configuration differs from the
+// snapshot taken by `snapshot`, with the notes on the task.
+//
+// # Errors
+//
+// * fail to read the configuration of a subsystem
+// * fail to lock, read, or write the event log
+pub(crate) fn record(
+    task: &str,
+    before: Vec<(Subsystem, Checkpoint)>,
+    notes: &[String],
+) -> Result<()> {
+    let mut changes = Vec::new();
+    for (subsystem, before) in before {
+        let after = Checkpoint::of(&[subsystem])?;
+        if after != before {
+            changes.push((
+                Some(subsystem),
+                summarize(&history::diff(Some(&before), &after)),
+            ));
+        }
+    }
+    append(task, changes, notes)
+}
+
+// Appends an event without a subsystem or a diff for a task which changed
+// the system outside the subsystems, e.g. a WireGuard configuration or a
+// service, with the notes on the task.
+//
+// # Errors
+//
+// * fail to lock, read, or write the event log
+pub(crate) fn record_untracked(task: &str, notes: &[String]) -> Result<()> {
+    append(task, vec![(None, (Vec::new(), Vec::new()))], notes)
+}
+
+// Appends an event for each change. The log is locked since several roxy
+// processes may record at the same time, and each event needs the next
+// sequence number.
+fn append(task: &str, changes: Vec<Change>, notes: &[String]) -> Result<()> {
+    if changes.is_empty() {
+        return Ok(());
+    }
+
+    let _lock = lock()?;
+    let mut events = load()?;
+    let last = events.last().map_or(0, |event| event.seq);
+    let time = Local::now().to_rfc3339();
+    let actor = actor();
+    let new_events = (last + 1..)
+        .zip(changes)
+        .map(|(seq, (subsystem, (before, after)))| ChangeEvent {
+            seq,
+            time: time.clone(),
+            subsystem,
+            task: task.to_string(),
+            actor: actor.clone(),
+            before,
+            after,
+            notes: notes.to_vec(),
+        })
+        .collect::<Vec<_>>();
+
+    let path = format!("{ROXY_STATE_DIR}/{CHANGES_FILE}");
+    if events.len() + new_events.len() > MAX_CHANGE_EVENTS {
+        events.extend(new_events);
+        let start = events.len() - MAX_CHANGE_EVENTS;
+        // The trimmed log is renamed into place, so that the events are not
+        // lost if roxy is interrupted while writing it.
+        let tmp = format!("{path}.tmp");
+        fs::write(&tmp, to_lines(&events[start..])?)?;
+        fs::rename(&tmp, &path)?;
+    } else {
+        let mut file = OpenOptions::new()
+            .read(true)
+            .create(true)
+            .append(true)
+            .open(&path)?;
+        let mut lines = to_lines(&new_events)?;
+        // A partial line left by an interrupted roxy is ended first, so that
+        // it does not swallow the first new event.
+        if !ends_with_newline(&mut file)? {
+            lines.insert(0, '\n');
+        }
+        file.write_all(lines.as_bytes())?;
+    }
+    Ok(())
+}
+
+// Gets the events after the cursor, oldest first, at most
+// `MAX_EVENTS_PER_REQUEST` of them.
+//
+// # Errors
+//
+// * fail to read the event log
+pub(crate) fn get(since: u64) -> Result<Vec<ChangeEvent>> {
+    Ok(load()?
+        .into_iter()
+        .filter(|event| event.seq > since)
+        .take(MAX_EVENTS_PER_REQUEST)
+        .collect())
+}
+
+fn lock() -> Result<Flock<File>> {
+    fs::create_dir_all(ROXY_STATE_DIR)?;
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(format!("{ROXY_STATE_DIR}/{CHANGES_LOCK}"))?;
+    Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, e)| anyhow!("fail to lock the change events. {}", e))
+}
+
+fn load() -> Result<Vec<ChangeEvent>> {
+    match fs::read_to_string(format!("{ROXY_STATE_DIR}/{CHANGES_FILE}")) {
+        Ok(contents) => Ok(parse(&contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Parses the event log, skipping a line which is not an event, e.g. a
+// partial line left by an interrupted roxy, so that one bad line does not
+// hide the whole log.
+fn parse(contents: &str) -> Vec<ChangeEvent> {
+    contents
+        .lines()
+        .filter_map(|line| serde_json::from_str::<ChangeEvent>(line).ok())
+        .collect()
+}
+
+fn ends_with_newline(file: &mut File) -> io::Result<bool> {
+    if file.metadata()?.len() == 0 {
+        return Ok(true);
+    }
+    let mut last = [0; 1];
+    file.seek(SeekFrom::End(-1))?;
+    file.read_exact(&mut last)?;
+    Ok(last[0] == b'\n')
+}
+
+fn to_lines(events: &[ChangeEvent]) -> Result<String> {
+    let mut ret = String::new();
+    for event in events {
+        ret.push_str(&serde_json::to_string(event)?);
+        ret.push('\n');
+    }
+    Ok(ret)
+}
+
+// The user who ran roxy, which is the real user of the setuid executable.
+pub(crate) fn actor() -> String {
+    let uid = getuid();
+    match User::from_uid(uid) {
+        Ok(Some(user)) => user.name,
+        _ => uid.to_string(),
+    }
+}
+
+// Splits a diff made by `history::diff` into the removed and added lines,
+// each prefixed with its file, e.g. "/etc/ntp.conf: server a iburst".
+fn summarize(diff: &str) -> (Vec<String>, Vec<String>) {
+    let mut before = Vec::new();
+    let mut after = Vec::new();
+    let mut file = "";
+    for line in diff.lines() {
+        if let Some(header) = line.strip_prefix("--- ") {
+            file = header;
+        } else if let Some(removed) = line.strip_prefix('-') {
+            before.push(format!("{file}: {removed}"));
+        } else if let Some(added) = line.strip_prefix('+') {
+            after.push(format!("{file}: {added}"));
+        }
+    }
+    (before, after)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn partial_line() {
+        let event = ChangeEvent {
+            seq: 1,
+            time: "2026-03-02T18:00:00+09:00".to_string(),
+            subsystem: Some(Subsystem::Ntp),
+            task: "Ntp::Set".to_string(),
+            actor: "root".to_string(),
+            before: Vec::new(),
+            after: vec!["/etc/ntp.conf: server a iburst".to_string()],
+            notes: Vec::new(),
+        };
+        let second = ChangeEvent {
+            seq: 2,
+            ..event.clone()
+        };
+        let mut contents = to_lines(&[event, second]).expect("JSON");
+        let full = parse(&contents);
+        assert_eq!(full.len(), 2);
+        // A partial line ended by the next append
+        contents.insert_str(contents.find('\n').expect("line") + 1, "{\"seq\":3,\"ti\n");
+        assert_eq!(parse(&contents), full);
+        contents.push_str("{\"seq\":3,\"ti");
+        assert_eq!(parse(&contents), full);
+    }
+
+    #[test]
+    fn summary() {
+        let diff = "--- hostname\n-sensor1\n+sensor2\n\
+                    --- /etc/ntp.conf\n-server a.example.com iburst\n\
+                    +server b.example.com iburst\n+server c.example.com iburst";
+        let (before, after) = summarize(diff);
+        assert_eq!(
+            before,
+            [
+                "hostname: sensor1",
+                "/etc/ntp.conf: server a.example.com iburst"
+            ]
+        );
+        assert_eq!(
+            after,
+            [
+                "hostname: sensor2",
+                "/etc/ntp.conf: server b.example.com iburst",
+                "/etc/ntp.conf: server c.example.com iburst"
+            ]
+        );
+    }
+}
@@ -0,0 +1,217 @@
+use std::{
+    fs, io,
+    os::unix::fs::MetadataExt,
+    path::Path,
+    time::{Duration, SystemTime},
+};
+
+use anyhow::{anyhow, Result};
+use roxy::common::CleanupReport;
+use serde::Deserialize;
+
+use super::task::log_debug;
+
+// Directories whose oldest files are removed when their size exceeds the
+// quota in bytes, e.g.
+//
+// - path: /data/logs
+//   max_size: 10737418240
+// - path: /data/pcap
+//   max_size: 107374182400
+pub(crate) const CLEANUP_CONF: &str = "/etc/roxy/cleanup.yaml";
+// A file modified more recently than this may still be being written, and
+// is not removed.
+const MIN_AGE: Duration = Duration::from_mins(1);
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Quota {
+    path: String,
+    max_size: u64,
+}
+
+struct Entry {
+    path: String,
+    size: u64,
+    modified: SystemTime,
+}
+
+// Removes the oldest files of each directory in `CLEANUP_CONF` until it is
+// within its quota, or only reports the files to be removed if `dry_run`.
+//
+// # Errors
+//
+// * fail to read or parse `CLEANUP_CONF`
+// * a path in `CLEANUP_CONF` is not an absolute path to a directory other
+//   than "/"
+// * fail to read a directory in `CLEANUP_CONF` that exists
+pub(crate) fn run(dry_run: bool) -> Result<Vec<CleanupReport>> {
+    let quotas = read_conf()?;
+    for quota in &quotas {
+        let path = Path::new(&quota.path);
+        if !path.is_absolute() || path.parent().is_none() {
+            return Err(anyhow!("invalid cleanup path: {}", quota.path));
+        }
+    }
+    quotas
+        .iter()
+        .map(|quota| enforce(quota, dry_run, SystemTime::now()))
+        .collect()
+}
+
+fn enforce(quota: &Quota, dry_run: bool, now: SystemTime) -> Result<CleanupReport> {
+    let mut files = Vec::new();
+    match collect(Path::new(&quota.path), &mut files) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => {}
+        ret => ret?,
+    }
+    let mut report = CleanupReport {
+        path: quota.path.clone(),
+        max_size: quota.max_size,
+        size: files.iter().map(|file| file.size).sum(),
+        ..CleanupReport::default()
+    };
+
+    files.sort_by_key(|file| file.modified);
+    for file in files {
+        if report.size - report.freed <= quota.max_size {
+            break;
+        }
+        if now.duration_since(file.modified).unwrap_or_default() < MIN_AGE {
+            // The remaining files are even newer.
+            break;
+        }
+        if !dry_run {
+            if let Err(e) = fs::remove_file(&file.path) {
+                log_debug(&format!("fail to remove {}. {e:?}", file.path));
+                continue;
+            }
+        }
+        report.freed += file.size;
+        report.removed.push(file.path);
+    }
+    Ok(report)
+}
+
+// Collects the regular files under the directory. Symbolic links are not
+// followed, so that no file outside the directory is removed. An entry that
+// vanishes or cannot be read while walking, e.g. a file rotated away, is
+// skipped; only an error reading the directory itself is returned.
+fn collect(dir: &Path, files: &mut Vec<Entry>) -> io::Result<()> {
+    for entry in fs::read_dir(dir)? {
+        let path = match entry {
+            Ok(entry) => entry.path(),
+            Err(e) => {
+                log_debug(&format!("fail to read {}. {e:?}", dir.display()));
+                continue;
+            }
+        };
+        let metadata = match path.symlink_metadata() {
+            Ok(metadata) => metadata,
+            Err(e) => {
+                log_debug(&format!("fail to read {}. {e:?}", path.display()));
+                continue;
+            }
+        };
+        if metadata.is_dir() {
+            if let Err(e) = collect(&path, files) {
+                log_debug(&format!("fail to read {}. {e:?}", path.display()));
+            }
+        } else if metadata.is_file() {
+            let Ok(modified) = metadata.modified() else {
+                continue;
+            };
+            files.push(Entry {
+                path: path.display().to_string(),
+                // The space taken on disk, which is less than the length of
+                // a sparse file.
+                size: metadata.blocks() * 512,
+                modified,
+            });
+        }
+    }
+    Ok(())
+}
+
+fn read_conf() -> Result<Vec<Quota>> {
+    match fs::read_to_string(CLEANUP_CONF) {
+        Ok(contents) if contents.trim().is_empty() => Ok(Vec::new()),
+        Ok(contents) => serde_yaml::from_str(&contents)
+            .map_err(|e| anyhow!("fail to parse {}. {}", CLEANUP_CONF, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, fs::File, process};
+
+    use super::*;
+
+    #[test]
+    fn oldest_files_removed() {
+        let dir = env::temp_dir().join(format!("roxy-cleanup-{}", process::id()));
+        fs::create_dir_all(dir.join("sub")).expect("temporary directory");
+        let now = SystemTime::now();
+        let write = |name: &str, age: u64| {
+            let path = dir.join(name);
+            fs::write(&path, vec![1; 4096]).expect("file");
+            let file = File::options().write(true).open(&path).expect("file");
+            file.set_modified(now - Duration::from_secs(age))
+                .expect("modification time");
+            path.display().to_string()
+        };
+        let oldest = write("sub/a.pcap", 3000);
+        let older = write("b.pcap", 2000);
+        write("c.pcap", 1000);
+        write("d.pcap", 10);
+
+        let size = |dir: &Path| {
+            let mut files = Vec::new();
+            collect(dir, &mut files).expect("files");
+            files.iter().map(|file| file.size).sum::<u64>()
+        };
+        let file_size = size(&dir) / 4;
+        let quota = Quota {
+            path: dir.display().to_string(),
+            max_size: file_size * 2,
+        };
+
+        let report = enforce(&quota, true, now).expect("dry run");
+        assert_eq!(report.removed, [oldest.clone(), older.clone()]);
+        assert!(!report.over_quota());
+        assert_eq!(size(&dir), file_size * 4);
+
+        let report = enforce(&quota, false, now).expect("cleanup");
+        assert_eq!(report.removed, [oldest, older]);
+        assert_eq!(size(&dir), file_size * 2);
+
+        // A file being written is kept over the quota.
+        let quota = Quota {
+            max_size: 0,
+            ..quota
+        };
+        let report = enforce(&quota, true, now).expect("dry run");
+        assert_eq!(report.removed.len(), 1);
+        assert!(report.over_quota());
+
+        fs::remove_dir_all(&dir).expect("remove temporary directory");
+    }
+
+    #[test]
+    fn missing_directory() {
+        let dir = env::temp_dir().join(format!("roxy-cleanup-missing-{}", process::id()));
+        let mut files = Vec::new();
+        let err = collect(&dir, &mut files).expect_err("missing directory");
+        assert_eq!(err.kind(), io::ErrorKind::NotFound);
+
+        let quota = Quota {
+            path: dir.display().to_string(),
+            max_size: 0,
+        };
+        let report = enforce(&quota, true, SystemTime::now()).expect("empty");
+        assert_eq!(report.size, 0);
+        assert!(report.removed.is_empty());
+    }
+}
@@ -0,0 +1,243 @@
+use std::{fs, io, path::Path};
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use data_encoding::BASE64;
+use roxy::common::{Baseline, ComplianceReport, DriftItem, Subsystem, SystemState};
+use serde::Deserialize;
+
+use super::{persist, signing, state, ROXY_STATE_DIR};
+
+// The signed baseline installed by set(), as it was pushed by the Manager.
+pub(crate) const BASELINE_FILE: &str = "/etc/roxy/baseline";
+// The report of the last check with remediation, which is what a scheduled
+// check runs.
+const REPORT: &str = "compliance-report.json";
+
+#[derive(Debug, Deserialize)]
+struct SignedBaseline {
+    payload: String,
+    signature: String,
+}
+
+// Installs a baseline after checking its signature with the public key of
+// the Manager, replacing the installed one.
+//
+// # Errors
+//
+// * no public key of the Manager is installed
+// * invalid or unsigned baseline
+// * fail to run openssl or to write the baseline
+pub(crate) fn set(blob: &[u8]) -> Result<()> {
+    let baseline = verify(blob)?;
+    validate(&baseline)?;
+    persist::write(BASELINE_FILE, blob)?;
+    let _ = fs::remove_file(format!("{ROXY_STATE_DIR}/{REPORT}"));
+    Ok(())
+}
+
+// Removes the installed baseline and its report.
+//
+// # Errors
+//
+// * fail to remove the baseline
+pub(crate) fn delete() -> Result<()> {
+    persist::remove(BASELINE_FILE)?;
+    let _ = fs::remove_file(format!("{ROXY_STATE_DIR}/{REPORT}"));
+    Ok(())
+}
+
+// Gets the report of the last check with remediation.
+//
+// # Return
+//
+// * None if the baseline has not been checked since it was installed
+//
+// # Errors
+//
+// * fail to read the report
+pub(crate) fn get() -> Result<Option<ComplianceReport>> {
+    match fs::read_to_string(format!("{ROXY_STATE_DIR}/{REPORT}")) {
+        Ok(contents) => Ok(Some(serde_json::from_str(&contents)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+// Checks the system against the installed baseline. If `remediate` is true,
+// the subsystems of the baseline to remediate are restored, and the report
+// is saved for get(). The signature of the baseline is checked again, so a
+// baseline modified after installed is not applied.
+//
+// # Return
+//
+// * None if no baseline is installed
+//
+// # Errors
+//
+// * the signature of the baseline is invalid
+// * fail to get the current configuration of a subsystem
+// * fail to restore a subsystem, in which case all changes are rolled back
+pub(crate) fn check(remediate: bool) -> Result<Option<ComplianceReport>> {
+    let blob = match fs::read(BASELINE_FILE) {
+        Ok(blob) => blob,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let baseline = verify(&blob)?;
+    let checked_at = Local::now().to_rfc3339();
+    let mut violations = state::check(&baseline.state)?;
+    let mut remediated = Vec::new();
+    if remediate {
+        let target = select(&baseline, &violations);
+        if target != SystemState::default() {
+            remediated = state::apply(&target)?;
+            violations = state::check(&baseline.state)?;
+        }
+    }
+    let report = ComplianceReport {
+        baseline: baseline.id,
+        checked_at,
+        violations,
+        remediated,
+    };
+    if remediate {
+        fs::create_dir_all(ROXY_STATE_DIR)?;
+        fs::write(
+            format!("{ROXY_STATE_DIR}/{REPORT}"),
+            serde_json::to_string(&report)?,
+        )?;
+    }
+    Ok(Some(report))
+}
+
+fn verify(blob: &[u8]) -> Result<Baseline> {
+    if !Path::new(signing::REQUEST_KEY).exists() {
+        return Err(anyhow!(
+            "no public key of the Manager in {}",
+            signing::REQUEST_KEY
+        ));
+    }
+    let signed = serde_json::from_slice::<SignedBaseline>(blob)?;
+    let payload = BASE64.decode(signed.payload.as_bytes())?;
+    let signature = BASE64.decode(signed.signature.as_bytes())?;
+    if !signing::verify(signing::REQUEST_KEY, &payload, &signature)? {
+        return Err(anyhow!("invalid signature of the baseline"));
+    }
+    Ok(serde_json::from_slice(&payload)?)
+}
+
+fn validate(baseline: &Baseline) -> Result<()> {
+    if baseline.id.is_empty() {
+        return Err(anyhow!("the baseline has no ID"));
+    }
+    for subsystem in &baseline.remediate {
+        if !is_set(&baseline.state, *subsystem) {
+            return Err(anyhow!("{} to remediate is not in the baseline", subsystem));
+        }
+    }
+    Ok(())
+}
+
+fn is_set(state: &SystemState, subsystem: Subsystem) -> bool {
+    match subsystem {
+        Subsystem::Firewall => state.firewall.is_some(),
+        Subsystem::Hostname => state.hostname.is_some(),
+        Subsystem::Interface => state.interfaces.is_some(),
+        Subsystem::Ntp => state.ntp.is_some(),
+        Subsystem::Sshd => state.sshd.is_some(),
+        Subsystem::Syslog => state.syslog.is_some(),
+        Subsystem::Version => false,
+    }
+}
+
+// Returns the part of the baseline to apply: the subsystems to remediate
+// which violate it.
+fn select(baseline: &Baseline, violations: &[DriftItem]) -> SystemState {
+    let restore = |subsystem| {
+        baseline.remediate.contains(&subsystem)
+            && violations.iter().any(|item| item.subsystem == subsystem)
+    };
+    let state = &baseline.state;
+    SystemState {
+        hostname: state
+            .hostname
+            .clone()
+            .filter(|_| restore(Subsystem::Hostname)),
+        interfaces: state
+            .interfaces
+            .clone()
+            .filter(|_| restore(Subsystem::Interface)),
+        ntp: state.ntp.clone().filter(|_| restore(Subsystem::Ntp)),
+        syslog: state.syslog.clone().filter(|_| restore(Subsystem::Syslog)),
+        firewall: state
+            .firewall
+            .clone()
+            .filter(|_| restore(Subsystem::Firewall)),
+        sshd: state.sshd.clone().filter(|_| restore(Subsystem::Sshd)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use roxy::common::{FirewallState, SshdState};
+
+    use super::*;
+
+    fn baseline() -> Baseline {
+        Baseline {
+            id: "pci-2026-10".to_string(),
+            state: SystemState {
+                ntp: Some(vec!["time.example.com".to_string()]),
+                firewall: Some(FirewallState {
+                    enabled: true,
+                    rules: vec!["allow 22/tcp".to_string()],
+                }),
+                sshd: Some(SshdState { port: 10022 }),
+                ..SystemState::default()
+            },
+            remediate: vec![Subsystem::Firewall, Subsystem::Sshd],
+        }
+    }
+
+    fn violation(subsystem: Subsystem) -> DriftItem {
+        DriftItem {
+            subsystem,
+            target: None,
+            desired: "desired".to_string(),
+            current: "current".to_string(),
+        }
+    }
+
+    #[test]
+    fn remediated_subsystems() {
+        let baseline = baseline();
+        let target = select(
+            &baseline,
+            &[violation(Subsystem::Ntp), violation(Subsystem::Firewall)],
+        );
+        assert_eq!(
+            target,
+            SystemState {
+                firewall: baseline.state.firewall.clone(),
+                ..SystemState::default()
+            }
+        );
+        assert_eq!(select(&baseline, &[]), SystemState::default());
+    }
+
+    #[test]
+    fn invalid_baseline() {
+        assert!(validate(&baseline()).is_ok());
+        let mut invalid = baseline();
+        invalid.remediate.push(Subsystem::Hostname);
+        assert!(validate(&invalid).is_err());
+        invalid.remediate = vec![Subsystem::Version];
+        assert!(validate(&invalid).is_err());
+        invalid = Baseline {
+            id: String::new(),
+            ..baseline()
+        };
+        assert!(validate(&invalid).is_err());
+    }
+}
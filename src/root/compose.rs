@@ -0,0 +1,187 @@
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command, ContainerHealth};
+use serde_json::Value;
+
+use super::services::{read_conf, Stack};
+
+// Creates and starts the containers of a stack. Compose pulls a missing
+// image, so nothing is started if any image is not from an allowed registry.
+//
+// # Errors
+//
+// * the stack is not in the services configuration
+// * an image is not from an allowed registry
+// * fail to run compose
+pub(crate) fn up(name: &str) -> Result<()> {
+    let stack = stack(name)?;
+    check_registries(name, &stack)?;
+    compose(name, &stack, &["up", "-d", "--remove-orphans"]).map(|_| ())
+}
+
+// Stops and removes the containers of a stack.
+//
+// # Errors
+//
+// * the stack is not in the services configuration
+// * fail to run compose
+pub(crate) fn down(name: &str) -> Result<()> {
+    let stack = stack(name)?;
+    compose(name, &stack, &["down"]).map(|_| ())
+}
+
+// Pulls the images of a stack and recreates the containers whose image is
+// updated. Nothing is pulled if any image is not from an allowed registry.
+//
+// # Errors
+//
+// * the stack is not in the services configuration
+// * an image is not from an allowed registry
+// * fail to run compose
+pub(crate) fn update(name: &str) -> Result<()> {
+    let stack = stack(name)?;
+    check_registries(name, &stack)?;
+    compose(name, &stack, &["pull"])?;
+    compose(
+        name,
+        &stack,
+        &["up", "-d", "--remove-orphans", "--pull", "never"],
+    )
+    .map(|_| ())
+}
+
+// Gets the state and health of the containers of a stack.
+//
+// # Errors
+//
+// * the stack is not in the services configuration
+// * fail to run compose or to parse its output
+pub(crate) fn status(name: &str) -> Result<Vec<ContainerHealth>> {
+    let stack = stack(name)?;
+    let output = compose(name, &stack, &["ps", "--all", "--format", "json"])?;
+    parse_ps(&output)
+}
+
+// Fails if any image of the stack is not from an allowed registry.
+fn check_registries(name: &str, stack: &Stack) -> Result<()> {
+    let images = compose(name, stack, &["config", "--images"])?;
+    for image in images.lines().map(str::trim).filter(|l| !l.is_empty()) {
+        let registry = registry(image);
+        if !stack.registries.iter().any(|allowed| allowed == registry) {
+            return Err(anyhow!("registry of {} is not allowed", image));
+        }
+    }
+    Ok(())
+}
+
+fn stack(name: &str) -> Result<Stack> {
+    read_conf()?
+        .stacks
+        .remove(name)
+        .ok_or_else(|| anyhow!("unknown stack: {}", name))
+}
+
+// Runs `<runtime> compose` for the project and returns its stdout.
+fn compose(name: &str, stack: &Stack, args: &[&str]) -> Result<String> {
+    let output = run_command(
+        command(stack.runtime.program())
+            .args(["compose", "--file", &stack.file, "--project-name", name])
+            .args(args),
+    )?;
+    if output.status.success() {
+        Ok(String::from_utf8_lossy(&output.stdout).into_owned())
+    } else {
+        Err(anyhow!(
+            "fail to run compose {} for {}",
+            args.join(" "),
+            name
+        ))
+    }
+}
+
+// Gets the registry of an image reference. The first component is a
+// registry only if it looks like a host, as docker does; otherwise the image
+// is from Docker Hub.
+fn registry(image: &str) -> &str {
+    match image.split_once('/') {
+        Some((first, _)) if first.contains(['.', ':']) || first == "localhost" => first,
+        _ => "docker.io",
+    }
+}
+
+// Parses the output of `compose ps --format json`, which is a JSON array in
+// older versions and a JSON object per line in newer ones.
+fn parse_ps(output: &str) -> Result<Vec<ContainerHealth>> {
+    let output = output.trim();
+    let containers = if output.starts_with('[') {
+        serde_json::from_str::<Vec<Value>>(output)?
+    } else {
+        output
+            .lines()
+            .map(serde_json::from_str::<Value>)
+            .collect::<Result<Vec<_>, _>>()?
+    };
+    Ok(containers
+        .iter()
+        .map(|container| {
+            let field = |key: &str| {
+                container
+                    .get(key)
+                    .and_then(Value::as_str)
+                    .unwrap_or_default()
+                    .to_string()
+            };
+            let health = field("Health");
+            ContainerHealth {
+                name: field("Name"),
+                service: field("Service"),
+                state: field("State"),
+                health: if health.is_empty() {
+                    None
+                } else {
+                    Some(health)
+                },
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn registries() {
+        assert_eq!(
+            registry("registry.example.com/aice/review:1.2"),
+            "registry.example.com"
+        );
+        assert_eq!(registry("localhost:5000/review"), "localhost:5000");
+        assert_eq!(registry("localhost/review"), "localhost");
+        assert_eq!(registry("aice/review:1.2"), "docker.io");
+        assert_eq!(registry("postgres:16"), "docker.io");
+    }
+
+    #[test]
+    fn ps() {
+        let lines = r#"{"Name":"aice-review-1","Service":"review","State":"running","Health":"healthy"}
+{"Name":"aice-db-1","Service":"db","State":"exited","Health":""}
+"#;
+        let array = r#"[{"Name":"aice-review-1","Service":"review","State":"running","Health":"healthy"},{"Name":"aice-db-1","Service":"db","State":"exited","Health":""}]"#;
+        for output in [lines, array] {
+            let containers = parse_ps(output).expect("valid output");
+            assert_eq!(containers.len(), 2);
+            assert_eq!(
+                containers[0],
+                ContainerHealth {
+                    name: "aice-review-1".to_string(),
+                    service: "review".to_string(),
+                    state: "running".to_string(),
+                    health: Some("healthy".to_string()),
+                }
+            );
+            assert_eq!(containers[1].health, None);
+        }
+        assert_eq!(parse_ps("").expect("no container"), Vec::new());
+        assert_eq!(parse_ps("[]").expect("no container"), Vec::new());
+    }
+}
@@ -0,0 +1,254 @@
+use std::{
+    fs, io,
+    net::{SocketAddr, TcpStream, ToSocketAddrs, UdpSocket},
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{Endpoint, EndpointCheck, Protocol, ReadinessReport};
+
+use super::{ntp, syslog};
+
+// Endpoints the node has to reach besides its NTP and syslog servers, e.g.
+//
+// - name: manager
+//   host: manager.example.com
+//   port: 38390
+// - name: update
+//   host: update.example.com
+//   port: 443
+//   protocol: tcp
+pub(crate) const ENDPOINTS_CONF: &str = "/etc/roxy/endpoints.yaml";
+const CHECK_TIMEOUT: Duration = Duration::from_secs(3);
+// The number of endpoints checked at the same time, which bounds the threads
+// spawned for a long list of endpoints.
+const MAX_CONCURRENT_CHECKS: usize = 16;
+const NTP_PORT: u16 = 123;
+const SYSLOG_PORT: u16 = 514;
+// SNTP client request: leap indicator 0, version 3, mode 3 (client).
+const NTP_REQUEST_HEADER: u8 = 0x1b;
+const NTP_PACKET_LEN: usize = 48;
+const NTP_MODE_SERVER: u8 = 4;
+
+// Checks the endpoints in `ENDPOINTS_CONF`, the NTP and syslog servers of
+// the node, and the given endpoints, up to `MAX_CONCURRENT_CHECKS` at the
+// same time. A syslog server with an invalid address is reported as a failed
+// check.
+//
+// # Errors
+//
+// * fail to read or parse `ENDPOINTS_CONF`
+pub(crate) fn check(extra: Vec<Endpoint>) -> Result<ReadinessReport> {
+    let mut endpoints = read_conf()?;
+    // A node without the ntp or rsyslog configuration has no such servers.
+    let ntp_servers = ntp::get().ok().flatten().unwrap_or_default();
+    endpoints.extend(ntp_servers.into_iter().map(|host| Endpoint {
+        name: "ntp".to_string(),
+        host,
+        port: NTP_PORT,
+        protocol: Protocol::Ntp,
+    }));
+    let mut invalid = Vec::new();
+    for (_, proto, addr) in syslog::get().ok().flatten().unwrap_or_default() {
+        let protocol = if proto == "tcp" {
+            Protocol::Tcp
+        } else {
+            Protocol::Udp
+        };
+        match split_host_port(&addr, SYSLOG_PORT) {
+            Ok((host, port)) => endpoints.push(Endpoint {
+                name: "syslog".to_string(),
+                host,
+                port,
+                protocol,
+            }),
+            Err(e) => invalid.push(EndpointCheck {
+                endpoint: Endpoint {
+                    name: "syslog".to_string(),
+                    host: addr,
+                    port: SYSLOG_PORT,
+                    protocol,
+                },
+                address: None,
+                latency_ms: None,
+                error: Some(e.to_string()),
+            }),
+        }
+    }
+    endpoints.extend(extra);
+
+    let mut checks = Vec::with_capacity(endpoints.len() + invalid.len());
+    for batch in endpoints.chunks(MAX_CONCURRENT_CHECKS) {
+        checks.extend(thread::scope(|s| {
+            let handles = batch
+                .iter()
+                .map(|endpoint| s.spawn(move || check_endpoint(endpoint.clone())))
+                .collect::<Vec<_>>();
+            handles
+                .into_iter()
+                .map(|handle| handle.join().map_err(|_| anyhow!("check panicked")))
+                .collect::<Result<Vec<_>>>()
+        })?);
+    }
+    checks.extend(invalid);
+    Ok(ReadinessReport { checks })
+}
+
+fn read_conf() -> Result<Vec<Endpoint>> {
+    match fs::read_to_string(ENDPOINTS_CONF) {
+        Ok(contents) if contents.trim().is_empty() => Ok(Vec::new()),
+        Ok(contents) => Ok(serde_yaml::from_str(&contents)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn check_endpoint(endpoint: Endpoint) -> EndpointCheck {
+    let address = match (endpoint.host.as_str(), endpoint.port).to_socket_addrs() {
+        Ok(mut addrs) => addrs.next(),
+        Err(_) => None,
+    };
+    let Some(addr) = address else {
+        return EndpointCheck {
+            error: Some(format!("cannot resolve {}", endpoint.host)),
+            endpoint,
+            address,
+            latency_ms: None,
+        };
+    };
+    let start = Instant::now();
+    let ret = match endpoint.protocol {
+        Protocol::Tcp => TcpStream::connect_timeout(&addr, CHECK_TIMEOUT).map(|_| true),
+        Protocol::Udp => probe_udp(addr).map(|()| false),
+        Protocol::Ntp => query_ntp(addr).map(|()| true),
+    };
+    let (latency_ms, error) = match ret {
+        Ok(measured) => (
+            measured.then(|| u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX)),
+            None,
+        ),
+        Err(e) => (None, Some(e.to_string())),
+    };
+    EndpointCheck {
+        endpoint,
+        address,
+        latency_ms,
+        error,
+    }
+}
+
+fn bind_for(addr: SocketAddr) -> io::Result<UdpSocket> {
+    let socket = if addr.is_ipv4() {
+        UdpSocket::bind("0.0.0.0:0")?
+    } else {
+        UdpSocket::bind("[::]:0")?
+    };
+    socket.connect(addr)?;
+    socket.set_read_timeout(Some(CHECK_TIMEOUT))?;
+    Ok(socket)
+}
+
+// Sends an empty datagram. A connected UDP socket receives the ICMP port
+// unreachable error of the host as `ConnectionRefused`.
+fn probe_udp(addr: SocketAddr) -> io::Result<()> {
+    let socket = bind_for(addr)?;
+    socket.send(&[])?;
+    let mut buf = [0; 1];
+    match socket.recv(&mut buf) {
+        Err(e)
+            if matches!(
+                e.kind(),
+                io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut
+            ) =>
+        {
+            Ok(())
+        }
+        Err(e) => Err(e),
+        Ok(_) => Ok(()),
+    }
+}
+
+fn query_ntp(addr: SocketAddr) -> io::Result<()> {
+    let socket = bind_for(addr)?;
+    let mut request = [0; NTP_PACKET_LEN];
+    request[0] = NTP_REQUEST_HEADER;
+    socket.send(&request)?;
+    let mut response = [0; NTP_PACKET_LEN];
+    let len = socket.recv(&mut response)?;
+    if len == NTP_PACKET_LEN && response[0] & 0x07 == NTP_MODE_SERVER {
+        Ok(())
+    } else {
+        Err(io::Error::new(
+            io::ErrorKind::InvalidData,
+            "invalid NTP response",
+        ))
+    }
+}
+
+// Splits "host:port", "[v6]:port", or "host" without a port.
+fn split_host_port(addr: &str, default_port: u16) -> Result<(String, u16)> {
+    let addr = addr.trim();
+    let (host, port) = if let Some(rest) = addr.strip_prefix('[') {
+        let (host, rest) = rest
+            .split_once(']')
+            .ok_or_else(|| anyhow!("invalid address: {}", addr))?;
+        (host, rest.strip_prefix(':'))
+    } else {
+        match addr.split_once(':') {
+            Some((host, port)) => (host, Some(port)),
+            None => (addr, None),
+        }
+    };
+    let port = match port {
+        Some(port) => port
+            .parse::<u16>()
+            .map_err(|_| anyhow!("invalid port: {}", addr))?,
+        None => default_port,
+    };
+    Ok((host.to_string(), port))
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::TcpListener;
+
+    use super::*;
+
+    #[test]
+    fn host_port() {
+        assert_eq!(
+            split_host_port("192.168.0.205:7500", 514).unwrap(),
+            ("192.168.0.205".to_string(), 7500)
+        );
+        assert_eq!(
+            split_host_port("[2001:db8::1]:601", 514).unwrap(),
+            ("2001:db8::1".to_string(), 601)
+        );
+        assert_eq!(
+            split_host_port("logs.example.com", 514).unwrap(),
+            ("logs.example.com".to_string(), 514)
+        );
+        assert!(split_host_port("logs.example.com:syslog", 514).is_err());
+    }
+
+    #[test]
+    fn tcp_endpoint() {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        let port = listener.local_addr().unwrap().port();
+        let endpoint = |port| Endpoint {
+            name: "manager".to_string(),
+            host: "127.0.0.1".to_string(),
+            port,
+            protocol: Protocol::Tcp,
+        };
+        let check = check_endpoint(endpoint(port));
+        assert!(check.passed());
+        assert!(check.latency_ms.is_some());
+
+        drop(listener);
+        let check = check_endpoint(endpoint(port));
+        assert!(!check.passed());
+        assert_eq!(check.latency_ms, None);
+    }
+}
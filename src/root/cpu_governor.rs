@@ -0,0 +1,213 @@
+use std::{fs, io, path::Path};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command, CpuGovernor, GovernorStatus};
+
+use crate::root::persist;
+
+const SYS_CPU: &str = "/sys/devices/system/cpu";
+const UNIT_PATH: &str = "/etc/systemd/system/roxy-cpu-governor.service";
+const UNIT_NAME: &str = "roxy-cpu-governor.service";
+// Ubuntu sets the "ondemand" or "powersave" governor a minute after boot,
+// overriding the governor set by roxy.
+const ONDEMAND_UNIT: &str = "ondemand.service";
+const ONDEMAND_UNIT_PATH: &str = "/lib/systemd/system/ondemand.service";
+
+// Sets the governor of all the CPUs, and installs a unit which sets it again
+// at boot.
+//
+// # Errors
+//
+// * the CPUs do not support frequency scaling, or the governor
+// * fail to set the governor of a CPU
+// * fail to write or enable the unit
+pub(crate) fn set(governor: &str) -> Result<()> {
+    let cpus = cpus()?;
+    if cpus.is_empty() {
+        return Err(anyhow!("CPU frequency scaling is not supported"));
+    }
+    if !available(&cpus)?.iter().any(|g| g == governor) {
+        return Err(anyhow!("unsupported governor: {}", governor));
+    }
+    for cpu in &cpus {
+        fs::write(governor_path(*cpu), governor)
+            .map_err(|e| anyhow!("fail to set the governor of CPU {}. {}", cpu, e))?;
+    }
+
+    persist::write(UNIT_PATH, unit(governor))?;
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", UNIT_NAME])?;
+    if Path::new(ONDEMAND_UNIT_PATH).exists() {
+        systemctl(&["disable", ONDEMAND_UNIT])?;
+    }
+    Ok(())
+}
+
+// Gets the governor of each CPU, the governors supported by all of them, and
+// the governor set at boot.
+//
+// # Errors
+//
+// * fail to read the governors
+pub(crate) fn get() -> Result<GovernorStatus> {
+    let cpus = cpus()?;
+    let driver = match cpus.first() {
+        Some(cpu) => Some(
+            fs::read_to_string(format!("{SYS_CPU}/cpu{cpu}/cpufreq/scaling_driver"))?
+                .trim()
+                .to_string(),
+        ),
+        None => None,
+    };
+    let available = available(&cpus)?;
+    let cpus = cpus
+        .into_iter()
+        .map(|cpu| {
+            Ok(CpuGovernor {
+                cpu,
+                governor: fs::read_to_string(governor_path(cpu))?.trim().to_string(),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    Ok(GovernorStatus {
+        driver,
+        available,
+        cpus,
+        persistent: fs::read_to_string(UNIT_PATH)
+            .ok()
+            .and_then(|contents| parse_unit(&contents)),
+    })
+}
+
+// Removes the unit setting the governor at boot, and lets Ubuntu set its
+// default governor again. The running CPUs keep their governor until reboot.
+//
+// # Errors
+//
+// * fail to disable or remove the unit
+pub(crate) fn delete() -> Result<()> {
+    if !Path::new(UNIT_PATH).exists() {
+        return Ok(());
+    }
+    systemctl(&["disable", UNIT_NAME])?;
+    persist::remove(UNIT_PATH)?;
+    systemctl(&["daemon-reload"])?;
+    if Path::new(ONDEMAND_UNIT_PATH).exists() {
+        systemctl(&["enable", ONDEMAND_UNIT])?;
+    }
+    Ok(())
+}
+
+// The online CPUs supporting frequency scaling, in order.
+fn cpus() -> Result<Vec<u32>> {
+    let mut cpus = fs::read_dir(SYS_CPU)?
+        .flatten()
+        .filter_map(|entry| {
+            entry
+                .file_name()
+                .to_string_lossy()
+                .strip_prefix("cpu")?
+                .parse::<u32>()
+                .ok()
+        })
+        .filter(|cpu| Path::new(&governor_path(*cpu)).exists())
+        .collect::<Vec<_>>();
+    cpus.sort_unstable();
+    Ok(cpus)
+}
+
+// The governors supported by all the CPUs, in the order listed by the first.
+fn available(cpus: &[u32]) -> Result<Vec<String>> {
+    let mut lists = Vec::new();
+    for cpu in cpus {
+        match fs::read_to_string(format!(
+            "{SYS_CPU}/cpu{cpu}/cpufreq/scaling_available_governors"
+        )) {
+            Ok(list) => lists.push(list),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => lists.push(String::new()),
+            Err(e) => return Err(e.into()),
+        }
+    }
+    Ok(common_governors(&lists))
+}
+
+fn common_governors(lists: &[String]) -> Vec<String> {
+    let Some((first, rest)) = lists.split_first() else {
+        return Vec::new();
+    };
+    first
+        .split_whitespace()
+        .filter(|governor| {
+            rest.iter()
+                .all(|list| list.split_whitespace().any(|g| g == *governor))
+        })
+        .map(ToString::to_string)
+        .collect()
+}
+
+fn governor_path(cpu: u32) -> String {
+    format!("{SYS_CPU}/cpu{cpu}/cpufreq/scaling_governor")
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let output = run_command(command("systemctl").args(args))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("fail to run systemctl {}", args.join(" ")))
+    }
+}
+
+// The governor is one of the available governors, which are single words.
+fn unit(governor: &str) -> String {
+    format!(
+        "# Written by roxy.\n\
+         [Unit]\n\
+         Description=CPU frequency scaling governor\n\
+         After={ONDEMAND_UNIT}\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         RemainAfterExit=yes\n\
+         ExecStart=/bin/sh -c 'for f in {SYS_CPU}/cpu*/cpufreq/scaling_governor; \
+         do echo {governor} > \"$$f\"; done'\n\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n"
+    )
+}
+
+// Reads the governor from a unit written by `unit`.
+fn parse_unit(contents: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let (_, rest) = line.strip_prefix("ExecStart=")?.split_once(" echo ")?;
+        rest.split_whitespace().next().map(ToString::to_string)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn governors_of_all_cpus() {
+        let lists = [
+            "performance powersave\n".to_string(),
+            "conservative ondemand userspace powersave performance schedutil\n".to_string(),
+        ];
+        assert_eq!(common_governors(&lists), ["performance", "powersave"]);
+        assert_eq!(
+            common_governors(&[lists[0].clone(), String::new()]),
+            Vec::<String>::new()
+        );
+        assert!(common_governors(&[]).is_empty());
+    }
+
+    #[test]
+    fn unit_round_trip() {
+        let contents = unit("performance");
+        assert!(contents.contains("do echo performance > \"$$f\"; done'\n"));
+        assert_eq!(parse_unit(&contents).as_deref(), Some("performance"));
+        assert_eq!(parse_unit("[Unit]\n"), None);
+    }
+}
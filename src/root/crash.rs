@@ -0,0 +1,126 @@
+use std::{
+    backtrace::Backtrace,
+    cell::RefCell,
+    fs, io,
+    panic::{self, PanicHookInfo},
+    process,
+};
+
+use anyhow::Result;
+use chrono::Local;
+use roxy::common::CrashReport;
+
+use super::ROXY_STATE_DIR;
+
+const CRASH_DIR: &str = "crash";
+// The oldest reports are removed beyond this.
+const MAX_REPORTS: usize = 20;
+
+thread_local! {
+    // The kind of the task being executed by the thread, for the crash
+    // report. The read-only tasks of a batch are executed on several threads.
+    static CURRENT_TASK: RefCell<Option<String>> = const { RefCell::new(None) };
+}
+
+// Writes a crash report when roxy panics, before the default hook prints the
+// panic.
+pub(crate) fn install_hook() {
+    let default_hook = panic::take_hook();
+    panic::set_hook(Box::new(move |info| {
+        if let Err(e) = write(info) {
+            super::task::log_debug(&format!("fail to write a crash report. {e:?}"));
+        }
+        default_hook(info);
+    }));
+}
+
+// Sets the kind of the task being executed by this thread.
+pub(crate) fn set_task(kind: &str) {
+    CURRENT_TASK.with_borrow_mut(|task| *task = Some(kind.to_string()));
+}
+
+fn write(info: &PanicHookInfo) -> Result<()> {
+    let payload = info.payload();
+    let message = payload
+        .downcast_ref::<&str>()
+        .map(ToString::to_string)
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_default();
+    let report = CrashReport {
+        time: Local::now().timestamp(),
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        // The task is not borrowed mutably while it is being set.
+        task: CURRENT_TASK
+            .try_with(|task| task.try_borrow().ok().and_then(|task| task.clone()))
+            .ok()
+            .flatten(),
+        message,
+        location: info.location().map(ToString::to_string),
+        backtrace: Backtrace::force_capture().to_string(),
+    };
+
+    let dir = format!("{ROXY_STATE_DIR}/{CRASH_DIR}");
+    fs::create_dir_all(&dir)?;
+    fs::write(
+        format!("{dir}/{}-{}.json", report.time, process::id()),
+        serde_json::to_vec_pretty(&report)?,
+    )?;
+
+    let mut names = names()?;
+    while names.len() > MAX_REPORTS {
+        fs::remove_file(format!("{dir}/{}", names.remove(0)))?;
+    }
+    Ok(())
+}
+
+// Gets the crash reports, oldest first.
+//
+// # Errors
+//
+// * fail to read the crash directory
+pub(crate) fn get() -> Result<Vec<CrashReport>> {
+    let mut reports = Vec::new();
+    for name in names()? {
+        let contents = fs::read(format!("{ROXY_STATE_DIR}/{CRASH_DIR}/{name}"))?;
+        // A report cut off by a crash while writing it is skipped.
+        if let Ok(report) = serde_json::from_slice(&contents) {
+            reports.push(report);
+        }
+    }
+    Ok(reports)
+}
+
+// Removes the crash reports.
+//
+// # Errors
+//
+// * fail to remove the crash directory
+pub(crate) fn clear() -> Result<()> {
+    match fs::remove_dir_all(format!("{ROXY_STATE_DIR}/{CRASH_DIR}")) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+// The names of the report files, oldest first.
+fn names() -> Result<Vec<String>> {
+    let entries = match fs::read_dir(format!("{ROXY_STATE_DIR}/{CRASH_DIR}")) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut names = entries
+        .flatten()
+        .filter(|entry| entry.path().extension().is_some_and(|ext| ext == "json"))
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    // Sorted by the time in the name, which may have fewer digits than
+    // another.
+    names.sort_by_key(|name| {
+        let time = name
+            .split_once('-')
+            .and_then(|(time, _)| time.parse::<i64>().ok());
+        (time, name.clone())
+    });
+    Ok(names)
+}
@@ -0,0 +1,218 @@
+use std::{fs, net::IpAddr};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command, DnsLookup, DnsResolver, UpstreamDns};
+
+use super::fqdn;
+
+const RESOLV_CONF: &str = "/etc/resolv.conf";
+// Addresses of the stub resolver of systemd-resolved.
+const RESOLVED_STUBS: [&str; 2] = ["127.0.0.53", "127.0.0.54"];
+// Exit status of getent if the name is not found.
+const GETENT_NOT_FOUND: i32 = 2;
+
+// Resolves a name or an address with the system resolver, the same way as
+// other programs on the node do, including /etc/hosts. getent reports only
+// the canonical name at the end of a CNAME chain, not the aliases in between.
+//
+// # Return
+//
+// * DnsLookup with no addresses if the name is not found
+//
+// # Errors
+//
+// * invalid name
+// * fail to run getent or to read /etc/resolv.conf
+pub(crate) fn lookup(name: &str) -> Result<DnsLookup> {
+    if name.parse::<IpAddr>().is_err() {
+        fqdn::validate(name.strip_suffix('.').unwrap_or(name))?;
+    }
+    let output = run_command(command("getent").args(["ahosts", "--", name]))?;
+    let (canonical_name, addresses) = match output.status.code() {
+        Some(0) => parse_ahosts(&String::from_utf8_lossy(&output.stdout)),
+        Some(GETENT_NOT_FOUND) => (None, Vec::new()),
+        _ => return Err(anyhow!("fail to resolve {}. {}", name, output.status)),
+    };
+    Ok(DnsLookup {
+        name: name.to_string(),
+        canonical_name: canonical_name.filter(|canonical| canonical != name),
+        addresses,
+        resolver: resolver()?,
+    })
+}
+
+// Flushes the cache of systemd-resolved.
+//
+// # Errors
+//
+// * fail to run resolvectl
+pub(crate) fn flush() -> Result<()> {
+    let output = run_command(command("resolvectl").arg("flush-caches"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "fail to flush DNS cache. {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ))
+    }
+}
+
+fn resolver() -> Result<DnsResolver> {
+    let mut resolver = parse_resolv_conf(&fs::read_to_string(RESOLV_CONF)?);
+    resolver.stub = resolver
+        .nameservers
+        .iter()
+        .any(|server| RESOLVED_STUBS.contains(&server.to_string().as_str()));
+    if resolver.stub {
+        // Without systemd-resolved, the stub answers nothing, which is the
+        // very problem to be diagnosed; it is reported as no upstream.
+        if let Ok(output) = run_command(command("resolvectl").arg("dns")) {
+            if output.status.success() {
+                resolver.upstream = parse_resolvectl_dns(&String::from_utf8_lossy(&output.stdout));
+            }
+        }
+    }
+    Ok(resolver)
+}
+
+// Gets the canonical name and the addresses from the output of
+// `getent ahosts`, which lists an address once for each socket type:
+//
+// 93.184.216.34   STREAM example.com
+// 93.184.216.34   DGRAM
+// 93.184.216.34   RAW
+fn parse_ahosts(output: &str) -> (Option<String>, Vec<IpAddr>) {
+    let mut canonical_name = None;
+    let mut addresses = Vec::new();
+    for line in output.lines() {
+        let mut fields = line.split_whitespace();
+        let Some(Ok(addr)) = fields.next().map(str::parse::<IpAddr>) else {
+            continue;
+        };
+        if canonical_name.is_none() {
+            canonical_name = fields.nth(1).map(ToString::to_string);
+        }
+        if !addresses.contains(&addr) {
+            addresses.push(addr);
+        }
+    }
+    (canonical_name, addresses)
+}
+
+fn parse_resolv_conf(contents: &str) -> DnsResolver {
+    let mut resolver = DnsResolver::default();
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        match fields.next() {
+            Some("nameserver") => {
+                // A link-local address may have a scope, e.g. "fe80::1%eth0".
+                if let Some(Ok(addr)) = fields
+                    .next()
+                    .map(|addr| addr.split('%').next().unwrap_or(addr).parse::<IpAddr>())
+                {
+                    resolver.nameservers.push(addr);
+                }
+            }
+            // The last of "search" and "domain" takes effect.
+            Some("search" | "domain") => {
+                resolver.search = fields.map(ToString::to_string).collect();
+            }
+            _ => {}
+        }
+    }
+    resolver
+}
+
+// Parses the output of `resolvectl dns`:
+//
+// Global: 1.1.1.1#cloudflare-dns.com
+// Link 2 (eth0): 10.0.0.1 10.0.0.2
+// Link 3 (eth1):
+fn parse_resolvectl_dns(output: &str) -> Vec<UpstreamDns> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (label, servers) = line.split_once(':')?;
+            let link = match label.split_once('(') {
+                Some((_, ifname)) => ifname.trim_end_matches(')'),
+                None => label.trim(),
+            };
+            let servers = servers
+                .split_whitespace()
+                .map(ToString::to_string)
+                .collect::<Vec<_>>();
+            (!servers.is_empty()).then(|| UpstreamDns {
+                link: link.to_string(),
+                servers,
+            })
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use std::net::{IpAddr, Ipv4Addr};
+
+    use super::*;
+
+    #[test]
+    fn ahosts() {
+        let output = "140.82.121.4    STREAM github.com\n\
+                      140.82.121.4    DGRAM  \n\
+                      140.82.121.4    RAW    \n\
+                      2001:db8::1     STREAM \n\
+                      2001:db8::1     DGRAM  \n";
+        let (canonical_name, addresses) = parse_ahosts(output);
+        assert_eq!(canonical_name.as_deref(), Some("github.com"));
+        assert_eq!(
+            addresses,
+            vec![
+                IpAddr::V4(Ipv4Addr::new(140, 82, 121, 4)),
+                "2001:db8::1".parse::<IpAddr>().unwrap()
+            ]
+        );
+        assert_eq!(parse_ahosts(""), (None, Vec::new()));
+    }
+
+    #[test]
+    fn resolv_conf() {
+        let resolver = parse_resolv_conf(
+            "# This is /run/systemd/resolve/stub-resolv.conf\n\
+             nameserver 127.0.0.53\n\
+             nameserver fe80::1%eth0\n\
+             options edns0 trust-ad\n\
+             search example.com corp.example.com\n",
+        );
+        assert_eq!(
+            resolver.nameservers,
+            vec![
+                IpAddr::V4(Ipv4Addr::new(127, 0, 0, 53)),
+                "fe80::1".parse::<IpAddr>().unwrap()
+            ]
+        );
+        assert_eq!(resolver.search, vec!["example.com", "corp.example.com"]);
+    }
+
+    #[test]
+    fn resolvectl_dns() {
+        let upstream = parse_resolvectl_dns(
+            "Global: 1.1.1.1#cloudflare-dns.com\n\
+             Link 2 (eth0): 10.0.0.1 10.0.0.2\n\
+             Link 3 (eth1):\n",
+        );
+        assert_eq!(
+            upstream,
+            vec![
+                UpstreamDns {
+                    link: "Global".to_string(),
+                    servers: vec!["1.1.1.1#cloudflare-dns.com".to_string()],
+                },
+                UpstreamDns {
+                    link: "eth0".to_string(),
+                    servers: vec!["10.0.0.1".to_string(), "10.0.0.2".to_string()],
+                },
+            ]
+        );
+    }
+}
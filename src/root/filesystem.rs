@@ -0,0 +1,162 @@
+use std::fs;
+
+use anyhow::Result;
+use chrono::{Local, NaiveDateTime, TimeZone};
+use roxy::common::{command, run_command, FilesystemHealth};
+
+use super::persist::unescape;
+
+const MOUNTS: &str = "/proc/self/mounts";
+// Filesystems whose superblock is read by tune2fs.
+const EXT_TYPES: [&str; 3] = ["ext2", "ext3", "ext4"];
+
+// Gets the errors of the filesystems mounted from block devices. A device
+// mounted at several mount points, e.g. by bind mounts, is reported once.
+//
+// # Errors
+//
+// * fail to read /proc/self/mounts
+pub(crate) fn health() -> Result<Vec<FilesystemHealth>> {
+    // The kernel log may be restricted, or empty in a container.
+    let kernel_log = run_command(command("dmesg").arg("--kernel"))
+        .ok()
+        .filter(|output| output.status.success())
+        .map(|output| String::from_utf8_lossy(&output.stdout).into_owned())
+        .unwrap_or_default();
+
+    let mut ret = Vec::new();
+    for mut health in parse_mounts(&fs::read_to_string(MOUNTS)?) {
+        if EXT_TYPES.contains(&health.fs_type.as_str()) {
+            if let Ok(output) = run_command(command("tune2fs").args(["-l", &health.device])) {
+                if output.status.success() {
+                    parse_tune2fs(&String::from_utf8_lossy(&output.stdout), &mut health);
+                }
+            }
+        }
+        // The kernel names a device mapper device, e.g. /dev/mapper/vg-root,
+        // by its node, e.g. dm-0.
+        let node = fs::canonicalize(&health.device)
+            .map_or_else(|_| health.device.clone(), |p| p.display().to_string());
+        health.kernel_errors = kernel_errors(&kernel_log, &node);
+        ret.push(health);
+    }
+    Ok(ret)
+}
+
+// Parses /proc/self/mounts such as:
+//
+// /dev/sda1 /data ext4 rw,relatime 0 0
+fn parse_mounts(contents: &str) -> Vec<FilesystemHealth> {
+    let mut mounts: Vec<FilesystemHealth> = Vec::new();
+    for line in contents.lines() {
+        let fields = line.split_whitespace().collect::<Vec<_>>();
+        let (Some(device), Some(mount_point), Some(fs_type)) =
+            (fields.first(), fields.get(1), fields.get(2))
+        else {
+            continue;
+        };
+        let device = unescape(device);
+        if !device.starts_with("/dev/") || mounts.iter().any(|m| m.device == device) {
+            continue;
+        }
+        mounts.push(FilesystemHealth {
+            device,
+            mount_point: unescape(mount_point),
+            fs_type: (*fs_type).to_string(),
+            ..FilesystemHealth::default()
+        });
+    }
+    mounts
+}
+
+// Parses the superblock listed by `tune2fs -l`, such as:
+//
+// Filesystem state:         clean with errors
+// FS Error count:           3
+// First error time:         Wed May  1 09:00:00 2024
+// Last error time:          Thu May  2 10:30:00 2024
+//
+// The error lines are listed only if an error has been recorded.
+fn parse_tune2fs(output: &str, health: &mut FilesystemHealth) {
+    let time = |value: &str| {
+        NaiveDateTime::parse_from_str(value, "%a %b %e %H:%M:%S %Y")
+            .ok()
+            .and_then(|time| Local.from_local_datetime(&time).earliest())
+            .map(|time| time.timestamp())
+    };
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        match key {
+            "Filesystem state" => health.state = Some(value.to_string()),
+            "FS Error count" => health.error_count = value.parse().unwrap_or_default(),
+            "First error time" => health.first_error = time(value),
+            "Last error time" => health.last_error = time(value),
+            _ => {}
+        }
+    }
+}
+
+// Counts the filesystem errors of the device in the kernel log, such as:
+//
+// EXT4-fs error (device sda1): ext4_lookup:1855: inode #2: comm ls: ...
+// XFS (sdb1): Corruption detected. Unmount and run xfs_repair
+fn kernel_errors(log: &str, device: &str) -> u64 {
+    let name = device.rsplit('/').next().unwrap_or(device);
+    let tags = [format!("(device {name})"), format!("({name}):")];
+    let count = log
+        .lines()
+        .filter(|line| tags.iter().any(|tag| line.contains(tag.as_str())))
+        .filter(|line| {
+            let line = line.to_lowercase();
+            line.contains("error") || line.contains("corrupt")
+        })
+        .count();
+    u64::try_from(count).unwrap_or(u64::MAX)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn filesystem_errors() {
+        let mounts = parse_mounts(
+            "sysfs /sys sysfs rw 0 0\n\
+             /dev/sda1 / ext4 rw,relatime 0 0\n\
+             /dev/sdb1 /data\\040disk xfs rw 0 0\n\
+             /dev/sda1 /var/lib/docker ext4 rw 0 0\n",
+        );
+        assert_eq!(mounts.len(), 2);
+        assert_eq!(mounts[1].mount_point, "/data disk");
+
+        let mut health = mounts[0].clone();
+        parse_tune2fs(
+            "Filesystem volume name:   <none>\n\
+             Filesystem state:         clean with errors\n\
+             FS Error count:           3\n\
+             First error time:         Wed May  1 09:00:00 2024\n\
+             Last error time:          Thu May 2 10:30:00 2024\n",
+            &mut health,
+        );
+        assert_eq!(health.state.as_deref(), Some("clean with errors"));
+        assert_eq!(health.error_count, 3);
+        let first = Local
+            .with_ymd_and_hms(2024, 5, 1, 9, 0, 0)
+            .earliest()
+            .map(|time| time.timestamp());
+        assert_eq!(health.first_error, first);
+        assert!(health.last_error > health.first_error);
+        assert!(health.has_errors());
+
+        let log = "[  1.0] EXT4-fs (sda1): mounted filesystem with ordered data mode\n\
+                   [  2.0] EXT4-fs error (device sda1): ext4_lookup:1855: inode #2\n\
+                   [  3.0] XFS (sdb1): Corruption detected. Unmount and run xfs_repair\n\
+                   [  4.0] EXT4-fs error (device sda10): ext4_lookup:1855: inode #2\n";
+        assert_eq!(kernel_errors(log, "/dev/sda1"), 1);
+        assert_eq!(kernel_errors(log, "/dev/sdb1"), 1);
+        assert_eq!(kernel_errors(log, "/dev/sdc1"), 0);
+    }
+}
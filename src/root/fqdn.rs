@@ -0,0 +1,180 @@
+use std::{fmt::Write as FmtWrite, fs};
+
+use anyhow::{anyhow, Result};
+use roxy::common::HostnameInfo;
+
+use crate::root::persist;
+
+pub(crate) const HOSTS: &str = "/etc/hosts";
+pub(crate) const DOMAIN_SYSCTL_CONF: &str = "/etc/sysctl.d/90-roxy-domainname.conf";
+const KERNEL_DOMAINNAME: &str = "/proc/sys/kernel/domainname";
+const NO_DOMAINNAME: &str = "(none)";
+const HOSTS_LOOPBACK: &str = "127.0.1.1";
+const MAX_LABEL_LEN: usize = 63;
+const MAX_NAME_LEN: usize = 253;
+
+// Gets the short and fully-qualified names. The domain is read from
+// kernel.domainname, or from the 127.0.1.1 entry in /etc/hosts if it is not
+// set.
+//
+// # Errors
+//
+// * fail to open /etc/hosts
+pub(crate) fn get() -> Result<HostnameInfo> {
+    let hostname = roxy::hostname();
+    let domain = fs::read_to_string(KERNEL_DOMAINNAME)
+        .ok()
+        .map(|domain| domain.trim().to_string())
+        .filter(|domain| !domain.is_empty() && domain != NO_DOMAINNAME);
+    let domain = match domain {
+        Some(domain) => Some(domain),
+        None => hosts_domain(&fs::read_to_string(HOSTS)?, &hostname),
+    };
+    let fqdn = match &domain {
+        Some(domain) => format!("{hostname}.{domain}"),
+        None => hostname.clone(),
+    };
+    Ok(HostnameInfo {
+        hostname,
+        domain,
+        fqdn,
+    })
+}
+
+// Sets the hostname. If a fully-qualified name is given, the first label is
+// set as the hostname, and the rest as the domain. The domain is set to
+// kernel.domainname, persisted in /etc/sysctl.d, and written to the
+// 127.0.1.1 entry in /etc/hosts so that `hostname -f` returns the FQDN.
+//
+// # Example
+//
+// fqdn::set("sensor1.example.com")?;
+//
+// # Errors
+//
+// * invalid hostname
+// * fail to set hostname or kernel.domainname
+// * fail to write /etc/hosts or the sysctl configuration
+pub(crate) fn set(name: &str) -> Result<()> {
+    validate(name)?;
+    let (hostname, domain) = match name.split_once('.') {
+        Some((hostname, domain)) => (hostname, Some(domain)),
+        None => (name, None),
+    };
+
+    hostname::set(hostname)?;
+    fs::write(KERNEL_DOMAINNAME, domain.unwrap_or_default())?;
+    if let Some(domain) = domain {
+        persist::write(
+            DOMAIN_SYSCTL_CONF,
+            format!("kernel.domainname = {domain}\n"),
+        )?;
+    } else {
+        persist::remove(DOMAIN_SYSCTL_CONF)?;
+    }
+
+    let contents = fs::read_to_string(HOSTS)?;
+    persist::write(HOSTS, update_hosts(&contents, hostname, domain))?;
+    Ok(())
+}
+
+pub(crate) fn validate(name: &str) -> Result<()> {
+    let valid = !name.is_empty()
+        && name.len() <= MAX_NAME_LEN
+        && name.split('.').all(|label| {
+            !label.is_empty()
+                && label.len() <= MAX_LABEL_LEN
+                && !label.starts_with('-')
+                && !label.ends_with('-')
+                && label.chars().all(|c| c.is_ascii_alphanumeric() || c == '-')
+        });
+    if valid {
+        Ok(())
+    } else {
+        Err(anyhow!("invalid hostname: {}", name))
+    }
+}
+
+// Gets the domain from an entry such as "127.0.1.1 sensor1.example.com sensor1".
+fn hosts_domain(contents: &str, hostname: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let mut fields = line.split_whitespace();
+        if fields.next() != Some(HOSTS_LOOPBACK) {
+            return None;
+        }
+        fields.find_map(|name| {
+            name.strip_prefix(hostname)
+                .and_then(|rest| rest.strip_prefix('.'))
+                .map(ToString::to_string)
+        })
+    })
+}
+
+// Replaces the 127.0.1.1 entry, or appends it if there is none.
+fn update_hosts(contents: &str, hostname: &str, domain: Option<&str>) -> String {
+    let entry = match domain {
+        Some(domain) => format!("{HOSTS_LOOPBACK}\t{hostname}.{domain} {hostname}"),
+        None => format!("{HOSTS_LOOPBACK}\t{hostname}"),
+    };
+    let mut new_contents = String::new();
+    let mut found = false;
+    for line in contents.lines() {
+        if line.split_whitespace().next() == Some(HOSTS_LOOPBACK) {
+            if !found {
+                writeln!(new_contents, "{entry}").expect("writing to string should not fail");
+                found = true;
+            }
+        } else {
+            new_contents.push_str(line);
+            new_contents.push('\n');
+        }
+    }
+    if !found {
+        writeln!(new_contents, "{entry}").expect("writing to string should not fail");
+    }
+    new_contents
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOSTS_FQDN: &str = "127.0.0.1\tlocalhost
+127.0.1.1\tsensor1.example.com sensor1
+
+::1     ip6-localhost ip6-loopback
+";
+
+    #[test]
+    fn domain_in_hosts() {
+        assert_eq!(
+            hosts_domain(HOSTS_FQDN, "sensor1").as_deref(),
+            Some("example.com")
+        );
+        assert_eq!(hosts_domain(HOSTS_FQDN, "sensor2"), None);
+        assert_eq!(hosts_domain("127.0.1.1\tsensor1\n", "sensor1"), None);
+    }
+
+    #[test]
+    fn hosts_entry() {
+        let contents = update_hosts(HOSTS_FQDN, "sensor2", Some("example.org"));
+        assert_eq!(
+            contents.lines().nth(1),
+            Some("127.0.1.1\tsensor2.example.org sensor2")
+        );
+        assert_eq!(contents.lines().count(), HOSTS_FQDN.lines().count());
+
+        let contents = update_hosts("127.0.0.1\tlocalhost\n", "sensor1", None);
+        assert_eq!(contents, "127.0.0.1\tlocalhost\n127.0.1.1\tsensor1\n");
+    }
+
+    #[test]
+    fn invalid_hostname() {
+        assert!(validate("sensor1").is_ok());
+        assert!(validate("sensor-1.example.com").is_ok());
+        assert!(validate("").is_err());
+        assert!(validate("sensor1.").is_err());
+        assert!(validate("-sensor1").is_err());
+        assert!(validate("sensor_1").is_err());
+    }
+}
@@ -0,0 +1,151 @@
+use std::{fs, io};
+
+use anyhow::Result;
+use chrono::Local;
+use roxy::common::{command, run_command, TrimRun, TrimStatus, TrimmedFilesystem, ROXY_STATE_DIR};
+
+// The timer of util-linux trimming the filesystems weekly.
+const FSTRIM_TIMER: &str = "fstrim.timer";
+// The last run of fstrim on demand, under `ROXY_STATE_DIR`.
+const LAST_RUN: &str = "fstrim.json";
+
+// Runs fstrim on all the mounted filesystems supporting it, and records the
+// result as the last run.
+//
+// # Errors
+//
+// * fail to run fstrim
+// * fail to record the result
+pub(crate) fn run() -> Result<TrimRun> {
+    let time = Local::now().timestamp();
+    let output = run_command(command("fstrim").args(["--all", "--verbose"]))?;
+    let run = TrimRun {
+        time,
+        success: output.status.success(),
+        filesystems: String::from_utf8_lossy(&output.stdout)
+            .lines()
+            .filter_map(parse_trimmed)
+            .collect(),
+        errors: String::from_utf8_lossy(&output.stderr)
+            .lines()
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(ToString::to_string)
+            .collect(),
+    };
+
+    // The result is renamed into place, so that a partial result is never
+    // reported.
+    let path = format!("{ROXY_STATE_DIR}/{LAST_RUN}");
+    let tmp = format!("{path}.tmp");
+    fs::create_dir_all(ROXY_STATE_DIR)?;
+    fs::write(&tmp, serde_json::to_vec(&run)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(run)
+}
+
+// Gets the state of the fstrim timer and the last run of fstrim on demand.
+//
+// # Errors
+//
+// * fail to run systemctl
+// * fail to read the last run
+pub(crate) fn status() -> Result<TrimStatus> {
+    let timer_enabled = systemctl_succeeds("is-enabled")?;
+    let timer_active = systemctl_succeeds("is-active")?;
+    let last_run = match fs::read(format!("{ROXY_STATE_DIR}/{LAST_RUN}")) {
+        // A result of an older roxy which cannot be parsed is not reported.
+        Ok(contents) => serde_json::from_slice(&contents).ok(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    Ok(TrimStatus {
+        timer_enabled,
+        timer_active,
+        last_run,
+    })
+}
+
+// Enables and starts the fstrim timer.
+//
+// # Errors
+//
+// * fail to run systemctl
+pub(crate) fn enable() -> Result<bool> {
+    let systemctl = systemctl::SystemCtl::default();
+    if !systemctl.enable(FSTRIM_TIMER)?.success() {
+        return Ok(false);
+    }
+    systemctl
+        .start(FSTRIM_TIMER)
+        .map(|status| status.success())
+        .map_err(Into::into)
+}
+
+// Stops and disables the fstrim timer.
+//
+// # Errors
+//
+// * fail to run systemctl
+pub(crate) fn disable() -> Result<bool> {
+    let systemctl = systemctl::SystemCtl::default();
+    if !systemctl.stop(FSTRIM_TIMER)?.success() {
+        return Ok(false);
+    }
+    systemctl
+        .disable(FSTRIM_TIMER)
+        .map(|status| status.success())
+        .map_err(Into::into)
+}
+
+// Runs a query of systemctl on the timer, such as "is-enabled", which
+// succeeds if the answer is yes.
+fn systemctl_succeeds(query: &str) -> Result<bool> {
+    let output = run_command(command("systemctl").args([query, "--quiet", FSTRIM_TIMER]))?;
+    Ok(output.status.success())
+}
+
+// Parses a line of `fstrim --verbose`, such as
+// "/data: 1.2 GiB (1288490188 bytes) trimmed on /dev/sda1". Older versions
+// do not report the device.
+fn parse_trimmed(line: &str) -> Option<TrimmedFilesystem> {
+    let (amount, device) = line.split_once(" bytes) trimmed")?;
+    let (human, bytes) = amount.rsplit_once(" (")?;
+    // The human-readable size does not contain ": ", while the mount point
+    // may.
+    let (mount_point, _) = human.rsplit_once(": ")?;
+    Some(TrimmedFilesystem {
+        mount_point: mount_point.to_string(),
+        device: device
+            .trim()
+            .strip_prefix("on ")
+            .map(|device| device.trim().to_string()),
+        trimmed: bytes.parse().ok()?,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn trimmed_filesystems() {
+        assert_eq!(
+            parse_trimmed("/data: 1.2 GiB (1288490188 bytes) trimmed on /dev/sda1"),
+            Some(TrimmedFilesystem {
+                mount_point: "/data".to_string(),
+                device: Some("/dev/sda1".to_string()),
+                trimmed: 1_288_490_188,
+            })
+        );
+        assert_eq!(
+            parse_trimmed("/: 0 B (0 bytes) trimmed"),
+            Some(TrimmedFilesystem {
+                mount_point: "/".to_string(),
+                device: None,
+                trimmed: 0,
+            })
+        );
+        assert_eq!(parse_trimmed("fstrim: /boot: FITRIM ioctl failed"), None);
+    }
+}
@@ -0,0 +1,61 @@
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command, HardwareInfo};
+
+// Gets hardware inventory using dmidecode, and the BMC firmware revision
+// using ipmitool if it is installed and a BMC is present.
+//
+// # Errors
+//
+// * fail to execute dmidecode
+pub(crate) fn get() -> Result<HardwareInfo> {
+    Ok(HardwareInfo {
+        manufacturer: dmidecode("system-manufacturer")?,
+        product_name: dmidecode("system-product-name")?,
+        serial_number: dmidecode("system-serial-number")?,
+        bios_vendor: dmidecode("bios-vendor")?,
+        bios_version: dmidecode("bios-version")?,
+        bios_date: dmidecode("bios-release-date")?,
+        bmc_firmware: bmc_firmware(),
+    })
+}
+
+fn dmidecode(keyword: &str) -> Result<String> {
+    let output = run_command(command("dmidecode").args(["-s", keyword]))?;
+    if !output.status.success() {
+        return Err(anyhow!("fail to get {keyword}"));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).trim().to_string())
+}
+
+fn bmc_firmware() -> Option<String> {
+    let output = run_command(command("ipmitool").args(["mc", "info"])).ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    parse_firmware_revision(&String::from_utf8_lossy(&output.stdout))
+}
+
+// Parses the output of `ipmitool mc info`.
+fn parse_firmware_revision(output: &str) -> Option<String> {
+    output.lines().find_map(|line| {
+        let (key, value) = line.split_once(':')?;
+        (key.trim() == "Firmware Revision").then(|| value.trim().to_string())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn firmware_revision() {
+        let output = "Device ID                 : 32
+Device Revision           : 1
+Firmware Revision         : 2.61
+IPMI Version              : 2.0
+Manufacturer ID           : 10876
+";
+        assert_eq!(parse_firmware_revision(output).as_deref(), Some("2.61"));
+        assert_eq!(parse_firmware_revision(""), None);
+    }
+}
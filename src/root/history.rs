@@ -0,0 +1,229 @@
+use std::{
+    fs::{self, OpenOptions},
+    io::Write,
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use roxy::common::{HistoryEntry, Subsystem, WRITE_BEGIN, WRITE_END, WRITE_MARKER_DIR};
+use serde::{Deserialize, Serialize};
+
+use super::{transaction::Checkpoint, ROXY_STATE_DIR};
+
+const HISTORY_DIR: &str = "history";
+const MAX_HISTORY: usize = 10;
+
+#[derive(Debug, Deserialize, Serialize)]
+struct Snapshot {
+    id: i64,
+    time: String,
+    checkpoint: Checkpoint,
+}
+
+// Saves the current configuration of the subsystem if it differs from the
+// last saved one. Only the last MAX_HISTORY configurations are kept. Only the
+// files in `Checkpoint::of` are saved; a task changing no subsystem leaves
+// no history.
+//
+// # Errors
+//
+// * fail to read the configuration of the subsystem
+// * fail to read or write the history directory
+pub(crate) fn record(subsystem: Subsystem) -> Result<()> {
+    let checkpoint = Checkpoint::of(&[subsystem])?;
+    let snapshots = load(subsystem)?;
+    if snapshots
+        .last()
+        .is_some_and(|last| last.checkpoint == checkpoint)
+    {
+        return Ok(());
+    }
+
+    let now = Local::now();
+    let id = now
+        .timestamp_nanos_opt()
+        .ok_or_else(|| anyhow!("invalid timestamp"))?;
+    let snapshot = Snapshot {
+        id,
+        time: now.to_rfc3339(),
+        checkpoint,
+    };
+
+    let dir = history_dir(subsystem);
+    fs::create_dir_all(&dir)?;
+    let mut file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(true)
+        .open(format!("{dir}/{id}.json"))?;
+    file.write_all(serde_json::to_string(&snapshot)?.as_bytes())?;
+
+    let count = snapshots.len() + 1;
+    if count > MAX_HISTORY {
+        for old in snapshots.iter().take(count - MAX_HISTORY) {
+            fs::remove_file(format!("{dir}/{}.json", old.id))?;
+        }
+    }
+    Ok(())
+}
+
+// Records the time when roxy started or finished modifying the subsystem, so
+// that modifications made outside roxy can be told apart. The start and the
+// end are written to separate files, so that a watcher sees them in order
+// with the modifications.
+//
+// # Errors
+//
+// * fail to write the marker file
+pub(crate) fn mark_write(subsystem: Subsystem, start: i64, end: Option<i64>) -> Result<()> {
+    let dir = format!("{ROXY_STATE_DIR}/{WRITE_MARKER_DIR}");
+    fs::create_dir_all(&dir)?;
+    match end {
+        Some(end) => fs::write(
+            format!("{dir}/{subsystem}.{WRITE_END}"),
+            format!("{start} {end}\n"),
+        )?,
+        None => fs::write(
+            format!("{dir}/{subsystem}.{WRITE_BEGIN}"),
+            format!("{start}\n"),
+        )?,
+    }
+    Ok(())
+}
+
+// Gets the saved configurations with the diff from the previous one, oldest
+// first. All subsystems if None.
+//
+// # Errors
+//
+// * fail to read the history directory
+pub(crate) fn get(subsystem: Option<Subsystem>) -> Result<Vec<HistoryEntry>> {
+    let subsystems = if let Some(subsystem) = subsystem {
+        vec![subsystem]
+    } else {
+        Checkpoint::subsystems().to_vec()
+    };
+
+    let mut ret = Vec::new();
+    for subsystem in subsystems {
+        let mut prev: Option<Checkpoint> = None;
+        for snapshot in load(subsystem)? {
+            ret.push(HistoryEntry {
+                subsystem,
+                id: snapshot.id,
+                time: snapshot.time,
+                diff: diff(prev.as_ref(), &snapshot.checkpoint),
+            });
+            prev = Some(snapshot.checkpoint);
+        }
+    }
+    Ok(ret)
+}
+
+// Restores the saved configuration of the subsystem and applies it.
+//
+// # Errors
+//
+// * configuration not found
+// * fail to read the history directory
+// * fail to restore or apply the configuration
+pub(crate) fn revert(subsystem: Subsystem, id: i64) -> Result<()> {
+    let snapshot = load(subsystem)?
+        .into_iter()
+        .find(|snapshot| snapshot.id == id)
+        .ok_or_else(|| anyhow!("configuration {} of {} not found", id, subsystem))?;
+    snapshot.checkpoint.restore()
+}
+
+fn history_dir(subsystem: Subsystem) -> String {
+    format!("{ROXY_STATE_DIR}/{HISTORY_DIR}/{subsystem}")
+}
+
+fn load(subsystem: Subsystem) -> Result<Vec<Snapshot>> {
+    let dir = history_dir(subsystem);
+    if !Path::new(&dir).exists() {
+        return Ok(Vec::new());
+    }
+
+    let mut snapshots = Vec::new();
+    for entry in fs::read_dir(&dir)?.flatten() {
+        let path = entry.path();
+        if path.extension().is_some_and(|ext| ext == "json") {
+            let contents = fs::read_to_string(&path)?;
+            snapshots.push(serde_json::from_str::<Snapshot>(&contents)?);
+        }
+    }
+    snapshots.sort_by_key(|snapshot| snapshot.id);
+    Ok(snapshots)
+}
+
+pub(crate) fn diff(old: Option<&Checkpoint>, new: &Checkpoint) -> String {
+    let mut ret = Vec::new();
+    let old_hostname = old.and_then(Checkpoint::hostname);
+    if old_hostname != new.hostname() {
+        ret.push("--- hostname".to_string());
+        if let Some(hostname) = old_hostname {
+            ret.push(format!("-{hostname}"));
+        }
+        if let Some(hostname) = new.hostname() {
+            ret.push(format!("+{hostname}"));
+        }
+    }
+
+    let mut paths = new
+        .files()
+        .iter()
+        .chain(old.map_or(&[][..], Checkpoint::files))
+        .map(|(path, _)| path)
+        .collect::<Vec<_>>();
+    paths.sort();
+    paths.dedup();
+    for path in paths {
+        let contents = |checkpoint: Option<&Checkpoint>| {
+            checkpoint
+                .and_then(|c| c.files().iter().find(|(p, _)| p == path))
+                .and_then(|(_, contents)| contents.clone())
+                .unwrap_or_default()
+        };
+        let lines = diff_lines(&contents(old), &contents(Some(new)));
+        if !lines.is_empty() {
+            ret.push(format!("--- {path}"));
+            ret.extend(lines);
+        }
+    }
+    ret.join("\n")
+}
+
+// Compares two texts line by line using the longest common subsequence, and
+// returns the removed lines prefixed with '-' and the added lines with '+'.
+fn diff_lines(old: &str, new: &str) -> Vec<String> {
+    let old = old.lines().collect::<Vec<_>>();
+    let new = new.lines().collect::<Vec<_>>();
+    let mut lcs = vec![vec![0_usize; new.len() + 1]; old.len() + 1];
+    for i in (0..old.len()).rev() {
+        for j in (0..new.len()).rev() {
+            lcs[i][j] = if old[i] == new[j] {
+                lcs[i + 1][j + 1] + 1
+            } else {
+                lcs[i + 1][j].max(lcs[i][j + 1])
+            };
+        }
+    }
+
+    let mut ret = Vec::new();
+    let (mut i, mut j) = (0, 0);
+    while i < old.len() || j < new.len() {
+        if i < old.len() && j < new.len() && old[i] == new[j] {
+            i += 1;
+            j += 1;
+        } else if j < new.len() && (i == old.len() || lcs[i][j + 1] >= lcs[i + 1][j]) {
+            ret.push(format!("+{}", new[j]));
+            j += 1;
+        } else {
+            ret.push(format!("-{}", old[i]));
+            i += 1;
+        }
+    }
+    ret
+}
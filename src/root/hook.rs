@@ -0,0 +1,297 @@
+use std::{
+    fs, io,
+    io::{Read, Write},
+    os::unix::{fs::MetadataExt, process::CommandExt},
+    path::Path,
+    process::{Child, Command, Stdio},
+    sync::mpsc,
+    thread,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use nix::{
+    sys::signal::{killpg, Signal},
+    unistd::Pid,
+};
+use serde::Serialize;
+
+use super::changes;
+
+// An executable run before each mutating task. See `ScriptHook`.
+pub(crate) const POLICY_HOOK: &str = "/etc/roxy/policy-hook";
+const HOOK_TIMEOUT: Duration = Duration::from_secs(2);
+const POLL_INTERVAL: Duration = Duration::from_millis(10);
+// The output of a hook kept as its note or reason. The rest is discarded.
+const MAX_HOOK_OUTPUT: u64 = 64 * 1024;
+
+// A task about to be executed, given to the policy hooks.
+#[derive(Debug, Serialize)]
+pub(crate) struct Operation<'a> {
+    // The kind of the task, e.g. "Ufw::Disable".
+    pub(crate) kind: &'a str,
+    // The arguments of the task, in base64 of bincode.
+    pub(crate) arg: &'a str,
+    // The user who ran roxy.
+    pub(crate) user: String,
+}
+
+#[derive(Debug, PartialEq)]
+pub(crate) enum Verdict {
+    Allow,
+    // Allows the task, and attaches the note to the changes it makes.
+    Annotate(String),
+    // Refuses the task for the reason.
+    Veto(String),
+}
+
+// A site-specific guardrail checked before each mutating task, e.g. "never
+// disable ufw in production".
+pub(crate) trait PolicyHook {
+    fn check(&self, op: &Operation) -> Verdict;
+}
+
+// Runs `path` with the kind of the task as its argument, and the `Operation`
+// in JSON as its input. It allows the task if it exits with 0, and vetoes it
+// otherwise, or if it does not exit in `timeout`. Its output is the note on
+// the task if it is allowed, or the reason if vetoed. It is skipped if `path`
+// does not exist, but it vetoes every task if `path` is not trusted by
+// `trusted`, since roxy runs it as root.
+struct ScriptHook {
+    path: &'static str,
+    timeout: Duration,
+}
+
+impl PolicyHook for ScriptHook {
+    fn check(&self, op: &Operation) -> Verdict {
+        match trusted(self.path) {
+            Ok(true) => {}
+            Ok(false) => return Verdict::Allow,
+            Err(e) => return Verdict::Veto(e.to_string()),
+        }
+
+        let input = match serde_json::to_vec(op) {
+            Ok(input) => input,
+            Err(e) => return Verdict::Veto(e.to_string()),
+        };
+        match run(self.path, op.kind, input, self.timeout) {
+            Ok((true, output)) if output.is_empty() => Verdict::Allow,
+            Ok((true, output)) => Verdict::Annotate(output),
+            Ok((false, output)) if output.is_empty() => {
+                Verdict::Veto(format!("vetoed by {}", self.path))
+            }
+            Ok((false, output)) => Verdict::Veto(output),
+            Err(e) => Verdict::Veto(e.to_string()),
+        }
+    }
+}
+
+// Returns whether a hook exists. A hook which is a symlink, or which others
+// than root can write or replace through its directory, is an error, since
+// roxy runs it as root.
+//
+// # Errors
+//
+// * fail to read the hook or its directory
+// * the hook is a symlink
+// * the hook or its directory is writable by others than root
+pub(crate) fn trusted(path: &str) -> Result<bool> {
+    let metadata = match fs::symlink_metadata(path) {
+        Ok(metadata) => metadata,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(false),
+        Err(e) => return Err(anyhow!("fail to read {}. {}", path, e)),
+    };
+    if metadata.file_type().is_symlink() {
+        return Err(anyhow!("{} is a symlink", path));
+    }
+    if metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+        return Err(anyhow!("{} is writable by others than root", path));
+    }
+    let dir = Path::new(path).parent().unwrap_or(Path::new("/"));
+    let metadata =
+        fs::metadata(dir).map_err(|e| anyhow!("fail to read {}. {}", dir.display(), e))?;
+    if metadata.uid() != 0 || metadata.mode() & 0o022 != 0 {
+        return Err(anyhow!("{} is writable by others than root", dir.display()));
+    }
+    Ok(true)
+}
+
+// Runs a hook with an argument and its input, and kills it with the processes
+// it started if it does not exit and close its output in `timeout`. Only the
+// first `MAX_HOOK_OUTPUT` bytes of the output are kept.
+//
+// # Return
+//
+// * whether the hook succeeded, and its output
+//
+// # Errors
+//
+// * fail to run the hook, or it timed out
+pub(crate) fn run(
+    path: &str,
+    arg: &str,
+    input: Vec<u8>,
+    timeout: Duration,
+) -> Result<(bool, String)> {
+    // The hook leads its own process group, so that the processes it starts
+    // are killed with it.
+    let mut child = Command::new(path)
+        .arg(arg)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .process_group(0)
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        // The hook may exit without reading its input.
+        thread::spawn(move || {
+            let _ = stdin.write_all(&input);
+        });
+    }
+    // The output is read while waiting, or a hook writing more than the pipe
+    // holds would block until it times out. The reader is not joined, since a
+    // process left by the hook may keep the output open.
+    let (tx, rx) = mpsc::channel();
+    if let Some(mut stdout) = child.stdout.take() {
+        thread::spawn(move || {
+            let mut output = Vec::new();
+            let _ = (&mut stdout).take(MAX_HOOK_OUTPUT).read_to_end(&mut output);
+            let _ = io::copy(&mut stdout, &mut io::sink());
+            let _ = tx.send(output);
+        });
+    }
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        if let Some(status) = child.try_wait()? {
+            break status;
+        }
+        if Instant::now() > deadline {
+            kill(&mut child);
+            return Err(anyhow!("{} timed out", path));
+        }
+        thread::sleep(POLL_INTERVAL);
+    };
+    let output = match rx.recv_timeout(deadline.saturating_duration_since(Instant::now())) {
+        Ok(output) => output,
+        Err(mpsc::RecvTimeoutError::Disconnected) => Vec::new(),
+        Err(mpsc::RecvTimeoutError::Timeout) => {
+            kill(&mut child);
+            return Err(anyhow!("{} timed out", path));
+        }
+    };
+    Ok((
+        status.success(),
+        String::from_utf8_lossy(&output).trim().to_string(),
+    ))
+}
+
+// Kills a hook and the processes in its process group.
+fn kill(child: &mut Child) {
+    if let Ok(pid) = i32::try_from(child.id()) {
+        let _ = killpg(Pid::from_raw(pid), Signal::SIGKILL);
+    }
+    let _ = child.kill();
+    let _ = child.wait();
+}
+
+// The hooks in the order they are checked. Hooks compiled into roxy for a
+// site are added here.
+fn hooks() -> Vec<Box<dyn PolicyHook>> {
+    vec![Box::new(ScriptHook {
+        path: POLICY_HOOK,
+        timeout: HOOK_TIMEOUT,
+    })]
+}
+
+// Checks the task with the policy hooks.
+//
+// # Return
+//
+// * the notes on the task
+//
+// # Errors
+//
+// * a hook vetoed the task
+pub(crate) fn check(kind: &str, arg: &str) -> Result<Vec<String>> {
+    let op = Operation {
+        kind,
+        arg,
+        user: changes::actor(),
+    };
+    let mut notes = Vec::new();
+    for hook in hooks() {
+        match hook.check(&op) {
+            Verdict::Allow => {}
+            Verdict::Annotate(note) => notes.push(note),
+            Verdict::Veto(reason) => return Err(anyhow!("{} vetoed. {}", kind, reason)),
+        }
+    }
+    Ok(notes)
+}
+
+#[cfg(test)]
+mod tests {
+    use std::{env, os::unix::fs::PermissionsExt, process};
+
+    use super::*;
+
+    fn run_script(name: &str, script: &str, timeout: Duration) -> Result<(bool, String)> {
+        let path = env::temp_dir().join(format!("roxy-hook-{name}-{}", process::id()));
+        fs::write(&path, script).expect("hook");
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).expect("permissions");
+
+        let ret = run(
+            &path.display().to_string(),
+            "Ufw::Disable",
+            Vec::new(),
+            timeout,
+        );
+        fs::remove_file(&path).expect("remove hook");
+        ret
+    }
+
+    #[test]
+    fn large_output() {
+        let ret = run_script(
+            "large",
+            "#!/bin/sh\nhead -c 1048576 /dev/zero | tr '\\0' x\n",
+            Duration::from_secs(10),
+        );
+        let (success, output) = ret.expect("hook should exit");
+        assert!(success);
+        assert_eq!(output.len(), 65_536);
+    }
+
+    #[test]
+    fn left_process() {
+        // The hook exits, but the process it started keeps its output open.
+        let started = Instant::now();
+        let ret = run_script(
+            "left",
+            "#!/bin/sh\nsleep 30 &\nexit 0\n",
+            Duration::from_secs(1),
+        );
+        assert!(ret.is_err());
+        assert!(started.elapsed() < Duration::from_secs(10));
+    }
+
+    #[test]
+    fn untrusted() {
+        let dir = env::temp_dir().join(format!("roxy-hook-dir-{}", process::id()));
+        fs::create_dir(&dir).expect("directory");
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o755)).expect("permissions");
+        let link = dir.join("policy-hook");
+        std::os::unix::fs::symlink("/bin/true", &link).expect("symlink");
+        let symlink = trusted(&link.display().to_string());
+        fs::remove_file(&link).expect("remove symlink");
+
+        fs::write(&link, "#!/bin/sh\n").expect("hook");
+        fs::set_permissions(&dir, fs::Permissions::from_mode(0o777)).expect("permissions");
+        let writable_dir = trusted(&link.display().to_string());
+        fs::remove_dir_all(&dir).expect("remove directory");
+
+        assert!(symlink.is_err_and(|e| e.to_string().ends_with("is a symlink")));
+        assert!(writable_dir.is_err());
+    }
+}
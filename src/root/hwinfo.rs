@@ -1,14 +1,11 @@
-use std::{
-    fs::{self, OpenOptions},
-    io::Write as IoWrite,
-};
+use std::fs;
 
 use anyhow::{anyhow, Result};
 
-use super::SubCommand;
+use super::{persist, SubCommand};
 
 // TODO: should change this path to /usr/local/aice/conf/version?
-const DEFAULT_VERSION_PATH: &str = "/etc/version";
+pub(crate) const DEFAULT_VERSION_PATH: &str = "/etc/version";
 
 pub(crate) fn set_version(kind: SubCommand, arg: &str) -> Result<()> {
     let contents = fs::read_to_string(DEFAULT_VERSION_PATH)?;
@@ -42,11 +39,6 @@ pub(crate) fn set_version(kind: SubCommand, arg: &str) -> Result<()> {
     new_contents.push_str(&new_version);
     new_contents.push('\n');
 
-    let mut file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(DEFAULT_VERSION_PATH)?;
-
-    file.write_all(new_contents.as_bytes())?;
+    persist::write(DEFAULT_VERSION_PATH, new_contents)?;
     Ok(())
 }
@@ -1,24 +1,51 @@
 use std::{
-    collections::HashMap,
+    collections::{BTreeMap, HashMap},
     fmt,
     fs::{self, File, OpenOptions},
-    io::{Read, Write},
+    io::{self, Read, Write},
     net::IpAddr,
-    process::Command,
+    process,
+    time::UNIX_EPOCH,
 };
 
 use anyhow::{anyhow, Result};
 use chrono::{DateTime, Local};
 use ipnet::IpNet;
 use pnet::datalink::interfaces;
-use roxy::common::DEFAULT_PATH_ENV;
+use roxy::common::{command, TunnelConfig, NETPLAN_DIR};
 use serde_derive::{Deserialize, Serialize};
 use serde_with::serde_as;
+use thiserror::Error;
 
-use super::{Nic, NicOutput};
+use super::{Nic, NicOutput, ROXY_STATE_DIR};
+use crate::root::{fqdn, persist, wireguard};
 
-const NETPLAN_PATH: &str = "/etc/netplan";
+pub(crate) const NETPLAN_PATH: &str = NETPLAN_DIR;
 const DEFAULT_NETPLAN_YAML: &str = "01-netcfg.yaml";
+// DNS priorities of interfaces, which netplan cannot keep. Each line has the
+// name of an interface and its priority.
+pub(crate) const DNS_PRIORITY_CONF: &str = "/etc/default/roxy-dns-priority";
+// Human labels of interfaces. Each line has the name of an interface and its
+// label.
+pub(crate) const INTERFACE_LABEL_CONF: &str = "/etc/default/roxy-interface-labels";
+const MAX_LABEL_LEN: usize = 32;
+const INTERFACE_CACHE: &str = "cache/interfaces.json";
+// Incremented when NicOutput changes, so that an old cache is not used.
+const INTERFACE_CACHE_VERSION: u32 = 6;
+
+// An address to be set is already used by another interface or host.
+#[derive(Debug, Error)]
+#[error("address conflict: {0}")]
+pub(crate) struct AddressConflict(String);
+
+// Interface configurations parsed from the netplan yaml files, which are
+// identified by their names, sizes, and modified times.
+#[derive(Debug, Deserialize, Serialize)]
+struct InterfaceCache {
+    version: u32,
+    files: Vec<(String, u64, u64, u32)>,
+    interfaces: Vec<(String, NicOutput)>,
+}
 
 #[derive(Debug, Deserialize, Serialize)]
 struct Address {
@@ -36,7 +63,66 @@ struct Bridge {
     nameservers: Address,
 }
 
-// only support ethernets, bridges. No wifis support.
+// A tunnel in netplan. The settings roxy does not manage, such as `ttl`, and
+// the tunnels of other modes, such as `sit`, are kept as they are.
+#[derive(Debug, Deserialize, Serialize)]
+struct Tunnel {
+    mode: String,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    local: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    remote: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    id: Option<u32>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    port: Option<u16>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    key: Option<serde_yaml::Value>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    addresses: Option<Vec<String>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    mtu: Option<u32>,
+    #[serde(flatten)]
+    other: BTreeMap<String, serde_yaml::Value>,
+}
+
+impl Tunnel {
+    fn new(config: &TunnelConfig) -> Result<Self> {
+        let mode = serde_yaml::to_value(config.mode)?;
+        Ok(Tunnel {
+            mode: mode.as_str().unwrap_or_default().to_string(),
+            local: Some(config.local.clone()),
+            remote: config.remote.clone(),
+            id: config.vni,
+            port: config.port,
+            key: config.key.map(serde_yaml::Value::from),
+            addresses: config.addresses.clone(),
+            mtu: config.mtu,
+            other: BTreeMap::new(),
+        })
+    }
+
+    // None for a tunnel of a mode roxy does not manage.
+    fn config(&self) -> Option<TunnelConfig> {
+        let mode = serde_yaml::from_value(serde_yaml::Value::from(self.mode.as_str())).ok()?;
+        Some(TunnelConfig {
+            mode,
+            local: self.local.clone()?,
+            remote: self.remote.clone(),
+            vni: self.id,
+            port: self.port,
+            key: self
+                .key
+                .as_ref()
+                .and_then(serde_yaml::Value::as_u64)
+                .and_then(|key| u32::try_from(key).ok()),
+            addresses: self.addresses.clone(),
+            mtu: self.mtu,
+        })
+    }
+}
+
+// only support ethernets, bridges, and tunnels. No wifis support.
 #[serde_as]
 #[derive(Debug, Deserialize, Serialize)]
 struct Network {
@@ -48,6 +134,8 @@ struct Network {
     ethernets: Vec<(String, Nic)>,
     #[serde(skip_serializing_if = "Option::is_none")]
     bridges: Option<HashMap<String, Bridge>>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    tunnels: Option<BTreeMap<String, Tunnel>>,
 }
 
 #[derive(Debug, Deserialize, Serialize)]
@@ -94,6 +182,13 @@ impl NetplanYaml {
         }
         self.network.ethernets.sort_by(|a, b| a.0.cmp(&b.0));
 
+        if let Some(new_tunnels) = newyml.network.tunnels {
+            self.network
+                .tunnels
+                .get_or_insert_with(BTreeMap::new)
+                .extend(new_tunnels);
+        }
+
         if let Some(new_bridges) = newyml.network.bridges {
             if let Some(self_bridges) = &mut self.network.bridges {
                 for (ifname, bridgecfg) in new_bridges {
@@ -117,13 +212,34 @@ impl NetplanYaml {
         }
     }
 
+    // apply() should be run to apply this change.
+    fn set_tunnel(&mut self, name: &str, config: &TunnelConfig) -> Result<()> {
+        let is_bridge = self
+            .network
+            .bridges
+            .as_ref()
+            .is_some_and(|bridges| bridges.contains_key(name));
+        if is_bridge || self.network.ethernets.iter().any(|(x, _)| x == name) {
+            return Err(anyhow!("{} is not a tunnel", name));
+        }
+        let tunnels = self.network.tunnels.get_or_insert_with(BTreeMap::new);
+        let mut tunnel = Tunnel::new(config)?;
+        if let Some(old) = tunnels.remove(name) {
+            tunnel.other = old.other;
+        }
+        tunnels.insert(name.to_string(), tunnel);
+        Ok(())
+    }
+
     // apply() should be run to apply this change.
     fn init_interface(&mut self, ifname: &str) {
-        let new_if = Nic::new(None, None, None, None, None);
+        let new_if = Nic::default();
         Self::set_interface(self, ifname, new_if);
     }
 
-    // Removes interface address, gateway4, nameservers. apply() should be run to apply this change.
+    // Removes interface address, gateway4, nameservers, search domains, optional, activation-mode,
+    // routes, and routing rules.
+    // apply() should be run to apply this change.
     // Use set() command instead of delete() if possible
     fn delete(&mut self, ifname: &str, nic_output: &NicOutput) -> Result<()> {
         let (_, ifs) = self
@@ -143,6 +259,31 @@ impl NetplanYaml {
         if nic_output.gateway4.is_some() && ifs.gateway4 == nic_output.gateway4 {
             ifs.gateway4 = None;
         }
+        if let (Some(gateway), Some(routes)) = (&nic_output.gateway4, &mut ifs.routes) {
+            routes.retain(|route| {
+                !route.is_default() || route.table.is_some() || route.via.as_ref() != Some(gateway)
+            });
+        }
+        if let (Some(removed), Some(routes)) = (&nic_output.routes, &mut ifs.routes) {
+            routes.retain(|route| !removed.iter().any(|x| x.to() == *route));
+        }
+        if let (Some(removed), Some(rules)) = (&nic_output.routing_policy, &mut ifs.routing_policy)
+        {
+            rules.retain(|rule| !removed.iter().any(|x| x.to() == *rule));
+        }
+        if ifs.routes.as_ref().is_some_and(Vec::is_empty) {
+            ifs.routes = None;
+        }
+        if ifs.routing_policy.as_ref().is_some_and(Vec::is_empty) {
+            ifs.routing_policy = None;
+        }
+        if nic_output.optional.is_some() && ifs.optional == nic_output.optional {
+            ifs.optional = None;
+        }
+        if nic_output.activation_mode.is_some() && ifs.activation_mode == nic_output.activation_mode
+        {
+            ifs.activation_mode = None;
+        }
 
         if let Some(addrs) = &nic_output.nameservers {
             for addr in addrs {
@@ -156,6 +297,12 @@ impl NetplanYaml {
             }
         }
 
+        if let Some(domains) = &nic_output.search_domains {
+            if let Some(search) = ifs.nameservers.as_mut().and_then(|nm| nm.get_mut("search")) {
+                search.retain(|x| !domains.contains(x));
+            }
+        }
+
         Ok(())
     }
 
@@ -193,13 +340,14 @@ impl NetplanYaml {
             .open(&from)?;
         write!(tmp, "{self}")?;
 
-        fs::copy(&from, &to)?;
+        invalidate_cache();
+        persist::write(&to, fs::read(&from)?)?;
         fs::remove_file(&from)?;
 
         for (_, _, file) in &files {
             let path = format!("{dir}/{file}");
             if path != to {
-                fs::remove_file(&path)?;
+                persist::remove(&path)?;
             }
         }
 
@@ -265,7 +413,10 @@ pub(crate) fn init(ifname: &str) -> Result<()> {
     for iface in all_interfaces {
         if iface.name == *ifname {
             netplan.init_interface(ifname);
+            let mut priorities = load_dns_priorities()?;
+            route_dns(&mut netplan, &mut priorities)?;
             netplan.apply(NETPLAN_PATH)?;
+            save_dns_priorities(&priorities)?;
 
             // init running interface setting with ifconfig command
             // because 'netplan apply' command would not init the running settings.
@@ -294,10 +445,20 @@ pub(crate) fn init(ifname: &str) -> Result<()> {
 // );
 // ifconfig::set("eno3", &nic_output)?;
 //
+// To keep booting from waiting for eno4, a capture interface which may be
+// unplugged:
+// let nic_output = NicOutput::new(None, None, None, None).optional(true);
+// ifconfig::set("eno4", &nic_output)?;
+//
 // Possible errors:
 // * fail to get or save, apply netplan yaml conf
 // * dhcp4 and static ip address or nameserver address is set in same interface
-// * try to set new gateway address when other interface already have the gateway
+// * try to set new gateway address or default route when other interface
+//   already have the gateway and either has no metric, or both have the same
+//   metric
+// * dns priority is set without nameservers, or is the same as another interface
+// * an address is already used by another interface or host, or its subnet
+//   overlaps that of another interface
 pub(crate) fn set(ifname: &str, nic_output: &NicOutput) -> Result<()> {
     let mut netplan = load_netplan_yaml(NETPLAN_PATH)?;
 
@@ -313,15 +474,11 @@ pub(crate) fn set(ifname: &str, nic_output: &NicOutput) -> Result<()> {
         if let Err(e) = validate_ipaddress(ipaddr) {
             return Err(anyhow!("invalid gateway4 address: {}. {:?}", ipaddr, e));
         }
-
-        for (nic_name, nic) in &netplan.network.ethernets {
-            if nic_name != ifname && nic.gateway4.is_some() {
-                return Err(anyhow!("only one interface can have gateway."));
-            }
-        }
+    } else if nic_output.gateway_metric.is_some() {
+        return Err(anyhow!("gateway metric cannot be set without gateway4"));
     }
 
-    while let Some(ip) = &nic_output.nameservers {
+    if let Some(ip) = &nic_output.nameservers {
         for ipaddr in ip {
             if let Err(e) = validate_ipaddress(ipaddr) {
                 return Err(anyhow!("invalid nameserver address: {}. {:?}", ipaddr, e));
@@ -329,6 +486,17 @@ pub(crate) fn set(ifname: &str, nic_output: &NicOutput) -> Result<()> {
         }
     }
 
+    validate_routes(nic_output)?;
+    check_default_routes(ifname, nic_output, &netplan)?;
+
+    if let Some(domains) = &nic_output.search_domains {
+        for domain in domains {
+            if let Err(e) = fqdn::validate(domain) {
+                return Err(anyhow!("invalid search domain: {}. {:?}", domain, e));
+            }
+        }
+    }
+
     if nic_output.dhcp4 == Some(true)
         && (nic_output.addresses.is_some() || nic_output.nameservers.is_some())
     {
@@ -337,11 +505,284 @@ pub(crate) fn set(ifname: &str, nic_output: &NicOutput) -> Result<()> {
         ));
     }
 
+    if let Some(addrs) = &nic_output.addresses {
+        check_configured_conflicts(ifname, addrs, &configured_addresses(&netplan)?)?;
+        check_address_conflicts(ifname, addrs)?;
+    }
+
+    let mut labels = load_labels()?;
+    if let Some(label) = &nic_output.label {
+        validate_label(ifname, label, &labels)?;
+        labels.retain(|(name, _)| name != ifname);
+        labels.push((ifname.to_string(), label.clone()));
+    }
+
+    let mut priorities = load_dns_priorities()?;
+    priorities.retain(|(name, _)| name != ifname);
+    if let Some(priority) = nic_output.dns_priority {
+        if nic_output.nameservers.as_ref().is_none_or(Vec::is_empty) {
+            return Err(anyhow!("dns priority cannot be set without nameservers"));
+        }
+        priorities.push((ifname.to_string(), priority));
+    }
+
     netplan.set_interface(ifname, nic_output.to());
+    route_dns(&mut netplan, &mut priorities)?;
     netplan.apply(NETPLAN_PATH)?;
+    save_dns_priorities(&priorities)?;
+    if nic_output.label.is_some() {
+        save_labels(&labels)?;
+    }
+    Ok(())
+}
+
+// Checks that the default routes of the interface, through `gateway4` or
+// static routes in the main table, are not ambiguous with each other or with
+// those of the other interfaces. Default routes are ambiguous unless each has
+// a different metric.
+fn check_default_routes(ifname: &str, nic_output: &NicOutput, netplan: &NetplanYaml) -> Result<()> {
+    let mut metrics = nic_output
+        .gateway4
+        .iter()
+        .map(|_| nic_output.gateway_metric)
+        .collect::<Vec<_>>();
+    metrics.extend(
+        nic_output
+            .routes
+            .iter()
+            .flatten()
+            .filter(|route| route.to().is_default() && route.table.is_none() && route.via.is_some())
+            .map(|route| route.metric),
+    );
+
+    let mut checked = Vec::new();
+    for (nic_name, nic) in &netplan.network.ethernets {
+        if nic_name != ifname {
+            checked.extend(
+                nic.default_gateways()
+                    .into_iter()
+                    .map(|(_, metric)| (nic_name.as_str(), metric)),
+            );
+        }
+    }
+    for metric in metrics {
+        for (nic_name, other) in &checked {
+            match (metric, other) {
+                (Some(metric), Some(other)) if metric == *other => {
+                    return Err(anyhow!(
+                        "{} has the same gateway metric {}",
+                        nic_name,
+                        metric
+                    ));
+                }
+                (Some(_), Some(_)) => {}
+                _ if *nic_name == ifname => {
+                    return Err(anyhow!(
+                        "default routes of {} should have different metrics.",
+                        ifname
+                    ));
+                }
+                _ => {
+                    return Err(anyhow!(
+                        "only one interface can have gateway without a metric."
+                    ));
+                }
+            }
+        }
+        checked.push((ifname, metric));
+    }
+    Ok(())
+}
+
+// Checks the static routes and routing rules of the interface.
+fn validate_routes(nic_output: &NicOutput) -> Result<()> {
+    for route in nic_output.routes.iter().flatten() {
+        if route.to != "default" {
+            if let Err(e) = validate_ipnetworks(&route.to) {
+                return Err(anyhow!("invalid route destination: {}. {:?}", route.to, e));
+            }
+        }
+        if let Some(via) = &route.via {
+            if let Err(e) = validate_ipaddress(via) {
+                return Err(anyhow!("invalid route gateway: {}. {:?}", via, e));
+            }
+        }
+    }
+
+    for rule in nic_output.routing_policy.iter().flatten() {
+        if rule.from.is_none() && rule.to.is_none() {
+            return Err(anyhow!("routing rule should have from or to"));
+        }
+        for network in rule.from.iter().chain(&rule.to) {
+            if let Err(e) = validate_ipnetworks(network) {
+                return Err(anyhow!(
+                    "invalid routing rule network: {}. {:?}",
+                    network,
+                    e
+                ));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Gets the addresses configured for the ethernets, bridges, and tunnels in
+// netplan, and for the WireGuard interfaces, including the interfaces which
+// are not up.
+//
+// # Errors
+//
+// * fail to read the WireGuard configurations
+fn configured_addresses(netplan: &NetplanYaml) -> Result<Vec<(String, Vec<String>)>> {
+    let network = &netplan.network;
+    let mut configured = network
+        .ethernets
+        .iter()
+        .filter_map(|(name, nic)| Some((name.clone(), nic.addresses.clone()?)))
+        .chain(
+            network
+                .bridges
+                .iter()
+                .flatten()
+                .map(|(name, bridge)| (name.clone(), bridge.addresses.clone())),
+        )
+        .chain(
+            network
+                .tunnels
+                .iter()
+                .flatten()
+                .filter_map(|(name, tunnel)| Some((name.clone(), tunnel.addresses.clone()?))),
+        )
+        .collect::<Vec<_>>();
+    configured.extend(wireguard::addresses()?);
+    Ok(configured)
+}
+
+// Checks the addresses against those configured for the other interfaces.
+// Overlapping subnets on two interfaces make the routes to them ambiguous.
+//
+// # Errors
+//
+// * an address is configured for another interface
+// * the subnet of an address overlaps that of another interface
+fn check_configured_conflicts(
+    ifname: &str,
+    addrs: &[String],
+    configured: &[(String, Vec<String>)],
+) -> Result<()> {
+    let configured = configured.iter().filter(|(name, _)| name != ifname);
+    let parse = |addrs: &[String]| -> Vec<IpNet> {
+        addrs.iter().filter_map(|addr| addr.parse().ok()).collect()
+    };
+    let nets = parse(addrs);
+    for (name, others) in configured {
+        for other in parse(others) {
+            for net in &nets {
+                if net.addr() == other.addr() {
+                    return Err(AddressConflict(format!(
+                        "{} is configured for {name}",
+                        net.addr()
+                    ))
+                    .into());
+                }
+                if net.contains(&other.network()) || other.contains(&net.network()) {
+                    return Err(AddressConflict(format!(
+                        "{} overlaps {} of {name}",
+                        net.trunc(),
+                        other.trunc()
+                    ))
+                    .into());
+                }
+            }
+        }
+    }
+    Ok(())
+}
+
+// Checks the addresses to be held by the interface, e.g. the virtual
+// addresses of VRRP, against those configured for the other interfaces.
+//
+// # Errors
+//
+// * fail to load netplan yaml conf or the WireGuard configurations
+// * an address conflicts with the addresses of another interface
+pub(crate) fn check_conflicts_with_configured(ifname: &str, addrs: &[String]) -> Result<()> {
+    let netplan = load_netplan_yaml(NETPLAN_PATH)?;
+    check_configured_conflicts(ifname, addrs, &configured_addresses(&netplan)?)
+}
+
+// Checks whether the addresses are already used by another local interface
+// or, for IPv4, by another host on the segment of the interface with arping
+// in duplicate address detection mode. Addresses the interface already has
+// are not checked. The check on the segment is skipped if arping is missing
+// or cannot probe, e.g. because the interface is down.
+//
+// # Errors
+//
+// * an address is assigned to another interface
+// * another host answers ARP for an address
+fn check_address_conflicts(ifname: &str, addrs: &[String]) -> Result<()> {
+    let nics = interfaces();
+    for addr in addrs {
+        let Ok(ip) = addr.parse::<IpNet>().map(|net| net.addr()) else {
+            continue;
+        };
+        if nics
+            .iter()
+            .any(|nic| nic.name == ifname && nic.ips.iter().any(|x| x.ip() == ip))
+        {
+            continue;
+        }
+        if let Some(other) = nics
+            .iter()
+            .find(|nic| nic.name != ifname && nic.ips.iter().any(|x| x.ip() == ip))
+        {
+            return Err(AddressConflict(format!("{ip} is assigned to {}", other.name)).into());
+        }
+
+        if ip.is_ipv4() {
+            let ip = ip.to_string();
+            match roxy::common::run_command(
+                command("arping").args(["-D", "-q", "-c", "2", "-w", "3", "-I", ifname, &ip]),
+            ) {
+                // arping exits with 1 if a reply is received.
+                Ok(output) if output.status.code() == Some(1) => {
+                    return Err(AddressConflict(format!(
+                        "{ip} is used by another host on {ifname}"
+                    ))
+                    .into());
+                }
+                Ok(_) => {}
+                Err(e) => log::debug!("duplicate address detection skipped. {e}"),
+            }
+        }
+    }
     Ok(())
 }
 
+// Checks whether the gateway answers ARP on the interface, which is done
+// after netplan apply to catch a mistyped gateway.
+//
+// # Return
+//
+// * None if arping cannot probe, e.g. because it is not installed
+pub(crate) fn gateway_reachable(ifname: &str, gateway: &str) -> Option<bool> {
+    match roxy::common::run_command(
+        command("arping").args(["-q", "-c", "3", "-w", "5", "-I", ifname, gateway]),
+    ) {
+        // arping exits with 1 if no reply is received, and 2 on errors.
+        Ok(output) => match output.status.code() {
+            Some(0) => Some(true),
+            Some(1) => Some(false),
+            _ => None,
+        },
+        Err(e) => {
+            log::debug!("gateway check skipped. {e}");
+            None
+        }
+    }
+}
+
 // Gets interface configurations
 //
 // To get all interfaces:
@@ -352,21 +793,226 @@ pub(crate) fn set(ifname: &str, nic_output: &NicOutput) -> Result<()> {
 //
 // Error: fail to load /etc/netplan yaml files
 pub(crate) fn get(ifname: Option<&String>) -> Result<Option<Vec<(String, NicOutput)>>> {
-    let netplan = load_netplan_yaml(NETPLAN_PATH)?;
+    let mut interfaces = load_interfaces(NETPLAN_PATH)?;
+    let priorities = load_dns_priorities()?;
+    let labels = load_labels()?;
+    for (name, nic) in &mut interfaces {
+        nic.dns_priority = priorities
+            .iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, priority)| *priority);
+        nic.label = labels
+            .iter()
+            .find(|(x, _)| x == name)
+            .map(|(_, label)| label.clone());
+    }
     if let Some(name) = ifname {
-        if let Some((_, nic)) = netplan.network.ethernets.iter().find(|(x, _)| *x == *name) {
-            return Ok(Some(vec![(name.to_string(), NicOutput::from(nic))]));
+        if let Some((_, nic)) = interfaces.into_iter().find(|(x, _)| *x == *name) {
+            return Ok(Some(vec![(name.clone(), nic)]));
         }
     } else {
-        let mut nic_output = Vec::new();
-        for (name, nic) in &netplan.network.ethernets {
-            nic_output.push((name.to_string(), NicOutput::from(nic)));
-        }
-        return Ok(Some(nic_output));
+        return Ok(Some(interfaces));
     }
     Ok(None)
 }
 
+// Loads interface configurations from the cache if the netplan yaml files
+// have not been changed since it was saved. Otherwise, parses the yaml files
+// and saves the result. A failure to save the cache is ignored.
+fn load_interfaces(dir: &str) -> Result<Vec<(String, NicOutput)>> {
+    let mut files = Vec::new();
+    for entry in fs::read_dir(dir)?.flatten() {
+        let metadata = entry.metadata()?;
+        if !metadata.is_file() {
+            continue;
+        }
+        let modified = metadata.modified()?.duration_since(UNIX_EPOCH)?;
+        files.push((
+            entry.file_name().to_string_lossy().into_owned(),
+            metadata.len(),
+            modified.as_secs(),
+            modified.subsec_nanos(),
+        ));
+    }
+    files.sort();
+
+    let path = format!("{ROXY_STATE_DIR}/{INTERFACE_CACHE}");
+    if let Some(cache) = fs::read(&path)
+        .ok()
+        .and_then(|buf| serde_json::from_slice::<InterfaceCache>(&buf).ok())
+    {
+        if cache.version == INTERFACE_CACHE_VERSION && cache.files == files {
+            return Ok(cache.interfaces);
+        }
+    }
+
+    let netplan = load_netplan_yaml(dir)?;
+    let cache = InterfaceCache {
+        version: INTERFACE_CACHE_VERSION,
+        files,
+        interfaces: netplan
+            .network
+            .ethernets
+            .iter()
+            .map(|(name, nic)| (name.clone(), NicOutput::from(nic)))
+            .collect(),
+    };
+    // Renaming a temporary file keeps the cache consistent when roxy runs
+    // concurrently.
+    let tmp = format!("{path}.{}", process::id());
+    let saved = fs::create_dir_all(format!("{ROXY_STATE_DIR}/cache"))
+        .and_then(|()| fs::write(&tmp, serde_json::to_vec(&cache)?))
+        .and_then(|()| fs::rename(&tmp, &path));
+    if saved.is_err() {
+        let _ = fs::remove_file(&tmp);
+    }
+    Ok(cache.interfaces)
+}
+
+// Reads the DNS priorities of interfaces. No interface has a priority if the
+// file does not exist.
+fn load_dns_priorities() -> Result<Vec<(String, u32)>> {
+    match fs::read_to_string(DNS_PRIORITY_CONF) {
+        Ok(contents) => Ok(parse_dns_priorities(&contents)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn parse_dns_priorities(contents: &str) -> Vec<(String, u32)> {
+    contents
+        .lines()
+        .filter(|line| !line.trim_start().starts_with('#'))
+        .filter_map(|line| {
+            let mut fields = line.split_whitespace();
+            let name = fields.next()?;
+            let priority = fields.next()?.parse().ok()?;
+            Some((name.to_string(), priority))
+        })
+        .collect()
+}
+
+fn save_dns_priorities(priorities: &[(String, u32)]) -> Result<()> {
+    if priorities.is_empty() {
+        return persist::remove(DNS_PRIORITY_CONF);
+    }
+    let lines = priorities
+        .iter()
+        .map(|(name, priority)| format!("{name} {priority}"))
+        .collect::<Vec<_>>();
+    persist::write(
+        DNS_PRIORITY_CONF,
+        format!(
+            "# DNS priority of each interface, written by roxy.\n{}\n",
+            lines.join("\n")
+        ),
+    )
+}
+
+// Returns the name of the interface labeled `name`, or `name` itself if it
+// is the name of an interface or no interface has the label.
+pub(crate) fn resolve(name: &str) -> String {
+    if interfaces().iter().any(|nic| nic.name == name) {
+        return name.to_string();
+    }
+    load_labels()
+        .ok()
+        .and_then(|labels| labels.into_iter().find(|(_, label)| label == name))
+        .map_or_else(|| name.to_string(), |(ifname, _)| ifname)
+}
+
+// Checks that the label is well-formed, and is not the name of another
+// interface or the label of another interface.
+fn validate_label(ifname: &str, label: &str, labels: &[(String, String)]) -> Result<()> {
+    if label.is_empty()
+        || label.len() > MAX_LABEL_LEN
+        || !label
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_' | '.'))
+    {
+        return Err(anyhow!("invalid interface label: {:?}", label));
+    }
+    if label != ifname && interfaces().iter().any(|nic| nic.name == label) {
+        return Err(anyhow!("{} is the name of another interface", label));
+    }
+    if let Some((other, _)) = labels.iter().find(|(name, x)| name != ifname && x == label) {
+        return Err(anyhow!("{} is the label of {}", label, other));
+    }
+    Ok(())
+}
+
+// Reads the labels of interfaces. No interface has a label if the file does
+// not exist.
+pub(crate) fn load_labels() -> Result<Vec<(String, String)>> {
+    match fs::read_to_string(INTERFACE_LABEL_CONF) {
+        Ok(contents) => Ok(contents
+            .lines()
+            .filter(|line| !line.trim_start().starts_with('#'))
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                Some((fields.next()?.to_string(), fields.next()?.to_string()))
+            })
+            .collect()),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_labels(labels: &[(String, String)]) -> Result<()> {
+    if labels.is_empty() {
+        return persist::remove(INTERFACE_LABEL_CONF);
+    }
+    let lines = labels
+        .iter()
+        .map(|(name, label)| format!("{name} {label}"))
+        .collect::<Vec<_>>();
+    persist::write(
+        INTERFACE_LABEL_CONF,
+        format!(
+            "# Label of each interface, written by roxy.\n{}\n",
+            lines.join("\n")
+        ),
+    )
+}
+
+// Makes the nameservers of the interface with the lowest DNS priority the
+// default route of DNS queries, so that the other nameservers are used only
+// for their search domains. The priorities of interfaces without nameservers
+// are dropped.
+//
+// # Errors
+//
+// * two interfaces with nameservers have the same priority
+fn route_dns(netplan: &mut NetplanYaml, priorities: &mut Vec<(String, u32)>) -> Result<()> {
+    let ethernets = &mut netplan.network.ethernets;
+    priorities.retain(|(name, _)| {
+        ethernets
+            .iter()
+            .any(|(x, nic)| x == name && nic.has_nameservers())
+    });
+    priorities.sort_by_key(|(_, priority)| *priority);
+    if let Some(pair) = priorities.windows(2).find(|pair| pair[0].1 == pair[1].1) {
+        return Err(anyhow!(
+            "{} and {} have the same dns priority {}",
+            pair[0].0,
+            pair[1].0,
+            pair[0].1
+        ));
+    }
+
+    let first = priorities.first().map(|(name, _)| name);
+    for (name, nic) in ethernets.iter_mut() {
+        nic.set_default_dns_route(Some(&*name) == first);
+    }
+    Ok(())
+}
+
+// Removes the cache before changing the netplan yaml files, in case the
+// modified time is not updated with enough precision.
+pub(crate) fn invalidate_cache() {
+    let _ = fs::remove_file(format!("{ROXY_STATE_DIR}/{INTERFACE_CACHE}"));
+}
+
 // Removes interface or name server or gateway address from the specified interface.
 //
 // To delete interface address "192.168.3.7/24", nameserver "164.124.101.2":
@@ -385,7 +1031,20 @@ pub(crate) fn get(ifname: Option<&String>) -> Result<Option<Vec<(String, NicOutp
 pub(crate) fn delete(ifname: &str, nic_output: &NicOutput) -> Result<()> {
     let mut netplan = load_netplan_yaml(NETPLAN_PATH)?;
     netplan.delete(ifname, nic_output)?;
+    let mut priorities = load_dns_priorities()?;
+    if nic_output.dns_priority.is_some() {
+        priorities.retain(|(name, priority)| {
+            name != ifname || Some(*priority) != nic_output.dns_priority
+        });
+    }
+    route_dns(&mut netplan, &mut priorities)?;
     netplan.apply(NETPLAN_PATH)?;
+    save_dns_priorities(&priorities)?;
+    if nic_output.label.is_some() {
+        let mut labels = load_labels()?;
+        labels.retain(|(name, _)| name != ifname);
+        save_labels(&labels)?;
+    }
 
     if let Some(addrs) = &nic_output.addresses {
         for addr in addrs {
@@ -397,6 +1056,71 @@ pub(crate) fn delete(ifname: &str, nic_output: &NicOutput) -> Result<()> {
     Ok(())
 }
 
+// Gets the GRE and VXLAN tunnels in netplan. The tunnels of other modes are
+// left out.
+//
+// Error: fail to load /etc/netplan yaml files
+pub(crate) fn tunnels() -> Result<Vec<(String, TunnelConfig)>> {
+    let netplan = load_netplan_yaml(NETPLAN_PATH)?;
+    Ok(netplan
+        .network
+        .tunnels
+        .iter()
+        .flatten()
+        .filter_map(|(name, tunnel)| Some((name.clone(), tunnel.config()?)))
+        .collect())
+}
+
+// Adds or replaces a tunnel in netplan, and applies it. The settings of the
+// tunnel roxy does not manage are kept. Its addresses are checked for
+// conflicts as those of an ethernet are.
+//
+// Possible errors:
+// * fail to load, save, or apply netplan yaml conf
+// * an ethernet or a bridge has the name
+// * an address conflicts with another interface or host
+pub(crate) fn set_tunnel(name: &str, config: &TunnelConfig) -> Result<()> {
+    let mut netplan = load_netplan_yaml(NETPLAN_PATH)?;
+    if let Some(addrs) = &config.addresses {
+        check_configured_conflicts(name, addrs, &configured_addresses(&netplan)?)?;
+        check_address_conflicts(name, addrs)?;
+    }
+    netplan.set_tunnel(name, config)?;
+    netplan.apply(NETPLAN_PATH)
+}
+
+// Removes a tunnel from netplan, and applies it. netplan leaves the running
+// tunnel interface, which is deleted with ip.
+//
+// Possible errors:
+// * fail to load, save, or apply netplan yaml conf
+// * the tunnel does not exist
+pub(crate) fn delete_tunnel(name: &str) -> Result<()> {
+    let mut netplan = load_netplan_yaml(NETPLAN_PATH)?;
+    if netplan
+        .network
+        .tunnels
+        .as_mut()
+        .and_then(|tunnels| tunnels.remove(name))
+        .is_none()
+    {
+        return Err(anyhow!("tunnel {} not found", name));
+    }
+    if netplan
+        .network
+        .tunnels
+        .as_ref()
+        .is_some_and(BTreeMap::is_empty)
+    {
+        netplan.network.tunnels = None;
+    }
+    netplan.apply(NETPLAN_PATH)?;
+    if interfaces().iter().any(|nic| nic.name == name) {
+        run_command("ip", &["link", "delete", name])?;
+    }
+    Ok(())
+}
+
 // Gets interface names starting with the specified prefix.
 // To get interface names starting with "en":
 // let names = ifconfig::get_interface_names(&Some("en".to_string()));
@@ -414,7 +1138,7 @@ pub(crate) fn get_interface_names(arg: Option<&String>) -> Vec<String> {
 // * dir is not exist or fail to read dir
 // * fail to get metadata from file
 // * fail to get modified time from file
-fn list_files(
+pub(crate) fn list_files(
     dir: &str,
     except: Option<&[&str]>,
     subdir: bool,
@@ -458,10 +1182,7 @@ fn list_files(
     Ok(files)
 }
 
-fn run_command(cmd: &str, args: &[&str]) -> Result<bool> {
-    let status = Command::new(cmd)
-        .env("PATH", DEFAULT_PATH_ENV)
-        .args(args)
-        .status()?;
-    Ok(status.success())
+pub(crate) fn run_command(cmd: &str, args: &[&str]) -> Result<bool> {
+    let output = roxy::common::run_command(command(cmd).args(args))?;
+    Ok(output.status.success())
 }
@@ -0,0 +1,26 @@
+use std::fs;
+
+use anyhow::Result;
+use roxy::common::NodeInfo;
+
+use super::fqdn;
+
+const OSRELEASE: &str = "/proc/sys/kernel/osrelease";
+
+// Gets the names, versions, and uptime of the node.
+//
+// # Errors
+//
+// * fail to get the hostname, uptime, or kernel release
+pub(crate) fn get() -> Result<NodeInfo> {
+    let (os_version, product_version) = roxy::version();
+    Ok(NodeInfo {
+        hostname: fqdn::get()?,
+        os_version,
+        product_version,
+        uptime: roxy::uptime()?.as_secs(),
+        kernel: fs::read_to_string(OSRELEASE)?.trim().to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        roxy_version: env!("CARGO_PKG_VERSION").to_string(),
+    })
+}
@@ -0,0 +1,152 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fs, io,
+};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{GroupSettings, InterfaceResult};
+
+use super::{capture, ifconfig};
+
+// Named groups of interfaces, e.g.
+//
+// ```yaml
+// capture: ["cap*", Capture-A]
+// uplinks: [eno1]
+// ```
+//
+// Each member is the name or label of an interface, or a glob pattern of
+// them with '*' and '?'.
+pub(crate) const INTERFACE_GROUPS_CONF: &str = "/etc/roxy/interface-groups.yaml";
+
+// Returns the names of the interfaces in the group named `target` in
+// `INTERFACE_GROUPS_CONF`, or matching `target` if it is a glob pattern.
+//
+// # Errors
+//
+// * `target` is neither a group nor a pattern, or no interface matches it
+// * fail to read or parse `INTERFACE_GROUPS_CONF`
+pub(crate) fn members(target: &str) -> Result<Vec<String>> {
+    let patterns = match read_conf()?.remove(target) {
+        Some(members) => members,
+        None if is_pattern(target) => vec![target.to_string()],
+        None => return Err(anyhow!("unknown interface group: {}", target)),
+    };
+
+    let mut names = ifconfig::get_interface_names(None)
+        .into_iter()
+        .collect::<BTreeSet<_>>();
+    names.extend(
+        ifconfig::get(None)?
+            .unwrap_or_default()
+            .into_iter()
+            .map(|(name, _)| name),
+    );
+    let labels = ifconfig::load_labels()?;
+
+    let mut members = BTreeSet::new();
+    for pattern in &patterns {
+        if !is_pattern(pattern) {
+            // An interface which does not exist fails when it is set.
+            members.insert(ifconfig::resolve(pattern));
+            continue;
+        }
+        members.extend(
+            names
+                .iter()
+                .filter(|name| matches(pattern.as_bytes(), name.as_bytes()))
+                .cloned(),
+        );
+        members.extend(
+            labels
+                .iter()
+                .filter(|(_, label)| matches(pattern.as_bytes(), label.as_bytes()))
+                .map(|(name, _)| name.clone()),
+        );
+    }
+    if members.is_empty() {
+        return Err(anyhow!("no interface matches {}", target));
+    }
+    Ok(members.into_iter().collect())
+}
+
+// Applies the settings to each interface of the group or pattern, and
+// continues with the rest if it fails for an interface.
+//
+// # Return
+//
+// * the result of each interface
+//
+// # Errors
+//
+// * no settings, or a label in the settings
+// * fail to find the interfaces of the group or pattern
+pub(crate) fn set(target: &str, settings: &GroupSettings) -> Result<Vec<InterfaceResult>> {
+    if settings.nic.is_none() && settings.capture.is_none() {
+        return Err(anyhow!("no settings to apply"));
+    }
+    if settings.nic.as_ref().is_some_and(|nic| nic.label.is_some()) {
+        return Err(anyhow!("a label cannot be set to a group"));
+    }
+
+    Ok(members(target)?
+        .into_iter()
+        .map(|ifname| {
+            let error = apply(&ifname, settings).err().map(|e| e.to_string());
+            InterfaceResult { ifname, error }
+        })
+        .collect())
+}
+
+fn apply(ifname: &str, settings: &GroupSettings) -> Result<()> {
+    if let Some(nic) = &settings.nic {
+        ifconfig::set(ifname, nic)?;
+    }
+    if let Some(capture) = settings.capture {
+        capture::set(ifname, capture)?;
+    }
+    Ok(())
+}
+
+fn read_conf() -> Result<BTreeMap<String, Vec<String>>> {
+    match fs::read_to_string(INTERFACE_GROUPS_CONF) {
+        Ok(contents) if contents.trim().is_empty() => Ok(BTreeMap::new()),
+        Ok(contents) => serde_yaml::from_str(&contents)
+            .map_err(|e| anyhow!("fail to parse {}. {}", INTERFACE_GROUPS_CONF, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn is_pattern(s: &str) -> bool {
+    s.contains(['*', '?'])
+}
+
+// Matches a glob pattern, where '*' matches any string and '?' any
+// character.
+fn matches(pattern: &[u8], name: &[u8]) -> bool {
+    match pattern.split_first() {
+        None => name.is_empty(),
+        Some((b'*', rest)) => (0..=name.len()).any(|i| matches(rest, &name[i..])),
+        Some((b'?', rest)) => !name.is_empty() && matches(rest, &name[1..]),
+        Some((c, rest)) => name.first() == Some(c) && matches(rest, &name[1..]),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn glob_patterns() {
+        let matched = |pattern: &str, name: &str| matches(pattern.as_bytes(), name.as_bytes());
+        assert!(matched("cap*", "cap0"));
+        assert!(matched("cap*", "cap"));
+        assert!(matched("*", "eno1"));
+        assert!(matched("en?1", "eno1"));
+        assert!(matched("*-A", "Capture-A"));
+        assert!(!matched("cap*", "eno1"));
+        assert!(!matched("en?1", "en1"));
+        assert!(!matched("eno", "eno1"));
+    }
+}
@@ -0,0 +1,354 @@
+use std::{fs, io, path::Path};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command, IrqAffinity, IrqLayout};
+
+use super::capture::{device_unit, validate, validate_name};
+use crate::root::persist;
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const PROC_IRQ: &str = "/proc/irq";
+const PROC_INTERRUPTS: &str = "/proc/interrupts";
+const CPU_ONLINE: &str = "/sys/devices/system/cpu/online";
+const UNIT_DIR: &str = "/etc/systemd/system";
+const IRQBALANCE_CONF: &str = "/etc/default/irqbalance";
+const IRQBALANCE_ARGS: &str = "IRQBALANCE_ARGS";
+const IRQBALANCE_UNIT: &str = "irqbalance";
+const POLICY_SCRIPT_ARG: &str = "--policyscript";
+pub(crate) const AFFINITY_SCRIPT: &str = "/etc/roxy/irq-affinity.sh";
+pub(crate) const POLICY_SCRIPT: &str = "/etc/roxy/irqbalance-policy.sh";
+
+// Pins the MSI-X IRQs of a NIC to the CPUs in turn, skipping the IRQs whose
+// affinity the kernel manages, and fails if none is pinned.
+const AFFINITY: &str = r#"#!/bin/sh
+# Written by roxy. Pins the IRQs of a NIC to the CPUs in turn.
+# usage: irq-affinity.sh <interface> <cpu>...
+nic=$1
+shift
+[ $# -gt 0 ] || exit 2
+pinned=0
+for irq in $(ls "/sys/class/net/$nic/device/msi_irqs" 2>/dev/null | sort -n); do
+    cpu=$1
+    shift
+    set -- "$@" "$cpu"
+    if echo "$cpu" 2>/dev/null > "/proc/irq/$irq/smp_affinity_list"; then
+        pinned=$((pinned + 1))
+    fi
+done
+[ "$pinned" -gt 0 ]
+"#;
+
+// Run by irqbalance with the sysfs path of a device and one of its IRQs.
+// The IRQs of the NICs pinned by roxy are banned from balancing.
+const POLICY: &str = r#"#!/bin/sh
+# Written by roxy. Keeps irqbalance away from the IRQs pinned by roxy.
+for nic in "$1"/net/*; do
+    if [ -e "/etc/systemd/system/roxy-irq-affinity-${nic##*/}.service" ]; then
+        echo ban=true
+    fi
+done
+exit 0
+"#;
+
+// Pins the IRQs of the NIC to the CPUs in turn, and installs a unit which
+// pins them again at boot. irqbalance is told to leave them alone.
+//
+// # Errors
+//
+// * invalid or unknown interface
+// * no CPUs, or a CPU which is not online
+// * the NIC has no MSI-X IRQs, or none of them can be pinned
+// * fail to write the scripts, the unit, or the irqbalance configuration
+// * fail to run systemctl
+pub(crate) fn set(ifname: &str, cpus: &[u32]) -> Result<()> {
+    validate(ifname)?;
+    let online = parse_cpu_list(fs::read_to_string(CPU_ONLINE)?.trim())
+        .ok_or_else(|| anyhow!("fail to parse {}", CPU_ONLINE))?;
+    if cpus.is_empty() {
+        return Err(anyhow!("no CPUs to pin the IRQs of {} to", ifname));
+    }
+    if let Some(cpu) = cpus.iter().find(|cpu| !online.contains(cpu)) {
+        return Err(anyhow!("CPU {} is not online", cpu));
+    }
+    if irqs(ifname)?.is_empty() {
+        return Err(anyhow!("no MSI-X IRQs of {}", ifname));
+    }
+
+    persist::write_executable(AFFINITY_SCRIPT, AFFINITY)?;
+    persist::write_executable(POLICY_SCRIPT, POLICY)?;
+    let conf = match fs::read_to_string(IRQBALANCE_CONF) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    let new_conf = with_policy_script(&conf);
+    if new_conf != conf {
+        persist::write(IRQBALANCE_CONF, new_conf)?;
+    }
+    persist::write(&unit_path(ifname), unit(ifname, cpus))?;
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", &unit_name(ifname)])?;
+    // irqbalance reads the policy script, and bans the IRQs, when it starts.
+    systemctl(&["try-restart", IRQBALANCE_UNIT])?;
+
+    let output = run_command(
+        command(AFFINITY_SCRIPT)
+            .arg(ifname)
+            .args(cpus.iter().map(ToString::to_string)),
+    )?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("fail to pin the IRQs of {}", ifname))
+    }
+}
+
+// Gets the IRQs of the NIC and the CPUs handling them, and the CPUs to which
+// they are pinned at boot.
+//
+// # Errors
+//
+// * invalid or unknown interface
+// * fail to read the IRQs or their affinity
+pub(crate) fn get(ifname: &str) -> Result<IrqLayout> {
+    validate(ifname)?;
+    let device = format!("{SYS_CLASS_NET}/{ifname}/device");
+    let read = |name: &str| {
+        fs::read_to_string(format!("{device}/{name}"))
+            .ok()
+            .map(|contents| contents.trim().to_string())
+    };
+    let interrupts = fs::read_to_string(PROC_INTERRUPTS)?;
+    let irqs = irqs(ifname)?
+        .into_iter()
+        .map(|irq| {
+            let read = |name: &str| fs::read_to_string(format!("{PROC_IRQ}/{irq}/{name}"));
+            Ok(IrqAffinity {
+                irq,
+                name: irq_name(&interrupts, irq),
+                cpus: read("smp_affinity_list")?.trim().to_string(),
+                effective_cpus: read("effective_affinity_list")
+                    .ok()
+                    .map(|cpus| cpus.trim().to_string()),
+            })
+        })
+        .collect::<Result<Vec<_>>>()?;
+    let rx_queues = match fs::read_dir(format!("{SYS_CLASS_NET}/{ifname}/queues")) {
+        Ok(entries) => entries
+            .flatten()
+            .filter(|entry| entry.file_name().to_string_lossy().starts_with("rx-"))
+            .count(),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => 0,
+        Err(e) => return Err(e.into()),
+    };
+
+    Ok(IrqLayout {
+        interface: ifname.to_string(),
+        // -1 if the system has a single node.
+        numa_node: read("numa_node").and_then(|node| node.parse().ok()),
+        local_cpus: read("local_cpulist"),
+        rx_queues,
+        irqs,
+        pinned: fs::read_to_string(unit_path(ifname))
+            .ok()
+            .and_then(|contents| parse_unit(&contents)),
+    })
+}
+
+// Removes the unit pinning the IRQs of the NIC at boot, and lets irqbalance
+// balance them again.
+//
+// # Errors
+//
+// * invalid interface name
+// * fail to disable or remove the unit
+// * fail to run systemctl
+pub(crate) fn delete(ifname: &str) -> Result<()> {
+    validate_name(ifname)?;
+    if !Path::new(&unit_path(ifname)).exists() {
+        return Ok(());
+    }
+    systemctl(&["disable", &unit_name(ifname)])?;
+    persist::remove(&unit_path(ifname))?;
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["try-restart", IRQBALANCE_UNIT])
+}
+
+// The MSI-X IRQs of the NIC, in the order of their numbers. A NIC without a
+// device, such as a bridge, has none.
+fn irqs(ifname: &str) -> Result<Vec<u32>> {
+    let entries = match fs::read_dir(format!("{SYS_CLASS_NET}/{ifname}/device/msi_irqs")) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut irqs = entries
+        .flatten()
+        .filter_map(|entry| entry.file_name().to_string_lossy().parse().ok())
+        .collect::<Vec<u32>>();
+    irqs.sort_unstable();
+    Ok(irqs)
+}
+
+// Gets the name of an IRQ from /proc/interrupts, e.g. "eth0-TxRx-0" from
+// "  42:   1024   0  IR-PCI-MSI 524288-edge  eth0-TxRx-0".
+fn irq_name(interrupts: &str, irq: u32) -> Option<String> {
+    interrupts.lines().find_map(|line| {
+        let (number, rest) = line.trim_start().split_once(':')?;
+        if number.parse::<u32>().ok()? != irq {
+            return None;
+        }
+        rest.split_whitespace().last().map(ToString::to_string)
+    })
+}
+
+// Parses a CPU list such as "0-3,8".
+fn parse_cpu_list(list: &str) -> Option<Vec<u32>> {
+    let mut cpus = Vec::new();
+    for range in list.split(',').filter(|range| !range.is_empty()) {
+        match range.split_once('-') {
+            Some((start, end)) => cpus.extend(start.parse::<u32>().ok()?..=end.parse().ok()?),
+            None => cpus.push(range.parse().ok()?),
+        }
+    }
+    Some(cpus)
+}
+
+// Sets the policy script in the arguments of irqbalance, keeping the other
+// arguments and lines.
+fn with_policy_script(contents: &str) -> String {
+    let mut args = Vec::new();
+    let mut lines = Vec::new();
+    for line in contents.lines() {
+        match line
+            .trim_start()
+            .strip_prefix(&format!("{IRQBALANCE_ARGS}="))
+        {
+            Some(value) if args.is_empty() => {
+                args = value
+                    .trim_matches('"')
+                    .split_whitespace()
+                    .filter(|arg| !arg.starts_with(POLICY_SCRIPT_ARG))
+                    .map(ToString::to_string)
+                    .collect();
+                args.push(format!("{POLICY_SCRIPT_ARG}={POLICY_SCRIPT}"));
+                lines.push(format!("{IRQBALANCE_ARGS}=\"{}\"", args.join(" ")));
+            }
+            Some(_) => {}
+            None => lines.push(line.to_string()),
+        }
+    }
+    if args.is_empty() {
+        lines.push(format!(
+            "{IRQBALANCE_ARGS}=\"{POLICY_SCRIPT_ARG}={POLICY_SCRIPT}\""
+        ));
+    }
+    let mut contents = lines.join("\n");
+    contents.push('\n');
+    contents
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let output = run_command(command("systemctl").args(args))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("fail to run systemctl {}", args.join(" ")))
+    }
+}
+
+fn unit_name(ifname: &str) -> String {
+    format!("roxy-irq-affinity-{ifname}.service")
+}
+
+fn unit_path(ifname: &str) -> String {
+    format!("{UNIT_DIR}/{}", unit_name(ifname))
+}
+
+fn unit(ifname: &str, cpus: &[u32]) -> String {
+    let device = device_unit(ifname);
+    let cpus = cpus
+        .iter()
+        .map(ToString::to_string)
+        .collect::<Vec<_>>()
+        .join(" ");
+    format!(
+        "# Written by roxy.\n\
+         [Unit]\n\
+         Description=IRQ affinity of {ifname}\n\
+         BindsTo={device}\n\
+         After={device} irqbalance.service\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         RemainAfterExit=yes\n\
+         ExecStart={AFFINITY_SCRIPT} {ifname} {cpus}\n\
+         \n\
+         [Install]\n\
+         WantedBy={device}\n"
+    )
+}
+
+// Reads the CPUs from a unit written by `unit`.
+fn parse_unit(contents: &str) -> Option<Vec<u32>> {
+    contents.lines().find_map(|line| {
+        let args = line
+            .strip_prefix("ExecStart=")?
+            .strip_prefix(AFFINITY_SCRIPT)?;
+        args.split_whitespace()
+            .skip(1)
+            .map(|cpu| cpu.parse().ok())
+            .collect()
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn cpu_list() {
+        assert_eq!(parse_cpu_list("0-3,8"), Some(vec![0, 1, 2, 3, 8]));
+        assert_eq!(parse_cpu_list("0"), Some(vec![0]));
+        assert_eq!(parse_cpu_list("0-x"), None);
+    }
+
+    #[test]
+    fn interrupt_names() {
+        let interrupts = "           CPU0       CPU1
+  0:         22          0   IO-APIC   2-edge      timer
+ 42:       1024          0  IR-PCI-MSI 524288-edge      eth0-TxRx-0
+NMI:          0          0   Non-maskable interrupts
+";
+        assert_eq!(irq_name(interrupts, 42).as_deref(), Some("eth0-TxRx-0"));
+        assert_eq!(irq_name(interrupts, 0).as_deref(), Some("timer"));
+        assert_eq!(irq_name(interrupts, 43), None);
+    }
+
+    #[test]
+    fn irqbalance_args() {
+        let policy = format!("{IRQBALANCE_ARGS}=\"--policyscript={POLICY_SCRIPT}\"\n");
+        assert_eq!(with_policy_script(""), policy);
+        assert_eq!(
+            with_policy_script("#IRQBALANCE_ONESHOT=\nIRQBALANCE_ARGS=\"--hintpolicy=exact\"\n"),
+            format!(
+                "#IRQBALANCE_ONESHOT=\n{IRQBALANCE_ARGS}=\"--hintpolicy=exact \
+                 --policyscript={POLICY_SCRIPT}\"\n"
+            )
+        );
+        assert_eq!(with_policy_script(&policy), policy);
+    }
+
+    #[test]
+    fn unit_round_trip() {
+        let contents = unit("eth0", &[2, 3, 4]);
+        assert!(contents.contains(&format!("ExecStart={AFFINITY_SCRIPT} eth0 2 3 4\n")));
+        assert_eq!(parse_unit(&contents), Some(vec![2, 3, 4]));
+        assert_eq!(parse_unit("[Unit]\n"), None);
+
+        let contents = unit("cap-0", &[1]);
+        assert!(contents.contains("BindsTo=sys-subsystem-net-devices-cap\\x2d0.device\n"));
+        assert!(contents.contains(&format!("ExecStart={AFFINITY_SCRIPT} cap-0 1\n")));
+        assert_eq!(parse_unit(&contents), Some(vec![1]));
+    }
+}
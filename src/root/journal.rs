@@ -0,0 +1,168 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io,
+    path::Path,
+    process,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use nix::fcntl::{Flock, FlockArg};
+use serde::{Deserialize, Serialize};
+
+use super::{changes, task::log_debug, transaction::Checkpoint, ROXY_STATE_DIR};
+
+// Directory under `ROXY_STATE_DIR` with an entry for each roxy process
+// changing the configuration, named after its PID.
+const JOURNAL_DIR: &str = "journal";
+const LOCK_FILE: &str = ".lock";
+// The task of the change events recorded when an interrupted change is
+// rolled back.
+const RECOVERY_TASK: &str = "Journal::Recover";
+
+// A change in progress, with the configuration before it.
+#[derive(Debug, Deserialize, Serialize)]
+struct Entry {
+    // The kind of the task, e.g. "Interface::Set", or "Transaction".
+    task: String,
+    // Seconds since the Unix epoch.
+    started: i64,
+    checkpoints: Vec<Checkpoint>,
+}
+
+// A change in progress recorded in the journal. The entry is removed by
+// `end`, and is left in the journal if roxy is killed or panics before it,
+// so that the next roxy rolls back the change.
+#[must_use]
+pub(crate) struct Journal {
+    path: String,
+}
+
+impl Journal {
+    // Removes the entry of the change, which has completed or failed
+    // without being interrupted.
+    pub(crate) fn end(self) {
+        if let Err(e) = fs::remove_file(&self.path) {
+            log_debug(&format!("fail to remove {}. {e:?}", self.path));
+        }
+    }
+}
+
+// Records that the task is about to change the configuration saved in the
+// checkpoints. Returns None if a change of this process is already in the
+// journal, e.g. a task in a transaction.
+//
+// Only the files of the subsystems in `Checkpoint::of` are journaled. A task
+// changing no subsystem, e.g. a WireGuard or keepalived configuration, is not
+// journaled, and is not rolled back if roxy is interrupted.
+//
+// # Errors
+//
+// * fail to write the entry
+pub(crate) fn begin(task: &str, checkpoints: Vec<Checkpoint>) -> Result<Option<Journal>> {
+    let dir = format!("{ROXY_STATE_DIR}/{JOURNAL_DIR}");
+    let path = format!("{dir}/{}.json", process::id());
+    if Path::new(&path).exists() {
+        return Ok(None);
+    }
+    let entry = Entry {
+        task: task.to_string(),
+        started: Local::now().timestamp(),
+        checkpoints,
+    };
+    fs::create_dir_all(&dir)?;
+    // The entry is renamed into place, so that a partial entry is never
+    // rolled back.
+    let tmp = format!("{path}.tmp");
+    fs::write(&tmp, serde_json::to_vec(&entry)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(Some(Journal { path }))
+}
+
+// Rolls back the changes of the roxy processes which were interrupted before
+// they completed, and records them in the change events. Only the
+// configuration saved in the checkpoints of an entry is restored. An entry is
+// removed even if the rollback fails, so that it is not retried by every roxy.
+//
+// # Errors
+//
+// * fail to lock or read the journal
+pub(crate) fn recover() -> Result<()> {
+    let dir = format!("{ROXY_STATE_DIR}/{JOURNAL_DIR}");
+    let entries = match fs::read_dir(&dir) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut pids = entries
+        .flatten()
+        .filter_map(|entry| {
+            let name = entry.file_name().to_string_lossy().into_owned();
+            name.strip_suffix(".json")?.parse::<u32>().ok()
+        })
+        .filter(|pid| !is_running(*pid))
+        .collect::<Vec<_>>();
+    if pids.is_empty() {
+        return Ok(());
+    }
+    pids.sort_unstable();
+
+    // Another roxy may be recovering the same entries.
+    let _lock = lock(&dir)?;
+    for pid in pids {
+        let path = format!("{dir}/{pid}.json");
+        let entry = match fs::read(&path) {
+            Ok(contents) => serde_json::from_slice::<Entry>(&contents),
+            Err(e) if e.kind() == io::ErrorKind::NotFound => continue,
+            Err(e) => return Err(e.into()),
+        };
+        match entry {
+            Ok(entry) => roll_back(&entry),
+            Err(e) => log_debug(&format!("invalid journal entry {path}. {e:?}")),
+        }
+        fs::remove_file(&path)?;
+    }
+    Ok(())
+}
+
+fn roll_back(entry: &Entry) {
+    log_debug(&format!(
+        "rolling back {} interrupted at {}",
+        entry.task, entry.started
+    ));
+    let before = changes::snapshot(Checkpoint::subsystems());
+    // The checkpoints are restored in the reverse order they were taken.
+    for checkpoint in entry.checkpoints.iter().rev() {
+        if let Err(e) = checkpoint.restore() {
+            log_debug(&format!("fail to roll back {}. {e:?}", entry.task));
+            return;
+        }
+    }
+    let note = format!("rolled back {} interrupted", entry.task);
+    if let Err(e) = changes::record(RECOVERY_TASK, before, &[note]) {
+        log_debug(&format!("fail to record changes. {e:?}"));
+    }
+}
+
+// Returns true if the process is another roxy, which is still changing the
+// configuration. A PID reused by another program is not roxy.
+fn is_running(pid: u32) -> bool {
+    if pid == process::id() {
+        return false;
+    }
+    let comm = |pid: &str| fs::read_to_string(format!("/proc/{pid}/comm")).ok();
+    match (comm(&pid.to_string()), comm("self")) {
+        (Some(other), Some(this)) => other == this,
+        _ => false,
+    }
+}
+
+fn lock(dir: &str) -> Result<Flock<File>> {
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(format!("{dir}/{LOCK_FILE}"))?;
+    Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, e)| anyhow!("fail to lock the journal. {}", e))
+}
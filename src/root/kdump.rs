@@ -0,0 +1,191 @@
+use std::{fmt::Write as FmtWrite, fs};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command, KdumpStatus};
+
+use crate::root::persist;
+
+pub(crate) const KDUMP_CONF: &str = "/etc/default/kdump-tools";
+pub(crate) const KDUMP_GRUB_CONF: &str = "/etc/default/grub.d/kdump-tools.cfg";
+const KDUMP_SERVICE_UNIT: &str = "kdump-tools";
+const KEXEC_CRASH_LOADED: &str = "/sys/kernel/kexec_crash_loaded";
+const PROC_CMDLINE: &str = "/proc/cmdline";
+const CRASH_DIR: &str = "/var/crash";
+const USE_KDUMP: &str = "USE_KDUMP";
+
+// Enables kdump and loads the crash kernel.
+//
+// # Errors
+//
+// * fail to open or write /etc/default/kdump-tools
+// * systemctl return error when starting kdump-tools service
+pub(crate) fn enable() -> Result<bool> {
+    set_use_kdump(true)?;
+    let systemctl = systemctl::SystemCtl::default();
+    if !systemctl.enable(KDUMP_SERVICE_UNIT)?.success() {
+        return Ok(false);
+    }
+    systemctl
+        .restart(KDUMP_SERVICE_UNIT)
+        .map(|status| status.success())
+        .map_err(Into::into)
+}
+
+// Disables kdump and unloads the crash kernel.
+//
+// # Errors
+//
+// * fail to open or write /etc/default/kdump-tools
+// * systemctl return error when stopping kdump-tools service
+pub(crate) fn disable() -> Result<bool> {
+    set_use_kdump(false)?;
+    let systemctl = systemctl::SystemCtl::default();
+    if !systemctl.stop(KDUMP_SERVICE_UNIT)?.success() {
+        return Ok(false);
+    }
+    systemctl
+        .disable(KDUMP_SERVICE_UNIT)
+        .map(|status| status.success())
+        .map_err(Into::into)
+}
+
+// Sets the memory reserved for the crash kernel, which takes effect after
+// reboot.
+//
+// # Example
+//
+// let ret = kdump::set_crashkernel("512M-:192M")?;
+//
+// # Errors
+//
+// * invalid reservation
+// * fail to write /etc/default/grub.d/kdump-tools.cfg
+// * fail to execute update-grub
+pub(crate) fn set_crashkernel(reservation: &str) -> Result<bool> {
+    if reservation.is_empty()
+        || !reservation
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || ":,-@".contains(c))
+    {
+        return Err(anyhow!("invalid crashkernel: {}", reservation));
+    }
+
+    persist::write(
+        KDUMP_GRUB_CONF,
+        format!(
+            "GRUB_CMDLINE_LINUX_DEFAULT=\"$GRUB_CMDLINE_LINUX_DEFAULT crashkernel={reservation}\"\n"
+        ),
+    )?;
+    let output = run_command(&mut command("update-grub"))?;
+    Ok(output.status.success())
+}
+
+// Gets kdump status and the list of vmcore files.
+//
+// # Errors
+//
+// * fail to read /proc/cmdline
+// * fail to read /var/crash
+pub(crate) fn status() -> Result<KdumpStatus> {
+    let enabled = fs::read_to_string(KDUMP_CONF)
+        .is_ok_and(|contents| get_value(&contents, USE_KDUMP).as_deref() == Some("1"));
+    let armed = fs::read_to_string(KEXEC_CRASH_LOADED).is_ok_and(|contents| contents.trim() == "1");
+    let crashkernel = parse_crashkernel(&fs::read_to_string(PROC_CMDLINE)?);
+    let configured_crashkernel = fs::read_to_string(KDUMP_GRUB_CONF)
+        .ok()
+        .and_then(|contents| parse_crashkernel(contents.trim_end().trim_end_matches('"')));
+
+    let mut vmcores = Vec::new();
+    match fs::read_dir(CRASH_DIR) {
+        Ok(entries) => {
+            for entry in entries.flatten() {
+                if !entry.file_type().is_ok_and(|t| t.is_dir()) {
+                    continue;
+                }
+                for file in fs::read_dir(entry.path())?.flatten() {
+                    if file.file_name().to_string_lossy().starts_with("dump.") {
+                        vmcores.push(file.path().to_string_lossy().into_owned());
+                    }
+                }
+            }
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {}
+        Err(e) => return Err(e.into()),
+    }
+    vmcores.sort();
+
+    Ok(KdumpStatus {
+        enabled,
+        armed,
+        crashkernel,
+        configured_crashkernel,
+        vmcores,
+    })
+}
+
+fn set_use_kdump(enabled: bool) -> Result<()> {
+    let contents = fs::read_to_string(KDUMP_CONF)?;
+    let value = if enabled { "1" } else { "0" };
+    let mut new_contents = String::new();
+    let mut found = false;
+    for line in contents.lines() {
+        if line.trim_start().starts_with(&format!("{USE_KDUMP}=")) {
+            if !found {
+                writeln!(new_contents, "{USE_KDUMP}={value}")
+                    .expect("writing to string should not fail");
+                found = true;
+            }
+        } else {
+            new_contents.push_str(line);
+            new_contents.push('\n');
+        }
+    }
+    if !found {
+        writeln!(new_contents, "{USE_KDUMP}={value}").expect("writing to string should not fail");
+    }
+    persist::write(KDUMP_CONF, new_contents)?;
+    Ok(())
+}
+
+// Gets the value of a shell variable assignment such as `USE_KDUMP=1`.
+fn get_value(contents: &str, key: &str) -> Option<String> {
+    contents.lines().find_map(|line| {
+        let (k, v) = line.trim().split_once('=')?;
+        (k == key).then(|| v.trim_matches('"').to_string())
+    })
+}
+
+fn parse_crashkernel(cmdline: &str) -> Option<String> {
+    cmdline
+        .split_whitespace()
+        .find_map(|arg| arg.strip_prefix("crashkernel="))
+        .map(ToString::to_string)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn crashkernel() {
+        assert_eq!(
+            parse_crashkernel(
+                "BOOT_IMAGE=/vmlinuz-5.15.0-88-generic root=/dev/sda2 ro crashkernel=512M-:192M"
+            )
+            .as_deref(),
+            Some("512M-:192M")
+        );
+        assert_eq!(parse_crashkernel("root=/dev/sda2 ro quiet"), None);
+    }
+
+    #[test]
+    fn use_kdump() {
+        let contents = "# kdump-tools configuration\nUSE_KDUMP=1\nKDUMP_COREDIR=\"/var/crash\"\n";
+        assert_eq!(get_value(contents, USE_KDUMP).as_deref(), Some("1"));
+        assert_eq!(
+            get_value(contents, "KDUMP_COREDIR").as_deref(),
+            Some("/var/crash")
+        );
+        assert_eq!(get_value(contents, "KDUMP_KERNEL"), None);
+    }
+}
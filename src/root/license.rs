@@ -0,0 +1,141 @@
+use std::{fs, io, path::Path};
+
+use anyhow::{anyhow, Result};
+use chrono::{Local, NaiveDate};
+use data_encoding::BASE64;
+use roxy::common::{License, LicenseStatus};
+use serde::Deserialize;
+
+use super::{persist, signing};
+
+// A license is a JSON document with the base64 of the JSON `License` and its
+// signature made by the private key of the product:
+//
+// {"payload": "eyJpZCI6...", "signature": "MEUCIQ..."}
+pub(crate) const LICENSE_FILE: &str = "/usr/local/aice/conf/license";
+// Public key of the product in PEM, which verifies the signature.
+const LICENSE_KEY: &str = "/usr/local/aice/conf/license.pem";
+const DATE_FORMAT: &str = "%Y-%m-%d";
+
+#[derive(Debug, Deserialize)]
+struct SignedLicense {
+    payload: String,
+    signature: String,
+}
+
+// Gets the installed license.
+//
+// # Return
+//
+// * None if no license is installed
+//
+// # Errors
+//
+// * fail to read or parse the license
+// * fail to run openssl
+pub(crate) fn get() -> Result<Option<LicenseStatus>> {
+    let blob = match fs::read(LICENSE_FILE) {
+        Ok(blob) => blob,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let (payload, signature) = parse_blob(&blob)?;
+    let license = serde_json::from_slice::<License>(&payload)?;
+    let today = Local::now().date_naive();
+    Ok(Some(LicenseStatus {
+        expired: is_expired(&license, today)?,
+        signature_valid: verify(&payload, &signature)?,
+        license,
+    }))
+}
+
+// Installs a license after checking its signature and expiry.
+//
+// # Errors
+//
+// * invalid, unsigned or expired license
+// * fail to run openssl or to write the license
+pub(crate) fn set(blob: &[u8]) -> Result<()> {
+    let (payload, signature) = parse_blob(blob)?;
+    let license = serde_json::from_slice::<License>(&payload)?;
+    if !verify(&payload, &signature)? {
+        return Err(anyhow!("invalid signature of license {}", license.id));
+    }
+    if is_expired(&license, Local::now().date_naive())? {
+        return Err(anyhow!("license {} is expired", license.id));
+    }
+    if let Some(dir) = Path::new(LICENSE_FILE).parent() {
+        fs::create_dir_all(dir)?;
+    }
+    persist::write(LICENSE_FILE, blob)
+}
+
+fn parse_blob(blob: &[u8]) -> Result<(Vec<u8>, Vec<u8>)> {
+    let signed = serde_json::from_slice::<SignedLicense>(blob)?;
+    Ok((
+        BASE64.decode(signed.payload.as_bytes())?,
+        BASE64.decode(signed.signature.as_bytes())?,
+    ))
+}
+
+fn is_expired(license: &License, today: NaiveDate) -> Result<bool> {
+    NaiveDate::parse_from_str(&license.issued, DATE_FORMAT)
+        .map_err(|_| anyhow!("invalid date of issue: {}", license.issued))?;
+    let Some(expires) = &license.expires else {
+        return Ok(false);
+    };
+    let expires = NaiveDate::parse_from_str(expires, DATE_FORMAT)
+        .map_err(|_| anyhow!("invalid expiry date: {}", expires))?;
+    Ok(expires < today)
+}
+
+// Verifies the signature of the payload with the product key.
+fn verify(payload: &[u8], signature: &[u8]) -> Result<bool> {
+    if !Path::new(LICENSE_KEY).exists() {
+        return Err(anyhow!("{} not found", LICENSE_KEY));
+    }
+    signing::verify(LICENSE_KEY, payload, signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(s: &str) -> NaiveDate {
+        NaiveDate::parse_from_str(s, DATE_FORMAT).expect("valid date")
+    }
+
+    #[test]
+    fn expiry() {
+        let mut license = License {
+            id: "L-1".to_string(),
+            customer: "Example".to_string(),
+            issued: "2026-01-01".to_string(),
+            expires: Some("2026-12-31".to_string()),
+            ..License::default()
+        };
+        assert!(!is_expired(&license, date("2026-12-31")).expect("valid"));
+        assert!(is_expired(&license, date("2027-01-01")).expect("valid"));
+        license.expires = None;
+        assert!(!is_expired(&license, date("2099-01-01")).expect("valid"));
+        license.issued = "01/01/2026".to_string();
+        assert!(is_expired(&license, date("2026-06-01")).is_err());
+    }
+
+    #[test]
+    fn blob() {
+        let payload = r#"{"id":"L-1","customer":"Example","issued":"2026-01-01"}"#;
+        let blob = format!(
+            r#"{{"payload":"{}","signature":"{}"}}"#,
+            BASE64.encode(payload.as_bytes()),
+            BASE64.encode(b"sig")
+        );
+        let (decoded, signature) = parse_blob(blob.as_bytes()).expect("valid blob");
+        assert_eq!(decoded, payload.as_bytes());
+        assert_eq!(signature, b"sig");
+        let license = serde_json::from_slice::<License>(&decoded).expect("valid license");
+        assert_eq!(license.features, Vec::<String>::new());
+        assert_eq!(license.max_nodes, None);
+        assert!(parse_blob(b"{\"payload\":\"!\",\"signature\":\"\"}").is_err());
+    }
+}
@@ -0,0 +1,105 @@
+use std::{
+    fs, io,
+    os::unix::process::ExitStatusExt,
+    process::{Command, Output},
+};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command};
+use serde::Deserialize;
+use thiserror::Error;
+
+pub(crate) const LIMITS_CONF: &str = "/etc/roxy/limits.yaml";
+const SIGKILL: i32 = 9;
+
+// Limits of the diagnostics spawned by roxy, such as smartctl and
+// journalctl, so that they cannot starve the capture services, e.g.
+//
+// ```yaml
+// cpu_quota: 50
+// memory_max: 268435456
+// ```
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Limits {
+    // Percent of the time of a CPU.
+    cpu_quota: Option<u32>,
+    // Bytes of memory, beyond which the diagnostic is killed.
+    memory_max: Option<u64>,
+}
+
+// A diagnostic was killed for exceeding its memory limit. The message is
+// sent to the client as is.
+#[derive(Debug, Error)]
+#[error("resource limit exceeded: {0}")]
+pub(crate) struct LimitExceeded(String);
+
+// Runs a diagnostic in a transient systemd scope with the limits in
+// `LIMITS_CONF`. Without limits, it is run directly.
+//
+// # Errors
+//
+// * fail to read or parse `LIMITS_CONF`
+// * fail to run the diagnostic or systemd-run
+// * the diagnostic is killed for exceeding its memory limit
+pub(crate) fn run(program: &str, args: &[&str]) -> Result<Output> {
+    let limits = read_conf()?;
+    let properties = properties(&limits);
+    if properties.is_empty() {
+        return Ok(run_command(command(program).args(args))?);
+    }
+
+    let mut cmd: Command = command("systemd-run");
+    cmd.args(["--scope", "--quiet", "--collect"]);
+    for property in &properties {
+        cmd.args(["-p", property]);
+    }
+    let output = run_command(cmd.arg("--").arg(program).args(args))?;
+    // The kernel kills a process in a scope which cannot reclaim memory
+    // under its limit.
+    if limits.memory_max.is_some() && output.status.signal() == Some(SIGKILL) {
+        return Err(LimitExceeded(program.to_string()).into());
+    }
+    Ok(output)
+}
+
+// The properties of the scope for the limits.
+fn properties(limits: &Limits) -> Vec<String> {
+    let mut properties = Vec::new();
+    if let Some(quota) = limits.cpu_quota {
+        properties.push(format!("CPUQuota={quota}%"));
+    }
+    if let Some(max) = limits.memory_max {
+        properties.push(format!("MemoryMax={max}"));
+        // Without swap, the limit is not evaded by swapping out.
+        properties.push("MemorySwapMax=0".to_string());
+    }
+    properties
+}
+
+fn read_conf() -> Result<Limits> {
+    match fs::read_to_string(LIMITS_CONF) {
+        Ok(contents) if contents.trim().is_empty() => Ok(Limits::default()),
+        Ok(contents) => serde_yaml::from_str(&contents)
+            .map_err(|e| anyhow!("fail to parse {}. {}", LIMITS_CONF, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Limits::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn scope_properties() {
+        assert!(properties(&Limits::default()).is_empty());
+        let limits: Limits =
+            serde_yaml::from_str("cpu_quota: 50\nmemory_max: 268435456\n").expect("valid limits");
+        assert_eq!(
+            properties(&limits),
+            ["CPUQuota=50%", "MemoryMax=268435456", "MemorySwapMax=0"]
+        );
+        assert!(serde_yaml::from_str::<Limits>("cpu: 50\n").is_err());
+    }
+}
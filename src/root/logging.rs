@@ -0,0 +1,218 @@
+use std::{
+    fs,
+    io::{self, Write},
+    os::unix::net::UnixDatagram,
+    path::Path,
+    process,
+    str::FromStr,
+    sync::OnceLock,
+};
+
+use anyhow::anyhow;
+use chrono::{DateTime, Local};
+use log::Level;
+
+// TODO: define the full path for roxy.log file
+const LOG_FILE: &str = "/data/logs/apps/roxy.log";
+// The socket of the local syslog daemon, which forwards the messages to the
+// remote servers set by `Syslog` `Set`.
+const SYSLOG_SOCKET: &str = "/dev/log";
+const APP_NAME: &str = "roxy";
+const DEFAULT_MAX_SIZE: u64 = 10 * 1024 * 1024;
+const DEFAULT_KEEP: usize = 5;
+// LOG_DAEMON
+const FACILITY: u8 = 3;
+
+// Where roxy writes its own log.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) enum Target {
+    File,
+    Syslog,
+    Both,
+}
+
+impl FromStr for Target {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "file" => Ok(Target::File),
+            "syslog" => Ok(Target::Syslog),
+            "both" => Ok(Target::Both),
+            _ => Err(anyhow!("invalid log target: {}", s)),
+        }
+    }
+}
+
+// When the log file is rotated, and how many rotated files are kept. The
+// log file is renamed to "roxy.log.1" when it reaches `max_size`, and
+// "roxy.log.1" to "roxy.log.2", and so on, removing the oldest beyond `keep`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub(crate) struct Rotation {
+    pub(crate) max_size: u64,
+    pub(crate) keep: usize,
+}
+
+impl Default for Rotation {
+    fn default() -> Self {
+        Rotation {
+            max_size: DEFAULT_MAX_SIZE,
+            keep: DEFAULT_KEEP,
+        }
+    }
+}
+
+static TARGET: OnceLock<Target> = OnceLock::new();
+static ROTATION: OnceLock<Rotation> = OnceLock::new();
+
+// Sets where the log is written. It can be set only once, and the log is
+// written to `LOG_FILE` until it is set.
+pub(crate) fn set_target(target: Target) {
+    let _ = TARGET.set(target);
+}
+
+// Sets the rotation of the log file. It can be set only once, and the default
+// is used until it is set.
+pub(crate) fn set_rotation(rotation: Rotation) {
+    let _ = ROTATION.set(rotation);
+}
+
+// Writes the message at the level, or without a level for a message which is
+// always written.
+pub(crate) fn write(level: Option<Level>, msg: &str) {
+    let target = TARGET.get().copied().unwrap_or(Target::File);
+    if target != Target::Syslog {
+        // The log is still written if the rotation fails.
+        let _r = rotate(
+            Path::new(LOG_FILE),
+            ROTATION.get().copied().unwrap_or_default(),
+        );
+        if let Ok(mut writer) = fs::OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(LOG_FILE)
+        {
+            let _r = match level {
+                Some(level) => writeln!(writer, "{:?}: {level} {msg}", Local::now()),
+                None => writeln!(writer, "{:?}: {msg}", Local::now()),
+            };
+        }
+    }
+    if target != Target::File {
+        let line = rfc5424(level, msg, &Local::now(), &hostname(), process::id());
+        // Nothing else can be done if the syslog daemon is not running.
+        let _r = send(&line);
+    }
+}
+
+// Rotates the log file if it has reached the maximum size. Concurrent roxy
+// processes may rotate it twice, which only makes the rotated files smaller.
+fn rotate(path: &Path, rotation: Rotation) -> io::Result<()> {
+    if fs::metadata(path)?.len() < rotation.max_size {
+        return Ok(());
+    }
+    if rotation.keep == 0 {
+        return fs::remove_file(path);
+    }
+    let rotated = |n: usize| format!("{}.{n}", path.display());
+    for n in (1..rotation.keep).rev() {
+        match fs::rename(rotated(n), rotated(n + 1)) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => return Err(e),
+            _ => {}
+        }
+    }
+    fs::rename(path, rotated(1))
+}
+
+fn send(line: &str) -> io::Result<()> {
+    let socket = UnixDatagram::unbound()?;
+    socket.send_to(line.as_bytes(), SYSLOG_SOCKET)?;
+    Ok(())
+}
+
+fn hostname() -> String {
+    gethostname::gethostname().to_string_lossy().into_owned()
+}
+
+// Formats a message in RFC 5424, e.g. "<30>1 2024-05-01T09:00:00.000000+09:00
+// sensor1 roxy 1234 - - fail to set eth0.", without structured data.
+fn rfc5424(
+    level: Option<Level>,
+    msg: &str,
+    time: &DateTime<Local>,
+    host: &str,
+    pid: u32,
+) -> String {
+    let severity: u8 = match level {
+        Some(Level::Error) => 3,
+        Some(Level::Warn) => 4,
+        None => 5,
+        Some(Level::Info) => 6,
+        Some(Level::Debug | Level::Trace) => 7,
+    };
+    format!(
+        "<{}>1 {} {} {APP_NAME} {pid} - - {msg}",
+        FACILITY * 8 + severity,
+        time.format("%Y-%m-%dT%H:%M:%S%.6f%:z"),
+        if host.is_empty() { "-" } else { host },
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    #[test]
+    fn rfc5424_format() {
+        let time = Local
+            .with_ymd_and_hms(2024, 5, 1, 9, 0, 0)
+            .single()
+            .expect("valid time");
+        let offset = time.format("%:z").to_string();
+        assert_eq!(
+            rfc5424(
+                Some(Level::Error),
+                "fail to set eth0.",
+                &time,
+                "sensor1",
+                1234
+            ),
+            format!(
+                "<27>1 2024-05-01T09:00:00.000000{offset} sensor1 roxy 1234 - - fail to set eth0."
+            )
+        );
+        assert!(rfc5424(None, "reboot", &time, "", 1).starts_with("<29>1 "));
+        assert!(rfc5424(None, "reboot", &time, "", 1).contains(" - roxy 1 - - reboot"));
+        assert_eq!("both".parse::<Target>().ok(), Some(Target::Both));
+        assert!("stderr".parse::<Target>().is_err());
+    }
+
+    #[test]
+    fn log_rotation() {
+        let dir = std::env::temp_dir().join(format!("roxy-log-{}", process::id()));
+        fs::create_dir_all(&dir).expect("temporary directory");
+        let path = dir.join("roxy.log");
+        let rotation = Rotation {
+            max_size: 4,
+            keep: 2,
+        };
+        let read = |name: &str| fs::read_to_string(dir.join(name)).ok();
+
+        for contents in ["abc", "abcd", "efgh", "ijkl"] {
+            fs::write(&path, contents).expect("log file");
+            rotate(&path, rotation).expect("rotate");
+        }
+        assert_eq!(read("roxy.log.1").as_deref(), Some("ijkl"));
+        assert_eq!(read("roxy.log.2").as_deref(), Some("efgh"));
+        assert_eq!(read("roxy.log.3"), None);
+        assert_eq!(read("roxy.log"), None);
+
+        fs::write(&path, "abc").expect("log file");
+        rotate(&path, rotation).expect("rotate");
+        assert_eq!(read("roxy.log").as_deref(), Some("abc"));
+
+        fs::remove_dir_all(&dir).expect("remove temporary directory");
+    }
+}
@@ -0,0 +1,129 @@
+use std::{
+    fs::File,
+    io::{Read, Seek, SeekFrom},
+    path::Path,
+};
+
+use roxy::common::{command, run_command, spawned_programs};
+
+const AUDIT_LOG: &str = "/var/log/audit/audit.log";
+// Only the end of the log is read, since the denials of interest are recent.
+const MAX_SCANNED: u64 = 1024 * 1024;
+// The kernel truncates the name of a process in `comm` to 15 bytes.
+const COMM_LEN: usize = 15;
+
+const ERR_SELINUX: &str = "fail; denied by SELinux. see the AVC records in the audit log";
+const ERR_APPARMOR: &str = "fail; denied by AppArmor. see apparmor=\"DENIED\" in the kernel log";
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Mac {
+    SeLinux,
+    AppArmor,
+}
+
+// Explains a failure by a denial of SELinux or AppArmor logged since `since`,
+// in seconds since the Unix epoch, for roxy or a program it ran. auditd
+// writes the denials to its log, or the kernel writes them to its ring
+// buffer if auditd is not running.
+//
+// # Return
+//
+// * the error with a hint about the denial, or None if there is none
+pub(crate) fn explain(since: i64) -> Option<&'static str> {
+    let mut programs = spawned_programs();
+    if let Some(name) = std::env::current_exe()
+        .ok()
+        .as_deref()
+        .and_then(Path::file_name)
+    {
+        programs.push(name.to_string_lossy().into_owned());
+    }
+    let scan = |log: &str| log.lines().find_map(|line| denial(line, since, &programs));
+    let mac = audit_log()
+        .and_then(|log| scan(&log))
+        .or_else(|| kernel_log().and_then(|log| scan(&log)))?;
+    Some(match mac {
+        Mac::SeLinux => ERR_SELINUX,
+        Mac::AppArmor => ERR_APPARMOR,
+    })
+}
+
+fn audit_log() -> Option<String> {
+    let mut file = File::open(AUDIT_LOG).ok()?;
+    let len = file.metadata().ok()?.len();
+    file.seek(SeekFrom::Start(len.saturating_sub(MAX_SCANNED)))
+        .ok()?;
+    let mut buf = Vec::new();
+    file.read_to_end(&mut buf).ok()?;
+    Some(String::from_utf8_lossy(&buf).into_owned())
+}
+
+fn kernel_log() -> Option<String> {
+    let output = run_command(&mut command("dmesg")).ok()?;
+    output
+        .status
+        .success()
+        .then(|| String::from_utf8_lossy(&output.stdout).into_owned())
+}
+
+// Parses an audit record, e.g.
+//
+// ```text
+// type=AVC msg=audit(1700000000.123:45): avc:  denied  { write } for  pid=1 comm="ntpd" ...
+// audit: type=1400 audit(1700000000.123:45): apparmor="DENIED" operation="open" ... comm="ntpd" ...
+// ```
+fn denial(line: &str, since: i64, programs: &[String]) -> Option<Mac> {
+    let mac = if line.contains("apparmor=\"DENIED\"") {
+        Mac::AppArmor
+    } else if line.contains("avc:") && line.contains(" denied ") {
+        Mac::SeLinux
+    } else {
+        return None;
+    };
+    let (_, time) = line.split_once("audit(")?;
+    let time = time.split(['.', ':']).next()?.parse::<i64>().ok()?;
+    if time < since {
+        return None;
+    }
+    let comm = field(line, "comm");
+    let exe = field(line, "exe").and_then(|exe| exe.rsplit('/').next());
+    programs
+        .iter()
+        .any(|program| {
+            let truncated = program.get(..COMM_LEN).unwrap_or(program);
+            comm == Some(truncated) || exe == Some(program.as_str())
+        })
+        .then_some(mac)
+}
+
+// The value of a quoted field, e.g. `comm="ntpd"`.
+fn field<'a>(line: &'a str, name: &str) -> Option<&'a str> {
+    let (_, rest) = line.split_once(&format!(" {name}=\""))?;
+    rest.split('"').next()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn denials() {
+        let programs = vec!["systemctl".to_string(), "systemd-timesyncd".to_string()];
+        let selinux = "type=AVC msg=audit(1700000000.123:45): avc:  denied  { write } for  \
+                       pid=1 comm=\"systemctl\" name=\"ntp.conf\" exe=\"/usr/bin/systemctl\"";
+        assert_eq!(
+            denial(selinux, 1_700_000_000, &programs),
+            Some(Mac::SeLinux)
+        );
+        assert_eq!(denial(selinux, 1_700_000_001, &programs), None);
+        assert_eq!(denial(selinux, 0, &["ntpd".to_string()]), None);
+
+        let apparmor = "[ 12.3] audit: type=1400 audit(1700000000.123:46): apparmor=\"DENIED\" \
+                        operation=\"open\" profile=\"timesyncd\" pid=2 comm=\"systemd-timesyn\"";
+        assert_eq!(denial(apparmor, 0, &programs), Some(Mac::AppArmor));
+
+        let allowed = "type=AVC msg=audit(1700000000.123:47): avc:  granted  { write } for  \
+                       pid=1 comm=\"systemctl\"";
+        assert_eq!(denial(allowed, 0, &programs), None);
+    }
+}
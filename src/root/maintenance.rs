@@ -0,0 +1,185 @@
+use std::{
+    fs::{self, File},
+    io,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Local};
+use nix::fcntl::{Flock, FlockArg};
+use roxy::common::{
+    is_deferrable, DeferredOperation, MaintenanceWindow, Node, NodeRequest, OperationStatus,
+};
+
+use super::ROXY_STATE_DIR;
+
+const MAINTENANCE_QUEUE: &str = "maintenance.json";
+// Serializes the changes of the queue by concurrent roxy processes, so that
+// an operation is not executed twice.
+const MAINTENANCE_LOCK: &str = "maintenance.lock";
+// The number of finished operations kept for their status.
+const MAX_FINISHED: usize = 100;
+
+// Queues a request to be executed in the maintenance window.
+//
+// # Return
+//
+// * the ID of the queued operation
+//
+// # Errors
+//
+// * the request cannot be deferred
+// * invalid window or expiry
+// * fail to read or write the queue
+pub(crate) fn add(
+    request: NodeRequest,
+    window: MaintenanceWindow,
+    expires: Option<String>,
+) -> Result<u64> {
+    if !is_deferrable(&request) {
+        return Err(anyhow!("{:?} cannot be deferred", request.kind));
+    }
+    // The deferred request is executed later by whoever runs the due
+    // operations, so its role is checked when it is queued.
+    super::role::authorize(&request)?;
+    let now = Local::now();
+    window.contains(now.time())?;
+    if let Some(expires) = &expires {
+        DateTime::parse_from_rfc3339(expires)
+            .map_err(|_| anyhow!("invalid expiry: {}", expires))?;
+    }
+
+    let _lock = lock()?;
+    let mut queue = load()?;
+    let id = queue.iter().map(|op| op.id).max().unwrap_or_default() + 1;
+    queue.push(DeferredOperation {
+        id,
+        request,
+        window,
+        queued: now.to_rfc3339(),
+        expires,
+        status: OperationStatus::Queued,
+        updated: None,
+    });
+    save(&queue)?;
+    Ok(id)
+}
+
+// Gets the queued and the recently finished operations, oldest first.
+//
+// # Errors
+//
+// * fail to read the queue
+pub(crate) fn get() -> Result<Vec<DeferredOperation>> {
+    load()
+}
+
+// Cancels a queued operation.
+//
+// # Errors
+//
+// * the operation is not queued
+// * fail to read or write the queue
+pub(crate) fn cancel(id: u64) -> Result<()> {
+    let _lock = lock()?;
+    let mut queue = load()?;
+    let op = queue
+        .iter_mut()
+        .find(|op| op.id == id && op.status == OperationStatus::Queued)
+        .ok_or_else(|| anyhow!("operation {} is not queued", id))?;
+    op.status = OperationStatus::Cancelled;
+    op.updated = Some(Local::now().to_rfc3339());
+    save(&queue)
+}
+
+// Executes the queued operations whose window has come, and expires those
+// past their expiry. Reboot and power off are executed last, after they are
+// saved as executed, since they do not return.
+//
+// # Return
+//
+// * the operations executed or expired
+//
+// # Errors
+//
+// * fail to read or write the queue
+pub(crate) fn run_due() -> Result<Vec<DeferredOperation>> {
+    let _lock = lock()?;
+    let mut queue = load()?;
+    let now = Local::now();
+    let mut due = Vec::new();
+    for (i, op) in queue.iter_mut().enumerate() {
+        if op.status != OperationStatus::Queued {
+            continue;
+        }
+        let expired = op
+            .expires
+            .as_deref()
+            .and_then(|expires| DateTime::parse_from_rfc3339(expires).ok())
+            .is_some_and(|expires| expires < now);
+        if expired {
+            op.status = OperationStatus::Expired;
+            op.updated = Some(now.to_rfc3339());
+            due.push(i);
+        } else if op.window.contains(now.time()).unwrap_or(false) {
+            due.push(i);
+        }
+    }
+    due.sort_by_key(|i| matches!(queue[*i].request.kind, Node::PowerOff | Node::Reboot));
+
+    for &i in &due {
+        let op = &mut queue[i];
+        if op.status != OperationStatus::Queued {
+            continue;
+        }
+        op.updated = Some(Local::now().to_rfc3339());
+        if matches!(op.request.kind, Node::PowerOff | Node::Reboot) {
+            op.status = OperationStatus::Executed;
+            save(&queue)?;
+        }
+        let op = &mut queue[i];
+        op.status = match crate::task(&op.request).execute() {
+            Ok(_) => OperationStatus::Executed,
+            Err(e) => OperationStatus::Failed(e.to_string()),
+        };
+    }
+
+    let finished = queue
+        .iter()
+        .filter(|op| op.status != OperationStatus::Queued)
+        .count();
+    let mut excess = finished.saturating_sub(MAX_FINISHED);
+    let ret = due.iter().map(|i| queue[*i].clone()).collect();
+    queue.retain(|op| {
+        if excess > 0 && op.status != OperationStatus::Queued {
+            excess -= 1;
+            false
+        } else {
+            true
+        }
+    });
+    save(&queue)?;
+    Ok(ret)
+}
+
+fn lock() -> Result<Flock<File>> {
+    fs::create_dir_all(ROXY_STATE_DIR)?;
+    let file = File::create(format!("{ROXY_STATE_DIR}/{MAINTENANCE_LOCK}"))?;
+    Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, e)| anyhow!("fail to lock the maintenance queue. {}", e))
+}
+
+fn load() -> Result<Vec<DeferredOperation>> {
+    match fs::read_to_string(format!("{ROXY_STATE_DIR}/{MAINTENANCE_QUEUE}")) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Vec::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save(queue: &[DeferredOperation]) -> Result<()> {
+    fs::write(
+        format!("{ROXY_STATE_DIR}/{MAINTENANCE_QUEUE}"),
+        serde_json::to_string(queue)?,
+    )?;
+    Ok(())
+}
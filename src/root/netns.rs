@@ -0,0 +1,280 @@
+use std::{fs, io, path::Path};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command, NetnsCapture, NetnsInterface};
+use serde::Deserialize;
+
+use crate::root::{capture, limits};
+
+// Where `ip netns add` mounts the named namespaces.
+const NETNS_DIR: &str = "/run/netns";
+const MAX_NAME_LEN: usize = 64;
+const MAX_PACKETS: u32 = 1000;
+const MAX_TIMEOUT_SECS: u64 = 60;
+const MAX_FILTER_LEN: usize = 1024;
+// The exit status of `timeout` when the command timed out.
+const TIMED_OUT: i32 = 124;
+
+#[derive(Deserialize)]
+struct IpLink {
+    ifname: String,
+    #[serde(default)]
+    flags: Vec<String>,
+    #[serde(default)]
+    operstate: String,
+    #[serde(default)]
+    link_type: String,
+    address: Option<String>,
+    stats64: Option<Stats64>,
+}
+
+#[derive(Default, Deserialize)]
+struct Stats64 {
+    rx: Counters,
+    tx: Counters,
+}
+
+#[derive(Default, Deserialize)]
+struct Counters {
+    bytes: u64,
+    packets: u64,
+    #[serde(default)]
+    dropped: u64,
+}
+
+// Lists the interfaces in a named namespace, or in every named namespace if
+// None. The loopback interface of each namespace is left out.
+//
+// # Errors
+//
+// * invalid or unknown namespace
+// * fail to read the namespaces or to run ip
+pub(crate) fn interfaces(namespace: Option<&str>) -> Result<Vec<NetnsInterface>> {
+    let namespaces = match namespace {
+        Some(namespace) => {
+            validate(namespace)?;
+            vec![namespace.to_string()]
+        }
+        None => namespaces()?,
+    };
+    let mut ret = Vec::new();
+    for namespace in namespaces {
+        let output =
+            run_command(command("ip").args(["-n", &namespace, "-j", "-s", "link", "show"]))?;
+        if !output.status.success() {
+            return Err(anyhow!("fail to list interfaces in {}", namespace));
+        }
+        ret.extend(parse_links(
+            &namespace,
+            &String::from_utf8_lossy(&output.stdout),
+        )?);
+    }
+    Ok(ret)
+}
+
+// Returns the namespace holding the interface, if it is in a named one.
+pub(crate) fn find(ifname: &str) -> Option<String> {
+    interfaces(None)
+        .ok()?
+        .into_iter()
+        .find(|nic| nic.name == ifname)
+        .map(|nic| nic.namespace)
+}
+
+// Captures packets on an interface in a named namespace with tcpdump.
+//
+// # Return
+//
+// * a line of tcpdump for each packet
+//
+// # Errors
+//
+// * invalid capture, or the interface is not in the namespace
+// * fail to run tcpdump, e.g. because of an invalid filter
+pub(crate) fn capture(c: &NetnsCapture) -> Result<Vec<String>> {
+    if c.count == 0 || c.count > MAX_PACKETS {
+        return Err(anyhow!("count should be 1 to {}", MAX_PACKETS));
+    }
+    if c.timeout_secs == 0 || c.timeout_secs > MAX_TIMEOUT_SECS {
+        return Err(anyhow!(
+            "timeout should be 1 to {} seconds",
+            MAX_TIMEOUT_SECS
+        ));
+    }
+    if c.filter
+        .as_ref()
+        .is_some_and(|filter| filter.len() > MAX_FILTER_LEN || filter.starts_with('-'))
+    {
+        return Err(anyhow!("invalid filter"));
+    }
+    capture::validate_name(&c.interface)?;
+    if !interfaces(Some(&c.namespace))?
+        .iter()
+        .any(|nic| nic.name == c.interface)
+    {
+        return Err(anyhow!(
+            "interface \"{}\" not found in {}",
+            c.interface,
+            c.namespace
+        ));
+    }
+
+    let args = tcpdump_args(c);
+    let output = limits::run("ip", &args.iter().map(String::as_str).collect::<Vec<_>>())?;
+    if !output.status.success() && output.status.code() != Some(TIMED_OUT) {
+        return Err(anyhow!(
+            "fail to run tcpdump. {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout)
+        .lines()
+        .map(ToString::to_string)
+        .collect())
+}
+
+fn namespaces() -> Result<Vec<String>> {
+    let entries = match fs::read_dir(NETNS_DIR) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut ret = entries
+        .flatten()
+        .map(|entry| entry.file_name().to_string_lossy().into_owned())
+        .collect::<Vec<_>>();
+    ret.sort();
+    Ok(ret)
+}
+
+// Checks the name, which is given to ip as an argument, and the existence of
+// the namespace.
+fn validate(namespace: &str) -> Result<()> {
+    let valid = !namespace.is_empty()
+        && namespace.len() <= MAX_NAME_LEN
+        && !namespace.starts_with(['.', '-'])
+        && namespace
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-_.".contains(c));
+    if !valid {
+        return Err(anyhow!("invalid namespace name: {}", namespace));
+    }
+    if Path::new(NETNS_DIR).join(namespace).exists() {
+        Ok(())
+    } else {
+        Err(anyhow!("network namespace \"{}\" not found", namespace))
+    }
+}
+
+// `timeout` stops tcpdump if fewer packets than the count arrive.
+fn tcpdump_args(c: &NetnsCapture) -> Vec<String> {
+    let mut args = [
+        "netns",
+        "exec",
+        &c.namespace,
+        "timeout",
+        &c.timeout_secs.to_string(),
+        "tcpdump",
+        "-n",
+        "-l",
+        "-c",
+        &c.count.to_string(),
+        "-i",
+        &c.interface,
+    ]
+    .map(ToString::to_string)
+    .to_vec();
+    if let Some(filter) = &c.filter {
+        args.push(filter.clone());
+    }
+    args
+}
+
+fn parse_links(namespace: &str, json: &str) -> Result<Vec<NetnsInterface>> {
+    let links = serde_json::from_str::<Vec<IpLink>>(json)
+        .map_err(|e| anyhow!("fail to parse interfaces in {}. {}", namespace, e))?;
+    Ok(links
+        .into_iter()
+        .filter(|link| link.link_type != "loopback")
+        .map(|link| {
+            let stats = link.stats64.unwrap_or_default();
+            NetnsInterface {
+                namespace: namespace.to_string(),
+                up: link.flags.iter().any(|flag| flag == "UP"),
+                name: link.ifname,
+                operstate: link.operstate,
+                mac: link.address,
+                rx_bytes: stats.rx.bytes,
+                rx_packets: stats.rx.packets,
+                rx_dropped: stats.rx.dropped,
+                tx_bytes: stats.tx.bytes,
+                tx_packets: stats.tx.packets,
+            }
+        })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn links() {
+        let json = r#"[{"ifindex":1,"ifname":"lo","flags":["LOOPBACK","UP"],"operstate":"UNKNOWN",
+            "link_type":"loopback","address":"00:00:00:00:00:00",
+            "stats64":{"rx":{"bytes":1,"packets":1,"dropped":0},"tx":{"bytes":1,"packets":1}}},
+            {"ifindex":4,"ifname":"enp3s0f1","flags":["BROADCAST","PROMISC","UP","LOWER_UP"],
+            "operstate":"UP","link_type":"ether","address":"3c:fd:fe:00:00:01",
+            "stats64":{"rx":{"bytes":9000,"packets":6,"errors":0,"dropped":2},
+            "tx":{"bytes":120,"packets":1,"errors":0,"dropped":0}}},
+            {"ifindex":5,"ifname":"veth0","flags":["BROADCAST"],"operstate":"DOWN",
+            "link_type":"ether"}]"#;
+        let nics = parse_links("capture", json).expect("valid links");
+        assert_eq!(nics.len(), 2);
+        assert_eq!(
+            nics[0],
+            NetnsInterface {
+                namespace: "capture".to_string(),
+                name: "enp3s0f1".to_string(),
+                up: true,
+                operstate: "UP".to_string(),
+                mac: Some("3c:fd:fe:00:00:01".to_string()),
+                rx_bytes: 9000,
+                rx_packets: 6,
+                rx_dropped: 2,
+                tx_bytes: 120,
+                tx_packets: 1,
+            }
+        );
+        assert!(!nics[1].up);
+        assert_eq!(nics[1].rx_bytes, 0);
+        assert!(parse_links("capture", "").is_err());
+    }
+
+    #[test]
+    fn tcpdump_command() {
+        let mut c = NetnsCapture {
+            namespace: "capture".to_string(),
+            interface: "enp3s0f1".to_string(),
+            count: 10,
+            timeout_secs: 5,
+            filter: None,
+        };
+        assert_eq!(
+            tcpdump_args(&c).join(" "),
+            "netns exec capture timeout 5 tcpdump -n -l -c 10 -i enp3s0f1"
+        );
+        c.filter = Some("tcp port 443".to_string());
+        assert_eq!(
+            tcpdump_args(&c).last().map(String::as_str),
+            Some("tcp port 443")
+        );
+    }
+
+    #[test]
+    fn invalid_names() {
+        for name in ["", "..", "-n", "a/b", "a b"] {
+            assert!(validate(name).is_err(), "{name}");
+        }
+    }
+}
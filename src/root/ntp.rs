@@ -1,13 +1,11 @@
-use std::{
-    fmt::Write as FmtWrite,
-    fs::{self, OpenOptions},
-    io::Write as IoWrite,
-};
+use std::{fmt::Write as FmtWrite, fs};
 
 use anyhow::Result;
 use regex::Regex;
 
-const NTP_CONF: &str = "/etc/ntp.conf";
+use crate::root::persist;
+
+pub(crate) const NTP_CONF: &str = "/etc/ntp.conf";
 const NTP_SERVICE_UNIT: &str = "ntp";
 
 // Set NTP server addresses.
@@ -37,12 +35,7 @@ pub(crate) fn set(servers: &[String]) -> Result<bool> {
             .expect("writing to string should not fail");
     }
 
-    let mut file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(NTP_CONF)?;
-
-    file.write_all(new_contents.as_bytes())?;
+    persist::write(NTP_CONF, new_contents)?;
 
     let systemctl = systemctl::SystemCtl::default();
     systemctl
@@ -0,0 +1,349 @@
+use std::{
+    fs,
+    io::{self, Write},
+    os::unix::fs::{OpenOptionsExt, PermissionsExt},
+    path::{Path, PathBuf},
+};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command};
+
+const MOUNTINFO: &str = "/proc/self/mountinfo";
+const OVERLAY_FS: &str = "overlay";
+const VOLATILE_FS: [&str; 2] = ["ramfs", "tmpfs"];
+const EXECUTABLE_MODE: u32 = 0o755;
+const SECRET_MODE: u32 = 0o600;
+
+// Where a write to a file should go to persist across reboots.
+#[derive(Debug, PartialEq)]
+enum Target {
+    // The file is on a writable and persistent filesystem.
+    InPlace,
+    // The file is on an overlay whose upper layer is volatile. The file is
+    // written to the lower layer as well, which is remounted read-write if
+    // needed.
+    Lower {
+        path: PathBuf,
+        remount: Option<PathBuf>,
+    },
+}
+
+#[derive(Clone, Debug, PartialEq)]
+struct MountEntry {
+    mount_point: PathBuf,
+    read_only: bool,
+    fs_type: String,
+    upper_dir: Option<PathBuf>,
+    lower_dir: Option<PathBuf>,
+}
+
+// Writes a configuration file so that it persists across reboots, even if
+// the root filesystem is mounted read-only with an overlay for /etc. The
+// directory of the file is created if it does not exist.
+//
+// # Errors
+//
+// * the file is on a read-only filesystem without a persistent layer
+// * fail to remount the persistent layer read-write
+// * fail to write the file
+pub(crate) fn write<C: AsRef<[u8]>>(path: &str, contents: C) -> Result<()> {
+    write_mode(path, contents.as_ref(), None)
+}
+
+// Writes a script run by another program, such as a service, as `write`
+// does, and makes it executable.
+//
+// # Errors
+//
+// * the file is on a read-only filesystem without a persistent layer
+// * fail to remount the persistent layer read-write
+// * fail to write the file or to change its mode
+pub(crate) fn write_executable<C: AsRef<[u8]>>(path: &str, contents: C) -> Result<()> {
+    write_mode(path, contents.as_ref(), Some(EXECUTABLE_MODE))
+}
+
+// Writes a file holding a secret, such as a private key, as `write` does,
+// readable only by root.
+//
+// # Errors
+//
+// * the file is on a read-only filesystem without a persistent layer
+// * fail to remount the persistent layer read-write
+// * fail to write the file or to change its mode
+pub(crate) fn write_secret<C: AsRef<[u8]>>(path: &str, contents: C) -> Result<()> {
+    write_mode(path, contents.as_ref(), Some(SECRET_MODE))
+}
+
+fn write_mode(path: &str, contents: &[u8], mode: Option<u32>) -> Result<()> {
+    match target(Path::new(path))? {
+        Target::InPlace => write_file(Path::new(path), contents, mode)?,
+        Target::Lower {
+            path: lower,
+            remount,
+        } => {
+            if let Some(mount_point) = &remount {
+                remount_fs(mount_point, "rw")?;
+            }
+            let ret = write_file(&lower, contents, mode);
+            if let Some(mount_point) = &remount {
+                remount_fs(mount_point, "ro")?;
+            }
+            ret.map_err(|e| anyhow!("fail to persist {}. {}", lower.display(), e))?;
+            write_file(Path::new(path), contents, mode)?;
+        }
+    }
+    Ok(())
+}
+
+fn write_file(path: &Path, contents: &[u8], mode: Option<u32>) -> io::Result<()> {
+    // Such as the drop-in directory of a unit.
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir)?;
+    }
+    // The mode is set before writing, so that a secret is never readable by
+    // others, even if the file existed with a looser mode.
+    let mut options = fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    if let Some(mode) = mode {
+        options.mode(mode);
+    }
+    let mut file = options.open(path)?;
+    if let Some(mode) = mode {
+        file.set_permissions(fs::Permissions::from_mode(mode))?;
+    }
+    file.write_all(contents)
+}
+
+// Removes a file so that it does not come back after reboot. It is not an
+// error if the file does not exist.
+//
+// # Errors
+//
+// * the file is on a read-only filesystem without a persistent layer
+// * fail to remount the persistent layer read-write
+// * fail to remove the file
+pub(crate) fn remove(path: &str) -> Result<()> {
+    if let Target::Lower {
+        path: lower,
+        remount,
+    } = target(Path::new(path))?
+    {
+        if let Some(mount_point) = &remount {
+            remount_fs(mount_point, "rw")?;
+        }
+        let ret = match fs::remove_file(&lower) {
+            Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e),
+            _ => Ok(()),
+        };
+        if let Some(mount_point) = &remount {
+            remount_fs(mount_point, "ro")?;
+        }
+        ret.map_err(|e| anyhow!("fail to remove {}. {}", lower.display(), e))?;
+    }
+    match fs::remove_file(path) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+// Checks whether a file can be written persistently.
+//
+// # Errors
+//
+// * the file is on a read-only filesystem without a persistent layer
+pub(crate) fn check(path: &str) -> Result<()> {
+    target(Path::new(path)).map(|_| ())
+}
+
+fn target(path: &Path) -> Result<Target> {
+    resolve(&mounts()?, path)
+}
+
+fn resolve(mounts: &[MountEntry], path: &Path) -> Result<Target> {
+    let Some(mount) = find_mount(mounts, path) else {
+        return Ok(Target::InPlace);
+    };
+
+    if mount.fs_type != OVERLAY_FS {
+        if mount.read_only {
+            return Err(anyhow!(
+                "cannot persist {}: {} is mounted read-only",
+                path.display(),
+                mount.mount_point.display()
+            ));
+        }
+        return Ok(Target::InPlace);
+    }
+
+    let volatile = mount
+        .upper_dir
+        .as_deref()
+        .and_then(|upper| find_mount(mounts, upper))
+        .is_none_or(|upper| VOLATILE_FS.contains(&upper.fs_type.as_str()));
+    if !mount.read_only && !volatile {
+        return Ok(Target::InPlace);
+    }
+
+    let lower = mount.lower_dir.as_deref().ok_or_else(|| {
+        anyhow!(
+            "cannot persist {}: {} is a volatile overlay without a lower layer",
+            path.display(),
+            mount.mount_point.display()
+        )
+    })?;
+    let relative = path.strip_prefix(&mount.mount_point)?;
+    let lower_path = lower.join(relative);
+    let remount = match find_mount(mounts, &lower_path) {
+        Some(m) if m.read_only => Some(m.mount_point.clone()),
+        Some(_) => None,
+        None => {
+            return Err(anyhow!(
+                "cannot persist {}: the lower layer {} is not mounted",
+                path.display(),
+                lower.display()
+            ))
+        }
+    };
+    Ok(Target::Lower {
+        path: lower_path,
+        remount,
+    })
+}
+
+fn remount_fs(mount_point: &Path, mode: &str) -> Result<()> {
+    let output = run_command(
+        command("mount")
+            .args(["-o", &format!("remount,{mode}")])
+            .arg(mount_point),
+    )?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "fail to remount {} {}",
+            mount_point.display(),
+            mode
+        ))
+    }
+}
+
+fn mounts() -> Result<Vec<MountEntry>> {
+    Ok(parse_mountinfo(&fs::read_to_string(MOUNTINFO)?))
+}
+
+// Finds the mount with the longest mount point containing the path.
+fn find_mount<'a>(mounts: &'a [MountEntry], path: &Path) -> Option<&'a MountEntry> {
+    mounts
+        .iter()
+        .filter(|m| path.starts_with(&m.mount_point))
+        .max_by_key(|m| m.mount_point.as_os_str().len())
+}
+
+// Parses /proc/self/mountinfo such as:
+//
+// 36 35 98:0 / /mnt1 rw,noatime master:1 - ext3 /dev/root rw,errors=continue
+fn parse_mountinfo(contents: &str) -> Vec<MountEntry> {
+    let mut mounts = Vec::new();
+    for line in contents.lines() {
+        let Some((mount, fs)) = line.split_once(" - ") else {
+            continue;
+        };
+        let mount = mount.split_whitespace().collect::<Vec<_>>();
+        let fs = fs.split_whitespace().collect::<Vec<_>>();
+        let (Some(mount_point), Some(options), Some(fs_type)) =
+            (mount.get(4), mount.get(5), fs.first())
+        else {
+            continue;
+        };
+        let super_options = fs.get(2).copied().unwrap_or_default();
+        let option = |name: &str| {
+            super_options
+                .split(',')
+                .find_map(|o| o.strip_prefix(name))
+                .map(|v| PathBuf::from(unescape(v)))
+        };
+        mounts.push(MountEntry {
+            mount_point: PathBuf::from(unescape(mount_point)),
+            read_only: options.split(',').any(|o| o == "ro")
+                || super_options.split(',').any(|o| o == "ro"),
+            fs_type: (*fs_type).to_string(),
+            upper_dir: option("upperdir="),
+            // The first one is the top-most lower layer.
+            lower_dir: option("lowerdir=").and_then(|v| {
+                v.to_str()
+                    .and_then(|v| v.split(':').next())
+                    .map(PathBuf::from)
+            }),
+        });
+    }
+    mounts
+}
+
+// Decodes octal escapes such as "\040" for a space.
+pub(crate) fn unescape(s: &str) -> String {
+    let mut decoded = String::new();
+    let mut rest = s;
+    while let Some(pos) = rest.find('\\') {
+        decoded.push_str(&rest[..pos]);
+        let code = rest.get(pos + 1..pos + 4);
+        if let Some(c) = code
+            .and_then(|code| u8::from_str_radix(code, 8).ok())
+            .map(char::from)
+        {
+            decoded.push(c);
+            rest = &rest[pos + 4..];
+        } else {
+            decoded.push('\\');
+            rest = &rest[pos + 1..];
+        }
+    }
+    decoded.push_str(rest);
+    decoded
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MOUNTINFO_OVERLAYROOT: &str = "22 1 8:2 / /media/root-ro ro,relatime - ext4 /dev/sda2 ro
+23 1 0:21 / /media/root-rw rw,relatime - tmpfs tmpfs-root rw
+24 0 0:22 / / rw,relatime - overlay overlayroot rw,lowerdir=/media/root-ro,upperdir=/media/root-rw/overlay,workdir=/media/root-rw/overlay-workdir/_
+25 24 8:3 / /data rw,relatime - ext4 /dev/sda3 rw
+26 24 8:4 / /mnt/my\\040disk ro,relatime - ext4 /dev/sda4 ro
+";
+
+    #[test]
+    fn mountinfo() {
+        let mounts = parse_mountinfo(MOUNTINFO_OVERLAYROOT);
+        assert_eq!(mounts.len(), 5);
+        let root = find_mount(&mounts, Path::new("/etc/ntp.conf")).unwrap();
+        assert_eq!(root.fs_type, "overlay");
+        assert_eq!(root.lower_dir.as_deref(), Some(Path::new("/media/root-ro")));
+        assert_eq!(
+            root.upper_dir.as_deref(),
+            Some(Path::new("/media/root-rw/overlay"))
+        );
+        let data = find_mount(&mounts, Path::new("/data/logs")).unwrap();
+        assert_eq!(data.fs_type, "ext4");
+        assert!(!data.read_only);
+        assert_eq!(mounts[4].mount_point, Path::new("/mnt/my disk"));
+        assert!(mounts[4].read_only);
+    }
+
+    #[test]
+    fn persistent_path() {
+        let mounts = parse_mountinfo(MOUNTINFO_OVERLAYROOT);
+        assert_eq!(
+            resolve(&mounts, Path::new("/etc/ntp.conf")).unwrap(),
+            Target::Lower {
+                path: PathBuf::from("/media/root-ro/etc/ntp.conf"),
+                remount: Some(PathBuf::from("/media/root-ro")),
+            }
+        );
+        assert_eq!(
+            resolve(&mounts, Path::new("/data/logs/apps/roxy.log")).unwrap(),
+            Target::InPlace
+        );
+        assert!(resolve(&mounts, Path::new("/mnt/my disk/conf")).is_err());
+    }
+}
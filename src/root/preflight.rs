@@ -0,0 +1,111 @@
+use std::{
+    fs::{self, OpenOptions},
+    os::unix::fs::{MetadataExt, PermissionsExt},
+    path::Path,
+};
+
+use nix::{
+    sys::statvfs::{statvfs, FsFlags},
+    unistd::geteuid,
+};
+use roxy::common::{PreflightCheck, DEFAULT_PATH_ENV};
+
+use super::{hwinfo, ifconfig, ntp, persist, sshd, syslog, ufw, ROXY_STATE_DIR};
+
+const REQUIRED_BINARIES: [&str; 5] = ["ifconfig", "ip", "netplan", "systemctl", "ufw"];
+
+// Checks the environment without changing the system: required external
+// binaries, write access to the managed configuration, privilege, and PATH.
+#[must_use]
+pub(crate) fn run() -> Vec<PreflightCheck> {
+    let mut ret = Vec::new();
+
+    let euid = geteuid();
+    ret.push(check("privilege", euid.is_root(), format!("euid {euid}")));
+
+    for binary in REQUIRED_BINARIES {
+        let found = DEFAULT_PATH_ENV
+            .split(':')
+            .map(|dir| Path::new(dir).join(binary))
+            .find(|path| {
+                fs::metadata(path).is_ok_and(|m| m.is_file() && m.permissions().mode() & 0o111 != 0)
+            });
+        ret.push(match found {
+            Some(path) => check(
+                &format!("binary {binary}"),
+                true,
+                path.display().to_string(),
+            ),
+            None => check(
+                &format!("binary {binary}"),
+                false,
+                format!("not found in {DEFAULT_PATH_ENV}"),
+            ),
+        });
+    }
+
+    for path in [
+        hwinfo::DEFAULT_VERSION_PATH,
+        ntp::NTP_CONF,
+        sshd::SSHD_CONFIG,
+        syslog::RSYSLOG_CONF,
+        ufw::UFW_CONF,
+    ] {
+        let ret_open = OpenOptions::new().append(true).open(path);
+        ret.push(
+            match ret_open
+                .map_err(Into::into)
+                .and_then(|_| persist::check(path))
+            {
+                Ok(()) => check(&format!("write {path}"), true, "writable".to_string()),
+                Err(e) => check(&format!("write {path}"), false, e.to_string()),
+            },
+        );
+    }
+
+    for dir in [ifconfig::NETPLAN_PATH, ROXY_STATE_DIR] {
+        ret.push(check_dir(dir));
+    }
+
+    for dir in DEFAULT_PATH_ENV.split(':') {
+        let name = format!("path {dir}");
+        ret.push(match fs::metadata(dir) {
+            Ok(m) if m.uid() != 0 => check(&name, false, format!("owned by uid {}", m.uid())),
+            Ok(m) if m.permissions().mode() & 0o002 != 0 => {
+                check(&name, false, "world-writable".to_string())
+            }
+            Ok(_) => check(&name, true, "owned by root".to_string()),
+            Err(e) => check(&name, false, e.to_string()),
+        });
+    }
+
+    ret
+}
+
+// A directory which does not exist yet is fine if its parent is writable,
+// since roxy creates it.
+fn check_dir(dir: &str) -> PreflightCheck {
+    let name = format!("write {dir}");
+    let mut path = Path::new(dir);
+    while !path.exists() {
+        match path.parent() {
+            Some(parent) => path = parent,
+            None => break,
+        }
+    }
+    match statvfs(path) {
+        Ok(stat) if stat.flags().contains(FsFlags::ST_RDONLY) => {
+            check(&name, false, format!("{} is read-only", path.display()))
+        }
+        Ok(_) => check(&name, true, "writable".to_string()),
+        Err(e) => check(&name, false, e.to_string()),
+    }
+}
+
+fn check(name: &str, passed: bool, detail: String) -> PreflightCheck {
+    PreflightCheck {
+        name: name.to_string(),
+        passed,
+        detail,
+    }
+}
@@ -0,0 +1,182 @@
+use std::fs;
+
+use anyhow::Result;
+use roxy::common::{command, run_command, MdArray, RaidStatus, RaidVolume};
+use serde_json::Value;
+
+const MDSTAT: &str = "/proc/mdstat";
+const STORCLI_BINARIES: [&str; 3] = ["storcli64", "storcli", "perccli64"];
+const STORCLI_OPTIMAL: &str = "Optl";
+
+// Gets the status of software RAID arrays, and virtual drives of hardware
+// RAID controllers if storcli is installed.
+//
+// # Errors
+//
+// * fail to read /proc/mdstat other than it does not exist
+pub(crate) fn get() -> Result<RaidStatus> {
+    let arrays = match fs::read_to_string(MDSTAT) {
+        Ok(contents) => parse_mdstat(&contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Vec::new(),
+        Err(e) => return Err(e.into()),
+    };
+
+    let volumes = STORCLI_BINARIES
+        .iter()
+        .find_map(|binary| {
+            let output = run_command(command(binary).args(["/call/vall", "show", "J"])).ok()?;
+            serde_json::from_slice::<Value>(&output.stdout).ok()
+        })
+        .map(|json| parse_storcli(&json))
+        .unwrap_or_default();
+
+    Ok(RaidStatus { arrays, volumes })
+}
+
+// Parses /proc/mdstat such as:
+//
+// md0 : active raid1 sdb1[1] sda1[0](F)
+//       1046528 blocks super 1.2 [2/1] [_U]
+//       [=>...................]  recovery =  8.5% (89088/1046528) finish=0.5min speed=29696K/sec
+fn parse_mdstat(contents: &str) -> Vec<MdArray> {
+    let mut arrays: Vec<MdArray> = Vec::new();
+    for line in contents.lines() {
+        if let Some((name, rest)) = line.split_once(" : ") {
+            if !name.starts_with("md") {
+                continue;
+            }
+            let mut fields = rest.split_whitespace();
+            let mut array = MdArray {
+                name: name.trim().to_string(),
+                state: fields.next().unwrap_or_default().to_string(),
+                ..MdArray::default()
+            };
+            for field in fields {
+                if field.starts_with('(') {
+                    // (auto-read-only) and similar
+                    continue;
+                }
+                if let Some((device, _)) = field.split_once('[') {
+                    array.devices.push(device.to_string());
+                    if field.ends_with("(F)") {
+                        array.failed_devices.push(device.to_string());
+                    }
+                } else if array.level.is_empty() {
+                    array.level = field.to_string();
+                }
+            }
+            arrays.push(array);
+        } else if let Some(array) = arrays.last_mut() {
+            let line = line.trim();
+            if let Some(counts) = line
+                .split_whitespace()
+                .find(|f| f.starts_with('[') && f.contains('/'))
+            {
+                if let Some((total, working)) = counts
+                    .trim_matches(|c| c == '[' || c == ']')
+                    .split_once('/')
+                {
+                    array.total = total.parse().unwrap_or_default();
+                    array.working = working.parse().unwrap_or_default();
+                }
+            } else if let Some(pos) = ["recovery", "resync", "reshape", "check"]
+                .iter()
+                .find_map(|op| line.find(op))
+            {
+                let sync = &line[pos..];
+                array.sync = Some(sync.split('(').next().unwrap_or(sync).trim().to_string());
+            }
+        }
+    }
+
+    for array in &mut arrays {
+        array.degraded = array.state != "active"
+            || array.working < array.total
+            || !array.failed_devices.is_empty();
+    }
+    arrays
+}
+
+// Parses the output of `storcli /call/vall show J`.
+fn parse_storcli(json: &Value) -> Vec<RaidVolume> {
+    let mut volumes = Vec::new();
+    let Some(controllers) = json["Controllers"].as_array() else {
+        return volumes;
+    };
+    for controller in controllers {
+        let id = controller["Command Status"]["Controller"]
+            .as_u64()
+            .and_then(|id| u32::try_from(id).ok())
+            .unwrap_or_default();
+        let Some(drives) = controller["Response Data"]["Virtual Drives"].as_array() else {
+            continue;
+        };
+        for drive in drives {
+            let field = |key: &str| drive[key].as_str().unwrap_or_default().to_string();
+            let state = field("State");
+            volumes.push(RaidVolume {
+                controller: id,
+                id: field("DG/VD"),
+                name: field("Name"),
+                level: field("TYPE"),
+                degraded: state != STORCLI_OPTIMAL,
+                state,
+            });
+        }
+    }
+    volumes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MDSTAT_DEGRADED: &str = "Personalities : [raid1] [raid6] [raid5] [raid4]
+md1 : active raid5 sdf1[3] sde1[1] sdd1[0]
+      2095104 blocks super 1.2 level 5, 512k chunk, algorithm 2 [3/3] [UUU]
+
+md0 : active raid1 sdb1[1] sda1[0](F)
+      1046528 blocks super 1.2 [2/1] [_U]
+      [=>...................]  recovery =  8.5% (89088/1046528) finish=0.5min speed=29696K/sec
+
+unused devices: <none>
+";
+
+    #[test]
+    fn mdstat() {
+        let arrays = parse_mdstat(MDSTAT_DEGRADED);
+        assert_eq!(arrays.len(), 2);
+
+        assert_eq!(arrays[0].name, "md1");
+        assert_eq!(arrays[0].level, "raid5");
+        assert_eq!(arrays[0].devices, ["sdf1", "sde1", "sdd1"]);
+        assert_eq!((arrays[0].total, arrays[0].working), (3, 3));
+        assert!(!arrays[0].degraded);
+
+        assert_eq!(arrays[1].name, "md0");
+        assert_eq!(arrays[1].failed_devices, ["sda1"]);
+        assert_eq!((arrays[1].total, arrays[1].working), (2, 1));
+        assert_eq!(arrays[1].sync.as_deref(), Some("recovery =  8.5%"));
+        assert!(arrays[1].degraded);
+    }
+
+    #[test]
+    fn storcli() {
+        let json = serde_json::json!({
+            "Controllers": [{
+                "Command Status": { "Controller": 0, "Status": "Success" },
+                "Response Data": {
+                    "Virtual Drives": [
+                        { "DG/VD": "0/0", "TYPE": "RAID1", "State": "Optl", "Name": "os" },
+                        { "DG/VD": "1/1", "TYPE": "RAID5", "State": "Dgrd", "Name": "data" }
+                    ]
+                }
+            }]
+        });
+        let volumes = parse_storcli(&json);
+        assert_eq!(volumes.len(), 2);
+        assert!(!volumes[0].degraded);
+        assert_eq!(volumes[1].name, "data");
+        assert!(volumes[1].degraded);
+    }
+}
@@ -0,0 +1,89 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use roxy::common::{StateChange, Subsystem, SystemState};
+use serde::Serialize;
+
+use crate::root::{hook, state};
+
+// An executable run after a task changes the configuration shared by the
+// nodes of an HA pair, with the shared state in JSON as its input, e.g.
+//
+// ```json
+// {"kind":"Ntp::Set","state":{"hostname":null,"interfaces":null,"ntp":[...],...}}
+// ```
+//
+// It sends the state to the peer, which applies it as a replica. It is
+// skipped if it does not exist.
+pub(crate) const REPLICATION_HOOK: &str = "/etc/roxy/replication-hook";
+// Sending the state to the peer may take a while over the network.
+const REPLICATION_TIMEOUT: Duration = Duration::from_secs(30);
+
+// The subsystems shared by the nodes of an HA pair. The hostname and the
+// interfaces identify each node.
+pub(crate) const SHARED_SUBSYSTEMS: [Subsystem; 4] = [
+    Subsystem::Firewall,
+    Subsystem::Ntp,
+    Subsystem::Sshd,
+    Subsystem::Syslog,
+];
+
+#[derive(Serialize)]
+struct Replication<'a> {
+    kind: &'a str,
+    state: SystemState,
+}
+
+// Reads the current state of the system without the fields identifying the
+// node.
+//
+// # Errors
+//
+// * fail to get the current configuration of a subsystem
+pub(crate) fn export() -> Result<SystemState> {
+    Ok(state::export()?.without_identity())
+}
+
+// Applies the state exported by the peer.
+//
+// # Return
+//
+// * the list of changes made to the system
+//
+// # Errors
+//
+// * the state has a field identifying the peer
+// * fail to apply the state
+pub(crate) fn apply(state: &SystemState) -> Result<Vec<StateChange>> {
+    if state.has_identity() {
+        return Err(anyhow!(
+            "a replica should not have a hostname or interfaces"
+        ));
+    }
+    state::apply(state)
+}
+
+// Runs `REPLICATION_HOOK` with the shared state if the task of `kind`
+// changed a shared subsystem.
+//
+// # Errors
+//
+// * the hook is writable by others than root
+// * fail to export the state
+// * the hook fails or times out
+pub(crate) fn replicate(kind: &str, subsystems: &[Subsystem]) -> Result<()> {
+    if !subsystems.iter().any(|s| SHARED_SUBSYSTEMS.contains(s)) {
+        return Ok(());
+    }
+    if !hook::trusted(REPLICATION_HOOK)? {
+        return Ok(());
+    }
+    let input = serde_json::to_vec(&Replication {
+        kind,
+        state: export()?,
+    })?;
+    match hook::run(REPLICATION_HOOK, kind, input, REPLICATION_TIMEOUT)? {
+        (true, _) => Ok(()),
+        (false, output) => Err(anyhow!("{} failed. {}", REPLICATION_HOOK, output)),
+    }
+}
@@ -0,0 +1,167 @@
+use std::{collections::HashMap, fs, io};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{NodeRequest, Role};
+use serde::Deserialize;
+
+use super::{changes, unsupported::kind_name};
+
+pub(crate) const ROLES_CONF: &str = "/etc/roxy/roles.yaml";
+// Subcommands which only read the system.
+const READ_SUBCOMMANDS: [&str; 3] = ["Get", "List", "Status"];
+// Tasks without a subcommand which only read the system.
+const READ_TASKS: [&str; 2] = ["Ping", "Preflight"];
+
+// Roles allowed to the local users, e.g.
+//
+// ```yaml
+// users:
+//   aice: admin
+//   review: operator
+// default: read-only
+// operator_deny:
+//   - Reboot
+//   - Ufw::Disable
+// ```
+#[derive(Debug, Default, Deserialize, PartialEq)]
+#[serde(deny_unknown_fields)]
+struct Policy {
+    // The highest role of each user running roxy.
+    #[serde(default)]
+    users: HashMap<String, Role>,
+    // The highest role of the users not in `users`. Admin if not given.
+    default: Option<Role>,
+    // Tasks denied to operators: a kind such as "Ufw" for all its
+    // subcommands, or a kind with a subcommand such as "Ufw::Disable". A
+    // versioned kind such as "InterfaceV2" is denied with "Interface".
+    #[serde(default)]
+    operator_deny: Vec<String>,
+}
+
+// Checks the role of the request against `ROLES_CONF`. Without
+// `ROLES_CONF`, every user is allowed the admin role.
+//
+// # Errors
+//
+// * the role of the request is higher than the role of the user
+// * the role does not allow the task
+// * fail to read or parse `ROLES_CONF`
+pub(crate) fn authorize(nr: &NodeRequest) -> Result<()> {
+    let policy = read_conf()?;
+    let user = changes::actor();
+    let highest = policy
+        .users
+        .get(&user)
+        .copied()
+        .or(policy.default)
+        .unwrap_or(Role::Admin);
+    let role = nr.role.unwrap_or(highest);
+    if role > highest {
+        return Err(anyhow!("{} is not allowed the {:?} role", user, role));
+    }
+
+    let kind = serde_json::to_value(&nr.kind)
+        .ok()
+        .as_ref()
+        .and_then(kind_name)
+        .ok_or_else(|| anyhow!("invalid task kind"))?;
+    if permits(&policy, role, &kind) {
+        Ok(())
+    } else {
+        Err(anyhow!("{} is not allowed to the {:?} role", kind, role))
+    }
+}
+
+fn permits(policy: &Policy, role: Role, kind: &str) -> bool {
+    match role {
+        Role::Admin => true,
+        Role::Operator => {
+            let unversioned = unversioned(kind);
+            !policy.operator_deny.iter().any(|denied| {
+                [kind, &unversioned].iter().any(|kind| {
+                    *kind == denied
+                        || kind
+                            .strip_prefix(denied.as_str())
+                            .is_some_and(|cmd| cmd.starts_with("::"))
+                })
+            })
+        }
+        Role::ReadOnly => is_read_only(kind),
+    }
+}
+
+// Returns true if the task of the kind, e.g. "Interface::Get", only reads
+// the system.
+pub(crate) fn is_read_only(kind: &str) -> bool {
+    match kind.split_once("::") {
+        Some((_, cmd)) => READ_SUBCOMMANDS.contains(&cmd),
+        None => READ_TASKS.contains(&kind),
+    }
+}
+
+// Returns the kind without the version of its name, e.g. "Interface::Set"
+// for "InterfaceV2::Set", so that denying a kind also denies its versions.
+fn unversioned(kind: &str) -> String {
+    let (name, cmd) = kind
+        .split_once("::")
+        .map_or((kind, None), |(n, c)| (n, Some(c)));
+    let name = name
+        .rsplit_once('V')
+        .filter(|(base, version)| {
+            !base.is_empty() && !version.is_empty() && version.bytes().all(|b| b.is_ascii_digit())
+        })
+        .map_or(name, |(base, _)| base);
+    cmd.map_or_else(|| name.to_string(), |cmd| format!("{name}::{cmd}"))
+}
+
+fn read_conf() -> Result<Policy> {
+    match fs::read_to_string(ROLES_CONF) {
+        Ok(contents) if contents.trim().is_empty() => Ok(Policy::default()),
+        Ok(contents) => serde_yaml::from_str(&contents)
+            .map_err(|e| anyhow!("fail to parse {}. {}", ROLES_CONF, e)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(Policy::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn role_permits() {
+        let policy: Policy = serde_yaml::from_str(
+            "users:\n  review: operator\ndefault: read-only\noperator_deny: [Reboot, Ufw]\n",
+        )
+        .expect("valid policy");
+        assert_eq!(policy.users.get("review"), Some(&Role::Operator));
+        assert_eq!(policy.default, Some(Role::ReadOnly));
+
+        assert!(permits(&policy, Role::ReadOnly, "Interface::Get"));
+        assert!(permits(&policy, Role::ReadOnly, "Ping"));
+        assert!(!permits(&policy, Role::ReadOnly, "Interface::Set"));
+        assert!(!permits(&policy, Role::ReadOnly, "PowerOff"));
+
+        assert!(permits(&policy, Role::Operator, "Interface::Set"));
+        assert!(permits(&policy, Role::Operator, "UfwLog::Set"));
+        assert!(!permits(&policy, Role::Operator, "Reboot"));
+        assert!(!permits(&policy, Role::Operator, "Ufw::Disable"));
+
+        assert!(permits(&policy, Role::Admin, "Reboot"));
+    }
+
+    #[test]
+    fn versioned_kind() {
+        assert_eq!(unversioned("InterfaceV2::Set"), "Interface::Set");
+        assert_eq!(unversioned("StateV2::Get"), "State::Get");
+        assert_eq!(unversioned("Interface::Set"), "Interface::Set");
+        assert_eq!(unversioned("Reboot"), "Reboot");
+
+        let policy: Policy =
+            serde_yaml::from_str("operator_deny: [Interface, State::Set]\n").expect("valid policy");
+        assert!(!permits(&policy, Role::Operator, "InterfaceV2::Set"));
+        assert!(!permits(&policy, Role::Operator, "StateV2::Set"));
+        assert!(permits(&policy, Role::Operator, "StateV2::Get"));
+        assert!(permits(&policy, Role::Operator, "InterfaceGroup::Set"));
+    }
+}
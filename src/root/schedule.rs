@@ -0,0 +1,70 @@
+use std::{
+    collections::BTreeMap,
+    fs::{self, File, OpenOptions},
+    io,
+};
+
+use anyhow::{anyhow, Result};
+use nix::fcntl::{Flock, FlockArg};
+use roxy::common::JobRun;
+
+use super::ROXY_STATE_DIR;
+
+// The last run of each job, keyed by the job name.
+const JOB_RUNS: &str = "job-runs.json";
+const JOB_RUNS_LOCK: &str = "job-runs.lock";
+const MAX_MESSAGE_LEN: usize = 4096;
+
+// Saves the run of a job, replacing its previous run. The runs are locked
+// since the jobs of several schedulers may finish at the same time.
+//
+// # Errors
+//
+// * fail to lock, read, or write the saved runs
+pub(crate) fn record(mut run: JobRun) -> Result<()> {
+    let _lock = lock()?;
+    let mut runs = load()?;
+    if run.message.len() > MAX_MESSAGE_LEN {
+        let mut end = MAX_MESSAGE_LEN;
+        while !run.message.is_char_boundary(end) {
+            end -= 1;
+        }
+        run.message.truncate(end);
+    }
+    runs.insert(run.name.clone(), run);
+    // The runs are renamed into place, so that a reader never sees a
+    // partial file.
+    let path = format!("{ROXY_STATE_DIR}/{JOB_RUNS}");
+    let tmp = format!("{path}.tmp");
+    fs::write(&tmp, serde_json::to_string(&runs)?)?;
+    fs::rename(&tmp, &path)?;
+    Ok(())
+}
+
+// Gets the last run of each job, sorted by the job name.
+//
+// # Errors
+//
+// * fail to read the saved runs
+pub(crate) fn get() -> Result<Vec<JobRun>> {
+    Ok(load()?.into_values().collect())
+}
+
+fn lock() -> Result<Flock<File>> {
+    fs::create_dir_all(ROXY_STATE_DIR)?;
+    let file = OpenOptions::new()
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(format!("{ROXY_STATE_DIR}/{JOB_RUNS_LOCK}"))?;
+    Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, e)| anyhow!("fail to lock the job runs. {}", e))
+}
+
+fn load() -> Result<BTreeMap<String, JobRun>> {
+    match fs::read_to_string(format!("{ROXY_STATE_DIR}/{JOB_RUNS}")) {
+        Ok(contents) => Ok(serde_json::from_str(&contents)?),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(BTreeMap::new()),
+        Err(e) => Err(e.into()),
+    }
+}
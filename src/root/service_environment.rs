@@ -0,0 +1,324 @@
+use std::{collections::BTreeMap, fs, io};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command};
+
+use crate::root::{persist, services};
+
+const SYSTEMD_UNIT_DIR: &str = "/etc/systemd/system";
+const DROP_IN: &str = "override.conf";
+const SERVICE_SECTION: &str = "[Service]";
+const ENVIRONMENT: &str = "Environment=";
+// Variables read by the dynamic loader, glibc, or systemd itself rather than
+// by the service, which could run code of another user in a service running
+// as root.
+const RESERVED_PREFIXES: [&str; 2] = ["LD_", "SYSTEMD_"];
+const RESERVED_NAMES: [&str; 3] = ["GCONV_PATH", "GLIBC_TUNABLES", "LOCPATH"];
+
+// Gets the environment variables set for a service in its drop-in.
+//
+// # Errors
+//
+// * invalid service name
+// * fail to read the drop-in
+pub(crate) fn get(service: &str) -> Result<BTreeMap<String, String>> {
+    let path = drop_in_path(service)?;
+    Ok(environment(&read(&path)?))
+}
+
+// Sets environment variables for a product service, keeping the others, and
+// reloads systemd. The service sees them when it is restarted.
+//
+// # Errors
+//
+// * invalid service name, variable name, or value
+// * the service is not listed in `SERVICES_CONF`
+// * fail to write the drop-in or to reload systemd
+pub(crate) fn set(service: &str, variables: &BTreeMap<String, String>) -> Result<()> {
+    let path = drop_in_path(service)?;
+    check_listed(service)?;
+    for (name, value) in variables {
+        validate(name, value)?;
+    }
+    let contents = read(&path)?;
+    let mut environment = environment(&contents);
+    environment.extend(variables.clone());
+    write(&path, &contents, &environment)
+}
+
+// Unsets environment variables of a product service, and reloads systemd.
+// The drop-in is removed if nothing else is left in it.
+//
+// # Errors
+//
+// * invalid service name
+// * the service is not listed in `SERVICES_CONF`
+// * fail to write or remove the drop-in, or to reload systemd
+pub(crate) fn unset(service: &str, names: &[String]) -> Result<()> {
+    let path = drop_in_path(service)?;
+    check_listed(service)?;
+    let contents = read(&path)?;
+    let mut environment = environment(&contents);
+    let before = environment.len();
+    for name in names {
+        environment.remove(name);
+    }
+    if environment.len() == before {
+        return Ok(());
+    }
+    write(&path, &contents, &environment)
+}
+
+// The drop-in of a service, which is also where `systemctl edit` writes. A
+// unit name without a type is a service.
+fn drop_in_path(service: &str) -> Result<String> {
+    let unit = if service.contains('.') {
+        service.to_string()
+    } else {
+        format!("{service}.service")
+    };
+    let valid = unit.strip_suffix(".service").is_some_and(|name| {
+        !name.is_empty()
+            && !name.starts_with('.')
+            && name
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || "-_.@:\\".contains(c))
+    });
+    if !valid {
+        return Err(anyhow!("invalid service name: {}", service));
+    }
+    Ok(format!("{SYSTEMD_UNIT_DIR}/{unit}.d/{DROP_IN}"))
+}
+
+// Only the environment of the product services is managed, not that of
+// system services such as sshd.
+fn check_listed(service: &str) -> Result<()> {
+    if services::read_conf()?.lists(service) {
+        Ok(())
+    } else {
+        Err(anyhow!(
+            "{} is not listed in {}",
+            service,
+            services::SERVICES_CONF
+        ))
+    }
+}
+
+fn validate(name: &str, value: &str) -> Result<()> {
+    let mut chars = name.chars();
+    let valid_name = chars
+        .next()
+        .is_some_and(|c| c.is_ascii_alphabetic() || c == '_')
+        && chars.all(|c| c.is_ascii_alphanumeric() || c == '_');
+    if !valid_name {
+        return Err(anyhow!("invalid environment variable name: {}", name));
+    }
+    if RESERVED_PREFIXES
+        .iter()
+        .any(|prefix| name.starts_with(prefix))
+        || RESERVED_NAMES.contains(&name)
+    {
+        return Err(anyhow!("{} may not be set", name));
+    }
+    if value.chars().any(char::is_control) {
+        return Err(anyhow!("invalid value of {}", name));
+    }
+    Ok(())
+}
+
+fn read(path: &str) -> Result<String> {
+    match fs::read_to_string(path) {
+        Ok(contents) => Ok(contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(String::new()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn write(path: &str, contents: &str, environment: &BTreeMap<String, String>) -> Result<()> {
+    let contents = with_environment(contents, environment);
+    if contents.trim().is_empty() || contents.trim() == SERVICE_SECTION {
+        persist::remove(path)?;
+    } else {
+        persist::write(path, contents)?;
+    }
+    let output = run_command(command("systemctl").arg("daemon-reload"))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("fail to run systemctl daemon-reload"))
+    }
+}
+
+// The variables set by the `Environment=` lines of the `[Service]` section.
+// A later assignment overrides an earlier one, and an empty `Environment=`
+// clears the ones before it.
+fn environment(contents: &str) -> BTreeMap<String, String> {
+    let mut environment = BTreeMap::new();
+    for line in service_lines(contents) {
+        let Some(assignments) = line.trim().strip_prefix(ENVIRONMENT) else {
+            continue;
+        };
+        if assignments.trim().is_empty() {
+            environment.clear();
+        }
+        for word in split_words(assignments) {
+            if let Some((name, value)) = word.split_once('=') {
+                environment.insert(name.to_string(), value.replace("%%", "%"));
+            }
+        }
+    }
+    environment
+}
+
+// The lines in the `[Service]` section.
+fn service_lines(contents: &str) -> impl Iterator<Item = &str> {
+    let mut in_service = false;
+    contents.lines().filter(move |line| {
+        let line = line.trim();
+        if line.starts_with('[') {
+            in_service = line == SERVICE_SECTION;
+            return false;
+        }
+        in_service
+    })
+}
+
+// Splits the value of `Environment=` into words, as systemd does: words are
+// separated by whitespace, and may be quoted and have escapes.
+fn split_words(s: &str) -> Vec<String> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut quote = None;
+    let mut chars = s.chars();
+    while let Some(c) = chars.next() {
+        match c {
+            '\\' => {
+                if let Some(escaped) = chars.next() {
+                    word.get_or_insert_with(String::new).push(escaped);
+                }
+            }
+            '"' | '\'' if quote.is_none() => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            }
+            c if quote == Some(c) => quote = None,
+            c if c.is_whitespace() && quote.is_none() => words.extend(word.take()),
+            c => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+    words.extend(word);
+    words
+}
+
+// Replaces the `Environment=` lines of the `[Service]` section, keeping the
+// other lines of the drop-in, such as those written by `systemctl edit`.
+fn with_environment(contents: &str, environment: &BTreeMap<String, String>) -> String {
+    let assignments = environment
+        .iter()
+        .map(|(name, value)| {
+            let value = value
+                .replace('\\', "\\\\")
+                .replace('"', "\\\"")
+                .replace('%', "%%");
+            format!("{ENVIRONMENT}\"{name}={value}\"\n")
+        })
+        .collect::<Vec<_>>()
+        .concat();
+
+    let mut lines = Vec::new();
+    let mut in_service = false;
+    let mut inserted = false;
+    for line in contents.lines() {
+        let trimmed = line.trim();
+        if trimmed.starts_with('[') {
+            in_service = trimmed == SERVICE_SECTION;
+        } else if in_service && trimmed.starts_with(ENVIRONMENT) {
+            continue;
+        }
+        lines.push(format!("{line}\n"));
+        if in_service && !inserted {
+            lines.push(assignments.clone());
+            inserted = true;
+        }
+    }
+    if !inserted && !assignments.is_empty() {
+        lines.push(format!("{SERVICE_SECTION}\n{assignments}"));
+    }
+    lines.concat()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drop_in_paths() {
+        assert_eq!(
+            drop_in_path("review").expect("valid name"),
+            "/etc/systemd/system/review.service.d/override.conf"
+        );
+        assert_eq!(
+            drop_in_path("aice-web@1.service").expect("valid name"),
+            "/etc/systemd/system/aice-web@1.service.d/override.conf"
+        );
+        assert!(drop_in_path("../review").is_err());
+        assert!(drop_in_path("review.timer").is_err());
+        assert!(drop_in_path(".service").is_err());
+    }
+
+    #[test]
+    fn variables() {
+        assert!(validate("RUST_LOG", "info,review=debug").is_ok());
+        assert!(validate("1ST", "").is_err());
+        assert!(validate("A-B", "").is_err());
+        assert!(validate("FLAGS", "a\nExecStart=/bin/sh").is_err());
+        assert!(validate("LD_PRELOAD", "/tmp/x.so").is_err());
+        assert!(validate("LD_LIBRARY_PATH", "/tmp").is_err());
+        assert!(validate("GLIBC_TUNABLES", "").is_err());
+        assert!(validate("SYSTEMD_LOG_LEVEL", "debug").is_err());
+        assert!(validate("OLD_LD_PATH", "/tmp").is_ok());
+    }
+
+    #[test]
+    fn parse_environment() {
+        let contents = "[Unit]\nEnvironment=IGNORED=1\n\
+                        [Service]\n\
+                        Environment=A=1 \"B=two words\" C='%%d'\n\
+                        Environment=A=3 D=a\\\"b\n\
+                        LimitNOFILE=65536\n";
+        let environment = environment(contents);
+        assert_eq!(
+            environment,
+            BTreeMap::from([
+                ("A".to_string(), "3".to_string()),
+                ("B".to_string(), "two words".to_string()),
+                ("C".to_string(), "%d".to_string()),
+                ("D".to_string(), "a\"b".to_string()),
+            ])
+        );
+        assert!(super::environment("[Service]\nEnvironment=A=1\nEnvironment=\n").is_empty());
+    }
+
+    #[test]
+    fn round_trip() {
+        let contents = "[Service]\nLimitNOFILE=65536\nEnvironment=OLD=1\n";
+        let environment = BTreeMap::from([
+            ("FLAGS".to_string(), "--a \"b\" 100%".to_string()),
+            ("RUST_LOG".to_string(), "debug".to_string()),
+        ]);
+        let written = with_environment(contents, &environment);
+        assert_eq!(
+            written,
+            "[Service]\n\
+             Environment=\"FLAGS=--a \\\"b\\\" 100%%\"\n\
+             Environment=\"RUST_LOG=debug\"\n\
+             LimitNOFILE=65536\n"
+        );
+        assert_eq!(super::environment(&written), environment);
+
+        let written = with_environment("", &environment);
+        assert!(written.starts_with("[Service]\n"));
+        assert_eq!(super::environment(&written), environment);
+        assert_eq!(with_environment(&written, &BTreeMap::new()), "[Service]\n");
+    }
+}
@@ -1,19 +1,473 @@
+use std::{
+    collections::{BTreeMap, HashMap},
+    fs,
+    net::SocketAddr,
+};
+
 use anyhow::{anyhow, Result};
-use roxy::common::SubCommand;
+use roxy::common::{
+    command, run_command, waitfor_up, Page, PageRequest, ServiceAction, SubCommand, MAX_PAGE_LIMIT,
+};
+use serde::Deserialize;
+
+use super::limits;
+
+// Services run by a container runtime instead of systemd, and compose
+// stacks, e.g.
+//
+// services:
+//   review:
+//     runtime: docker
+//     container: aice-review
+// stacks:
+//   aice:
+//     file: /usr/local/aice/compose/docker-compose.yml
+//     registries: [registry.example.com]
+// startup:
+//   postgres:
+//     ready: 127.0.0.1:5432
+//   review:
+//     after: [postgres]
+//     ready: 127.0.0.1:38390
+//     timeout: 120
+pub(crate) const SERVICES_CONF: &str = "/etc/roxy/services.yaml";
+const MAX_LOG_LINES: u32 = 10_000;
+// How far back the pages of a log can go.
+const MAX_PAGED_LOG_LINES: u64 = 100_000;
+const DEFAULT_READY_TIMEOUT: u64 = 60;
+
+#[derive(Debug, Default, Deserialize, PartialEq)]
+pub(crate) struct ServicesConf {
+    #[serde(default)]
+    services: HashMap<String, Container>,
+    #[serde(default)]
+    pub(crate) stacks: HashMap<String, Stack>,
+    #[serde(default)]
+    startup: BTreeMap<String, Startup>,
+}
 
+// How a service is started by `start_all`: after the services it depends
+// on, and ready once its port accepts a connection.
+#[derive(Debug, Deserialize, PartialEq)]
+struct Startup {
+    #[serde(default)]
+    after: Vec<String>,
+    ready: Option<SocketAddr>,
+    #[serde(default = "default_ready_timeout")]
+    timeout: u64,
+}
+
+fn default_ready_timeout() -> u64 {
+    DEFAULT_READY_TIMEOUT
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+struct Container {
+    runtime: Runtime,
+    container: String,
+}
+
+// A compose project. Images are pulled only from the allowed registries.
+#[derive(Debug, Deserialize, PartialEq)]
+pub(crate) struct Stack {
+    #[serde(default)]
+    pub(crate) runtime: Runtime,
+    pub(crate) file: String,
+    #[serde(default)]
+    pub(crate) registries: Vec<String>,
+}
+
+#[derive(Clone, Copy, Debug, Default, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum Runtime {
+    #[default]
+    Docker,
+    Podman,
+}
+
+impl Runtime {
+    pub(crate) fn program(self) -> &'static str {
+        match self {
+            Runtime::Docker => "docker",
+            Runtime::Podman => "podman",
+        }
+    }
+}
+
+// Stops, (re)starts, or checks a service with the subcommand of earlier
+// versions.
+//
+// # Errors
+//
+// * fail to control the service
+// * invalid command
 pub fn service_control(unit: &str, cmd: SubCommand) -> Result<bool> {
+    let action = match cmd {
+        SubCommand::Disable => ServiceAction::Stop,
+        SubCommand::Enable | SubCommand::Update => ServiceAction::Restart,
+        SubCommand::Status => ServiceAction::Status,
+        _ => return Err(anyhow!("invalid command")),
+    };
+    control(unit, action)
+}
+
+// Runs an action on a service. A service listed in `SERVICES_CONF` is
+// controlled through its container runtime, and the others through systemd.
+//
+// # Return
+//
+// * whether the service is running for `Status`, and whether the action
+//   succeeded for the others
+//
+// # Errors
+//
+// * fail to read `SERVICES_CONF`
+// * fail to run systemctl or the container runtime
+pub(crate) fn control(unit: &str, action: ServiceAction) -> Result<bool> {
+    if let Some(container) = container(unit)? {
+        return container_control(&container, action);
+    }
+
     let systemctl = systemctl::SystemCtl::default();
+    let status = match action {
+        ServiceAction::Start => systemctl.start(unit),
+        ServiceAction::Stop => systemctl.stop(unit),
+        ServiceAction::Restart => systemctl.restart(unit),
+        ServiceAction::Status => return systemctl.is_active(unit).map_err(Into::into),
+        ServiceAction::EnableAtBoot => systemctl.enable(unit),
+        ServiceAction::DisableAtBoot => systemctl.disable(unit),
+    };
+    Ok(status?.success())
+}
+
+// Gets the last lines of the log of a service, from the journal or from
+// its container runtime.
+//
+// # Errors
+//
+// * fail to read `SERVICES_CONF`
+// * fail to run journalctl or the container runtime
+// * journalctl or the container runtime exceeds its memory limit
+pub(crate) fn logs(unit: &str, lines: u32) -> Result<String> {
+    tail(unit, u64::from(lines.min(MAX_LOG_LINES)))
+}
+
+// Gets a page of the log of a service. The offset counts back from the
+// newest line, and the lines in the page are from the oldest.
+//
+// # Errors
+//
+// * fail to read `SERVICES_CONF`
+// * fail to run journalctl or the container runtime
+// * journalctl or the container runtime exceeds its memory limit
+pub(crate) fn log_page(unit: &str, page: PageRequest) -> Result<Page<String>> {
+    let end = page
+        .offset
+        .saturating_add(page.limit.min(MAX_PAGE_LIMIT))
+        .min(MAX_PAGED_LOG_LINES);
+    Ok(page_from_end(&tail(unit, end)?, page.offset, end))
+}
+
+// Takes the lines from `end` back to `offset` back from the last line.
+fn page_from_end(logs: &str, offset: u64, end: u64) -> Page<String> {
+    let lines = logs.lines().collect::<Vec<_>>();
+    let len = lines.len() as u64;
+    let first = usize::try_from(len.saturating_sub(end)).unwrap_or_default();
+    let last = usize::try_from(len.saturating_sub(offset))
+        .unwrap_or_default()
+        .max(first);
+    Page {
+        items: lines[first..last].iter().map(ToString::to_string).collect(),
+        offset,
+        total: None,
+    }
+}
+
+// Gets the last lines of the log of a service.
+fn tail(unit: &str, lines: u64) -> Result<String> {
+    let lines = lines.to_string();
+    let output = if let Some(container) = container(unit)? {
+        limits::run(
+            container.runtime.program(),
+            &["logs", "--tail", &lines, &container.container],
+        )?
+    } else {
+        limits::run("journalctl", &["-u", unit, "-n", &lines, "--no-pager"])?
+    };
+    if !output.status.success() {
+        return Err(anyhow!("fail to get logs of {}", unit));
+    }
+    // A container runtime writes the stderr of the container to its stderr.
+    let mut logs = String::from_utf8_lossy(&output.stdout).into_owned();
+    logs.push_str(&String::from_utf8_lossy(&output.stderr));
+    Ok(logs)
+}
+
+// Starts the services under `startup` in `SERVICES_CONF`, a stage at a time.
+// A stage starts after all the services of the previous stages are ready.
+//
+// # Errors
+//
+// * fail to read `SERVICES_CONF`, or its dependencies have a cycle
+// * a service fails to start or is not ready in time
+pub(crate) fn start_all() -> Result<()> {
+    let startup = read_conf()?.startup;
+    for stage in stages(&startup)? {
+        for unit in &stage {
+            if !control(unit, ServiceAction::Start)? {
+                return Err(anyhow!("fail to start {}", unit));
+            }
+        }
+        for unit in &stage {
+            let Some(addr) = startup[unit].ready else {
+                continue;
+            };
+            let ready = waitfor_up(
+                &addr.ip().to_string(),
+                &addr.port().to_string(),
+                startup[unit].timeout,
+            )?;
+            if !ready {
+                return Err(anyhow!("{} is not ready", unit));
+            }
+        }
+    }
+    Ok(())
+}
+
+// Stops the services under `startup` in `SERVICES_CONF` in the reverse
+// order of `start_all`. It tries to stop all the services even if some of
+// them fail to stop.
+//
+// # Errors
+//
+// * fail to read `SERVICES_CONF`, or its dependencies have a cycle
+// * a service fails to stop
+pub(crate) fn stop_all() -> Result<()> {
+    let startup = read_conf()?.startup;
+    let stages = stages(&startup)?;
+    let mut failed = Vec::new();
+    for unit in stages.iter().rev().flatten() {
+        if !matches!(control(unit, ServiceAction::Stop), Ok(true)) {
+            failed.push(unit.as_str());
+        }
+    }
+    if failed.is_empty() {
+        Ok(())
+    } else {
+        Err(anyhow!("fail to stop {}", failed.join(", ")))
+    }
+}
+
+// Stops all the services and starts them again in order.
+//
+// # Errors
+//
+// * fail to stop or start the services
+pub(crate) fn restart_all() -> Result<()> {
+    stop_all()?;
+    start_all()
+}
+
+// Groups the services into stages, each of which depends only on the
+// services of the previous stages.
+fn stages(startup: &BTreeMap<String, Startup>) -> Result<Vec<Vec<String>>> {
+    for (unit, conf) in startup {
+        if let Some(dep) = conf.after.iter().find(|dep| !startup.contains_key(*dep)) {
+            return Err(anyhow!("{} depends on unknown service {}", unit, dep));
+        }
+    }
+    let mut stages: Vec<Vec<String>> = Vec::new();
+    let mut left = startup.keys().collect::<Vec<_>>();
+    while !left.is_empty() {
+        let (stage, rest): (Vec<_>, Vec<_>) = left.into_iter().partition(|unit| {
+            startup[*unit]
+                .after
+                .iter()
+                .all(|dep| stages.iter().flatten().any(|started| started == dep))
+        });
+        if stage.is_empty() {
+            let cycle = rest.iter().map(|unit| unit.as_str()).collect::<Vec<_>>();
+            return Err(anyhow!("dependency cycle among {}", cycle.join(", ")));
+        }
+        stages.push(stage.into_iter().cloned().collect());
+        left = rest;
+    }
+    Ok(stages)
+}
+
+fn container(unit: &str) -> Result<Option<Container>> {
+    Ok(read_conf()?.services.remove(unit))
+}
+
+// Reads `SERVICES_CONF`, which is empty if it does not exist.
+//
+// # Errors
+//
+// * fail to read or parse `SERVICES_CONF`
+pub(crate) fn read_conf() -> Result<ServicesConf> {
+    match fs::read_to_string(SERVICES_CONF) {
+        Ok(contents) => parse_conf(&contents),
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => Ok(ServicesConf::default()),
+        Err(e) => Err(e.into()),
+    }
+}
+
+impl ServicesConf {
+    // Whether the service is one of the product services listed under
+    // `services` or `startup`. A unit name may have the `.service` suffix.
+    pub(crate) fn lists(&self, service: &str) -> bool {
+        let name = service.strip_suffix(".service").unwrap_or(service);
+        self.services.contains_key(name) || self.startup.contains_key(name)
+    }
+}
+
+fn parse_conf(contents: &str) -> Result<ServicesConf> {
+    if contents.trim().is_empty() {
+        return Ok(ServicesConf::default());
+    }
+    Ok(serde_yaml::from_str(contents)?)
+}
+
+fn container_control(container: &Container, action: ServiceAction) -> Result<bool> {
+    let program = container.runtime.program();
+    let name = container.container.as_str();
+    let args = match action {
+        ServiceAction::Start => vec!["start", name],
+        ServiceAction::Stop => vec!["stop", name],
+        // `restart` starts a stopped container as well.
+        ServiceAction::Restart => vec!["restart", name],
+        ServiceAction::Status => {
+            let output = run_command(command(program).args([
+                "inspect",
+                "--format",
+                "{{.State.Running}}",
+                name,
+            ]))?;
+            return if output.status.success() {
+                parse_running(&String::from_utf8_lossy(&output.stdout))
+            } else {
+                // The container does not exist.
+                Ok(false)
+            };
+        }
+        // The runtime starts the container at boot by its restart policy.
+        ServiceAction::EnableAtBoot => vec!["update", "--restart", "unless-stopped", name],
+        ServiceAction::DisableAtBoot => vec!["update", "--restart", "no", name],
+    };
+    Ok(run_command(command(program).args(args))?.status.success())
+}
+
+fn parse_running(output: &str) -> Result<bool> {
+    match output.trim() {
+        "true" => Ok(true),
+        "false" => Ok(false),
+        other => Err(anyhow!("unexpected state: {}", other)),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn conf() {
+        let conf = parse_conf(
+            "services:
+  review:
+    runtime: docker
+    container: aice-review
+  crusher:
+    runtime: podman
+    container: crusher
+",
+        )
+        .expect("valid conf");
+        assert_eq!(conf.services.len(), 2);
+        assert_eq!(
+            conf.services["review"],
+            Container {
+                runtime: Runtime::Docker,
+                container: "aice-review".to_string(),
+            }
+        );
+        assert_eq!(conf.services["crusher"].runtime, Runtime::Podman);
+        assert!(conf.stacks.is_empty());
+        assert!(conf.lists("review"));
+        assert!(conf.lists("crusher.service"));
+        assert!(!conf.lists("sshd"));
+
+        let conf = parse_conf(
+            "stacks:
+  aice:
+    file: /usr/local/aice/compose/docker-compose.yml
+    registries: [registry.example.com]
+",
+        )
+        .expect("valid conf");
+        assert_eq!(
+            conf.stacks["aice"],
+            Stack {
+                runtime: Runtime::Docker,
+                file: "/usr/local/aice/compose/docker-compose.yml".to_string(),
+                registries: vec!["registry.example.com".to_string()],
+            }
+        );
+        assert_eq!(parse_conf("").expect("empty conf"), ServicesConf::default());
+        assert!(parse_conf("services:\n  review:\n    runtime: lxc\n    container: x\n").is_err());
+    }
+
+    fn startup(conf: &str) -> BTreeMap<String, Startup> {
+        parse_conf(conf).expect("valid conf").startup
+    }
+
+    #[test]
+    fn dependency_stages() {
+        let conf = startup(
+            "startup:
+  review:
+    after: [postgres, redis]
+    ready: 127.0.0.1:38390
+  postgres:
+    ready: 127.0.0.1:5432
+    timeout: 120
+  redis: {}
+  web:
+    after: [review]
+",
+        );
+        assert_eq!(conf["postgres"].timeout, 120);
+        assert_eq!(conf["redis"].timeout, DEFAULT_READY_TIMEOUT);
+        assert_eq!(
+            stages(&conf).expect("acyclic"),
+            vec![
+                vec!["postgres".to_string(), "redis".to_string()],
+                vec!["review".to_string()],
+                vec!["web".to_string()],
+            ]
+        );
+        assert!(stages(&BTreeMap::new()).expect("empty").is_empty());
+
+        let cycle = startup("startup:\n  a:\n    after: [b]\n  b:\n    after: [a]\n  c: {}\n");
+        assert!(stages(&cycle).is_err());
+        let unknown = startup("startup:\n  a:\n    after: [b]\n");
+        assert!(stages(&unknown).is_err());
+    }
+
+    #[test]
+    fn log_pages() {
+        let logs = "1\n2\n3\n4\n5\n";
+        assert_eq!(page_from_end(logs, 0, 2).items, ["4", "5"]);
+        assert_eq!(page_from_end(logs, 2, 4).items, ["2", "3"]);
+        assert_eq!(page_from_end(logs, 4, 6).items, ["1"]);
+        assert!(page_from_end(logs, 6, 8).items.is_empty());
+        assert!(page_from_end(logs, 8, 6).items.is_empty());
+    }
 
-    match cmd {
-        SubCommand::Disable => systemctl
-            .stop(unit)
-            .map(|status| status.success())
-            .map_err(Into::into),
-        SubCommand::Enable | SubCommand::Update => systemctl
-            .restart(unit)
-            .map(|status| status.success())
-            .map_err(Into::into),
-        SubCommand::Status => systemctl.is_active(unit).map_err(Into::into),
-        _ => Err(anyhow!("invalid command")),
+    #[test]
+    fn running() {
+        assert!(parse_running("true\n").expect("state"));
+        assert!(!parse_running("false\n").expect("state"));
+        assert!(parse_running("").is_err());
     }
 }
@@ -0,0 +1,185 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, Write},
+    path::Path,
+};
+
+use anyhow::{anyhow, Result};
+use chrono::Utc;
+use data_encoding::HEXLOWER;
+use nix::{
+    fcntl::{Flock, FlockArg},
+    unistd::mkdtemp,
+};
+use roxy::common::{command, node_id, run_command, NodeRequest, RequestSignature};
+
+use super::{role, unsupported::kind_name, ROXY_STATE_DIR};
+
+// Public key of the Manager in PEM. If it exists, roxy executes a request
+// which changes the system only if the request is signed with the private
+// key of the Manager.
+pub(crate) const REQUEST_KEY: &str = "/etc/roxy/request-signing.pem";
+// How far the time of a signature can be from the clock of roxy. The nonces
+// of signatures are kept for this time, so that a signed request cannot be
+// replayed.
+const MAX_SIGNATURE_AGE: i64 = 300;
+const MAX_NONCE_LEN: usize = 64;
+const NONCE_FILE: &str = "signature-nonces";
+// Tasks which the node runs by itself, e.g. as jobs of a `Scheduler`, with no
+// request of the Manager to sign. They only apply what the Manager has signed
+// before, i.e. the compliance baseline and the queued operations, or record
+// the run of a job.
+const NODE_ORIGINATED: [&str; 3] = ["Compliance::Update", "Maintenance::Update", "Schedule::Set"];
+
+// Verifies the signature of a request with `REQUEST_KEY`. Requests which
+// only read the system, and tasks the node runs by itself, need no
+// signature, and no request needs one without `REQUEST_KEY`.
+//
+// # Errors
+//
+// * the request is not signed, or the signature is too old, for another
+//   node, replayed, or invalid
+// * fail to run openssl or to record the nonce
+pub(crate) fn verify_request(nr: &NodeRequest) -> Result<()> {
+    if !Path::new(REQUEST_KEY).exists() {
+        return Ok(());
+    }
+    let kind = serde_json::to_value(&nr.kind)
+        .ok()
+        .as_ref()
+        .and_then(kind_name)
+        .ok_or_else(|| anyhow!("invalid task kind"))?;
+    if needs_no_signature(&kind) {
+        return Ok(());
+    }
+
+    let Some(signature) = &nr.signature else {
+        return Err(anyhow!("{} is not signed", kind));
+    };
+    if (Utc::now().timestamp() - signature.signed_at).abs() > MAX_SIGNATURE_AGE {
+        return Err(anyhow!("the signature of {} has expired", kind));
+    }
+    if signature.nonce.is_empty() || signature.nonce.len() > MAX_NONCE_LEN {
+        return Err(anyhow!("invalid nonce in the signature of {}", kind));
+    }
+    if signature.node != node_id()? {
+        return Err(anyhow!("{} is signed for another node", kind));
+    }
+    let payload = nr.signed_payload(signature.signed_at, &signature.nonce, &signature.node)?;
+    if !verify(REQUEST_KEY, &payload, &signature.signature)? {
+        return Err(anyhow!("invalid signature of {}", kind));
+    }
+    if record_nonce(signature)? {
+        Ok(())
+    } else {
+        Err(anyhow!("the signature of {} is replayed", kind))
+    }
+}
+
+// Returns true if the task of the kind runs without a signature.
+fn needs_no_signature(kind: &str) -> bool {
+    role::is_read_only(kind) || NODE_ORIGINATED.contains(&kind)
+}
+
+// Records the nonce of a verified signature, dropping the nonces too old to
+// be accepted again. Returns false if the nonce is already recorded. The
+// file is locked since several roxy processes may run at the same time.
+//
+// # Errors
+//
+// * fail to lock, read, or write the nonce file
+fn record_nonce(signature: &RequestSignature) -> Result<bool> {
+    let mut file = lock()?;
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    let oldest = Utc::now().timestamp() - MAX_SIGNATURE_AGE;
+    let nonce = HEXLOWER.encode(&signature.nonce);
+    let mut kept = Vec::new();
+    for line in contents.lines() {
+        let Some((signed_at, seen)) = line
+            .split_once(' ')
+            .and_then(|(t, n)| t.parse::<i64>().ok().map(|t| (t, n)))
+        else {
+            continue;
+        };
+        if seen == nonce {
+            return Ok(false);
+        }
+        if signed_at >= oldest {
+            kept.push(line);
+        }
+    }
+    let entry = format!("{} {}", signature.signed_at, nonce);
+    kept.push(&entry);
+
+    file.rewind()?;
+    file.set_len(0)?;
+    file.write_all(format!("{}\n", kept.join("\n")).as_bytes())?;
+    Ok(true)
+}
+
+fn lock() -> Result<Flock<File>> {
+    fs::create_dir_all(ROXY_STATE_DIR)?;
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(format!("{ROXY_STATE_DIR}/{NONCE_FILE}"))?;
+    Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, e)| anyhow!("fail to lock {}. {}", NONCE_FILE, e))
+}
+
+// Verifies the SHA-256 signature of the payload with a public key. The
+// payload and the signature are written in a new directory under the state
+// directory, which only root can write to, since openssl reads them from
+// files.
+//
+// # Errors
+//
+// * fail to write the files or to run openssl
+pub(crate) fn verify(key: &str, payload: &[u8], signature: &[u8]) -> Result<bool> {
+    fs::create_dir_all(ROXY_STATE_DIR)?;
+    // Other threads or roxy processes may be verifying at the same time.
+    let dir = mkdtemp(format!("{ROXY_STATE_DIR}/verify-XXXXXX").as_str())?;
+    let payload_path = dir.join("payload");
+    let signature_path = dir.join("signature");
+    let ret = fs::write(&payload_path, payload)
+        .and_then(|()| fs::write(&signature_path, signature))
+        .and_then(|()| {
+            run_command(
+                command("openssl")
+                    .args(["dgst", "-sha256", "-verify", key, "-signature"])
+                    .arg(&signature_path)
+                    .arg(&payload_path),
+            )
+        });
+    let _ = fs::remove_dir_all(&dir);
+    Ok(ret?.status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::needs_no_signature;
+
+    #[test]
+    fn node_originated_tasks() {
+        for kind in [
+            "Compliance::Update",
+            "Maintenance::Update",
+            "Schedule::Set",
+            "Ntp::Get",
+        ] {
+            assert!(needs_no_signature(kind), "{kind}");
+        }
+        for kind in [
+            "Compliance::Set",
+            "Maintenance::Set",
+            "Schedule::Delete",
+            "Ntp::Set",
+            "Reboot",
+        ] {
+            assert!(!needs_no_signature(kind), "{kind}");
+        }
+    }
+}
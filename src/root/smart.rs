@@ -0,0 +1,135 @@
+use anyhow::{anyhow, Result};
+use roxy::common::{DiskHealth, SmartAttribute};
+use serde_json::Value;
+
+use super::limits;
+
+// Gets SMART health of the disks found by `smartctl --scan`.
+//
+// # Errors
+//
+// * fail to execute smartctl
+// * smartctl exceeds its memory limit
+pub(crate) fn get() -> Result<Vec<DiskHealth>> {
+    let scan = smartctl(&["--scan", "--json"])?;
+    let Some(devices) = scan["devices"].as_array() else {
+        return Ok(Vec::new());
+    };
+
+    let mut disks = Vec::new();
+    for device in devices {
+        let Some(name) = device["name"].as_str() else {
+            continue;
+        };
+        let mut args = vec!["--health", "--attributes", "--json"];
+        if let Some(kind) = device["type"].as_str() {
+            args.extend(["--device", kind]);
+        }
+        args.push(name);
+        disks.push(parse_smartctl(name, &smartctl(&args)?));
+    }
+    Ok(disks)
+}
+
+// smartctl sets bits of the exit status for disk problems as well, so the
+// output is parsed unless it is not JSON.
+fn smartctl(args: &[&str]) -> Result<Value> {
+    let output = limits::run("smartctl", args)?;
+    serde_json::from_slice(&output.stdout).map_err(|e| anyhow!("fail to parse smartctl. {}", e))
+}
+
+// Parses the output of `smartctl --health --attributes --json`. An ATA
+// attribute is failing if it has ever failed or reached its threshold. For
+// NVMe disks, the critical warning is reported as an attribute.
+fn parse_smartctl(device: &str, json: &Value) -> DiskHealth {
+    let u32_of = |v: &Value| {
+        v.as_u64()
+            .and_then(|n| u32::try_from(n).ok())
+            .unwrap_or_default()
+    };
+
+    let mut failing_attributes = Vec::new();
+    if let Some(table) = json["ata_smart_attributes"]["table"].as_array() {
+        for attr in table {
+            let value = u32_of(&attr["value"]);
+            let threshold = u32_of(&attr["thresh"]);
+            let when_failed = attr["when_failed"].as_str().unwrap_or_default();
+            if !when_failed.is_empty() || (threshold > 0 && value <= threshold) {
+                failing_attributes.push(SmartAttribute {
+                    id: u32_of(&attr["id"]),
+                    name: attr["name"].as_str().unwrap_or_default().to_string(),
+                    value,
+                    threshold,
+                    raw: attr["raw"]["string"]
+                        .as_str()
+                        .unwrap_or_default()
+                        .to_string(),
+                });
+            }
+        }
+    }
+    let critical_warning = u32_of(&json["nvme_smart_health_information_log"]["critical_warning"]);
+    if critical_warning != 0 {
+        failing_attributes.push(SmartAttribute {
+            id: 0,
+            name: "Critical_Warning".to_string(),
+            value: critical_warning,
+            threshold: 0,
+            raw: format!("{critical_warning:#04x}"),
+        });
+    }
+
+    DiskHealth {
+        device: device.to_string(),
+        model: json["model_name"].as_str().unwrap_or_default().to_string(),
+        passed: json["smart_status"]["passed"].as_bool().unwrap_or(true),
+        failing_attributes,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn failing_ata_attributes() {
+        let json = serde_json::json!({
+            "model_name": "ST4000NM0035",
+            "smart_status": { "passed": true },
+            "ata_smart_attributes": {
+                "table": [
+                    { "id": 1, "name": "Raw_Read_Error_Rate", "value": 82, "worst": 64,
+                      "thresh": 44, "when_failed": "", "raw": { "value": 0, "string": "0" } },
+                    { "id": 5, "name": "Reallocated_Sector_Ct", "value": 8, "worst": 8,
+                      "thresh": 10, "when_failed": "now", "raw": { "value": 3912, "string": "3912" } },
+                    { "id": 194, "name": "Temperature_Celsius", "value": 35, "worst": 45,
+                      "thresh": 0, "when_failed": "", "raw": { "value": 35, "string": "35" } }
+                ]
+            }
+        });
+        let disk = parse_smartctl("/dev/sda", &json);
+        assert!(disk.passed);
+        assert!(disk.is_failing());
+        assert_eq!(disk.failing_attributes.len(), 1);
+        assert_eq!(disk.failing_attributes[0].name, "Reallocated_Sector_Ct");
+        assert_eq!(disk.failing_attributes[0].raw, "3912");
+    }
+
+    #[test]
+    fn nvme_critical_warning() {
+        let json = serde_json::json!({
+            "model_name": "Samsung SSD 970 EVO",
+            "smart_status": { "passed": true },
+            "nvme_smart_health_information_log": { "critical_warning": 0 }
+        });
+        assert!(!parse_smartctl("/dev/nvme0", &json).is_failing());
+
+        let json = serde_json::json!({
+            "smart_status": { "passed": false },
+            "nvme_smart_health_information_log": { "critical_warning": 4 }
+        });
+        let disk = parse_smartctl("/dev/nvme0", &json);
+        assert!(!disk.passed);
+        assert_eq!(disk.failing_attributes[0].raw, "0x04");
+    }
+}
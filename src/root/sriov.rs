@@ -0,0 +1,365 @@
+use std::{collections::BTreeMap, fs};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command, SriovStatus, VfConfig, VirtualFunction};
+
+use super::capture::{validate, validate_name};
+use crate::root::persist;
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const RULES_DIR: &str = "/etc/udev/rules.d";
+const MAX_VLAN: u16 = 4094;
+
+// VFs enabled at boot and their settings, kept in a udev rule of the PF.
+#[derive(Debug, Default, PartialEq)]
+struct Rules {
+    num_vfs: Option<u32>,
+    vfs: BTreeMap<u32, VfConfig>,
+}
+
+// Enables the given number of VFs of a PF, and writes a udev rule which
+// enables them again at boot. The settings of the VFs that remain are
+// applied again since the driver resets them.
+//
+// # Errors
+//
+// * invalid or unknown interface, or the NIC does not support SR-IOV
+// * the NIC supports fewer VFs
+// * fail to write `sriov_numvfs` or the udev rule
+pub(crate) fn set(ifname: &str, num_vfs: u32) -> Result<()> {
+    validate(ifname)?;
+    let total_vfs = read_attr(ifname, "sriov_totalvfs")?;
+    if num_vfs > total_vfs {
+        return Err(anyhow!("{} supports up to {} VFs", ifname, total_vfs));
+    }
+    let current = read_attr(ifname, "sriov_numvfs")?;
+    let mut rules = read_rules(ifname);
+    rules.num_vfs = Some(num_vfs);
+    rules.vfs.retain(|index, _| *index < num_vfs);
+    if current != num_vfs {
+        // The kernel refuses to change the number of VFs without disabling
+        // them first.
+        if current != 0 && num_vfs != 0 {
+            write_attr(ifname, "sriov_numvfs", 0)?;
+        }
+        write_attr(ifname, "sriov_numvfs", num_vfs)?;
+        for (index, config) in &rules.vfs {
+            configure_vf(ifname, *index, config)?;
+        }
+    }
+    write_rules(ifname, &rules)
+}
+
+// Sets the MAC address or VLAN of a VF, and records them in the udev rule
+// so that they are set again at boot.
+//
+// # Errors
+//
+// * invalid or unknown interface, or no such VF
+// * invalid MAC address or VLAN
+// * fail to run ip or to write the udev rule
+pub(crate) fn update(ifname: &str, index: u32, config: &VfConfig) -> Result<()> {
+    validate(ifname)?;
+    let num_vfs = read_attr(ifname, "sriov_numvfs")?;
+    if index >= num_vfs {
+        return Err(anyhow!("{} has no VF {}", ifname, index));
+    }
+    if let Some(mac) = &config.mac {
+        validate_mac(mac)?;
+    }
+    if config.vlan.is_some_and(|vlan| vlan > MAX_VLAN) {
+        return Err(anyhow!("invalid VLAN"));
+    }
+    if config.mac.is_none() && config.vlan.is_none() {
+        return Ok(());
+    }
+    configure_vf(ifname, index, config)?;
+
+    let mut rules = read_rules(ifname);
+    let persistent = rules.vfs.entry(index).or_default();
+    if config.mac.is_some() {
+        persistent.mac.clone_from(&config.mac);
+    }
+    if config.vlan.is_some() {
+        persistent.vlan = config.vlan;
+    }
+    write_rules(ifname, &rules)
+}
+
+// Gets the number of VFs and their MAC addresses and VLANs.
+//
+// # Errors
+//
+// * invalid or unknown interface, or the NIC does not support SR-IOV
+// * fail to run ip
+pub(crate) fn get(ifname: &str) -> Result<SriovStatus> {
+    validate(ifname)?;
+    let total_vfs = read_attr(ifname, "sriov_totalvfs")?;
+    let num_vfs = read_attr(ifname, "sriov_numvfs")?;
+    let output = run_command(command("ip").args(["link", "show", "dev", ifname]))?;
+    if !output.status.success() {
+        return Err(anyhow!("fail to get VFs of {}", ifname));
+    }
+    let mut vfs = parse_vfs(&String::from_utf8_lossy(&output.stdout));
+    for vf in &mut vfs {
+        vf.ifname = vf_ifname(ifname, vf.index);
+    }
+
+    Ok(SriovStatus {
+        total_vfs,
+        num_vfs,
+        persistent_vfs: read_rules(ifname).num_vfs,
+        vfs,
+    })
+}
+
+// Removes the udev rule of a PF. The running VFs are left as they are until
+// reboot.
+//
+// # Errors
+//
+// * invalid interface name
+// * fail to remove the udev rule
+pub(crate) fn delete(ifname: &str) -> Result<()> {
+    validate_name(ifname)?;
+    persist::remove(&rules_path(ifname))
+}
+
+fn read_attr(ifname: &str, name: &str) -> Result<u32> {
+    let path = format!("{SYS_CLASS_NET}/{ifname}/device/{name}");
+    let value =
+        fs::read_to_string(path).map_err(|_| anyhow!("{} does not support SR-IOV", ifname))?;
+    Ok(value.trim().parse()?)
+}
+
+fn write_attr(ifname: &str, name: &str, value: u32) -> Result<()> {
+    let path = format!("{SYS_CLASS_NET}/{ifname}/device/{name}");
+    fs::write(path, value.to_string())
+        .map_err(|e| anyhow!("fail to set {} of {}: {}", name, ifname, e))
+}
+
+fn configure_vf(ifname: &str, index: u32, config: &VfConfig) -> Result<()> {
+    let args = vf_args(index, config);
+    let output = run_command(
+        command("ip")
+            .args(["link", "set", "dev", ifname])
+            .args(&args),
+    )?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("fail to set VF {} of {}", index, ifname))
+    }
+}
+
+// Arguments of `ip link set dev <PF>` for a VF, e.g. ["vf", "0", "vlan",
+// "100"].
+fn vf_args(index: u32, config: &VfConfig) -> Vec<String> {
+    let mut args = vec!["vf".to_string(), index.to_string()];
+    if let Some(mac) = &config.mac {
+        args.extend(["mac".to_string(), mac.clone()]);
+    }
+    if let Some(vlan) = config.vlan {
+        args.extend(["vlan".to_string(), vlan.to_string()]);
+    }
+    args
+}
+
+// Interface name of a VF bound to a network driver on the host.
+fn vf_ifname(ifname: &str, index: u32) -> Option<String> {
+    let dir = format!("{SYS_CLASS_NET}/{ifname}/device/virtfn{index}/net");
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .find_map(|entry| entry.file_name().into_string().ok())
+}
+
+// Checks a unicast MAC address such as "02:00:00:00:00:01", which goes into
+// the udev rule.
+fn validate_mac(mac: &str) -> Result<()> {
+    let octets = mac
+        .split(':')
+        .map(|octet| {
+            if octet.len() == 2 {
+                u8::from_str_radix(octet, 16).ok()
+            } else {
+                None
+            }
+        })
+        .collect::<Option<Vec<_>>>();
+    match octets {
+        Some(octets) if octets.len() == 6 && octets[0] & 1 == 0 => Ok(()),
+        _ => Err(anyhow!("invalid MAC address: {}", mac)),
+    }
+}
+
+// Parses the VFs in the output of `ip link show`, such as:
+//
+// vf 0     link/ether 02:00:00:00:00:01 brd ff:ff:ff:ff:ff:ff, vlan 100, ...
+//
+// Older versions of ip print "vf 0 MAC 02:00:00:00:00:01, vlan 100, ...".
+fn parse_vfs(output: &str) -> Vec<VirtualFunction> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let tokens = line
+                .split_whitespace()
+                .map(|token| token.trim_end_matches(','))
+                .collect::<Vec<_>>();
+            if tokens.first() != Some(&"vf") {
+                return None;
+            }
+            let index = tokens.get(1)?.parse().ok()?;
+            let value = |key: &str| {
+                tokens
+                    .windows(2)
+                    .find(|pair| pair[0] == key)
+                    .map(|pair| pair[1])
+            };
+            let mac = value("link/ether").or_else(|| value("MAC"))?;
+            let vlan = value("vlan").and_then(|vlan| vlan.parse().ok());
+            Some(VirtualFunction {
+                index,
+                ifname: None,
+                mac: mac.to_string(),
+                vlan: vlan.unwrap_or_default(),
+            })
+        })
+        .collect()
+}
+
+fn rules_path(ifname: &str) -> String {
+    format!("{RULES_DIR}/70-roxy-sriov-{ifname}.rules")
+}
+
+fn read_rules(ifname: &str) -> Rules {
+    fs::read_to_string(rules_path(ifname))
+        .map(|contents| parse_rules(&contents))
+        .unwrap_or_default()
+}
+
+// The rule matches the PF by its PCI address since the interface may not
+// have its final name yet when the rule runs.
+fn write_rules(ifname: &str, rules: &Rules) -> Result<()> {
+    let device = fs::read_link(format!("{SYS_CLASS_NET}/{ifname}/device"))?;
+    let pci = device
+        .file_name()
+        .and_then(|name| name.to_str())
+        .ok_or_else(|| anyhow!("PCI address of {} not found", ifname))?;
+    persist::write(&rules_path(ifname), format_rules(pci, rules))
+}
+
+fn format_rules(pci: &str, rules: &Rules) -> String {
+    let matches = format!("ACTION==\"add\", SUBSYSTEM==\"net\", KERNELS==\"{pci}\"");
+    let mut lines = vec!["# Written by roxy.".to_string()];
+    if let Some(num_vfs) = rules.num_vfs {
+        lines.push(format!(
+            "{matches}, ATTR{{device/sriov_numvfs}}=\"{num_vfs}\""
+        ));
+    }
+    for (index, config) in &rules.vfs {
+        lines.push(format!(
+            "{matches}, RUN+=\"/sbin/ip link set dev $name {}\"",
+            vf_args(*index, config).join(" ")
+        ));
+    }
+    lines.push(String::new());
+    lines.join("\n")
+}
+
+// Reads the rules written by `format_rules`.
+fn parse_rules(contents: &str) -> Rules {
+    let mut rules = Rules::default();
+    for line in contents.lines() {
+        if let Some((_, value)) = line.split_once("ATTR{device/sriov_numvfs}=\"") {
+            rules.num_vfs = value.trim_end_matches('"').parse().ok();
+        } else if let Some((_, args)) = line.split_once(" $name ") {
+            let args = args
+                .trim_end_matches('"')
+                .split_whitespace()
+                .collect::<Vec<_>>();
+            let Some(index) = args.get(1).and_then(|index| index.parse().ok()) else {
+                continue;
+            };
+            let value = |key: &str| {
+                args.windows(2)
+                    .find(|pair| pair[0] == key)
+                    .map(|pair| pair[1])
+            };
+            rules.vfs.insert(
+                index,
+                VfConfig {
+                    mac: value("mac").map(ToString::to_string),
+                    vlan: value("vlan").and_then(|vlan| vlan.parse().ok()),
+                },
+            );
+        }
+    }
+    rules
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn vfs() {
+        let output = "4: enp3s0f0: <BROADCAST,MULTICAST,UP,LOWER_UP> mtu 1500 qdisc mq state UP
+    link/ether 3c:fd:fe:00:00:01 brd ff:ff:ff:ff:ff:ff
+    vf 0     link/ether 02:00:00:00:00:01 brd ff:ff:ff:ff:ff:ff, vlan 100, spoof checking on, link-state auto, trust off
+    vf 1     link/ether 00:00:00:00:00:00 brd ff:ff:ff:ff:ff:ff, spoof checking on, link-state auto, trust off
+    vf 2 MAC 02:00:00:00:00:03, vlan 200, spoof checking on, link-state auto
+";
+        let vfs = parse_vfs(output);
+        assert_eq!(vfs.len(), 3);
+        assert_eq!(
+            (vfs[0].index, vfs[0].mac.as_str()),
+            (0, "02:00:00:00:00:01")
+        );
+        assert_eq!(vfs[0].vlan, 100);
+        assert_eq!((vfs[1].index, vfs[1].vlan), (1, 0));
+        assert_eq!(
+            (vfs[2].mac.as_str(), vfs[2].vlan),
+            ("02:00:00:00:00:03", 200)
+        );
+    }
+
+    #[test]
+    fn rules_round_trip() {
+        let mut rules = Rules {
+            num_vfs: Some(4),
+            ..Rules::default()
+        };
+        rules.vfs.insert(
+            0,
+            VfConfig {
+                mac: Some("02:00:00:00:00:01".to_string()),
+                vlan: Some(100),
+            },
+        );
+        rules.vfs.insert(
+            2,
+            VfConfig {
+                mac: None,
+                vlan: Some(200),
+            },
+        );
+        let contents = format_rules("0000:03:00.0", &rules);
+        assert!(contents.contains("KERNELS==\"0000:03:00.0\", ATTR{device/sriov_numvfs}=\"4\"\n"));
+        assert!(contents.contains(
+            "RUN+=\"/sbin/ip link set dev $name vf 0 mac 02:00:00:00:00:01 vlan 100\"\n"
+        ));
+        assert_eq!(parse_rules(&contents), rules);
+        assert_eq!(parse_rules(""), Rules::default());
+    }
+
+    #[test]
+    fn mac() {
+        assert!(validate_mac("02:00:00:00:00:01").is_ok());
+        assert!(validate_mac("3C:FD:FE:00:00:01").is_ok());
+        assert!(validate_mac("01:00:5e:00:00:01").is_err());
+        assert!(validate_mac("02:00:00:00:00").is_err());
+        assert!(validate_mac("02:00:00:00:00:1").is_err());
+        assert!(validate_mac("02:00:00:00:00:01\" RUN+=\"x").is_err());
+    }
+}
@@ -1,12 +1,10 @@
-use std::{
-    fmt::Write as FmtWrite,
-    fs::{self, OpenOptions},
-    io::Write as IoWrite,
-};
+use std::{fmt::Write as FmtWrite, fs};
 
 use anyhow::Result;
 
-const SSHD_CONFIG: &str = "/etc/ssh/sshd_config";
+use crate::root::persist;
+
+pub(crate) const SSHD_CONFIG: &str = "/etc/ssh/sshd_config";
 const SSHD_DEFAULT_PORT: u16 = 22;
 const SSHD_SERVICE_UNIT: &str = "sshd";
 
@@ -37,12 +35,7 @@ pub(crate) fn set(port: &str) -> Result<bool> {
 
     writeln!(new_contents, "Port {port}").expect("writing to string should not fail");
 
-    let mut file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(SSHD_CONFIG)?;
-
-    file.write_all(new_contents.as_bytes())?;
+    persist::write(SSHD_CONFIG, new_contents)?;
     let systemctl = systemctl::SystemCtl::default();
 
     systemctl
@@ -51,6 +44,32 @@ pub(crate) fn set(port: &str) -> Result<bool> {
         .map_err(Into::into)
 }
 
+// Sets the file shown before authentication, or removes the banner if `None`.
+//
+// # Errors
+//
+// * fail to open ``/etc/ssh/sshd_config``
+// * fail to write modified contents to ``/etc/ssh/sshd_config``
+// * fail to restart sshd service
+pub(crate) fn set_banner(path: Option<&str>) -> Result<bool> {
+    let contents = fs::read_to_string(SSHD_CONFIG)?;
+    let lines = contents.lines();
+    let mut new_contents = String::new();
+    for line in lines {
+        if !line.starts_with("Banner ") {
+            new_contents.push_str(line);
+            new_contents.push('\n');
+        }
+    }
+
+    if let Some(path) = path {
+        writeln!(new_contents, "Banner {path}").expect("writing to string should not fail");
+    }
+
+    persist::write(SSHD_CONFIG, new_contents)?;
+    start()
+}
+
 // Gets sshd port number
 //
 // # Errors
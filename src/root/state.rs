@@ -0,0 +1,479 @@
+use std::{fs, io};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{
+    DriftItem, FirewallState, NicOutput, SshdState, StateChange, Subsystem, SystemState,
+};
+
+use crate::root::{
+    fqdn, ifconfig, ntp, sshd, syslog, transaction::Checkpoint, ufw, ROXY_STATE_DIR,
+};
+
+// The last state applied by apply(), against which drift() compares the
+// system. Only the subsystems set in an applied state are kept.
+const DESIRED_STATE: &str = "desired-state.yaml";
+
+// Converges the system to the desired state. Subsystems which are not
+// specified in the state are left untouched. If it fails to apply any
+// subsystem, all changes are rolled back.
+//
+// # Return
+//
+// * the list of changes made to the system
+//
+// # Errors
+//
+// * fail to save or restore the current configuration
+// * fail to get the current configuration of a subsystem
+// * fail to apply the desired configuration of a subsystem
+pub(crate) fn apply(state: &SystemState) -> Result<Vec<StateChange>> {
+    let checkpoint = Checkpoint::new()?;
+    match converge(state) {
+        Ok(changes) => {
+            let mut desired = load_desired()?.unwrap_or_default();
+            merge(&mut desired, state.clone());
+            save_desired(&desired)?;
+            Ok(changes)
+        }
+        Err(e) => {
+            checkpoint.restore()?;
+            Err(e)
+        }
+    }
+}
+
+// Reads the current state of the system in the same form used by apply().
+//
+// # Errors
+//
+// * fail to get the current configuration of a subsystem
+pub(crate) fn export() -> Result<SystemState> {
+    let interfaces = ifconfig::get(None)?
+        .unwrap_or_default()
+        .into_iter()
+        .collect();
+
+    Ok(SystemState {
+        hostname: Some(fqdn::get()?.fqdn),
+        interfaces: Some(interfaces),
+        ntp: Some(ntp::get()?.unwrap_or_default()),
+        syslog: Some(syslog::get_addrs()?),
+        firewall: Some(FirewallState {
+            enabled: ufw::is_active()?,
+            rules: ufw::get()?.unwrap_or_default(),
+        }),
+        sshd: Some(SshdState { port: sshd::get()? }),
+    })
+}
+
+// Compares the system with the last state applied by apply().
+//
+// # Return
+//
+// * the differences, or nothing if no state has been applied
+//
+// # Errors
+//
+// * fail to read the applied state
+// * fail to get the current configuration of a subsystem
+pub(crate) fn drift() -> Result<Vec<DriftItem>> {
+    let Some(desired) = load_desired()? else {
+        return Ok(Vec::new());
+    };
+    check(&desired)
+}
+
+// Compares the system with a state, e.g. a compliance baseline, without
+// applying it.
+//
+// # Errors
+//
+// * fail to get the current configuration of a subsystem
+pub(crate) fn check(desired: &SystemState) -> Result<Vec<DriftItem>> {
+    Ok(compare(desired, &current_of(desired)?))
+}
+
+// Updates the applied state with the current configuration of the
+// subsystems changed by roxy, so that they are not reported as drift. The
+// subsystems not in the applied state are left out.
+//
+// # Errors
+//
+// * fail to read or write the applied state
+// * fail to get the current configuration of a subsystem
+pub(crate) fn refresh(subsystems: &[Subsystem]) -> Result<()> {
+    let Some(mut desired) = load_desired()? else {
+        return Ok(());
+    };
+    let current = current_of(&desired)?;
+    for subsystem in subsystems {
+        match subsystem {
+            Subsystem::Firewall if desired.firewall.is_some() => {
+                desired.firewall.clone_from(&current.firewall);
+            }
+            Subsystem::Hostname if desired.hostname.is_some() => {
+                desired.hostname.clone_from(&current.hostname);
+            }
+            Subsystem::Interface => {
+                if let (Some(desired), Some(current)) =
+                    (&mut desired.interfaces, &current.interfaces)
+                {
+                    for (ifname, nic) in desired.iter_mut() {
+                        if let Some(new) = current.get(ifname) {
+                            nic.clone_from(new);
+                        }
+                    }
+                }
+            }
+            Subsystem::Ntp if desired.ntp.is_some() => desired.ntp.clone_from(&current.ntp),
+            Subsystem::Sshd if desired.sshd.is_some() => desired.sshd.clone_from(&current.sshd),
+            Subsystem::Syslog if desired.syslog.is_some() => {
+                desired.syslog.clone_from(&current.syslog);
+            }
+            _ => {}
+        }
+    }
+    save_desired(&desired)
+}
+
+// Reads the current configuration of the subsystems set in `desired`.
+fn current_of(desired: &SystemState) -> Result<SystemState> {
+    let mut current = SystemState::default();
+    if desired.hostname.is_some() {
+        current.hostname = Some(fqdn::get()?.fqdn);
+    }
+    if desired.interfaces.is_some() {
+        current.interfaces = Some(
+            ifconfig::get(None)?
+                .unwrap_or_default()
+                .into_iter()
+                .collect(),
+        );
+    }
+    if desired.ntp.is_some() {
+        current.ntp = Some(ntp::get()?.unwrap_or_default());
+    }
+    if desired.syslog.is_some() {
+        current.syslog = Some(syslog::get_addrs()?);
+    }
+    if desired.firewall.is_some() {
+        current.firewall = Some(FirewallState {
+            enabled: ufw::is_active()?,
+            rules: ufw::get()?.unwrap_or_default(),
+        });
+    }
+    if desired.sshd.is_some() {
+        current.sshd = Some(SshdState { port: sshd::get()? });
+    }
+    Ok(current)
+}
+
+fn load_desired() -> Result<Option<SystemState>> {
+    match fs::read_to_string(format!("{ROXY_STATE_DIR}/{DESIRED_STATE}")) {
+        Ok(contents) => Ok(Some(serde_yaml::from_str(&contents)?)),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        Err(e) => Err(e.into()),
+    }
+}
+
+fn save_desired(state: &SystemState) -> Result<()> {
+    fs::create_dir_all(ROXY_STATE_DIR)?;
+    fs::write(
+        format!("{ROXY_STATE_DIR}/{DESIRED_STATE}"),
+        state.to_string(),
+    )?;
+    Ok(())
+}
+
+// Sets the subsystems of `state` to `desired`, and adds its interfaces to
+// those of `desired`.
+fn merge(desired: &mut SystemState, state: SystemState) {
+    if state.hostname.is_some() {
+        desired.hostname = state.hostname;
+    }
+    if let Some(interfaces) = state.interfaces {
+        desired
+            .interfaces
+            .get_or_insert_with(Default::default)
+            .extend(interfaces);
+    }
+    if state.ntp.is_some() {
+        desired.ntp = state.ntp;
+    }
+    if state.syslog.is_some() {
+        desired.syslog = state.syslog;
+    }
+    if state.firewall.is_some() {
+        desired.firewall = state.firewall;
+    }
+    if state.sshd.is_some() {
+        desired.sshd = state.sshd;
+    }
+}
+
+fn compare(desired: &SystemState, current: &SystemState) -> Vec<DriftItem> {
+    let mut ret = Vec::new();
+    let mut check = |subsystem, target: Option<&str>, desired: String, current: String| {
+        if desired != current {
+            ret.push(DriftItem {
+                subsystem,
+                target: target.map(ToString::to_string),
+                desired,
+                current,
+            });
+        }
+    };
+
+    if let Some(hostname) = &desired.hostname {
+        let current = current.hostname.clone().unwrap_or_default();
+        check(Subsystem::Hostname, None, hostname.clone(), current);
+    }
+    if let Some(interfaces) = &desired.interfaces {
+        for (ifname, nic) in interfaces {
+            let current = current
+                .interfaces
+                .as_ref()
+                .and_then(|nics| nics.get(ifname));
+            check(
+                Subsystem::Interface,
+                Some(ifname),
+                nic_to_string(nic),
+                current.map_or_else(|| "-".to_string(), nic_to_string),
+            );
+        }
+    }
+    if let Some(ntp) = &desired.ntp {
+        let current = current.ntp.clone().unwrap_or_default();
+        check(Subsystem::Ntp, None, join(ntp), join(&current));
+    }
+    if let Some(syslog) = &desired.syslog {
+        let current = current.syslog.clone().unwrap_or_default();
+        check(Subsystem::Syslog, None, join(syslog), join(&current));
+    }
+    if let Some(firewall) = &desired.firewall {
+        let current = current.firewall.clone().unwrap_or_default();
+        // ufw may list the rules in another order than they are applied.
+        let sorted = |rules: &[String]| {
+            let mut rules = rules.to_vec();
+            rules.sort();
+            join(&rules)
+        };
+        check(
+            Subsystem::Firewall,
+            Some("rules"),
+            sorted(&firewall.rules),
+            sorted(&current.rules),
+        );
+        check(
+            Subsystem::Firewall,
+            Some("enabled"),
+            firewall.enabled.to_string(),
+            current.enabled.to_string(),
+        );
+    }
+    if let Some(sshd) = &desired.sshd {
+        let current = current
+            .sshd
+            .as_ref()
+            .map_or_else(|| "-".to_string(), |sshd| sshd.port.to_string());
+        check(
+            Subsystem::Sshd,
+            Some("port"),
+            sshd.port.to_string(),
+            current,
+        );
+    }
+    ret
+}
+
+fn converge(state: &SystemState) -> Result<Vec<StateChange>> {
+    let mut changes = Vec::new();
+
+    if let Some(new) = &state.hostname {
+        let old = fqdn::get()?.fqdn;
+        if old != *new {
+            fqdn::set(new)?;
+            changes.push(change(Subsystem::Hostname, None, old, new.clone()));
+        }
+    }
+
+    if let Some(interfaces) = &state.interfaces {
+        for (ifname, new) in interfaces {
+            let old = ifconfig::get(Some(ifname))?
+                .and_then(|nics| nics.into_iter().next())
+                .map(|(_, nic)| nic);
+            if old.as_ref() != Some(new) {
+                ifconfig::set(ifname, new)?;
+                changes.push(change(
+                    Subsystem::Interface,
+                    Some(ifname),
+                    old.as_ref().map_or_else(|| "-".to_string(), nic_to_string),
+                    nic_to_string(new),
+                ));
+            }
+        }
+    }
+
+    if let Some(new) = &state.ntp {
+        let old = ntp::get()?.unwrap_or_default();
+        if old != *new {
+            ntp::set(new)?;
+            changes.push(change(Subsystem::Ntp, None, join(&old), join(new)));
+        }
+    }
+
+    if let Some(new) = &state.syslog {
+        let old = syslog::get_addrs()?;
+        if old != *new {
+            syslog::set(Some(new))?;
+            changes.push(change(Subsystem::Syslog, None, join(&old), join(new)));
+        }
+    }
+
+    if let Some(new) = &state.firewall {
+        let old_rules = ufw::get()?.unwrap_or_default();
+        let added = new
+            .rules
+            .iter()
+            .filter(|rule| !old_rules.contains(rule))
+            .cloned()
+            .collect::<Vec<_>>();
+        let deleted = old_rules
+            .iter()
+            .filter(|rule| !new.rules.contains(rule))
+            .cloned()
+            .collect::<Vec<_>>();
+        if !deleted.is_empty() && !ufw::delete(&deleted)? {
+            return Err(anyhow!("fail to delete ufw rules"));
+        }
+        if !added.is_empty() && !ufw::add(&added)? {
+            return Err(anyhow!("fail to add ufw rules"));
+        }
+        if !added.is_empty() || !deleted.is_empty() {
+            changes.push(change(
+                Subsystem::Firewall,
+                Some("rules"),
+                join(&old_rules),
+                join(&new.rules),
+            ));
+        }
+
+        let old_enabled = ufw::is_active()?;
+        if old_enabled != new.enabled {
+            let ret = if new.enabled {
+                ufw::enable()?
+            } else {
+                ufw::disable()?
+            };
+            if !ret {
+                return Err(anyhow!("fail to change ufw status"));
+            }
+            changes.push(change(
+                Subsystem::Firewall,
+                Some("enabled"),
+                old_enabled.to_string(),
+                new.enabled.to_string(),
+            ));
+        }
+    }
+
+    if let Some(new) = &state.sshd {
+        let old = sshd::get()?;
+        if old != new.port {
+            sshd::set(&new.port.to_string())?;
+            changes.push(change(
+                Subsystem::Sshd,
+                Some("port"),
+                old.to_string(),
+                new.port.to_string(),
+            ));
+        }
+    }
+
+    Ok(changes)
+}
+
+fn change(
+    subsystem: Subsystem,
+    target: Option<&str>,
+    before: String,
+    after: String,
+) -> StateChange {
+    StateChange {
+        subsystem,
+        target: target.map(ToString::to_string),
+        before,
+        after,
+    }
+}
+
+fn join(items: &[String]) -> String {
+    if items.is_empty() {
+        "-".to_string()
+    } else {
+        items.join(", ")
+    }
+}
+
+fn nic_to_string(nic: &NicOutput) -> String {
+    serde_json::to_string(nic).unwrap_or_default()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn drift_items() {
+        let desired = SystemState {
+            hostname: Some("sensor1".to_string()),
+            ntp: Some(vec!["time.example.com".to_string()]),
+            firewall: Some(FirewallState {
+                enabled: true,
+                rules: vec!["allow 22/tcp".to_string(), "allow 443/tcp".to_string()],
+            }),
+            ..SystemState::default()
+        };
+        let mut current = SystemState {
+            hostname: Some("sensor1".to_string()),
+            ntp: Some(vec!["pool.ntp.org".to_string()]),
+            syslog: Some(vec!["@10.0.0.1:514".to_string()]),
+            firewall: Some(FirewallState {
+                enabled: true,
+                rules: vec!["allow 443/tcp".to_string(), "allow 22/tcp".to_string()],
+            }),
+            sshd: Some(SshdState { port: 22 }),
+            ..SystemState::default()
+        };
+        assert_eq!(
+            compare(&desired, &current),
+            vec![DriftItem {
+                subsystem: Subsystem::Ntp,
+                target: None,
+                desired: "time.example.com".to_string(),
+                current: "pool.ntp.org".to_string(),
+            }]
+        );
+
+        current.ntp.clone_from(&desired.ntp);
+        assert!(compare(&desired, &current).is_empty());
+    }
+
+    #[test]
+    fn merge_states() {
+        let mut desired = SystemState {
+            ntp: Some(vec!["time.example.com".to_string()]),
+            sshd: Some(SshdState { port: 22 }),
+            ..SystemState::default()
+        };
+        merge(
+            &mut desired,
+            SystemState {
+                sshd: Some(SshdState { port: 10022 }),
+                ..SystemState::default()
+            },
+        );
+        assert_eq!(desired.ntp, Some(vec!["time.example.com".to_string()]));
+        assert_eq!(desired.sshd, Some(SshdState { port: 10022 }));
+    }
+}
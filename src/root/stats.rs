@@ -0,0 +1,84 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{Read, Seek, Write},
+    time::Duration,
+};
+
+use anyhow::{anyhow, Result};
+use nix::fcntl::{Flock, FlockArg};
+use roxy::common::TaskStats;
+
+use super::ROXY_STATE_DIR;
+
+const STATS_FILE: &str = "stats.json";
+
+// Adds a handled task to the statistics of its kind. The file is locked
+// since several roxy processes may run at the same time.
+//
+// # Errors
+//
+// * fail to lock, read, or write the stats file
+pub(crate) fn record(kind: &str, elapsed: Duration, failed: bool) -> Result<()> {
+    let mut file = lock()?;
+    let mut stats = read(&mut file)?;
+    let elapsed_ms = u64::try_from(elapsed.as_millis()).unwrap_or(u64::MAX);
+    if let Some(entry) = stats.iter_mut().find(|entry| entry.kind == kind) {
+        entry.add(elapsed_ms, failed);
+    } else {
+        let mut entry = TaskStats {
+            kind: kind.to_string(),
+            ..TaskStats::default()
+        };
+        entry.add(elapsed_ms, failed);
+        stats.push(entry);
+        stats.sort_by(|a, b| a.kind.cmp(&b.kind));
+    }
+
+    file.rewind()?;
+    file.set_len(0)?;
+    file.write_all(serde_json::to_string(&stats)?.as_bytes())?;
+    Ok(())
+}
+
+// Gets the statistics of each task kind.
+//
+// # Errors
+//
+// * fail to lock or read the stats file
+pub(crate) fn get() -> Result<Vec<TaskStats>> {
+    let mut file = lock()?;
+    read(&mut file)
+}
+
+// Clears the statistics.
+//
+// # Errors
+//
+// * fail to lock or truncate the stats file
+pub(crate) fn reset() -> Result<()> {
+    let file = lock()?;
+    file.set_len(0)?;
+    Ok(())
+}
+
+fn lock() -> Result<Flock<File>> {
+    fs::create_dir_all(ROXY_STATE_DIR)?;
+    let file = OpenOptions::new()
+        .read(true)
+        .write(true)
+        .create(true)
+        .truncate(false)
+        .open(format!("{ROXY_STATE_DIR}/{STATS_FILE}"))?;
+    Flock::lock(file, FlockArg::LockExclusive)
+        .map_err(|(_, e)| anyhow!("fail to lock {}. {}", STATS_FILE, e))
+}
+
+fn read(file: &mut File) -> Result<Vec<TaskStats>> {
+    let mut contents = String::new();
+    file.read_to_string(&mut contents)?;
+    if contents.trim().is_empty() {
+        Ok(Vec::new())
+    } else {
+        Ok(serde_json::from_str(&contents)?)
+    }
+}
@@ -1,13 +1,10 @@
-use std::{
-    fmt::Write as FmtWrite,
-    fs::{self, OpenOptions},
-    io::Write as IoWrite,
-    net::SocketAddr,
-};
+use std::{fmt::Write as FmtWrite, fs, net::SocketAddr};
 
 use anyhow::{anyhow, Result};
 
-const RSYSLOG_CONF: &str = "/etc/rsyslog.d/50-default.conf";
+use crate::root::persist;
+
+pub(crate) const RSYSLOG_CONF: &str = "/etc/rsyslog.d/50-default.conf";
 const DEFAULT_FACILITY: &str = "user.*";
 const SYSLOG_SERVICE_UNIT: &str = "rsyslog";
 
@@ -59,12 +56,7 @@ pub(crate) fn set(remote_addrs: Option<&Vec<String>>) -> Result<bool> {
         }
     }
 
-    let mut file = OpenOptions::new()
-        .write(true)
-        .truncate(true)
-        .open(RSYSLOG_CONF)?;
-
-    file.write_all(new_contents.as_bytes())?;
+    persist::write(RSYSLOG_CONF, new_contents)?;
 
     let systemctl = systemctl::SystemCtl::default();
     systemctl
@@ -129,6 +121,29 @@ pub(crate) fn get() -> Result<Option<Vec<(String, String, String)>>> {
     }
 }
 
+// Gets rsyslog remote server addresses in the form used by set().
+//
+// # Example
+//
+// let addrs = syslog::get_addrs()?; // ["@@192.168.0.205:7500", "@192.168.1.71:500"]
+//
+// # Errors
+//
+// * fail to open /etc/rsyslog.d/50-default.conf
+pub(crate) fn get_addrs() -> Result<Vec<String>> {
+    Ok(get()?
+        .unwrap_or_default()
+        .into_iter()
+        .map(|(_, proto, addr)| {
+            if proto == "tcp" {
+                format!("@@{addr}")
+            } else {
+                format!("@{addr}")
+            }
+        })
+        .collect())
+}
+
 // (re)start rsyslog service
 pub(crate) fn start() -> Result<bool> {
     let systemctl = systemctl::SystemCtl::default();
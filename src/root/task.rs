@@ -1,26 +1,71 @@
-use std::fs;
-use std::io::Write;
+use std::{borrow::Cow, collections::BTreeMap, time::Instant};
 
 use anyhow::{anyhow, Result};
 use chrono::Local;
 use data_encoding::BASE64;
+use roxy::common::{
+    decode_arg, CaptureSettings, Endpoint, GroupSettings, JobRun, MaintenanceWindow, NetnsCapture,
+    NodeRequest, Page, PageRequest, PingInfo, ServiceAction, TunnelConfig, VfConfig, VrrpConfig,
+    WireguardConfig, MAX_RESPONSE_SIZE, RESPONSE_TOO_LARGE,
+};
 use serde::{Deserialize, Serialize};
 
-use super::{NicOutput, SubCommand};
-use crate::root;
+use super::{NicOutput, NicOutputV1, SubCommand, Subsystem, SystemState, SystemStateV1};
+use crate::root::{self, ifconfig::AddressConflict, limits::LimitExceeded};
 
 #[derive(Debug, Deserialize, Serialize)]
 pub(crate) enum Task {
+    Audit { cmd: SubCommand, arg: String },
+    Banner { cmd: SubCommand, arg: String },
+    Bond { cmd: SubCommand, arg: String },
+    Capture { cmd: SubCommand, arg: String },
+    Changes { cmd: SubCommand, arg: String },
+    Cleanup { cmd: SubCommand, arg: String },
+    Compliance { cmd: SubCommand, arg: String },
+    Compose { cmd: SubCommand, arg: String },
+    Connectivity { cmd: SubCommand, arg: String },
+    CpuGovernor { cmd: SubCommand, arg: String },
+    Crash { cmd: SubCommand, arg: String },
+    Dns { cmd: SubCommand, arg: String },
+    Filesystem { cmd: SubCommand, arg: String },
+    Fstrim { cmd: SubCommand, arg: String },
+    Hardware { cmd: SubCommand, arg: String },
+    History { cmd: SubCommand, arg: String },
     Hostname { cmd: SubCommand, arg: String },
+    Info { cmd: SubCommand, arg: String },
     Interface { cmd: SubCommand, arg: String },
+    InterfaceGroup { cmd: SubCommand, arg: String },
+    InterfaceV2 { cmd: SubCommand, arg: String },
+    IrqAffinity { cmd: SubCommand, arg: String },
+    Kdump { cmd: SubCommand, arg: String },
+    License { cmd: SubCommand, arg: String },
+    Maintenance { cmd: SubCommand, arg: String },
+    Netns { cmd: SubCommand, arg: String },
     Ntp { cmd: SubCommand, arg: String },
+    Ping(String),
     PowerOff(String),
+    Preflight(String),
+    Raid { cmd: SubCommand, arg: String },
     Reboot(String),
+    Replica { cmd: SubCommand, arg: String },
+    Schedule { cmd: SubCommand, arg: String },
     Service { cmd: SubCommand, arg: String },
+    ServiceEnvironment { cmd: SubCommand, arg: String },
+    ServiceGroup { cmd: SubCommand, arg: String },
+    SessionAudit { cmd: SubCommand, arg: String },
+    Smart { cmd: SubCommand, arg: String },
+    Sriov { cmd: SubCommand, arg: String },
     Sshd { cmd: SubCommand, arg: String },
+    State { cmd: SubCommand, arg: String },
+    StateV2 { cmd: SubCommand, arg: String },
+    Stats { cmd: SubCommand, arg: String },
     Syslog { cmd: SubCommand, arg: String },
+    Tuning { cmd: SubCommand, arg: String },
+    Tunnel { cmd: SubCommand, arg: String },
     Ufw { cmd: SubCommand, arg: String },
     Version { cmd: SubCommand, arg: String },
+    Vrrp { cmd: SubCommand, arg: String },
+    Wireguard { cmd: SubCommand, arg: String },
 }
 
 impl Task {
@@ -29,239 +74,1954 @@ impl Task {
         T: serde::de::DeserializeOwned + std::fmt::Debug,
     {
         match self {
-            Task::Hostname { cmd: _, arg }
+            Task::Audit { cmd: _, arg }
+            | Task::Banner { cmd: _, arg }
+            | Task::Bond { cmd: _, arg }
+            | Task::Capture { cmd: _, arg }
+            | Task::Changes { cmd: _, arg }
+            | Task::Compliance { cmd: _, arg }
+            | Task::Compose { cmd: _, arg }
+            | Task::Connectivity { cmd: _, arg }
+            | Task::CpuGovernor { cmd: _, arg }
+            | Task::Dns { cmd: _, arg }
+            | Task::History { cmd: _, arg }
+            | Task::Hostname { cmd: _, arg }
             | Task::Interface { cmd: _, arg }
+            | Task::InterfaceGroup { cmd: _, arg }
+            | Task::InterfaceV2 { cmd: _, arg }
+            | Task::IrqAffinity { cmd: _, arg }
+            | Task::Kdump { cmd: _, arg }
+            | Task::License { cmd: _, arg }
+            | Task::Maintenance { cmd: _, arg }
+            | Task::Netns { cmd: _, arg }
             | Task::Ntp { cmd: _, arg }
+            | Task::Replica { cmd: _, arg }
+            | Task::Schedule { cmd: _, arg }
             | Task::Service { cmd: _, arg }
+            | Task::ServiceEnvironment { cmd: _, arg }
+            | Task::Sriov { cmd: _, arg }
             | Task::Sshd { cmd: _, arg }
+            | Task::State { cmd: _, arg }
+            | Task::StateV2 { cmd: _, arg }
             | Task::Syslog { cmd: _, arg }
-            | Task::Version { cmd: _, arg } => {
-                match bincode::deserialize::<T>(&BASE64.decode(arg.as_bytes())?) {
-                    Ok(r) => {
-                        log_debug(&format!("arg={r:?}"));
-                        Ok(r)
+            | Task::Tuning { cmd: _, arg }
+            | Task::Tunnel { cmd: _, arg }
+            | Task::Ufw { cmd: _, arg }
+            | Task::Version { cmd: _, arg }
+            | Task::Vrrp { cmd: _, arg }
+            | Task::Wireguard { cmd: _, arg } => {
+                let r = decode_arg::<T>(&BASE64.decode(arg.as_bytes())?)?;
+                log_debug(&format!("arg={r:?}"));
+                Ok(r)
+            }
+            _ => Err(anyhow!(ERR_INVALID_COMMAND)),
+        }
+    }
+}
+
+pub(crate) type ExecResult = std::result::Result<String, Cow<'static, str>>;
+pub(crate) const OKAY: &str = "Ok";
+pub(crate) const ERR_INVALID_COMMAND: &str = "invalid command";
+pub(crate) const ERR_PERMISSION_DENIED: &str = "permission denied";
+const ERR_FAIL: &str = "fail";
+const ERR_VETOED: &str = "vetoed by policy";
+const ERR_PARSE_FAIL: &str = "fail to serialize response message";
+
+impl Task {
+    // # Errors
+    //
+    // * unsupported command
+    // * vetoed by a policy hook
+    // * got error from the executed command, with a hint if SELinux or
+    //   AppArmor denied it
+    pub fn execute(&self) -> ExecResult {
+        log_debug(&format!("task {self:?}"));
+
+        let kind = self.kind();
+        root::crash::set_task(&kind);
+        let notes = if root::role::is_read_only(&kind) {
+            Vec::new()
+        } else {
+            match root::hook::check(&kind, self.arg()) {
+                Ok(notes) => notes,
+                Err(e) => {
+                    log_debug(&format!("{e:?}"));
+                    return Err(ERR_VETOED.into());
+                }
+            }
+        };
+        for note in &notes {
+            log_debug(&format!("{kind}: {note}"));
+        }
+
+        // Saves the configuration before and after the change, so that the
+        // change can be reverted even if the previous one was made outside roxy.
+        let subsystems = self.modified_subsystems();
+        for subsystem in &subsystems {
+            if let Err(e) = root::history::record(*subsystem) {
+                log_debug(&format!("fail to record {subsystem} history. {e:?}"));
+            }
+        }
+        let before = root::changes::snapshot(&subsystems);
+        // The change is rolled back by the next roxy if this one is killed
+        // before it completes. Only the subsystems saved in a checkpoint are
+        // journaled; a change of any other file is left as it is.
+        let journal = if subsystems.is_empty() {
+            None
+        } else {
+            let checkpoints = before.iter().map(|(_, c)| c.clone()).collect();
+            root::journal::begin(&kind, checkpoints).unwrap_or_else(|e| {
+                log_debug(&format!("fail to journal {kind}. {e:?}"));
+                None
+            })
+        };
+        let start = Local::now().timestamp();
+        mark_write(&subsystems, start, None);
+        let started = Instant::now();
+        let ret = self.dispatch();
+        let elapsed = started.elapsed();
+        // A command denied by SELinux or AppArmor fails like any other.
+        let ret = ret.map_err(|e| {
+            if e == ERR_FAIL {
+                root::mac::explain(start).map_or(e, Cow::Borrowed)
+            } else {
+                e
+            }
+        });
+        if let Some(journal) = journal {
+            journal.end();
+        }
+        mark_write(&subsystems, start, Some(Local::now().timestamp()));
+        // A ping leaves no trace, even in the statistics.
+        if !matches!(self, Task::Ping(_) | Task::Stats { .. }) {
+            if let Err(e) = root::stats::record(&kind, elapsed, ret.is_err()) {
+                log_debug(&format!("fail to record {kind} stats. {e:?}"));
+            }
+        }
+        if ret.is_ok() {
+            for subsystem in &subsystems {
+                if let Err(e) = root::history::record(*subsystem) {
+                    log_debug(&format!("fail to record {subsystem} history. {e:?}"));
+                }
+            }
+            if let Err(e) = root::changes::record(&kind, before, &notes) {
+                log_debug(&format!("fail to record changes. {e:?}"));
+            }
+            if self.changes_untracked() {
+                if let Err(e) = root::changes::record_untracked(&kind, &notes) {
+                    log_debug(&format!("fail to record changes. {e:?}"));
+                }
+            }
+            if !subsystems.is_empty() {
+                if let Err(e) = root::state::refresh(&subsystems) {
+                    log_debug(&format!("fail to refresh the applied state. {e:?}"));
+                }
+                // A replica is not sent back to the peer it came from.
+                if !matches!(self, Task::Replica { .. }) {
+                    if let Err(e) = root::replica::replicate(&kind, &subsystems) {
+                        log_debug(&format!("fail to replicate {kind}. {e:?}"));
+                    }
+                }
+            }
+        }
+        ret
+    }
+
+    // Returns the encoded arguments of the task.
+    fn arg(&self) -> &str {
+        match self {
+            Task::Ping(arg)
+            | Task::PowerOff(arg)
+            | Task::Preflight(arg)
+            | Task::Reboot(arg)
+            | Task::Audit { cmd: _, arg }
+            | Task::Banner { cmd: _, arg }
+            | Task::Bond { cmd: _, arg }
+            | Task::Capture { cmd: _, arg }
+            | Task::Changes { cmd: _, arg }
+            | Task::Cleanup { cmd: _, arg }
+            | Task::Compliance { cmd: _, arg }
+            | Task::Compose { cmd: _, arg }
+            | Task::Connectivity { cmd: _, arg }
+            | Task::CpuGovernor { cmd: _, arg }
+            | Task::Crash { cmd: _, arg }
+            | Task::Dns { cmd: _, arg }
+            | Task::Filesystem { cmd: _, arg }
+            | Task::Fstrim { cmd: _, arg }
+            | Task::Hardware { cmd: _, arg }
+            | Task::History { cmd: _, arg }
+            | Task::Hostname { cmd: _, arg }
+            | Task::Info { cmd: _, arg }
+            | Task::Interface { cmd: _, arg }
+            | Task::InterfaceGroup { cmd: _, arg }
+            | Task::InterfaceV2 { cmd: _, arg }
+            | Task::IrqAffinity { cmd: _, arg }
+            | Task::Kdump { cmd: _, arg }
+            | Task::License { cmd: _, arg }
+            | Task::Maintenance { cmd: _, arg }
+            | Task::Netns { cmd: _, arg }
+            | Task::Ntp { cmd: _, arg }
+            | Task::Raid { cmd: _, arg }
+            | Task::Replica { cmd: _, arg }
+            | Task::Schedule { cmd: _, arg }
+            | Task::Service { cmd: _, arg }
+            | Task::ServiceEnvironment { cmd: _, arg }
+            | Task::ServiceGroup { cmd: _, arg }
+            | Task::SessionAudit { cmd: _, arg }
+            | Task::Smart { cmd: _, arg }
+            | Task::Sriov { cmd: _, arg }
+            | Task::Sshd { cmd: _, arg }
+            | Task::State { cmd: _, arg }
+            | Task::StateV2 { cmd: _, arg }
+            | Task::Stats { cmd: _, arg }
+            | Task::Syslog { cmd: _, arg }
+            | Task::Tuning { cmd: _, arg }
+            | Task::Tunnel { cmd: _, arg }
+            | Task::Ufw { cmd: _, arg }
+            | Task::Version { cmd: _, arg }
+            | Task::Vrrp { cmd: _, arg }
+            | Task::Wireguard { cmd: _, arg } => arg,
+        }
+    }
+
+    // Returns the kind of the task used in the statistics, e.g.
+    // "Interface::Set".
+    fn kind(&self) -> String {
+        let (name, cmd) = match self {
+            Task::Audit { cmd, arg: _ } => ("Audit", Some(cmd)),
+            Task::Banner { cmd, arg: _ } => ("Banner", Some(cmd)),
+            Task::Bond { cmd, arg: _ } => ("Bond", Some(cmd)),
+            Task::Capture { cmd, arg: _ } => ("Capture", Some(cmd)),
+            Task::Changes { cmd, arg: _ } => ("Changes", Some(cmd)),
+            Task::Cleanup { cmd, arg: _ } => ("Cleanup", Some(cmd)),
+            Task::Compliance { cmd, arg: _ } => ("Compliance", Some(cmd)),
+            Task::Compose { cmd, arg: _ } => ("Compose", Some(cmd)),
+            Task::Connectivity { cmd, arg: _ } => ("Connectivity", Some(cmd)),
+            Task::CpuGovernor { cmd, arg: _ } => ("CpuGovernor", Some(cmd)),
+            Task::Crash { cmd, arg: _ } => ("Crash", Some(cmd)),
+            Task::Dns { cmd, arg: _ } => ("Dns", Some(cmd)),
+            Task::Filesystem { cmd, arg: _ } => ("Filesystem", Some(cmd)),
+            Task::Fstrim { cmd, arg: _ } => ("Fstrim", Some(cmd)),
+            Task::Hardware { cmd, arg: _ } => ("Hardware", Some(cmd)),
+            Task::History { cmd, arg: _ } => ("History", Some(cmd)),
+            Task::Hostname { cmd, arg: _ } => ("Hostname", Some(cmd)),
+            Task::Info { cmd, arg: _ } => ("Info", Some(cmd)),
+            Task::Interface { cmd, arg: _ } => ("Interface", Some(cmd)),
+            Task::InterfaceGroup { cmd, arg: _ } => ("InterfaceGroup", Some(cmd)),
+            Task::InterfaceV2 { cmd, arg: _ } => ("InterfaceV2", Some(cmd)),
+            Task::IrqAffinity { cmd, arg: _ } => ("IrqAffinity", Some(cmd)),
+            Task::Kdump { cmd, arg: _ } => ("Kdump", Some(cmd)),
+            Task::License { cmd, arg: _ } => ("License", Some(cmd)),
+            Task::Maintenance { cmd, arg: _ } => ("Maintenance", Some(cmd)),
+            Task::Netns { cmd, arg: _ } => ("Netns", Some(cmd)),
+            Task::Ntp { cmd, arg: _ } => ("Ntp", Some(cmd)),
+            Task::PowerOff(_) => ("PowerOff", None),
+            Task::Ping(_) => ("Ping", None),
+            Task::Preflight(_) => ("Preflight", None),
+            Task::Raid { cmd, arg: _ } => ("Raid", Some(cmd)),
+            Task::Reboot(_) => ("Reboot", None),
+            Task::Replica { cmd, arg: _ } => ("Replica", Some(cmd)),
+            Task::Schedule { cmd, arg: _ } => ("Schedule", Some(cmd)),
+            Task::Service { cmd, arg: _ } => ("Service", Some(cmd)),
+            Task::ServiceEnvironment { cmd, arg: _ } => ("ServiceEnvironment", Some(cmd)),
+            Task::ServiceGroup { cmd, arg: _ } => ("ServiceGroup", Some(cmd)),
+            Task::SessionAudit { cmd, arg: _ } => ("SessionAudit", Some(cmd)),
+            Task::Smart { cmd, arg: _ } => ("Smart", Some(cmd)),
+            Task::Sriov { cmd, arg: _ } => ("Sriov", Some(cmd)),
+            Task::Sshd { cmd, arg: _ } => ("Sshd", Some(cmd)),
+            Task::State { cmd, arg: _ } => ("State", Some(cmd)),
+            Task::StateV2 { cmd, arg: _ } => ("StateV2", Some(cmd)),
+            Task::Stats { cmd, arg: _ } => ("Stats", Some(cmd)),
+            Task::Syslog { cmd, arg: _ } => ("Syslog", Some(cmd)),
+            Task::Tuning { cmd, arg: _ } => ("Tuning", Some(cmd)),
+            Task::Tunnel { cmd, arg: _ } => ("Tunnel", Some(cmd)),
+            Task::Ufw { cmd, arg: _ } => ("Ufw", Some(cmd)),
+            Task::Version { cmd, arg: _ } => ("Version", Some(cmd)),
+            Task::Vrrp { cmd, arg: _ } => ("Vrrp", Some(cmd)),
+            Task::Wireguard { cmd, arg: _ } => ("Wireguard", Some(cmd)),
+        };
+        cmd.map_or_else(|| name.to_string(), |cmd| format!("{name}::{cmd:?}"))
+    }
+
+    // Returns the subsystems whose configuration can be changed by this task.
+    fn modified_subsystems(&self) -> Vec<Subsystem> {
+        match self {
+            Task::History {
+                cmd: SubCommand::Revert,
+                arg: _,
+            } => self
+                .parse::<(Subsystem, i64)>()
+                .map(|(subsystem, _)| vec![subsystem])
+                .unwrap_or_default(),
+            Task::Hostname {
+                cmd: SubCommand::Set,
+                arg: _,
+            } => vec![Subsystem::Hostname],
+            Task::Interface {
+                cmd: SubCommand::Delete | SubCommand::Init | SubCommand::Set,
+                arg: _,
+            }
+            | Task::InterfaceV2 {
+                cmd: SubCommand::Delete | SubCommand::Set,
+                arg: _,
+            }
+            | Task::InterfaceGroup {
+                cmd: SubCommand::Set,
+                arg: _,
+            }
+            | Task::Tunnel {
+                cmd: SubCommand::Delete | SubCommand::Set,
+                arg: _,
+            } => vec![Subsystem::Interface],
+            Task::Ntp {
+                cmd: SubCommand::Set,
+                arg: _,
+            } => vec![Subsystem::Ntp],
+            Task::Banner {
+                cmd: SubCommand::Set,
+                arg: _,
+            }
+            | Task::Sshd {
+                cmd: SubCommand::Set,
+                arg: _,
+            } => vec![Subsystem::Sshd],
+            Task::Compliance {
+                cmd: SubCommand::Update,
+                arg: _,
+            }
+            | Task::State {
+                cmd: SubCommand::Set,
+                arg: _,
+            }
+            | Task::StateV2 {
+                cmd: SubCommand::Set,
+                arg: _,
+            } => root::transaction::Checkpoint::subsystems().to_vec(),
+            Task::Replica {
+                cmd: SubCommand::Set,
+                arg: _,
+            } => root::replica::SHARED_SUBSYSTEMS.to_vec(),
+            Task::Syslog {
+                cmd: SubCommand::Init | SubCommand::Set,
+                arg: _,
+            } => vec![Subsystem::Syslog],
+            Task::Ufw {
+                cmd: SubCommand::Add | SubCommand::Delete | SubCommand::Disable | SubCommand::Enable,
+                arg: _,
+            } => vec![Subsystem::Firewall],
+            Task::Version {
+                cmd: SubCommand::SetOsVersion | SubCommand::SetProductVersion,
+                arg: _,
+            } => vec![Subsystem::Version],
+            _ => Vec::new(),
+        }
+    }
+
+    // Returns true if the task changes the system outside the subsystems of
+    // `modified_subsystems`, e.g. a WireGuard configuration, a systemd unit,
+    // or a running service, which a checkpoint does not save. Such a task is
+    // recorded in the change events without a diff.
+    fn changes_untracked(&self) -> bool {
+        matches!(
+            self,
+            Task::PowerOff(_)
+                | Task::Reboot(_)
+                | Task::Audit {
+                    cmd: SubCommand::Disable | SubCommand::Enable,
+                    arg: _,
+                }
+                | Task::Capture {
+                    cmd: SubCommand::Delete | SubCommand::Set,
+                    arg: _,
+                }
+                | Task::Cleanup {
+                    cmd: SubCommand::Update,
+                    arg: _,
+                }
+                | Task::Compose {
+                    cmd: SubCommand::Disable | SubCommand::Enable | SubCommand::Update,
+                    arg: _,
+                }
+                | Task::CpuGovernor {
+                    cmd: SubCommand::Delete | SubCommand::Set,
+                    arg: _,
+                }
+                | Task::Fstrim {
+                    cmd: SubCommand::Disable | SubCommand::Enable | SubCommand::Update,
+                    arg: _,
+                }
+                | Task::InterfaceGroup {
+                    cmd: SubCommand::Set,
+                    arg: _,
+                }
+                | Task::IrqAffinity {
+                    cmd: SubCommand::Delete | SubCommand::Set,
+                    arg: _,
+                }
+                | Task::Kdump {
+                    cmd: SubCommand::Disable | SubCommand::Enable | SubCommand::Set,
+                    arg: _,
+                }
+                | Task::License {
+                    cmd: SubCommand::Set,
+                    arg: _,
+                }
+                | Task::Ntp {
+                    cmd: SubCommand::Disable | SubCommand::Enable,
+                    arg: _,
+                }
+                | Task::Service {
+                    cmd: SubCommand::Disable
+                        | SubCommand::Enable
+                        | SubCommand::Set
+                        | SubCommand::Update,
+                    arg: _,
+                }
+                | Task::ServiceEnvironment {
+                    cmd: SubCommand::Delete | SubCommand::Set,
+                    arg: _,
+                }
+                | Task::ServiceGroup {
+                    cmd: SubCommand::Disable | SubCommand::Enable | SubCommand::Update,
+                    arg: _,
+                }
+                | Task::SessionAudit {
+                    cmd: SubCommand::Disable | SubCommand::Enable,
+                    arg: _,
+                }
+                | Task::Sriov {
+                    cmd: SubCommand::Delete | SubCommand::Set | SubCommand::Update,
+                    arg: _,
+                }
+                | Task::Sshd {
+                    cmd: SubCommand::Enable,
+                    arg: _,
+                }
+                | Task::Syslog {
+                    cmd: SubCommand::Enable,
+                    arg: _,
+                }
+                | Task::Tuning {
+                    cmd: SubCommand::Set,
+                    arg: _,
+                }
+                | Task::Vrrp {
+                    cmd: SubCommand::Delete | SubCommand::Set,
+                    arg: _,
+                }
+                | Task::Wireguard {
+                    cmd: SubCommand::Delete | SubCommand::Set,
+                    arg: _,
+                }
+        )
+    }
+
+    // Returns true if restoring a checkpoint undoes the task, which only
+    // reads the system or changes only the configuration of
+    // `modified_subsystems`.
+    pub(crate) fn is_restorable(&self) -> bool {
+        !self.changes_untracked()
+            && (root::role::is_read_only(&self.kind()) || !self.modified_subsystems().is_empty())
+    }
+
+    fn dispatch(&self) -> ExecResult {
+        match self {
+            #[cfg(target_os = "linux")]
+            Task::PowerOff(_) => self.poweroff(),
+            #[cfg(target_os = "linux")]
+            Task::Reboot(_) => self.reboot(),
+            Task::Ping(_) => response(self, PingInfo::local()),
+            Task::Preflight(_) => response(self, root::preflight::run()),
+            Task::Audit { cmd, arg: _ } => self.audit(*cmd),
+            Task::Banner { cmd, arg: _ } => self.banner(*cmd),
+            Task::Bond { cmd, arg: _ } => self.bond(*cmd),
+            Task::Capture { cmd, arg: _ } => self.capture(*cmd),
+            Task::Changes { cmd, arg: _ } => self.changes(*cmd),
+            Task::Cleanup { cmd, arg: _ } => self.cleanup(*cmd),
+            Task::Compliance { cmd, arg: _ } => self.compliance(*cmd),
+            Task::Compose { cmd, arg: _ } => self.compose(*cmd),
+            Task::Connectivity { cmd, arg: _ } => self.connectivity(*cmd),
+            Task::CpuGovernor { cmd, arg: _ } => self.cpu_governor(*cmd),
+            Task::Crash { cmd, arg: _ } => self.crash(*cmd),
+            Task::Dns { cmd, arg: _ } => self.dns(*cmd),
+            Task::Filesystem { cmd, arg: _ } => self.filesystem(*cmd),
+            Task::Fstrim { cmd, arg: _ } => self.fstrim(*cmd),
+            Task::Hardware { cmd, arg: _ } => self.hardware(*cmd),
+            Task::History { cmd, arg: _ } => self.history(*cmd),
+            Task::Hostname { cmd, arg: _ } => self.hostname(*cmd),
+            Task::Info { cmd, arg: _ } => self.info(*cmd),
+            Task::Raid { cmd, arg: _ } => self.raid(*cmd),
+            Task::Replica { cmd, arg: _ } => self.replica(*cmd),
+            Task::Interface { cmd, arg: _ } => self.interface(*cmd, false),
+            Task::InterfaceGroup { cmd, arg: _ } => self.interface_group(*cmd),
+            Task::InterfaceV2 { cmd, arg: _ } => self.interface(*cmd, true),
+            Task::IrqAffinity { cmd, arg: _ } => self.irq_affinity(*cmd),
+            Task::Kdump { cmd, arg: _ } => self.kdump(*cmd),
+            Task::License { cmd, arg: _ } => self.license(*cmd),
+            Task::Maintenance { cmd, arg: _ } => self.maintenance(*cmd),
+            Task::Netns { cmd, arg: _ } => self.netns(*cmd),
+            Task::Ntp { cmd, arg: _ } => self.ntp(*cmd),
+            Task::Sshd { cmd, arg: _ } => self.sshd(*cmd),
+            Task::State { cmd, arg: _ } => self.state(*cmd, false),
+            Task::StateV2 { cmd, arg: _ } => self.state(*cmd, true),
+            Task::Stats { cmd, arg: _ } => self.stats(*cmd),
+            Task::Syslog { cmd, arg: _ } => self.syslog(*cmd),
+            Task::Tuning { cmd, arg: _ } => self.tuning(*cmd),
+            Task::Tunnel { cmd, arg: _ } => self.tunnel(*cmd),
+            Task::Ufw { cmd, arg: _ } => self.ufw(*cmd),
+            Task::Version { cmd, arg: _ } => self.version(*cmd),
+            Task::Vrrp { cmd, arg: _ } => self.vrrp(*cmd),
+            Task::Wireguard { cmd, arg: _ } => self.wireguard(*cmd),
+            Task::Schedule { cmd, arg: _ } => self.schedule(*cmd),
+            Task::Service { cmd, arg: _ } => self.service(*cmd),
+            Task::ServiceEnvironment { cmd, arg: _ } => self.service_environment(*cmd),
+            Task::ServiceGroup { cmd, arg: _ } => self.service_group(*cmd),
+            Task::SessionAudit { cmd, arg: _ } => self.session_audit(*cmd),
+            Task::Smart { cmd, arg: _ } => self.smart(*cmd),
+            Task::Sriov { cmd, arg: _ } => self.sriov(*cmd),
+            #[cfg(not(target_os = "linux"))]
+            Task::PowerOff(_) | Task::Reboot(_) => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    #[cfg(target_os = "linux")]
+    fn reboot(&self) -> ExecResult {
+        nix::sys::reboot::reboot(nix::sys::reboot::RebootMode::RB_AUTOBOOT)
+            .map_err(|_| ERR_INVALID_COMMAND)?;
+        response(self, OKAY)
+    }
+
+    #[cfg(target_os = "linux")]
+    fn poweroff(&self) -> ExecResult {
+        nix::sys::reboot::reboot(nix::sys::reboot::RebootMode::RB_POWER_OFF)
+            .map_err(|_| ERR_INVALID_COMMAND)?;
+        response(self, OKAY)
+    }
+
+    // Gets the saved configurations or reverts to one of them
+    //
+    // # Return
+    //
+    // * Vec<HistoryEntry>: Get command. Saved configurations of the subsystem.
+    //   All subsystems if None
+    // * OKAY: Revert command. Success to execute command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn history(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Get => {
+                let subsystem = self
+                    .parse::<Option<Subsystem>>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::history::get(subsystem) {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
+                }
+            }
+            SubCommand::Revert => {
+                let (subsystem, id) = self
+                    .parse::<(Subsystem, i64)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::history::revert(subsystem, id) {
+                    Ok(()) => response(self, OKAY),
+                    Err(e) => {
+                        log_debug(&format!("fail to revert {subsystem}. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Gets or resets the statistics of the tasks handled by roxy
+    //
+    // # Return
+    //
+    // * Vec<TaskStats>: Get command. Statistics of each task kind
+    // * OKAY: Delete command. Success to execute command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand
+    fn stats(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Delete => match root::stats::reset() {
+                Ok(()) => response(self, OKAY),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            SubCommand::Get => match root::stats::get() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Gets or clears the reports of the panics of roxy
+    //
+    // # Return
+    //
+    // * Vec<CrashReport>: Get command. The reports, oldest first
+    // * OKAY: Delete command. Success to execute command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand
+    fn crash(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Delete => match root::crash::clear() {
+                Ok(()) => response(self, OKAY),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            SubCommand::Get => match root::crash::get() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Gets the status of RAID arrays
+    //
+    // # Return
+    //
+    // * RaidStatus: Status command. Software RAID arrays and hardware RAID
+    //   virtual drives
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand
+    fn raid(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Status => match root::raid::get() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Gets the status of bonding interfaces
+    //
+    // # Return
+    //
+    // * Vec<BondStatus>: Status command. The bond with the name, or every bond
+    //   if None
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn bond(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Status => {
+                let name = self
+                    .parse::<Option<String>>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::bond::get(name.as_deref()) {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("bond {cmd:?} failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Manages the auditd ruleset, and gets audit events
+    //
+    // # Return
+    //
+    // * OKAY: Disable, Enable command. Success to execute command
+    // * AuditStatus: Status command
+    // * Vec<AuditEvent>: Get command. Events generated by the ruleset
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn audit(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Disable | SubCommand::Enable => {
+                let ret = if cmd == SubCommand::Enable {
+                    root::audit::enable()
+                } else {
+                    root::audit::disable()
+                };
+                match ret {
+                    Ok(true) => response(self, OKAY),
+                    _ => Err(ERR_FAIL.into()),
+                }
+            }
+            SubCommand::Get => {
+                let start = self
+                    .parse::<Option<String>>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::audit::events(start.as_deref()) {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => Err(diagnostic_error(&e)),
+                }
+            }
+            SubCommand::Status => match root::audit::status() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Gets or sets the login banner
+    //
+    // # Return
+    //
+    // * OKAY: Set command. Success to execute command
+    // * String: Get command. Banner text
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn banner(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Get => match root::banner::get() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            SubCommand::Set => {
+                let text = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::banner::set(&text) {
+                    Ok(true) => response(self, OKAY),
+                    _ => Err(ERR_FAIL.into()),
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Manages promiscuous mode and offloads of capture interfaces
+    //
+    // # Return
+    //
+    // * OKAY: Delete, Set command. Success to execute command
+    // * CaptureStatus: Get command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn capture(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Delete => {
+                let ifname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                root::capture::delete(&ifname)
+            }
+            SubCommand::Get => {
+                let ifname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                return match root::capture::get(&ifname) {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
+                };
+            }
+            SubCommand::Set => {
+                let (ifname, settings) = self
+                    .parse::<(String, CaptureSettings)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::capture::set(&ifname, settings)
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) => {
+                log_debug(&format!("capture {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Lists the interfaces in named network namespaces, or captures packets
+    // on one of them
+    //
+    // # Return
+    //
+    // * Vec<NetnsInterface>: List command. The interfaces and their counters
+    // * Vec<String>: Update command. A line of tcpdump for each packet
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn netns(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::List => {
+                let namespace = self
+                    .parse::<Option<String>>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::netns::interfaces(namespace.as_deref()) {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("netns {cmd:?} failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
+                }
+            }
+            SubCommand::Update => {
+                let capture = self
+                    .parse::<NetnsCapture>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::netns::capture(&capture) {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("netns {cmd:?} failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Removes the oldest files of the directories over their quotas, or
+    // reports the files to be removed
+    //
+    // # Return
+    //
+    // * Vec<CleanupReport>: Status command. The files to be removed
+    // * Vec<CleanupReport>: Update command. The files removed
+    //
+    // # Errors
+    //
+    // * fail to read the quotas or a directory
+    // * unknown subcommand
+    fn cleanup(&self, cmd: SubCommand) -> ExecResult {
+        let dry_run = match cmd {
+            SubCommand::Status => true,
+            SubCommand::Update => false,
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match root::cleanup::run(dry_run) {
+            Ok(ret) => response(self, ret),
+            Err(e) => {
+                log_debug(&format!("cleanup failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Installs or removes a compliance baseline signed by the Manager, or
+    // checks the system against it
+    //
+    // # Return
+    //
+    // * OKAY: Set, Delete command. Success to install or remove the baseline
+    // * Option<ComplianceReport>: Get command. The last check with remediation
+    // * Option<ComplianceReport>: Status command. Violations of the baseline
+    // * Option<ComplianceReport>: Update command. Violations left after
+    //   remediation
+    //
+    // # Errors
+    //
+    // * invalid or unsigned baseline
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn compliance(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Delete => root::compliance::delete(),
+            SubCommand::Get | SubCommand::Status | SubCommand::Update => {
+                let ret = if cmd == SubCommand::Get {
+                    root::compliance::get()
+                } else {
+                    root::compliance::check(cmd == SubCommand::Update)
+                };
+                return match ret {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("compliance {cmd:?} failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
+                };
+            }
+            SubCommand::Set => {
+                let blob = self.parse::<Vec<u8>>().map_err(|_| ERR_INVALID_COMMAND)?;
+                root::compliance::set(&blob)
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) => {
+                log_debug(&format!("compliance {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Brings compose stacks up or down, pulls their images, or gets the state
+    // of their containers
+    //
+    // # Return
+    //
+    // * OKAY: Disable, Enable, Update command. Success to execute command
+    // * Vec<ContainerHealth>: Status command
+    //
+    // # Errors
+    //
+    // * fail to execute command, or an image is not from an allowed registry
+    // * unknown subcommand or invalid argument
+    fn compose(&self, cmd: SubCommand) -> ExecResult {
+        let stack = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+        let ret = match cmd {
+            SubCommand::Disable => root::compose::down(&stack),
+            SubCommand::Enable => root::compose::up(&stack),
+            SubCommand::Status => {
+                return match root::compose::status(&stack) {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
+                };
+            }
+            SubCommand::Update => root::compose::update(&stack),
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) => {
+                log_debug(&format!("compose {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Gets the errors of the mounted filesystems
+    //
+    // # Return
+    //
+    // * Vec<FilesystemHealth>: Status command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand
+    fn filesystem(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Status => match root::filesystem::health() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Trims the filesystems, or manages the periodic fstrim timer
+    //
+    // # Return
+    //
+    // * OKAY: Disable, Enable command. Success to execute command
+    // * TrimRun: Update command. The result of fstrim
+    // * TrimStatus: Status command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand
+    fn fstrim(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Disable => root::fstrim::disable(),
+            SubCommand::Enable => root::fstrim::enable(),
+            SubCommand::Status => {
+                return match root::fstrim::status() {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
+                };
+            }
+            SubCommand::Update => {
+                return match root::fstrim::run() {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("fstrim failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
+                };
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(true) => response(self, OKAY),
+            _ => Err(ERR_FAIL.into()),
+        }
+    }
+
+    // Gets hardware inventory including BIOS and BMC firmware
+    //
+    // # Return
+    //
+    // * HardwareInfo: Get command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand
+    fn hardware(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Get => match root::hardware::get() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Sets the frequency scaling governor of the CPUs, or gets their
+    // governors
+    //
+    // # Return
+    //
+    // * OKAY: Delete, Set command. Success to execute command
+    // * GovernorStatus: Get command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn cpu_governor(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Delete => root::cpu_governor::delete(),
+            SubCommand::Get => {
+                return match root::cpu_governor::get() {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
+                };
+            }
+            SubCommand::Set => {
+                let governor = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                root::cpu_governor::set(&governor)
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) => {
+                log_debug(&format!("cpu governor {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Pins the IRQs of a NIC to CPUs, or gets the IRQs and their CPUs
+    //
+    // # Return
+    //
+    // * OKAY: Delete, Set command. Success to execute command
+    // * IrqLayout: Get command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn irq_affinity(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Delete => {
+                let ifname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                root::irq_affinity::delete(&ifname)
+            }
+            SubCommand::Get => {
+                let ifname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                return match root::irq_affinity::get(&ifname) {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
+                };
+            }
+            SubCommand::Set => {
+                let (ifname, cpus) = self
+                    .parse::<(String, Vec<u32>)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::irq_affinity::set(&ifname, &cpus)
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) => {
+                log_debug(&format!("irq affinity {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Manages kernel crash dump
+    //
+    // # Return
+    //
+    // * OKAY: Disable, Enable, Set command. Success to execute command
+    // * KdumpStatus: Status command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn kdump(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Disable => root::kdump::disable(),
+            SubCommand::Enable => root::kdump::enable(),
+            SubCommand::Set => {
+                let reservation = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                root::kdump::set_crashkernel(&reservation)
+            }
+            SubCommand::Status => {
+                return match root::kdump::status() {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
+                };
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(true) => response(self, OKAY),
+            _ => Err(ERR_FAIL.into()),
+        }
+    }
+
+    // Gets or installs the product license
+    //
+    // # Return
+    //
+    // * OKAY: Set command. Success to install the license
+    // * Option<LicenseStatus>: Get command. None if no license is installed
+    //
+    // # Errors
+    //
+    // * fail to execute command, or the license is unsigned or expired
+    // * unknown subcommand or invalid argument
+    fn license(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Get => match root::license::get() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            SubCommand::Set => {
+                let blob = self.parse::<Vec<u8>>().map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::license::set(&blob) {
+                    Ok(()) => response(self, OKAY),
+                    Err(e) => {
+                        log_debug(&format!("license Set failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Defers requests to maintenance windows, and executes them in the
+    // windows
+    //
+    // # Return
+    //
+    // * u64: Add command. ID of the deferred operation
+    // * OKAY: Delete command. Success to cancel the operation
+    // * Vec<DeferredOperation>: Get command. Queued and recently finished
+    //   operations
+    // * Vec<DeferredOperation>: Update command. Operations executed or expired
+    //
+    // # Errors
+    //
+    // * the request cannot be deferred, or the operation is not queued
+    // * fail to read or write the queue
+    // * unknown subcommand or invalid argument
+    fn maintenance(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Add => {
+                let (request, window, expires) = self
+                    .parse::<(String, MaintenanceWindow, Option<String>)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                let request = serde_json::from_str::<NodeRequest>(&request)
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::maintenance::add(request, window, expires).map(|id| response(self, id))
+            }
+            SubCommand::Delete => {
+                let id = self.parse::<u64>().map_err(|_| ERR_INVALID_COMMAND)?;
+                root::maintenance::cancel(id).map(|()| response(self, OKAY))
+            }
+            SubCommand::Get => root::maintenance::get().map(|ret| response(self, ret)),
+            SubCommand::Update => root::maintenance::run_due().map(|ret| response(self, ret)),
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        ret.unwrap_or_else(|e| {
+            log_debug(&format!("maintenance {cmd:?} failed. {e:?}"));
+            Err(ERR_FAIL.into())
+        })
+    }
+
+    // Manages session recording of interactive logins
+    //
+    // # Return
+    //
+    // * OKAY: Disable, Enable command. Success to execute command
+    // * SessionAuditStatus: Status command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand
+    fn session_audit(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Disable | SubCommand::Enable => {
+                match root::audit::set_session_audit(cmd == SubCommand::Enable) {
+                    Ok(()) => response(self, OKAY),
+                    Err(_) => Err(ERR_FAIL.into()),
+                }
+            }
+            SubCommand::Status => match root::audit::session_audit_status() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Gets SMART health of disks
+    //
+    // # Return
+    //
+    // * Vec<DiskHealth>: Status command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand
+    fn smart(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Status => match root::smart::get() {
+                Ok(ret) => response(self, ret),
+                Err(e) => Err(diagnostic_error(&e)),
+            },
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Manages SR-IOV virtual functions of physical NICs
+    //
+    // # Return
+    //
+    // * OKAY: Delete, Set, Update command. Success to execute command
+    // * SriovStatus: Get command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn sriov(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Delete => {
+                let ifname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                root::sriov::delete(&ifname)
+            }
+            SubCommand::Get => {
+                let ifname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                return match root::sriov::get(&ifname) {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
+                };
+            }
+            SubCommand::Set => {
+                let (ifname, num_vfs) = self
+                    .parse::<(String, u32)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::sriov::set(&ifname, num_vfs)
+            }
+            SubCommand::Update => {
+                let (ifname, index, config) = self
+                    .parse::<(String, u32, VfConfig)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::sriov::update(&ifname, index, &config)
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) => {
+                log_debug(&format!("sriov {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Gets or sets version for OS and Product
+    //
+    // # Return
+    // * (String, String): (OS version, Product Version)
+    //
+    // # Errors
+    // * fail to set version
+    // * unknown subcommand or invalid argument
+    fn version(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::SetOsVersion | SubCommand::SetProductVersion => {
+                let arg = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                if crate::root::hwinfo::set_version(cmd, &arg).is_ok() {
+                    response(self, OKAY)
+                } else {
+                    Err(ERR_FAIL.into())
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Start, stop, status(is-active), restart(update) the services or get status.
+    // Services listed in /etc/roxy/services.yaml are run by docker or podman.
+    //
+    // # Return
+    //
+    // * bool: Disable, Enable, Set, Status, Update command
+    // * String: Get command. The last lines of the log
+    // * Page<String>: Get command with a PageRequest. Log lines counted back
+    //   from the newest
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn service(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Set => {
+                let (service, action) = self
+                    .parse::<(String, ServiceAction)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::services::control(&service, action) {
+                    Ok(r) => response(self, r),
+                    _ => Err(ERR_FAIL.into()),
+                }
+            }
+            SubCommand::Get => {
+                if let Ok((service, _, page)) = self.parse::<(String, u32, PageRequest)>() {
+                    return match root::services::log_page(&service, page) {
+                        Ok(r) => response(self, r),
+                        Err(e) => Err(diagnostic_error(&e)),
+                    };
+                }
+                let (service, lines) = self
+                    .parse::<(String, u32)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::services::logs(&service, lines) {
+                    Ok(r) => response(self, r),
+                    Err(e) => Err(diagnostic_error(&e)),
+                }
+            }
+            SubCommand::Disable | SubCommand::Enable | SubCommand::Status | SubCommand::Update => {
+                let service = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::services::service_control(&service, cmd) {
+                    Ok(r) => response(self, r),
+                    _ => Err(ERR_FAIL.into()),
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Sets, unsets, or gets the environment variables of a service in its
+    // systemd drop-in
+    //
+    // # Return
+    //
+    // * OKAY: Delete, Set command. Success to execute command
+    // * BTreeMap<String, String>: Get command. The variables
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn service_environment(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Delete => {
+                let (service, names) = self
+                    .parse::<(String, Vec<String>)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::service_environment::unset(&service, &names)
+            }
+            SubCommand::Get => {
+                let service = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                return match root::service_environment::get(&service) {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
+                };
+            }
+            SubCommand::Set => {
+                let (service, variables) = self
+                    .parse::<(String, BTreeMap<String, String>)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::service_environment::set(&service, &variables)
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) => {
+                log_debug(&format!("service environment {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Starts, stops, or restarts all the services under `startup` in
+    // /etc/roxy/services.yaml in the order of their dependencies
+    //
+    // # Return
+    //
+    // * OKAY: Disable, Enable, Update command. Success to execute command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand
+    fn service_group(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Disable => root::services::stop_all(),
+            SubCommand::Enable => root::services::start_all(),
+            SubCommand::Update => root::services::restart_all(),
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) => {
+                log_debug(&format!("service group {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Gets or sets or restarts remote syslog servers
+    //
+    // # Return
+    //
+    // * OKAY: Init, Set command. success to execute command
+    // * Option<Vec<(String, String, String)>>: Get command.
+    //   None if remote server addresses are not exist, else (facility, proto, addr) list
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn syslog(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Get => {
+                let ret = root::syslog::get().map_err(|_| ERR_FAIL)?;
+                response(self, ret)
+            }
+            SubCommand::Init => {
+                if root::syslog::set(None).is_ok() {
+                    response(self, OKAY)
+                } else {
+                    Err(ERR_FAIL.into())
+                }
+            }
+            SubCommand::Set => {
+                let remote_addrs = self
+                    .parse::<Vec<String>>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+
+                if root::syslog::set(Some(&remote_addrs)).is_ok() {
+                    response(self, OKAY)
+                } else {
+                    Err(ERR_FAIL.into())
+                }
+            }
+            SubCommand::Enable => {
+                if root::syslog::start().is_ok() {
+                    response(self, OKAY)
+                } else {
+                    Err(ERR_FAIL.into())
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Applies a kernel tuning profile, or gets the profile and its settings
+    //
+    // # Return
+    //
+    // * OKAY: Set command. Success to apply the profile
+    // * TuningStatus: Get command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn tuning(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Get => match root::tuning::get() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            SubCommand::Set => {
+                let profile = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::tuning::set(&profile) {
+                    Ok(()) => response(self, OKAY),
+                    Err(e) => {
+                        log_debug(&format!("tuning profile {profile} failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Configures keepalived for a VRRP instance of an HA pair, or gets its
+    // configuration and state
+    //
+    // # Return
+    //
+    // * OKAY: Delete, Set command. Success to execute command
+    // * VrrpStatus: Get command. The password is not returned
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * a virtual address conflicts with another interface, with the
+    //   conflict in the message
+    // * unknown subcommand or invalid argument
+    fn vrrp(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Delete => root::vrrp::delete(),
+            SubCommand::Get => {
+                return match root::vrrp::get() {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
+                };
+            }
+            SubCommand::Set => {
+                let config = self
+                    .parse::<VrrpConfig>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::vrrp::set(&config)
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) if e.is::<AddressConflict>() => {
+                log_debug(&format!("vrrp {cmd:?} failed. {e}"));
+                Err(e.to_string().into())
+            }
+            Err(e) => {
+                log_debug(&format!("vrrp {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Adds, replaces, or removes a GRE or VXLAN tunnel in netplan, or gets the
+    // tunnels
+    //
+    // # Return
+    //
+    // * OKAY: Set, Delete command. Success to apply netplan
+    // * Vec<(String, TunnelConfig)>: Get command. The tunnel with the name, or
+    //   every tunnel if None
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn tunnel(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Delete => {
+                let name = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                root::tunnel::delete(&name)
+            }
+            SubCommand::Get => {
+                let name = self
+                    .parse::<Option<String>>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                return match root::tunnel::get(name.as_deref()) {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("tunnel {cmd:?} failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
+                };
+            }
+            SubCommand::Set => {
+                let (name, config) = self
+                    .parse::<(String, TunnelConfig)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::tunnel::set(&name, &config)
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) if e.is::<AddressConflict>() => {
+                log_debug(&format!("tunnel {cmd:?} failed. {e}"));
+                Err(e.to_string().into())
+            }
+            Err(e) => {
+                log_debug(&format!("tunnel {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
+            }
+        }
+    }
+
+    // Configures a WireGuard interface with wg-quick, or gets its
+    // configuration and the state of its tunnel
+    //
+    // # Return
+    //
+    // * OKAY: Delete, Set command. Success to execute command
+    // * Option<WireguardStatus>: Get command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn wireguard(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Delete => {
+                let ifname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                root::wireguard::delete(&ifname)
+            }
+            SubCommand::Get => {
+                let ifname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                return match root::wireguard::get(&ifname) {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("wireguard {cmd:?} failed. {e:?}"));
+                        Err(ERR_FAIL.into())
                     }
-                    Err(e) => Err(anyhow!("fail to parse argument. {}", e)),
-                }
+                };
+            }
+            SubCommand::Set => {
+                let config = self
+                    .parse::<WireguardConfig>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::wireguard::set(&config)
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        match ret {
+            Ok(()) => response(self, OKAY),
+            Err(e) => {
+                log_debug(&format!("wireguard {cmd:?} failed. {e:?}"));
+                Err(ERR_FAIL.into())
             }
-            _ => Err(anyhow!(ERR_INVALID_COMMAND)),
         }
     }
-}
 
-pub(crate) type ExecResult = std::result::Result<String, &'static str>;
-pub(crate) const OKAY: &str = "Ok";
-pub(crate) const ERR_INVALID_COMMAND: &str = "invalid command";
-const ERR_FAIL: &str = "fail";
-const ERR_MESSAGE_TOO_LONG: &str = "message too long";
-const ERR_PARSE_FAIL: &str = "fail to serialize response message";
-
-impl Task {
+    // Gets or sets hostname. A fully-qualified name sets the domain as well.
+    //
+    // # Return
+    //
+    // * OKAY: Set command. Success to execute command
+    // * String: Get command. Hostname
+    // * HostnameInfo: Status command. Short and fully-qualified names
+    //
     // # Errors
     //
-    // * unsupported command
-    // * got error from the executed command
-    pub fn execute(&self) -> ExecResult {
-        log_debug(&format!("task {self:?}"));
-        match self {
-            #[cfg(target_os = "linux")]
-            Task::PowerOff(_) => self.poweroff(),
-            #[cfg(target_os = "linux")]
-            Task::Reboot(_) => self.reboot(),
-            Task::Hostname { cmd, arg: _ } => self.hostname(*cmd),
-            Task::Interface { cmd, arg: _ } => self.interface(*cmd),
-            Task::Ntp { cmd, arg: _ } => self.ntp(*cmd),
-            Task::Sshd { cmd, arg: _ } => self.sshd(*cmd),
-            Task::Syslog { cmd, arg: _ } => self.syslog(*cmd),
-            Task::Version { cmd, arg: _ } => self.version(*cmd),
-            Task::Service { cmd, arg: _ } => self.service(*cmd),
-            _ => Err(ERR_INVALID_COMMAND),
+    // * fail to execute comand
+    // * unknown subcommand or invalid argument
+    fn hostname(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Get => response(self, roxy::hostname()),
+            SubCommand::Status => match root::fqdn::get() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            SubCommand::Set => {
+                let hostname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                if root::fqdn::set(&hostname).is_ok() {
+                    response(self, OKAY)
+                } else {
+                    Err(ERR_FAIL.into())
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
         }
     }
 
-    #[cfg(target_os = "linux")]
-    fn reboot(&self) -> ExecResult {
-        nix::sys::reboot::reboot(nix::sys::reboot::RebootMode::RB_AUTOBOOT)
-            .map_err(|_| ERR_INVALID_COMMAND)?;
-        response(self, OKAY)
-    }
-
-    #[cfg(target_os = "linux")]
-    fn poweroff(&self) -> ExecResult {
-        nix::sys::reboot::reboot(nix::sys::reboot::RebootMode::RB_POWER_OFF)
-            .map_err(|_| ERR_INVALID_COMMAND)?;
-        response(self, OKAY)
-    }
-
-    // Gets or sets version for OS and Product
+    // Records or gets the last runs of scheduled jobs
     //
     // # Return
-    // * (String, String): (OS version, Product Version)
+    //
+    // * OKAY: Set command. Success to record the run
+    // * Vec<JobRun>: Get command. The last run of each job
     //
     // # Errors
-    // * fail to set version
+    //
+    // * fail to read or write the runs
     // * unknown subcommand or invalid argument
-    fn version(&self, cmd: SubCommand) -> ExecResult {
+    fn schedule(&self, cmd: SubCommand) -> ExecResult {
         match cmd {
-            SubCommand::SetOsVersion | SubCommand::SetProductVersion => {
-                let arg = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
-                if crate::root::hwinfo::set_version(cmd, &arg).is_ok() {
-                    response(self, OKAY)
-                } else {
-                    Err(ERR_FAIL)
+            SubCommand::Get => match root::schedule::get() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            SubCommand::Set => {
+                let run = self.parse::<JobRun>().map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::schedule::record(run) {
+                    Ok(()) => response(self, OKAY),
+                    Err(e) => {
+                        log_debug(&format!("schedule Set failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
                 }
             }
-            _ => Err(ERR_INVALID_COMMAND),
+            _ => Err(ERR_INVALID_COMMAND.into()),
         }
     }
 
-    // Start, stop, status(is-active), restart(update) the services or get status
-    fn service(&self, cmd: SubCommand) -> ExecResult {
+    // Gets the changes of the configuration made by roxy
+    //
+    // # Return
+    //
+    // * Vec<ChangeEvent>: Get command. Events after the given sequence number
+    //
+    // # Errors
+    //
+    // * fail to read the event log
+    // * unknown subcommand or invalid argument
+    fn changes(&self, cmd: SubCommand) -> ExecResult {
         match cmd {
-            SubCommand::Disable | SubCommand::Enable | SubCommand::Status | SubCommand::Update => {
-                let service = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
-                match root::services::service_control(&service, cmd) {
-                    Ok(r) => response(self, r),
-                    _ => Err(ERR_FAIL),
+            SubCommand::Get => {
+                let since = self.parse::<u64>().map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::changes::get(since) {
+                    Ok(ret) => response(self, ret),
+                    Err(_) => Err(ERR_FAIL.into()),
                 }
             }
-            _ => Err(ERR_INVALID_COMMAND),
+            _ => Err(ERR_INVALID_COMMAND.into()),
         }
     }
 
-    // Gets or sets or restarts remote syslog servers
+    // Checks whether the endpoints required by the node are reachable
     //
     // # Return
     //
-    // * OKAY: Init, Set command. success to execute command
-    // * Option<Vec<(String, String, String)>>: Get command.
-    //   None if remote server addresses are not exist, else (facility, proto, addr) list
+    // * ReadinessReport: Get command
     //
     // # Errors
     //
-    // * fail to execute command
+    // * fail to read the list of endpoints
     // * unknown subcommand or invalid argument
-    fn syslog(&self, cmd: SubCommand) -> ExecResult {
+    fn connectivity(&self, cmd: SubCommand) -> ExecResult {
         match cmd {
             SubCommand::Get => {
-                let ret = root::syslog::get().map_err(|_| ERR_FAIL)?;
-                response(self, ret)
-            }
-            SubCommand::Init => {
-                if root::syslog::set(None).is_ok() {
-                    response(self, OKAY)
-                } else {
-                    Err(ERR_FAIL)
-                }
-            }
-            SubCommand::Set => {
-                let remote_addrs = self
-                    .parse::<Vec<String>>()
+                let extra = self
+                    .parse::<Vec<Endpoint>>()
                     .map_err(|_| ERR_INVALID_COMMAND)?;
-
-                if root::syslog::set(Some(&remote_addrs)).is_ok() {
-                    response(self, OKAY)
-                } else {
-                    Err(ERR_FAIL)
-                }
-            }
-            SubCommand::Enable => {
-                if root::syslog::start().is_ok() {
-                    response(self, OKAY)
-                } else {
-                    Err(ERR_FAIL)
+                match root::connectivity::check(extra) {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("connectivity Get failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
                 }
             }
-            _ => Err(ERR_INVALID_COMMAND),
+            _ => Err(ERR_INVALID_COMMAND.into()),
         }
     }
 
-    // Gets or sets hostname
+    // Resolves a name with the system resolver, or flushes the DNS cache
     //
     // # Return
     //
-    // * OKAY: Set command. Success to execute command
-    // * String: Get command. Hostname
+    // * DnsLookup: Get command. No addresses if the name is not found
+    // * OKAY: Delete command. Success to flush the cache
     //
     // # Errors
     //
-    // * fail to execute comand
+    // * fail to execute command
     // * unknown subcommand or invalid argument
-    fn hostname(&self, cmd: SubCommand) -> ExecResult {
+    fn dns(&self, cmd: SubCommand) -> ExecResult {
         match cmd {
-            SubCommand::Get => response(self, roxy::hostname()),
-            SubCommand::Set => {
-                let hostname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
-                if hostname::set(hostname).is_ok() {
-                    response(self, OKAY)
-                } else {
-                    Err(ERR_FAIL)
+            SubCommand::Get => {
+                let name = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::dns::lookup(&name) {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("dns Get failed. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
                 }
             }
-            _ => Err(ERR_INVALID_COMMAND),
+            SubCommand::Delete => match root::dns::flush() {
+                Ok(()) => response(self, OKAY),
+                Err(e) => {
+                    log_debug(&format!("dns Delete failed. {e:?}"));
+                    Err(ERR_FAIL.into())
+                }
+            },
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Gets names, versions, uptime, and kernel of the node at once
+    //
+    // # Return
+    //
+    // * NodeInfo: Get command
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand
+    fn info(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Get => match root::info::get() {
+                Ok(ret) => response(self, ret),
+                Err(_) => Err(ERR_FAIL.into()),
+            },
+            _ => Err(ERR_INVALID_COMMAND.into()),
         }
     }
 
     // TODO: simplify interface configuration for Get command
-    // Manages Nic setting
+    // Manages Nic setting. `Interface` exchanges NicOutputV1, and
+    // `InterfaceV2` (`v2`) exchanges NicOutput.
     //
     // # Return
     //
     // * OKAY: all commands except Get and List. Success to execute command
+    // * Option<bool>: Set command with the flag to check the gateway. Whether
+    //   the gateway answers ARP, or None if it is not set or cannot be checked.
     // * Option<Vec<(String, Nic)>>: Get command. Interface name and it's configuration.
     // * Vec<String>: List command. Interface names list
     //
     // # Errors
     //
     // * fail to execute command
+    // * an address to be set is already in use
     // * unknown subcommand or invalid argument
-    fn interface(&self, cmd: SubCommand) -> ExecResult {
+    fn interface(&self, cmd: SubCommand, v2: bool) -> ExecResult {
         match cmd {
             SubCommand::Delete => {
-                let (ifname, nic_output) = self
-                    .parse::<(String, NicOutput)>()
-                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                let (ifname, nic_output, _) = self.parse_nic(v2).ok_or(ERR_INVALID_COMMAND)?;
+                let ifname = root::ifconfig::resolve(&ifname);
                 if root::ifconfig::delete(&ifname, &nic_output).is_ok() {
                     response(self, OKAY)
                 } else {
-                    Err(ERR_FAIL)
+                    Err(ERR_FAIL.into())
                 }
             }
             SubCommand::Get => {
                 let arg = self
                     .parse::<Option<String>>()
-                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                    .map_err(|_| ERR_INVALID_COMMAND)?
+                    .map(|name| root::ifconfig::resolve(&name));
                 match root::ifconfig::get(arg.as_ref()) {
-                    Ok(ret) => response(self, ret),
-                    Err(_) => Err(ERR_FAIL),
+                    Ok(ret) if v2 => response(self, ret),
+                    Ok(ret) => response(
+                        self,
+                        ret.map(|nics| {
+                            nics.into_iter()
+                                .map(|(name, nic)| (name, NicOutputV1::from(nic)))
+                                .collect::<Vec<_>>()
+                        }),
+                    ),
+                    Err(_) => Err(ERR_FAIL.into()),
                 }
             }
-            SubCommand::Init => {
+            SubCommand::Init if !v2 => {
                 let ifname = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                let ifname = root::ifconfig::resolve(&ifname);
                 if root::ifconfig::init(&ifname).is_ok() {
                     response(self, OKAY)
                 } else {
-                    Err(ERR_FAIL)
+                    Err(ERR_FAIL.into())
                 }
             }
-            SubCommand::List => {
+            SubCommand::List if !v2 => {
                 if let Ok(arg) = self.parse::<Option<String>>() {
                     response(self, root::ifconfig::get_interface_names(arg.as_ref()))
                 } else {
-                    Err(ERR_INVALID_COMMAND)
+                    Err(ERR_INVALID_COMMAND.into())
                 }
             }
             SubCommand::Set => {
-                let (ifname, nic_output) = self
-                    .parse::<(String, NicOutput)>()
-                    .map_err(|_| ERR_INVALID_COMMAND)?;
-                if root::ifconfig::set(&ifname, &nic_output).is_err() {
-                    return Err(ERR_FAIL);
+                let (ifname, nic_output, check_gateway) =
+                    self.parse_nic(v2).ok_or(ERR_INVALID_COMMAND)?;
+                let ifname = root::ifconfig::resolve(&ifname);
+                match root::ifconfig::set(&ifname, &nic_output) {
+                    Ok(()) if check_gateway => response(
+                        self,
+                        nic_output.gateway4.as_ref().and_then(|gateway| {
+                            root::ifconfig::gateway_reachable(&ifname, gateway)
+                        }),
+                    ),
+                    Ok(()) => response(self, OKAY),
+                    Err(e) if e.is::<AddressConflict>() => {
+                        log_debug(&format!("fail to set {ifname}. {e}"));
+                        // The message names the conflicting interface.
+                        Err(e.to_string().into())
+                    }
+                    Err(_) => Err(ERR_FAIL.into()),
                 }
-                response(self, OKAY)
             }
-            _ => Err(ERR_INVALID_COMMAND),
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Parses the interface name, NicOutput, and the flag to check the
+    // gateway, which is missing in a request from an older library. The
+    // NicOutputV1 of `Interface` may also be NicOutput, sent by a library
+    // before `InterfaceV2`. NicOutput is tried first, since the shorter
+    // NicOutputV1 would be decoded from its prefix.
+    fn parse_nic(&self, v2: bool) -> Option<(String, NicOutput, bool)> {
+        if let Ok(arg) = self.parse::<(String, NicOutput, bool)>() {
+            return Some(arg);
+        }
+        if let Ok((ifname, nic)) = self.parse::<(String, NicOutput)>() {
+            return Some((ifname, nic, false));
         }
+        if v2 {
+            return None;
+        }
+        if let Ok((ifname, nic, check)) = self.parse::<(String, NicOutputV1, bool)>() {
+            return Some((ifname, nic.into(), check));
+        }
+        let (ifname, nic) = self.parse::<(String, NicOutputV1)>().ok()?;
+        Some((ifname, nic.into(), false))
+    }
+
+    // Gets the interfaces of a group, or applies the same settings to them
+    //
+    // # Return
+    //
+    // * Vec<String>: Get command. Names of the interfaces
+    // * Vec<InterfaceResult>: Set command. Result of each interface
+    //
+    // # Errors
+    //
+    // * unknown group, or no interface matches the pattern
+    // * invalid settings
+    // * unknown subcommand or invalid argument
+    fn interface_group(&self, cmd: SubCommand) -> ExecResult {
+        let ret = match cmd {
+            SubCommand::Get => {
+                let target = self.parse::<String>().map_err(|_| ERR_INVALID_COMMAND)?;
+                root::interface_group::members(&target).map(|ret| response(self, ret))
+            }
+            SubCommand::Set => {
+                let (target, settings) = self
+                    .parse::<(String, GroupSettings)>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                root::interface_group::set(&target, &settings).map(|ret| response(self, ret))
+            }
+            _ => return Err(ERR_INVALID_COMMAND.into()),
+        };
+        ret.unwrap_or_else(|e| {
+            log_debug(&format!("interface group {cmd:?} failed. {e:?}"));
+            Err(ERR_FAIL.into())
+        })
     }
 
     // Gets or sets or restarts sshd
@@ -280,7 +2040,7 @@ impl Task {
                 if let Ok(port) = root::sshd::get() {
                     response(self, port)
                 } else {
-                    Err(ERR_FAIL)
+                    Err(ERR_FAIL.into())
                 }
             }
             SubCommand::Set => {
@@ -288,17 +2048,104 @@ impl Task {
                 if root::sshd::set(&port).is_ok() {
                     response(self, OKAY)
                 } else {
-                    Err(ERR_FAIL)
+                    Err(ERR_FAIL.into())
                 }
             }
             SubCommand::Enable => {
                 if root::sshd::start().is_ok() {
                     response(self, OKAY)
                 } else {
-                    Err(ERR_FAIL)
+                    Err(ERR_FAIL.into())
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Gets the current state or applies the desired state of the system, or
+    // checks the drift from the last applied state. `State` exchanges
+    // SystemStateV1, and `StateV2` (`v2`) exchanges SystemState.
+    //
+    // # Return
+    //
+    // * SystemState: Get command. Current state
+    // * Vec<StateChange>: Set command. Changes made to the system
+    // * Vec<DriftItem>: Status command. Differences from the applied state
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn state(&self, cmd: SubCommand, v2: bool) -> ExecResult {
+        match cmd {
+            SubCommand::Get => match root::state::export() {
+                Ok(ret) if v2 => response(self, ret),
+                Ok(ret) => response(self, SystemStateV1::from(ret)),
+                Err(e) => {
+                    log_debug(&format!("fail to export state. {e:?}"));
+                    Err(ERR_FAIL.into())
+                }
+            },
+            SubCommand::Status if !v2 => match root::state::drift() {
+                Ok(ret) => response(self, ret),
+                Err(e) => {
+                    log_debug(&format!("fail to check drift. {e:?}"));
+                    Err(ERR_FAIL.into())
+                }
+            },
+            SubCommand::Set => {
+                let state = if v2 {
+                    self.parse::<SystemState>()
+                } else {
+                    self.parse::<SystemStateV1>().map(SystemState::from)
+                }
+                .map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::state::apply(&state) {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("fail to apply state. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Exports the state shared with the peer of an HA pair, or applies the
+    // state exported by the peer
+    //
+    // # Return
+    //
+    // * SystemState: Get command. Without the hostname and interfaces
+    // * Vec<StateChange>: Set command. The changes made to the system
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn replica(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Get => match root::replica::export() {
+                Ok(ret) => response(self, ret),
+                Err(e) => {
+                    log_debug(&format!("fail to export replica. {e:?}"));
+                    Err(ERR_FAIL.into())
+                }
+            },
+            SubCommand::Set => {
+                let state = self
+                    .parse::<SystemState>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                match root::replica::apply(&state) {
+                    Ok(ret) => response(self, ret),
+                    Err(e) => {
+                        log_debug(&format!("fail to apply replica. {e:?}"));
+                        Err(ERR_FAIL.into())
+                    }
                 }
             }
-            _ => Err(ERR_INVALID_COMMAND),
+            _ => Err(ERR_INVALID_COMMAND.into()),
         }
     }
 
@@ -318,21 +2165,21 @@ impl Task {
                 if let Ok(ret) = root::ntp::get() {
                     response(self, ret)
                 } else {
-                    Err(ERR_FAIL)
+                    Err(ERR_FAIL.into())
                 }
             }
             SubCommand::Disable => {
                 if root::ntp::disable().is_ok() {
                     response(self, OKAY)
                 } else {
-                    Err(ERR_FAIL)
+                    Err(ERR_FAIL.into())
                 }
             }
             SubCommand::Enable => {
                 if root::ntp::enable().is_ok() {
                     response(self, OKAY)
                 } else {
-                    Err(ERR_FAIL)
+                    Err(ERR_FAIL.into())
                 }
             }
             SubCommand::Set => {
@@ -343,45 +2190,123 @@ impl Task {
                 if root::ntp::set(&servers).is_ok() {
                     response(self, OKAY)
                 } else {
-                    Err(ERR_FAIL)
+                    Err(ERR_FAIL.into())
                 }
             }
             SubCommand::Status => response(self, root::ntp::is_active()),
-            _ => Err(ERR_INVALID_COMMAND),
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+
+    // Gets or sets ufw rules, or enables and disables ufw
+    //
+    // # Return
+    //
+    // * OKAY: Add, Delete, Disable, Enable command. Success to execute command
+    // * Option<Vec<String>>: Get command. User rules. e.g. "allow 22/tcp"
+    // * Page<String>: Get command with a PageRequest. A page of user rules
+    // * true/false: Status command.
+    //
+    // # Errors
+    //
+    // * fail to execute command
+    // * unknown subcommand or invalid argument
+    fn ufw(&self, cmd: SubCommand) -> ExecResult {
+        match cmd {
+            SubCommand::Add | SubCommand::Delete => {
+                let rules = self
+                    .parse::<Vec<String>>()
+                    .map_err(|_| ERR_INVALID_COMMAND)?;
+                let ret = if cmd == SubCommand::Add {
+                    root::ufw::add(&rules)
+                } else {
+                    root::ufw::delete(&rules)
+                };
+                if let Ok(true) = ret {
+                    response(self, OKAY)
+                } else {
+                    Err(ERR_FAIL.into())
+                }
+            }
+            SubCommand::Disable => {
+                if let Ok(true) = root::ufw::disable() {
+                    response(self, OKAY)
+                } else {
+                    Err(ERR_FAIL.into())
+                }
+            }
+            SubCommand::Enable => {
+                if let Ok(true) = root::ufw::enable() {
+                    response(self, OKAY)
+                } else {
+                    Err(ERR_FAIL.into())
+                }
+            }
+            SubCommand::Get => {
+                // Earlier versions send None without a page.
+                let page = self.parse::<Option<PageRequest>>().unwrap_or(None);
+                match (root::ufw::get(), page) {
+                    (Ok(ret), None) => response(self, ret),
+                    (Ok(ret), Some(page)) => {
+                        response(self, Page::of(ret.unwrap_or_default(), page))
+                    }
+                    (Err(_), _) => Err(ERR_FAIL.into()),
+                }
+            }
+            SubCommand::Status => {
+                if let Ok(ret) = root::ufw::is_active() {
+                    response(self, ret)
+                } else {
+                    Err(ERR_FAIL.into())
+                }
+            }
+            _ => Err(ERR_INVALID_COMMAND.into()),
+        }
+    }
+}
+
+fn mark_write(subsystems: &[Subsystem], start: i64, end: Option<i64>) {
+    for subsystem in subsystems {
+        if let Err(e) = root::history::mark_write(*subsystem, start, end) {
+            log_debug(&format!("fail to mark {subsystem} write. {e:?}"));
         }
     }
 }
 
-// Makes response message. max size is u32 bit long.
+// Returns the error of a diagnostic. The message of a diagnostic killed at
+// its memory limit is sent as is.
+fn diagnostic_error(e: &anyhow::Error) -> Cow<'static, str> {
+    if e.is::<LimitExceeded>() {
+        log_debug(&e.to_string());
+        e.to_string().into()
+    } else {
+        ERR_FAIL.into()
+    }
+}
+
+// Makes response message.
 //
 // # Errors
 //
-// * message size is over 64k
+// * message size is over MAX_RESPONSE_SIZE
 // * fail to serialize input
 fn response<I>(taskcode: &Task, input: I) -> ExecResult
 where
     I: Serialize,
 {
     if let Ok(message) = bincode::serialize(&input) {
-        if u32::try_from(message.len()).is_err() {
-            log::error!("reponse is too long. Task: {:?}", taskcode);
-            Err(ERR_MESSAGE_TOO_LONG)
+        if message.len() as u64 > MAX_RESPONSE_SIZE {
+            log::error!("reponse is too large. Task: {:?}", taskcode);
+            Err(RESPONSE_TOO_LARGE.into())
         } else {
             Ok(BASE64.encode(&message))
         }
     } else {
         log::error!("failed to serialize response message. Task: {:?}", taskcode);
-        Err(ERR_PARSE_FAIL)
+        Err(ERR_PARSE_FAIL.into())
     }
 }
 
-// TODO: define the full path for roxy.log file
 pub fn log_debug(msg: &str) {
-    if let Ok(mut writer) = fs::OpenOptions::new()
-        .create(true)
-        .append(true)
-        .open("/data/logs/apps/roxy.log")
-    {
-        let _r = writeln!(writer, "{:?}: {msg}", Local::now());
-    }
+    root::logging::write(None, msg);
 }
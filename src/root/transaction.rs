@@ -0,0 +1,273 @@
+use std::fs;
+
+use anyhow::Result;
+use roxy::common::Subsystem;
+use serde::{Deserialize, Serialize};
+
+use super::task::{log_debug, ExecResult, Task, ERR_INVALID_COMMAND};
+use crate::root::{changes, fqdn, ifconfig, journal, ntp, persist, sshd, syslog, ufw};
+
+pub(crate) const ERR_NOT_EXECUTED: &str = "not executed";
+const ERR_ROLLED_BACK: &str = "rolled back";
+const ERR_ROLLBACK_FAIL: &str = "fail to roll back";
+// The task of the change events recorded when a transaction is rolled back.
+const ROLLBACK_TASK: &str = "Transaction::Rollback";
+// The task of the journal entry of a transaction.
+const TRANSACTION_TASK: &str = "Transaction";
+
+// Saved system configuration which can be restored when a transaction fails.
+#[derive(Debug, Clone, Deserialize, Serialize, PartialEq)]
+pub(crate) struct Checkpoint {
+    hostname: Option<String>,
+    files: Vec<(String, Option<String>)>,
+    netplan_files: Option<Vec<String>>,
+}
+
+impl Checkpoint {
+    // Saves hostname and the configuration files managed by roxy.
+    //
+    // # Errors
+    //
+    // * fail to read a configuration file
+    // * fail to get file list from /etc/netplan
+    pub(crate) fn new() -> Result<Self> {
+        Self::of(&Subsystem::ALL)
+    }
+
+    // Saves the configuration of the specified subsystems.
+    //
+    // # Errors
+    //
+    // * fail to read a configuration file
+    // * fail to read /etc/hosts
+    // * fail to get file list from /etc/netplan
+    pub(crate) fn of(subsystems: &[Subsystem]) -> Result<Self> {
+        let mut hostname = None;
+        let mut netplan_files = None;
+        let mut paths = Vec::new();
+        for subsystem in subsystems {
+            match subsystem {
+                Subsystem::Hostname => hostname = Some(fqdn::get()?.fqdn),
+                Subsystem::Interface => {
+                    let files = ifconfig::list_files(ifconfig::NETPLAN_PATH, None, false)?
+                        .into_iter()
+                        .map(|(_, _, name)| format!("{}/{name}", ifconfig::NETPLAN_PATH))
+                        .collect::<Vec<_>>();
+                    paths.extend(files.iter().cloned());
+                    netplan_files = Some(files);
+                }
+                Subsystem::Firewall
+                | Subsystem::Ntp
+                | Subsystem::Sshd
+                | Subsystem::Syslog
+                | Subsystem::Version => {}
+            }
+            paths.extend(subsystem.files().iter().map(ToString::to_string));
+        }
+
+        let mut files = Vec::new();
+        for path in paths {
+            let contents = match fs::read_to_string(&path) {
+                Ok(contents) => Some(contents),
+                Err(e) if e.kind() == std::io::ErrorKind::NotFound => None,
+                Err(e) => return Err(e.into()),
+            };
+            files.push((path, contents));
+        }
+
+        Ok(Checkpoint {
+            hostname,
+            files,
+            netplan_files,
+        })
+    }
+
+    pub(crate) fn subsystems() -> &'static [Subsystem] {
+        &Subsystem::ALL
+    }
+
+    pub(crate) fn hostname(&self) -> Option<&String> {
+        self.hostname.as_ref()
+    }
+
+    pub(crate) fn files(&self) -> &[(String, Option<String>)] {
+        &self.files
+    }
+
+    // Restores the saved configuration, and applies it to the system if it
+    // has been changed since the checkpoint.
+    //
+    // # Errors
+    //
+    // * fail to write a configuration file
+    // * fail to remove a netplan yaml file created after the checkpoint
+    // * fail to set hostname
+    // * fail to apply netplan or restart a service
+    pub(crate) fn restore(&self) -> Result<()> {
+        if self.netplan_files.is_some() {
+            ifconfig::invalidate_cache();
+        }
+        // The hostname is set first, since setting it rewrites /etc/hosts,
+        // which is restored as saved below.
+        if let Some(hostname) = &self.hostname {
+            if fqdn::get()?.fqdn != *hostname {
+                fqdn::set(hostname)?;
+            }
+        }
+        let mut changed = Vec::new();
+        for (path, contents) in &self.files {
+            let current = fs::read_to_string(path).ok();
+            if current == *contents {
+                continue;
+            }
+            if let Some(contents) = contents {
+                persist::write(path, contents)?;
+            } else {
+                persist::remove(path)?;
+            }
+            changed.push(path.as_str());
+        }
+
+        let mut netplan_changed = changed
+            .iter()
+            .any(|path| path.starts_with(ifconfig::NETPLAN_PATH));
+        if let Some(netplan_files) = &self.netplan_files {
+            for (_, _, name) in ifconfig::list_files(ifconfig::NETPLAN_PATH, None, false)? {
+                let path = format!("{}/{name}", ifconfig::NETPLAN_PATH);
+                if !netplan_files.contains(&path) {
+                    persist::remove(&path)?;
+                    netplan_changed = true;
+                }
+            }
+        }
+
+        if netplan_changed {
+            ifconfig::run_command("netplan", &["apply"])?;
+        }
+        if changed.contains(&ntp::NTP_CONF) {
+            ntp::enable()?;
+        }
+        if changed.contains(&sshd::SSHD_CONFIG) {
+            sshd::start()?;
+        }
+        if changed.contains(&syslog::RSYSLOG_CONF) {
+            syslog::start()?;
+        }
+        if changed.contains(&ufw::UFW_CONF) {
+            if ufw::is_active()? {
+                ufw::enable()?;
+            } else {
+                ufw::disable()?;
+            }
+        } else if changed.contains(&ufw::UFW_USER_RULES) || changed.contains(&ufw::UFW_USER6_RULES)
+        {
+            ufw::reload()?;
+        }
+        Ok(())
+    }
+}
+
+// Executes tasks sequentially as a transaction. If any task fails, the
+// remaining tasks are not executed and the system configuration is restored
+// to the state before the transaction. No task is executed if any of them
+// makes a change which the checkpoint cannot restore, e.g. a reboot or a
+// WireGuard configuration.
+//
+// # Return
+//
+// * Result of each task. If the transaction is rolled back, the results of
+//   the tasks executed successfully are replaced with an error.
+pub(crate) fn execute(tasks: &[Task]) -> Vec<ExecResult> {
+    if !tasks.iter().all(Task::is_restorable) {
+        return tasks
+            .iter()
+            .map(|_| Err(ERR_INVALID_COMMAND.into()))
+            .collect();
+    }
+
+    let checkpoint = match Checkpoint::new() {
+        Ok(checkpoint) => checkpoint,
+        Err(e) => {
+            log_debug(&format!("fail to save checkpoint. {e:?}"));
+            return tasks.iter().map(|_| Err(ERR_NOT_EXECUTED.into())).collect();
+        }
+    };
+    // The tasks are not journaled separately, so that the whole transaction
+    // is rolled back if roxy is killed in the middle of it.
+    let journal = journal::begin(TRANSACTION_TASK, vec![checkpoint.clone()]).unwrap_or_else(|e| {
+        log_debug(&format!("fail to journal the transaction. {e:?}"));
+        None
+    });
+
+    let mut results = Vec::new();
+    for task in tasks {
+        let ret = task.execute();
+        let failed = ret.is_err();
+        results.push(ret);
+        if failed {
+            break;
+        }
+    }
+
+    if results.iter().any(Result::is_err) {
+        let before = changes::snapshot(Checkpoint::subsystems());
+        let undone = match checkpoint.restore() {
+            Ok(()) => {
+                if let Err(e) = changes::record(ROLLBACK_TASK, before, &[]) {
+                    log_debug(&format!("fail to record changes. {e:?}"));
+                }
+                ERR_ROLLED_BACK
+            }
+            Err(e) => {
+                log_debug(&format!("fail to restore checkpoint. {e:?}"));
+                ERR_ROLLBACK_FAIL
+            }
+        };
+        for ret in &mut results {
+            if ret.is_ok() {
+                *ret = Err(undone.into());
+            }
+        }
+        results.resize(tasks.len(), Err(ERR_NOT_EXECUTED.into()));
+    }
+    if let Some(journal) = journal {
+        journal.end();
+    }
+    results
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::root::{banner, hwinfo};
+
+    // The files of the modules are those saved in a checkpoint and watched
+    // by `ConfigWatcher`.
+    #[test]
+    fn subsystem_files() {
+        let files = [
+            (Subsystem::Firewall, ufw::UFW_CONF),
+            (Subsystem::Firewall, ufw::UFW_USER_RULES),
+            (Subsystem::Firewall, ufw::UFW_USER6_RULES),
+            (Subsystem::Hostname, fqdn::HOSTS),
+            (Subsystem::Hostname, fqdn::DOMAIN_SYSCTL_CONF),
+            (Subsystem::Interface, ifconfig::DNS_PRIORITY_CONF),
+            (Subsystem::Interface, ifconfig::INTERFACE_LABEL_CONF),
+            (Subsystem::Ntp, ntp::NTP_CONF),
+            (Subsystem::Sshd, sshd::SSHD_CONFIG),
+            (Subsystem::Sshd, banner::ISSUE),
+            (Subsystem::Sshd, banner::ISSUE_NET),
+            (Subsystem::Sshd, banner::MOTD),
+            (Subsystem::Syslog, syslog::RSYSLOG_CONF),
+            (Subsystem::Version, hwinfo::DEFAULT_VERSION_PATH),
+        ];
+        for (subsystem, file) in files {
+            assert!(subsystem.files().contains(&file), "{file} of {subsystem}");
+        }
+        let count = Subsystem::ALL
+            .iter()
+            .map(|s| s.files().len())
+            .sum::<usize>();
+        assert_eq!(count, files.len());
+    }
+}
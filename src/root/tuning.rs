@@ -0,0 +1,253 @@
+use std::{fs, io, path::Path};
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command, TuningSetting, TuningStatus};
+
+use crate::root::persist;
+
+pub(crate) const TUNING_SYSCTL_CONF: &str = "/etc/sysctl.d/90-roxy-tuning.conf";
+const UNIT_PATH: &str = "/etc/systemd/system/roxy-tuning.service";
+const UNIT_NAME: &str = "roxy-tuning.service";
+const PROC_SYS: &str = "/proc/sys/";
+const SYS: &str = "/sys/";
+const PROFILE_HEADER: &str = "# roxy tuning profile: ";
+const DEFAULT_PROFILE: &str = "default";
+
+const THP_ENABLED: &str = "/sys/kernel/mm/transparent_hugepage/enabled";
+const THP_DEFRAG: &str = "/sys/kernel/mm/transparent_hugepage/defrag";
+const SWAPPINESS: &str = "/proc/sys/vm/swappiness";
+const DIRTY_BACKGROUND_RATIO: &str = "/proc/sys/vm/dirty_background_ratio";
+const DIRTY_RATIO: &str = "/proc/sys/vm/dirty_ratio";
+const NETDEV_MAX_BACKLOG: &str = "/proc/sys/net/core/netdev_max_backlog";
+const RMEM_MAX: &str = "/proc/sys/net/core/rmem_max";
+
+// The settings of a profile, by their paths under /proc/sys or /sys. Every
+// profile sets the same paths, so that applying one undoes another.
+type Settings = [(&'static str, &'static str); 7];
+
+// The defaults of the kernel and Ubuntu.
+const DEFAULT: Settings = [
+    (THP_ENABLED, "madvise"),
+    (THP_DEFRAG, "madvise"),
+    (SWAPPINESS, "60"),
+    (DIRTY_BACKGROUND_RATIO, "10"),
+    (DIRTY_RATIO, "20"),
+    (NETDEV_MAX_BACKLOG, "1000"),
+    (RMEM_MAX, "212992"),
+];
+
+// Packet capture: no stalls compacting memory for huge pages or swapping out
+// the capture buffers, and room for bursts of packets.
+const CAPTURE: Settings = [
+    (THP_ENABLED, "never"),
+    (THP_DEFRAG, "never"),
+    (SWAPPINESS, "1"),
+    (DIRTY_BACKGROUND_RATIO, "10"),
+    (DIRTY_RATIO, "20"),
+    (NETDEV_MAX_BACKLOG, "250000"),
+    (RMEM_MAX, "134217728"),
+];
+
+// Databases: no huge pages, which databases recommend against, and smaller
+// bursts of writeback.
+const DATABASE: Settings = [
+    (THP_ENABLED, "never"),
+    (THP_DEFRAG, "never"),
+    (SWAPPINESS, "1"),
+    (DIRTY_BACKGROUND_RATIO, "5"),
+    (DIRTY_RATIO, "15"),
+    (NETDEV_MAX_BACKLOG, "1000"),
+    (RMEM_MAX, "212992"),
+];
+
+const PROFILES: [(&str, &Settings); 3] = [
+    (DEFAULT_PROFILE, &DEFAULT),
+    ("capture", &CAPTURE),
+    ("database", &DATABASE),
+];
+
+// Applies the settings of a profile, and applies them again at boot. If a
+// setting fails, the settings applied before it are restored. The default
+// profile removes the configuration of the other profiles.
+//
+// # Errors
+//
+// * unknown profile
+// * fail to read or write a setting
+// * fail to write or enable the configuration applied at boot
+pub(crate) fn set(name: &str) -> Result<()> {
+    let settings = settings(name).ok_or_else(|| anyhow!("unknown tuning profile: {}", name))?;
+    let before = settings
+        .iter()
+        .map(|(path, _)| read(path))
+        .collect::<Result<Vec<_>>>()?;
+    let ret = apply(settings).and_then(|()| persist_profile(name, settings));
+    if ret.is_err() {
+        for ((path, _), value) in settings.iter().zip(&before) {
+            // The setting may not have been applied.
+            let _ = fs::write(path, value);
+        }
+    }
+    ret
+}
+
+// Gets the profile applied at boot, and the running values of its settings.
+//
+// # Errors
+//
+// * fail to read the configuration applied at boot
+pub(crate) fn get() -> Result<TuningStatus> {
+    let profile = match fs::read_to_string(TUNING_SYSCTL_CONF) {
+        Ok(contents) => parse_profile(&contents)
+            .ok_or_else(|| anyhow!("no tuning profile in {}", TUNING_SYSCTL_CONF))?,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => DEFAULT_PROFILE.to_string(),
+        Err(e) => return Err(e.into()),
+    };
+    let settings = settings(&profile)
+        .ok_or_else(|| anyhow!("unknown tuning profile: {}", profile))?
+        .iter()
+        .map(|(path, value)| TuningSetting {
+            name: name(path),
+            expected: (*value).to_string(),
+            current: read(path).ok(),
+        })
+        .collect();
+    Ok(TuningStatus {
+        profile,
+        settings,
+        profiles: PROFILES
+            .iter()
+            .map(|(name, _)| (*name).to_string())
+            .collect(),
+    })
+}
+
+fn settings(name: &str) -> Option<&'static Settings> {
+    PROFILES
+        .iter()
+        .find_map(|(profile, settings)| (*profile == name).then_some(*settings))
+}
+
+fn apply(settings: &Settings) -> Result<()> {
+    for (path, value) in settings {
+        fs::write(path, value).map_err(|e| anyhow!("fail to set {}. {}", name(path), e))?;
+    }
+    Ok(())
+}
+
+// Writes the sysctl configuration, and a unit for the settings under /sys,
+// which sysctl does not apply.
+fn persist_profile(name: &str, settings: &Settings) -> Result<()> {
+    if name == DEFAULT_PROFILE {
+        if Path::new(UNIT_PATH).exists() {
+            systemctl(&["disable", UNIT_NAME])?;
+            persist::remove(UNIT_PATH)?;
+            systemctl(&["daemon-reload"])?;
+        }
+        return persist::remove(TUNING_SYSCTL_CONF);
+    }
+    persist::write(TUNING_SYSCTL_CONF, sysctl_conf(name, settings))?;
+    persist::write(UNIT_PATH, unit(name, settings))?;
+    systemctl(&["daemon-reload"])?;
+    systemctl(&["enable", UNIT_NAME])
+}
+
+// Reads a setting. The value of a setting with choices, such as
+// "always [madvise] never", is the one in brackets.
+fn read(path: &str) -> Result<String> {
+    let contents = fs::read_to_string(path)?;
+    let value = contents
+        .split_whitespace()
+        .find_map(|choice| choice.strip_prefix('[')?.strip_suffix(']'))
+        .unwrap_or(contents.trim());
+    Ok(value.to_string())
+}
+
+// The sysctl key of a path under /proc/sys, or the path under /sys with
+// dots.
+fn name(path: &str) -> String {
+    path.strip_prefix(PROC_SYS)
+        .or_else(|| path.strip_prefix(SYS))
+        .unwrap_or(path)
+        .replace('/', ".")
+}
+
+fn sysctl_conf(name: &str, settings: &Settings) -> String {
+    let keys = settings
+        .iter()
+        .filter(|(path, _)| path.starts_with(PROC_SYS))
+        .map(|(path, value)| format!("{} = {value}\n", self::name(path)))
+        .collect::<Vec<_>>()
+        .concat();
+    format!("# Written by roxy.\n{PROFILE_HEADER}{name}\n{keys}")
+}
+
+fn parse_profile(contents: &str) -> Option<String> {
+    contents
+        .lines()
+        .find_map(|line| line.strip_prefix(PROFILE_HEADER))
+        .map(|name| name.trim().to_string())
+}
+
+fn unit(name: &str, settings: &Settings) -> String {
+    let exec_start = settings
+        .iter()
+        .filter(|(path, _)| !path.starts_with(PROC_SYS))
+        .map(|(path, value)| format!("ExecStart=/bin/sh -c 'echo {value} > {path}'\n"))
+        .collect::<Vec<_>>()
+        .concat();
+    format!(
+        "# Written by roxy.\n\
+         [Unit]\n\
+         Description=Kernel tuning profile {name}\n\
+         \n\
+         [Service]\n\
+         Type=oneshot\n\
+         RemainAfterExit=yes\n\
+         {exec_start}\
+         \n\
+         [Install]\n\
+         WantedBy=multi-user.target\n"
+    )
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let output = run_command(command("systemctl").args(args))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("fail to run systemctl {}", args.join(" ")))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn profiles_set_same_paths() {
+        for (_, settings) in PROFILES {
+            let paths = settings.iter().map(|(path, _)| path);
+            assert!(paths.eq(DEFAULT.iter().map(|(path, _)| path)));
+        }
+    }
+
+    #[test]
+    fn setting_names() {
+        assert_eq!(name(SWAPPINESS), "vm.swappiness");
+        assert_eq!(name(THP_ENABLED), "kernel.mm.transparent_hugepage.enabled");
+    }
+
+    #[test]
+    fn profile_files() {
+        let conf = sysctl_conf("capture", &CAPTURE);
+        assert_eq!(parse_profile(&conf).as_deref(), Some("capture"));
+        assert!(conf.contains("vm.swappiness = 1\n"));
+        assert!(!conf.contains("transparent_hugepage"));
+        let unit = unit("capture", &CAPTURE);
+        assert!(unit.contains(&format!(
+            "ExecStart=/bin/sh -c 'echo never > {THP_ENABLED}'\n"
+        )));
+        assert!(!unit.contains("swappiness"));
+    }
+}
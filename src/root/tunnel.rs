@@ -0,0 +1,308 @@
+use std::{fs, net::IpAddr};
+
+use anyhow::{anyhow, Result};
+use ipnet::IpNet;
+use pnet::datalink::interfaces;
+use roxy::common::{TunnelConfig, TunnelMode};
+
+use crate::root::{capture, ifconfig};
+
+const MAX_VNI: u32 = 0x00ff_ffff;
+// The smallest MTU of IPv4.
+const MIN_MTU: u32 = 68;
+// The outer IPv6 header of VXLAN is 20 bytes longer than the IPv4 one.
+const VXLAN_IPV6_EXTRA: u32 = 20;
+
+// Gets the GRE and VXLAN tunnels in netplan, or the one with the name.
+//
+// # Errors
+//
+// * fail to load netplan yaml files
+pub(crate) fn get(name: Option<&str>) -> Result<Vec<(String, TunnelConfig)>> {
+    let mut tunnels = ifconfig::tunnels()?;
+    if let Some(name) = name {
+        tunnels.retain(|(x, _)| x == name);
+    }
+    Ok(tunnels)
+}
+
+// Adds or replaces a tunnel in netplan, and applies it. If the MTU is not
+// given, the largest one the interface carrying the tunnel allows is set.
+//
+// # Errors
+//
+// * invalid tunnel, e.g. an endpoint not assigned to this node, or an MTU
+//   larger than the carrying interface allows
+// * an address of the tunnel conflicts with another interface or host
+// * fail to save or apply netplan yaml conf
+pub(crate) fn set(name: &str, config: &TunnelConfig) -> Result<()> {
+    capture::validate_name(name)?;
+    let local = validate(config)?;
+    let underlay = underlay_mtu(local)?;
+    let mut config = config.clone();
+    config.mtu = Some(check_mtu(&config, local, underlay)?);
+    ifconfig::set_tunnel(name, &config)
+}
+
+// Removes a tunnel from netplan and deletes its interface.
+//
+// # Errors
+//
+// * the tunnel does not exist
+// * fail to save or apply netplan yaml conf
+pub(crate) fn delete(name: &str) -> Result<()> {
+    capture::validate_name(name)?;
+    ifconfig::delete_tunnel(name)
+}
+
+// Checks the settings which do not depend on the interfaces of this node, and
+// returns the local endpoint.
+fn validate(config: &TunnelConfig) -> Result<IpAddr> {
+    let local = config
+        .local
+        .parse::<IpAddr>()
+        .map_err(|_| anyhow!("invalid local address: {}", config.local))?;
+    if local.is_unspecified() || local.is_multicast() {
+        return Err(anyhow!("invalid local address: {}", local));
+    }
+    let ipv6_only = match config.mode {
+        TunnelMode::Gre | TunnelMode::Gretap => Some(false),
+        TunnelMode::Ip6Gre | TunnelMode::Ip6Gretap => Some(true),
+        TunnelMode::Vxlan => None,
+    };
+    if ipv6_only.is_some_and(|ipv6| ipv6 != local.is_ipv6()) {
+        return Err(anyhow!(
+            "local address {} does not match mode {:?}",
+            local,
+            config.mode
+        ));
+    }
+
+    match &config.remote {
+        Some(remote) => {
+            let remote = remote
+                .parse::<IpAddr>()
+                .map_err(|_| anyhow!("invalid remote address: {}", remote))?;
+            if remote.is_unspecified() || remote == local {
+                return Err(anyhow!("invalid remote address: {}", remote));
+            }
+            if remote.is_ipv6() != local.is_ipv6() {
+                return Err(anyhow!(
+                    "remote address {} is not of the family of {}",
+                    remote,
+                    local
+                ));
+            }
+            // A multicast group is for VXLAN to learn its peers.
+            if remote.is_multicast() && config.mode != TunnelMode::Vxlan {
+                return Err(anyhow!("invalid remote address: {}", remote));
+            }
+        }
+        None if config.mode != TunnelMode::Vxlan => {
+            return Err(anyhow!("remote address is required for {:?}", config.mode));
+        }
+        None => {}
+    }
+
+    if config.mode == TunnelMode::Vxlan {
+        match config.vni {
+            Some(vni) if (1..=MAX_VNI).contains(&vni) => {}
+            Some(vni) => return Err(anyhow!("VNI should be 1 to {}: {}", MAX_VNI, vni)),
+            None => return Err(anyhow!("VNI is required for Vxlan")),
+        }
+        if config.port == Some(0) {
+            return Err(anyhow!("invalid port: 0"));
+        }
+        if config.key.is_some() {
+            return Err(anyhow!("key is only for GRE"));
+        }
+    } else if config.vni.is_some() || config.port.is_some() {
+        return Err(anyhow!("VNI and port are only for Vxlan"));
+    }
+
+    for addr in config.addresses.iter().flatten() {
+        if addr.parse::<IpNet>().is_err() {
+            return Err(anyhow!("invalid tunnel address: {}", addr));
+        }
+    }
+    Ok(local)
+}
+
+// Returns the MTU of the interface the local endpoint is assigned to.
+fn underlay_mtu(local: IpAddr) -> Result<u32> {
+    let nic = interfaces()
+        .into_iter()
+        .find(|nic| nic.ips.iter().any(|ip| ip.ip() == local))
+        .ok_or_else(|| anyhow!("local address {} is not assigned to this node", local))?;
+    let mtu = fs::read_to_string(format!("/sys/class/net/{}/mtu", nic.name))?;
+    mtu.trim()
+        .parse()
+        .map_err(|_| anyhow!("fail to read the MTU of {}", nic.name))
+}
+
+// Returns the MTU of the tunnel, which should leave room for the
+// encapsulation within the MTU of the carrying interface. Otherwise, the
+// encapsulated packets are fragmented or dropped.
+fn check_mtu(config: &TunnelConfig, local: IpAddr, underlay: u32) -> Result<u32> {
+    let mut overhead = config.mode.overhead(config.key.is_some());
+    if config.mode == TunnelMode::Vxlan && local.is_ipv6() {
+        overhead += VXLAN_IPV6_EXTRA;
+    }
+    let max = underlay.saturating_sub(overhead);
+    match config.mtu {
+        Some(mtu) if mtu > max => Err(anyhow!(
+            "MTU {} is too large. {:?} adds {} bytes to each packet, and the interface \
+             carrying it has MTU {}, so the MTU should be at most {}",
+            mtu,
+            config.mode,
+            overhead,
+            underlay,
+            max
+        )),
+        Some(mtu) if mtu < MIN_MTU => Err(anyhow!("MTU should be at least {}", MIN_MTU)),
+        Some(mtu) => Ok(mtu),
+        None if max < MIN_MTU => Err(anyhow!(
+            "the interface carrying the tunnel has too small MTU {}",
+            underlay
+        )),
+        None => Ok(max),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn gre() -> TunnelConfig {
+        TunnelConfig {
+            mode: TunnelMode::Gre,
+            local: "192.0.2.10".to_string(),
+            remote: Some("198.51.100.1".to_string()),
+            vni: None,
+            port: None,
+            key: None,
+            addresses: Some(vec!["10.0.0.1/30".to_string()]),
+            mtu: None,
+        }
+    }
+
+    fn vxlan() -> TunnelConfig {
+        TunnelConfig {
+            mode: TunnelMode::Vxlan,
+            remote: None,
+            vni: Some(100),
+            addresses: None,
+            ..gre()
+        }
+    }
+
+    #[test]
+    fn endpoints() {
+        assert!(validate(&gre()).is_ok());
+        assert!(validate(&vxlan()).is_ok());
+
+        let invalid = [
+            TunnelConfig {
+                local: "0.0.0.0".to_string(),
+                ..gre()
+            },
+            TunnelConfig {
+                remote: None,
+                ..gre()
+            },
+            TunnelConfig {
+                remote: Some("192.0.2.10".to_string()),
+                ..gre()
+            },
+            TunnelConfig {
+                remote: Some("2001:db8::1".to_string()),
+                ..gre()
+            },
+            TunnelConfig {
+                remote: Some("239.1.1.1".to_string()),
+                ..gre()
+            },
+            TunnelConfig {
+                mode: TunnelMode::Ip6Gre,
+                ..gre()
+            },
+            TunnelConfig {
+                addresses: Some(vec!["10.0.0.1".to_string()]),
+                ..gre()
+            },
+        ];
+        for config in invalid {
+            assert!(validate(&config).is_err(), "{config:?}");
+        }
+        assert!(validate(&TunnelConfig {
+            remote: Some("239.1.1.1".to_string()),
+            ..vxlan()
+        })
+        .is_ok());
+    }
+
+    #[test]
+    fn mode_settings() {
+        for config in [
+            TunnelConfig {
+                vni: None,
+                ..vxlan()
+            },
+            TunnelConfig {
+                vni: Some(MAX_VNI + 1),
+                ..vxlan()
+            },
+            TunnelConfig {
+                port: Some(0),
+                ..vxlan()
+            },
+            TunnelConfig {
+                key: Some(1),
+                ..vxlan()
+            },
+            TunnelConfig {
+                vni: Some(100),
+                ..gre()
+            },
+            TunnelConfig {
+                port: Some(4789),
+                ..gre()
+            },
+        ] {
+            assert!(validate(&config).is_err(), "{config:?}");
+        }
+    }
+
+    #[test]
+    fn mtu() {
+        let local = "192.0.2.10".parse().unwrap();
+        assert_eq!(check_mtu(&gre(), local, 1500).unwrap(), 1476);
+        let keyed = TunnelConfig {
+            key: Some(7),
+            ..gre()
+        };
+        assert_eq!(check_mtu(&keyed, local, 1500).unwrap(), 1472);
+        let tap = TunnelConfig {
+            mode: TunnelMode::Gretap,
+            ..gre()
+        };
+        assert_eq!(check_mtu(&tap, local, 1500).unwrap(), 1462);
+        assert_eq!(check_mtu(&vxlan(), local, 1500).unwrap(), 1450);
+        let local6 = "2001:db8::10".parse().unwrap();
+        assert_eq!(check_mtu(&vxlan(), local6, 1500).unwrap(), 1430);
+        assert_eq!(check_mtu(&vxlan(), local, 9000).unwrap(), 8950);
+
+        let large = TunnelConfig {
+            mtu: Some(1500),
+            ..gre()
+        };
+        let err = check_mtu(&large, local, 1500).unwrap_err().to_string();
+        assert!(err.contains("at most 1476"), "{err}");
+        let small = TunnelConfig {
+            mtu: Some(60),
+            ..gre()
+        };
+        assert!(check_mtu(&small, local, 1500).is_err());
+        assert!(check_mtu(&gre(), local, 80).is_err());
+    }
+}
@@ -0,0 +1,171 @@
+use std::fs;
+
+use anyhow::{anyhow, Result};
+use roxy::common::{command, run_command};
+
+pub(crate) const UFW_CONF: &str = "/etc/ufw/ufw.conf";
+pub(crate) const UFW_USER_RULES: &str = "/etc/ufw/user.rules";
+pub(crate) const UFW_USER6_RULES: &str = "/etc/ufw/user6.rules";
+const RULE_ACTIONS: [&str; 4] = ["allow", "deny", "limit", "reject"];
+
+// True if ufw is enabled.
+//
+// # Errors
+//
+// * fail to open /etc/ufw/ufw.conf
+pub(crate) fn is_active() -> Result<bool> {
+    let contents = fs::read_to_string(UFW_CONF)?;
+    Ok(contents
+        .lines()
+        .any(|line| line.trim().eq_ignore_ascii_case("ENABLED=yes")))
+}
+
+// Enables ufw.
+//
+// # Errors
+//
+// * fail to execute ufw command
+pub(crate) fn enable() -> Result<bool> {
+    run_ufw(&["--force", "enable"])
+}
+
+// Disables ufw.
+//
+// # Errors
+//
+// * fail to execute ufw command
+pub(crate) fn disable() -> Result<bool> {
+    run_ufw(&["disable"])
+}
+
+// Gets user rules in the form of ufw command arguments.
+//
+// # Example
+//
+// if let Some(rules) = ufw::get()? {
+//     for rule in &rules {
+//         println!("{rule}"); // "allow 22/tcp"
+//     }
+// }
+//
+// # Errors
+//
+// * fail to execute ufw command
+pub(crate) fn get() -> Result<Option<Vec<String>>> {
+    let output = run_command(command("ufw").args(["show", "added"]))?;
+    if !output.status.success() {
+        return Err(anyhow!("fail to get ufw rules"));
+    }
+
+    let ret = parse_added_rules(&String::from_utf8_lossy(&output.stdout));
+    if ret.is_empty() {
+        Ok(None)
+    } else {
+        Ok(Some(ret))
+    }
+}
+
+// Parses the output of `ufw show added`.
+fn parse_added_rules(output: &str) -> Vec<String> {
+    output
+        .lines()
+        .filter_map(|line| line.trim().strip_prefix("ufw "))
+        .map(ToString::to_string)
+        .collect()
+}
+
+// Adds user rules.
+//
+// # Example
+//
+// let ret = ufw::add(&["allow 22/tcp".to_string(), "deny from 10.0.0.1".to_string()])?;
+//
+// # Errors
+//
+// * invalid rule
+// * fail to execute ufw command
+pub(crate) fn add(rules: &[String]) -> Result<bool> {
+    validate_rules(rules)?;
+    for rule in rules {
+        let args = rule.split_whitespace().collect::<Vec<_>>();
+        if !run_ufw(&args)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// Deletes user rules.
+//
+// # Errors
+//
+// * invalid rule
+// * fail to execute ufw command
+pub(crate) fn delete(rules: &[String]) -> Result<bool> {
+    validate_rules(rules)?;
+    for rule in rules {
+        let mut args = vec!["delete"];
+        args.extend(rule.split_whitespace());
+        if !run_ufw(&args)? {
+            return Ok(false);
+        }
+    }
+    Ok(true)
+}
+
+// Reloads ufw to apply rules written to the ufw conf files.
+//
+// # Errors
+//
+// * fail to execute ufw command
+pub(crate) fn reload() -> Result<bool> {
+    run_ufw(&["reload"])
+}
+
+// Only rules starting with an action are accepted to prevent other ufw
+// commands such as reset.
+fn validate_rules(rules: &[String]) -> Result<()> {
+    for rule in rules {
+        match rule.split_whitespace().next() {
+            Some(action) if RULE_ACTIONS.contains(&action) => {}
+            _ => return Err(anyhow!("invalid ufw rule: {}", rule)),
+        }
+    }
+    Ok(())
+}
+
+fn run_ufw(args: &[&str]) -> Result<bool> {
+    let output = run_command(command("ufw").args(args))?;
+    Ok(output.status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SHOW_ADDED: &str = "Added user rules (see 'ufw status' for running firewall):
+ufw allow 22/tcp
+ufw limit 10022/tcp
+ufw deny from 10.0.0.1
+";
+
+    const SHOW_ADDED_NONE: &str = "Added user rules (see 'ufw status' for running firewall):
+(None)
+";
+
+    #[test]
+    fn added_rules() {
+        assert_eq!(
+            parse_added_rules(SHOW_ADDED),
+            ["allow 22/tcp", "limit 10022/tcp", "deny from 10.0.0.1"]
+        );
+        assert!(parse_added_rules(SHOW_ADDED_NONE).is_empty());
+    }
+
+    #[test]
+    fn rules_without_action() {
+        assert!(validate_rules(&["allow 22/tcp".to_string()]).is_ok());
+        assert!(validate_rules(&["reset".to_string()]).is_err());
+        assert!(validate_rules(&[String::new()]).is_err());
+    }
+}
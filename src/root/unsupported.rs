@@ -0,0 +1,154 @@
+use std::borrow::Cow;
+
+use roxy::common::{unsupported_task, NodeRequest};
+use serde::Deserialize;
+use serde_json::Value;
+
+use super::task::ExecResult;
+use super::transaction::ERR_NOT_EXECUTED;
+
+// The shape of RoxyRequest with each request left undecoded.
+#[derive(Deserialize)]
+#[serde(untagged)]
+enum RawRequest {
+    Batch(Vec<Value>),
+    Transaction { transaction: Vec<Value> },
+    Single(Value),
+}
+
+enum Parsed {
+    Known(NodeRequest),
+    Unknown(String),
+}
+
+// Answers a request which has task kinds unknown to this version of roxy,
+// e.g. sent by a newer library. The unknown tasks fail as unsupported, and
+// the known tasks in a batch are executed by `execute`. No task in a
+// transaction is executed.
+//
+// # Return
+//
+// * the results in the same form as for a valid request. None if the
+//   request is malformed.
+pub(crate) fn respond<F>(buf: &[u8], execute: F) -> Option<Value>
+where
+    F: Fn(&NodeRequest) -> ExecResult,
+{
+    let result = |parsed: &Parsed| -> Result<String, String> {
+        match parsed {
+            Parsed::Known(nr) => execute(nr).map_err(Cow::into_owned),
+            Parsed::Unknown(kind) => Err(unsupported_task(kind)),
+        }
+    };
+
+    let ret = match serde_json::from_slice::<RawRequest>(buf).ok()? {
+        RawRequest::Batch(values) => {
+            let parsed = values.into_iter().map(parse).collect::<Option<Vec<_>>>()?;
+            serde_json::to_value(parsed.iter().map(result).collect::<Vec<_>>())
+        }
+        RawRequest::Single(value) => serde_json::to_value(result(&parse(value)?)),
+        RawRequest::Transaction { transaction } => {
+            let parsed = transaction
+                .into_iter()
+                .map(parse)
+                .collect::<Option<Vec<_>>>()?;
+            serde_json::to_value(
+                parsed
+                    .iter()
+                    .map(|parsed| match parsed {
+                        Parsed::Known(_) => Err(ERR_NOT_EXECUTED.to_string()),
+                        Parsed::Unknown(kind) => Err(unsupported_task(kind)),
+                    })
+                    .collect::<Vec<Result<String, String>>>(),
+            )
+        }
+    };
+    ret.ok()
+}
+
+fn parse(value: Value) -> Option<Parsed> {
+    let kind = request_kind(&value)?;
+    match serde_json::from_value::<NodeRequest>(value) {
+        Ok(nr) => Some(Parsed::Known(nr)),
+        Err(_) => Some(Parsed::Unknown(kind)),
+    }
+}
+
+// Returns the kind of a well-formed request, e.g. "Interface::Set", without
+// checking whether it is known.
+fn request_kind(value: &Value) -> Option<String> {
+    let request = value.as_object()?;
+    serde_json::from_value::<Vec<u8>>(request.get("arg")?.clone()).ok()?;
+    kind_name(request.get("kind")?)
+}
+
+// Returns the name of a serialized task kind, e.g. "Interface::Set" for
+// `{"Interface": "Set"}`.
+pub(crate) fn kind_name(kind: &Value) -> Option<String> {
+    match kind {
+        Value::String(kind) => Some(kind.clone()),
+        Value::Object(kind) if kind.len() == 1 => {
+            let (name, cmd) = kind.iter().next()?;
+            Some(format!("{name}::{}", cmd.as_str()?))
+        }
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn execute() -> impl Fn(&NodeRequest) -> ExecResult {
+        |_| Ok("AQ==".to_string())
+    }
+
+    #[test]
+    fn unknown_task_in_batch() {
+        let buf = br#"[
+            {"kind": {"Ntp": "Get"}, "arg": [0]},
+            {"kind": {"Frobnicator": "Set"}, "arg": [0]},
+            {"kind": "SelfDestruct", "arg": []}
+        ]"#;
+        assert_eq!(
+            respond(buf, execute()),
+            Some(serde_json::json!([
+                {"Ok": "AQ=="},
+                {"Err": "unsupported task: Frobnicator::Set"},
+                {"Err": "unsupported task: SelfDestruct"}
+            ]))
+        );
+    }
+
+    #[test]
+    fn unknown_subcommand() {
+        let buf = br#"{"kind": {"Ntp": "Frobnicate"}, "arg": [0]}"#;
+        assert_eq!(
+            respond(buf, execute()),
+            Some(serde_json::json!({"Err": "unsupported task: Ntp::Frobnicate"}))
+        );
+    }
+
+    #[test]
+    fn unknown_task_in_transaction() {
+        let buf = br#"{"transaction": [
+            {"kind": {"Ntp": "Set"}, "arg": [0]},
+            {"kind": {"Frobnicator": "Set"}, "arg": [0]}
+        ]}"#;
+        assert_eq!(
+            respond(buf, execute()),
+            Some(serde_json::json!([
+                {"Err": "not executed"},
+                {"Err": "unsupported task: Frobnicator::Set"}
+            ]))
+        );
+    }
+
+    #[test]
+    fn malformed_request() {
+        assert!(respond(b"{", execute()).is_none());
+        assert!(respond(br#"{"kind": "Reboot"}"#, execute()).is_none());
+        assert!(respond(br#"{"kind": "Reboot", "arg": [256]}"#, execute()).is_none());
+        assert!(respond(br#"[{"kind": {"Ntp": 1}, "arg": []}]"#, execute()).is_none());
+    }
+}
@@ -0,0 +1,353 @@
+use std::{fs, io, net::IpAddr};
+
+use anyhow::{anyhow, Result};
+use ipnet::IpNet;
+use pnet::datalink::interfaces;
+use roxy::common::{command, run_command, VrrpConfig, VrrpState, VrrpStatus};
+
+use crate::root::{ifconfig, persist};
+
+pub(crate) const KEEPALIVED_CONF: &str = "/etc/keepalived/keepalived.conf";
+const NOTIFY_SCRIPT: &str = "/etc/roxy/vrrp-notify.sh";
+const STATE_DIR: &str = "/run/roxy";
+const STATE_FILE: &str = "/run/roxy/vrrp-state";
+const KEEPALIVED_UNIT: &str = "keepalived.service";
+const INSTANCE: &str = "roxy";
+// The first line of the files written by roxy.
+const WRITTEN_BY_ROXY: &str = "# Written by roxy.";
+const MAX_AUTH_PASS_LEN: usize = 8;
+
+// Writes the keepalived configuration, readable only by root since it has
+// the password, and starts keepalived at boot and now, or reloads it if it
+// is running.
+//
+// # Errors
+//
+// * invalid configuration
+// * a virtual address conflicts with the addresses of another interface
+// * the configuration is not written by roxy
+// * fail to write the configuration
+// * fail to enable or reload keepalived
+pub(crate) fn set(config: &VrrpConfig) -> Result<()> {
+    validate(config)?;
+    ifconfig::check_conflicts_with_configured(&config.interface, &config.virtual_addresses)?;
+    check_written_by_roxy()?;
+    persist::write_executable(NOTIFY_SCRIPT, notify_script())?;
+    persist::write_secret(KEEPALIVED_CONF, keepalived_conf(config))?;
+    systemctl(&["enable", KEEPALIVED_UNIT])?;
+    systemctl(&["reload-or-restart", KEEPALIVED_UNIT])
+}
+
+// Gets the configuration written by `set` without the password, and the
+// state of the instance.
+//
+// # Errors
+//
+// * fail to read the configuration or the state
+pub(crate) fn get() -> Result<VrrpStatus> {
+    let config = match fs::read_to_string(KEEPALIVED_CONF) {
+        Ok(contents) if contents.starts_with(WRITTEN_BY_ROXY) => {
+            parse_conf(&contents).map(|config| VrrpConfig {
+                auth_pass: None,
+                ..config
+            })
+        }
+        Ok(_) => None,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let state = match fs::read_to_string(STATE_FILE) {
+        Ok(contents) => parse_state(&contents),
+        Err(e) if e.kind() == io::ErrorKind::NotFound => None,
+        Err(e) => return Err(e.into()),
+    };
+    let holds_addresses = config.as_ref().is_some_and(|config| {
+        let Some(ip) = config
+            .virtual_addresses
+            .first()
+            .and_then(|addr| addr.parse::<IpNet>().ok())
+            .map(|net| net.addr())
+        else {
+            return false;
+        };
+        has_address(&config.interface, ip)
+    });
+    let output = run_command(command("systemctl").args(["is-active", "--quiet", KEEPALIVED_UNIT]))?;
+    Ok(VrrpStatus {
+        config,
+        active: output.status.success(),
+        state,
+        holds_addresses,
+    })
+}
+
+// Stops keepalived, which releases the virtual addresses, and removes its
+// configuration.
+//
+// # Errors
+//
+// * the configuration is not written by roxy
+// * fail to stop or disable keepalived
+// * fail to remove the configuration
+pub(crate) fn delete() -> Result<()> {
+    check_written_by_roxy()?;
+    systemctl(&["disable", "--now", KEEPALIVED_UNIT])?;
+    persist::remove(KEEPALIVED_CONF)?;
+    persist::remove(NOTIFY_SCRIPT)?;
+    match fs::remove_file(STATE_FILE) {
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+fn validate(config: &VrrpConfig) -> Result<()> {
+    if !interfaces().iter().any(|nic| nic.name == config.interface) {
+        return Err(anyhow!("interface not found: {}", config.interface));
+    }
+    if config.virtual_router_id == 0 {
+        return Err(anyhow!("virtual router id should be 1 to 255"));
+    }
+    if config.priority == 0 || config.priority == u8::MAX {
+        return Err(anyhow!("priority should be 1 to 254"));
+    }
+    let Some(first) = config.virtual_addresses.first() else {
+        return Err(anyhow!("no virtual address"));
+    };
+    let ipv4 = first.parse::<IpNet>().map(|net| net.addr().is_ipv4());
+    for addr in &config.virtual_addresses {
+        let net = addr
+            .parse::<IpNet>()
+            .map_err(|e| anyhow!("invalid virtual address {}. {:?}", addr, e))?;
+        // An instance advertises over either IPv4 or IPv6.
+        if Ok(net.addr().is_ipv4()) != ipv4 {
+            return Err(anyhow!("virtual addresses mix IPv4 and IPv6"));
+        }
+    }
+    if let Some(pass) = &config.auth_pass {
+        let valid = !pass.is_empty()
+            && pass.len() <= MAX_AUTH_PASS_LEN
+            && pass
+                .chars()
+                .all(|c| c.is_ascii_graphic() && !"#!\"{}".contains(c));
+        if !valid {
+            return Err(anyhow!(
+                "password should be 1 to {} printable characters",
+                MAX_AUTH_PASS_LEN
+            ));
+        }
+    }
+    Ok(())
+}
+
+// Checks that the keepalived configuration does not exist or is written by
+// roxy, so that the instances configured otherwise are not replaced.
+fn check_written_by_roxy() -> Result<()> {
+    match fs::read_to_string(KEEPALIVED_CONF) {
+        Ok(contents) if !contents.starts_with(WRITTEN_BY_ROXY) => {
+            Err(anyhow!("{} is not written by roxy", KEEPALIVED_CONF))
+        }
+        Err(e) if e.kind() != io::ErrorKind::NotFound => Err(e.into()),
+        _ => Ok(()),
+    }
+}
+
+fn has_address(ifname: &str, ip: IpAddr) -> bool {
+    interfaces()
+        .iter()
+        .any(|nic| nic.name == ifname && nic.ips.iter().any(|net| net.ip() == ip))
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let output = run_command(command("systemctl").args(args))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("fail to run systemctl {}", args.join(" ")))
+    }
+}
+
+// keepalived runs the script with the type and the name of the instance, and
+// its new state.
+fn notify_script() -> String {
+    format!(
+        "#!/bin/sh\n\
+         {WRITTEN_BY_ROXY}\n\
+         mkdir -p {STATE_DIR}\n\
+         echo \"$3\" > {STATE_FILE}\n"
+    )
+}
+
+// Both nodes start as backup, and the one with the higher priority becomes
+// the master.
+fn keepalived_conf(config: &VrrpConfig) -> String {
+    let authentication = config
+        .auth_pass
+        .as_ref()
+        .map(|pass| {
+            format!(
+                "    authentication {{\n        \
+                 auth_type PASS\n        \
+                 auth_pass {pass}\n    \
+                 }}\n"
+            )
+        })
+        .unwrap_or_default();
+    let addresses = config
+        .virtual_addresses
+        .iter()
+        .map(|addr| format!("        {addr}\n"))
+        .collect::<Vec<_>>()
+        .concat();
+    format!(
+        "{WRITTEN_BY_ROXY}\n\
+         global_defs {{\n    \
+         script_user root\n    \
+         enable_script_security\n\
+         }}\n\
+         \n\
+         vrrp_instance {INSTANCE} {{\n    \
+         state BACKUP\n    \
+         interface {interface}\n    \
+         virtual_router_id {id}\n    \
+         priority {priority}\n    \
+         advert_int 1\n\
+         {authentication}    \
+         virtual_ipaddress {{\n\
+         {addresses}    \
+         }}\n    \
+         notify {NOTIFY_SCRIPT}\n\
+         }}\n",
+        interface = config.interface,
+        id = config.virtual_router_id,
+        priority = config.priority,
+    )
+}
+
+// Reads a configuration written by `keepalived_conf`.
+fn parse_conf(contents: &str) -> Option<VrrpConfig> {
+    let mut interface = None;
+    let mut virtual_router_id = None;
+    let mut priority = None;
+    let mut auth_pass = None;
+    let mut virtual_addresses = Vec::new();
+    let mut tokens = contents
+        .lines()
+        .map(|line| line.split(['#', '!']).next().unwrap_or_default())
+        .flat_map(str::split_whitespace);
+    while let Some(token) = tokens.next() {
+        match token {
+            "interface" => interface = tokens.next().map(ToString::to_string),
+            "virtual_router_id" => virtual_router_id = tokens.next()?.parse().ok(),
+            "priority" => priority = tokens.next()?.parse().ok(),
+            "auth_pass" => auth_pass = tokens.next().map(ToString::to_string),
+            "virtual_ipaddress" => {
+                if tokens.next() != Some("{") {
+                    return None;
+                }
+                for addr in tokens.by_ref() {
+                    if addr == "}" {
+                        break;
+                    }
+                    virtual_addresses.push(addr.to_string());
+                }
+            }
+            _ => {}
+        }
+    }
+    Some(VrrpConfig {
+        interface: interface?,
+        virtual_router_id: virtual_router_id?,
+        priority: priority?,
+        virtual_addresses,
+        auth_pass,
+    })
+}
+
+fn parse_state(contents: &str) -> Option<VrrpState> {
+    match contents.trim() {
+        "MASTER" => Some(VrrpState::Master),
+        "BACKUP" => Some(VrrpState::Backup),
+        "FAULT" => Some(VrrpState::Fault),
+        "STOP" => Some(VrrpState::Stop),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn config() -> VrrpConfig {
+        VrrpConfig {
+            interface: "lo".to_string(),
+            virtual_router_id: 51,
+            priority: 150,
+            virtual_addresses: vec!["192.0.2.100/24".to_string(), "192.0.2.101/24".to_string()],
+            auth_pass: Some("s3cret".to_string()),
+        }
+    }
+
+    #[test]
+    fn conf_round_trip() {
+        let config = config();
+        let contents = keepalived_conf(&config);
+        assert!(contents.contains("\n    interface lo\n"));
+        assert!(contents.contains("\n        auth_pass s3cret\n"));
+        assert!(contents.contains("\n        192.0.2.101/24\n    }\n"));
+        assert_eq!(parse_conf(&contents), Some(config.clone()));
+
+        let config = VrrpConfig {
+            auth_pass: None,
+            ..config
+        };
+        let contents = keepalived_conf(&config);
+        assert!(!contents.contains("authentication"));
+        assert_eq!(parse_conf(&contents), Some(config));
+        assert_eq!(parse_conf("# no instance\n"), None);
+    }
+
+    #[test]
+    fn invalid_configs() {
+        assert!(validate(&config()).is_ok());
+        let invalid = [
+            VrrpConfig {
+                interface: "no-such-nic".to_string(),
+                ..config()
+            },
+            VrrpConfig {
+                virtual_router_id: 0,
+                ..config()
+            },
+            VrrpConfig {
+                priority: 255,
+                ..config()
+            },
+            VrrpConfig {
+                virtual_addresses: Vec::new(),
+                ..config()
+            },
+            VrrpConfig {
+                virtual_addresses: vec!["192.0.2.100/24".to_string(), "2001:db8::1/64".to_string()],
+                ..config()
+            },
+            VrrpConfig {
+                auth_pass: Some("too-long-password".to_string()),
+                ..config()
+            },
+            VrrpConfig {
+                auth_pass: Some("a}b".to_string()),
+                ..config()
+            },
+        ];
+        for config in invalid {
+            assert!(validate(&config).is_err(), "{config:?}");
+        }
+    }
+
+    #[test]
+    fn states() {
+        assert_eq!(parse_state("MASTER\n"), Some(VrrpState::Master));
+        assert_eq!(parse_state("FAULT\n"), Some(VrrpState::Fault));
+        assert_eq!(parse_state(""), None);
+    }
+}
@@ -0,0 +1,464 @@
+use std::{
+    fs, io,
+    io::Write,
+    net::IpAddr,
+    process::{Command, Stdio},
+};
+
+use anyhow::{anyhow, Result};
+use data_encoding::BASE64;
+use ipnet::IpNet;
+use roxy::common::{
+    command, run_command, WireguardConfig, WireguardPeer, WireguardStatus, SANITIZED_ENV,
+};
+
+use crate::root::{capture, persist};
+
+const CONF_DIR: &str = "/etc/wireguard";
+const KEY_LEN: usize = 32;
+
+// The keys of this node, kept in the configuration. The public key is kept
+// as a comment, so that reading it does not need the private key.
+struct Keys {
+    private: String,
+    public: String,
+}
+
+// Writes the configuration of the interface for wg-quick, and brings the
+// interface up now and at boot. The keys of the interface are generated the
+// first time, and kept afterward, so that the peer need not be updated.
+//
+// # Errors
+//
+// * invalid configuration
+// * fail to generate the keys
+// * fail to write the configuration
+// * fail to enable or restart the interface
+pub(crate) fn set(config: &WireguardConfig) -> Result<()> {
+    validate(config)?;
+    let keys = match read_conf(&config.interface)? {
+        Some((_, keys)) => keys,
+        None => generate_keys()?,
+    };
+    persist::write_secret(&conf_path(&config.interface), conf(config, &keys))?;
+    let unit = unit_name(&config.interface);
+    systemctl(&["enable", &unit])?;
+    systemctl(&["restart", &unit])
+}
+
+// Gets the configuration of the interface, the public key of this node, and
+// the state of the tunnel.
+//
+// # Return
+//
+// * None if the interface is not configured by roxy
+//
+// # Errors
+//
+// * invalid interface name
+// * fail to read the configuration or the state of the tunnel
+pub(crate) fn get(ifname: &str) -> Result<Option<WireguardStatus>> {
+    let Some((config, keys)) = read_conf(ifname)? else {
+        return Ok(None);
+    };
+    let output =
+        run_command(command("systemctl").args(["is-active", "--quiet", &unit_name(ifname)]))?;
+    let active = output.status.success();
+    let (latest_handshake, rx_bytes, tx_bytes) = if active {
+        let output = run_command(command("wg").args(["show", ifname, "dump"]))?;
+        if !output.status.success() {
+            return Err(anyhow!("fail to get the state of {}", ifname));
+        }
+        transfer(
+            &String::from_utf8_lossy(&output.stdout),
+            &config.peer.public_key,
+        )
+    } else {
+        (None, 0, 0)
+    };
+    Ok(Some(WireguardStatus {
+        config,
+        public_key: keys.public,
+        active,
+        latest_handshake,
+        rx_bytes,
+        tx_bytes,
+    }))
+}
+
+// Brings the interface down, and removes its configuration and keys.
+//
+// # Errors
+//
+// * invalid interface name
+// * fail to disable the interface
+// * fail to remove the configuration
+pub(crate) fn delete(ifname: &str) -> Result<()> {
+    capture::validate_name(ifname)?;
+    if fs::metadata(conf_path(ifname)).is_err() {
+        return Ok(());
+    }
+    systemctl(&["disable", "--now", &unit_name(ifname)])?;
+    persist::remove(&conf_path(ifname))
+}
+
+// Gets the addresses of the interfaces configured in `CONF_DIR`. A
+// configuration which cannot be parsed is left out.
+//
+// # Errors
+//
+// * fail to read `CONF_DIR`
+pub(crate) fn addresses() -> Result<Vec<(String, Vec<String>)>> {
+    let entries = match fs::read_dir(CONF_DIR) {
+        Ok(entries) => entries,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(e) => return Err(e.into()),
+    };
+    let mut ret = Vec::new();
+    for entry in entries.flatten() {
+        let path = entry.path();
+        let Some(ifname) = path
+            .file_name()
+            .and_then(|name| name.to_str())
+            .and_then(|name| name.strip_suffix(".conf"))
+        else {
+            continue;
+        };
+        let Ok(contents) = fs::read_to_string(&path) else {
+            continue;
+        };
+        if let Some((config, _)) = parse_conf(ifname, &contents) {
+            ret.push((config.interface, config.addresses));
+        }
+    }
+    Ok(ret)
+}
+
+fn validate(config: &WireguardConfig) -> Result<()> {
+    capture::validate_name(&config.interface)?;
+    if config.addresses.is_empty() {
+        return Err(anyhow!("no address"));
+    }
+    for addr in &config.addresses {
+        addr.parse::<IpNet>()
+            .map_err(|e| anyhow!("invalid address {}. {:?}", addr, e))?;
+    }
+    if config.listen_port == Some(0) {
+        return Err(anyhow!("listen port should be 1 to 65535"));
+    }
+    let peer = &config.peer;
+    if !is_key(&peer.public_key) {
+        return Err(anyhow!("invalid public key: {}", peer.public_key));
+    }
+    if let Some(endpoint) = &peer.endpoint {
+        if !is_endpoint(endpoint) {
+            return Err(anyhow!("invalid endpoint: {}", endpoint));
+        }
+    }
+    if peer.allowed_ips.is_empty() {
+        return Err(anyhow!("no allowed IP"));
+    }
+    for net in &peer.allowed_ips {
+        net.parse::<IpNet>()
+            .map_err(|e| anyhow!("invalid allowed IP {}. {:?}", net, e))?;
+    }
+    if peer.persistent_keepalive == Some(0) {
+        return Err(anyhow!("keepalive should be 1 to 65535 seconds"));
+    }
+    Ok(())
+}
+
+// A Curve25519 key in base64.
+fn is_key(key: &str) -> bool {
+    BASE64
+        .decode(key.as_bytes())
+        .is_ok_and(|key| key.len() == KEY_LEN)
+}
+
+// "host:port" or "[IPv6 address]:port".
+fn is_endpoint(endpoint: &str) -> bool {
+    let Some((host, port)) = endpoint.rsplit_once(':') else {
+        return false;
+    };
+    if !port.parse::<u16>().is_ok_and(|port| port > 0) {
+        return false;
+    }
+    if let Some(ip) = host.strip_prefix('[').and_then(|h| h.strip_suffix(']')) {
+        return ip.parse::<IpAddr>().is_ok_and(|ip| ip.is_ipv6());
+    }
+    !host.is_empty()
+        && host
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || "-.".contains(c))
+}
+
+fn generate_keys() -> Result<Keys> {
+    let output = run_command(command("wg").arg("genkey"))?;
+    let private = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if !output.status.success() || !is_key(&private) {
+        return Err(anyhow!("fail to generate a private key"));
+    }
+    let public = public_key(&private)?;
+    Ok(Keys { private, public })
+}
+
+// The private key is given through stdin, not to be seen in the command line.
+fn public_key(private: &str) -> Result<String> {
+    let mut child = Command::new("wg")
+        .arg("pubkey")
+        .env_clear()
+        .envs(SANITIZED_ENV)
+        .stdin(Stdio::piped())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let Some(mut stdin) = child.stdin.take() {
+        writeln!(stdin, "{private}")?;
+    }
+    let output = child.wait_with_output()?;
+    let public = String::from_utf8_lossy(&output.stdout).trim().to_string();
+    if output.status.success() && is_key(&public) {
+        Ok(public)
+    } else {
+        Err(anyhow!("fail to derive the public key"))
+    }
+}
+
+fn systemctl(args: &[&str]) -> Result<()> {
+    let output = run_command(command("systemctl").args(args))?;
+    if output.status.success() {
+        Ok(())
+    } else {
+        Err(anyhow!("fail to run systemctl {}", args.join(" ")))
+    }
+}
+
+fn unit_name(ifname: &str) -> String {
+    format!("wg-quick@{ifname}.service")
+}
+
+fn conf_path(ifname: &str) -> String {
+    format!("{CONF_DIR}/{ifname}.conf")
+}
+
+fn conf(config: &WireguardConfig, keys: &Keys) -> String {
+    let peer = &config.peer;
+    let optional = |name: &str, value: Option<String>| {
+        value
+            .map(|value| format!("{name} = {value}\n"))
+            .unwrap_or_default()
+    };
+    format!(
+        "# Written by roxy.\n\
+         # PublicKey = {public}\n\
+         [Interface]\n\
+         PrivateKey = {private}\n\
+         Address = {addresses}\n\
+         {listen_port}\
+         \n\
+         [Peer]\n\
+         PublicKey = {peer_key}\n\
+         {endpoint}\
+         AllowedIPs = {allowed_ips}\n\
+         {keepalive}",
+        public = keys.public,
+        private = keys.private,
+        addresses = config.addresses.join(", "),
+        listen_port = optional("ListenPort", config.listen_port.map(|p| p.to_string())),
+        peer_key = peer.public_key,
+        endpoint = optional("Endpoint", peer.endpoint.clone()),
+        allowed_ips = peer.allowed_ips.join(", "),
+        keepalive = optional(
+            "PersistentKeepalive",
+            peer.persistent_keepalive.map(|k| k.to_string())
+        ),
+    )
+}
+
+// Reads the configuration and the keys of the interface, or None if it
+// does not exist.
+fn read_conf(ifname: &str) -> Result<Option<(WireguardConfig, Keys)>> {
+    capture::validate_name(ifname)?;
+    let path = conf_path(ifname);
+    let contents = match fs::read_to_string(&path) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => return Ok(None),
+        Err(e) => return Err(e.into()),
+    };
+    let (config, keys) =
+        parse_conf(ifname, &contents).ok_or_else(|| anyhow!("invalid configuration: {}", path))?;
+    let keys = match keys {
+        (private, Some(public)) => Keys { private, public },
+        // Written by another program.
+        (private, None) => Keys {
+            public: public_key(&private)?,
+            private,
+        },
+    };
+    Ok(Some((config, keys)))
+}
+
+// Reads a configuration with a single peer, as written by `conf`.
+fn parse_conf(ifname: &str, contents: &str) -> Option<(WireguardConfig, (String, Option<String>))> {
+    let list = |value: &str| {
+        value
+            .split(',')
+            .map(|item| item.trim().to_string())
+            .filter(|item| !item.is_empty())
+            .collect::<Vec<_>>()
+    };
+    let mut public = None;
+    let mut private = None;
+    let mut addresses = Vec::new();
+    let mut listen_port = None;
+    let mut peer_key = None;
+    let mut endpoint = None;
+    let mut allowed_ips = Vec::new();
+    let mut persistent_keepalive = None;
+    let mut section = "";
+    for line in contents.lines().map(str::trim) {
+        if let Some(comment) = line.strip_prefix('#') {
+            if let Some(("PublicKey", value)) = comment
+                .split_once('=')
+                .map(|(name, value)| (name.trim(), value.trim()))
+            {
+                public = Some(value.to_string());
+            }
+            continue;
+        }
+        if line.starts_with('[') {
+            section = line;
+            continue;
+        }
+        let Some((name, value)) = line.split_once('=') else {
+            continue;
+        };
+        let value = value.trim();
+        match (section, name.trim()) {
+            ("[Interface]", "PrivateKey") => private = Some(value.to_string()),
+            ("[Interface]", "Address") => addresses.extend(list(value)),
+            ("[Interface]", "ListenPort") => listen_port = value.parse().ok(),
+            ("[Peer]", "PublicKey") => peer_key = Some(value.to_string()),
+            ("[Peer]", "Endpoint") => endpoint = Some(value.to_string()),
+            ("[Peer]", "AllowedIPs") => allowed_ips.extend(list(value)),
+            ("[Peer]", "PersistentKeepalive") => persistent_keepalive = value.parse().ok(),
+            _ => {}
+        }
+    }
+    Some((
+        WireguardConfig {
+            interface: ifname.to_string(),
+            addresses,
+            listen_port,
+            peer: WireguardPeer {
+                public_key: peer_key?,
+                endpoint,
+                allowed_ips,
+                persistent_keepalive,
+            },
+        },
+        (private?, public.filter(|key| is_key(key))),
+    ))
+}
+
+// The latest handshake and the bytes received from and sent to the peer, in
+// the output of `wg show <interface> dump`. The first line is of the
+// interface, and the others are of its peers, e.g.
+//
+// ```text
+// <public key>  <preshared key>  <endpoint>  <allowed ips>  <latest handshake>  <rx>  <tx>  <keepalive>
+// ```
+fn transfer(dump: &str, peer_key: &str) -> (Option<i64>, u64, u64) {
+    dump.lines()
+        .skip(1)
+        .map(|line| line.split('\t').collect::<Vec<_>>())
+        .find(|fields| fields.len() >= 7 && fields[0] == peer_key)
+        .map_or((None, 0, 0), |fields| {
+            let handshake = fields[4].parse::<i64>().ok().filter(|&t| t > 0);
+            (
+                handshake,
+                fields[5].parse().unwrap_or_default(),
+                fields[6].parse().unwrap_or_default(),
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const PEER_KEY: &str = "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=";
+    const PRIVATE_KEY: &str = "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=";
+    const PUBLIC_KEY: &str = "HIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PKf8ykw=";
+
+    fn config() -> WireguardConfig {
+        WireguardConfig {
+            interface: "wg-mgmt".to_string(),
+            addresses: vec!["10.99.0.2/24".to_string()],
+            listen_port: None,
+            peer: WireguardPeer {
+                public_key: PEER_KEY.to_string(),
+                endpoint: Some("manager.example.com:51820".to_string()),
+                allowed_ips: vec!["10.99.0.0/24".to_string(), "192.0.2.10/32".to_string()],
+                persistent_keepalive: Some(25),
+            },
+        }
+    }
+
+    #[test]
+    fn conf_round_trip() {
+        let keys = Keys {
+            private: PRIVATE_KEY.to_string(),
+            public: PUBLIC_KEY.to_string(),
+        };
+        let contents = conf(&config(), &keys);
+        assert!(contents.contains("\nPrivateKey = yAnz5TF"));
+        assert!(contents.contains("\nAllowedIPs = 10.99.0.0/24, 192.0.2.10/32\n"));
+        assert!(!contents.contains("ListenPort"));
+        let (parsed, (private, public)) = parse_conf("wg-mgmt", &contents).expect("valid conf");
+        assert_eq!(parsed, config());
+        assert_eq!(private, PRIVATE_KEY);
+        assert_eq!(public.as_deref(), Some(PUBLIC_KEY));
+
+        let mut config = config();
+        config.listen_port = Some(51820);
+        config.peer.endpoint = None;
+        config.peer.persistent_keepalive = None;
+        let contents = conf(&config, &keys);
+        assert_eq!(
+            parse_conf("wg-mgmt", &contents).map(|(config, _)| config),
+            Some(config)
+        );
+        assert!(parse_conf("wg-mgmt", "[Interface]\nAddress = 10.0.0.1/24\n").is_none());
+    }
+
+    #[test]
+    fn invalid_configs() {
+        assert!(validate(&config()).is_ok());
+        let mut invalid = vec![config(); 7];
+        invalid[0].interface = "wg/0".to_string();
+        invalid[1].addresses = vec!["10.99.0.2".to_string()];
+        invalid[2].peer.public_key = "not-a-key".to_string();
+        invalid[3].peer.endpoint = Some("manager.example.com".to_string());
+        invalid[4].peer.endpoint = Some("manager example:51820".to_string());
+        invalid[5].peer.allowed_ips = Vec::new();
+        invalid[6].peer.persistent_keepalive = Some(0);
+        for config in invalid {
+            assert!(validate(&config).is_err(), "{config:?}");
+        }
+        assert!(is_endpoint("[2001:db8::1]:51820"));
+        assert!(!is_endpoint("[10.0.0.1]:51820"));
+    }
+
+    #[test]
+    fn peer_transfer() {
+        let dump = format!(
+            "{PRIVATE_KEY}\t{PUBLIC_KEY}\t41413\toff\n\
+             {PEER_KEY}\t(none)\t192.0.2.1:51820\t10.99.0.0/24\t1700000000\t1024\t2048\t25\n"
+        );
+        assert_eq!(transfer(&dump, PEER_KEY), (Some(1_700_000_000), 1024, 2048));
+        let idle = dump.replace("1700000000", "0");
+        assert_eq!(transfer(&idle, PEER_KEY), (None, 1024, 2048));
+        assert_eq!(transfer(&dump, PUBLIC_KEY), (None, 0, 0));
+    }
+}
@@ -1,3 +1,14 @@
+pub mod cache;
+pub mod clock;
+pub mod ethtool;
 pub mod hwinfo;
+pub mod ifstat;
+pub mod link;
+pub mod monitor;
+pub mod notify;
 pub mod process;
+pub mod reboot;
+pub mod scheduler;
 pub mod usg;
+pub mod watch;
+pub mod watchdog;
@@ -0,0 +1,156 @@
+use std::{
+    sync::Mutex,
+    time::{Duration, Instant},
+};
+
+use anyhow::Result;
+
+use super::process::{process_list, Process};
+use crate::common::{DiskHealth, Page, PageRequest};
+
+const DEFAULT_PROCESS_LIST_TTL: Duration = Duration::from_secs(5);
+const DEFAULT_DISK_HEALTH_TTL: Duration = Duration::from_mins(5);
+
+#[derive(Debug)]
+struct Entry<T> {
+    value: T,
+    collected: Instant,
+}
+
+/// Caches the results of expensive read-only queries for a while, so that
+/// frequent polling does not collect them again each time. Each query has
+/// its own time to live, and a query given `bypass` collects the result
+/// again and replaces the cached one. Errors are not cached.
+///
+/// # Example
+///
+/// ```ignore
+/// let cache = ResponseCache::new().disk_health_ttl(Duration::from_mins(10));
+/// let processes = cache.process_list(false).await;
+/// let disks = cache.disk_health(true)?;
+/// ```
+#[derive(Debug)]
+pub struct ResponseCache {
+    process_list_ttl: Duration,
+    disk_health_ttl: Duration,
+    processes: Mutex<Option<Entry<Vec<Process>>>>,
+    disks: Mutex<Option<Entry<Vec<DiskHealth>>>>,
+}
+
+impl Default for ResponseCache {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ResponseCache {
+    /// Creates a cache with the time to live of 5 seconds for the process
+    /// list, and 5 minutes for the disk health.
+    #[must_use]
+    pub fn new() -> Self {
+        ResponseCache {
+            process_list_ttl: DEFAULT_PROCESS_LIST_TTL,
+            disk_health_ttl: DEFAULT_DISK_HEALTH_TTL,
+            processes: Mutex::new(None),
+            disks: Mutex::new(None),
+        }
+    }
+
+    /// Sets the time to live of the process list. Zero disables caching.
+    #[must_use]
+    pub fn process_list_ttl(mut self, ttl: Duration) -> Self {
+        self.process_list_ttl = ttl;
+        self
+    }
+
+    /// Sets the time to live of the disk health. Zero disables caching.
+    #[must_use]
+    pub fn disk_health_ttl(mut self, ttl: Duration) -> Self {
+        self.disk_health_ttl = ttl;
+        self
+    }
+
+    /// Returns the processes as [`process_list`] does, cached unless
+    /// `bypass` is true.
+    pub async fn process_list(&self, bypass: bool) -> Vec<Process> {
+        if !bypass {
+            if let Some(processes) = cached(&self.processes, self.process_list_ttl) {
+                return processes;
+            }
+        }
+        let processes = process_list().await;
+        store(&self.processes, &processes);
+        processes
+    }
+
+    /// Returns a page of the processes returned by
+    /// [`ResponseCache::process_list`]. The pages of the same cached list do
+    /// not overlap.
+    pub async fn process_page(&self, page: PageRequest, bypass: bool) -> Page<Process> {
+        Page::of(self.process_list(bypass).await, page)
+    }
+
+    /// Returns the SMART health of the disks as [`crate::disk_health`] does,
+    /// cached unless `bypass` is true.
+    ///
+    /// # Errors
+    ///
+    /// * Return error if [`crate::disk_health`] returns error
+    pub fn disk_health(&self, bypass: bool) -> Result<Vec<DiskHealth>> {
+        if !bypass {
+            if let Some(disks) = cached(&self.disks, self.disk_health_ttl) {
+                return Ok(disks);
+            }
+        }
+        let disks = crate::disk_health()?;
+        store(&self.disks, &disks);
+        Ok(disks)
+    }
+
+    /// Removes the cached results.
+    pub fn clear(&self) {
+        clear(&self.processes);
+        clear(&self.disks);
+    }
+}
+
+// The cached value if it has not expired. The lock is not held while a value
+// is being collected, so concurrent misses may collect it more than once.
+fn cached<T: Clone>(entry: &Mutex<Option<Entry<T>>>, ttl: Duration) -> Option<T> {
+    let entry = entry.lock().ok()?;
+    entry
+        .as_ref()
+        .filter(|entry| entry.collected.elapsed() < ttl)
+        .map(|entry| entry.value.clone())
+}
+
+fn store<T: Clone>(entry: &Mutex<Option<Entry<T>>>, value: &T) {
+    if let Ok(mut entry) = entry.lock() {
+        *entry = Some(Entry {
+            value: value.clone(),
+            collected: Instant::now(),
+        });
+    }
+}
+
+fn clear<T>(entry: &Mutex<Option<Entry<T>>>) {
+    if let Ok(mut entry) = entry.lock() {
+        *entry = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn time_to_live() {
+        let entry = Mutex::new(None);
+        assert_eq!(cached::<u32>(&entry, Duration::from_mins(1)), None);
+        store(&entry, &7);
+        assert_eq!(cached(&entry, Duration::from_mins(1)), Some(7));
+        assert_eq!(cached(&entry, Duration::ZERO), None);
+        clear(&entry);
+        assert_eq!(cached::<u32>(&entry, Duration::from_mins(1)), None);
+    }
+}
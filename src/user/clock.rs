@@ -0,0 +1,135 @@
+use std::time::Duration;
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+use crate::common::{command, run_command};
+
+/// How far the clock of this node is from the clock of a server, such as the
+/// Manager, measured with the `Date` header of an HTTP response.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct ClockSkew {
+    /// The time of the server in RFC 3339 format.
+    pub server_time: String,
+    /// Seconds this node is ahead of the server, or behind it if negative.
+    /// The `Date` header has a resolution of a second.
+    pub skew: i64,
+    /// Round-trip time of the request in milliseconds.
+    pub round_trip_ms: u64,
+}
+
+impl ClockSkew {
+    /// Returns true if the clocks differ by `tolerance` or less.
+    #[must_use]
+    pub fn is_within(&self, tolerance: Duration) -> bool {
+        self.skew.unsigned_abs() <= tolerance.as_secs()
+    }
+}
+
+/// Compares the clock of this node with the `Date` header of the response of
+/// an HTTP or HTTPS server, such as the Manager before enrolling with it. A
+/// skewed clock makes certificates look expired or not yet valid, which fails
+/// mutual TLS with an error which does not mention the clock.
+///
+/// The certificate of the server is not verified, since it cannot be while
+/// the clock is wrong, and nothing but the time is read from the response.
+/// `curl` is used to send the request.
+///
+/// # Errors
+///
+/// * The URL is not an HTTP or HTTPS URL
+/// * Failure to connect to the server in `timeout`
+/// * The response has no valid `Date` header
+pub fn clock_skew(url: &str, timeout: Duration) -> Result<ClockSkew> {
+    if !url.starts_with("https://") && !url.starts_with("http://") {
+        return Err(anyhow!("invalid url: {}", url));
+    }
+    let sent = Utc::now();
+    let output = run_command(
+        command("curl")
+            .args(["--silent", "--show-error", "--head", "--insecure"])
+            .args(["--proto", "=http,https"])
+            .arg("--max-time")
+            .arg(timeout.as_secs().max(1).to_string())
+            .arg(url),
+    )?;
+    let received = Utc::now();
+    if !output.status.success() {
+        return Err(anyhow!(
+            "fail to connect to {}. {}",
+            url,
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    let server_time = parse_date(&String::from_utf8_lossy(&output.stdout))
+        .ok_or_else(|| anyhow!("no Date header in the response of {}", url))?;
+    Ok(skew(sent, received, server_time))
+}
+
+// The `Date` header of the last response, e.g. after "100 Continue".
+fn parse_date(headers: &str) -> Option<DateTime<Utc>> {
+    headers
+        .lines()
+        .filter_map(|line| {
+            let (name, value) = line.split_once(':')?;
+            name.trim().eq_ignore_ascii_case("date").then_some(value)
+        })
+        .next_back()
+        .and_then(|value| DateTime::parse_from_rfc2822(value.trim()).ok())
+        .map(|time| time.with_timezone(&Utc))
+}
+
+// The server read its clock about halfway through the request, and the
+// `Date` header truncates it to the second.
+fn skew(sent: DateTime<Utc>, received: DateTime<Utc>, server_time: DateTime<Utc>) -> ClockSkew {
+    let round_trip = received - sent;
+    let local = sent + round_trip / 2;
+    let server = server_time + chrono::Duration::milliseconds(500);
+    let skew_ms = (local - server).num_milliseconds();
+    ClockSkew {
+        server_time: server_time.to_rfc3339(),
+        skew: (skew_ms + 500).div_euclid(1000),
+        round_trip_ms: u64::try_from(round_trip.num_milliseconds()).unwrap_or_default(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn date_header() {
+        let headers = "HTTP/1.1 100 Continue\r\n\
+                       Date: Sun, 06 Nov 1994 08:49:36 GMT\r\n\
+                       \r\n\
+                       HTTP/1.1 405 Method Not Allowed\r\n\
+                       content-type: text/plain\r\n\
+                       date: Sun, 06 Nov 1994 08:49:37 GMT\r\n\r\n";
+        assert_eq!(
+            parse_date(headers).map(|date| date.to_rfc3339()).as_deref(),
+            Some("1994-11-06T08:49:37+00:00")
+        );
+        assert_eq!(parse_date("HTTP/1.1 200 OK\r\n\r\n"), None);
+        assert_eq!(parse_date("Date: yesterday\r\n"), None);
+    }
+
+    #[test]
+    fn skew_from_midpoint() {
+        let server = DateTime::parse_from_rfc2822("Sun, 06 Nov 1994 08:49:37 GMT")
+            .expect("valid date")
+            .with_timezone(&Utc);
+        let sent = server + chrono::Duration::seconds(90);
+        let received = sent + chrono::Duration::milliseconds(1000);
+        let skew = skew(sent, received, server);
+        assert_eq!(skew.skew, 90);
+        assert_eq!(skew.round_trip_ms, 1000);
+        assert!(skew.is_within(Duration::from_secs(90)));
+        assert!(!skew.is_within(Duration::from_secs(89)));
+
+        let sent = server - chrono::Duration::hours(2);
+        let behind = super::skew(sent, sent + chrono::Duration::seconds(1), server);
+        assert_eq!(behind.skew, -7200);
+        assert!(!behind.is_within(Duration::from_secs(30)));
+    }
+}
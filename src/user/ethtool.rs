@@ -0,0 +1,181 @@
+use std::{
+    collections::BTreeMap,
+    fs,
+    io::{Error, ErrorKind, Result},
+    path::Path,
+};
+
+use serde::{Deserialize, Serialize};
+
+use crate::common::{command, run_command};
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+/// Names of the NIC statistics returned by [`nic_info`]. They count the
+/// packets dropped in the NIC before the kernel sees them, e.g. because the
+/// ring buffer was full. Drivers name them differently, and report only
+/// some of them.
+pub const DROP_STATISTICS: [&str; 6] = [
+    "rx_missed_errors",
+    "rx_fifo_errors",
+    "rx_no_buffer_count",
+    "rx_dropped",
+    "rx_out_of_buffer",
+    "rx_discards_phy",
+];
+
+/// Driver and firmware of a NIC, and the counters of packets it dropped.
+#[derive(Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct NicInfo {
+    pub ifname: String,
+    /// The driver, or None for a virtual interface.
+    pub driver: Option<String>,
+    pub driver_version: Option<String>,
+    pub firmware_version: Option<String>,
+    /// The PCI address of the NIC, e.g. "0000:03:00.0".
+    pub bus_info: Option<String>,
+    /// Those of [`DROP_STATISTICS`] the driver reports.
+    pub statistics: BTreeMap<String, u64>,
+}
+
+/// Returns the driver, firmware and drop counters of the NIC, as reported
+/// by `ethtool -i` and `ethtool -S`. A counter not reported by the driver is
+/// taken from `/sys/class/net/<ifname>/statistics`, if it is there.
+///
+/// # Errors
+///
+/// Returns an error if the interface does not exist, or if it fails to run
+/// ethtool.
+pub fn nic_info(ifname: &str) -> Result<NicInfo> {
+    // The name goes into the arguments of ethtool.
+    if ifname.is_empty() || ifname.starts_with('-') || ifname.contains('/') {
+        return Err(Error::new(
+            ErrorKind::InvalidInput,
+            format!("invalid interface name: {ifname}"),
+        ));
+    }
+    let dir = Path::new(SYS_CLASS_NET).join(ifname);
+    if !dir.exists() {
+        return Err(Error::new(
+            ErrorKind::NotFound,
+            format!("interface \"{ifname}\" not found"),
+        ));
+    }
+
+    let mut info = NicInfo {
+        ifname: ifname.to_string(),
+        ..NicInfo::default()
+    };
+    // A virtual interface has no driver information or NIC statistics, and
+    // ethtool fails.
+    let output = run_command(command("ethtool").args(["-i", ifname]))?;
+    if output.status.success() {
+        parse_driver(&String::from_utf8_lossy(&output.stdout), &mut info);
+    }
+    let output = run_command(command("ethtool").args(["-S", ifname]))?;
+    if output.status.success() {
+        info.statistics = parse_statistics(&String::from_utf8_lossy(&output.stdout));
+    }
+    for name in DROP_STATISTICS {
+        if info.statistics.contains_key(name) {
+            continue;
+        }
+        if let Some(value) = fs::read_to_string(dir.join("statistics").join(name))
+            .ok()
+            .and_then(|s| s.trim().parse().ok())
+        {
+            info.statistics.insert(name.to_string(), value);
+        }
+    }
+    Ok(info)
+}
+
+/// Returns [`nic_info`] of all NICs, excluding virtual interfaces.
+///
+/// # Errors
+///
+/// Returns an error if it fails to read `/sys/class/net` or to run ethtool.
+pub fn nic_infos() -> Result<Vec<NicInfo>> {
+    let mut names = fs::read_dir(SYS_CLASS_NET)?
+        .flatten()
+        .filter(|entry| entry.path().join("device").exists())
+        .map(|entry| entry.file_name().to_string_lossy().to_string())
+        .collect::<Vec<_>>();
+    names.sort();
+    names.iter().map(|name| nic_info(name)).collect()
+}
+
+// Parses the output of `ethtool -i`, e.g. "firmware-version: 3.30, 0x800005cc".
+fn parse_driver(output: &str, info: &mut NicInfo) {
+    for line in output.lines() {
+        let Some((key, value)) = line.split_once(':') else {
+            continue;
+        };
+        let value = value.trim();
+        if value.is_empty() || value == "N/A" {
+            continue;
+        }
+        let field = match key.trim() {
+            "driver" => &mut info.driver,
+            "version" => &mut info.driver_version,
+            "firmware-version" => &mut info.firmware_version,
+            "bus-info" => &mut info.bus_info,
+            _ => continue,
+        };
+        *field = Some(value.to_string());
+    }
+}
+
+// Parses those of `DROP_STATISTICS` in the output of `ethtool -S`.
+fn parse_statistics(output: &str) -> BTreeMap<String, u64> {
+    output
+        .lines()
+        .filter_map(|line| {
+            let (key, value) = line.split_once(':')?;
+            let key = key.trim();
+            if !DROP_STATISTICS.contains(&key) {
+                return None;
+            }
+            Some((key.to_string(), value.trim().parse().ok()?))
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ethtool_output() {
+        let mut info = NicInfo::default();
+        parse_driver(
+            "driver: igb
+version: 5.15.0-91-generic
+firmware-version: 3.30, 0x800005cc
+expansion-rom-version:
+bus-info: 0000:03:00.0
+supports-statistics: yes
+",
+            &mut info,
+        );
+        assert_eq!(info.driver.as_deref(), Some("igb"));
+        assert_eq!(info.driver_version.as_deref(), Some("5.15.0-91-generic"));
+        assert_eq!(info.firmware_version.as_deref(), Some("3.30, 0x800005cc"));
+        assert_eq!(info.bus_info.as_deref(), Some("0000:03:00.0"));
+
+        let statistics = parse_statistics(
+            "NIC statistics:
+     rx_packets: 1843221
+     rx_missed_errors: 12
+     rx_fifo_errors: 3
+     tx_queue_0_restart: 0
+",
+        );
+        assert_eq!(
+            statistics.into_iter().collect::<Vec<_>>(),
+            [
+                ("rx_fifo_errors".to_string(), 3),
+                ("rx_missed_errors".to_string(), 12)
+            ]
+        );
+    }
+}
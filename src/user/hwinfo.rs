@@ -1,7 +1,11 @@
 use std::{fmt, fs::File, io::Read, time::Duration};
 
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
 use thiserror::Error;
 
+use super::reboot::{reboot_required, RebootReason};
+
 const DEFAULT_VERSION_STRING: &str = "AICE security";
 // TODO: should change this path to /usr/local/aice/conf/version?
 const DEFAULT_VERSION_PATH: &str = "/etc/version";
@@ -38,6 +42,99 @@ pub fn uptime() -> Result<Duration, UptimeError> {
     })
 }
 
+/// Boot time, uptime, and load averages of the system.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct BootInfo {
+    /// The time when the system booted, in seconds since the Unix epoch.
+    pub boot_time: u64,
+    /// How long the system has been running, in seconds.
+    pub uptime: u64,
+    /// The average load within one, five, and fifteen minutes.
+    pub load_average: (f64, f64, f64),
+    /// The reasons why the system should be rebooted. See
+    /// [`reboot_required`].
+    pub reboot_required: Vec<RebootReason>,
+}
+
+impl fmt::Display for BootInfo {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "up {}, load average: {:.2}, {:.2}, {:.2}",
+            format_uptime(Duration::from_secs(self.uptime)),
+            self.load_average.0,
+            self.load_average.1,
+            self.load_average.2
+        )?;
+        if !self.reboot_required.is_empty() {
+            write!(f, ", reboot required")?;
+        }
+        Ok(())
+    }
+}
+
+/// Returns the boot time, uptime, and load averages of the system, and
+/// whether it should be rebooted.
+///
+/// # Errors
+///
+/// Returns an error if the operating system does not return uptime or boottime.
+///
+/// # Examples
+///
+/// ```rust
+/// # use std::error::Error;
+/// # fn main() -> Result<(), Box<dyn Error>> {
+/// let info = roxy::boot_info()?;
+/// println!("{info}");
+/// #     Ok(())
+/// # }
+/// ```
+pub fn boot_info() -> Result<BootInfo, UptimeError> {
+    let boot_time = System::boot_time();
+    if boot_time == 0 {
+        return Err(UptimeError {
+            message: "fail to get boot time".to_string(),
+        });
+    }
+    let load = System::load_average();
+    Ok(BootInfo {
+        boot_time,
+        uptime: uptime()?.as_secs(),
+        load_average: (load.one, load.five, load.fifteen),
+        reboot_required: reboot_required(),
+    })
+}
+
+/// Formats uptime like `uptime` command does.
+///
+/// # Examples
+///
+/// ```rust
+/// use std::time::Duration;
+///
+/// assert_eq!(roxy::format_uptime(Duration::from_secs(720)), "12 min");
+/// assert_eq!(roxy::format_uptime(Duration::from_secs(273_900)), "3 days, 4:05");
+/// ```
+#[must_use]
+pub fn format_uptime(uptime: Duration) -> String {
+    let secs = uptime.as_secs();
+    let days = secs / 86_400;
+    let hours = secs % 86_400 / 3_600;
+    let mins = secs % 3_600 / 60;
+
+    let time = if hours == 0 {
+        format!("{mins} min")
+    } else {
+        format!("{hours}:{mins:02}")
+    };
+    match days {
+        0 => time,
+        1 => format!("1 day, {time}"),
+        _ => format!("{days} days, {time}"),
+    }
+}
+
 /// Returns OS and Product versions by reading /etc/version.
 ///
 /// # Example
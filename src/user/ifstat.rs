@@ -0,0 +1,184 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs,
+    io::Result,
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_CAPACITY: usize = 360;
+
+/// Byte and packet counters of an interface since it came up.
+#[derive(Copy, Clone, Debug, Default, Deserialize, Serialize, PartialEq, Eq)]
+pub struct InterfaceCounters {
+    pub rx_bytes: u64,
+    pub rx_packets: u64,
+    pub tx_bytes: u64,
+    pub tx_packets: u64,
+}
+
+impl InterfaceCounters {
+    fn delta(&self, prev: &Self) -> Self {
+        // A counter smaller than the previous one means the interface was
+        // reset, so the current value is the delta.
+        let delta = |cur: u64, prev: u64| cur.checked_sub(prev).unwrap_or(cur);
+        InterfaceCounters {
+            rx_bytes: delta(self.rx_bytes, prev.rx_bytes),
+            rx_packets: delta(self.rx_packets, prev.rx_packets),
+            tx_bytes: delta(self.tx_bytes, prev.tx_bytes),
+            tx_packets: delta(self.tx_packets, prev.tx_packets),
+        }
+    }
+}
+
+/// Counters of an interface at a point in time.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct InterfaceSample {
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+    pub counters: InterfaceCounters,
+}
+
+/// Increase of counters between two consecutive samples.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct InterfaceDelta {
+    /// Seconds since the Unix epoch, at the end of the interval.
+    pub time: i64,
+    /// Length of the interval in seconds.
+    pub interval: i64,
+    pub counters: InterfaceCounters,
+}
+
+/// Returns the counters of all interfaces.
+///
+/// # Errors
+///
+/// Returns an error if it fails to read `/sys/class/net`.
+pub fn interface_counters() -> Result<Vec<(String, InterfaceCounters)>> {
+    let mut ret = Vec::new();
+    for entry in fs::read_dir(SYS_CLASS_NET)?.flatten() {
+        let name = entry.file_name().to_string_lossy().to_string();
+        let stats = entry.path().join("statistics");
+        let read = |counter: &str| read_counter(&stats, counter);
+        ret.push((
+            name,
+            InterfaceCounters {
+                rx_bytes: read("rx_bytes"),
+                rx_packets: read("rx_packets"),
+                tx_bytes: read("tx_bytes"),
+                tx_packets: read("tx_packets"),
+            },
+        ));
+    }
+    ret.sort_by(|a, b| a.0.cmp(&b.0));
+    Ok(ret)
+}
+
+fn read_counter(dir: &Path, counter: &str) -> u64 {
+    fs::read_to_string(dir.join(counter))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+        .unwrap_or_default()
+}
+
+type Samples = HashMap<String, VecDeque<InterfaceSample>>;
+
+/// Samples the counters of all interfaces on a background thread and keeps
+/// the latest samples of each interface. By default, it samples every 10
+/// seconds and keeps the samples for the last hour.
+///
+/// The thread stops when the sampler is dropped.
+///
+/// # Example
+///
+/// ```ignore
+/// let sampler = InterfaceSampler::start(Duration::from_secs(10), 360);
+/// // ...
+/// for delta in sampler.deltas("eno1") {
+///     println!("{}: {} bytes received", delta.time, delta.counters.rx_bytes);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct InterfaceSampler {
+    samples: Arc<Mutex<Samples>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Default for InterfaceSampler {
+    fn default() -> Self {
+        Self::start(DEFAULT_INTERVAL, DEFAULT_CAPACITY)
+    }
+}
+
+impl InterfaceSampler {
+    /// Starts sampling every `interval`, keeping `capacity` samples per
+    /// interface.
+    #[must_use]
+    pub fn start(interval: Duration, capacity: usize) -> Self {
+        let samples = Arc::new(Mutex::new(Samples::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_samples = Arc::clone(&samples);
+        let thread_stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            while !thread_stop.load(Ordering::Relaxed) {
+                if let Ok(counters) = interface_counters() {
+                    let time = Utc::now().timestamp();
+                    if let Ok(mut samples) = thread_samples.lock() {
+                        samples.retain(|name, _| counters.iter().any(|(n, _)| n == name));
+                        for (name, counters) in counters {
+                            let buf = samples.entry(name).or_default();
+                            if buf.len() >= capacity {
+                                buf.pop_front();
+                            }
+                            buf.push_back(InterfaceSample { time, counters });
+                        }
+                    }
+                }
+                thread::sleep(interval);
+            }
+        });
+
+        InterfaceSampler { samples, stop }
+    }
+
+    /// Returns the samples of the interface, oldest first.
+    #[must_use]
+    pub fn samples(&self, ifname: &str) -> Vec<InterfaceSample> {
+        self.samples
+            .lock()
+            .ok()
+            .and_then(|samples| samples.get(ifname).map(|buf| buf.iter().copied().collect()))
+            .unwrap_or_default()
+    }
+
+    /// Returns the increase of the counters between consecutive samples of
+    /// the interface, oldest first.
+    #[must_use]
+    pub fn deltas(&self, ifname: &str) -> Vec<InterfaceDelta> {
+        self.samples(ifname)
+            .windows(2)
+            .map(|w| InterfaceDelta {
+                time: w[1].time,
+                interval: w[1].time - w[0].time,
+                counters: w[1].counters.delta(&w[0].counters),
+            })
+            .collect()
+    }
+}
+
+impl Drop for InterfaceSampler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
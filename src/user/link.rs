@@ -0,0 +1,377 @@
+use std::{
+    collections::{HashMap, VecDeque},
+    fs, io,
+    io::Result,
+    mem,
+    os::fd::{AsRawFd, FromRawFd, OwnedFd},
+    path::Path,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
+
+use chrono::Utc;
+use serde::{Deserialize, Serialize};
+
+const SYS_CLASS_NET: &str = "/sys/class/net";
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+// Large enough for a burst of link notifications, which are only counted.
+const NETLINK_BUFFER_SIZE: usize = 16 * 1024;
+const DEFAULT_CAPACITY: usize = 100;
+
+/// Carrier state of an interface, and its transitions between up and down.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LinkState {
+    pub ifname: String,
+    /// Whether the link is up, or None if the interface is administratively
+    /// down.
+    pub carrier: Option<bool>,
+    /// Carrier transitions counted by the kernel since the interface was
+    /// created.
+    pub carrier_changes: u64,
+    pub carrier_up_count: u64,
+    pub carrier_down_count: u64,
+    /// Seconds since the Unix epoch when a [`LinkMonitor`] last saw a
+    /// transition, or None if it has not seen any.
+    pub last_change: Option<i64>,
+}
+
+/// Carrier transitions seen by a [`LinkMonitor`] in one read of the state.
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct LinkTransition {
+    /// Seconds since the Unix epoch.
+    pub time: i64,
+    /// The carrier after the transitions.
+    pub carrier: Option<bool>,
+    /// The number of transitions, which is more than one if the link
+    /// flapped faster than the state was read.
+    pub changes: u64,
+}
+
+/// Returns the carrier state of all interfaces. `last_change` is always None,
+/// since it is known only to a [`LinkMonitor`].
+///
+/// # Errors
+///
+/// Returns an error if it fails to read `/sys/class/net`.
+pub fn link_states() -> Result<Vec<LinkState>> {
+    let mut ret = Vec::new();
+    for entry in fs::read_dir(SYS_CLASS_NET)?.flatten() {
+        let dir = entry.path();
+        let read = |counter: &str| read_value(&dir, counter);
+        ret.push(LinkState {
+            ifname: entry.file_name().to_string_lossy().to_string(),
+            // Reading the carrier of an interface which is down fails.
+            carrier: read("carrier").map(|carrier| carrier == 1),
+            carrier_changes: read("carrier_changes").unwrap_or_default(),
+            carrier_up_count: read("carrier_up_count").unwrap_or_default(),
+            carrier_down_count: read("carrier_down_count").unwrap_or_default(),
+            last_change: None,
+        });
+    }
+    ret.sort_by(|a, b| a.ifname.cmp(&b.ifname));
+    Ok(ret)
+}
+
+fn read_value(dir: &Path, name: &str) -> Option<u64> {
+    fs::read_to_string(dir.join(name))
+        .ok()
+        .and_then(|s| s.trim().parse().ok())
+}
+
+// A netlink socket subscribed to the link notifications of the kernel, so
+// that a transition is seen when it happens instead of at the next poll.
+struct LinkEvents(OwnedFd);
+
+impl LinkEvents {
+    // Opens the socket. Waiting for a notification gives up after `timeout`.
+    fn open(timeout: Duration) -> Result<Self> {
+        // SAFETY: socket takes no pointer, and its result is checked.
+        let fd = unsafe {
+            libc::socket(
+                libc::AF_NETLINK,
+                libc::SOCK_RAW | libc::SOCK_CLOEXEC,
+                libc::NETLINK_ROUTE,
+            )
+        };
+        if fd < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        // SAFETY: fd is a new socket which nothing else owns.
+        let socket = LinkEvents(unsafe { OwnedFd::from_raw_fd(fd) });
+
+        // SAFETY: sockaddr_nl is plain data, for which all zeros is valid.
+        let mut addr: libc::sockaddr_nl = unsafe { mem::zeroed() };
+        addr.nl_family = libc::sa_family_t::try_from(libc::AF_NETLINK).unwrap_or_default();
+        addr.nl_groups = libc::RTMGRP_LINK.try_into().unwrap_or_default();
+        // SAFETY: addr is a valid sockaddr_nl of the given length.
+        let ret = unsafe {
+            libc::bind(
+                socket.0.as_raw_fd(),
+                (&raw const addr).cast(),
+                socklen_of::<libc::sockaddr_nl>(),
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+
+        let timeout = libc::timeval {
+            tv_sec: libc::time_t::try_from(timeout.as_secs()).unwrap_or(libc::time_t::MAX),
+            tv_usec: timeout.subsec_micros().into(),
+        };
+        // SAFETY: timeout is a valid timeval of the given length.
+        let ret = unsafe {
+            libc::setsockopt(
+                socket.0.as_raw_fd(),
+                libc::SOL_SOCKET,
+                libc::SO_RCVTIMEO,
+                (&raw const timeout).cast(),
+                socklen_of::<libc::timeval>(),
+            )
+        };
+        if ret < 0 {
+            return Err(io::Error::last_os_error());
+        }
+        Ok(socket)
+    }
+
+    // Waits for link notifications. They are not parsed, since the state is
+    // read from sysfs after them.
+    //
+    // # Return
+    //
+    // * false if no notification came before the timeout
+    fn wait(&self) -> Result<bool> {
+        let mut buf = [0_u8; NETLINK_BUFFER_SIZE];
+        // SAFETY: buf is valid for writes of its length.
+        let len = unsafe { libc::recv(self.0.as_raw_fd(), buf.as_mut_ptr().cast(), buf.len(), 0) };
+        if len >= 0 {
+            return Ok(true);
+        }
+        let e = io::Error::last_os_error();
+        match e.kind() {
+            io::ErrorKind::WouldBlock | io::ErrorKind::TimedOut | io::ErrorKind::Interrupted => {
+                Ok(false)
+            }
+            // Notifications were dropped, so the state has to be read again.
+            _ if e.raw_os_error() == Some(libc::ENOBUFS) => Ok(true),
+            _ => Err(e),
+        }
+    }
+}
+
+fn socklen_of<T>() -> libc::socklen_t {
+    libc::socklen_t::try_from(mem::size_of::<T>()).unwrap_or_default()
+}
+
+#[derive(Debug)]
+struct Link {
+    state: LinkState,
+    transitions: VecDeque<LinkTransition>,
+}
+
+impl Link {
+    // Updates the state with a newer one, and records a transition if the
+    // carrier changed since the last read.
+    fn observe(&mut self, mut state: LinkState, time: i64, capacity: usize) {
+        // The counter restarts if the interface is recreated.
+        let changes = state
+            .carrier_changes
+            .checked_sub(self.state.carrier_changes)
+            .unwrap_or(state.carrier_changes);
+        if changes > 0 || state.carrier != self.state.carrier {
+            if self.transitions.len() >= capacity {
+                self.transitions.pop_front();
+            }
+            self.transitions.push_back(LinkTransition {
+                time,
+                carrier: state.carrier,
+                changes: changes.max(1),
+            });
+            state.last_change = Some(time);
+        } else {
+            state.last_change = self.state.last_change;
+        }
+        self.state = state;
+    }
+}
+
+/// Watches the carrier of all interfaces on a background thread, and records
+/// when each link goes up or down, so that intermittent cabling issues
+/// become visible. The thread subscribes to the link notifications of the
+/// kernel over rtnetlink, and reads the state again on each of them. It also
+/// reads the state at an interval, every 10 seconds by default, in case a
+/// notification is lost; without rtnetlink, it only polls at the interval.
+/// It keeps the last 100 transitions of each interface by default.
+///
+/// The thread stops within the interval after the monitor is dropped.
+///
+/// # Example
+///
+/// ```ignore
+/// let monitor = LinkMonitor::default();
+/// // ...
+/// for ifname in monitor.flapping(Duration::from_secs(600), 5) {
+///     println!("{ifname} is flapping");
+/// }
+/// ```
+#[derive(Debug)]
+pub struct LinkMonitor {
+    links: Arc<Mutex<HashMap<String, Link>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Default for LinkMonitor {
+    fn default() -> Self {
+        Self::start(DEFAULT_INTERVAL, DEFAULT_CAPACITY)
+    }
+}
+
+impl LinkMonitor {
+    /// Starts watching the links, reading their state at least every
+    /// `interval` and keeping `capacity` transitions per interface.
+    #[must_use]
+    pub fn start(interval: Duration, capacity: usize) -> Self {
+        let links = Arc::new(Mutex::new(HashMap::<String, Link>::new()));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_links = Arc::clone(&links);
+        let thread_stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            let events = LinkEvents::open(interval).ok();
+            while !thread_stop.load(Ordering::Relaxed) {
+                if let Ok(states) = link_states() {
+                    let time = Utc::now().timestamp();
+                    if let Ok(mut links) = thread_links.lock() {
+                        links.retain(|name, _| states.iter().any(|s| s.ifname == *name));
+                        for state in states {
+                            if let Some(link) = links.get_mut(&state.ifname) {
+                                link.observe(state, time, capacity);
+                            } else {
+                                links.insert(
+                                    state.ifname.clone(),
+                                    Link {
+                                        state,
+                                        transitions: VecDeque::new(),
+                                    },
+                                );
+                            }
+                        }
+                    }
+                }
+                match &events {
+                    Some(events) if events.wait().is_ok() => {}
+                    _ => thread::sleep(interval),
+                }
+            }
+        });
+
+        LinkMonitor { links, stop }
+    }
+
+    /// Returns the carrier state of all interfaces, with the time of their
+    /// last transition.
+    #[must_use]
+    pub fn states(&self) -> Vec<LinkState> {
+        let mut ret: Vec<LinkState> = self
+            .links
+            .lock()
+            .map(|links| links.values().map(|link| link.state.clone()).collect())
+            .unwrap_or_default();
+        ret.sort_by(|a, b| a.ifname.cmp(&b.ifname));
+        ret
+    }
+
+    /// Returns the transitions of the interface, oldest first.
+    #[must_use]
+    pub fn transitions(&self, ifname: &str) -> Vec<LinkTransition> {
+        self.links
+            .lock()
+            .ok()
+            .and_then(|links| {
+                links
+                    .get(ifname)
+                    .map(|link| link.transitions.iter().copied().collect())
+            })
+            .unwrap_or_default()
+    }
+
+    /// Returns the interfaces whose carrier changed at least `count` times
+    /// within the last `window`.
+    #[must_use]
+    pub fn flapping(&self, window: Duration, count: u64) -> Vec<String> {
+        let since = Utc::now().timestamp() - i64::try_from(window.as_secs()).unwrap_or(i64::MAX);
+        let mut ret: Vec<String> = self
+            .links
+            .lock()
+            .map(|links| {
+                links
+                    .iter()
+                    .filter(|(_, link)| {
+                        link.transitions
+                            .iter()
+                            .filter(|t| t.time >= since)
+                            .map(|t| t.changes)
+                            .sum::<u64>()
+                            >= count
+                    })
+                    .map(|(name, _)| name.clone())
+                    .collect()
+            })
+            .unwrap_or_default();
+        ret.sort();
+        ret
+    }
+}
+
+impl Drop for LinkMonitor {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn carrier_transitions() {
+        let state = |carrier, changes| LinkState {
+            ifname: "cap0".to_string(),
+            carrier: Some(carrier),
+            carrier_changes: changes,
+            carrier_up_count: 0,
+            carrier_down_count: 0,
+            last_change: None,
+        };
+        let mut link = Link {
+            state: state(true, 4),
+            transitions: VecDeque::new(),
+        };
+
+        link.observe(state(true, 4), 100, 2);
+        assert!(link.transitions.is_empty());
+        assert_eq!(link.state.last_change, None);
+
+        // Down and up again between reads.
+        link.observe(state(true, 6), 101, 2);
+        link.observe(state(false, 7), 102, 2);
+        link.observe(state(false, 7), 103, 2);
+        assert_eq!(link.state.last_change, Some(102));
+        let changes = link
+            .transitions
+            .iter()
+            .map(|t| (t.time, t.carrier, t.changes))
+            .collect::<Vec<_>>();
+        assert_eq!(changes, [(101, Some(true), 2), (102, Some(false), 1)]);
+
+        // The interface is recreated, and the kernel counter restarts.
+        link.observe(state(true, 1), 104, 2);
+        assert_eq!(link.transitions.len(), 2);
+        assert_eq!(link.transitions[1].changes, 1);
+    }
+}
@@ -0,0 +1,375 @@
+use std::{collections::VecDeque, fmt, time::Duration};
+
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use super::{
+    notify::{Event, EventKind, Notifier},
+    usg::{resource_usage, ResourceUsage},
+};
+use crate::common::DiskHealth;
+
+const DEFAULT_HYSTERESIS: f32 = 5.0;
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(30);
+const MAX_RECENT_ALERTS: usize = 100;
+
+/// Resource monitored by [`HealthMonitor`].
+#[derive(Copy, Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub enum Metric {
+    Cpu,
+    Disk,
+    Memory,
+}
+
+impl fmt::Display for Metric {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let s = match self {
+            Metric::Cpu => "cpu",
+            Metric::Disk => "disk",
+            Metric::Memory => "memory",
+        };
+        write!(f, "{s}")
+    }
+}
+
+/// Usage thresholds in percent. `None` disables the check.
+#[derive(Clone, Debug, Default, Deserialize, Serialize)]
+pub struct Thresholds {
+    pub cpu_usage: Option<f32>,
+    pub disk_usage: Option<f32>,
+    pub memory_usage: Option<f32>,
+}
+
+/// A transition of a metric across its threshold.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Alert {
+    pub metric: Metric,
+    /// Usage in percent.
+    pub value: f32,
+    pub threshold: f32,
+    /// True if the usage exceeded the threshold, false if it recovered.
+    pub raised: bool,
+    /// The time when the alert was raised or cleared, in RFC 3339 format.
+    pub time: String,
+}
+
+/// Samples resource usage on an interval and raises an alert when the usage
+/// of a metric exceeds its threshold. The alert is cleared when the usage
+/// falls below the threshold minus the hysteresis, to avoid flapping alerts.
+///
+/// If SMART checks are enabled, disks starting to fail are reported to the
+/// notifier as well. Since SMART health is read through roxy, the roxy
+/// executable should be available.
+///
+/// # Example
+///
+/// ```ignore
+/// let thresholds = Thresholds {
+///     disk_usage: Some(90.0),
+///     ..Default::default()
+/// };
+/// let mut monitor = HealthMonitor::new(thresholds)
+///     .disk_health(true)
+///     .notifier(Notifier::new("https://alert.example.com/roxy"));
+/// monitor.run(|alert| println!("{alert:?}")).await;
+/// ```
+#[derive(Debug)]
+pub struct HealthMonitor {
+    thresholds: Thresholds,
+    hysteresis: f32,
+    interval: Duration,
+    notifier: Option<Notifier>,
+    disk_health: bool,
+    raised: Vec<Metric>,
+    recent_alerts: VecDeque<Alert>,
+    failing_disks: Vec<DiskHealth>,
+}
+
+impl HealthMonitor {
+    #[must_use]
+    pub fn new(thresholds: Thresholds) -> Self {
+        HealthMonitor {
+            thresholds,
+            hysteresis: DEFAULT_HYSTERESIS,
+            interval: DEFAULT_INTERVAL,
+            notifier: None,
+            disk_health: false,
+            raised: Vec::new(),
+            recent_alerts: VecDeque::new(),
+            failing_disks: Vec::new(),
+        }
+    }
+
+    /// Sets the hysteresis in percentage points.
+    #[must_use]
+    pub fn hysteresis(mut self, hysteresis: f32) -> Self {
+        self.hysteresis = hysteresis;
+        self
+    }
+
+    /// Sets the sampling interval.
+    #[must_use]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the notifier to which alerts are forwarded.
+    #[must_use]
+    pub fn notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Enables SMART checks of disks on each interval.
+    #[must_use]
+    pub fn disk_health(mut self, enabled: bool) -> Self {
+        self.disk_health = enabled;
+        self
+    }
+
+    /// Returns the disks failing as of the last SMART check, to be included
+    /// in status reports.
+    #[must_use]
+    pub fn failing_disks(&self) -> &[DiskHealth] {
+        &self.failing_disks
+    }
+
+    /// Returns the alerts recorded recently, oldest first.
+    #[must_use]
+    pub fn recent_alerts(&self) -> &VecDeque<Alert> {
+        &self.recent_alerts
+    }
+
+    /// Evaluates a sample against the thresholds, and returns the alerts
+    /// raised or cleared by it.
+    #[allow(clippy::cast_precision_loss)] // bytes to percent
+    pub fn evaluate(&mut self, usage: &ResourceUsage) -> Vec<Alert> {
+        let percent = |used: u64, total: u64| {
+            if total == 0 {
+                0.0
+            } else {
+                used as f32 / total as f32 * 100.0
+            }
+        };
+        let samples = [
+            (Metric::Cpu, self.thresholds.cpu_usage, usage.cpu_usage),
+            (
+                Metric::Disk,
+                self.thresholds.disk_usage,
+                percent(usage.used_disk_space, usage.total_disk_space),
+            ),
+            (
+                Metric::Memory,
+                self.thresholds.memory_usage,
+                percent(usage.used_memory, usage.total_memory),
+            ),
+        ];
+
+        let mut alerts = Vec::new();
+        for (metric, threshold, value) in samples {
+            let Some(threshold) = threshold else {
+                continue;
+            };
+            let raised = self.raised.contains(&metric);
+            if !raised && value >= threshold {
+                self.raised.push(metric);
+            } else if raised && value < threshold - self.hysteresis {
+                self.raised.retain(|m| *m != metric);
+            } else {
+                continue;
+            }
+            alerts.push(Alert {
+                metric,
+                value,
+                threshold,
+                raised: !raised,
+                time: Local::now().to_rfc3339(),
+            });
+        }
+
+        for alert in &alerts {
+            if self.recent_alerts.len() == MAX_RECENT_ALERTS {
+                self.recent_alerts.pop_front();
+            }
+            self.recent_alerts.push_back(alert.clone());
+        }
+        alerts
+    }
+
+    /// Evaluates SMART health of disks, and returns the disks which started
+    /// failing since the last evaluation.
+    pub fn evaluate_disks(&mut self, disks: Vec<DiskHealth>) -> Vec<DiskHealth> {
+        let failing = disks
+            .into_iter()
+            .filter(DiskHealth::is_failing)
+            .collect::<Vec<_>>();
+        let new = failing
+            .iter()
+            .filter(|disk| !self.failing_disks.iter().any(|d| d.device == disk.device))
+            .cloned()
+            .collect();
+        self.failing_disks = failing;
+        new
+    }
+
+    /// Samples resource usage on the interval forever, and calls the handler
+    /// with each alert. Alerts are also forwarded to the notifier if it is
+    /// set. A failure to forward an alert is logged and ignored.
+    pub async fn run<F>(&mut self, mut handler: F)
+    where
+        F: FnMut(&Alert),
+    {
+        loop {
+            let usage = resource_usage().await;
+            for alert in self.evaluate(&usage) {
+                if let Some(notifier) = &self.notifier {
+                    let event = Event::new(EventKind::ThresholdCrossed {
+                        metric: alert.metric,
+                        value: alert.value,
+                        threshold: alert.threshold,
+                        raised: alert.raised,
+                    });
+                    if let Err(e) = notifier.send_async(event).await {
+                        log::error!("fail to forward {} alert. {}", alert.metric, e);
+                    }
+                }
+                handler(&alert);
+            }
+            if self.disk_health {
+                self.check_disks().await;
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    // Reads SMART health through roxy, which may take a while for each disk,
+    // on a thread for blocking operations.
+    async fn check_disks(&mut self) {
+        let disks = match tokio::task::spawn_blocking(crate::disk_health)
+            .await
+            .map_err(anyhow::Error::from)
+            .and_then(|ret| ret)
+        {
+            Ok(disks) => disks,
+            Err(e) => {
+                log::error!("fail to get disk health. {}", e);
+                return;
+            }
+        };
+        for disk in self.evaluate_disks(disks) {
+            log::warn!("disk {} is failing", disk.device);
+            if let Some(notifier) = &self.notifier {
+                let event = Event::new(EventKind::DiskFailing {
+                    device: disk.device.clone(),
+                    model: disk.model,
+                    attributes: disk
+                        .failing_attributes
+                        .into_iter()
+                        .map(|a| a.name)
+                        .collect(),
+                });
+                if let Err(e) = notifier.send_async(event).await {
+                    log::error!("fail to forward disk failure of {}. {}", disk.device, e);
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::SmartAttribute;
+
+    fn usage(cpu_usage: f32, used_memory: u64, total_memory: u64) -> ResourceUsage {
+        ResourceUsage {
+            cpu_usage,
+            total_memory,
+            used_memory,
+            total_disk_space: 100,
+            used_disk_space: 10,
+            disks: Vec::new(),
+        }
+    }
+
+    fn disk(device: &str, passed: bool) -> DiskHealth {
+        DiskHealth {
+            device: device.to_string(),
+            model: "SSD".to_string(),
+            passed,
+            failing_attributes: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn threshold_hysteresis() {
+        let mut monitor = HealthMonitor::new(Thresholds {
+            cpu_usage: Some(90.0),
+            ..Thresholds::default()
+        })
+        .hysteresis(5.0);
+        assert!(monitor.evaluate(&usage(89.0, 0, 0)).is_empty());
+
+        let alerts = monitor.evaluate(&usage(95.0, 0, 0));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].metric, Metric::Cpu);
+        assert!(alerts[0].raised);
+        // A raised alert is not repeated, and is not cleared within the
+        // hysteresis.
+        assert!(monitor.evaluate(&usage(97.0, 0, 0)).is_empty());
+        assert!(monitor.evaluate(&usage(86.0, 0, 0)).is_empty());
+
+        let alerts = monitor.evaluate(&usage(84.0, 0, 0));
+        assert_eq!(alerts.len(), 1);
+        assert!(!alerts[0].raised);
+        assert!(monitor.evaluate(&usage(80.0, 0, 0)).is_empty());
+        assert_eq!(monitor.recent_alerts().len(), 2);
+    }
+
+    #[test]
+    fn zero_total() {
+        let mut monitor = HealthMonitor::new(Thresholds {
+            memory_usage: Some(0.0),
+            disk_usage: Some(50.0),
+            ..Thresholds::default()
+        });
+        // No memory is reported as 0% used, not as NaN.
+        let alerts = monitor.evaluate(&usage(0.0, 10, 0));
+        assert_eq!(alerts.len(), 1);
+        assert_eq!(alerts[0].metric, Metric::Memory);
+        assert!(alerts[0].value.abs() < f32::EPSILON);
+    }
+
+    #[test]
+    fn failing_disks() {
+        let mut monitor = HealthMonitor::new(Thresholds::default());
+        assert!(monitor
+            .evaluate_disks(vec![disk("/dev/sda", true)])
+            .is_empty());
+
+        let worn = DiskHealth {
+            failing_attributes: vec![SmartAttribute {
+                id: 5,
+                name: "Reallocated_Sector_Ct".to_string(),
+                value: 10,
+                threshold: 36,
+                raw: "1000".to_string(),
+            }],
+            ..disk("/dev/sdb", true)
+        };
+        let new = monitor.evaluate_disks(vec![disk("/dev/sda", false), worn.clone()]);
+        assert_eq!(new.len(), 2);
+        // A failing disk is reported once.
+        assert!(monitor
+            .evaluate_disks(vec![disk("/dev/sda", false), worn.clone()])
+            .is_empty());
+        assert_eq!(monitor.failing_disks().len(), 2);
+
+        // A disk which recovered is reported again when it fails.
+        assert!(monitor.evaluate_disks(vec![worn.clone()]).is_empty());
+        let new = monitor.evaluate_disks(vec![disk("/dev/sda", false), worn]);
+        assert_eq!(new.len(), 1);
+        assert_eq!(new[0].device, "/dev/sda");
+    }
+}
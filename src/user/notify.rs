@@ -0,0 +1,204 @@
+use std::{io::Write, path::PathBuf, process::Stdio, thread, time::Duration};
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use serde::{Deserialize, Serialize};
+
+use super::monitor::Metric;
+use crate::common::{command, Subsystem};
+
+const DEFAULT_RETRIES: u32 = 3;
+const DEFAULT_RETRY_INTERVAL: Duration = Duration::from_secs(5);
+const DEFAULT_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Critical local event delivered to a webhook.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct Event {
+    pub hostname: String,
+    /// The time when the event occurred, in RFC 3339 format.
+    pub time: String,
+    #[serde(flatten)]
+    pub kind: EventKind,
+}
+
+impl Event {
+    /// Creates an event which occurred now on this host.
+    #[must_use]
+    pub fn new(kind: EventKind) -> Self {
+        Event {
+            hostname: crate::hostname(),
+            time: Local::now().to_rfc3339(),
+            kind,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Deserialize, Serialize)]
+#[serde(tag = "kind", rename_all = "snake_case")]
+pub enum EventKind {
+    CertExpiring {
+        path: String,
+        expires_at: String,
+    },
+    ConfigChanged {
+        subsystem: Subsystem,
+    },
+    DiskFailing {
+        device: String,
+        model: String,
+        /// Names of the failing SMART attributes.
+        attributes: Vec<String>,
+    },
+    DiskNearlyFull {
+        mount_point: String,
+        usage: f32,
+    },
+    ServiceCrashed {
+        service: String,
+        /// True if the service keeps failing after restarts.
+        flapping: bool,
+    },
+    /// A failed service was restarted.
+    ServiceRestarted {
+        service: String,
+    },
+    ThresholdCrossed {
+        metric: Metric,
+        value: f32,
+        threshold: f32,
+        raised: bool,
+    },
+}
+
+/// Posts events as JSON to an HTTPS endpoint. `curl` is used to send
+/// requests.
+///
+/// # Example
+///
+/// ```ignore
+/// let notifier = Notifier::new("https://alert.example.com/roxy").retries(5);
+/// notifier.send(&Event::new(EventKind::ServiceCrashed {
+///     service: "zeek".to_string(),
+///     flapping: false,
+/// }))?;
+/// ```
+#[derive(Clone, Debug)]
+pub struct Notifier {
+    endpoint: String,
+    ca_cert: Option<PathBuf>,
+    retries: u32,
+    retry_interval: Duration,
+    timeout: Duration,
+}
+
+impl Notifier {
+    #[must_use]
+    pub fn new<S: Into<String>>(endpoint: S) -> Self {
+        Notifier {
+            endpoint: endpoint.into(),
+            ca_cert: None,
+            retries: DEFAULT_RETRIES,
+            retry_interval: DEFAULT_RETRY_INTERVAL,
+            timeout: DEFAULT_TIMEOUT,
+        }
+    }
+
+    /// Sets the CA certificate file used to verify the endpoint.
+    #[must_use]
+    pub fn ca_cert<P: Into<PathBuf>>(mut self, path: P) -> Self {
+        self.ca_cert = Some(path.into());
+        self
+    }
+
+    /// Sets the number of retries after the first attempt fails.
+    #[must_use]
+    pub fn retries(mut self, retries: u32) -> Self {
+        self.retries = retries;
+        self
+    }
+
+    /// Sets the interval between retries.
+    #[must_use]
+    pub fn retry_interval(mut self, interval: Duration) -> Self {
+        self.retry_interval = interval;
+        self
+    }
+
+    /// Sets the timeout of each attempt.
+    #[must_use]
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.timeout = timeout;
+        self
+    }
+
+    /// Posts an event, retrying on failure.
+    ///
+    /// # Errors
+    ///
+    /// * The endpoint is not an HTTPS URL
+    /// * Failure to serialize the event
+    /// * All attempts to post the event failed
+    pub fn send(&self, event: &Event) -> Result<()> {
+        if !self.endpoint.starts_with("https://") {
+            return Err(anyhow!("invalid endpoint: {}", self.endpoint));
+        }
+        let body = serde_json::to_vec(event)?;
+
+        let mut attempt = 0;
+        loop {
+            match self.post(&body) {
+                Ok(()) => return Ok(()),
+                Err(e) if attempt >= self.retries => {
+                    return Err(anyhow!("fail to send event. {}", e));
+                }
+                Err(_) => {
+                    attempt += 1;
+                    thread::sleep(self.retry_interval);
+                }
+            }
+        }
+    }
+
+    /// Posts an event like [`send`](Self::send), on a thread for blocking
+    /// operations, so that the retries do not stall the async runtime.
+    ///
+    /// # Errors
+    ///
+    /// * The errors of [`send`](Self::send)
+    /// * The thread panicked
+    pub async fn send_async(&self, event: Event) -> Result<()> {
+        let notifier = self.clone();
+        tokio::task::spawn_blocking(move || notifier.send(&event)).await?
+    }
+
+    fn post(&self, body: &[u8]) -> Result<()> {
+        let mut cmd = command("curl");
+        cmd.args(["--silent", "--show-error", "--fail", "--proto", "=https"])
+            .arg("--max-time")
+            .arg(self.timeout.as_secs().max(1).to_string())
+            .args(["--header", "Content-Type: application/json"])
+            .args(["--data-binary", "@-"]);
+        if let Some(ca_cert) = &self.ca_cert {
+            cmd.arg("--cacert").arg(ca_cert);
+        }
+        let mut child = cmd
+            .arg(&self.endpoint)
+            .stdin(Stdio::piped())
+            .stdout(Stdio::null())
+            .stderr(Stdio::piped())
+            .spawn()?;
+
+        if let Some(mut stdin) = child.stdin.take() {
+            stdin.write_all(body)?;
+        }
+        let output = child.wait_with_output()?;
+        if output.status.success() {
+            Ok(())
+        } else {
+            Err(anyhow!(
+                "{}",
+                String::from_utf8_lossy(&output.stderr).trim()
+            ))
+        }
+    }
+}
@@ -1,11 +1,18 @@
+use std::{iter, time::Instant};
+
 use serde::{Deserialize, Serialize};
-use sysinfo::{System, Users, MINIMUM_CPU_UPDATE_INTERVAL};
+use sysinfo::{
+    CpuRefreshKind, ProcessRefreshKind, ProcessesToUpdate, System, UpdateKind, Users,
+    MINIMUM_CPU_UPDATE_INTERVAL,
+};
+
+use crate::common::{Page, PageRequest, MAX_PAGE_LIMIT};
 
 const KTHREAD_PID: u32 = 2;
 const DEFAULT_USER_NAME: &str = "N/A";
 const NANO_SEC: i64 = 1_000_000_000;
 
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct Process {
     pub user: String,
     pub cpu_usage: f32,
@@ -15,51 +22,204 @@ pub struct Process {
 }
 
 /// Returns processes's username, cpu usage, memory usage, start time, and command except kernel thread.
-#[allow(
-    clippy::module_name_repetitions,
-    // start_time u64 to i64
-    clippy::cast_possible_wrap,
-    // memory u64 to f64
-    clippy::cast_precision_loss
-)]
 #[must_use]
 pub async fn process_list() -> Vec<Process> {
-    let mut system = System::new_all();
-    let mut processes = Vec::new();
-    let users = Users::new_with_refreshed_list();
-
-    // Calculating CPU usage requires a time interval.
-    tokio::time::sleep(MINIMUM_CPU_UPDATE_INTERVAL).await;
-    system.refresh_all();
-
-    let total_memory = system.total_memory() as f64;
-    let num_cpu = system.cpus().len() as f32;
-
-    for process in system.processes().values() {
-        if process
-            .parent()
-            .is_some_and(|ppid| ppid.as_u32() == KTHREAD_PID)
-        {
-            continue;
+    let mut sampler = ProcessSampler::new();
+    sampler.refresh().await;
+    sampler.processes()
+}
+
+/// Returns a page of the processes returned by [`process_list`], in the
+/// order of their PIDs.
+pub async fn process_page(page: PageRequest) -> Page<Process> {
+    let mut sampler = ProcessSampler::new();
+    sampler.refresh().await;
+    sampler.page(page)
+}
+
+/// Samples the processes, keeping what it has read between samples. Each
+/// refresh reads only the CPU and memory usage of the processes, and the
+/// user and command of new processes, so that a caller sampling often does
+/// not read every process from scratch. Processes are converted to
+/// [`Process`] only for the requested page.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut sampler = ProcessSampler::new();
+/// sampler.refresh().await;
+/// for page in sampler.pages(500) {
+///     send(page).await?;
+/// }
+/// ```
+#[allow(clippy::module_name_repetitions)]
+pub struct ProcessSampler {
+    system: System,
+    users: Users,
+    refreshed: Option<Instant>,
+}
+
+impl Default for ProcessSampler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl ProcessSampler {
+    #[must_use]
+    pub fn new() -> Self {
+        let mut system = System::new();
+        system.refresh_cpu_list(CpuRefreshKind::nothing());
+        ProcessSampler {
+            system,
+            users: Users::new(),
+            refreshed: None,
+        }
+    }
+
+    /// Samples the processes. CPU usage is measured since the last sample,
+    /// so the first sample, or one taken too soon after the last, waits for
+    /// the minimum interval of sysinfo.
+    pub async fn refresh(&mut self) {
+        let wait = if let Some(refreshed) = self.refreshed {
+            MINIMUM_CPU_UPDATE_INTERVAL.saturating_sub(refreshed.elapsed())
+        } else {
+            self.refresh_processes();
+            MINIMUM_CPU_UPDATE_INTERVAL
+        };
+        if !wait.is_zero() {
+            tokio::time::sleep(wait).await;
         }
+        self.refresh_processes();
+        self.system.refresh_memory();
+        self.users.refresh();
+        self.refreshed = Some(Instant::now());
+    }
+
+    /// Returns the processes of the last sample, in the order of their PIDs.
+    #[must_use]
+    pub fn processes(&self) -> Vec<Process> {
+        self.entries()
+            .into_iter()
+            .map(|p| self.process(p))
+            .collect()
+    }
+
+    /// Returns a page of the processes of the last sample, in the order of
+    /// their PIDs.
+    #[must_use]
+    pub fn page(&self, page: PageRequest) -> Page<Process> {
+        let entries = self.entries();
+        self.page_of(&entries, page)
+    }
+
+    /// Returns the pages of the processes of the last sample with `limit`
+    /// processes each, at most [`MAX_PAGE_LIMIT`]. Each page is converted
+    /// when it is taken.
+    pub fn pages(&self, limit: u64) -> impl Iterator<Item = Page<Process>> + '_ {
+        let entries = self.entries();
+        let mut next = Some(PageRequest::new(0, limit.clamp(1, MAX_PAGE_LIMIT)));
+        iter::from_fn(move || {
+            let req = next.take()?;
+            let page = self.page_of(&entries, req);
+            if page.has_more() {
+                next = Some(req.next());
+            }
+            Some(page)
+        })
+    }
+
+    fn refresh_processes(&mut self) {
+        self.system.refresh_processes_specifics(
+            ProcessesToUpdate::All,
+            true,
+            ProcessRefreshKind::nothing()
+                .with_cpu()
+                .with_memory()
+                .with_user(UpdateKind::OnlyIfNotSet),
+        );
+    }
+
+    // The processes except kernel threads, sorted by PID so that pages of
+    // the list do not overlap.
+    fn entries(&self) -> Vec<&sysinfo::Process> {
+        let mut entries = self
+            .system
+            .processes()
+            .iter()
+            .filter(|(_, process)| {
+                process
+                    .parent()
+                    .is_none_or(|ppid| ppid.as_u32() != KTHREAD_PID)
+            })
+            .collect::<Vec<_>>();
+        entries.sort_unstable_by_key(|(pid, _)| **pid);
+        entries.into_iter().map(|(_, process)| process).collect()
+    }
+
+    fn page_of(&self, entries: &[&sysinfo::Process], req: PageRequest) -> Page<Process> {
+        let items = entries
+            .iter()
+            .skip(usize::try_from(req.offset).unwrap_or(usize::MAX))
+            .take(usize::try_from(req.limit.min(MAX_PAGE_LIMIT)).unwrap_or(usize::MAX))
+            .map(|process| self.process(process))
+            .collect();
+        Page {
+            items,
+            offset: req.offset,
+            total: Some(entries.len() as u64),
+        }
+    }
+
+    #[allow(
+        // start_time u64 to i64
+        clippy::cast_possible_wrap,
+        // memory u64 to f64
+        clippy::cast_precision_loss
+    )]
+    fn process(&self, process: &sysinfo::Process) -> Process {
+        let total_memory = self.system.total_memory() as f64;
+        let num_cpu = self.system.cpus().len() as f32;
         let user = process
             .user_id()
-            .and_then(|uid| users.get_user_by_id(uid))
+            .and_then(|uid| self.users.get_user_by_id(uid))
             .map_or(DEFAULT_USER_NAME, |u| u.name())
             .to_string();
-        let cpu_usage = process.cpu_usage() / num_cpu;
-        let mem_usage = process.memory() as f64 / total_memory * 100.0;
-        let start_time = process.start_time() as i64 * NANO_SEC;
-        let command = process.name().to_string_lossy().to_string();
-
-        processes.push(Process {
+        Process {
             user,
-            cpu_usage,
-            mem_usage,
-            start_time,
-            command,
-        });
+            cpu_usage: process.cpu_usage() / num_cpu,
+            mem_usage: process.memory() as f64 / total_memory * 100.0,
+            start_time: process.start_time() as i64 * NANO_SEC,
+            command: process.name().to_string_lossy().to_string(),
+        }
     }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-    processes
+    #[test]
+    fn process_pages() {
+        let mut sampler = ProcessSampler::new();
+        sampler.refresh_processes();
+        let processes = sampler.processes();
+        assert!(!processes.is_empty());
+
+        let pages = sampler.pages(2).collect::<Vec<_>>();
+        assert_eq!(pages.len(), processes.len().div_ceil(2));
+        assert!(pages
+            .iter()
+            .all(|page| page.total == Some(processes.len() as u64)));
+        let commands = pages
+            .into_iter()
+            .flat_map(|page| page.items)
+            .map(|process| process.command)
+            .collect::<Vec<_>>();
+        let expected = processes
+            .into_iter()
+            .map(|process| process.command)
+            .collect::<Vec<_>>();
+        assert_eq!(commands, expected);
+    }
 }
@@ -0,0 +1,141 @@
+use std::{
+    fmt, fs,
+    path::Path,
+    time::{Duration, SystemTime, UNIX_EPOCH},
+};
+
+use serde::{Deserialize, Serialize};
+use sysinfo::System;
+
+const REBOOT_REQUIRED: &str = "/var/run/reboot-required";
+const REBOOT_REQUIRED_PKGS: &str = "/var/run/reboot-required.pkgs";
+const RUNNING_KERNEL: &str = "/proc/sys/kernel/osrelease";
+const DEFAULT_KERNEL: &str = "/boot/vmlinuz";
+const GRUB_CFG: &str = "/boot/grub/grub.cfg";
+const GRUB_DEFAULT: &str = "/etc/default/grub";
+const GRUB_DEFAULT_DIR: &str = "/etc/default/grub.d";
+const NETPLAN_PATH: &str = "/etc/netplan";
+const NETPLAN_GENERATED_PATHS: [&str; 2] = [
+    "/run/systemd/network",
+    "/run/NetworkManager/system-connections",
+];
+
+/// A reason why the system should be rebooted, or why a change has not taken
+/// effect yet.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+#[serde(tag = "reason", rename_all = "snake_case")]
+pub enum RebootReason {
+    /// Packages requested a reboot after they were upgraded.
+    Packages { packages: Vec<String> },
+    /// The default kernel differs from the running kernel.
+    KernelUpdated { running: String, installed: String },
+    /// The boot loader configuration has changed since boot, e.g. the kernel
+    /// command line.
+    BootloaderChanged,
+    /// `/etc/default/grub` has changed, but `update-grub` has not been run.
+    GrubNotUpdated,
+    /// Netplan configuration has changed, but it has not been applied.
+    NetplanNotApplied,
+}
+
+impl fmt::Display for RebootReason {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RebootReason::Packages { packages } => {
+                write!(f, "packages upgraded: {}", packages.join(", "))
+            }
+            RebootReason::KernelUpdated { running, installed } => {
+                write!(f, "kernel updated: {running} -> {installed}")
+            }
+            RebootReason::BootloaderChanged => write!(f, "boot loader configuration changed"),
+            RebootReason::GrubNotUpdated => write!(f, "grub configuration not updated"),
+            RebootReason::NetplanNotApplied => write!(f, "netplan configuration not applied"),
+        }
+    }
+}
+
+/// Returns the reasons why the system should be rebooted, or changes are
+/// pending. An empty list means nothing is pending.
+///
+/// Files which cannot be read by the current user are ignored.
+///
+/// # Example
+///
+/// ```ignore
+/// for reason in roxy::reboot_required() {
+///     println!("{reason}");
+/// }
+/// ```
+#[must_use]
+pub fn reboot_required() -> Vec<RebootReason> {
+    let mut reasons = Vec::new();
+
+    if Path::new(REBOOT_REQUIRED).exists() {
+        let packages = fs::read_to_string(REBOOT_REQUIRED_PKGS)
+            .map(|contents| {
+                let mut packages: Vec<String> = contents.lines().map(ToString::to_string).collect();
+                packages.sort();
+                packages.dedup();
+                packages
+            })
+            .unwrap_or_default();
+        reasons.push(RebootReason::Packages { packages });
+    }
+
+    if let (Ok(running), Ok(target)) = (
+        fs::read_to_string(RUNNING_KERNEL),
+        fs::read_link(DEFAULT_KERNEL),
+    ) {
+        let running = running.trim();
+        let target = target.to_string_lossy();
+        let installed = target
+            .rsplit('/')
+            .next()
+            .unwrap_or(&target)
+            .trim_start_matches("vmlinuz-");
+        if installed != running {
+            reasons.push(RebootReason::KernelUpdated {
+                running: running.to_string(),
+                installed: installed.to_string(),
+            });
+        }
+    }
+
+    let grub_cfg = modified(Path::new(GRUB_CFG));
+    let boot_time = UNIX_EPOCH + Duration::from_secs(System::boot_time());
+    if grub_cfg.is_some_and(|t| t > boot_time) {
+        reasons.push(RebootReason::BootloaderChanged);
+    }
+    let grub_default = modified(Path::new(GRUB_DEFAULT)).max(newest(GRUB_DEFAULT_DIR));
+    if let (Some(grub_default), Some(grub_cfg)) = (grub_default, grub_cfg) {
+        if grub_default > grub_cfg {
+            reasons.push(RebootReason::GrubNotUpdated);
+        }
+    }
+
+    let netplan = newest(NETPLAN_PATH);
+    let generated = NETPLAN_GENERATED_PATHS
+        .iter()
+        .filter_map(|p| newest(p))
+        .max();
+    if let (Some(netplan), Some(generated)) = (netplan, generated) {
+        if netplan > generated {
+            reasons.push(RebootReason::NetplanNotApplied);
+        }
+    }
+
+    reasons
+}
+
+fn modified(path: &Path) -> Option<SystemTime> {
+    fs::metadata(path).and_then(|m| m.modified()).ok()
+}
+
+// Returns the last modification time of the files in a directory.
+fn newest(dir: &str) -> Option<SystemTime> {
+    fs::read_dir(dir)
+        .ok()?
+        .flatten()
+        .filter_map(|entry| modified(&entry.path()))
+        .max()
+}
@@ -0,0 +1,440 @@
+use std::{
+    collections::HashMap,
+    fmt, fs, io,
+    str::FromStr,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Local, TimeZone, Timelike};
+
+use crate::common::JobRun;
+
+// Schedules of the registered jobs, e.g.
+//
+// disk_health: "0 */6 * * *"
+// support_bundle: "30 2 * * 0"
+const SCHEDULE_CONF: &str = "/etc/roxy/schedule.yaml";
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(15);
+// How many minutes missed since the last check are caught up, e.g. after a
+// long job or a suspend.
+const MAX_CATCH_UP_MINUTES: i64 = 60;
+
+/// A cron expression with five fields: minute, hour, day of month, month,
+/// and day of week. A field is `*`, a value, a range such as `1-5`, a step
+/// such as `*/15` or `0-30/10`, or a comma-separated list of them. Sunday is
+/// 0 or 7. As in cron, a day matches if either the day of month or the day
+/// of week matches when both are restricted.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub struct Schedule {
+    expr: String,
+    minutes: u64,
+    hours: u64,
+    days: u64,
+    months: u64,
+    weekdays: u64,
+    any_day: bool,
+    any_weekday: bool,
+}
+
+impl Schedule {
+    /// Returns true if the job should run in the minute of `time`.
+    #[must_use]
+    pub fn matches<T: Datelike + Timelike>(&self, time: &T) -> bool {
+        let day = bit(self.days, time.day());
+        let weekday = bit(self.weekdays, time.weekday().num_days_from_sunday());
+        let day_matches = match (self.any_day, self.any_weekday) {
+            (true, true) => true,
+            (true, false) => weekday,
+            (false, true) => day,
+            (false, false) => day || weekday,
+        };
+        bit(self.minutes, time.minute())
+            && bit(self.hours, time.hour())
+            && bit(self.months, time.month())
+            && day_matches
+    }
+}
+
+impl FromStr for Schedule {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> Result<Self> {
+        let fields = s.split_whitespace().collect::<Vec<_>>();
+        let [minute, hour, day, month, weekday] = fields[..] else {
+            return Err(anyhow!("invalid schedule: {}", s));
+        };
+        let mut weekdays = parse_field(weekday, 0, 7)?;
+        // Both 0 and 7 are Sunday.
+        if bit(weekdays, 7) {
+            weekdays |= 1;
+        }
+        Ok(Schedule {
+            expr: fields.join(" "),
+            minutes: parse_field(minute, 0, 59)?,
+            hours: parse_field(hour, 0, 23)?,
+            days: parse_field(day, 1, 31)?,
+            months: parse_field(month, 1, 12)?,
+            weekdays,
+            any_day: day == "*",
+            any_weekday: weekday == "*",
+        })
+    }
+}
+
+impl fmt::Display for Schedule {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.expr)
+    }
+}
+
+fn bit(mask: u64, n: u32) -> bool {
+    mask & (1 << n) != 0
+}
+
+// Returns the bitmask of the values in the field.
+fn parse_field(field: &str, min: u32, max: u32) -> Result<u64> {
+    let invalid = || anyhow!("invalid schedule field: {}", field);
+    let mut mask = 0;
+    for part in field.split(',') {
+        let (range, step) = match part.split_once('/') {
+            Some((range, step)) => (range, step.parse::<u32>().map_err(|_| invalid())?),
+            None => (part, 1),
+        };
+        let (start, end) = if range == "*" {
+            (min, max)
+        } else if let Some((start, end)) = range.split_once('-') {
+            (
+                start.parse::<u32>().map_err(|_| invalid())?,
+                end.parse::<u32>().map_err(|_| invalid())?,
+            )
+        } else {
+            let start = range.parse::<u32>().map_err(|_| invalid())?;
+            // "5/10" is from 5 to the maximum every 10.
+            (start, if part.contains('/') { max } else { start })
+        };
+        if step == 0 || start < min || end > max || start > end {
+            return Err(invalid());
+        }
+        for n in (start..=end).step_by(step as usize) {
+            mask |= 1 << n;
+        }
+    }
+    Ok(mask)
+}
+
+type Job = Box<dyn FnMut() -> Result<String> + Send>;
+
+struct ScheduledJob {
+    name: String,
+    schedule: Schedule,
+    job: Job,
+}
+
+/// Runs registered jobs, such as a disk health check, on cron-like
+/// schedules. The last run of each job is recorded with roxy, and can be
+/// queried with [`job_runs`](crate::job_runs).
+///
+/// Jobs run one at a time on a thread for blocking operations, so a long job
+/// delays the others but not the task calling [`Scheduler::run`]. A job
+/// scheduled in a minute missed meanwhile runs once when the scheduler
+/// catches up, for up to an hour of missed minutes.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut scheduler = Scheduler::from_conf()?
+///     .register("disk_health", || Ok(format!("{:?}", roxy::disk_health()?)));
+/// scheduler.run(|run| println!("{} finished", run.name)).await;
+/// ```
+pub struct Scheduler {
+    schedules: HashMap<String, Schedule>,
+    jobs: Vec<ScheduledJob>,
+    interval: Duration,
+    last_minute: Option<i64>,
+    last_runs: HashMap<String, JobRun>,
+}
+
+impl fmt::Debug for Scheduler {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("Scheduler")
+            .field(
+                "jobs",
+                &self.jobs.iter().map(|job| &job.name).collect::<Vec<_>>(),
+            )
+            .field("interval", &self.interval)
+            .finish_non_exhaustive()
+    }
+}
+
+impl Default for Scheduler {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Scheduler {
+    /// Creates a scheduler without schedules. Add jobs with
+    /// [`Scheduler::job`].
+    #[must_use]
+    pub fn new() -> Self {
+        Scheduler {
+            schedules: HashMap::new(),
+            jobs: Vec::new(),
+            interval: DEFAULT_INTERVAL,
+            last_minute: None,
+            last_runs: HashMap::new(),
+        }
+    }
+
+    /// Creates a scheduler with the schedules in `/etc/roxy/schedule.yaml`,
+    /// which maps a job name to a cron expression. Add the jobs with
+    /// [`Scheduler::register`].
+    ///
+    /// # Errors
+    ///
+    /// * Failure to read the configuration
+    /// * Invalid cron expression
+    pub fn from_conf() -> Result<Self> {
+        let contents = match fs::read_to_string(SCHEDULE_CONF) {
+            Ok(contents) => contents,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+        let mut scheduler = Self::new();
+        if !contents.trim().is_empty() {
+            for (name, expr) in serde_yaml::from_str::<HashMap<String, String>>(&contents)? {
+                scheduler.schedules.insert(name, expr.parse()?);
+            }
+        }
+        Ok(scheduler)
+    }
+
+    /// Adds a job run on the schedule.
+    #[must_use]
+    pub fn job<F>(mut self, name: &str, schedule: Schedule, job: F) -> Self
+    where
+        F: FnMut() -> Result<String> + Send + 'static,
+    {
+        self.jobs.push(ScheduledJob {
+            name: name.to_string(),
+            schedule,
+            job: Box::new(job),
+        });
+        self
+    }
+
+    /// Adds a job run on its schedule in the configuration. The job is not
+    /// added if the configuration has no schedule for it.
+    #[must_use]
+    pub fn register<F>(self, name: &str, job: F) -> Self
+    where
+        F: FnMut() -> Result<String> + Send + 'static,
+    {
+        if let Some(schedule) = self.schedules.get(name).cloned() {
+            self.job(name, schedule, job)
+        } else {
+            log::info!("{name} is not scheduled");
+            self
+        }
+    }
+
+    /// Sets the interval of checking the schedules, which should be shorter
+    /// than a minute.
+    #[must_use]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Returns the last run of the job in this scheduler.
+    #[must_use]
+    pub fn last_run(&self, name: &str) -> Option<&JobRun> {
+        self.last_runs.get(name)
+    }
+
+    /// Runs the jobs scheduled in the minutes since the last check up to the
+    /// minute of `now`, unless they already ran in that minute, and records
+    /// the runs with roxy. A failure to record a run is logged and ignored.
+    pub fn tick(&mut self, now: DateTime<Local>) -> Vec<JobRun> {
+        let mut runs = Vec::new();
+        for i in self.due(now) {
+            let job = &mut self.jobs[i];
+            let run = execute(&job.name, &job.schedule, &mut job.job);
+            record(&run);
+            self.last_runs.insert(run.name.clone(), run.clone());
+            runs.push(run);
+        }
+        runs
+    }
+
+    /// Runs the jobs on their schedules forever, and calls the handler with
+    /// each run. Jobs run, and their runs are recorded, on a thread for
+    /// blocking operations.
+    pub async fn run<F>(&mut self, mut handler: F)
+    where
+        F: FnMut(&JobRun),
+    {
+        loop {
+            for i in self.due(Local::now()) {
+                let name = self.jobs[i].name.clone();
+                let schedule = self.jobs[i].schedule.clone();
+                let panicked = name.clone();
+                // The job is moved to the thread, and put back when it is done.
+                let mut job = std::mem::replace(
+                    &mut self.jobs[i].job,
+                    Box::new(move || Err(anyhow!("{} panicked", panicked))),
+                );
+                let ret = tokio::task::spawn_blocking(move || {
+                    let run = execute(&name, &schedule, &mut job);
+                    record(&run);
+                    (job, run)
+                })
+                .await;
+                match ret {
+                    Ok((job, run)) => {
+                        self.jobs[i].job = job;
+                        self.last_runs.insert(run.name.clone(), run.clone());
+                        handler(&run);
+                    }
+                    Err(e) => log::error!("{} panicked. {}", self.jobs[i].name, e),
+                }
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+
+    // Returns the indices of the jobs scheduled in the minutes since the last
+    // check up to the minute of `now`, each once. At most
+    // `MAX_CATCH_UP_MINUTES` missed minutes are caught up, and the rest are
+    // logged.
+    fn due(&mut self, now: DateTime<Local>) -> Vec<usize> {
+        let minute = now.timestamp().div_euclid(60);
+        let first = match self.last_minute {
+            Some(last) if last == minute => return Vec::new(),
+            Some(last) if last + 1 < minute => {
+                let missed = minute - last - 1;
+                if missed > MAX_CATCH_UP_MINUTES {
+                    log::warn!(
+                        "skipped {} minutes of schedules",
+                        missed - MAX_CATCH_UP_MINUTES
+                    );
+                }
+                minute - missed.min(MAX_CATCH_UP_MINUTES)
+            }
+            // The first check, the next minute, or the clock went back.
+            _ => minute,
+        };
+        self.last_minute = Some(minute);
+
+        let minutes = (first..=minute)
+            .filter_map(|m| Local.timestamp_opt(m * 60, 0).single())
+            .collect::<Vec<_>>();
+        self.jobs
+            .iter()
+            .enumerate()
+            .filter(|(_, job)| minutes.iter().any(|m| job.schedule.matches(m)))
+            .map(|(i, _)| i)
+            .collect()
+    }
+}
+
+// Runs the job, and returns its run.
+fn execute(name: &str, schedule: &Schedule, job: &mut Job) -> JobRun {
+    let started = Local::now();
+    let start = Instant::now();
+    let ret = job();
+    JobRun {
+        name: name.to_string(),
+        schedule: schedule.to_string(),
+        started: started.to_rfc3339(),
+        duration_ms: u64::try_from(start.elapsed().as_millis()).unwrap_or(u64::MAX),
+        succeeded: ret.is_ok(),
+        message: match ret {
+            Ok(output) => output,
+            Err(e) => e.to_string(),
+        },
+    }
+}
+
+// Records the run with roxy. A failure is logged and ignored.
+fn record(run: &JobRun) {
+    if let Err(e) = crate::record_job_run(run.clone()) {
+        log::error!("fail to record the run of {}. {}", run.name, e);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::NaiveDate;
+
+    use super::*;
+
+    #[test]
+    fn cron_expressions() {
+        let at = |y, m, d, h, min| {
+            NaiveDate::from_ymd_opt(y, m, d)
+                .and_then(|date| date.and_hms_opt(h, min, 0))
+                .expect("valid time")
+        };
+        let every_six_hours = "0 */6 * * *".parse::<Schedule>().unwrap();
+        assert!(every_six_hours.matches(&at(2026, 3, 2, 18, 0)));
+        assert!(!every_six_hours.matches(&at(2026, 3, 2, 19, 0)));
+        assert!(!every_six_hours.matches(&at(2026, 3, 2, 18, 1)));
+
+        // 2026-03-01 is a Sunday.
+        let sunday = "30 2 * * 7".parse::<Schedule>().unwrap();
+        assert!(sunday.matches(&at(2026, 3, 1, 2, 30)));
+        assert!(!sunday.matches(&at(2026, 3, 2, 2, 30)));
+
+        // Either the first day of the month or a weekday.
+        let either = "0 9 1 * 1-5".parse::<Schedule>().unwrap();
+        assert!(either.matches(&at(2026, 3, 1, 9, 0)));
+        assert!(either.matches(&at(2026, 3, 2, 9, 0)));
+        assert!(!either.matches(&at(2026, 3, 7, 9, 0)));
+
+        assert_eq!(
+            "0,30  1-3 * * *".parse::<Schedule>().unwrap().to_string(),
+            "0,30 1-3 * * *"
+        );
+        for invalid in [
+            "* * * *",
+            "60 * * * *",
+            "*/0 * * * *",
+            "5-1 * * * *",
+            "@daily",
+        ] {
+            assert!(invalid.parse::<Schedule>().is_err(), "{invalid}");
+        }
+    }
+
+    #[test]
+    fn missed_minutes() {
+        let at = |h, min| {
+            Local
+                .with_ymd_and_hms(2026, 3, 2, h, min, 0)
+                .single()
+                .expect("valid time")
+        };
+        let job = |expr: &str| (expr.parse::<Schedule>().unwrap(), || Ok(String::new()));
+        let mut scheduler = Scheduler::new();
+        for (name, expr) in [
+            ("five", "*/5 * * * *"),
+            ("noon", "30 12 * * *"),
+            ("eleven", "0 11 * * *"),
+        ] {
+            let (schedule, f) = job(expr);
+            scheduler = scheduler.job(name, schedule, f);
+        }
+
+        assert!(scheduler.due(at(10, 2)).is_empty());
+        // 10:05 was missed, and runs once.
+        assert_eq!(scheduler.due(at(10, 7)), [0]);
+        assert!(scheduler.due(at(10, 7)).is_empty());
+        assert!(scheduler.due(at(10, 8)).is_empty());
+        assert_eq!(scheduler.due(at(10, 10)), [0]);
+
+        // Only the last hour is caught up.
+        assert_eq!(scheduler.due(at(13, 10)), [0, 1]);
+    }
+}
@@ -1,9 +1,25 @@
-use std::path::Path;
+use std::{
+    fs, io,
+    sync::{
+        atomic::{AtomicBool, Ordering},
+        Arc, Mutex,
+    },
+    thread,
+    time::Duration,
+};
 
+use anyhow::Result;
+use chrono::Utc;
+use nix::sys::statvfs::statvfs;
 use serde::{Deserialize, Serialize};
+use sysinfo::{Disks, RefreshKind, System, MINIMUM_CPU_UPDATE_INTERVAL};
+
+const DEFAULT_SAMPLE_INTERVAL: Duration = Duration::from_secs(1);
+const DISK_USAGE_CONF: &str = "/etc/roxy/disk-usage.yaml";
+const DEFAULT_DISK_USAGE_MOUNTS: [&str; 1] = ["/data"];
 
 /// CPU, memory, and disk usage.
-#[derive(Debug, Deserialize, Serialize)]
+#[derive(Clone, Debug, Deserialize, Serialize)]
 pub struct ResourceUsage {
     /// The average CPU usage in percent.
     pub cpu_usage: f32,
@@ -14,44 +30,348 @@ pub struct ResourceUsage {
     /// The amount of used RAM in bytes.
     pub used_memory: u64,
 
-    /// The total disk space in bytes.
+    /// The total disk space in bytes of the first monitored mount point
+    /// which is mounted, or of the largest disk if none is mounted.
     pub total_disk_space: u64,
 
-    /// The total disk space in bytes that is currently used.
+    /// The disk space in bytes that is currently used, of the same disk as
+    /// `total_disk_space`.
     pub used_disk_space: u64,
+
+    /// The monitored mount points which are mounted, in the order of
+    /// [`disk_usage_mounts`].
+    #[serde(default)]
+    pub disks: Vec<MountUsage>,
+}
+
+/// Disk space of a mount point.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq, Eq)]
+pub struct MountUsage {
+    pub mount_point: String,
+    /// The total space in bytes.
+    pub total_space: u64,
+    /// The used space in bytes.
+    pub used_space: u64,
+    /// The total number of inodes, or 0 if the filesystem has no fixed
+    /// number of inodes, e.g. btrfs.
+    pub total_inodes: u64,
+    /// The number of inodes in use. A filesystem runs out of inodes with
+    /// free space left if it holds many small files.
+    pub used_inodes: u64,
+}
+
+/// Returns the mount points whose disk usage is reported, which are listed
+/// in `/etc/roxy/disk-usage.yaml`, e.g.
+///
+/// ```yaml
+/// - /data
+/// - /opt/clumit/var
+/// ```
+///
+/// `/data` is monitored if the file does not exist, since the data path
+/// differs between product generations.
+///
+/// # Errors
+///
+/// * Failure to read or parse the configuration
+pub fn disk_usage_mounts() -> Result<Vec<String>> {
+    let contents = match fs::read_to_string(DISK_USAGE_CONF) {
+        Ok(contents) => contents,
+        Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+        Err(e) => return Err(e.into()),
+    };
+    if contents.trim().is_empty() {
+        return Ok(DEFAULT_DISK_USAGE_MOUNTS
+            .iter()
+            .map(ToString::to_string)
+            .collect());
+    }
+    Ok(serde_yaml::from_str(&contents)?)
 }
 
 /// Returns CPU, memory, and disk usage.
 pub async fn resource_usage() -> ResourceUsage {
-    use sysinfo::{Disks, RefreshKind, System};
-
     let mut system = System::new_with_specifics(RefreshKind::everything().without_processes());
-    let (total_disk_space, used_disk_space) = {
-        let disks = Disks::new_with_refreshed_list();
-        if let Some(d) = disks
-            .iter()
-            .find(|&disk| disk.mount_point() == Path::new("/data"))
-        {
-            (d.total_space(), d.total_space() - d.available_space())
-        } else {
-            // Find the disk with the largest space if `/data` is not found
-            if let Some(d) = disks.iter().max_by_key(|&disk| disk.total_space()) {
-                (d.total_space(), d.total_space() - d.available_space())
-            } else {
-                (0, 0)
-            }
-        }
-    };
+    let disks = Disks::new_with_refreshed_list();
 
     // Calculating CPU usage requires a time interval.
-    tokio::time::sleep(std::time::Duration::from_millis(200)).await;
+    tokio::time::sleep(Duration::from_millis(200)).await;
     system.refresh_cpu_usage();
 
+    usage(&system, &disks)
+}
+
+/// Resource usage sampled by [`UsageSampler`].
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct UsageSample {
+    pub usage: ResourceUsage,
+    /// Seconds since the Unix epoch when the usage was sampled.
+    pub time: i64,
+}
+
+/// Samples resource usage on a background thread, keeping the same sysinfo
+/// state between samples, so that a usage query returns the latest sample
+/// at once instead of sampling for 200 ms. By default, it samples every
+/// second.
+///
+/// The thread stops when the sampler is dropped.
+///
+/// # Example
+///
+/// ```ignore
+/// let sampler = UsageSampler::default();
+/// // ...
+/// if let Some(sample) = sampler.latest() {
+///     println!("{}% CPU at {}", sample.usage.cpu_usage, sample.time);
+/// }
+/// ```
+#[derive(Debug)]
+pub struct UsageSampler {
+    latest: Arc<Mutex<Option<UsageSample>>>,
+    stop: Arc<AtomicBool>,
+}
+
+impl Default for UsageSampler {
+    fn default() -> Self {
+        Self::start(DEFAULT_SAMPLE_INTERVAL)
+    }
+}
+
+impl UsageSampler {
+    /// Starts sampling every `interval`, which is at least the minimum
+    /// interval of sysinfo to measure CPU usage. The first sample is taken
+    /// after the minimum interval.
+    #[must_use]
+    pub fn start(interval: Duration) -> Self {
+        let latest = Arc::new(Mutex::new(None));
+        let stop = Arc::new(AtomicBool::new(false));
+
+        let thread_latest = Arc::clone(&latest);
+        let thread_stop = Arc::clone(&stop);
+        thread::spawn(move || {
+            let mut system =
+                System::new_with_specifics(RefreshKind::everything().without_processes());
+            let mut disks = Disks::new_with_refreshed_list();
+            thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+            while !thread_stop.load(Ordering::Relaxed) {
+                system.refresh_cpu_usage();
+                system.refresh_memory();
+                disks.refresh(true);
+                let sample = UsageSample {
+                    usage: usage(&system, &disks),
+                    time: Utc::now().timestamp(),
+                };
+                if let Ok(mut latest) = thread_latest.lock() {
+                    *latest = Some(sample);
+                }
+                thread::sleep(interval.max(MINIMUM_CPU_UPDATE_INTERVAL));
+            }
+        });
+
+        UsageSampler { latest, stop }
+    }
+
+    /// Returns the latest sample, or None before the first sample.
+    #[must_use]
+    pub fn latest(&self) -> Option<UsageSample> {
+        self.latest.lock().ok().and_then(|latest| latest.clone())
+    }
+}
+
+impl Drop for UsageSampler {
+    fn drop(&mut self) {
+        self.stop.store(true, Ordering::Relaxed);
+    }
+}
+
+fn usage(system: &System, disks: &Disks) -> ResourceUsage {
+    let mounted = disks
+        .iter()
+        .map(|disk| {
+            let (total_inodes, used_inodes) = statvfs(disk.mount_point()).map_or((0, 0), |stat| {
+                (stat.files(), stat.files().saturating_sub(stat.files_free()))
+            });
+            MountUsage {
+                mount_point: disk.mount_point().to_string_lossy().into_owned(),
+                total_space: disk.total_space(),
+                used_space: disk.total_space().saturating_sub(disk.available_space()),
+                total_inodes,
+                used_inodes,
+            }
+        })
+        .collect::<Vec<_>>();
+    // An invalid configuration falls back to the default mount points, so
+    // that the usage is still reported.
+    let mounts = disk_usage_mounts().unwrap_or_else(|_| {
+        DEFAULT_DISK_USAGE_MOUNTS
+            .iter()
+            .map(ToString::to_string)
+            .collect()
+    });
+    let disks = monitored(&mounted, &mounts);
+    // Find the disk with the largest space if no mount point is mounted.
+    let (total_disk_space, used_disk_space) = disks
+        .first()
+        .or_else(|| mounted.iter().max_by_key(|disk| disk.total_space))
+        .map_or((0, 0), |disk| (disk.total_space, disk.used_space));
+
     ResourceUsage {
         cpu_usage: system.global_cpu_usage(),
         total_memory: system.total_memory(),
         used_memory: system.used_memory(),
         total_disk_space,
         used_disk_space,
+        disks,
+    }
+}
+
+// The mounted disks of the mount points, in the order of the mount points. A
+// mount point listed twice is reported once.
+fn monitored(mounted: &[MountUsage], mounts: &[String]) -> Vec<MountUsage> {
+    let mut disks: Vec<MountUsage> = Vec::new();
+    for mount in mounts {
+        if disks.iter().any(|disk| disk.mount_point == *mount) {
+            continue;
+        }
+        if let Some(disk) = mounted.iter().find(|disk| disk.mount_point == *mount) {
+            disks.push(disk.clone());
+        }
+    }
+    disks
+}
+
+/// Pressure stall information of CPU, memory, and I/O. `None` if the kernel
+/// does not support PSI.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct Pressure {
+    pub cpu: Option<PressureStat>,
+    pub memory: Option<PressureStat>,
+    pub io: Option<PressureStat>,
+}
+
+/// Share of time in which some or all tasks were stalled on a resource.
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct PressureStat {
+    /// At least one task was stalled.
+    pub some: PressureAvg,
+    /// All non-idle tasks were stalled at the same time. `None` for CPU on
+    /// old kernels.
+    pub full: Option<PressureAvg>,
+}
+
+#[derive(Debug, Default, Deserialize, Serialize, PartialEq)]
+pub struct PressureAvg {
+    /// The average stall time in percent over the last 10 seconds.
+    pub avg10: f32,
+    /// The average stall time in percent over the last 60 seconds.
+    pub avg60: f32,
+    /// The average stall time in percent over the last 300 seconds.
+    pub avg300: f32,
+    /// The total stall time in microseconds.
+    pub total: u64,
+}
+
+/// Returns pressure stall information by reading `/proc/pressure`, which
+/// shows overload even when CPU usage looks moderate.
+#[must_use]
+pub fn pressure() -> Pressure {
+    let read = |resource: &str| {
+        fs::read_to_string(format!("/proc/pressure/{resource}"))
+            .ok()
+            .and_then(|contents| parse_pressure(&contents))
+    };
+    Pressure {
+        cpu: read("cpu"),
+        memory: read("memory"),
+        io: read("io"),
+    }
+}
+
+// Parses a /proc/pressure file such as:
+//
+// some avg10=0.12 avg60=0.05 avg300=0.01 total=123456
+// full avg10=0.00 avg60=0.00 avg300=0.00 total=0
+fn parse_pressure(contents: &str) -> Option<PressureStat> {
+    let mut some = None;
+    let mut full = None;
+    for line in contents.lines() {
+        let mut fields = line.split_whitespace();
+        let kind = fields.next();
+        let mut avg = PressureAvg::default();
+        for field in fields {
+            let (key, val) = field.split_once('=')?;
+            match key {
+                "avg10" => avg.avg10 = val.parse().ok()?,
+                "avg60" => avg.avg60 = val.parse().ok()?,
+                "avg300" => avg.avg300 = val.parse().ok()?,
+                "total" => avg.total = val.parse().ok()?,
+                _ => {}
+            }
+        }
+        match kind {
+            Some("some") => some = Some(avg),
+            Some("full") => full = Some(avg),
+            _ => {}
+        }
+    }
+    Some(PressureStat { some: some?, full })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn pressure_file() {
+        let stat = parse_pressure(
+            "some avg10=1.50 avg60=0.75 avg300=0.25 total=123456\n\
+             full avg10=0.10 avg60=0.00 avg300=0.00 total=789\n",
+        )
+        .unwrap();
+        assert!((stat.some.avg10 - 1.5).abs() < f32::EPSILON);
+        assert_eq!(stat.some.total, 123_456);
+        assert_eq!(stat.full.unwrap().total, 789);
+
+        let stat = parse_pressure("some avg10=0.00 avg60=0.00 avg300=0.00 total=0\n").unwrap();
+        assert!(stat.full.is_none());
+        assert!(parse_pressure("").is_none());
+    }
+
+    #[test]
+    fn monitored_mounts() {
+        let disk = |mount_point: &str, total_space| MountUsage {
+            mount_point: mount_point.to_string(),
+            total_space,
+            used_space: 1,
+            total_inodes: 10,
+            used_inodes: 1,
+        };
+        let mounted = [disk("/", 10), disk("/data", 100), disk("/boot", 1)];
+        let mounts = |mounts: &[&str]| mounts.iter().map(ToString::to_string).collect::<Vec<_>>();
+
+        assert_eq!(
+            monitored(
+                &mounted,
+                &mounts(&["/opt/clumit/var", "/data", "/", "/data"])
+            ),
+            [disk("/data", 100), disk("/", 10)]
+        );
+        assert!(monitored(&mounted, &mounts(&["/opt/clumit/var"])).is_empty());
+    }
+
+    #[test]
+    fn usage_sampler() {
+        let sampler = UsageSampler::start(Duration::ZERO);
+        let mut sample = None;
+        for _ in 0..50 {
+            sample = sampler.latest();
+            if sample.is_some() {
+                break;
+            }
+            thread::sleep(MINIMUM_CPU_UPDATE_INTERVAL);
+        }
+        let sample = sample.expect("sampled");
+        assert!(sample.usage.total_memory > 0);
+        assert!(sample.time > 0);
     }
 }
@@ -0,0 +1,428 @@
+use std::{
+    collections::HashMap,
+    ffi::OsStr,
+    fs,
+    path::Path,
+    time::{Duration, Instant},
+};
+
+use anyhow::{anyhow, Result};
+use chrono::Local;
+use nix::{
+    errno::Errno,
+    sys::inotify::{AddWatchFlags, InitFlags, Inotify, WatchDescriptor},
+};
+use serde::{Deserialize, Serialize};
+
+use super::notify::{Event, EventKind, Notifier};
+use crate::common::{
+    DriftItem, Subsystem, NETPLAN_DIR, ROXY_STATE_DIR, WRITE_BEGIN, WRITE_END, WRITE_MARKER_DIR,
+};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(1);
+// How long roxy may take to modify a subsystem. A begin marker without an
+// end marker for longer is left by a roxy which was killed, and is ignored.
+const MAX_WRITE_TIME: Duration = Duration::from_secs(15 * 60);
+
+/// A modification of a managed configuration file made outside roxy.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub struct ExternalChange {
+    pub subsystem: Subsystem,
+    pub path: String,
+    /// The time when the change was detected, in RFC 3339 format.
+    pub time: String,
+}
+
+/// Watches the configuration files managed by roxy using inotify, and
+/// records the modifications made outside roxy, e.g. by an administrator
+/// editing netplan by hand. The affected subsystems stay flagged as
+/// externally modified until [`ConfigWatcher::clear`] is called.
+///
+/// # Example
+///
+/// ```ignore
+/// let mut watcher = ConfigWatcher::new()?
+///     .notifier(Notifier::new("https://alert.example.com/roxy"));
+/// watcher.run(|change| println!("{} modified", change.path)).await;
+/// ```
+#[derive(Debug)]
+pub struct ConfigWatcher {
+    inotify: Inotify,
+    watches: HashMap<WatchDescriptor, &'static str>,
+    // The state directory, until the marker directory is created in it.
+    state_dir: Option<WatchDescriptor>,
+    markers: Option<WatchDescriptor>,
+    roxy_writes: RoxyWrites,
+    interval: Duration,
+    notifier: Option<Notifier>,
+    modified: Vec<ExternalChange>,
+}
+
+// The subsystems roxy is modifying. Roxy writes a marker before and after
+// modifying a subsystem, and inotify reports them in order with the
+// modifications, so a modification between the two is made by roxy.
+#[derive(Debug, Default)]
+struct RoxyWrites(HashMap<Subsystem, Instant>);
+
+impl RoxyWrites {
+    // Reads the markers left in the marker directory, e.g. by a roxy still
+    // running when the watcher starts.
+    fn scan(dir: &str) -> Self {
+        let modified = |path: String| fs::metadata(path).and_then(|m| m.modified()).ok();
+        let mut writes = RoxyWrites::default();
+        for subsystem in Subsystem::ALL {
+            let Some(begin) = modified(format!("{dir}/{subsystem}.{WRITE_BEGIN}")) else {
+                continue;
+            };
+            if matches!(modified(format!("{dir}/{subsystem}.{WRITE_END}")), Some(end) if end >= begin)
+            {
+                continue;
+            }
+            if let Some(begun) = begin
+                .elapsed()
+                .ok()
+                .and_then(|age| Instant::now().checked_sub(age))
+            {
+                writes.0.insert(subsystem, begun);
+            }
+        }
+        writes
+    }
+
+    // Updates the writes with a marker, e.g. "ntp.begin", seen at `now`.
+    fn mark(&mut self, name: &str, now: Instant) {
+        let Some((name, ext)) = name.rsplit_once('.') else {
+            return;
+        };
+        let Some(subsystem) = Subsystem::ALL
+            .into_iter()
+            .find(|subsystem| subsystem.to_string() == name)
+        else {
+            return;
+        };
+        match ext {
+            WRITE_BEGIN => {
+                self.0.insert(subsystem, now);
+            }
+            WRITE_END => {
+                self.0.remove(&subsystem);
+            }
+            _ => {}
+        }
+    }
+
+    // True if roxy is modifying the subsystem, and has not taken too long.
+    fn contains(&self, subsystem: Subsystem, now: Instant) -> bool {
+        self.0
+            .get(&subsystem)
+            .is_some_and(|begun| now.duration_since(*begun) < MAX_WRITE_TIME)
+    }
+}
+
+impl ConfigWatcher {
+    /// Creates a watcher on the directories of the managed configuration
+    /// files, and on the markers roxy writes around its modifications.
+    /// Directories which do not exist are skipped.
+    ///
+    /// # Errors
+    ///
+    /// * Failure to initialize inotify
+    /// * Failure to watch an existing directory
+    pub fn new() -> Result<Self> {
+        let inotify = Inotify::init(InitFlags::IN_NONBLOCK | InitFlags::IN_CLOEXEC)?;
+        let mut watches = HashMap::new();
+        for dir in managed_dirs() {
+            let flags = AddWatchFlags::IN_CLOSE_WRITE
+                | AddWatchFlags::IN_MOVED_TO
+                | AddWatchFlags::IN_DELETE;
+            match inotify.add_watch(dir, flags) {
+                Ok(wd) => {
+                    watches.insert(wd, dir);
+                }
+                Err(Errno::ENOENT) => {}
+                Err(e) => return Err(anyhow!("fail to watch {}. {}", dir, e)),
+            }
+        }
+        let mut ret = ConfigWatcher {
+            inotify,
+            watches,
+            state_dir: None,
+            markers: None,
+            roxy_writes: RoxyWrites::default(),
+            interval: DEFAULT_INTERVAL,
+            notifier: None,
+            modified: Vec::new(),
+        };
+        if !ret.watch_markers()? {
+            match ret
+                .inotify
+                .add_watch(ROXY_STATE_DIR, AddWatchFlags::IN_CREATE)
+            {
+                Ok(wd) => ret.state_dir = Some(wd),
+                Err(Errno::ENOENT) => {}
+                Err(e) => return Err(anyhow!("fail to watch {}. {}", ROXY_STATE_DIR, e)),
+            }
+        }
+        Ok(ret)
+    }
+
+    // Watches the marker directory, and returns false if it does not exist.
+    fn watch_markers(&mut self) -> Result<bool> {
+        let dir = format!("{ROXY_STATE_DIR}/{WRITE_MARKER_DIR}");
+        let flags = AddWatchFlags::IN_CLOSE_WRITE | AddWatchFlags::IN_MOVED_TO;
+        match self.inotify.add_watch(dir.as_str(), flags) {
+            Ok(wd) => {
+                self.markers = Some(wd);
+                self.roxy_writes = RoxyWrites::scan(&dir);
+                Ok(true)
+            }
+            Err(Errno::ENOENT) => Ok(false),
+            Err(e) => Err(anyhow!("fail to watch {}. {}", dir, e)),
+        }
+    }
+
+    /// Sets the interval of polling for changes in [`ConfigWatcher::run`].
+    #[must_use]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the notifier to which external changes are forwarded.
+    #[must_use]
+    pub fn notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Returns the last external change of each subsystem modified outside
+    /// roxy.
+    #[must_use]
+    pub fn externally_modified(&self) -> &[ExternalChange] {
+        &self.modified
+    }
+
+    /// True if the subsystem has been modified outside roxy.
+    #[must_use]
+    pub fn is_externally_modified(&self, subsystem: Subsystem) -> bool {
+        self.modified.iter().any(|c| c.subsystem == subsystem)
+    }
+
+    /// Clears the flag of the subsystem, e.g. after its configuration is
+    /// synchronized again.
+    pub fn clear(&mut self, subsystem: Subsystem) {
+        self.modified.retain(|c| c.subsystem != subsystem);
+    }
+
+    /// Reads pending inotify events without blocking, and returns the
+    /// changes made outside roxy.
+    ///
+    /// # Errors
+    ///
+    /// * Failure to read inotify events
+    pub fn poll(&mut self) -> Result<Vec<ExternalChange>> {
+        let events = match self.inotify.read_events() {
+            Ok(events) => events,
+            Err(Errno::EAGAIN) => return Ok(Vec::new()),
+            Err(e) => return Err(e.into()),
+        };
+
+        let now = Instant::now();
+        let mut changes = Vec::new();
+        for event in events {
+            let Some(name) = event.name.as_deref() else {
+                continue;
+            };
+            if self.markers.as_ref() == Some(&event.wd) {
+                if let Some(name) = name.to_str() {
+                    self.roxy_writes.mark(name, now);
+                }
+                continue;
+            }
+            if self.state_dir.as_ref() == Some(&event.wd) {
+                if name == WRITE_MARKER_DIR && self.watch_markers()? {
+                    if let Some(wd) = self.state_dir.take() {
+                        let _ = self.inotify.rm_watch(wd);
+                    }
+                }
+                continue;
+            }
+            let Some(dir) = self.watches.get(&event.wd) else {
+                continue;
+            };
+            let Some(subsystem) = managed_subsystem(dir, name) else {
+                continue;
+            };
+            if self.roxy_writes.contains(subsystem, now) {
+                continue;
+            }
+            let change = ExternalChange {
+                subsystem,
+                path: format!("{dir}/{}", name.to_string_lossy()),
+                time: Local::now().to_rfc3339(),
+            };
+            if !changes.contains(&change) {
+                changes.push(change);
+            }
+        }
+
+        for change in &changes {
+            self.modified.retain(|c| c.subsystem != change.subsystem);
+            self.modified.push(change.clone());
+        }
+        Ok(changes)
+    }
+
+    /// Checks the drift of the system from the last state applied with
+    /// [`apply_state`](crate::apply_state), which inotify cannot see if it was
+    /// made while the watcher was not running, e.g. before a reboot. Call it
+    /// once at startup. The drifted subsystems are flagged as externally
+    /// modified, and forwarded to the notifier if it is set. Roxy is run on
+    /// a thread for blocking operations.
+    ///
+    /// # Errors
+    ///
+    /// * Failure to check the drift with roxy
+    pub async fn check_drift(&mut self) -> Result<Vec<DriftItem>> {
+        let items = tokio::task::spawn_blocking(crate::config_drift).await??;
+        let mut subsystems = items.iter().map(|item| item.subsystem).collect::<Vec<_>>();
+        subsystems.dedup();
+        for subsystem in subsystems {
+            let change = ExternalChange {
+                subsystem,
+                path: managed_path(subsystem),
+                time: Local::now().to_rfc3339(),
+            };
+            if let Some(notifier) = &self.notifier {
+                let event = Event::new(EventKind::ConfigChanged { subsystem });
+                if let Err(e) = notifier.send_async(event).await {
+                    log::error!("fail to forward drift of {}. {}", subsystem, e);
+                }
+            }
+            self.modified.retain(|c| c.subsystem != subsystem);
+            self.modified.push(change);
+        }
+        Ok(items)
+    }
+
+    /// Polls for changes on the interval forever, and calls the handler with
+    /// each external change. Changes are also forwarded to the notifier if it
+    /// is set. A failure to read events or forward a change is logged and
+    /// ignored.
+    pub async fn run<F>(&mut self, mut handler: F)
+    where
+        F: FnMut(&ExternalChange),
+    {
+        loop {
+            match self.poll() {
+                Ok(changes) => {
+                    for change in changes {
+                        if let Some(notifier) = &self.notifier {
+                            let event = Event::new(EventKind::ConfigChanged {
+                                subsystem: change.subsystem,
+                            });
+                            if let Err(e) = notifier.send_async(event).await {
+                                log::error!("fail to forward change of {}. {}", change.path, e);
+                            }
+                        }
+                        handler(&change);
+                    }
+                }
+                Err(e) => log::error!("fail to read config changes. {}", e),
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+// The directories watched: those of the files of the subsystems, and the
+// netplan directory.
+fn managed_dirs() -> Vec<&'static str> {
+    let mut dirs = Subsystem::ALL
+        .iter()
+        .flat_map(|subsystem| subsystem.files())
+        .filter_map(|file| Path::new(file).parent()?.to_str())
+        .collect::<Vec<_>>();
+    dirs.push(NETPLAN_DIR);
+    dirs.sort_unstable();
+    dirs.dedup();
+    dirs
+}
+
+// The main file of the subsystem, or the netplan directory for interfaces.
+fn managed_path(subsystem: Subsystem) -> String {
+    if subsystem == Subsystem::Interface {
+        return NETPLAN_DIR.to_string();
+    }
+    subsystem
+        .files()
+        .first()
+        .map(ToString::to_string)
+        .unwrap_or_default()
+}
+
+fn managed_subsystem(dir: &str, name: &OsStr) -> Option<Subsystem> {
+    let path = Path::new(dir).join(name);
+    if dir == NETPLAN_DIR {
+        return path
+            .extension()
+            .is_some_and(|ext| ext.eq_ignore_ascii_case("yaml"))
+            .then_some(Subsystem::Interface);
+    }
+    Subsystem::ALL
+        .into_iter()
+        .find(|subsystem| subsystem.files().iter().any(|file| path == Path::new(file)))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn roxy_writes() {
+        let start = Instant::now();
+        let mut writes = RoxyWrites::default();
+        assert!(!writes.contains(Subsystem::Ntp, start));
+
+        writes.mark("ntp.begin", start);
+        assert!(writes.contains(Subsystem::Ntp, start));
+        assert!(!writes.contains(Subsystem::Sshd, start));
+        writes.mark("ntp.end", start);
+        assert!(!writes.contains(Subsystem::Ntp, start));
+
+        // A marker left by a roxy which was killed expires.
+        writes.mark("interface.begin", start);
+        assert!(writes.contains(Subsystem::Interface, start + Duration::from_secs(1)));
+        assert!(!writes.contains(Subsystem::Interface, start + MAX_WRITE_TIME));
+
+        // Markers of older roxy and unknown subsystems are ignored.
+        writes.mark("ntp", start);
+        writes.mark("unknown.begin", start);
+        writes.mark("ntp.tmp", start);
+        assert!(!writes.contains(Subsystem::Ntp, start));
+    }
+
+    #[test]
+    fn managed_files() {
+        let name = |name: &str| OsStr::new(name).to_owned();
+        assert_eq!(
+            managed_subsystem("/etc", &name("motd")),
+            Some(Subsystem::Sshd)
+        );
+        assert_eq!(
+            managed_subsystem("/etc/sysctl.d", &name("90-roxy-domainname.conf")),
+            Some(Subsystem::Hostname)
+        );
+        assert_eq!(
+            managed_subsystem("/etc/default", &name("roxy-interface-labels")),
+            Some(Subsystem::Interface)
+        );
+        assert_eq!(
+            managed_subsystem(NETPLAN_DIR, &name("01-netcfg.yaml")),
+            Some(Subsystem::Interface)
+        );
+        assert_eq!(managed_subsystem("/etc", &name("passwd")), None);
+        assert!(managed_dirs().contains(&"/etc/default"));
+        assert_eq!(managed_path(Subsystem::Sshd), "/etc/ssh/sshd_config");
+    }
+}
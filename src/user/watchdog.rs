@@ -0,0 +1,259 @@
+use std::{
+    collections::{HashMap, HashSet, VecDeque},
+    time::{Duration, Instant},
+};
+
+use serde::{Deserialize, Serialize};
+
+use super::notify::{Event, EventKind, Notifier};
+use crate::common::{command, run_command, ServiceAction};
+
+const DEFAULT_INTERVAL: Duration = Duration::from_secs(10);
+const DEFAULT_MAX_RESTARTS: usize = 3;
+const DEFAULT_WINDOW: Duration = Duration::from_secs(600);
+
+/// Limits how often a failed service is restarted. If a service has been
+/// restarted `max_restarts` times within `window`, it is regarded as flapping
+/// and is not restarted until the window slides.
+#[derive(Clone, Debug, Deserialize, Serialize)]
+pub struct RestartPolicy {
+    pub max_restarts: usize,
+    pub window: Duration,
+}
+
+impl Default for RestartPolicy {
+    fn default() -> Self {
+        RestartPolicy {
+            max_restarts: DEFAULT_MAX_RESTARTS,
+            window: DEFAULT_WINDOW,
+        }
+    }
+}
+
+/// What the watchdog did for a failed service.
+#[derive(Clone, Debug, Deserialize, Serialize, PartialEq)]
+pub enum WatchdogEvent {
+    Restarted(String),
+    RestartFailed {
+        service: String,
+        error: String,
+    },
+    /// The service failed again after reaching the restart limit. It is
+    /// reported once until the window slides and the service is restarted
+    /// again.
+    Flapping {
+        service: String,
+        restarts: usize,
+    },
+}
+
+/// Polls systemd for failed services and restarts them through roxy
+/// according to a [`RestartPolicy`].
+///
+/// # Example
+///
+/// ```ignore
+/// let mut watchdog = Watchdog::new(vec!["zeek".to_string(), "peek".to_string()])
+///     .notifier(Notifier::new("https://alert.example.com/roxy"));
+/// watchdog.run(|event| println!("{event:?}")).await;
+/// ```
+#[derive(Debug)]
+pub struct Watchdog {
+    services: Vec<String>,
+    policy: RestartPolicy,
+    interval: Duration,
+    notifier: Option<Notifier>,
+    restarts: HashMap<String, VecDeque<Instant>>,
+    flapping: HashSet<String>,
+}
+
+// What to do with a failed service.
+#[derive(Debug, PartialEq)]
+enum Verdict {
+    Restart,
+    // The service has just reached the restart limit.
+    Flapping(usize),
+    // The service is flapping, and has been reported.
+    Suppressed,
+}
+
+impl Watchdog {
+    #[must_use]
+    pub fn new(services: Vec<String>) -> Self {
+        Watchdog {
+            services,
+            policy: RestartPolicy::default(),
+            interval: DEFAULT_INTERVAL,
+            notifier: None,
+            restarts: HashMap::new(),
+            flapping: HashSet::new(),
+        }
+    }
+
+    #[must_use]
+    pub fn policy(mut self, policy: RestartPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Sets the polling interval.
+    #[must_use]
+    pub fn interval(mut self, interval: Duration) -> Self {
+        self.interval = interval;
+        self
+    }
+
+    /// Sets the notifier to which crashes are reported.
+    #[must_use]
+    pub fn notifier(mut self, notifier: Notifier) -> Self {
+        self.notifier = Some(notifier);
+        self
+    }
+
+    /// Checks the services once, and restarts the failed ones within the
+    /// restart limit.
+    pub fn check(&mut self) -> Vec<WatchdogEvent> {
+        let now = Instant::now();
+        let mut events = Vec::new();
+        for service in self.services.clone() {
+            if !is_failed(&service) {
+                continue;
+            }
+
+            match self.verdict(&service, now) {
+                Verdict::Restart => {}
+                Verdict::Flapping(restarts) => {
+                    events.push(WatchdogEvent::Flapping { service, restarts });
+                    continue;
+                }
+                Verdict::Suppressed => continue,
+            }
+            match crate::service_action(service.clone(), ServiceAction::Restart) {
+                Ok(true) => events.push(WatchdogEvent::Restarted(service)),
+                Ok(false) => events.push(WatchdogEvent::RestartFailed {
+                    service,
+                    error: "systemctl returned failure".to_string(),
+                }),
+                Err(e) => events.push(WatchdogEvent::RestartFailed {
+                    service,
+                    error: e.to_string(),
+                }),
+            }
+        }
+        events
+    }
+
+    // Decides what to do with a service which failed at `now`, and records
+    // the restart if it is to be restarted.
+    fn verdict(&mut self, service: &str, now: Instant) -> Verdict {
+        let restarts = self.restarts.entry(service.to_string()).or_default();
+        while restarts
+            .front()
+            .is_some_and(|t| now.duration_since(*t) > self.policy.window)
+        {
+            restarts.pop_front();
+        }
+        if restarts.len() >= self.policy.max_restarts {
+            if self.flapping.insert(service.to_string()) {
+                return Verdict::Flapping(restarts.len());
+            }
+            return Verdict::Suppressed;
+        }
+        self.flapping.remove(service);
+        restarts.push_back(now);
+        Verdict::Restart
+    }
+
+    /// Checks the services on the interval forever, and calls the handler
+    /// with each event. The events are also reported to the notifier if it
+    /// is set. A failure to report an event is logged and ignored.
+    pub async fn run<F>(&mut self, mut handler: F)
+    where
+        F: FnMut(&WatchdogEvent),
+    {
+        loop {
+            for event in self.check() {
+                if let Some(notifier) = &self.notifier {
+                    if let Err(e) = notifier.send_async(Event::new(notification(&event))).await {
+                        log::error!("fail to report {:?}. {}", event, e);
+                    }
+                }
+                handler(&event);
+            }
+            tokio::time::sleep(self.interval).await;
+        }
+    }
+}
+
+// Returns the event to report to the notifier.
+fn notification(event: &WatchdogEvent) -> EventKind {
+    match event {
+        WatchdogEvent::Restarted(service) => EventKind::ServiceRestarted {
+            service: service.clone(),
+        },
+        WatchdogEvent::RestartFailed { service, .. } => EventKind::ServiceCrashed {
+            service: service.clone(),
+            flapping: false,
+        },
+        WatchdogEvent::Flapping { service, .. } => EventKind::ServiceCrashed {
+            service: service.clone(),
+            flapping: true,
+        },
+    }
+}
+
+fn is_failed(service: &str) -> bool {
+    run_command(command("systemctl").args(["is-failed", "--quiet", service]))
+        .is_ok_and(|output| output.status.success())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flapping_once_per_window() {
+        let mut watchdog = Watchdog::new(vec!["zeek".to_string()]).policy(RestartPolicy {
+            max_restarts: 2,
+            window: Duration::from_secs(30),
+        });
+        let start = Instant::now();
+        assert_eq!(watchdog.verdict("zeek", start), Verdict::Restart);
+        assert_eq!(watchdog.verdict("zeek", start), Verdict::Restart);
+        assert_eq!(watchdog.verdict("zeek", start), Verdict::Flapping(2));
+        assert_eq!(watchdog.verdict("zeek", start), Verdict::Suppressed);
+        assert_eq!(watchdog.verdict("peek", start), Verdict::Restart);
+
+        let later = start + Duration::from_secs(31);
+        assert_eq!(watchdog.verdict("zeek", later), Verdict::Restart);
+        assert_eq!(watchdog.verdict("zeek", later), Verdict::Restart);
+        assert_eq!(watchdog.verdict("zeek", later), Verdict::Flapping(2));
+        assert_eq!(watchdog.verdict("zeek", later), Verdict::Suppressed);
+    }
+
+    #[test]
+    fn notifications() {
+        let service = "zeek".to_string();
+        assert!(matches!(
+            notification(&WatchdogEvent::Restarted(service.clone())),
+            EventKind::ServiceRestarted { service } if service == "zeek"
+        ));
+        assert!(matches!(
+            notification(&WatchdogEvent::RestartFailed {
+                service: service.clone(),
+                error: "fail".to_string(),
+            }),
+            EventKind::ServiceCrashed {
+                flapping: false,
+                ..
+            }
+        ));
+        assert!(matches!(
+            notification(&WatchdogEvent::Flapping {
+                service,
+                restarts: 3,
+            }),
+            EventKind::ServiceCrashed { flapping: true, .. }
+        ));
+    }
+}
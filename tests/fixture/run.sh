@@ -0,0 +1,15 @@
+#!/bin/sh
+# Runs the integration tests in a disposable container. Set CONTAINER to use
+# another engine such as podman.
+set -eu
+
+engine="${CONTAINER:-docker}"
+root="$(cd "$(dirname "$0")/../.." && pwd)"
+image=roxy-fixture
+
+"$engine" build -t "$image" "$root/tests/fixture"
+"$engine" run --rm \
+    -v "$root:/src:ro" \
+    -e CARGO_TARGET_DIR=/tmp/target \
+    "$image" \
+    cargo test --test integration -- --ignored --test-threads=1
@@ -0,0 +1,2314 @@
+//! End-to-end tests of the privileged task handlers.
+//!
+//! The tests change the configuration files under `/etc`, so they are ignored
+//! by default and run in a disposable container by `tests/fixture/run.sh`.
+//! The container provides the fixture files and replaces the commands which
+//! change the system, such as systemctl and netplan, with a stub recording
+//! its invocations.
+
+use std::{
+    collections::BTreeMap,
+    env,
+    fs::{self, File},
+    net::{IpAddr, TcpListener},
+    os::unix::fs::PermissionsExt,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+    time::SystemTime,
+};
+
+use chrono::{Duration, Local};
+use data_encoding::BASE64;
+use roxy::{
+    common::{
+        node_id, ActivationMode, Baseline, BondStatus, CaptureSettings, ChangeEvent, CleanupReport,
+        ComplianceReport, CrashReport, DeferredOperation, DnsLookup, DriftItem, Endpoint,
+        FilesystemHealth, FirewallState, GovernorStatus, GroupSettings, HostnameInfo,
+        InterfaceResult, IrqLayout, JobRun, LicenseStatus, MaintenanceWindow, NetnsCapture,
+        NetnsInterface, NicOutput, NicOutputV1, Node, NodeInfo, NodeRequest, OperationStatus,
+        PingInfo, Protocol, ReadinessReport, RequestSignature, Role, RouteOutput,
+        RoutingRuleOutput, ServiceAction, StateChange, SubCommand, Subsystem, SystemState,
+        SystemStateV1, TrimRun, TrimStatus, TuningStatus, TunnelConfig, TunnelMode, VrrpConfig,
+        VrrpStatus, WireguardConfig, WireguardPeer, WireguardStatus,
+    },
+    AddressConflict, ConfigWatcher, RoxyClient,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+const FIXTURE_ENV: &str = "ROXY_FIXTURE";
+const FIXTURE_FILES: [&str; 6] = [
+    "etc/netplan/01-netcfg.yaml",
+    "etc/ntp.conf",
+    "etc/roxy/services.yaml",
+    "etc/rsyslog.d/50-default.conf",
+    "etc/ssh/sshd_config",
+    "etc/ufw/ufw.conf",
+];
+const NETPLAN_DIR: &str = "/etc/netplan";
+const NTP_CONF: &str = "/etc/ntp.conf";
+const HOSTS: &str = "/etc/hosts";
+const DNS_PRIORITY_CONF: &str = "/etc/default/roxy-dns-priority";
+const INTERFACE_LABEL_CONF: &str = "/etc/default/roxy-interface-labels";
+const INTERFACE_GROUPS_CONF: &str = "/etc/roxy/interface-groups.yaml";
+const ENDPOINTS_CONF: &str = "/etc/roxy/endpoints.yaml";
+const ROLES_CONF: &str = "/etc/roxy/roles.yaml";
+const POLICY_HOOK: &str = "/etc/roxy/policy-hook";
+const REPLICATION_HOOK: &str = "/etc/roxy/replication-hook";
+const LIMITS_CONF: &str = "/etc/roxy/limits.yaml";
+const CLEANUP_CONF: &str = "/etc/roxy/cleanup.yaml";
+const CAPTURE_UNIT: &str = "/etc/systemd/system/roxy-capture-lo.service";
+const LICENSE_FILE: &str = "/usr/local/aice/conf/license";
+const LICENSE_KEY: &str = "/usr/local/aice/conf/license.pem";
+const REQUEST_KEY: &str = "/etc/roxy/request-signing.pem";
+const BASELINE_FILE: &str = "/etc/roxy/baseline";
+const WIREGUARD_CONF: &str = "/etc/wireguard/wg-mgmt.conf";
+const CALLS: &str = "/var/log/roxy-fixture/calls";
+const AUDIT_LOG: &str = "/var/log/audit/audit.log";
+const ROXY_STATE_DIR: &str = "/usr/local/aice/var/roxy";
+
+static LOCK: Mutex<()> = Mutex::new(());
+
+// A system reset to the fixture. Tests are serialized since they share the
+// files under /etc.
+struct Fixture {
+    client: RoxyClient,
+    _guard: MutexGuard<'static, ()>,
+}
+
+impl Fixture {
+    fn new() -> Self {
+        let guard = LOCK
+            .lock()
+            .unwrap_or_else(std::sync::PoisonError::into_inner);
+        let dir = env::var(FIXTURE_ENV)
+            .unwrap_or_else(|_| panic!("{FIXTURE_ENV} is not set. Run tests/fixture/run.sh"));
+        // Roxy may have renamed or added netplan yaml files.
+        for entry in fs::read_dir(NETPLAN_DIR)
+            .expect("netplan directory")
+            .flatten()
+        {
+            fs::remove_file(entry.path()).expect("netplan yaml should be removable");
+        }
+        for file in FIXTURE_FILES {
+            fs::copy(Path::new(&dir).join(file), Path::new("/").join(file))
+                .expect("fixture file should be copied");
+        }
+        let _ = fs::remove_file(DNS_PRIORITY_CONF);
+        let _ = fs::remove_file(INTERFACE_LABEL_CONF);
+        let _ = fs::remove_file(INTERFACE_GROUPS_CONF);
+        let _ = fs::remove_file(CAPTURE_UNIT);
+        let _ = fs::remove_file(ENDPOINTS_CONF);
+        let _ = fs::remove_file(ROLES_CONF);
+        let _ = fs::remove_file(POLICY_HOOK);
+        let _ = fs::remove_file(REPLICATION_HOOK);
+        let _ = fs::remove_file(LIMITS_CONF);
+        let _ = fs::remove_file(CLEANUP_CONF);
+        let _ = fs::remove_file(LICENSE_FILE);
+        let _ = fs::remove_file(LICENSE_KEY);
+        let _ = fs::remove_file(REQUEST_KEY);
+        let _ = fs::remove_file(BASELINE_FILE);
+        let _ = fs::remove_file(WIREGUARD_CONF);
+        let _ = fs::remove_file(CALLS);
+        let _ = fs::remove_dir_all(ROXY_STATE_DIR);
+
+        Fixture {
+            client: RoxyClient::new().program(env!("CARGO_BIN_EXE_roxy")),
+            _guard: guard,
+        }
+    }
+
+    fn run<A: Serialize, T: DeserializeOwned>(&self, kind: Node, arg: A) -> anyhow::Result<T> {
+        let req = NodeRequest::new(kind, arg).expect("argument should serialize");
+        self.client.run(req)
+    }
+
+    fn set<A: Serialize>(&self, kind: Node, arg: A) {
+        let name = format!("{kind:?}");
+        let ret = self
+            .run::<A, String>(kind, arg)
+            .unwrap_or_else(|e| panic!("{name} failed: {e}"));
+        assert_eq!(ret, "Ok");
+    }
+
+    // Commands run by roxy, e.g. "netplan apply".
+    fn calls() -> Vec<String> {
+        fs::read_to_string(CALLS)
+            .unwrap_or_default()
+            .lines()
+            .map(ToString::to_string)
+            .collect()
+    }
+}
+
+fn nic(
+    addresses: Option<&[&str]>,
+    dhcp4: Option<bool>,
+    gateway4: Option<&str>,
+    nameservers: Option<&[&str]>,
+) -> NicOutput {
+    let strings = |items: &[&str]| items.iter().map(ToString::to_string).collect();
+    NicOutput::new(
+        addresses.map(strings),
+        dhcp4,
+        gateway4.map(ToString::to_string),
+        nameservers.map(strings),
+    )
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn ntp_servers() {
+    let fixture = Fixture::new();
+    let servers = vec!["time.example.com".to_string(), "10.0.0.1".to_string()];
+    fixture.set(Node::Ntp(SubCommand::Set), &servers);
+
+    let ret = fixture
+        .run::<_, Option<Vec<String>>>(Node::Ntp(SubCommand::Get), None::<String>)
+        .expect("ntp servers");
+    assert_eq!(ret, Some(servers));
+    let conf = fs::read_to_string("/etc/ntp.conf").expect("ntp.conf");
+    assert!(conf.contains("driftfile /var/lib/ntp/ntp.drift"));
+    assert!(!conf.contains("ubuntu.pool.ntp.org"));
+    assert!(Fixture::calls().contains(&"systemctl restart ntp".to_string()));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn change_events() {
+    let fixture = Fixture::new();
+    fixture.set(Node::Ntp(SubCommand::Set), vec!["time.example.com"]);
+    fixture.set(Node::Ntp(SubCommand::Set), vec!["time.example.com"]);
+
+    let events = fixture
+        .run::<_, Vec<ChangeEvent>>(Node::Changes(SubCommand::Get), 0_u64)
+        .expect("change events");
+    assert_eq!(events.len(), 1);
+    let event = &events[0];
+    assert_eq!(event.seq, 1);
+    assert_eq!(event.subsystem, Some(Subsystem::Ntp));
+    assert_eq!(event.task, "Ntp::Set");
+    assert_eq!(
+        event.before,
+        ["/etc/ntp.conf: server 0.ubuntu.pool.ntp.org iburst"]
+    );
+    assert_eq!(
+        event.after,
+        ["/etc/ntp.conf: server time.example.com iburst"]
+    );
+
+    let events = fixture
+        .run::<_, Vec<ChangeEvent>>(Node::Changes(SubCommand::Get), event.seq)
+        .expect("change events");
+    assert!(events.is_empty());
+
+    // A change outside the subsystems is recorded without a diff.
+    fixture.set(
+        Node::Capture(SubCommand::Set),
+        ("lo".to_string(), CaptureSettings::capture()),
+    );
+    let events = fixture
+        .run::<_, Vec<ChangeEvent>>(Node::Changes(SubCommand::Get), event.seq)
+        .expect("change events");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].subsystem, None);
+    assert_eq!(events[0].task, "Capture::Set");
+    assert!(events[0].before.is_empty() && events[0].after.is_empty());
+    fixture.set(Node::Capture(SubCommand::Delete), "lo".to_string());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn interrupted_change() {
+    let fixture = Fixture::new();
+    let ntp_conf = fs::read_to_string(NTP_CONF).expect("ntp.conf");
+    // A roxy killed while setting the NTP servers, whose PID is not in use.
+    let entry = serde_json::json!({
+        "task": "Ntp::Set",
+        "started": 1_714_521_600,
+        "checkpoints": [{
+            "hostname": null,
+            "files": [[NTP_CONF, ntp_conf]],
+            "netplan_files": null,
+        }],
+    });
+    let dir = format!("{ROXY_STATE_DIR}/journal");
+    fs::create_dir_all(&dir).expect("journal directory");
+    fs::write(format!("{dir}/999999.json"), entry.to_string()).expect("journal entry");
+    fs::write(NTP_CONF, "server time.example.com iburst\n").expect("ntp.conf");
+
+    // Any request recovers the interrupted change first.
+    fixture
+        .run::<_, Option<Vec<String>>>(Node::Ntp(SubCommand::Get), None::<String>)
+        .expect("ntp servers");
+    assert_eq!(fs::read_to_string(NTP_CONF).expect("ntp.conf"), ntp_conf);
+    assert!(!Path::new(&format!("{dir}/999999.json")).exists());
+    let events = fixture
+        .run::<_, Vec<ChangeEvent>>(Node::Changes(SubCommand::Get), 0_u64)
+        .expect("change events");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].task, "Journal::Recover");
+    assert_eq!(events[0].notes, ["rolled back Ntp::Set interrupted"]);
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn state_drift() {
+    let fixture = Fixture::new();
+    let drift = || {
+        fixture
+            .run::<_, Vec<DriftItem>>(Node::State(SubCommand::Status), None::<String>)
+            .expect("drift")
+    };
+    assert!(drift().is_empty());
+
+    let state = SystemState {
+        ntp: Some(vec!["time.example.com".to_string()]),
+        ..SystemState::default()
+    };
+    fixture
+        .run::<_, Vec<StateChange>>(Node::StateV2(SubCommand::Set), state)
+        .expect("state applied");
+    assert!(drift().is_empty());
+
+    // Out-of-band change, e.g. editing ntp.conf by hand.
+    let conf = fs::read_to_string("/etc/ntp.conf").expect("ntp.conf");
+    fs::write(
+        "/etc/ntp.conf",
+        conf.replace("time.example.com", "pool.example.com"),
+    )
+    .expect("ntp.conf should be written");
+    let items = drift();
+    assert_eq!(items.len(), 1);
+    assert_eq!(items[0].subsystem, Subsystem::Ntp);
+    assert_eq!(items[0].desired, "time.example.com");
+    assert_eq!(items[0].current, "pool.example.com");
+
+    // A change made by roxy is not drift.
+    fixture.set(Node::Ntp(SubCommand::Set), vec!["10.0.0.1"]);
+    assert!(drift().is_empty());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn config_watcher() {
+    let fixture = Fixture::new();
+    // Roxy creates the marker directory on its first change.
+    fixture.set(Node::Ntp(SubCommand::Set), vec!["10.0.0.1"]);
+    let mut watcher = ConfigWatcher::new().expect("watcher");
+    assert!(watcher.poll().expect("changes").is_empty());
+
+    fixture.set(Node::Ntp(SubCommand::Set), vec!["time.example.com"]);
+    assert!(watcher.poll().expect("changes").is_empty());
+
+    // An edit right after roxy finished is still reported.
+    let conf = fs::read_to_string(NTP_CONF).expect("ntp.conf");
+    fs::write(
+        NTP_CONF,
+        conf.replace("time.example.com", "pool.example.com"),
+    )
+    .expect("ntp.conf should be written");
+    let changes = watcher.poll().expect("changes");
+    assert_eq!(changes.len(), 1);
+    assert_eq!(changes[0].subsystem, Subsystem::Ntp);
+    assert_eq!(changes[0].path, NTP_CONF);
+    assert!(watcher.is_externally_modified(Subsystem::Ntp));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn job_runs() {
+    let fixture = Fixture::new();
+    let run = |name: &str, succeeded| JobRun {
+        name: name.to_string(),
+        schedule: "0 */6 * * *".to_string(),
+        started: "2026-03-02T18:00:00+09:00".to_string(),
+        duration_ms: 10,
+        succeeded,
+        message: "x".repeat(10_000),
+    };
+    fixture.set(Node::Schedule(SubCommand::Set), run("update_check", true));
+    fixture.set(Node::Schedule(SubCommand::Set), run("disk_health", false));
+    fixture.set(Node::Schedule(SubCommand::Set), run("disk_health", true));
+
+    let runs = fixture
+        .run::<_, Vec<JobRun>>(Node::Schedule(SubCommand::Get), None::<String>)
+        .expect("job runs");
+    let names = runs.iter().map(|run| run.name.as_str()).collect::<Vec<_>>();
+    assert_eq!(names, ["disk_health", "update_check"]);
+    assert!(runs[0].succeeded);
+    assert!(runs[0].message.len() < 10_000);
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn crash_reports() {
+    let fixture = Fixture::new();
+    let crashes = || {
+        fixture
+            .run::<_, Vec<CrashReport>>(Node::Crash(SubCommand::Get), None::<String>)
+            .expect("crash reports")
+    };
+    assert!(crashes().is_empty());
+
+    let report = CrashReport {
+        time: 1_714_521_600,
+        version: "0.5.0".to_string(),
+        task: Some("Interface::Set".to_string()),
+        message: "index out of bounds".to_string(),
+        location: Some("src/root/ifconfig.rs:42:9".to_string()),
+        backtrace: String::new(),
+    };
+    let dir = format!("{ROXY_STATE_DIR}/crash");
+    fs::create_dir_all(&dir).expect("crash directory");
+    fs::write(
+        format!("{dir}/1714521600-100.json"),
+        serde_json::to_vec(&report).expect("report should serialize"),
+    )
+    .expect("crash report");
+    // A report cut off by a crash while writing it.
+    fs::write(format!("{dir}/1714521700-200.json"), "{\"time\":").expect("crash report");
+    assert_eq!(crashes(), [report]);
+
+    fixture.set(Node::Crash(SubCommand::Delete), None::<String>);
+    assert!(crashes().is_empty());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn maintenance_windows() {
+    let fixture = Fixture::new();
+    let now = Local::now();
+    let window = |from: i64, to: i64| MaintenanceWindow {
+        start: (now + Duration::hours(from)).format("%H:%M").to_string(),
+        end: (now + Duration::hours(to)).format("%H:%M").to_string(),
+    };
+    let defer = |request: NodeRequest, window: MaintenanceWindow| {
+        let request = serde_json::to_string(&request).expect("request should serialize");
+        fixture.run::<_, u64>(
+            Node::Maintenance(SubCommand::Add),
+            (request, window, None::<String>),
+        )
+    };
+    let restart = NodeRequest::new(
+        Node::Service(SubCommand::Set),
+        ("ntp", ServiceAction::Restart),
+    )
+    .expect("argument should serialize");
+    let reboot =
+        NodeRequest::new::<Option<String>>(Node::Reboot, None).expect("argument should serialize");
+
+    let later = defer(reboot.clone(), window(2, 3)).expect("reboot deferred");
+    let cancelled = defer(reboot, window(2, 3)).expect("reboot deferred");
+    let now_due = defer(restart, window(-1, 1)).expect("restart deferred");
+    let hostname = NodeRequest::new(Node::Hostname(SubCommand::Set), "node1")
+        .expect("argument should serialize");
+    assert!(defer(hostname, window(-1, 1)).is_err());
+    fixture.set(Node::Maintenance(SubCommand::Delete), cancelled);
+    assert!(fixture
+        .run::<_, String>(Node::Maintenance(SubCommand::Delete), cancelled)
+        .is_err());
+    assert!(Fixture::calls().is_empty());
+
+    let ran = fixture
+        .run::<_, Vec<DeferredOperation>>(Node::Maintenance(SubCommand::Update), None::<String>)
+        .expect("due operations");
+    assert_eq!(ran.len(), 1);
+    assert_eq!(ran[0].id, now_due);
+    assert_eq!(ran[0].status, OperationStatus::Executed);
+    assert!(Fixture::calls().contains(&"systemctl restart ntp".to_string()));
+
+    let queue = fixture
+        .run::<_, Vec<DeferredOperation>>(Node::Maintenance(SubCommand::Get), None::<String>)
+        .expect("maintenance queue");
+    let status = queue
+        .iter()
+        .map(|op| (op.id, op.status.clone()))
+        .collect::<Vec<_>>();
+    assert_eq!(
+        status,
+        [
+            (later, OperationStatus::Queued),
+            (cancelled, OperationStatus::Cancelled),
+            (now_due, OperationStatus::Executed),
+        ]
+    );
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn requester_roles() {
+    let fixture = Fixture::new();
+    let ntp = fs::read_to_string("/etc/ntp.conf").expect("ntp.conf");
+    let request = |kind: Node, role: Option<Role>| {
+        let req = NodeRequest::new(kind, vec!["time.example.com".to_string()])
+            .expect("argument should serialize");
+        match role {
+            Some(role) => req.role(role),
+            None => req,
+        }
+    };
+    let run = |req: NodeRequest| fixture.client.run::<Option<Vec<String>>>(req);
+    let denied = |ret: anyhow::Result<Option<Vec<String>>>| {
+        ret.expect_err("should be denied").to_string() == "permission denied"
+    };
+
+    // Without a policy, a role limits only its own requests.
+    assert!(run(request(Node::Ntp(SubCommand::Get), Some(Role::ReadOnly))).is_ok());
+    assert!(denied(run(request(
+        Node::Ntp(SubCommand::Set),
+        Some(Role::ReadOnly)
+    ))));
+
+    fs::write(ROLES_CONF, "default: operator\noperator_deny: [Ntp::Set]\n")
+        .expect("roles.yaml should be written");
+    assert!(denied(run(request(Node::Ntp(SubCommand::Set), None))));
+    assert!(denied(run(request(
+        Node::Ntp(SubCommand::Get),
+        Some(Role::Admin)
+    ))));
+    assert!(run(request(Node::Ntp(SubCommand::Get), None)).is_ok());
+
+    let results = fixture
+        .client
+        .clone()
+        .role(Role::ReadOnly)
+        .run_transaction(vec![
+            request(Node::Syslog(SubCommand::Init), Some(Role::Operator)),
+            request(Node::Ntp(SubCommand::Set), None),
+        ])
+        .expect("transaction results");
+    let errors = results
+        .into_iter()
+        .map(|ret| ret.decode::<String>().expect_err("should fail").to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(errors, ["not executed", "permission denied"]);
+    assert_eq!(fs::read_to_string("/etc/ntp.conf").expect("ntp.conf"), ntp);
+    assert!(Fixture::calls().is_empty());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn policy_hook() {
+    let fixture = Fixture::new();
+    fs::write(
+        POLICY_HOOK,
+        r#"#!/bin/sh
+case "$1" in
+Ntp::Set) echo "approved by $(grep -o '"user":"[a-z]*"' | cut -d'"' -f4)" ;;
+Hostname::Set) sleep 10 ;;
+*) echo "never in production"; exit 1 ;;
+esac
+"#,
+    )
+    .expect("policy hook should be written");
+    fs::set_permissions(POLICY_HOOK, fs::Permissions::from_mode(0o755))
+        .expect("policy hook should be executable");
+    let vetoed = |ret: anyhow::Result<String>| {
+        ret.expect_err("should be vetoed").to_string() == "vetoed by policy"
+    };
+
+    assert!(vetoed(
+        fixture.run(Node::Ufw(SubCommand::Disable), None::<String>)
+    ));
+    // A hook which does not finish in time vetoes the task.
+    assert!(vetoed(
+        fixture.run(Node::Hostname(SubCommand::Set), "node1.example.com")
+    ));
+    // Read-only tasks are not checked.
+    fixture
+        .run::<_, Option<Vec<String>>>(Node::Ntp(SubCommand::Get), None::<String>)
+        .expect("ntp servers");
+
+    fixture.set(Node::Ntp(SubCommand::Set), vec!["time.example.com"]);
+    let events = fixture
+        .run::<_, Vec<ChangeEvent>>(Node::Changes(SubCommand::Get), 0_u64)
+        .expect("change events");
+    assert_eq!(events.len(), 1);
+    assert_eq!(events[0].notes, ["approved by root"]);
+
+    fs::set_permissions(POLICY_HOOK, fs::Permissions::from_mode(0o777))
+        .expect("policy hook should be writable");
+    assert!(vetoed(
+        fixture.run(Node::Ntp(SubCommand::Set), vec!["10.0.0.1"])
+    ));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn replica() {
+    const SENT: &str = "/var/log/roxy-fixture/replica.json";
+    let fixture = Fixture::new();
+    let _ = fs::remove_file(SENT);
+    fs::write(REPLICATION_HOOK, format!("#!/bin/sh\ncat > {SENT}\n"))
+        .expect("replication hook should be written");
+    fs::set_permissions(REPLICATION_HOOK, fs::Permissions::from_mode(0o755))
+        .expect("replication hook should be executable");
+
+    // A change to the hostname is not shared with the peer.
+    fixture.set(Node::Hostname(SubCommand::Set), "node1.example.com");
+    assert!(fs::metadata(SENT).is_err());
+
+    fixture.set(Node::Ntp(SubCommand::Set), vec!["time.example.com"]);
+    let sent: serde_json::Value =
+        serde_json::from_str(&fs::read_to_string(SENT).expect("replica sent")).expect("json");
+    assert_eq!(sent["kind"], "Ntp::Set");
+    assert!(sent["state"]["hostname"].is_null());
+    assert!(sent["state"]["interfaces"].is_null());
+    let state: SystemState = serde_json::from_value(sent["state"].clone()).expect("state");
+    assert_eq!(state.ntp, Some(vec!["time.example.com".to_string()]));
+    let exported = fixture
+        .run::<_, SystemState>(Node::Replica(SubCommand::Get), None::<String>)
+        .expect("replica");
+    assert_eq!(exported, state);
+
+    // A replica from the peer is not sent back.
+    fs::remove_file(SENT).expect("replica sent");
+    let replica = SystemState {
+        ntp: Some(vec!["10.0.0.1".to_string()]),
+        ..state.clone()
+    };
+    fixture
+        .run::<_, Vec<StateChange>>(Node::Replica(SubCommand::Set), replica)
+        .expect("replica applied");
+    assert!(fs::read_to_string(NTP_CONF)
+        .expect("ntp.conf")
+        .contains("10.0.0.1"));
+    assert!(fs::metadata(SENT).is_err());
+
+    let ret = fixture.run::<_, Vec<StateChange>>(
+        Node::Replica(SubCommand::Set),
+        SystemState {
+            hostname: Some("node2".to_string()),
+            ..state
+        },
+    );
+    assert!(ret.is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn syslog_servers() {
+    let fixture = Fixture::new();
+    fixture.set(
+        Node::Syslog(SubCommand::Set),
+        vec!["@@192.168.0.205:7500".to_string()],
+    );
+    let ret = fixture
+        .run::<_, Option<Vec<(String, String, String)>>>(
+            Node::Syslog(SubCommand::Get),
+            None::<String>,
+        )
+        .expect("syslog servers");
+    assert_eq!(
+        ret,
+        Some(vec![(
+            "user.*".to_string(),
+            "tcp".to_string(),
+            "192.168.0.205:7500".to_string()
+        )])
+    );
+
+    fixture.set(Node::Syslog(SubCommand::Init), None::<String>);
+    let conf = fs::read_to_string("/etc/rsyslog.d/50-default.conf").expect("rsyslog conf");
+    assert!(!conf.contains('@'));
+    assert!(conf.contains("/var/log/auth.log"));
+    assert!(Fixture::calls().contains(&"systemctl restart rsyslog".to_string()));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn interface_set_and_delete() {
+    let fixture = Fixture::new();
+    let new = nic(
+        Some(&["10.0.0.2/24", "10.0.1.2/24"]),
+        None,
+        None,
+        Some(&["10.0.0.53"]),
+    )
+    .search_domains(vec![
+        "example.com".to_string(),
+        "corp.example.com".to_string(),
+    ]);
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Set),
+        ("eth0".to_string(), &new),
+    );
+
+    let get = |ifname: &str| {
+        fixture
+            .run::<_, Option<Vec<(String, NicOutput)>>>(
+                Node::InterfaceV2(SubCommand::Get),
+                Some(ifname.to_string()),
+            )
+            .expect("interface")
+            .and_then(|nics| nics.into_iter().next())
+            .map(|(_, nic)| nic)
+    };
+    assert_eq!(get("eth0"), Some(new));
+    assert!(Fixture::calls().contains(&"netplan apply".to_string()));
+
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Delete),
+        (
+            "eth0".to_string(),
+            nic(Some(&["10.0.1.2/24"]), None, None, None)
+                .search_domains(vec!["corp.example.com".to_string()]),
+        ),
+    );
+    assert_eq!(
+        get("eth0"),
+        Some(
+            nic(Some(&["10.0.0.2/24"]), None, None, Some(&["10.0.0.53"]))
+                .search_domains(vec!["example.com".to_string()])
+        )
+    );
+    assert!(Fixture::calls().contains(&"ip addr del 10.0.1.2/24 dev eth0".to_string()));
+
+    // eth1 keeps its configuration.
+    assert_eq!(
+        get("eth1"),
+        Some(nic(
+            Some(&["192.168.10.2/24"]),
+            None,
+            Some("192.168.10.1"),
+            Some(&["192.168.10.1"])
+        ))
+    );
+}
+
+// A library before `InterfaceV2` exchanges only the settings of
+// NicOutputV1.
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn interface_v1() {
+    let fixture = Fixture::new();
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(Some(&["10.0.0.2/24"]), None, None, Some(&["10.0.0.53"]))
+                .search_domains(vec!["example.com".to_string()]),
+        ),
+    );
+    let old = NicOutputV1::from(nic(
+        Some(&["10.0.0.2/24"]),
+        None,
+        None,
+        Some(&["10.0.0.53"]),
+    ));
+    let get = fixture
+        .run::<_, Option<Vec<(String, NicOutputV1)>>>(
+            Node::Interface(SubCommand::Get),
+            Some("eth0".to_string()),
+        )
+        .expect("interface");
+    assert_eq!(get, Some(vec![("eth0".to_string(), old.clone())]));
+
+    // The settings unknown to the library are overwritten, as before.
+    let new = NicOutputV1 {
+        addresses: Some(vec!["10.0.0.3/24".to_string()]),
+        ..old
+    };
+    fixture.set(
+        Node::Interface(SubCommand::Set),
+        ("eth0".to_string(), new.clone()),
+    );
+    let get = fixture
+        .run::<_, Option<Vec<(String, NicOutput)>>>(
+            Node::InterfaceV2(SubCommand::Get),
+            Some("eth0".to_string()),
+        )
+        .expect("interface");
+    let new: NicOutput = new.into();
+    assert_eq!(get, Some(vec![("eth0".to_string(), new)]));
+
+    let state = fixture
+        .run::<_, SystemStateV1>(Node::State(SubCommand::Get), None::<String>)
+        .expect("state");
+    assert!(state
+        .interfaces
+        .is_some_and(|nics| nics.contains_key("eth0")));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn optional_interface() {
+    let fixture = Fixture::new();
+    let new = nic(None, Some(true), None, None)
+        .optional(true)
+        .activation_mode(ActivationMode::Manual);
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Set),
+        ("eth0".to_string(), &new),
+    );
+
+    let ret = fixture
+        .run::<_, Option<Vec<(String, NicOutput)>>>(
+            Node::InterfaceV2(SubCommand::Get),
+            Some("eth0".to_string()),
+        )
+        .expect("interface");
+    assert_eq!(ret, Some(vec![("eth0".to_string(), new)]));
+    let yaml = fs::read_to_string("/etc/netplan/01-netcfg.yaml").expect("netplan yaml");
+    assert!(yaml.contains("optional: true"));
+    assert!(yaml.contains("activation-mode: manual"));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn dns_priority() {
+    let fixture = Fixture::new();
+    let eth0 = nic(Some(&["10.0.0.2/24"]), None, None, Some(&["10.0.0.53"])).dns_priority(10);
+    let eth1 = nic(
+        Some(&["192.168.10.2/24"]),
+        None,
+        Some("192.168.10.1"),
+        Some(&["192.168.10.1"]),
+    )
+    .dns_priority(20);
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Set),
+        ("eth1".to_string(), &eth1),
+    );
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Set),
+        ("eth0".to_string(), &eth0),
+    );
+
+    let interfaces = fixture
+        .run::<_, Option<Vec<(String, NicOutput)>>>(
+            Node::InterfaceV2(SubCommand::Get),
+            None::<String>,
+        )
+        .expect("interfaces")
+        .expect("all interfaces");
+    assert_eq!(
+        interfaces,
+        vec![("eth0".to_string(), eth0), ("eth1".to_string(), eth1)]
+    );
+    assert_eq!(
+        roxy::common::nameserver_order(&interfaces),
+        ["eth0", "eth1"]
+    );
+    let yaml = fs::read_to_string("/etc/netplan/01-netcfg.yaml").expect("netplan yaml");
+    assert_eq!(yaml.matches("~.").count(), 1);
+
+    // eth1 already has priority 20.
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(None, None, None, Some(&["10.0.0.53"])).dns_priority(20),
+        ),
+    );
+    assert!(ret.is_err());
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(None, Some(true), None, None).dns_priority(5),
+        ),
+    );
+    assert!(ret.is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn interface_labels() {
+    let fixture = Fixture::new();
+    let eth1 = nic(
+        Some(&["192.168.10.2/24"]),
+        None,
+        Some("192.168.10.1"),
+        Some(&["192.168.10.1"]),
+    );
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Set),
+        ("eth1".to_string(), eth1.clone().label("Capture-A")),
+    );
+    let get = |name: &str| {
+        fixture
+            .run::<_, Option<Vec<(String, NicOutput)>>>(
+                Node::InterfaceV2(SubCommand::Get),
+                Some(name.to_string()),
+            )
+            .expect("interface")
+    };
+    assert_eq!(
+        get("Capture-A"),
+        Some(vec![("eth1".to_string(), eth1.clone().label("Capture-A"))])
+    );
+
+    for label in ["Capture-A", "lo", "Capture A", ""] {
+        let ret = fixture.run::<_, String>(
+            Node::InterfaceV2(SubCommand::Set),
+            (
+                "eth0".to_string(),
+                nic(None, Some(true), None, None).label(label),
+            ),
+        );
+        assert!(ret.is_err(), "{label:?} should be refused");
+    }
+
+    // The label is kept when the interface is set by its label.
+    let eth1 = nic(Some(&["192.168.10.3/24"]), None, None, None);
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Set),
+        ("Capture-A".to_string(), &eth1),
+    );
+    assert_eq!(
+        get("eth1"),
+        Some(vec![("eth1".to_string(), eth1.clone().label("Capture-A"))])
+    );
+
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Delete),
+        (
+            "Capture-A".to_string(),
+            nic(None, None, None, None).label("Capture-A"),
+        ),
+    );
+    assert_eq!(get("eth1"), Some(vec![("eth1".to_string(), eth1)]));
+    assert!(!Path::new(INTERFACE_LABEL_CONF).exists());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn interface_groups() {
+    let fixture = Fixture::new();
+    let members =
+        |target: &str| fixture.run::<_, Vec<String>>(Node::InterfaceGroup(SubCommand::Get), target);
+    let names = members("eth*").expect("interfaces matching eth*");
+    assert!(names.contains(&"eth0".to_string()));
+    assert!(names.contains(&"eth1".to_string()));
+    assert!(members("uplinks").is_err());
+
+    fs::write(INTERFACE_GROUPS_CONF, "uplinks: [eth1]\n").expect("groups should be written");
+    assert_eq!(members("uplinks").expect("uplinks"), ["eth1"]);
+
+    // eth1 keeps its gateway, which has no metric, until it is set.
+    let settings = GroupSettings {
+        nic: Some(nic(Some(&["10.0.0.2/24"]), None, Some("10.0.0.1"), None)),
+        capture: None,
+    };
+    let results = fixture
+        .run::<_, Vec<InterfaceResult>>(Node::InterfaceGroup(SubCommand::Set), ("eth*", &settings))
+        .expect("results");
+    let result = |ifname: &str| {
+        results
+            .iter()
+            .find(|ret| ret.ifname == ifname)
+            .expect("result of the interface")
+    };
+    assert!(!result("eth0").succeeded());
+    assert!(result("eth1").succeeded());
+    let yaml = fs::read_to_string("/etc/netplan/01-netcfg.yaml").expect("netplan yaml");
+    assert!(yaml.contains("10.0.0.2/24"));
+
+    let settings = GroupSettings {
+        nic: Some(nic(None, Some(true), None, None).label("uplink")),
+        capture: None,
+    };
+    assert!(fixture
+        .run::<_, Vec<InterfaceResult>>(
+            Node::InterfaceGroup(SubCommand::Set),
+            ("uplinks", &settings),
+        )
+        .is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn gateway_check() {
+    let fixture = Fixture::new();
+    let eth1 = nic(
+        Some(&["192.168.10.2/24"]),
+        None,
+        Some("192.168.10.3"),
+        Some(&["192.168.10.1"]),
+    );
+    let ret = fixture
+        .run::<_, Option<bool>>(
+            Node::InterfaceV2(SubCommand::Set),
+            ("eth1".to_string(), &eth1, true),
+        )
+        .expect("gateway check");
+    // The stub of arping always succeeds.
+    assert_eq!(ret, Some(true));
+    assert!(Fixture::calls().contains(&"arping -q -c 3 -w 5 -I eth1 192.168.10.3".to_string()));
+
+    let ret = fixture
+        .run::<_, Option<bool>>(
+            Node::InterfaceV2(SubCommand::Set),
+            ("eth0".to_string(), nic(None, Some(true), None, None), true),
+        )
+        .expect("gateway check");
+    assert_eq!(ret, None);
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn gateway_metrics() {
+    let fixture = Fixture::new();
+    let eth1 = nic(
+        Some(&["192.168.10.2/24"]),
+        None,
+        Some("192.168.10.1"),
+        Some(&["192.168.10.1"]),
+    )
+    .gateway_metric(100);
+    let eth0 = nic(Some(&["10.0.0.2/24"]), None, Some("10.0.0.1"), None).gateway_metric(200);
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Set),
+        ("eth1".to_string(), &eth1),
+    );
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Set),
+        ("eth0".to_string(), &eth0),
+    );
+
+    let interfaces = fixture
+        .run::<_, Option<Vec<(String, NicOutput)>>>(
+            Node::InterfaceV2(SubCommand::Get),
+            None::<String>,
+        )
+        .expect("interfaces");
+    assert_eq!(
+        interfaces,
+        Some(vec![("eth0".to_string(), eth0), ("eth1".to_string(), eth1)])
+    );
+    let yaml = fs::read_to_string("/etc/netplan/01-netcfg.yaml").expect("netplan yaml");
+    assert!(!yaml.contains("gateway4"));
+    assert!(yaml.contains("metric: 200"));
+
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(None, None, Some("10.0.0.1"), None).gateway_metric(100),
+        ),
+    );
+    assert!(ret.is_err());
+
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Delete),
+        ("eth0".to_string(), nic(None, None, Some("10.0.0.1"), None)),
+    );
+    assert!(!fs::read_to_string("/etc/netplan/01-netcfg.yaml")
+        .expect("netplan yaml")
+        .contains("metric: 200"));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn policy_routing() {
+    let fixture = Fixture::new();
+    // Traffic from 10.0.0.2 leaves through 10.0.0.1 while eth1 has the main
+    // default route.
+    let route = RouteOutput {
+        to: "default".to_string(),
+        via: Some("10.0.0.1".to_string()),
+        metric: None,
+        table: Some(100),
+    };
+    let rule = RoutingRuleOutput {
+        from: Some("10.0.0.2/32".to_string()),
+        to: None,
+        table: 100,
+        priority: Some(10),
+        mark: None,
+    };
+    let eth0 = nic(Some(&["10.0.0.2/24"]), None, None, None)
+        .routes(vec![route.clone()])
+        .routing_policy(vec![rule.clone()]);
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Set),
+        ("eth0".to_string(), &eth0),
+    );
+
+    let get = || {
+        fixture
+            .run::<_, Option<Vec<(String, NicOutput)>>>(
+                Node::InterfaceV2(SubCommand::Get),
+                Some("eth0".to_string()),
+            )
+            .expect("interface")
+            .and_then(|nics| nics.into_iter().next())
+            .map(|(_, nic)| nic)
+    };
+    assert_eq!(get(), Some(eth0));
+    let yaml = fs::read_to_string("/etc/netplan/01-netcfg.yaml").expect("netplan yaml");
+    assert!(yaml.contains("routing-policy"));
+    assert!(yaml.contains("table: 100"));
+
+    fixture.set(
+        Node::InterfaceV2(SubCommand::Delete),
+        (
+            "eth0".to_string(),
+            nic(None, None, None, None)
+                .routes(vec![route.clone()])
+                .routing_policy(vec![rule]),
+        ),
+    );
+    let eth0 = get().expect("eth0");
+    assert!(eth0.routes.is_none());
+    assert!(eth0.routing_policy.is_none());
+
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(None, None, None, None).routing_policy(vec![RoutingRuleOutput {
+                from: None,
+                to: None,
+                table: 100,
+                priority: None,
+                mark: Some(1),
+            }]),
+        ),
+    );
+    assert!(ret.is_err());
+
+    // A default route in the main table is checked like a gateway: eth1 has
+    // a gateway without a metric.
+    let main = RouteOutput {
+        table: None,
+        ..route.clone()
+    };
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(Some(&["10.0.0.2/24"]), None, None, None).routes(vec![main.clone()]),
+        ),
+    );
+    assert!(ret.is_err());
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth1".to_string(),
+            nic(Some(&["192.168.10.2/24"]), None, Some("192.168.10.1"), None)
+                .gateway_metric(100)
+                .routes(vec![RouteOutput {
+                    metric: Some(100),
+                    ..main
+                }]),
+        ),
+    );
+    assert!(ret.is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn invalid_interface() {
+    let fixture = Fixture::new();
+    let yaml = fs::read_to_string("/etc/netplan/01-netcfg.yaml").expect("netplan yaml");
+
+    // Only one interface can have a gateway without a metric, and eth1 has
+    // one.
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        ("eth0".to_string(), nic(None, None, Some("10.0.0.1"), None)),
+    );
+    assert!(ret.is_err());
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(Some(&["10.0.0.256/24"]), None, None, None),
+        ),
+    );
+    assert!(ret.is_err());
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(None, None, None, None).search_domains(vec!["example..com".to_string()]),
+        ),
+    );
+    assert!(ret.is_err());
+    // The loopback interface has 127.0.0.1.
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(Some(&["127.0.0.1/8"]), None, None, None),
+        ),
+    );
+    assert!(ret.is_err_and(|e| {
+        e.downcast_ref::<AddressConflict>()
+            == Some(&AddressConflict("127.0.0.1 is assigned to lo".to_string()))
+    }));
+    // eth1 is configured with 192.168.10.2/24.
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(Some(&["192.168.10.2/16"]), None, None, None),
+        ),
+    );
+    assert!(ret.is_err_and(|e| {
+        e.downcast_ref::<AddressConflict>()
+            == Some(&AddressConflict(
+                "192.168.10.2 is configured for eth1".to_string(),
+            ))
+    }));
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(Some(&["192.168.0.1/16"]), None, None, None),
+        ),
+    );
+    assert!(ret.is_err_and(|e| {
+        e.downcast_ref::<AddressConflict>()
+            == Some(&AddressConflict(
+                "192.168.0.0/16 overlaps 192.168.10.0/24 of eth1".to_string(),
+            ))
+    }));
+    // So are WireGuard interfaces.
+    fs::create_dir_all("/etc/wireguard").expect("wireguard directory");
+    fs::write(
+        "/etc/wireguard/wg-test.conf",
+        "[Interface]\nPrivateKey = yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=\n\
+         Address = 10.99.0.2/24\n[Peer]\n\
+         PublicKey = HIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PKf8ykw=\nAllowedIPs = 0.0.0.0/0\n",
+    )
+    .expect("wireguard conf should be written");
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(Some(&["10.99.0.5/24"]), None, None, None),
+        ),
+    );
+    fs::remove_file("/etc/wireguard/wg-test.conf").expect("wireguard conf should be removed");
+    assert!(ret.is_err_and(|e| {
+        e.downcast_ref::<AddressConflict>()
+            == Some(&AddressConflict(
+                "10.99.0.0/24 overlaps 10.99.0.0/24 of wg-test".to_string(),
+            ))
+    }));
+
+    assert_eq!(
+        fs::read_to_string("/etc/netplan/01-netcfg.yaml").expect("netplan yaml"),
+        yaml
+    );
+    assert!(!Fixture::calls().contains(&"netplan apply".to_string()));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn transaction_rollback() {
+    let fixture = Fixture::new();
+    let ntp = fs::read_to_string("/etc/ntp.conf").expect("ntp.conf");
+    let hosts = fs::read_to_string(HOSTS).expect("hosts");
+    let hostname = fixture
+        .run::<_, HostnameInfo>(Node::Hostname(SubCommand::Status), None::<String>)
+        .expect("hostname");
+
+    let reqs = vec![
+        NodeRequest::new(
+            Node::Ntp(SubCommand::Set),
+            vec!["time.example.com".to_string()],
+        )
+        .expect("argument should serialize"),
+        NodeRequest::new(
+            Node::Hostname(SubCommand::Set),
+            format!("{}.rollback.example.com", hostname.hostname),
+        )
+        .expect("argument should serialize"),
+        NodeRequest::new(
+            Node::InterfaceV2(SubCommand::Set),
+            (
+                "eth0".to_string(),
+                nic(Some(&["invalid"]), None, None, None),
+            ),
+        )
+        .expect("argument should serialize"),
+    ];
+    let results = fixture
+        .client
+        .run_transaction(reqs)
+        .expect("transaction results");
+    assert_eq!(results.len(), 3);
+    for ret in results {
+        assert!(ret.decode::<String>().is_err());
+    }
+    assert_eq!(fs::read_to_string("/etc/ntp.conf").expect("ntp.conf"), ntp);
+    assert_eq!(fs::read_to_string(HOSTS).expect("hosts"), hosts);
+    let restored = fixture
+        .run::<_, HostnameInfo>(Node::Hostname(SubCommand::Status), None::<String>)
+        .expect("hostname");
+    assert_eq!(restored, hostname);
+    let short = fixture
+        .run::<_, String>(Node::Hostname(SubCommand::Get), None::<String>)
+        .expect("hostname");
+    assert_eq!(short, hostname.hostname);
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn transaction_untracked() {
+    let fixture = Fixture::new();
+    let reqs = vec![
+        NodeRequest::new(
+            Node::Capture(SubCommand::Set),
+            ("lo".to_string(), CaptureSettings::capture()),
+        )
+        .expect("argument should serialize"),
+        NodeRequest::new(
+            Node::InterfaceV2(SubCommand::Set),
+            (
+                "eth0".to_string(),
+                nic(Some(&["invalid"]), None, None, None),
+            ),
+        )
+        .expect("argument should serialize"),
+    ];
+    let results = fixture
+        .client
+        .run_transaction(reqs)
+        .expect("transaction results");
+    let errors = results
+        .into_iter()
+        .map(|ret| ret.decode::<String>().expect_err("should fail").to_string())
+        .collect::<Vec<_>>();
+    assert_eq!(errors, ["invalid command", "invalid command"]);
+    assert!(!Path::new(CAPTURE_UNIT).exists());
+    assert!(Fixture::calls().is_empty());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn capture_settings() {
+    let fixture = Fixture::new();
+    fixture.set(
+        Node::Capture(SubCommand::Set),
+        ("lo".to_string(), CaptureSettings::capture()),
+    );
+    let calls = Fixture::calls();
+    assert!(calls.contains(&"ip link set dev lo promisc on".to_string()));
+    assert!(calls.contains(&"ethtool -K lo gro off lro off tso off".to_string()));
+    assert!(calls.contains(&"systemctl enable roxy-capture-lo.service".to_string()));
+    let unit = fs::read_to_string(CAPTURE_UNIT).expect("capture unit");
+    assert!(unit.contains("ExecStart=ethtool -K lo gro off lro off tso off"));
+
+    let ret = fixture.run::<_, String>(
+        Node::Capture(SubCommand::Set),
+        ("no-such-nic".to_string(), CaptureSettings::capture()),
+    );
+    assert!(ret.is_err());
+
+    fixture.set(Node::Capture(SubCommand::Delete), "lo".to_string());
+    assert!(fs::metadata(CAPTURE_UNIT).is_err());
+    assert!(Fixture::calls().contains(&"systemctl disable roxy-capture-lo.service".to_string()));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn netns() {
+    let fixture = Fixture::new();
+    // The fixture has no named network namespace.
+    let nics = fixture
+        .run::<_, Vec<NetnsInterface>>(Node::Netns(SubCommand::List), None::<String>)
+        .expect("netns interfaces");
+    assert!(nics.is_empty());
+    let ret = fixture.run::<_, Vec<NetnsInterface>>(
+        Node::Netns(SubCommand::List),
+        Some("no-such-ns".to_string()),
+    );
+    assert!(ret.is_err());
+
+    let capture = NetnsCapture {
+        namespace: "no-such-ns".to_string(),
+        interface: "eth0".to_string(),
+        count: 10,
+        timeout_secs: 5,
+        filter: None,
+    };
+    let req = NodeRequest::new(Node::Netns(SubCommand::Update), capture)
+        .expect("argument should serialize");
+    let ret = fixture
+        .client
+        .run::<Vec<String>>(req.clone().role(Role::ReadOnly));
+    assert_eq!(
+        ret.expect_err("should be denied").to_string(),
+        "permission denied"
+    );
+    let ret = fixture.client.run::<Vec<String>>(req);
+    assert!(ret.is_err());
+    assert!(!Fixture::calls()
+        .iter()
+        .any(|call| call.starts_with("ip netns")));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn bond_status() {
+    let fixture = Fixture::new();
+    let bonds = fixture
+        .run::<_, Vec<BondStatus>>(
+            Node::Bond(SubCommand::Status),
+            Some("no-such-bond".to_string()),
+        )
+        .expect("bond status");
+    assert!(bonds.is_empty());
+    assert!(fixture
+        .run::<_, Vec<BondStatus>>(Node::Bond(SubCommand::Get), None::<String>)
+        .is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn cpu_governor() {
+    let fixture = Fixture::new();
+    let status = fixture
+        .run::<_, GovernorStatus>(Node::CpuGovernor(SubCommand::Get), None::<String>)
+        .expect("cpu governors");
+    assert_eq!(status.persistent, None);
+    assert!(status.cpus.windows(2).all(|pair| pair[0].cpu < pair[1].cpu));
+
+    let ret = fixture.run::<_, String>(
+        Node::CpuGovernor(SubCommand::Set),
+        "no-such-governor".to_string(),
+    );
+    assert!(ret.is_err());
+    assert!(fs::metadata("/etc/systemd/system/roxy-cpu-governor.service").is_err());
+    fixture.set(Node::CpuGovernor(SubCommand::Delete), None::<String>);
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn service_environment() {
+    const DROP_IN: &str = "/etc/systemd/system/review.service.d/override.conf";
+    let fixture = Fixture::new();
+    fixture.set(
+        Node::ServiceEnvironment(SubCommand::Set),
+        (
+            "review",
+            BTreeMap::from([
+                ("RUST_LOG".to_string(), "debug".to_string()),
+                ("FLAGS".to_string(), "--capture \"eth1\"".to_string()),
+            ]),
+        ),
+    );
+    let contents = fs::read_to_string(DROP_IN).expect("drop-in");
+    assert!(contents.starts_with("[Service]\n"));
+    assert!(contents.contains("Environment=\"RUST_LOG=debug\"\n"));
+    assert!(Fixture::calls().contains(&"systemctl daemon-reload".to_string()));
+
+    let environment = fixture
+        .run::<_, BTreeMap<String, String>>(
+            Node::ServiceEnvironment(SubCommand::Get),
+            "review.service".to_string(),
+        )
+        .expect("environment");
+    assert_eq!(environment["FLAGS"], "--capture \"eth1\"");
+
+    let ret = fixture.run::<_, String>(
+        Node::ServiceEnvironment(SubCommand::Set),
+        (
+            "../review".to_string(),
+            BTreeMap::from([("A".to_string(), "1".to_string())]),
+        ),
+    );
+    assert!(ret.is_err());
+    // Only the services in services.yaml, and no loader variables.
+    let ret = fixture.run::<_, String>(
+        Node::ServiceEnvironment(SubCommand::Set),
+        (
+            "ssh".to_string(),
+            BTreeMap::from([("A".to_string(), "1".to_string())]),
+        ),
+    );
+    assert!(ret.is_err());
+    let ret = fixture.run::<_, String>(
+        Node::ServiceEnvironment(SubCommand::Set),
+        (
+            "review".to_string(),
+            BTreeMap::from([("LD_PRELOAD".to_string(), "/tmp/x.so".to_string())]),
+        ),
+    );
+    assert!(ret.is_err());
+    assert!(!fs::read_to_string(DROP_IN)
+        .expect("drop-in")
+        .contains("LD_PRELOAD"));
+
+    fixture.set(
+        Node::ServiceEnvironment(SubCommand::Delete),
+        ("review", vec!["RUST_LOG".to_string(), "FLAGS".to_string()]),
+    );
+    assert!(fs::metadata(DROP_IN).is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn vrrp() {
+    const KEEPALIVED_CONF: &str = "/etc/keepalived/keepalived.conf";
+    let fixture = Fixture::new();
+    let config = VrrpConfig {
+        interface: "lo".to_string(),
+        virtual_router_id: 51,
+        priority: 150,
+        virtual_addresses: vec!["192.0.2.100/24".to_string()],
+        auth_pass: Some("s3cret".to_string()),
+    };
+    fixture.set(Node::Vrrp(SubCommand::Set), config.clone());
+    let contents = fs::read_to_string(KEEPALIVED_CONF).expect("keepalived.conf");
+    assert!(contents.contains("    virtual_router_id 51\n"));
+    let conf = fs::metadata(KEEPALIVED_CONF).expect("keepalived.conf");
+    assert_eq!(conf.permissions().mode() & 0o777, 0o600);
+    let script = fs::metadata("/etc/roxy/vrrp-notify.sh").expect("notify script");
+    assert_eq!(script.permissions().mode() & 0o777, 0o755);
+    assert!(
+        Fixture::calls().contains(&"systemctl reload-or-restart keepalived.service".to_string())
+    );
+
+    let status = fixture
+        .run::<_, VrrpStatus>(Node::Vrrp(SubCommand::Get), None::<String>)
+        .expect("vrrp status");
+    let without_pass = VrrpConfig {
+        auth_pass: None,
+        ..config.clone()
+    };
+    assert_eq!(status.config, Some(without_pass));
+    assert!(!status.holds_addresses);
+
+    let ret = fixture.run::<_, String>(
+        Node::Vrrp(SubCommand::Set),
+        VrrpConfig {
+            interface: "no-such-nic".to_string(),
+            ..config.clone()
+        },
+    );
+    assert!(ret.is_err());
+    // eth1 is configured with 192.168.10.2/24.
+    let ret = fixture.run::<_, String>(
+        Node::Vrrp(SubCommand::Set),
+        VrrpConfig {
+            virtual_addresses: vec!["192.168.10.100/24".to_string()],
+            ..config.clone()
+        },
+    );
+    assert_eq!(
+        ret.expect_err("should conflict").to_string(),
+        "address conflict: 192.168.10.0/24 overlaps 192.168.10.0/24 of eth1"
+    );
+
+    fixture.set(Node::Vrrp(SubCommand::Delete), None::<String>);
+    assert!(fs::metadata(KEEPALIVED_CONF).is_err());
+
+    // A configuration written otherwise is neither replaced nor removed.
+    let foreign = "vrrp_instance other {\n    interface lo\n}\n";
+    fs::write(KEEPALIVED_CONF, foreign).expect("keepalived.conf should be written");
+    assert!(fixture
+        .run::<_, String>(Node::Vrrp(SubCommand::Set), config)
+        .is_err());
+    assert!(fixture
+        .run::<_, String>(Node::Vrrp(SubCommand::Delete), None::<String>)
+        .is_err());
+    let status = fixture
+        .run::<_, VrrpStatus>(Node::Vrrp(SubCommand::Get), None::<String>)
+        .expect("vrrp status");
+    assert_eq!(status.config, None);
+    assert_eq!(
+        fs::read_to_string(KEEPALIVED_CONF).expect("keepalived.conf"),
+        foreign
+    );
+    fs::remove_file(KEEPALIVED_CONF).expect("keepalived.conf should be removed");
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn wireguard() {
+    const PRIVATE_KEY: &str = "yAnz5TF+lXXJte14tji3zlMNq+hd2rYUIgJBgB3fBmk=";
+    const PUBLIC_KEY: &str = "HIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PKf8ykw=";
+    let fixture = Fixture::new();
+    let config = WireguardConfig {
+        interface: "wg-mgmt".to_string(),
+        addresses: vec!["10.99.0.2/24".to_string()],
+        listen_port: None,
+        peer: WireguardPeer {
+            public_key: "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=".to_string(),
+            endpoint: Some("manager.example.com:51820".to_string()),
+            allowed_ips: vec!["10.99.0.0/24".to_string()],
+            persistent_keepalive: Some(25),
+        },
+    };
+    // The stub of wg generates no key.
+    let ret = fixture.run::<_, String>(Node::Wireguard(SubCommand::Set), config.clone());
+    assert!(ret.is_err());
+    assert!(fs::metadata(WIREGUARD_CONF).is_err());
+
+    // The keys of an existing interface are kept.
+    fs::create_dir_all("/etc/wireguard").expect("wireguard directory");
+    fs::write(
+        WIREGUARD_CONF,
+        format!(
+            "# PublicKey = {PUBLIC_KEY}\n[Interface]\nPrivateKey = {PRIVATE_KEY}\n\
+             Address = 10.0.0.1/24\n[Peer]\nPublicKey = {PUBLIC_KEY}\nAllowedIPs = 0.0.0.0/0\n"
+        ),
+    )
+    .expect("wireguard conf should be written");
+    fixture.set(Node::Wireguard(SubCommand::Set), config.clone());
+    let contents = fs::read_to_string(WIREGUARD_CONF).expect("wireguard conf");
+    assert!(contents.contains(&format!("PrivateKey = {PRIVATE_KEY}\n")));
+    assert!(contents.contains("Endpoint = manager.example.com:51820\n"));
+    let metadata = fs::metadata(WIREGUARD_CONF).expect("wireguard conf");
+    assert_eq!(metadata.permissions().mode() & 0o777, 0o600);
+    let calls = Fixture::calls();
+    assert!(calls.contains(&"systemctl enable wg-quick@wg-mgmt.service".to_string()));
+    assert!(calls.contains(&"systemctl restart wg-quick@wg-mgmt.service".to_string()));
+
+    let status = fixture
+        .run::<_, Option<WireguardStatus>>(Node::Wireguard(SubCommand::Get), "wg-mgmt")
+        .expect("wireguard status")
+        .expect("configured interface");
+    assert_eq!(status.config, config);
+    assert_eq!(status.public_key, PUBLIC_KEY);
+    assert_eq!(status.latest_handshake, None);
+
+    fixture.set(Node::Wireguard(SubCommand::Delete), "wg-mgmt");
+    assert!(fs::metadata(WIREGUARD_CONF).is_err());
+    let status = fixture
+        .run::<_, Option<WireguardStatus>>(Node::Wireguard(SubCommand::Get), "wg-mgmt")
+        .expect("wireguard status");
+    assert_eq!(status, None);
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn tunnel() {
+    let fixture = Fixture::new();
+    // A tunnel roxy does not manage is kept.
+    let mut yaml = fs::read_to_string("/etc/netplan/01-netcfg.yaml").expect("netplan yaml");
+    yaml.push_str("  tunnels:\n    he-ipv6:\n      mode: sit\n      remote: 198.51.100.2\n");
+    fs::write("/etc/netplan/01-netcfg.yaml", yaml).expect("netplan yaml should be written");
+
+    // lo carries the tunnel, with MTU 65536.
+    let mut config = TunnelConfig {
+        mode: TunnelMode::Gre,
+        local: "127.0.0.1".to_string(),
+        remote: Some("192.0.2.1".to_string()),
+        vni: None,
+        port: None,
+        key: None,
+        addresses: Some(vec!["10.0.0.1/30".to_string()]),
+        mtu: Some(65530),
+    };
+    let ret = fixture.run::<_, String>(
+        Node::Tunnel(SubCommand::Set),
+        ("mirror0".to_string(), config.clone()),
+    );
+    assert!(ret.is_err());
+    assert!(!Fixture::calls().contains(&"netplan apply".to_string()));
+    let ret = fixture.run::<_, String>(
+        Node::Tunnel(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            TunnelConfig {
+                mtu: None,
+                ..config.clone()
+            },
+        ),
+    );
+    assert!(ret.is_err());
+    // eth1 is configured with 192.168.10.2/24.
+    let ret = fixture.run::<_, String>(
+        Node::Tunnel(SubCommand::Set),
+        (
+            "mirror0".to_string(),
+            TunnelConfig {
+                addresses: Some(vec!["192.168.10.9/30".to_string()]),
+                mtu: None,
+                ..config.clone()
+            },
+        ),
+    );
+    assert!(ret.is_err_and(|e| {
+        e.downcast_ref::<AddressConflict>()
+            == Some(&AddressConflict(
+                "192.168.10.8/30 overlaps 192.168.10.0/24 of eth1".to_string(),
+            ))
+    }));
+    assert!(!Fixture::calls().contains(&"netplan apply".to_string()));
+
+    config.mtu = None;
+    fixture.set(
+        Node::Tunnel(SubCommand::Set),
+        ("mirror0".to_string(), config.clone()),
+    );
+    assert!(Fixture::calls().contains(&"netplan apply".to_string()));
+    let yaml = fs::read_to_string("/etc/netplan/01-netcfg.yaml").expect("netplan yaml");
+    assert!(yaml.contains("mirror0:"));
+    assert!(yaml.contains("mode: sit"));
+    // The address of a tunnel is not set for another interface.
+    let ret = fixture.run::<_, String>(
+        Node::InterfaceV2(SubCommand::Set),
+        (
+            "eth0".to_string(),
+            nic(Some(&["10.0.0.2/30"]), None, None, None),
+        ),
+    );
+    assert!(ret.is_err_and(|e| {
+        e.downcast_ref::<AddressConflict>()
+            == Some(&AddressConflict(
+                "10.0.0.0/30 overlaps 10.0.0.0/30 of mirror0".to_string(),
+            ))
+    }));
+    let tunnels = fixture
+        .run::<_, Vec<(String, TunnelConfig)>>(Node::Tunnel(SubCommand::Get), None::<String>)
+        .expect("tunnels");
+    config.mtu = Some(65512);
+    assert_eq!(tunnels, vec![("mirror0".to_string(), config)]);
+
+    fixture.set(Node::Tunnel(SubCommand::Delete), "mirror0");
+    let tunnels = fixture
+        .run::<_, Vec<(String, TunnelConfig)>>(
+            Node::Tunnel(SubCommand::Get),
+            Some("mirror0".to_string()),
+        )
+        .expect("tunnels");
+    assert!(tunnels.is_empty());
+    let yaml = fs::read_to_string("/etc/netplan/01-netcfg.yaml").expect("netplan yaml");
+    assert!(yaml.contains("he-ipv6:"));
+    let ret = fixture.run::<_, String>(Node::Tunnel(SubCommand::Delete), "mirror0");
+    assert!(ret.is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn tuning() {
+    let fixture = Fixture::new();
+    let status = fixture
+        .run::<_, TuningStatus>(Node::Tuning(SubCommand::Get), None::<String>)
+        .expect("tuning profile");
+    assert_eq!(status.profile, "default");
+    assert!(status.profiles.iter().any(|p| p == "capture"));
+    assert!(!status.settings.is_empty());
+
+    // Applying a profile writes to the running kernel, which the fixture
+    // shares with the host.
+    let ret =
+        fixture.run::<_, String>(Node::Tuning(SubCommand::Set), "no-such-profile".to_string());
+    assert!(ret.is_err());
+    assert!(fs::metadata("/etc/sysctl.d/90-roxy-tuning.conf").is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn mac_denial_hint() {
+    let fixture = Fixture::new();
+    let ret =
+        fixture.run::<_, String>(Node::Tuning(SubCommand::Set), "no-such-profile".to_string());
+    assert_eq!(ret.expect_err("unknown profile").to_string(), "fail");
+
+    // A denial logged by auditd while the task runs.
+    fs::create_dir_all("/var/log/audit").expect("audit log directory");
+    fs::write(
+        AUDIT_LOG,
+        format!(
+            "type=AVC msg=audit({}.123:45): avc:  denied  {{ write }} for  pid=1 comm=\"roxy\" \
+             name=\"sysctl.d\" scontext=system_u:system_r:roxy_t:s0\n",
+            Local::now().timestamp() + 60
+        ),
+    )
+    .expect("audit log should be written");
+    let ret =
+        fixture.run::<_, String>(Node::Tuning(SubCommand::Set), "no-such-profile".to_string());
+    fs::remove_file(AUDIT_LOG).expect("audit log should be removable");
+    let err = ret.expect_err("unknown profile").to_string();
+    assert!(err.contains("denied by SELinux"), "{err}");
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn irq_affinity() {
+    let fixture = Fixture::new();
+    let layout = fixture
+        .run::<_, IrqLayout>(Node::IrqAffinity(SubCommand::Get), "lo".to_string())
+        .expect("irq layout");
+    assert_eq!(layout.interface, "lo");
+    assert!(layout.irqs.is_empty());
+    assert_eq!(layout.pinned, None);
+
+    // The loopback has no IRQs to pin.
+    let ret = fixture.run::<_, String>(
+        Node::IrqAffinity(SubCommand::Set),
+        ("lo".to_string(), vec![0_u32]),
+    );
+    assert!(ret.is_err());
+    assert!(fs::metadata("/etc/systemd/system/roxy-irq-affinity-lo.service").is_err());
+    fixture.set(Node::IrqAffinity(SubCommand::Delete), "lo".to_string());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn container_service() {
+    let fixture = Fixture::new();
+    for (cmd, call) in [
+        (SubCommand::Enable, "docker restart aice-review"),
+        (SubCommand::Disable, "docker stop aice-review"),
+        (SubCommand::Update, "podman restart crusher"),
+    ] {
+        let service = if call.starts_with("docker") {
+            "review"
+        } else {
+            "crusher"
+        };
+        let ret = fixture
+            .run::<_, bool>(Node::Service(cmd), service)
+            .expect("service control");
+        assert!(ret);
+        assert!(Fixture::calls().contains(&call.to_string()));
+    }
+
+    for (action, call) in [
+        (ServiceAction::Start, "docker start aice-review"),
+        (
+            ServiceAction::EnableAtBoot,
+            "docker update --restart unless-stopped aice-review",
+        ),
+    ] {
+        let ret = fixture
+            .run::<_, bool>(Node::Service(SubCommand::Set), ("review", action))
+            .expect("service action");
+        assert!(ret);
+        assert!(Fixture::calls().contains(&call.to_string()));
+    }
+    let ret = fixture
+        .run::<_, bool>(
+            Node::Service(SubCommand::Set),
+            ("ntp", ServiceAction::DisableAtBoot),
+        )
+        .expect("service action");
+    assert!(ret);
+    assert!(Fixture::calls().contains(&"systemctl disable ntp".to_string()));
+
+    fixture
+        .run::<_, String>(Node::Service(SubCommand::Get), ("review", 100_u32))
+        .expect("service logs");
+    assert!(Fixture::calls().contains(&"docker logs --tail 100 aice-review".to_string()));
+    fixture
+        .run::<_, String>(Node::Service(SubCommand::Get), ("ntp", 20_u32))
+        .expect("service logs");
+    assert!(Fixture::calls().contains(&"journalctl -u ntp -n 20 --no-pager".to_string()));
+
+    // Diagnostics run in a scope with the limits.
+    fs::write(LIMITS_CONF, "cpu_quota: 50\nmemory_max: 268435456\n").expect("limits");
+    fixture
+        .run::<_, String>(Node::Service(SubCommand::Get), ("ntp", 20_u32))
+        .expect("service logs");
+    assert!(Fixture::calls().contains(
+        &"systemd-run --scope --quiet --collect -p CPUQuota=50% -p MemoryMax=268435456 \
+          -p MemorySwapMax=0 -- journalctl -u ntp -n 20 --no-pager"
+            .to_string()
+    ));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn compose_stack() {
+    let fixture = Fixture::new();
+    let compose = "docker compose --file /usr/local/aice/compose/docker-compose.yml \
+                   --project-name aice";
+    fixture.set(Node::Compose(SubCommand::Enable), "aice");
+    assert!(Fixture::calls().contains(&format!("{compose} up -d --remove-orphans")));
+    fixture.set(Node::Compose(SubCommand::Update), "aice");
+    let calls = Fixture::calls();
+    assert!(calls.contains(&format!("{compose} config --images")));
+    assert!(calls.contains(&format!("{compose} pull")));
+    fixture.set(Node::Compose(SubCommand::Disable), "aice");
+    assert!(Fixture::calls().contains(&format!("{compose} down")));
+
+    let ret = fixture.run::<_, String>(Node::Compose(SubCommand::Enable), "unknown");
+    assert!(ret.is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn dependency_order() {
+    let fixture = Fixture::new();
+    fixture.set(Node::ServiceGroup(SubCommand::Update), None::<String>);
+    let calls = Fixture::calls();
+    let position = |call: &str| {
+        calls
+            .iter()
+            .position(|c| c == call)
+            .unwrap_or_else(|| panic!("{call} not run"))
+    };
+    assert!(position("docker stop aice-review") < position("systemctl stop ntp"));
+    assert!(position("systemctl stop ntp") < position("systemctl start ntp"));
+    assert!(position("systemctl start ntp") < position("docker start aice-review"));
+}
+
+// Signs the payload with a new key, whose public key is installed at
+// `public_key`, and returns the signature.
+fn sign(payload: &[u8], public_key: &str) -> Vec<u8> {
+    let dir = env::temp_dir().join("roxy-signing");
+    let key = new_key(&dir, public_key);
+    let signature = sign_with(&key, payload);
+    fs::remove_dir_all(&dir).expect("temporary directory should be removable");
+    signature
+}
+
+fn openssl(args: &[&std::ffi::OsStr]) {
+    let status = std::process::Command::new("openssl")
+        .args(args)
+        .status()
+        .expect("openssl");
+    assert!(status.success());
+}
+
+// Generates a private key in `dir`, installs its public key at `public_key`,
+// and returns the path of the private key.
+fn new_key(dir: &Path, public_key: &str) -> PathBuf {
+    fs::create_dir_all(dir).expect("temporary directory");
+    let key = dir.join("key.pem");
+    openssl(&[
+        "ecparam".as_ref(),
+        "-genkey".as_ref(),
+        "-name".as_ref(),
+        "prime256v1".as_ref(),
+        "-noout".as_ref(),
+        "-out".as_ref(),
+        key.as_os_str(),
+    ]);
+    if let Some(parent) = Path::new(public_key).parent() {
+        fs::create_dir_all(parent).expect("key directory");
+    }
+    openssl(&[
+        "ec".as_ref(),
+        "-in".as_ref(),
+        key.as_os_str(),
+        "-pubout".as_ref(),
+        "-out".as_ref(),
+        public_key.as_ref(),
+    ]);
+    key
+}
+
+// Signs the payload with the private key in the directory of `key`.
+fn sign_with(key: &Path, payload: &[u8]) -> Vec<u8> {
+    let dir = key.parent().expect("key directory");
+    let payload_path = dir.join("payload");
+    let signature_path = dir.join("signature");
+    fs::write(&payload_path, payload).expect("payload should be written");
+    openssl(&[
+        "dgst".as_ref(),
+        "-sha256".as_ref(),
+        "-sign".as_ref(),
+        key.as_os_str(),
+        "-out".as_ref(),
+        signature_path.as_os_str(),
+        payload_path.as_os_str(),
+    ]);
+    fs::read(&signature_path).expect("signature")
+}
+
+// Signs the license with a new key installed as the product key, and returns
+// the license blob.
+fn signed_license(payload: &str) -> Vec<u8> {
+    let signature = sign(payload.as_bytes(), LICENSE_KEY);
+    format!(
+        r#"{{"payload":"{}","signature":"{}"}}"#,
+        BASE64.encode(payload.as_bytes()),
+        BASE64.encode(&signature)
+    )
+    .into_bytes()
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn license() {
+    let fixture = Fixture::new();
+    let ret = fixture
+        .run::<_, Option<LicenseStatus>>(Node::License(SubCommand::Get), None::<String>)
+        .expect("license");
+    assert_eq!(ret, None);
+
+    let blob = signed_license(
+        r#"{"id":"L-1","customer":"Example","issued":"2026-01-01","features":["review"]}"#,
+    );
+    let mut forged = serde_json::from_slice::<serde_json::Value>(&blob).expect("JSON");
+    forged["payload"] = BASE64
+        .encode(br#"{"id":"L-1","customer":"Example","issued":"2026-01-01","max_nodes":99}"#)
+        .into();
+    let forged = serde_json::to_vec(&forged).expect("JSON");
+    let ret = fixture.run::<_, String>(Node::License(SubCommand::Set), forged);
+    assert!(ret.is_err());
+    assert!(fs::metadata(LICENSE_FILE).is_err());
+
+    fixture.set(Node::License(SubCommand::Set), blob);
+    let ret = fixture
+        .run::<_, Option<LicenseStatus>>(Node::License(SubCommand::Get), None::<String>)
+        .expect("license")
+        .expect("installed license");
+    assert!(ret.is_valid());
+    assert_eq!(ret.license.id, "L-1");
+    assert_eq!(ret.license.features, vec!["review".to_string()]);
+
+    // Read-only requests of a batch run on several threads, each verifying
+    // the signature at the same time.
+    let get = NodeRequest::new(Node::License(SubCommand::Get), None::<String>).expect("request");
+    let results = fixture.client.run_batch(vec![get; 8]).expect("batch");
+    for ret in results {
+        let status = ret
+            .decode::<Option<LicenseStatus>>()
+            .expect("license")
+            .expect("installed license");
+        assert!(status.signature_valid);
+    }
+
+    let expired = signed_license(
+        r#"{"id":"L-2","customer":"Example","issued":"2020-01-01","expires":"2020-12-31"}"#,
+    );
+    let ret = fixture.run::<_, String>(Node::License(SubCommand::Set), expired);
+    assert!(ret.is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn request_signing() {
+    let fixture = Fixture::new();
+    let servers = vec!["time.example.com".to_string()];
+    let req = NodeRequest::new(Node::Ntp(SubCommand::Set), &servers).expect("request");
+    let signed_at = Local::now().timestamp();
+    let nonce = vec![1, 2, 3, 4];
+    let node = node_id().expect("machine ID");
+    let payload = req
+        .signed_payload(signed_at, &nonce, &node)
+        .expect("payload");
+    let signature = RequestSignature {
+        signed_at,
+        nonce,
+        node,
+        signature: sign(&payload, REQUEST_KEY),
+    };
+
+    let ret = fixture.run::<_, String>(Node::Ntp(SubCommand::Set), &servers);
+    assert!(ret.is_err());
+    fixture
+        .run::<_, Option<Vec<String>>>(Node::Ntp(SubCommand::Get), None::<String>)
+        .expect("reading needs no signature");
+    // The node runs its own periodic tasks with no signature.
+    fixture
+        .run::<_, Vec<DeferredOperation>>(Node::Maintenance(SubCommand::Update), None::<String>)
+        .expect("due operations need no signature");
+    fixture
+        .run::<_, String>(
+            Node::Schedule(SubCommand::Set),
+            JobRun {
+                name: "update_check".to_string(),
+                schedule: "0 */6 * * *".to_string(),
+                started: "2026-03-02T18:00:00+09:00".to_string(),
+                duration_ms: 10,
+                succeeded: true,
+                message: String::new(),
+            },
+        )
+        .expect("job runs need no signature");
+
+    let forged = NodeRequest::new(Node::Ntp(SubCommand::Set), vec!["10.0.0.1"])
+        .expect("request")
+        .signature(signature.clone());
+    assert!(fixture.client.run::<String>(forged).is_err());
+    let elevated = req.clone().role(Role::Admin).signature(signature.clone());
+    assert!(fixture.client.run::<String>(elevated).is_err());
+    let expired = req.clone().signature(RequestSignature {
+        signed_at: signed_at - 3600,
+        ..signature.clone()
+    });
+    assert!(fixture.client.run::<String>(expired).is_err());
+    let other_node = req.clone().signature(RequestSignature {
+        node: "another-node".to_string(),
+        ..signature.clone()
+    });
+    assert!(fixture.client.run::<String>(other_node).is_err());
+
+    let ret = fixture
+        .client
+        .run::<String>(req.clone().signature(signature.clone()))
+        .expect("signed request");
+    assert_eq!(ret, "Ok");
+    let conf = fs::read_to_string(NTP_CONF).expect("ntp.conf");
+    assert!(conf.contains("time.example.com"));
+    let replayed = fixture.client.run::<String>(req.signature(signature));
+    assert!(replayed.is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn compliance() {
+    let fixture = Fixture::new();
+    let baseline = Baseline {
+        id: "pci-2026-10".to_string(),
+        state: SystemState {
+            ntp: Some(vec!["time.example.com".to_string()]),
+            firewall: Some(FirewallState {
+                enabled: true,
+                rules: Vec::new(),
+            }),
+            ..SystemState::default()
+        },
+        remediate: vec![Subsystem::Ntp],
+    };
+    let payload = serde_json::to_vec(&baseline).expect("JSON");
+    let blob = |payload: &[u8], signature: &[u8]| {
+        serde_json::to_vec(&serde_json::json!({
+            "payload": BASE64.encode(payload),
+            "signature": BASE64.encode(signature),
+        }))
+        .expect("JSON")
+    };
+
+    // No baseline is accepted without the key of the Manager.
+    let ret = fixture.run::<_, String>(
+        Node::Compliance(SubCommand::Set),
+        blob(&payload, b"signature"),
+    );
+    assert!(ret.is_err());
+
+    // Installing a baseline changes the system, so the request is signed as
+    // well.
+    let dir = env::temp_dir().join("roxy-baseline");
+    let key = new_key(&dir, REQUEST_KEY);
+    let signed = blob(&payload, &sign_with(&key, &payload));
+    let req = NodeRequest::new(Node::Compliance(SubCommand::Set), &signed).expect("request");
+    let signed_at = Local::now().timestamp();
+    let nonce = vec![5, 6, 7, 8];
+    let node = node_id().expect("machine ID");
+    let payload = req
+        .signed_payload(signed_at, &nonce, &node)
+        .expect("payload");
+    let signature = RequestSignature {
+        signed_at,
+        nonce,
+        node,
+        signature: sign_with(&key, &payload),
+    };
+    fs::remove_dir_all(&dir).expect("temporary directory should be removable");
+    assert!(fixture.client.run::<String>(req.clone()).is_err());
+    let ret = fixture
+        .client
+        .run::<String>(req.signature(signature))
+        .expect("signed request");
+    assert_eq!(ret, "Ok");
+
+    let report = fixture
+        .run::<_, Option<ComplianceReport>>(Node::Compliance(SubCommand::Status), None::<String>)
+        .expect("compliance")
+        .expect("installed baseline");
+    assert_eq!(report.baseline, "pci-2026-10");
+    let violated = |report: &ComplianceReport| {
+        report
+            .violations
+            .iter()
+            .map(|item| item.subsystem)
+            .collect::<Vec<_>>()
+    };
+    // The firewall has no rules as the baseline, but it is inactive.
+    assert_eq!(violated(&report), vec![Subsystem::Ntp, Subsystem::Firewall]);
+    assert!(report.remediated.is_empty());
+    assert!(!Fixture::calls().contains(&"systemctl restart ntp".to_string()));
+    let last = fixture
+        .run::<_, Option<ComplianceReport>>(Node::Compliance(SubCommand::Get), None::<String>)
+        .expect("compliance");
+    assert_eq!(last, None);
+
+    // A scheduled check needs no signature.
+    let report = fixture
+        .run::<_, Option<ComplianceReport>>(Node::Compliance(SubCommand::Update), None::<String>)
+        .expect("compliance")
+        .expect("installed baseline");
+    assert!(Fixture::calls().contains(&"systemctl restart ntp".to_string()));
+    assert!(report
+        .remediated
+        .iter()
+        .any(|change| change.subsystem == Subsystem::Ntp));
+    assert_eq!(violated(&report), vec![Subsystem::Firewall]);
+    assert!(!report.is_compliant());
+    let last = fixture
+        .run::<_, Option<ComplianceReport>>(Node::Compliance(SubCommand::Get), None::<String>)
+        .expect("compliance");
+    assert_eq!(last, Some(report));
+
+    // A baseline modified on the node is not applied.
+    let mut modified = serde_json::from_slice::<serde_json::Value>(&signed).expect("JSON");
+    modified["payload"] = BASE64
+        .encode(
+            &serde_json::to_vec(&Baseline {
+                remediate: vec![Subsystem::Ntp, Subsystem::Firewall],
+                ..baseline
+            })
+            .expect("JSON"),
+        )
+        .into();
+    fs::write(BASELINE_FILE, serde_json::to_vec(&modified).expect("JSON"))
+        .expect("baseline should be written");
+    let ret = fixture
+        .run::<_, Option<ComplianceReport>>(Node::Compliance(SubCommand::Update), None::<String>);
+    assert!(ret.is_err());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn node_info() {
+    let fixture = Fixture::new();
+    let info = fixture
+        .run::<_, NodeInfo>(Node::Info(SubCommand::Get), None::<String>)
+        .expect("node info");
+    assert_eq!(info.roxy_version, env!("CARGO_PKG_VERSION"));
+    assert_eq!(info.arch, std::env::consts::ARCH);
+    assert!(!info.kernel.is_empty());
+    assert!(!info.hostname.hostname.is_empty());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn network_readiness() {
+    let fixture = Fixture::new();
+    let listener = TcpListener::bind("127.0.0.1:0").expect("listener");
+    let port = listener.local_addr().expect("local address").port();
+    fs::write(
+        ENDPOINTS_CONF,
+        format!("- name: manager\n  host: 127.0.0.1\n  port: {port}\n"),
+    )
+    .expect("endpoints should be written");
+    let extra = vec![Endpoint {
+        name: "update".to_string(),
+        host: "update.invalid".to_string(),
+        port: 443,
+        protocol: Protocol::Tcp,
+    }];
+
+    let report = fixture
+        .run::<_, ReadinessReport>(Node::Connectivity(SubCommand::Get), extra)
+        .expect("readiness report");
+    let names = report
+        .checks
+        .iter()
+        .map(|check| check.endpoint.name.as_str())
+        .collect::<Vec<_>>();
+    assert_eq!(names, ["manager", "ntp", "update"]);
+    assert!(report.checks[0].passed());
+    assert!(report.checks[0].latency_ms.is_some());
+    assert_eq!(report.checks[1].endpoint.protocol, Protocol::Ntp);
+    assert_eq!(report.checks[2].address, None);
+    assert!(!report.is_ready());
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn dns() {
+    let fixture = Fixture::new();
+    let ret = fixture
+        .run::<_, DnsLookup>(Node::Dns(SubCommand::Get), "localhost")
+        .expect("lookup");
+    assert!(ret.addresses.iter().all(IpAddr::is_loopback));
+    assert!(!ret.addresses.is_empty());
+    assert!(fixture
+        .run::<_, DnsLookup>(Node::Dns(SubCommand::Get), "-s hosts")
+        .is_err());
+
+    fixture.set(Node::Dns(SubCommand::Delete), None::<String>);
+    assert!(Fixture::calls().contains(&"resolvectl flush-caches".to_string()));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn filesystem_health() {
+    let fixture = Fixture::new();
+    let ret = fixture
+        .run::<_, Vec<FilesystemHealth>>(Node::Filesystem(SubCommand::Status), None::<String>)
+        .expect("filesystem health");
+    assert!(ret.iter().all(|fs| fs.device.starts_with("/dev/")));
+    assert!(ret
+        .iter()
+        .all(|fs| fs.state.is_none() || fs.fs_type.starts_with("ext")));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn cleanup() {
+    let fixture = Fixture::new();
+    let reports = fixture
+        .run::<_, Vec<CleanupReport>>(Node::Cleanup(SubCommand::Status), None::<String>)
+        .expect("no quotas");
+    assert!(reports.is_empty());
+
+    let dir = env::temp_dir().join("roxy-cleanup");
+    let _ = fs::remove_dir_all(&dir);
+    fs::create_dir_all(&dir).expect("temporary directory");
+    let old = dir.join("old.log");
+    fs::write(&old, vec![1; 8192]).expect("log file");
+    File::options()
+        .write(true)
+        .open(&old)
+        .and_then(|file| file.set_modified(SystemTime::now() - std::time::Duration::from_hours(1)))
+        .expect("modification time");
+    fs::write(dir.join("new.log"), vec![1; 8192]).expect("log file");
+    fs::write(
+        CLEANUP_CONF,
+        format!("- path: {}\n  max_size: 8192\n", dir.display()),
+    )
+    .expect("quotas");
+
+    let reports = fixture
+        .run::<_, Vec<CleanupReport>>(Node::Cleanup(SubCommand::Status), None::<String>)
+        .expect("dry run");
+    assert_eq!(reports[0].removed, [old.display().to_string()]);
+    assert!(old.exists());
+    let reports = fixture
+        .run::<_, Vec<CleanupReport>>(Node::Cleanup(SubCommand::Update), None::<String>)
+        .expect("cleanup");
+    assert!(!reports[0].over_quota());
+    assert!(!old.exists());
+    assert!(dir.join("new.log").exists());
+
+    fs::write(CLEANUP_CONF, "- path: /\n  max_size: 0\n").expect("quotas");
+    assert!(fixture
+        .run::<_, Vec<CleanupReport>>(Node::Cleanup(SubCommand::Update), None::<String>)
+        .is_err());
+    fs::remove_dir_all(&dir).expect("remove temporary directory");
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn fstrim() {
+    let fixture = Fixture::new();
+    let status = fixture
+        .run::<_, TrimStatus>(Node::Fstrim(SubCommand::Status), None::<String>)
+        .expect("trim status");
+    assert_eq!(status.last_run, None);
+
+    let run = fixture
+        .run::<_, TrimRun>(Node::Fstrim(SubCommand::Update), None::<String>)
+        .expect("fstrim");
+    assert!(run.success);
+    assert!(Fixture::calls().contains(&"fstrim --all --verbose".to_string()));
+    let status = fixture
+        .run::<_, TrimStatus>(Node::Fstrim(SubCommand::Status), None::<String>)
+        .expect("trim status");
+    assert_eq!(status.last_run, Some(run));
+
+    fixture.set(Node::Fstrim(SubCommand::Enable), None::<String>);
+    let calls = Fixture::calls();
+    assert!(calls.contains(&"systemctl enable fstrim.timer".to_string()));
+    assert!(calls.contains(&"systemctl start fstrim.timer".to_string()));
+    fixture.set(Node::Fstrim(SubCommand::Disable), None::<String>);
+    assert!(Fixture::calls().contains(&"systemctl disable fstrim.timer".to_string()));
+}
+
+#[test]
+#[ignore = "changes /etc; run tests/fixture/run.sh"]
+fn ping() {
+    let fixture = Fixture::new();
+    let ret = fixture
+        .run::<_, PingInfo>(Node::Ping, None::<String>)
+        .expect("ping");
+    assert_eq!(ret, PingInfo::local());
+    assert!(ret.is_compatible());
+    assert!(fs::metadata(ROXY_STATE_DIR).is_err());
+}
@@ -0,0 +1,1268 @@
+//! Golden samples of the messages exchanged between the library and roxy.
+//!
+//! The library and roxy may be upgraded separately, so a change in the
+//! encoding of a message breaks a fleet running mixed versions. Bincode
+//! encodes an enum variant by its index, and a struct without field names, so
+//! reordering variants or fields silently changes the encoding. This test
+//! fails if any sample is encoded differently from `tests/data/wire_format.json`
+//! or the saved encoding no longer decodes to the same value.
+//!
+//! Add a sample when a message type is added. After an intentional change of
+//! the wire format, regenerate the file with `ROXY_BLESS=1 cargo test --test
+//! wire_format`.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    env, fs,
+    net::{IpAddr, Ipv4Addr},
+};
+
+use data_encoding::BASE64;
+use roxy::{
+    common::{
+        decode_arg, ActivationMode, Aggregator, AuditEvent, AuditRecord, AuditStatus, BondSlave,
+        BondStatus, CaptureSettings, CaptureStatus, ChangeEvent, CleanupReport, ComplianceReport,
+        ContainerHealth, CpuGovernor, CrashReport, DeferredOperation, DiskHealth, DnsLookup,
+        DnsResolver, DriftItem, Endpoint, EndpointCheck, FilesystemHealth, FirewallState,
+        GovernorStatus, GroupSettings, HardwareInfo, HistoryEntry, HostnameInfo, InterfaceResult,
+        IrqAffinity, IrqLayout, JobRun, KdumpStatus, License, LicenseStatus, MaintenanceWindow,
+        MdArray, NetnsCapture, NetnsInterface, NicOutput, NicOutputV1, Node, NodeInfo, NodeRequest,
+        OperationStatus, Page, PageRequest, PingInfo, PreflightCheck, Protocol, RaidStatus,
+        RaidVolume, ReadinessReport, RequestSignature, Role, RouteOutput, RoutingRuleOutput,
+        RoxyRequest, ServiceAction, SessionAuditStatus, SmartAttribute, SriovStatus, SshdState,
+        StateChange, SubCommand, Subsystem, SystemState, SystemStateV1, TaskStats, TrimRun,
+        TrimStatus, TrimmedFilesystem, TuningSetting, TuningStatus, TunnelConfig, TunnelMode,
+        UpstreamDns, VfConfig, VirtualFunction, VrrpConfig, VrrpState, VrrpStatus, WireguardConfig,
+        WireguardPeer, WireguardStatus,
+    },
+    AddressConflict, LimitExceeded, ResponseTooLarge, TaskResult, Unsupported,
+};
+use serde::{de::DeserializeOwned, Serialize};
+
+const GOLDEN: &str = "tests/data/wire_format.json";
+const BLESS_ENV: &str = "ROXY_BLESS";
+
+struct Sample {
+    name: String,
+    encoded: String,
+    decodes: Box<dyn Fn(&str) -> bool>,
+}
+
+// A request sent from the library to roxy, in JSON.
+fn request(name: &str, msg: RoxyRequest) -> Sample {
+    let encoded = serde_json::to_string(&msg).expect("request should serialize");
+    Sample {
+        name: format!("request/{name}"),
+        encoded,
+        decodes: Box::new(move |s| {
+            serde_json::from_str::<RoxyRequest>(s).ok() == Some(msg.clone())
+        }),
+    }
+}
+
+fn single<T: Serialize>(kind: Node, arg: T) -> RoxyRequest {
+    RoxyRequest::Single(NodeRequest::new(kind, arg).expect("argument should serialize"))
+}
+
+// Arguments of a task, in base64 of bincode, decoded by roxy.
+fn arg<T>(name: &str, value: T) -> Sample
+where
+    T: Serialize + DeserializeOwned + PartialEq + 'static,
+{
+    let encoded = BASE64.encode(&bincode::serialize(&value).expect("argument should serialize"));
+    Sample {
+        name: format!("arg/{name}"),
+        encoded,
+        decodes: Box::new(move |s| {
+            BASE64
+                .decode(s.as_bytes())
+                .ok()
+                .and_then(|buf| decode_arg::<T>(&buf).ok())
+                .is_some_and(|decoded| decoded == value)
+        }),
+    }
+}
+
+// A successful response of roxy, in JSON of base64 of bincode, decoded by
+// the library.
+fn response<T>(name: &str, value: T) -> Sample
+where
+    T: Serialize + DeserializeOwned + PartialEq + 'static,
+{
+    let encoded = serde_json::to_string(&HashMap::from([(
+        "Ok",
+        BASE64.encode(&bincode::serialize(&value).expect("response should serialize")),
+    )]))
+    .expect("response should serialize");
+    Sample {
+        name: format!("response/{name}"),
+        encoded,
+        decodes: Box::new(move |s| {
+            serde_json::from_str::<TaskResult>(s)
+                .ok()
+                .and_then(|ret| ret.decode::<T>().ok())
+                .is_some_and(|decoded| decoded == value)
+        }),
+    }
+}
+
+fn strings(items: &[&str]) -> Vec<String> {
+    items.iter().map(ToString::to_string).collect()
+}
+
+fn nic() -> NicOutput {
+    NicOutput::new(
+        Some(strings(&["192.168.0.2/24"])),
+        Some(false),
+        Some("192.168.0.1".to_string()),
+        Some(strings(&["8.8.8.8", "8.8.4.4"])),
+    )
+    .gateway_metric(100)
+    .search_domains(strings(&["example.com"]))
+    .optional(true)
+    .activation_mode(ActivationMode::Manual)
+    .routes(vec![RouteOutput {
+        to: "default".to_string(),
+        via: Some("192.168.0.254".to_string()),
+        metric: None,
+        table: Some(100),
+    }])
+    .routing_policy(vec![RoutingRuleOutput {
+        from: Some("192.168.0.0/24".to_string()),
+        to: None,
+        table: 100,
+        priority: Some(10),
+        mark: None,
+    }])
+    .label("WAN")
+}
+
+// The interface of `Interface` requests, whose layout is that of the first
+// samples.
+fn nic_v1() -> NicOutputV1 {
+    NicOutputV1 {
+        addresses: Some(strings(&["192.168.0.2/24"])),
+        dhcp4: Some(false),
+        gateway4: Some("192.168.0.1".to_string()),
+        nameservers: Some(strings(&["8.8.8.8", "8.8.4.4"])),
+    }
+}
+
+fn job_run() -> JobRun {
+    JobRun {
+        name: "disk_health".to_string(),
+        schedule: "0 */6 * * *".to_string(),
+        started: "2023-11-14T18:00:00+00:00".to_string(),
+        duration_ms: 1_250,
+        succeeded: false,
+        message: "smartctl not found".to_string(),
+    }
+}
+
+fn maintenance_window() -> MaintenanceWindow {
+    MaintenanceWindow {
+        start: "23:00".to_string(),
+        end: "01:00".to_string(),
+    }
+}
+
+fn system_state() -> SystemState {
+    SystemState {
+        hostname: Some("node1.example.com".to_string()),
+        interfaces: Some(BTreeMap::from([("eno1".to_string(), nic())])),
+        ntp: Some(strings(&["time.example.com"])),
+        syslog: Some(strings(&["@@192.168.0.205:7500"])),
+        firewall: Some(FirewallState {
+            enabled: true,
+            rules: strings(&["allow 22/tcp"]),
+        }),
+        sshd: Some(SshdState { port: 10022 }),
+    }
+}
+
+fn system_state_v1() -> SystemStateV1 {
+    SystemStateV1 {
+        interfaces: Some(BTreeMap::from([("eno1".to_string(), nic_v1())])),
+        ..system_state().into()
+    }
+}
+
+fn vrrp_config() -> VrrpConfig {
+    VrrpConfig {
+        interface: "eno1".to_string(),
+        virtual_router_id: 51,
+        priority: 150,
+        virtual_addresses: strings(&["192.168.0.100/24"]),
+        auth_pass: Some("s3cret".to_string()),
+    }
+}
+
+fn wireguard_config() -> WireguardConfig {
+    WireguardConfig {
+        interface: "wg-mgmt".to_string(),
+        addresses: strings(&["10.99.0.2/24"]),
+        listen_port: None,
+        peer: WireguardPeer {
+            public_key: "xTIBA5rboUvnH4htodjb6e697QjLERt1NAB4mZqp8Dg=".to_string(),
+            endpoint: Some("manager.example.com:51820".to_string()),
+            allowed_ips: strings(&["10.99.0.0/24"]),
+            persistent_keepalive: Some(25),
+        },
+    }
+}
+
+fn tunnel_config() -> TunnelConfig {
+    TunnelConfig {
+        mode: TunnelMode::Gretap,
+        local: "192.168.0.10".to_string(),
+        remote: Some("192.168.0.1".to_string()),
+        vni: None,
+        port: None,
+        key: Some(7),
+        addresses: None,
+        mtu: Some(1458),
+    }
+}
+
+#[allow(clippy::too_many_lines)] // a table of samples
+fn requests() -> Vec<Sample> {
+    let none = None::<String>;
+    vec![
+        request(
+            "audit",
+            single(Node::Audit(SubCommand::Get), Some("recent")),
+        ),
+        request(
+            "banner",
+            single(Node::Banner(SubCommand::Set), "Authorized only"),
+        ),
+        request(
+            "capture",
+            single(
+                Node::Capture(SubCommand::Set),
+                ("eno2", CaptureSettings::capture()),
+            ),
+        ),
+        request(
+            "netns_capture",
+            single(
+                Node::Netns(SubCommand::Update),
+                NetnsCapture {
+                    namespace: "capture".to_string(),
+                    interface: "enp3s0f1".to_string(),
+                    count: 10,
+                    timeout_secs: 5,
+                    filter: Some("tcp port 443".to_string()),
+                },
+            ),
+        ),
+        request(
+            "license",
+            single(
+                Node::License(SubCommand::Set),
+                b"{\"payload\":\"\"}".to_vec(),
+            ),
+        ),
+        request(
+            "compliance",
+            single(
+                Node::Compliance(SubCommand::Set),
+                b"{\"payload\":\"\"}".to_vec(),
+            ),
+        ),
+        request("info", single(Node::Info(SubCommand::Get), none.clone())),
+        request("ping", single(Node::Ping, none.clone())),
+        request(
+            "dns",
+            single(Node::Dns(SubCommand::Get), "manager.example.com"),
+        ),
+        request(
+            "connectivity",
+            single(
+                Node::Connectivity(SubCommand::Get),
+                vec![Endpoint {
+                    name: "manager".to_string(),
+                    host: "manager.example.com".to_string(),
+                    port: 38390,
+                    protocol: Protocol::Tcp,
+                }],
+            ),
+        ),
+        request("compose", single(Node::Compose(SubCommand::Update), "aice")),
+        request(
+            "hardware",
+            single(Node::Hardware(SubCommand::Get), none.clone()),
+        ),
+        request(
+            "history",
+            single(
+                Node::History(SubCommand::Revert),
+                (Subsystem::Ntp, 1_700_000_000_000_000_000_i64),
+            ),
+        ),
+        request("changes", single(Node::Changes(SubCommand::Get), 42_u64)),
+        request(
+            "job_run",
+            single(Node::Schedule(SubCommand::Set), job_run()),
+        ),
+        request(
+            "defer",
+            single(
+                Node::Maintenance(SubCommand::Add),
+                (
+                    serde_json::to_string(
+                        &NodeRequest::new(
+                            Node::Service(SubCommand::Set),
+                            ("ntp", ServiceAction::Restart),
+                        )
+                        .expect("argument should serialize"),
+                    )
+                    .expect("request should serialize"),
+                    maintenance_window(),
+                    Some("2023-11-15T01:00:00+00:00".to_string()),
+                ),
+            ),
+        ),
+        request(
+            "hostname",
+            single(Node::Hostname(SubCommand::Set), "node1.example.com"),
+        ),
+        request(
+            "hostname_status",
+            single(Node::Hostname(SubCommand::Status), none.clone()),
+        ),
+        request(
+            "interface_group",
+            single(
+                Node::InterfaceGroup(SubCommand::Set),
+                (
+                    "cap*",
+                    GroupSettings {
+                        nic: Some(NicOutput::new(None, Some(false), None, None).optional(true)),
+                        capture: Some(CaptureSettings::capture()),
+                    },
+                ),
+            ),
+        ),
+        request(
+            "read_only",
+            RoxyRequest::Single(
+                NodeRequest::new(Node::Interface(SubCommand::Get), "eno1")
+                    .expect("argument should serialize")
+                    .role(Role::ReadOnly),
+            ),
+        ),
+        request(
+            "interface",
+            single(Node::Interface(SubCommand::Set), ("eno1", nic_v1())),
+        ),
+        request(
+            "interface_v2",
+            single(Node::InterfaceV2(SubCommand::Set), ("eno1", nic())),
+        ),
+        request(
+            "interface_checked",
+            single(Node::Interface(SubCommand::Set), ("eno1", nic(), true)),
+        ),
+        request("kdump", single(Node::Kdump(SubCommand::Set), "512M-:192M")),
+        request(
+            "ntp",
+            single(Node::Ntp(SubCommand::Set), strings(&["time.example.com"])),
+        ),
+        request("poweroff", single(Node::PowerOff, none.clone())),
+        request("preflight", single(Node::Preflight, none.clone())),
+        request("raid", single(Node::Raid(SubCommand::Status), none.clone())),
+        request(
+            "bond",
+            single(Node::Bond(SubCommand::Status), Some("bond0".to_string())),
+        ),
+        request("reboot", single(Node::Reboot, none.clone())),
+        request("service", single(Node::Service(SubCommand::Status), "sshd")),
+        request(
+            "signed",
+            RoxyRequest::Single(
+                NodeRequest::new(Node::Ntp(SubCommand::Set), strings(&["time.example.com"]))
+                    .expect("argument should serialize")
+                    .signature(RequestSignature {
+                        signed_at: 1_700_000_000,
+                        nonce: vec![0x9f, 0x3c, 0x51, 0x07],
+                        node: "3d1219c7c4c5404aaa1f6d2a48adfda4".to_string(),
+                        signature: vec![0x30, 0x45, 0x02, 0x21],
+                    }),
+            ),
+        ),
+        request(
+            "session_audit",
+            single(Node::SessionAudit(SubCommand::Enable), none.clone()),
+        ),
+        request(
+            "smart",
+            single(Node::Smart(SubCommand::Status), none.clone()),
+        ),
+        request(
+            "sriov",
+            single(Node::Sriov(SubCommand::Set), ("enp3s0f0", 4_u32)),
+        ),
+        request(
+            "sriov_vf",
+            single(
+                Node::Sriov(SubCommand::Update),
+                (
+                    "enp3s0f0",
+                    1_u32,
+                    VfConfig {
+                        mac: Some("02:00:00:00:00:02".to_string()),
+                        vlan: Some(100),
+                    },
+                ),
+            ),
+        ),
+        request(
+            "service_action",
+            single(
+                Node::Service(SubCommand::Set),
+                ("review", ServiceAction::EnableAtBoot),
+            ),
+        ),
+        request(
+            "service_log_page",
+            single(
+                Node::Service(SubCommand::Get),
+                ("review", 0_u32, PageRequest::new(100, 50)),
+            ),
+        ),
+        request(
+            "ufw_page",
+            single(Node::Ufw(SubCommand::Get), Some(PageRequest::new(0, 50))),
+        ),
+        request(
+            "service_environment",
+            single(
+                Node::ServiceEnvironment(SubCommand::Set),
+                (
+                    "review",
+                    BTreeMap::from([("RUST_LOG".to_string(), "info".to_string())]),
+                ),
+            ),
+        ),
+        request(
+            "service_group",
+            single(Node::ServiceGroup(SubCommand::Update), none.clone()),
+        ),
+        request(
+            "service_logs",
+            single(Node::Service(SubCommand::Get), ("review", 100_u32)),
+        ),
+        request("sshd", single(Node::Sshd(SubCommand::Set), "10022")),
+        request(
+            "state",
+            single(Node::State(SubCommand::Set), system_state_v1()),
+        ),
+        request(
+            "state_v2",
+            single(Node::StateV2(SubCommand::Set), system_state()),
+        ),
+        request(
+            "replica",
+            single(
+                Node::Replica(SubCommand::Set),
+                system_state().without_identity(),
+            ),
+        ),
+        request(
+            "state_drift",
+            single(Node::State(SubCommand::Status), none.clone()),
+        ),
+        request(
+            "stats",
+            single(Node::Stats(SubCommand::Delete), none.clone()),
+        ),
+        request("crash", single(Node::Crash(SubCommand::Get), none.clone())),
+        request(
+            "cleanup",
+            single(Node::Cleanup(SubCommand::Update), none.clone()),
+        ),
+        request(
+            "filesystem",
+            single(Node::Filesystem(SubCommand::Status), none.clone()),
+        ),
+        request(
+            "fstrim",
+            single(Node::Fstrim(SubCommand::Update), none.clone()),
+        ),
+        request(
+            "cpu_governor",
+            single(
+                Node::CpuGovernor(SubCommand::Set),
+                "performance".to_string(),
+            ),
+        ),
+        request("vrrp", single(Node::Vrrp(SubCommand::Set), vrrp_config())),
+        request(
+            "wireguard",
+            single(Node::Wireguard(SubCommand::Set), wireguard_config()),
+        ),
+        request(
+            "tunnel",
+            single(
+                Node::Tunnel(SubCommand::Set),
+                ("mirror0".to_string(), tunnel_config()),
+            ),
+        ),
+        request(
+            "tuning",
+            single(Node::Tuning(SubCommand::Set), "capture".to_string()),
+        ),
+        request(
+            "irq_affinity",
+            single(
+                Node::IrqAffinity(SubCommand::Set),
+                ("eth1".to_string(), vec![2_u32, 3, 4, 5]),
+            ),
+        ),
+        request(
+            "syslog",
+            single(
+                Node::Syslog(SubCommand::Set),
+                strings(&["@192.168.0.3:514"]),
+            ),
+        ),
+        request(
+            "ufw",
+            single(Node::Ufw(SubCommand::Add), strings(&["allow 22/tcp"])),
+        ),
+        request(
+            "version",
+            single(Node::Version(SubCommand::SetOsVersion), "1.2.3"),
+        ),
+        request(
+            "subcommands",
+            RoxyRequest::Batch(
+                [
+                    SubCommand::Add,
+                    SubCommand::Delete,
+                    SubCommand::Disable,
+                    SubCommand::Enable,
+                    SubCommand::Get,
+                    SubCommand::Init,
+                    SubCommand::List,
+                    SubCommand::Revert,
+                    SubCommand::Set,
+                    SubCommand::SetOsVersion,
+                    SubCommand::SetProductVersion,
+                    SubCommand::Status,
+                    SubCommand::Update,
+                ]
+                .into_iter()
+                .map(|cmd| {
+                    NodeRequest::new(Node::Ntp(cmd), none.clone())
+                        .expect("argument should serialize")
+                })
+                .collect(),
+            ),
+        ),
+        request(
+            "transaction",
+            RoxyRequest::Transaction {
+                transaction: vec![
+                    NodeRequest::new(Node::Hostname(SubCommand::Set), "node1")
+                        .expect("argument should serialize"),
+                    NodeRequest::new(Node::Sshd(SubCommand::Set), "10022")
+                        .expect("argument should serialize"),
+                ],
+            },
+        ),
+    ]
+}
+
+fn args() -> Vec<Sample> {
+    vec![
+        arg("none", None::<String>),
+        arg("string", "node1.example.com".to_string()),
+        arg("strings", strings(&["time.example.com", "10.0.0.1"])),
+        arg("interface", ("eno1".to_string(), nic_v1())),
+        arg("interface_v2", ("eno1".to_string(), nic())),
+        arg("history_get", Some(Subsystem::Version)),
+        arg(
+            "subsystems",
+            [
+                Subsystem::Firewall,
+                Subsystem::Hostname,
+                Subsystem::Interface,
+                Subsystem::Ntp,
+                Subsystem::Sshd,
+                Subsystem::Syslog,
+                Subsystem::Version,
+            ]
+            .map(|subsystem| (subsystem, 1_700_000_000_000_000_000_i64))
+            .to_vec(),
+        ),
+        arg("system_state", system_state_v1()),
+        arg("system_state_v2", system_state()),
+    ]
+}
+
+#[allow(clippy::too_many_lines)] // a table of samples
+fn responses() -> Vec<Sample> {
+    vec![
+        response("ok", "Ok".to_string()),
+        response("bool", true),
+        response(
+            "capture",
+            CaptureStatus {
+                current: CaptureSettings {
+                    promiscuous: false,
+                    gro: true,
+                    lro: false,
+                    tso: true,
+                },
+                persistent: Some(CaptureSettings::capture()),
+            },
+        ),
+        response(
+            "compose",
+            vec![ContainerHealth {
+                name: "aice-review-1".to_string(),
+                service: "review".to_string(),
+                state: "running".to_string(),
+                health: Some("healthy".to_string()),
+            }],
+        ),
+        response(
+            "netns",
+            vec![NetnsInterface {
+                namespace: "capture".to_string(),
+                name: "enp3s0f1".to_string(),
+                up: true,
+                operstate: "UP".to_string(),
+                mac: Some("3c:fd:fe:00:00:01".to_string()),
+                rx_bytes: 9000,
+                rx_packets: 6,
+                rx_dropped: 2,
+                tx_bytes: 120,
+                tx_packets: 1,
+            }],
+        ),
+        response(
+            "license",
+            Some(LicenseStatus {
+                license: License {
+                    id: "L-2026-0001".to_string(),
+                    customer: "Example Corp.".to_string(),
+                    issued: "2026-01-01".to_string(),
+                    expires: Some("2026-12-31".to_string()),
+                    features: strings(&["review", "crusher"]),
+                    max_nodes: Some(8),
+                },
+                signature_valid: true,
+                expired: false,
+            }),
+        ),
+        response(
+            "info",
+            NodeInfo {
+                hostname: HostnameInfo {
+                    hostname: "sensor1".to_string(),
+                    domain: Some("example.com".to_string()),
+                    fqdn: "sensor1.example.com".to_string(),
+                },
+                os_version: "AICE OS 2.0".to_string(),
+                product_version: "AICE Sensor 1.4".to_string(),
+                uptime: 86_400,
+                kernel: "6.8.0-45-generic".to_string(),
+                arch: "x86_64".to_string(),
+                roxy_version: "0.4.0".to_string(),
+            },
+        ),
+        response(
+            "ping",
+            PingInfo {
+                version: "0.4.0".to_string(),
+                capability_hash: 0x0123_4567_89ab_cdef,
+            },
+        ),
+        response(
+            "dns",
+            DnsLookup {
+                name: "manager.example.com".to_string(),
+                canonical_name: Some("lb.example.com".to_string()),
+                addresses: vec![
+                    IpAddr::V4(Ipv4Addr::new(10, 0, 0, 5)),
+                    "2001:db8::5".parse::<IpAddr>().expect("valid address"),
+                ],
+                resolver: DnsResolver {
+                    nameservers: vec![IpAddr::V4(Ipv4Addr::new(127, 0, 0, 53))],
+                    search: strings(&["example.com"]),
+                    stub: true,
+                    upstream: vec![UpstreamDns {
+                        link: "eno1".to_string(),
+                        servers: strings(&["10.0.0.1"]),
+                    }],
+                },
+            },
+        ),
+        response(
+            "connectivity",
+            ReadinessReport {
+                checks: vec![
+                    EndpointCheck {
+                        endpoint: Endpoint {
+                            name: "ntp".to_string(),
+                            host: "0.ubuntu.pool.ntp.org".to_string(),
+                            port: 123,
+                            protocol: Protocol::Ntp,
+                        },
+                        address: Some("185.125.190.56:123".parse().expect("valid address")),
+                        latency_ms: Some(12),
+                        error: None,
+                    },
+                    EndpointCheck {
+                        endpoint: Endpoint {
+                            name: "syslog".to_string(),
+                            host: "logs.example.com".to_string(),
+                            port: 514,
+                            protocol: Protocol::Udp,
+                        },
+                        address: None,
+                        latency_ms: None,
+                        error: Some("cannot resolve logs.example.com".to_string()),
+                    },
+                ],
+            },
+        ),
+        response(
+            "page",
+            Page {
+                items: strings(&["allow 22/tcp", "allow 443/tcp"]),
+                offset: 0,
+                total: Some(2),
+            },
+        ),
+        response("gateway_reachable", Some(false)),
+        response("banner", "Authorized only\n".to_string()),
+        response("interfaces", Some(vec![("eno1".to_string(), nic_v1())])),
+        response("interfaces_v2", Some(vec![("eno1".to_string(), nic())])),
+        response("interface_names", strings(&["eno1", "eno2"])),
+        response(
+            "syslog",
+            Some(vec![(
+                "user.*".to_string(),
+                "tcp".to_string(),
+                "192.168.0.205:7500".to_string(),
+            )]),
+        ),
+        response(
+            "hostname",
+            HostnameInfo {
+                hostname: "node1".to_string(),
+                domain: Some("example.com".to_string()),
+                fqdn: "node1.example.com".to_string(),
+            },
+        ),
+        response(
+            "history",
+            vec![HistoryEntry {
+                subsystem: Subsystem::Sshd,
+                id: 1_700_000_000_000_000_000,
+                time: "2023-11-14T22:13:20+00:00".to_string(),
+                diff: "--- /etc/ssh/sshd_config\n-Port 22\n+Port 10022".to_string(),
+            }],
+        ),
+        response(
+            "changes",
+            vec![ChangeEvent {
+                seq: 43,
+                time: "2023-11-14T22:13:20+00:00".to_string(),
+                subsystem: Some(Subsystem::Sshd),
+                task: "Sshd::Set".to_string(),
+                actor: "aice".to_string(),
+                before: strings(&["/etc/ssh/sshd_config: Port 22"]),
+                after: strings(&["/etc/ssh/sshd_config: Port 10022"]),
+                notes: strings(&["approved in CHG-1024"]),
+            }],
+        ),
+        response(
+            "preflight",
+            vec![PreflightCheck {
+                name: "netplan".to_string(),
+                passed: true,
+                detail: "/etc/netplan is writable".to_string(),
+            }],
+        ),
+        response("system_state", system_state_v1()),
+        response("system_state_v2", system_state()),
+        response(
+            "state_changes",
+            vec![StateChange {
+                subsystem: Subsystem::Firewall,
+                target: Some("enabled".to_string()),
+                before: "false".to_string(),
+                after: "true".to_string(),
+            }],
+        ),
+        response("job_runs", vec![job_run()]),
+        response(
+            "interface_results",
+            vec![
+                InterfaceResult {
+                    ifname: "cap0".to_string(),
+                    error: None,
+                },
+                InterfaceResult {
+                    ifname: "cap1".to_string(),
+                    error: Some("interface \"cap1\" not found.".to_string()),
+                },
+            ],
+        ),
+        response(
+            "deferred_operations",
+            vec![DeferredOperation {
+                id: 3,
+                request: NodeRequest::new::<Option<String>>(Node::Reboot, None)
+                    .expect("argument should serialize"),
+                window: maintenance_window(),
+                queued: "2023-11-14T18:00:00+00:00".to_string(),
+                expires: None,
+                status: OperationStatus::Failed("reboot failed".to_string()),
+                updated: Some("2023-11-14T23:00:05+00:00".to_string()),
+            }],
+        ),
+        response(
+            "drift",
+            vec![DriftItem {
+                subsystem: Subsystem::Sshd,
+                target: Some("port".to_string()),
+                desired: "10022".to_string(),
+                current: "22".to_string(),
+            }],
+        ),
+        response(
+            "compliance_report",
+            Some(ComplianceReport {
+                baseline: "pci-2026-10".to_string(),
+                checked_at: "2026-10-15T03:00:00+09:00".to_string(),
+                violations: vec![DriftItem {
+                    subsystem: Subsystem::Ntp,
+                    target: None,
+                    desired: "time.example.com".to_string(),
+                    current: "pool.ntp.org".to_string(),
+                }],
+                remediated: vec![StateChange {
+                    subsystem: Subsystem::Sshd,
+                    target: Some("port".to_string()),
+                    before: "22".to_string(),
+                    after: "10022".to_string(),
+                }],
+            }),
+        ),
+        response(
+            "raid",
+            RaidStatus {
+                arrays: vec![MdArray {
+                    name: "md0".to_string(),
+                    state: "active".to_string(),
+                    level: "raid1".to_string(),
+                    devices: strings(&["sda1", "sdb1"]),
+                    failed_devices: strings(&["sdb1"]),
+                    total: 2,
+                    working: 1,
+                    degraded: true,
+                    sync: Some("recovery = 12.6%".to_string()),
+                }],
+                volumes: vec![RaidVolume {
+                    controller: 0,
+                    id: "0/239".to_string(),
+                    name: "data".to_string(),
+                    level: "RAID5".to_string(),
+                    state: "Optl".to_string(),
+                    degraded: false,
+                }],
+            },
+        ),
+        response(
+            "bond",
+            vec![BondStatus {
+                name: "bond0".to_string(),
+                mode: "IEEE 802.3ad Dynamic link aggregation".to_string(),
+                mii_status: "up".to_string(),
+                active_slave: None,
+                aggregator: Some(Aggregator {
+                    id: 1,
+                    ports: 1,
+                    partner_mac: "00:1c:73:00:00:99".to_string(),
+                    partner_key: 32773,
+                }),
+                slaves: vec![BondSlave {
+                    name: "enp1s0f0".to_string(),
+                    mii_status: "up".to_string(),
+                    speed: Some("10000 Mbps".to_string()),
+                    duplex: Some("full".to_string()),
+                    link_failure_count: 2,
+                    permanent_mac: Some("3c:fd:fe:00:00:01".to_string()),
+                    aggregator_id: Some(1),
+                    partner_mac: Some("00:1c:73:00:00:99".to_string()),
+                    actor_churn_state: Some("none".to_string()),
+                    partner_churn_state: Some("churned".to_string()),
+                }],
+                degraded: false,
+            }],
+        ),
+        response(
+            "smart",
+            vec![DiskHealth {
+                device: "/dev/sda".to_string(),
+                model: "Samsung SSD 870".to_string(),
+                passed: true,
+                failing_attributes: vec![SmartAttribute {
+                    id: 5,
+                    name: "Reallocated_Sector_Ct".to_string(),
+                    value: 9,
+                    threshold: 10,
+                    raw: "1024".to_string(),
+                }],
+            }],
+        ),
+        response(
+            "sriov",
+            SriovStatus {
+                total_vfs: 64,
+                num_vfs: 2,
+                persistent_vfs: Some(2),
+                vfs: vec![
+                    VirtualFunction {
+                        index: 0,
+                        ifname: Some("enp3s0f0v0".to_string()),
+                        mac: "02:00:00:00:00:01".to_string(),
+                        vlan: 0,
+                    },
+                    VirtualFunction {
+                        index: 1,
+                        ifname: None,
+                        mac: "02:00:00:00:00:02".to_string(),
+                        vlan: 100,
+                    },
+                ],
+            },
+        ),
+        response(
+            "hardware",
+            HardwareInfo {
+                manufacturer: "Dell Inc.".to_string(),
+                product_name: "PowerEdge R650".to_string(),
+                serial_number: "ABC1234".to_string(),
+                bios_vendor: "Dell Inc.".to_string(),
+                bios_version: "1.6.5".to_string(),
+                bios_date: "04/15/2022".to_string(),
+                bmc_firmware: Some("5.10".to_string()),
+            },
+        ),
+        response(
+            "kdump",
+            KdumpStatus {
+                enabled: true,
+                armed: false,
+                crashkernel: Some("512M-:192M".to_string()),
+                configured_crashkernel: Some("512M-:256M".to_string()),
+                vmcores: strings(&["/var/crash/202401010000/dump.202401010000"]),
+            },
+        ),
+        response(
+            "session_audit",
+            SessionAuditStatus {
+                enabled: true,
+                auditd_active: true,
+            },
+        ),
+        response(
+            "audit_status",
+            AuditStatus {
+                active: true,
+                installed: true,
+                loaded_rules: 12,
+            },
+        ),
+        response(
+            "audit_events",
+            vec![AuditEvent {
+                serial: 42,
+                time: "1700000000.123".to_string(),
+                key: Some("roxy-config".to_string()),
+                records: vec![AuditRecord {
+                    kind: "SYSCALL".to_string(),
+                    fields: BTreeMap::from([
+                        ("comm".to_string(), "vi".to_string()),
+                        ("uid".to_string(), "0".to_string()),
+                    ]),
+                }],
+            }],
+        ),
+        response(
+            "stats",
+            vec![TaskStats {
+                kind: "Interface::Set".to_string(),
+                count: 3,
+                failures: 1,
+                total_ms: 4_200,
+                max_ms: 3_000,
+                buckets: vec![0, 0, 2, 1, 0, 0],
+            }],
+        ),
+        response(
+            "crash_reports",
+            vec![CrashReport {
+                time: 1_714_521_600,
+                version: "0.5.0".to_string(),
+                task: Some("Interface::Set".to_string()),
+                message: "index out of bounds".to_string(),
+                location: Some("src/root/ifconfig.rs:42:9".to_string()),
+                backtrace: "   0: roxy::main\n".to_string(),
+            }],
+        ),
+        response(
+            "cleanup_reports",
+            vec![CleanupReport {
+                path: "/data/pcap".to_string(),
+                max_size: 8192,
+                size: 16384,
+                removed: vec!["/data/pcap/a.pcap".to_string()],
+                freed: 4096,
+            }],
+        ),
+        response(
+            "filesystem_health",
+            vec![FilesystemHealth {
+                device: "/dev/sda1".to_string(),
+                mount_point: "/data".to_string(),
+                fs_type: "ext4".to_string(),
+                state: Some("clean with errors".to_string()),
+                error_count: 3,
+                first_error: Some(1_714_521_600),
+                last_error: Some(1_714_613_400),
+                kernel_errors: 1,
+            }],
+        ),
+        response(
+            "governor_status",
+            GovernorStatus {
+                driver: Some("intel_pstate".to_string()),
+                available: vec!["performance".to_string(), "powersave".to_string()],
+                cpus: vec![
+                    CpuGovernor {
+                        cpu: 0,
+                        governor: "performance".to_string(),
+                    },
+                    CpuGovernor {
+                        cpu: 1,
+                        governor: "powersave".to_string(),
+                    },
+                ],
+                persistent: Some("performance".to_string()),
+            },
+        ),
+        response(
+            "vrrp_status",
+            VrrpStatus {
+                config: Some(vrrp_config()),
+                active: true,
+                state: Some(VrrpState::Master),
+                holds_addresses: true,
+            },
+        ),
+        response(
+            "wireguard_status",
+            Some(WireguardStatus {
+                config: wireguard_config(),
+                public_key: "HIgo9xNzJMWLKASShiTqIybxZ0U3wGLiUeJ1PKf8ykw=".to_string(),
+                active: true,
+                latest_handshake: Some(1_700_000_000),
+                rx_bytes: 1024,
+                tx_bytes: 2048,
+            }),
+        ),
+        response(
+            "tunnels",
+            vec![
+                ("mirror0".to_string(), tunnel_config()),
+                (
+                    "vxlan100".to_string(),
+                    TunnelConfig {
+                        mode: TunnelMode::Vxlan,
+                        remote: None,
+                        vni: Some(100),
+                        port: Some(4789),
+                        key: None,
+                        addresses: Some(strings(&["10.100.0.1/24"])),
+                        mtu: Some(1450),
+                        ..tunnel_config()
+                    },
+                ),
+            ],
+        ),
+        response(
+            "tuning_status",
+            TuningStatus {
+                profile: "capture".to_string(),
+                settings: vec![
+                    TuningSetting {
+                        name: "kernel.mm.transparent_hugepage.enabled".to_string(),
+                        expected: "never".to_string(),
+                        current: Some("madvise".to_string()),
+                    },
+                    TuningSetting {
+                        name: "vm.swappiness".to_string(),
+                        expected: "1".to_string(),
+                        current: Some("1".to_string()),
+                    },
+                ],
+                profiles: vec![
+                    "default".to_string(),
+                    "capture".to_string(),
+                    "database".to_string(),
+                ],
+            },
+        ),
+        response(
+            "irq_layout",
+            IrqLayout {
+                interface: "eth1".to_string(),
+                numa_node: Some(0),
+                local_cpus: Some("0-7".to_string()),
+                rx_queues: 2,
+                irqs: vec![
+                    IrqAffinity {
+                        irq: 42,
+                        name: Some("eth1-TxRx-0".to_string()),
+                        cpus: "2".to_string(),
+                        effective_cpus: Some("2".to_string()),
+                    },
+                    IrqAffinity {
+                        irq: 43,
+                        name: Some("eth1-TxRx-1".to_string()),
+                        cpus: "3".to_string(),
+                        effective_cpus: None,
+                    },
+                ],
+                pinned: Some(vec![2, 3]),
+            },
+        ),
+        response(
+            "trim_status",
+            TrimStatus {
+                timer_enabled: true,
+                timer_active: true,
+                last_run: Some(TrimRun {
+                    time: 1_714_521_600,
+                    success: false,
+                    filesystems: vec![TrimmedFilesystem {
+                        mount_point: "/data".to_string(),
+                        device: Some("/dev/sda1".to_string()),
+                        trimmed: 1_288_490_188,
+                    }],
+                    errors: vec![
+                        "fstrim: /boot: the discard operation is not supported".to_string()
+                    ],
+                }),
+            },
+        ),
+    ]
+}
+
+#[test]
+fn golden_samples() {
+    let samples = requests()
+        .into_iter()
+        .chain(args())
+        .chain(responses())
+        .collect::<Vec<_>>();
+
+    if env::var_os(BLESS_ENV).is_some() {
+        let golden = samples
+            .iter()
+            .map(|sample| (sample.name.clone(), sample.encoded.clone()))
+            .collect::<BTreeMap<_, _>>();
+        let mut json = serde_json::to_string_pretty(&golden).expect("samples should serialize");
+        json.push('\n');
+        fs::write(GOLDEN, json).expect("golden file should be writable");
+        return;
+    }
+
+    let golden = serde_json::from_str::<BTreeMap<String, String>>(
+        &fs::read_to_string(GOLDEN).expect("golden file should exist"),
+    )
+    .expect("golden file should be valid");
+    for sample in &samples {
+        let saved = golden
+            .get(&sample.name)
+            .unwrap_or_else(|| panic!("{} is not in {GOLDEN}", sample.name));
+        assert!(
+            (sample.decodes)(saved),
+            "{} saved in {GOLDEN} does not decode to the same value",
+            sample.name
+        );
+        assert_eq!(
+            sample.encoded, *saved,
+            "{} is encoded differently from {GOLDEN}",
+            sample.name
+        );
+    }
+    for name in golden.keys() {
+        assert!(
+            samples.iter().any(|sample| sample.name == *name),
+            "{name} in {GOLDEN} is no longer tested"
+        );
+    }
+}
+
+// The error of an older roxy for a task it does not know.
+#[test]
+fn unsupported_task() {
+    let ret = serde_json::from_str::<TaskResult>(r#"{"Err":"unsupported task: Frobnicator::Set"}"#)
+        .expect("response should deserialize");
+    let err = ret.decode::<String>().expect_err("should fail");
+    assert_eq!(
+        err.downcast_ref::<Unsupported>(),
+        Some(&Unsupported("Frobnicator::Set".to_string()))
+    );
+
+    let ret = serde_json::from_str::<TaskResult>(r#"{"Err":"invalid command"}"#)
+        .expect("response should deserialize");
+    let err = ret.decode::<String>().expect_err("should fail");
+    assert!(err.downcast_ref::<Unsupported>().is_none());
+}
+
+#[test]
+fn address_conflict() {
+    let ret = serde_json::from_str::<TaskResult>(
+        r#"{"Err":"address conflict: 10.0.0.0/24 overlaps 10.0.0.0/16 of eth1"}"#,
+    )
+    .expect("response should deserialize");
+    let err = ret.decode::<String>().expect_err("should fail");
+    assert_eq!(
+        err.downcast_ref::<AddressConflict>(),
+        Some(&AddressConflict(
+            "10.0.0.0/24 overlaps 10.0.0.0/16 of eth1".to_string()
+        ))
+    );
+}
+
+#[test]
+fn limit_exceeded() {
+    let ret = serde_json::from_str::<TaskResult>(r#"{"Err":"resource limit exceeded: smartctl"}"#)
+        .expect("response should deserialize");
+    let err = ret.decode::<String>().expect_err("should fail");
+    assert_eq!(
+        err.downcast_ref::<LimitExceeded>(),
+        Some(&LimitExceeded("smartctl".to_string()))
+    );
+}
+
+#[test]
+fn response_too_large() {
+    let ret = serde_json::from_str::<TaskResult>(r#"{"Err":"response too large"}"#)
+        .expect("response should deserialize");
+    let err = ret.decode::<Vec<String>>().expect_err("should fail");
+    assert_eq!(
+        err.downcast_ref::<ResponseTooLarge>(),
+        Some(&ResponseTooLarge)
+    );
+}